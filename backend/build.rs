@@ -0,0 +1,13 @@
+//! Generates the gRPC server from the workspace's shared proto without needing `protoc`.
+
+const PROTO_ROOT: &str = "../proto";
+const PROTO: &str = "../proto/odin/v1/odin.proto";
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    println!("cargo:rerun-if-changed={}", PROTO);
+    let descriptors = protox::compile([PROTO], [PROTO_ROOT])?;
+    tonic_prost_build::configure()
+        .build_client(false)
+        .compile_fds(descriptors)?;
+    Ok(())
+}
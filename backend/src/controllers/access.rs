@@ -0,0 +1,59 @@
+use std::net::SocketAddr;
+
+use axum::extract::{ConnectInfo, Request, State};
+use axum::http::{Extensions, Method};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+
+use odin_core::services::AuthService;
+use odin_core::types::Peer;
+
+use super::AppState;
+#[cfg(unix)]
+use crate::server::UnixPeer;
+
+/// The connection a request came over: the TCP listener records the peer's address and
+/// the unix socket listener a [`UnixPeer`] marker, so a request with neither is unknown.
+#[cfg(unix)]
+pub(super) fn peer(extensions: &Extensions) -> Peer {
+    if extensions.get::<ConnectInfo<UnixPeer>>().is_some() {
+        return Peer::Unix;
+    }
+    tcp_peer(extensions)
+}
+
+#[cfg(not(unix))]
+pub(super) fn peer(extensions: &Extensions) -> Peer {
+    tcp_peer(extensions)
+}
+
+fn tcp_peer(extensions: &Extensions) -> Peer {
+    extensions
+        .get::<ConnectInfo<SocketAddr>>()
+        .map_or(Peer::Unknown, |ConnectInfo(addr)| Peer::Tcp(addr.ip()))
+}
+
+/// Guard routes that need the ingest or admin scope with `auth.privileged_cidrs`.
+pub(super) async fn privileged(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let peer = peer(request.extensions());
+    if let Err(err) = state.services.auth.check_privileged_peer(peer) {
+        return err.into_response();
+    }
+    next.run(request).await
+}
+
+/// Refuse cookie-authenticated writes that do not echo the session's CSRF token.
+pub(super) async fn csrf(request: Request, next: Next) -> Response {
+    let safe = matches!(
+        *request.method(),
+        Method::GET | Method::HEAD | Method::OPTIONS
+    );
+    if !safe && let Err(err) = AuthService::check_csrf(request.headers()) {
+        return err.into_response();
+    }
+    next.run(request).await
+}
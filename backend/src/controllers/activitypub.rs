@@ -0,0 +1,118 @@
+use axum::body::Bytes;
+use axum::extract::{OriginalUri, Path, Query, State};
+use axum::http::header::CONTENT_TYPE;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use serde::Deserialize;
+use serde_json::Value;
+
+use odin_core::errors::AppError;
+use odin_core::services::ACTIVITY_JSON;
+use odin_core::types::Scope;
+
+use super::AppState;
+
+#[derive(Deserialize)]
+pub(super) struct WebfingerParams {
+    resource: String,
+}
+
+pub(super) async fn webfinger(
+    State(state): State<AppState>,
+    Query(params): Query<WebfingerParams>,
+) -> Result<Response, AppError> {
+    let document = state
+        .services
+        .activitypub
+        .webfinger(&params.resource)
+        .await?;
+    Ok(typed("application/jrd+json", document))
+}
+
+pub(super) async fn actor(State(state): State<AppState>) -> Result<Response, AppError> {
+    Ok(typed(
+        ACTIVITY_JSON,
+        state.services.activitypub.actor().await?,
+    ))
+}
+
+pub(super) async fn outbox(State(state): State<AppState>) -> Result<Response, AppError> {
+    Ok(typed(
+        ACTIVITY_JSON,
+        state.services.activitypub.outbox().await?,
+    ))
+}
+
+pub(super) async fn followers(State(state): State<AppState>) -> Result<Response, AppError> {
+    Ok(typed(
+        ACTIVITY_JSON,
+        state.services.activitypub.followers().await?,
+    ))
+}
+
+pub(super) async fn note(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Response, AppError> {
+    Ok(typed(
+        ACTIVITY_JSON,
+        state.services.activitypub.note(id).await?,
+    ))
+}
+
+/// Authenticated by the sender's HTTP signature rather than an odin token.
+pub(super) async fn inbox(
+    State(state): State<AppState>,
+    OriginalUri(uri): OriginalUri,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<StatusCode, AppError> {
+    let target = uri
+        .path_and_query()
+        .map_or(uri.path(), |target| target.as_str());
+    state
+        .services
+        .activitypub
+        .inbox(&headers, target, &body)
+        .await?;
+    Ok(StatusCode::ACCEPTED)
+}
+
+pub(super) async fn publish_bookmark(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<i64>,
+) -> Result<StatusCode, AppError> {
+    set_public(state, headers, id, true).await
+}
+
+pub(super) async fn unpublish_bookmark(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<i64>,
+) -> Result<StatusCode, AppError> {
+    set_public(state, headers, id, false).await
+}
+
+async fn set_public(
+    state: AppState,
+    headers: HeaderMap,
+    id: i64,
+    public: bool,
+) -> Result<StatusCode, AppError> {
+    let caller = state
+        .services
+        .auth
+        .authorize(&headers, Scope::Ingest)
+        .await?;
+    state
+        .services
+        .activitypub
+        .set_public(caller, id, public)
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+fn typed(content_type: &'static str, document: Value) -> Response {
+    ([(CONTENT_TYPE, content_type)], document.to_string()).into_response()
+}
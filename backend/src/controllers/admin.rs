@@ -0,0 +1,217 @@
+use axum::Json;
+use axum::body::Body;
+use axum::extract::{Query, State};
+use axum::http::header::{CONTENT_DISPOSITION, CONTENT_TYPE};
+use axum::http::{Extensions, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use futures_util::StreamExt;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_util::io::ReaderStream;
+
+use super::jobs::X_ODIN_JOB_ID;
+use odin_core::errors::AppError;
+use odin_core::types::{
+    AdminStatusResponse, BootstrapRequest, ConsistencyResponse, CreateApiKeyResponse,
+    DbMaintenanceResponse, DomainStatsParams, DomainStatsResponse, ReadOnlyStatus,
+    ReconcileResponse, ReindexRequest, RuntimeSettings, Scope, SetReadOnlyRequest,
+    UpdateSettingsRequest,
+};
+
+use super::AppState;
+
+/// First-run setup: no token, since none exists yet. Over TCP the setup secret is
+/// needed unless `auth.bootstrap_trust_loopback` trusts an unforwarded loopback peer.
+pub(super) async fn bootstrap(
+    State(state): State<AppState>,
+    extensions: Extensions,
+    headers: HeaderMap,
+    Json(request): Json<BootstrapRequest>,
+) -> Result<Json<CreateApiKeyResponse>, AppError> {
+    let forwarded = headers.contains_key("forwarded") || headers.contains_key("x-forwarded-for");
+    let peer = super::access::peer(&extensions);
+    let key = state
+        .services
+        .api_keys
+        .claim_bootstrap(peer, forwarded, request)
+        .await?;
+    Ok(Json(key))
+}
+
+pub(super) async fn status(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<AdminStatusResponse>, AppError> {
+    state
+        .services
+        .auth
+        .authorize(&headers, Scope::Admin)
+        .await?;
+    Ok(Json(state.services.status.status().await?))
+}
+
+/// Fetch totals per domain, to find the hosts that slow or fail the crawl.
+pub(super) async fn domain_stats(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<DomainStatsParams>,
+) -> Result<Json<DomainStatsResponse>, AppError> {
+    state
+        .services
+        .auth
+        .authorize(&headers, Scope::Admin)
+        .await?;
+    Ok(Json(state.services.domain_stats.stats(params).await?))
+}
+
+pub(super) async fn get_settings(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<RuntimeSettings>, AppError> {
+    state
+        .services
+        .auth
+        .authorize(&headers, Scope::Admin)
+        .await?;
+    Ok(Json(state.services.settings.get()))
+}
+
+pub(super) async fn update_settings(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<UpdateSettingsRequest>,
+) -> Result<Json<RuntimeSettings>, AppError> {
+    state
+        .services
+        .auth
+        .authorize(&headers, Scope::Admin)
+        .await?;
+    Ok(Json(state.services.settings.update(payload).await?))
+}
+
+pub(super) async fn get_read_only(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<ReadOnlyStatus>, AppError> {
+    state
+        .services
+        .auth
+        .authorize(&headers, Scope::Admin)
+        .await?;
+    Ok(Json(state.services.read_only.get()))
+}
+
+pub(super) async fn set_read_only(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<SetReadOnlyRequest>,
+) -> Result<Json<ReadOnlyStatus>, AppError> {
+    state
+        .services
+        .auth
+        .authorize(&headers, Scope::Admin)
+        .await?;
+    Ok(Json(state.services.read_only.set(payload)?))
+}
+
+pub(super) async fn db_maintenance(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<DbMaintenanceResponse>, AppError> {
+    state
+        .services
+        .auth
+        .authorize(&headers, Scope::Admin)
+        .await?;
+    Ok(Json(state.services.maintenance.optimize_db().await?))
+}
+
+pub(super) async fn reconcile(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<ReconcileResponse>, AppError> {
+    state
+        .services
+        .auth
+        .authorize(&headers, Scope::Admin)
+        .await?;
+    Ok(Json(state.services.reconcile.reconcile().await?))
+}
+
+pub(super) async fn consistency(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<ConsistencyResponse>, AppError> {
+    state
+        .services
+        .auth
+        .authorize(&headers, Scope::Admin)
+        .await?;
+    Ok(Json(state.services.reconcile.check().await?))
+}
+
+pub(super) async fn backup(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let caller = state
+        .services
+        .auth
+        .authorize(&headers, Scope::Admin)
+        .await?;
+    let archive = state.services.backup.create(caller.user_id).await?;
+    let disposition = format!("attachment; filename=\"{}\"", archive.file_name);
+    Ok((
+        [
+            (CONTENT_TYPE, "application/gzip".to_string()),
+            (CONTENT_DISPOSITION, disposition),
+            (X_ODIN_JOB_ID.clone(), archive.job_id.to_string()),
+        ],
+        Body::from_stream(ReaderStream::new(archive.file)),
+    )
+        .into_response())
+}
+
+pub(super) async fn restore(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Body,
+) -> Result<StatusCode, AppError> {
+    state
+        .services
+        .auth
+        .authorize(&headers, Scope::Admin)
+        .await?;
+    state.services.backup.stage_restore(body).await?;
+    Ok(StatusCode::ACCEPTED)
+}
+
+pub(super) async fn reindex(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<ReindexRequest>,
+) -> Result<Response, AppError> {
+    let caller = state
+        .services
+        .auth
+        .authorize(&headers, Scope::Admin)
+        .await?;
+    let (job_id, progress) = state
+        .services
+        .ingest
+        .reindex(caller.user_id, payload)
+        .await?;
+    let lines = ReceiverStream::new(progress).map(|event| {
+        serde_json::to_vec(&event).map(|mut line| {
+            line.push(b'\n');
+            line
+        })
+    });
+    Ok((
+        [
+            (CONTENT_TYPE, "application/x-ndjson".to_string()),
+            (X_ODIN_JOB_ID.clone(), job_id.to_string()),
+        ],
+        Body::from_stream(lines),
+    )
+        .into_response())
+}
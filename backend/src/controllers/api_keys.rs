@@ -0,0 +1,64 @@
+use axum::Json;
+use axum::extract::{Path, State};
+use axum::http::{HeaderMap, StatusCode};
+
+use odin_core::errors::AppError;
+use odin_core::types::{
+    ApiKeysResponse, CreateApiKeyRequest, CreateApiKeyResponse, RotateApiKeyRequest,
+    RotateApiKeyResponse, Scope,
+};
+
+use super::AppState;
+
+pub(super) async fn list_keys(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<ApiKeysResponse>, AppError> {
+    let caller = state.services.auth.authorize(&headers, Scope::Read).await?;
+    let response = state.services.api_keys.list(caller).await?;
+    Ok(Json(response))
+}
+
+pub(super) async fn create_key(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<CreateApiKeyRequest>,
+) -> Result<Json<CreateApiKeyResponse>, AppError> {
+    let caller = state
+        .services
+        .auth
+        .authorize(&headers, Scope::Ingest)
+        .await?;
+    let response = state.services.api_keys.create(caller, payload).await?;
+    Ok(Json(response))
+}
+
+pub(super) async fn revoke_key(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<i64>,
+) -> Result<StatusCode, AppError> {
+    let caller = state
+        .services
+        .auth
+        .authorize(&headers, Scope::Ingest)
+        .await?;
+    state.services.api_keys.revoke(caller, id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub(super) async fn rotate_key(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<i64>,
+    payload: Option<Json<RotateApiKeyRequest>>,
+) -> Result<Json<RotateApiKeyResponse>, AppError> {
+    let caller = state
+        .services
+        .auth
+        .authorize(&headers, Scope::Ingest)
+        .await?;
+    let payload = payload.map(|Json(payload)| payload).unwrap_or_default();
+    let response = state.services.api_keys.rotate(caller, id, payload).await?;
+    Ok(Json(response))
+}
@@ -1,25 +1,203 @@
-use crate::errors::AppError;
-use crate::types::{AppState, BookmarksResponse};
 use axum::Json;
 use axum::extract::Path;
-use axum::extract::State;
-use axum::http::HeaderMap;
-use axum::http::StatusCode;
-use tracing::info;
+use axum::extract::{Query, State};
+use axum::http::header::{CONTENT_SECURITY_POLICY, CONTENT_TYPE, X_CONTENT_TYPE_OPTIONS};
+use axum::http::{HeaderMap, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use odin_core::errors::AppError;
+use odin_core::types::{
+    BookmarkListItem, BookmarkTagsRequest, BookmarkTagsResponse, BookmarksParams,
+    BulkDeleteRequest, BulkDeleteResponse, LookupParams, NamespacesResponse, RefetchParams,
+    RefetchResponse, Scope, TagsResponse,
+};
+use serde::Deserialize;
+
+use super::etag;
+
+use super::AppState;
 
 pub(super) async fn list_bookmarks(
     State(state): State<AppState>,
-) -> Result<Json<BookmarksResponse>, AppError> {
-    let response = state.services.bookmarks.list().await?;
+    headers: HeaderMap,
+    Query(params): Query<BookmarksParams>,
+) -> Result<Response, AppError> {
+    let caller = state.services.auth.authorize(&headers, Scope::Read).await?;
+    let response = state.services.bookmarks.list(caller, params).await?;
+    // Status changes (pending, failed) never touch the index, so hash the listing itself.
+    let body = serde_json::to_vec(&response).map_err(anyhow::Error::from)?;
+    let etag = etag::weak_etag(&body);
+    if etag::is_fresh(&headers, &etag) {
+        return Ok(etag::not_modified(etag));
+    }
+    Ok(etag::json_with_etag(etag, response))
+}
+
+pub(super) async fn lookup_bookmark(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<LookupParams>,
+) -> Result<Json<BookmarkListItem>, AppError> {
+    let caller = state.services.auth.authorize(&headers, Scope::Read).await?;
+    let bookmark = state.services.bookmarks.lookup(caller, params).await?;
+    Ok(Json(bookmark))
+}
+
+pub(super) async fn get_bookmark(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<i64>,
+) -> Result<Json<BookmarkListItem>, AppError> {
+    let caller = state.services.auth.authorize(&headers, Scope::Read).await?;
+    let bookmark = state
+        .services
+        .bookmarks
+        .get(&caller, id)
+        .await?
+        .ok_or_else(|| AppError::not_found("bookmark not found"))?;
+    Ok(Json(bookmark))
+}
+
+pub(super) async fn list_namespaces(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<NamespacesResponse>, AppError> {
+    let caller = state.services.auth.authorize(&headers, Scope::Read).await?;
+    let response = state.services.bookmarks.namespaces(caller).await?;
+    Ok(Json(response))
+}
+
+pub(super) async fn list_tags(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<TagsResponse>, AppError> {
+    let caller = state.services.auth.authorize(&headers, Scope::Read).await?;
+    let response = state.services.bookmarks.tags(caller).await?;
+    Ok(Json(response))
+}
+
+pub(super) async fn set_bookmark_tags(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<i64>,
+    Json(payload): Json<BookmarkTagsRequest>,
+) -> Result<Json<BookmarkTagsResponse>, AppError> {
+    let caller = state
+        .services
+        .auth
+        .authorize(&headers, Scope::Ingest)
+        .await?;
+    let response = state
+        .services
+        .bookmarks
+        .set_tags(caller, id, payload)
+        .await?;
     Ok(Json(response))
 }
 
+#[derive(Deserialize)]
+pub(super) struct ContentParams {
+    /// `html` serves the archived page instead of the extracted text.
+    format: Option<String>,
+}
+
+pub(super) async fn bookmark_content(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<i64>,
+    Query(params): Query<ContentParams>,
+) -> Result<Response, AppError> {
+    let caller = state.services.auth.authorize(&headers, Scope::Read).await?;
+    match params.format.as_deref() {
+        None | Some("text") => {
+            let response = state.services.bookmarks.content(caller, id).await?;
+            Ok(Json(response).into_response())
+        }
+        Some("html") => {
+            let page = state.services.bookmarks.archive(caller, id).await?;
+            let content_type = HeaderValue::from_str(&page.content_type)
+                .unwrap_or(HeaderValue::from_static("text/html"));
+            // Served from the API's origin, so the page must not run scripts or reach
+            // anything but itself.
+            Ok((
+                [
+                    (CONTENT_TYPE, content_type),
+                    (
+                        CONTENT_SECURITY_POLICY,
+                        HeaderValue::from_static("sandbox; default-src 'none'; img-src * data:; style-src * 'unsafe-inline'"),
+                    ),
+                    (X_CONTENT_TYPE_OPTIONS, HeaderValue::from_static("nosniff")),
+                ],
+                page.body,
+            )
+                .into_response())
+        }
+        Some(other) => Err(AppError::bad_request(format!(
+            "unknown content format `{}` (expected text or html)",
+            other
+        ))),
+    }
+}
+
 pub(super) async fn delete_bookmark(
     State(state): State<AppState>,
     headers: HeaderMap,
     Path(id): Path<i64>,
 ) -> Result<StatusCode, AppError> {
-    state.services.auth.authorize(&headers)?;
-    state.services.bookmarks.delete(id).await?;
+    let caller = state
+        .services
+        .auth
+        .authorize(&headers, Scope::Delete)
+        .await?;
+    state.services.bookmarks.delete(caller, id).await?;
     Ok(StatusCode::NO_CONTENT)
 }
+
+pub(super) async fn bulk_delete_bookmarks(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<BulkDeleteRequest>,
+) -> Result<Json<BulkDeleteResponse>, AppError> {
+    let caller = state
+        .services
+        .auth
+        .authorize(&headers, Scope::Delete)
+        .await?;
+    let response = state
+        .services
+        .bookmarks
+        .delete_many(caller, payload)
+        .await?;
+    Ok(Json(response))
+}
+
+pub(super) async fn refetch_bookmark(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<i64>,
+) -> Result<Json<RefetchResponse>, AppError> {
+    let caller = state
+        .services
+        .auth
+        .authorize(&headers, Scope::Ingest)
+        .await?;
+    let response = state.services.bookmarks.refetch(caller, id).await?;
+    Ok(Json(response))
+}
+
+pub(super) async fn refetch_bookmarks(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<RefetchParams>,
+) -> Result<Json<RefetchResponse>, AppError> {
+    let caller = state
+        .services
+        .auth
+        .authorize(&headers, Scope::Ingest)
+        .await?;
+    let response = state
+        .services
+        .bookmarks
+        .refetch_many(caller, params)
+        .await?;
+    Ok(Json(response))
+}
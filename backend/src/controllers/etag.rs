@@ -0,0 +1,44 @@
+use axum::Json;
+use axum::http::header::{CACHE_CONTROL, ETAG, IF_NONE_MATCH};
+use axum::http::{HeaderMap, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+/// Weak validator over `seed`; only equality matters, so a truncated digest is plenty.
+pub(super) fn weak_etag(seed: &[u8]) -> HeaderValue {
+    let digest = hex::encode(&Sha256::digest(seed)[..16]);
+    HeaderValue::from_str(&format!("W/\"{}\"", digest)).expect("hex etag is a valid header")
+}
+
+/// Whether the client's `If-None-Match` already names `etag` (weak comparison).
+pub(super) fn is_fresh(headers: &HeaderMap, etag: &HeaderValue) -> bool {
+    let Some(Ok(candidates)) = headers.get(IF_NONE_MATCH).map(HeaderValue::to_str) else {
+        return false;
+    };
+    let ours = opaque_tag(etag.to_str().unwrap_or_default());
+    candidates
+        .split(',')
+        .map(str::trim)
+        .any(|candidate| candidate == "*" || opaque_tag(candidate) == ours)
+}
+
+fn opaque_tag(tag: &str) -> &str {
+    tag.strip_prefix("W/").unwrap_or(tag)
+}
+
+pub(super) fn not_modified(etag: HeaderValue) -> Response {
+    (StatusCode::NOT_MODIFIED, cache_headers(etag)).into_response()
+}
+
+pub(super) fn json_with_etag(etag: HeaderValue, body: impl Serialize) -> Response {
+    (cache_headers(etag), Json(body)).into_response()
+}
+
+/// Responses are per-caller, and browsers should revalidate each time so polling sees changes.
+fn cache_headers(etag: HeaderValue) -> [(axum::http::HeaderName, HeaderValue); 2] {
+    [
+        (ETAG, etag),
+        (CACHE_CONTROL, HeaderValue::from_static("private, no-cache")),
+    ]
+}
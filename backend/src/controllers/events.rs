@@ -0,0 +1,40 @@
+use std::convert::Infallible;
+
+use axum::extract::{Query, State};
+use axum::http::HeaderMap;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use futures_util::{Stream, StreamExt};
+
+use odin_core::errors::AppError;
+use odin_core::types::{EventsParams, Scope};
+
+use super::AppState;
+
+pub(super) async fn events(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<EventsParams>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, AppError> {
+    let caller = match params.token {
+        Some(token) if !headers.contains_key("authorization") => {
+            state
+                .services
+                .auth
+                .authorize_token(token.trim(), params.namespace.as_deref(), Scope::Read)
+                .await?
+        }
+        _ => state.services.auth.authorize(&headers, Scope::Read).await?,
+    };
+
+    let stream = state.services.events.subscribe(caller).map(|item| {
+        let event = match item {
+            Ok(event) => Event::default()
+                .event(event.event.as_str())
+                .json_data(&event)
+                .unwrap_or_else(|_| Event::default().event("error")),
+            Err(skipped) => Event::default().event("lagged").data(skipped.to_string()),
+        };
+        Ok(event)
+    });
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
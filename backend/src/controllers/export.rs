@@ -0,0 +1,73 @@
+use axum::body::Body;
+use axum::extract::{Query, State};
+use axum::http::HeaderMap;
+use axum::http::header::{CONTENT_DISPOSITION, CONTENT_TYPE};
+use axum::response::{IntoResponse, Response};
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_util::io::ReaderStream;
+
+use odin_core::errors::AppError;
+use odin_core::services::ExportStream;
+use odin_core::types::{ExportFormat, ExportParams, ExportStreamParams, Scope};
+
+use super::AppState;
+
+pub(super) async fn export_markdown(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let caller = state.services.auth.authorize(&headers, Scope::Read).await?;
+    let archive = state.services.export.markdown(caller).await?;
+    let disposition = format!("attachment; filename=\"{}\"", archive.file_name);
+    Ok((
+        [
+            (CONTENT_TYPE, "application/zip".to_string()),
+            (CONTENT_DISPOSITION, disposition),
+        ],
+        Body::from_stream(ReaderStream::new(archive.file)),
+    )
+        .into_response())
+}
+
+/// Every bookmark as one JSON object per line, streamed as the rows are read.
+pub(super) async fn export_stream(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<ExportStreamParams>,
+) -> Result<Response, AppError> {
+    let caller = state.services.auth.authorize(&headers, Scope::Read).await?;
+    let export = state
+        .services
+        .export
+        .stream(caller, ExportFormat::Ndjson, params.content)
+        .await?;
+    Ok(stream_response(export))
+}
+
+/// Every bookmark as JSON, NDJSON, CSV, or a Netscape bookmark file, streamed as the
+/// rows are read.
+pub(super) async fn export(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<ExportParams>,
+) -> Result<Response, AppError> {
+    let caller = state.services.auth.authorize(&headers, Scope::Read).await?;
+    let export = state
+        .services
+        .export
+        .stream(caller, params.format, params.content)
+        .await?;
+    Ok(stream_response(export))
+}
+
+fn stream_response(export: ExportStream) -> Response {
+    let disposition = format!("attachment; filename=\"{}\"", export.file_name);
+    (
+        [
+            (CONTENT_TYPE, export.content_type.to_string()),
+            (CONTENT_DISPOSITION, disposition),
+        ],
+        Body::from_stream(ReceiverStream::new(export.lines)),
+    )
+        .into_response()
+}
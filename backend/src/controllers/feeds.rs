@@ -0,0 +1,44 @@
+use axum::Json;
+use axum::extract::{Path, State};
+use axum::http::{HeaderMap, StatusCode};
+
+use odin_core::errors::AppError;
+use odin_core::types::{FeedCreateRequest, FeedItem, FeedsResponse, Scope};
+
+use super::AppState;
+
+pub(super) async fn list_feeds(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<FeedsResponse>, AppError> {
+    let caller = state.services.auth.authorize(&headers, Scope::Read).await?;
+    Ok(Json(state.services.feeds.list(caller).await?))
+}
+
+pub(super) async fn add_feed(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<FeedCreateRequest>,
+) -> Result<(StatusCode, Json<FeedItem>), AppError> {
+    let caller = state
+        .services
+        .auth
+        .authorize(&headers, Scope::Ingest)
+        .await?;
+    let feed = state.services.feeds.add(caller, payload).await?;
+    Ok((StatusCode::CREATED, Json(feed)))
+}
+
+pub(super) async fn remove_feed(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<i64>,
+) -> Result<StatusCode, AppError> {
+    let caller = state
+        .services
+        .auth
+        .authorize(&headers, Scope::Ingest)
+        .await?;
+    state.services.feeds.remove(caller, id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,70 @@
+use axum::body::Body;
+use axum::http::header::{CACHE_CONTROL, CONTENT_TYPE, ETAG};
+use axum::http::{HeaderMap, HeaderValue, StatusCode, Uri};
+use axum::response::{IntoResponse, Response};
+use rust_embed::{EmbeddedFile, RustEmbed};
+use tracing::warn;
+
+use super::etag;
+use odin_core::errors::AppError;
+
+const INDEX: &str = "index.html";
+
+/// The Vite build output; debug builds read it from disk, release builds embed it.
+#[derive(RustEmbed)]
+#[folder = "../frontend/dist"]
+#[allow_missing = true]
+struct Bundle;
+
+pub(super) fn check_bundle() {
+    if Bundle::get(INDEX).is_none() {
+        warn!("server.serve_frontend is set but no frontend bundle was built into this binary");
+    }
+}
+
+/// Serve a bundled file, or `index.html` for extensionless paths so client-side routes load.
+pub(super) async fn serve(uri: Uri, headers: HeaderMap) -> Result<Response, AppError> {
+    let path = uri.path().trim_start_matches('/');
+    let (path, file) = match Bundle::get(path) {
+        Some(file) => (path, file),
+        _ if path.starts_with("v1/") || path.rsplit('/').next().unwrap_or("").contains('.') => {
+            return Err(AppError::not_found("not found"));
+        }
+        _ => (
+            INDEX,
+            Bundle::get(INDEX).ok_or_else(|| AppError::not_found("frontend is not built"))?,
+        ),
+    };
+
+    let etag = strong_etag(&file);
+    let cache_control = if path.starts_with("assets/") {
+        // Vite fingerprints everything under assets/, so it never changes in place.
+        HeaderValue::from_static("public, max-age=31536000, immutable")
+    } else {
+        HeaderValue::from_static("no-cache")
+    };
+    if etag::is_fresh(&headers, &etag) {
+        return Ok((
+            StatusCode::NOT_MODIFIED,
+            [(ETAG, etag), (CACHE_CONTROL, cache_control)],
+        )
+            .into_response());
+    }
+
+    let content_type = HeaderValue::from_str(file.metadata.mimetype())
+        .unwrap_or(HeaderValue::from_static("application/octet-stream"));
+    Ok((
+        [
+            (CONTENT_TYPE, content_type),
+            (ETAG, etag),
+            (CACHE_CONTROL, cache_control),
+        ],
+        Body::from(file.data),
+    )
+        .into_response())
+}
+
+fn strong_etag(file: &EmbeddedFile) -> HeaderValue {
+    let digest = hex::encode(&file.metadata.sha256_hash()[..16]);
+    HeaderValue::from_str(&format!("\"{}\"", digest)).expect("hex etag is a valid header")
+}
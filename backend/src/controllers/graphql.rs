@@ -0,0 +1,18 @@
+use axum::Json;
+use axum::extract::State;
+use axum::http::HeaderMap;
+
+use odin_core::errors::AppError;
+use odin_core::types::Scope;
+
+use super::AppState;
+
+/// Authentication happens up front; errors inside the query come back in the GraphQL body.
+pub(super) async fn graphql(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<async_graphql::Request>,
+) -> Result<Json<async_graphql::Response>, AppError> {
+    let caller = state.services.auth.authorize(&headers, Scope::Read).await?;
+    Ok(Json(state.graphql.execute(request.data(caller)).await))
+}
@@ -1,3 +1,22 @@
+use axum::Json;
+use axum::extract::State;
+use axum::http::StatusCode;
+
+use odin_core::types::{HealthStatus, ReadinessResponse};
+
+use super::AppState;
+
 pub(super) async fn healthz() -> &'static str {
     "ok"
 }
+
+/// 503 when any component fails, so orchestrators can pull or restart the instance.
+pub(super) async fn readyz(State(state): State<AppState>) -> (StatusCode, Json<ReadinessResponse>) {
+    let readiness = state.services.health.readiness().await;
+    let status = if readiness.status == HealthStatus::Error {
+        StatusCode::SERVICE_UNAVAILABLE
+    } else {
+        StatusCode::OK
+    };
+    (status, Json(readiness))
+}
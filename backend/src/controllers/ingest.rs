@@ -1,16 +1,132 @@
 use axum::Json;
-use axum::extract::State;
+use axum::body::Body;
+use axum::extract::{FromRequest, Multipart, Query, Request, State};
 use axum::http::HeaderMap;
+use axum::http::header::CONTENT_TYPE;
+use futures_util::StreamExt;
 
-use crate::errors::AppError;
-use crate::types::{AppState, IngestUrlsRequest, IngestUrlsResponse};
+use odin_core::errors::AppError;
+use odin_core::types::{
+    BrowserSyncParams, BrowserSyncResponse, ImportParams, ImportResponse, IngestUrlsRequest,
+    IngestUrlsResponse, InstapaperImportParams, InstapaperImportResponse, OpmlImportParams,
+    OpmlImportResponse, Scope,
+};
+
+use super::AppState;
 
 pub(super) async fn ingest_urls(
     State(state): State<AppState>,
     headers: HeaderMap,
     Json(payload): Json<IngestUrlsRequest>,
 ) -> Result<Json<IngestUrlsResponse>, AppError> {
-    state.services.auth.authorize(&headers)?;
-    let response = state.services.ingest.ingest_urls(payload).await?;
+    let caller = state
+        .services
+        .auth
+        .authorize(&headers, Scope::Ingest)
+        .await?;
+    let response = state.services.ingest.ingest_urls(caller, payload).await?;
     Ok(Json(response))
 }
+
+/// Import every URL in an uploaded export: `multipart/form-data` (one or more files)
+/// or a raw body. The upload is scanned as it streams in, never buffered whole, unless
+/// `format` names a structured export.
+pub(super) async fn import(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<ImportParams>,
+    request: Request,
+) -> Result<Json<ImportResponse>, AppError> {
+    let caller = state
+        .services
+        .auth
+        .authorize(&headers, Scope::Ingest)
+        .await?;
+    let mut session = state.services.import.begin(caller, params.format).await?;
+
+    let is_multipart = headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("multipart/form-data"));
+    if is_multipart {
+        let mut multipart = Multipart::from_request(request, &state)
+            .await
+            .map_err(|err| AppError::bad_request(err.body_text()))?;
+        while let Some(mut field) = multipart.next_field().await.map_err(AppError::upload)? {
+            while let Some(chunk) = field.chunk().await.map_err(AppError::upload)? {
+                session.feed(&chunk).await?;
+            }
+            session.end_part().await?;
+        }
+    } else {
+        let mut body = request.into_body().into_data_stream();
+        while let Some(chunk) = body.next().await {
+            session.feed(&chunk.map_err(AppError::upload)?).await?;
+        }
+    }
+    Ok(Json(session.finish().await?))
+}
+
+/// Subscribe to the feeds and save the links of an OPML export, as a raw body.
+pub(super) async fn import_opml(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<OpmlImportParams>,
+    body: Body,
+) -> Result<Json<OpmlImportResponse>, AppError> {
+    let caller = state
+        .services
+        .auth
+        .authorize(&headers, Scope::Ingest)
+        .await?;
+    Ok(Json(
+        state
+            .services
+            .import
+            .opml(caller, body, params.from_now)
+            .await?,
+    ))
+}
+
+/// Sync a browser profile's bookmarks: Chrome's `Bookmarks` JSON or Firefox's
+/// `places.sqlite`, told apart by content, as a raw body.
+pub(super) async fn sync_browser(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<BrowserSyncParams>,
+    body: Body,
+) -> Result<Json<BrowserSyncResponse>, AppError> {
+    let caller = state
+        .services
+        .auth
+        .authorize(&headers, Scope::Ingest)
+        .await?;
+    Ok(Json(
+        state
+            .services
+            .browser_sync
+            .sync(caller, params, body)
+            .await?,
+    ))
+}
+
+/// Import Instapaper's CSV export as a raw body.
+pub(super) async fn import_instapaper(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<InstapaperImportParams>,
+    body: Body,
+) -> Result<Json<InstapaperImportResponse>, AppError> {
+    let caller = state
+        .services
+        .auth
+        .authorize(&headers, Scope::Ingest)
+        .await?;
+    Ok(Json(
+        state
+            .services
+            .import
+            .instapaper(caller, body, params.dry_run)
+            .await?,
+    ))
+}
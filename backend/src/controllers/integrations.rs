@@ -0,0 +1,82 @@
+use axum::Json;
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode};
+
+use odin_core::errors::AppError;
+use odin_core::types::{
+    FeedReaderConnectRequest, FeedReaderStatus, ReadwiseConnectRequest, ReadwiseStatus, Scope,
+};
+
+use super::AppState;
+
+pub(super) async fn readwise_status(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<ReadwiseStatus>, AppError> {
+    let caller = state.services.auth.authorize(&headers, Scope::Read).await?;
+    Ok(Json(state.services.readwise.status(caller).await?))
+}
+
+pub(super) async fn connect_readwise(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<ReadwiseConnectRequest>,
+) -> Result<Json<ReadwiseStatus>, AppError> {
+    let caller = state
+        .services
+        .auth
+        .authorize(&headers, Scope::Ingest)
+        .await?;
+    Ok(Json(
+        state.services.readwise.connect(caller, payload).await?,
+    ))
+}
+
+pub(super) async fn disconnect_readwise(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<StatusCode, AppError> {
+    let caller = state
+        .services
+        .auth
+        .authorize(&headers, Scope::Ingest)
+        .await?;
+    state.services.readwise.disconnect(caller).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub(super) async fn feed_reader_status(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<FeedReaderStatus>, AppError> {
+    let caller = state.services.auth.authorize(&headers, Scope::Read).await?;
+    Ok(Json(state.services.feed_reader.status(caller).await?))
+}
+
+pub(super) async fn connect_feed_reader(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<FeedReaderConnectRequest>,
+) -> Result<Json<FeedReaderStatus>, AppError> {
+    let caller = state
+        .services
+        .auth
+        .authorize(&headers, Scope::Ingest)
+        .await?;
+    Ok(Json(
+        state.services.feed_reader.connect(caller, payload).await?,
+    ))
+}
+
+pub(super) async fn disconnect_feed_reader(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<StatusCode, AppError> {
+    let caller = state
+        .services
+        .auth
+        .authorize(&headers, Scope::Ingest)
+        .await?;
+    state.services.feed_reader.disconnect(caller).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
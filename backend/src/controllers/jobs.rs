@@ -0,0 +1,44 @@
+use axum::Json;
+use axum::extract::{Path, State};
+use axum::http::{HeaderMap, HeaderName};
+
+use odin_core::errors::AppError;
+use odin_core::types::{JobItem, JobsResponse, Scope};
+
+use super::AppState;
+
+/// Set on responses that started a job, so clients can follow or cancel it.
+pub(super) static X_ODIN_JOB_ID: HeaderName = HeaderName::from_static("x-odin-job-id");
+
+pub(super) async fn list_jobs(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<JobsResponse>, AppError> {
+    let caller = state.services.auth.authorize(&headers, Scope::Read).await?;
+    let response = state.services.jobs.list(caller).await?;
+    Ok(Json(response))
+}
+
+pub(super) async fn get_job(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<i64>,
+) -> Result<Json<JobItem>, AppError> {
+    let caller = state.services.auth.authorize(&headers, Scope::Read).await?;
+    let job = state.services.jobs.get(caller, id).await?;
+    Ok(Json(job))
+}
+
+pub(super) async fn cancel_job(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<i64>,
+) -> Result<Json<JobItem>, AppError> {
+    let caller = state
+        .services
+        .auth
+        .authorize(&headers, Scope::Ingest)
+        .await?;
+    let job = state.services.jobs.cancel(caller, id).await?;
+    Ok(Json(job))
+}
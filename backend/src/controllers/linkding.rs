@@ -0,0 +1,170 @@
+use axum::Json;
+use axum::extract::{Path, Query, State};
+use axum::http::{HeaderMap, StatusCode};
+use odin_core::errors::AppError;
+use odin_core::types::{
+    LinkdingBookmark, LinkdingBookmarkRequest, LinkdingCheckResponse, LinkdingListParams,
+    LinkdingPage, LinkdingProfile, LinkdingTag, LookupParams, Scope,
+};
+
+use super::AppState;
+
+pub(super) async fn list_bookmarks(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<LinkdingListParams>,
+) -> Result<Json<LinkdingPage<LinkdingBookmark>>, AppError> {
+    let caller = state.services.auth.authorize(&headers, Scope::Read).await?;
+    let page = state.services.linkding.list(caller, params, false).await?;
+    Ok(Json(page))
+}
+
+pub(super) async fn list_archived(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<LinkdingListParams>,
+) -> Result<Json<LinkdingPage<LinkdingBookmark>>, AppError> {
+    let caller = state.services.auth.authorize(&headers, Scope::Read).await?;
+    let page = state.services.linkding.list(caller, params, true).await?;
+    Ok(Json(page))
+}
+
+pub(super) async fn check_url(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<LookupParams>,
+) -> Result<Json<LinkdingCheckResponse>, AppError> {
+    let caller = state.services.auth.authorize(&headers, Scope::Read).await?;
+    let response = state.services.linkding.check(caller, params).await?;
+    Ok(Json(response))
+}
+
+pub(super) async fn get_bookmark(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<i64>,
+) -> Result<Json<LinkdingBookmark>, AppError> {
+    let caller = state.services.auth.authorize(&headers, Scope::Read).await?;
+    let bookmark = state.services.linkding.get(&caller, id).await?;
+    Ok(Json(bookmark))
+}
+
+pub(super) async fn create_bookmark(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<LinkdingBookmarkRequest>,
+) -> Result<(StatusCode, Json<LinkdingBookmark>), AppError> {
+    let caller = state
+        .services
+        .auth
+        .authorize(&headers, Scope::Ingest)
+        .await?;
+    let bookmark = state.services.linkding.create(caller, payload).await?;
+    Ok((StatusCode::CREATED, Json(bookmark)))
+}
+
+pub(super) async fn update_bookmark(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<i64>,
+    Json(payload): Json<LinkdingBookmarkRequest>,
+) -> Result<Json<LinkdingBookmark>, AppError> {
+    let caller = state
+        .services
+        .auth
+        .authorize(&headers, Scope::Ingest)
+        .await?;
+    let bookmark = state.services.linkding.update(caller, id, payload).await?;
+    Ok(Json(bookmark))
+}
+
+pub(super) async fn delete_bookmark(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<i64>,
+) -> Result<StatusCode, AppError> {
+    let caller = state
+        .services
+        .auth
+        .authorize(&headers, Scope::Delete)
+        .await?;
+    state.services.linkding.delete(caller, id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub(super) async fn archive_bookmark(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<i64>,
+) -> Result<StatusCode, AppError> {
+    set_archived(state, headers, id, true).await
+}
+
+pub(super) async fn unarchive_bookmark(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<i64>,
+) -> Result<StatusCode, AppError> {
+    set_archived(state, headers, id, false).await
+}
+
+async fn set_archived(
+    state: AppState,
+    headers: HeaderMap,
+    id: i64,
+    archived: bool,
+) -> Result<StatusCode, AppError> {
+    let caller = state
+        .services
+        .auth
+        .authorize(&headers, Scope::Ingest)
+        .await?;
+    state
+        .services
+        .linkding
+        .set_archived(caller, id, archived)
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub(super) async fn list_tags(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<LinkdingListParams>,
+) -> Result<Json<LinkdingPage<LinkdingTag>>, AppError> {
+    let caller = state.services.auth.authorize(&headers, Scope::Read).await?;
+    let page = state.services.linkding.tags(caller, params).await?;
+    Ok(Json(page))
+}
+
+pub(super) async fn get_tag(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<i64>,
+) -> Result<Json<LinkdingTag>, AppError> {
+    let caller = state.services.auth.authorize(&headers, Scope::Read).await?;
+    let tag = state.services.linkding.tag(caller, id).await?;
+    Ok(Json(tag))
+}
+
+pub(super) async fn create_tag(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<LinkdingTag>, AppError> {
+    state
+        .services
+        .auth
+        .authorize(&headers, Scope::Ingest)
+        .await?;
+    Err(AppError::bad_request(
+        "tags are created by tagging a bookmark, not on their own",
+    ))
+}
+
+pub(super) async fn user_profile(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<LinkdingProfile>, AppError> {
+    state.services.auth.authorize(&headers, Scope::Read).await?;
+    Ok(Json(state.services.linkding.profile()))
+}
@@ -0,0 +1,51 @@
+use std::time::Instant;
+
+use axum::Json;
+use axum::extract::{MatchedPath, Request, State};
+use axum::http::HeaderMap;
+use axum::http::header::CONTENT_TYPE;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+
+use odin_core::errors::AppError;
+use odin_core::types::{Scope, StatsResponse};
+
+use super::AppState;
+
+pub(super) async fn metrics(State(state): State<AppState>) -> Result<Response, AppError> {
+    let body = state.services.metrics.render()?;
+    Ok(([(CONTENT_TYPE, "text/plain; version=0.0.4")], body).into_response())
+}
+
+/// Bookmark counts by status and disk usage, for people rather than scrapers.
+pub(super) async fn stats(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<StatsResponse>, AppError> {
+    let caller = state.services.auth.authorize(&headers, Scope::Read).await?;
+    Ok(Json(state.services.status.stats(caller).await?))
+}
+
+/// Record request counts and latency labelled by the matched route template.
+pub(super) async fn track_requests(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let method = request.method().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+    let started = Instant::now();
+
+    let response = next.run(request).await;
+    state.services.metrics.observe_request(
+        &method,
+        &route,
+        response.status().as_u16(),
+        started.elapsed(),
+    );
+    response
+}
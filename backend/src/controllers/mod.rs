@@ -1,31 +1,460 @@
 use axum::Router;
-use axum::http::Method;
-use axum::routing::{delete, get, post};
-use tower_http::cors::{Any, CorsLayer};
+use axum::http::{HeaderName, HeaderValue, Method};
+use axum::middleware;
+use axum::routing::{delete, get, post, put};
+use tower_http::cors::{AllowCredentials, AllowHeaders, AllowMethods, AllowOrigin, CorsLayer};
 use tower_http::limit::RequestBodyLimitLayer;
+use tower_http::request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer};
 use tower_http::trace::TraceLayer;
 
-use crate::types::AppState;
+use odin_core::Odin;
+use odin_core::config::{Config, CorsConfig};
+use odin_core::services::{ConfigService, Services};
 
+mod access;
+mod activitypub;
+mod admin;
+mod api_keys;
 mod bookmarks;
+mod etag;
+mod events;
+mod export;
+mod feeds;
+mod frontend;
+mod graphql;
 mod healthz;
 mod ingest;
+mod integrations;
+mod jobs;
+mod linkding;
+mod metrics;
+mod opensearch;
+mod pinboard;
+mod quick_add;
+mod rate_limit;
+mod request_id;
 mod search;
+mod session;
+mod timeout;
+mod users;
+mod wayback;
+mod webhooks;
 
-pub fn build_router(state: AppState) -> Router {
-    let cors = CorsLayer::new()
-        .allow_origin(Any)
-        .allow_methods([Method::GET, Method::POST, Method::DELETE, Method::OPTIONS])
-        .allow_headers(Any);
+#[derive(Clone)]
+pub struct AppState {
+    pub services: Services,
+    pub graphql: crate::graphql::GraphqlSchema,
+}
+
+impl AppState {
+    pub fn new(services: Services) -> Self {
+        let graphql = crate::graphql::build_schema(services.clone());
+        Self { services, graphql }
+    }
+
+    /// A complete state over empty in-memory storage, for integration tests that should
+    /// not touch disk. Keep the returned [`Odin`] and shut it down when done.
+    pub async fn in_memory() -> anyhow::Result<(Odin, Self)> {
+        let odin = Odin::open(Config::in_memory()).await?;
+        let state = Self::new(odin.services().clone());
+        Ok((odin, state))
+    }
+}
 
-    Router::new()
+pub fn build_router(state: AppState, config: &Config) -> Router {
+    // Every route that needs the ingest or admin scope.
+    let privileged = || middleware::from_fn_with_state(state.clone(), access::privileged);
+    let mut router = Router::new()
         .route("/healthz", get(healthz::healthz))
-        .route("/v1/search", get(search::search))
+        .route("/readyz", get(healthz::readyz))
+        .route("/metrics", get(metrics::metrics))
+        .route("/v1/stats", get(metrics::stats))
+        .route(
+            "/v1/search",
+            get(search::search).route_layer(middleware::from_fn_with_state(
+                state.clone(),
+                rate_limit::limit_search,
+            )),
+        )
+        .route(
+            "/v1/graphql",
+            post(graphql::graphql).route_layer(middleware::from_fn_with_state(
+                state.clone(),
+                rate_limit::limit_search,
+            )),
+        )
         .route("/v1/bookmarks", get(bookmarks::list_bookmarks))
-        .route("/v1/bookmarks/{id}", delete(bookmarks::delete_bookmark))
-        .route("/v1/ingest/urls", post(ingest::ingest_urls))
-        .layer(RequestBodyLimitLayer::new(2 * 1024 * 1024))
-        .layer(cors)
-        .layer(TraceLayer::new_for_http())
+        .route("/v1/bookmarks/lookup", get(bookmarks::lookup_bookmark))
+        .route(
+            "/v1/bookmarks/{id}",
+            get(bookmarks::get_bookmark)
+                .merge(delete(bookmarks::delete_bookmark).route_layer(privileged())),
+        )
+        .route(
+            "/v1/bookmarks/{id}/content",
+            get(bookmarks::bookmark_content),
+        )
+        .route(
+            "/v1/bookmarks/{id}/public",
+            put(activitypub::publish_bookmark)
+                .delete(activitypub::unpublish_bookmark)
+                .route_layer(privileged()),
+        )
+        .route(
+            "/v1/bookmarks/{id}/wayback",
+            get(wayback::snapshot).merge(post(wayback::submit).route_layer(privileged())),
+        )
+        .route(
+            "/v1/bookmarks/{id}/tags",
+            put(bookmarks::set_bookmark_tags).route_layer(privileged()),
+        )
+        .route("/v1/tags", get(bookmarks::list_tags))
+        .route(
+            "/v1/bookmarks/{id}/refetch",
+            post(bookmarks::refetch_bookmark)
+                .route_layer(middleware::from_fn_with_state(
+                    state.clone(),
+                    rate_limit::limit_ingest,
+                ))
+                .route_layer(privileged()),
+        )
+        .route(
+            "/v1/bookmarks/refetch",
+            post(bookmarks::refetch_bookmarks)
+                .route_layer(middleware::from_fn_with_state(
+                    state.clone(),
+                    rate_limit::limit_ingest,
+                ))
+                .route_layer(privileged()),
+        )
+        .route(
+            "/v1/bookmarks/bulk-delete",
+            post(bookmarks::bulk_delete_bookmarks).route_layer(privileged()),
+        )
+        .route(
+            "/v1/ingest/urls",
+            post(ingest::ingest_urls)
+                .route_layer(middleware::from_fn_with_state(
+                    state.clone(),
+                    rate_limit::limit_ingest,
+                ))
+                .route_layer(privileged()),
+        )
+        .route("/opensearch.xml", get(opensearch::description))
+        .route("/search", get(opensearch::search_redirect))
+        .route("/v1/bookmarklet", get(quick_add::bookmarklet))
+        .route(
+            "/v1/quick-add",
+            get(quick_add::quick_add)
+                .route_layer(middleware::from_fn_with_state(
+                    state.clone(),
+                    rate_limit::limit_ingest,
+                ))
+                .route_layer(privileged()),
+        )
+        .route("/v1/namespaces", get(bookmarks::list_namespaces))
+        .route("/v1/export", get(export::export))
+        .route("/v1/export/markdown", get(export::export_markdown))
+        .route("/v1/export/stream", get(export::export_stream))
+        // linkding's REST API, for its apps and browser extensions.
+        .route(
+            "/api/bookmarks/",
+            get(linkding::list_bookmarks).merge(
+                post(linkding::create_bookmark)
+                    .route_layer(middleware::from_fn_with_state(
+                        state.clone(),
+                        rate_limit::limit_ingest,
+                    ))
+                    .route_layer(privileged()),
+            ),
+        )
+        .route("/api/bookmarks/archived/", get(linkding::list_archived))
+        .route("/api/bookmarks/check/", get(linkding::check_url))
+        .route(
+            "/api/bookmarks/{id}/",
+            get(linkding::get_bookmark).merge(
+                put(linkding::update_bookmark)
+                    .patch(linkding::update_bookmark)
+                    .delete(linkding::delete_bookmark)
+                    .route_layer(privileged()),
+            ),
+        )
+        .route(
+            "/api/bookmarks/{id}/archive/",
+            post(linkding::archive_bookmark).route_layer(privileged()),
+        )
+        .route(
+            "/api/bookmarks/{id}/unarchive/",
+            post(linkding::unarchive_bookmark).route_layer(privileged()),
+        )
+        .route(
+            "/api/tags/",
+            get(linkding::list_tags).merge(post(linkding::create_tag).route_layer(privileged())),
+        )
+        .route("/api/tags/{id}/", get(linkding::get_tag))
+        .route("/api/user/profile/", get(linkding::user_profile))
+        // Pinboard's v1 API, for its clients; writes are GETs there too.
+        .route(
+            "/v1/posts/add",
+            get(pinboard::add_post)
+                .route_layer(middleware::from_fn_with_state(
+                    state.clone(),
+                    rate_limit::limit_ingest,
+                ))
+                .route_layer(privileged()),
+        )
+        .route(
+            "/v1/posts/delete",
+            get(pinboard::delete_post).route_layer(privileged()),
+        )
+        .route("/v1/posts/all", get(pinboard::all_posts))
+        // The fediverse actor; inbox deliveries carry HTTP signatures, not odin tokens.
+        .route("/.well-known/webfinger", get(activitypub::webfinger))
+        .route("/ap/actor", get(activitypub::actor))
+        .route("/ap/inbox", post(activitypub::inbox))
+        .route("/ap/outbox", get(activitypub::outbox))
+        .route("/ap/followers", get(activitypub::followers))
+        .route("/ap/notes/{id}", get(activitypub::note))
+        .route("/v1/events", get(events::events))
+        .route(
+            "/v1/users",
+            get(users::list_users)
+                .post(users::create_user)
+                .route_layer(privileged()),
+        )
+        .route(
+            "/v1/keys",
+            get(api_keys::list_keys).merge(post(api_keys::create_key).route_layer(privileged())),
+        )
+        .route(
+            "/v1/keys/{id}",
+            delete(api_keys::revoke_key).route_layer(privileged()),
+        )
+        .route(
+            "/v1/keys/{id}/rotate",
+            post(api_keys::rotate_key).route_layer(privileged()),
+        )
+        .route(
+            "/v1/integrations/readwise",
+            get(integrations::readwise_status).merge(
+                put(integrations::connect_readwise)
+                    .delete(integrations::disconnect_readwise)
+                    .route_layer(privileged()),
+            ),
+        )
+        .route(
+            "/v1/integrations/feed-reader",
+            get(integrations::feed_reader_status).merge(
+                put(integrations::connect_feed_reader)
+                    .delete(integrations::disconnect_feed_reader)
+                    .route_layer(privileged()),
+            ),
+        )
+        .route(
+            "/v1/feeds",
+            get(feeds::list_feeds).merge(post(feeds::add_feed).route_layer(privileged())),
+        )
+        .route(
+            "/v1/feeds/{id}",
+            delete(feeds::remove_feed).route_layer(privileged()),
+        )
+        .route(
+            "/v1/webhooks",
+            get(webhooks::list_webhooks)
+                .merge(post(webhooks::add_webhook).route_layer(privileged())),
+        )
+        .route(
+            "/v1/webhooks/{id}",
+            delete(webhooks::remove_webhook).route_layer(privileged()),
+        )
+        .route("/v1/jobs", get(jobs::list_jobs))
+        .route("/v1/jobs/{id}", get(jobs::get_job))
+        .route(
+            "/v1/jobs/{id}/cancel",
+            post(jobs::cancel_job).route_layer(privileged()),
+        )
+        .route(
+            "/v1/admin/bootstrap",
+            post(admin::bootstrap).route_layer(privileged()),
+        )
+        .route(
+            "/v1/admin/status",
+            get(admin::status).route_layer(privileged()),
+        )
+        .route(
+            "/v1/admin/domains/stats",
+            get(admin::domain_stats).route_layer(privileged()),
+        )
+        .route(
+            "/v1/admin/settings",
+            get(admin::get_settings)
+                .patch(admin::update_settings)
+                .route_layer(privileged()),
+        )
+        .route(
+            "/v1/admin/read-only",
+            get(admin::get_read_only)
+                .put(admin::set_read_only)
+                .route_layer(privileged()),
+        )
+        .route(
+            "/v1/admin/maintenance/db",
+            post(admin::db_maintenance).route_layer(privileged()),
+        )
+        .route(
+            "/v1/admin/reconcile",
+            post(admin::reconcile).route_layer(privileged()),
+        )
+        .route(
+            "/v1/admin/consistency",
+            get(admin::consistency).route_layer(privileged()),
+        )
+        .route(
+            "/v1/admin/backup",
+            get(admin::backup).route_layer(privileged()),
+        )
+        .route(
+            "/v1/admin/reindex",
+            post(admin::reindex).route_layer(privileged()),
+        )
+        .layer(RequestBodyLimitLayer::new(config.server.max_body_bytes))
+        // Uploads below stream instead of buffering, so they get their own limits.
+        .route(
+            "/v1/import",
+            post(ingest::import)
+                .layer(RequestBodyLimitLayer::new(
+                    config.server.import_max_body_bytes,
+                ))
+                .route_layer(middleware::from_fn_with_state(
+                    state.clone(),
+                    rate_limit::limit_ingest,
+                ))
+                .route_layer(privileged()),
+        )
+        .route(
+            "/v1/import/instapaper",
+            post(ingest::import_instapaper)
+                .layer(RequestBodyLimitLayer::new(
+                    config.server.import_max_body_bytes,
+                ))
+                .route_layer(middleware::from_fn_with_state(
+                    state.clone(),
+                    rate_limit::limit_ingest,
+                ))
+                .route_layer(privileged()),
+        )
+        .route(
+            "/v1/import/opml",
+            post(ingest::import_opml)
+                .layer(RequestBodyLimitLayer::new(
+                    config.server.import_max_body_bytes,
+                ))
+                .route_layer(middleware::from_fn_with_state(
+                    state.clone(),
+                    rate_limit::limit_ingest,
+                ))
+                .route_layer(privileged()),
+        )
+        .route(
+            "/v1/import/browser",
+            post(ingest::sync_browser)
+                .layer(RequestBodyLimitLayer::new(
+                    config.server.import_max_body_bytes,
+                ))
+                .route_layer(middleware::from_fn_with_state(
+                    state.clone(),
+                    rate_limit::limit_ingest,
+                ))
+                .route_layer(privileged()),
+        )
+        // Restore uploads stream to disk, so they sit outside the body size limit.
+        .route(
+            "/v1/admin/restore",
+            post(admin::restore).route_layer(privileged()),
+        )
+        .route("/v1/auth/session", get(session::session))
+        .route("/v1/auth/logout", post(session::logout))
+        .layer(middleware::from_fn(access::csrf))
+        // Login starts the session CSRF tokens derive from, so it sits outside the check.
+        .route("/v1/auth/login", post(session::login))
+        .route("/v1/auth/oidc", get(session::oidc_status))
+        .route("/v1/auth/oidc/login", get(session::oidc_login))
+        .route("/v1/auth/oidc/callback", get(session::oidc_callback));
+    // Anything no API route claims falls through to the UI.
+    if config.server.serve_frontend {
+        frontend::check_bundle();
+        router = router.fallback(get(frontend::serve));
+    }
+    router
+        .route_layer(middleware::from_fn_with_state(
+            timeout::RequestTimeouts::new(&config.server),
+            timeout::enforce,
+        ))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            metrics::track_requests,
+        ))
+        .layer(cors_layer(&config.cors, state.services.config.clone()))
+        .layer(middleware::from_fn(request_id::scope_request_id))
+        .layer(TraceLayer::new_for_http().make_span_with(request_id::make_span))
+        .layer(PropagateRequestIdLayer::new(
+            request_id::X_REQUEST_ID.clone(),
+        ))
+        .layer(SetRequestIdLayer::new(
+            request_id::X_REQUEST_ID.clone(),
+            MakeRequestUuid,
+        ))
         .with_state(state)
 }
+
+/// Entries were checked by `Config::validate`, so unparseable ones cannot reach here.
+/// Origins are read from the live config on each request so reloads apply to them.
+/// Only origins listed by name may send the session cookie, never `*`.
+fn cors_layer(config: &CorsConfig, live: ConfigService) -> CorsLayer {
+    let listed = live.clone();
+    let origins = AllowOrigin::predicate(move |origin: &HeaderValue, _| {
+        let allowed = &live.current().cors.allowed_origins;
+        CorsConfig::is_any(allowed)
+            || allowed
+                .iter()
+                .any(|allowed| allowed.as_bytes() == origin.as_bytes())
+    });
+    let credentials = AllowCredentials::predicate(move |origin: &HeaderValue, _| {
+        listed
+            .current()
+            .cors
+            .allowed_origins
+            .iter()
+            .any(|allowed| allowed.as_bytes() == origin.as_bytes())
+    });
+    let methods = if CorsConfig::is_any(&config.allowed_methods) {
+        AllowMethods::any()
+    } else {
+        AllowMethods::list(
+            config
+                .allowed_methods
+                .iter()
+                .filter_map(|method| Method::from_bytes(method.as_bytes()).ok()),
+        )
+    };
+    let headers = if CorsConfig::is_any(&config.allowed_headers) {
+        AllowHeaders::any()
+    } else {
+        AllowHeaders::list(
+            config
+                .allowed_headers
+                .iter()
+                .filter_map(|header| HeaderName::from_bytes(header.as_bytes()).ok()),
+        )
+    };
+
+    CorsLayer::new()
+        .allow_origin(origins)
+        .allow_methods(methods)
+        .allow_headers(headers)
+        .allow_credentials(credentials)
+        .expose_headers([
+            request_id::X_REQUEST_ID.clone(),
+            jobs::X_ODIN_JOB_ID.clone(),
+        ])
+}
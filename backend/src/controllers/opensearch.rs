@@ -0,0 +1,58 @@
+use axum::extract::Query;
+use axum::http::HeaderMap;
+use axum::http::header::{CONTENT_TYPE, HOST};
+use axum::response::{IntoResponse, Redirect, Response};
+use serde::Deserialize;
+
+use odin_core::errors::AppError;
+
+#[derive(Deserialize)]
+pub(super) struct SearchRedirectParams {
+    #[serde(default)]
+    q: String,
+}
+
+/// Lets browsers add odin as a search engine. Absolute URLs are required, so the
+/// origin comes from the request, honouring `X-Forwarded-Proto` behind a TLS proxy.
+pub(super) async fn description(headers: HeaderMap) -> Result<Response, AppError> {
+    let host = headers
+        .get(HOST)
+        .and_then(|value| value.to_str().ok())
+        .filter(|host| !host.is_empty() && !host.contains(['"', '<', '>', '&', '/']))
+        .ok_or_else(|| AppError::bad_request("missing Host header"))?;
+    let scheme = match headers
+        .get("x-forwarded-proto")
+        .and_then(|value| value.to_str().ok())
+    {
+        Some("https") => "https",
+        _ => "http",
+    };
+    let origin = format!("{}://{}", scheme, host);
+    let body = format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<OpenSearchDescription xmlns="http://a9.com/-/spec/opensearch/1.1/" xmlns:moz="http://www.mozilla.org/2006/browser/search/">
+  <ShortName>Odin</ShortName>
+  <Description>Search your saved pages</Description>
+  <InputEncoding>UTF-8</InputEncoding>
+  <Url type="text/html" method="get" template="{origin}/search?q={{searchTerms}}"/>
+  <Url type="application/opensearchdescription+xml" rel="self" template="{origin}/opensearch.xml"/>
+  <moz:SearchForm>{origin}/</moz:SearchForm>
+</OpenSearchDescription>
+"#
+    );
+    Ok((
+        [(CONTENT_TYPE, "application/opensearchdescription+xml")],
+        body,
+    )
+        .into_response())
+}
+
+/// The address-bar entry point: hand the query to the web UI, which reads `?q=`.
+pub(super) async fn search_redirect(Query(params): Query<SearchRedirectParams>) -> Redirect {
+    let query = params.q.trim();
+    if query.is_empty() {
+        return Redirect::to("/");
+    }
+    let encoded: String = url::form_urlencoded::byte_serialize(query.as_bytes()).collect();
+    Redirect::to(&format!("/?q={}", encoded))
+}
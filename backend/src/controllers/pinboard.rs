@@ -0,0 +1,130 @@
+use std::fmt::Write as _;
+
+use axum::Json;
+use axum::extract::{Query, State};
+use axum::http::HeaderMap;
+use axum::http::header::{AUTHORIZATION, CONTENT_TYPE};
+use axum::response::{IntoResponse, Response};
+use serde_json::json;
+
+use odin_core::errors::AppError;
+use odin_core::types::{
+    Caller, PinboardAddParams, PinboardAllParams, PinboardAuth, PinboardDeleteParams, PinboardPost,
+    Scope,
+};
+
+use super::AppState;
+
+pub(super) async fn add_post(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(auth): Query<PinboardAuth>,
+    Query(params): Query<PinboardAddParams>,
+) -> Result<Response, AppError> {
+    let caller = authorize(&state, &headers, &auth, Scope::Ingest).await?;
+    let code = state.services.pinboard.add(caller, params).await?;
+    Ok(result(&auth, code))
+}
+
+pub(super) async fn delete_post(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(auth): Query<PinboardAuth>,
+    Query(params): Query<PinboardDeleteParams>,
+) -> Result<Response, AppError> {
+    let caller = authorize(&state, &headers, &auth, Scope::Delete).await?;
+    let code = state.services.pinboard.delete(caller, params).await?;
+    Ok(result(&auth, code))
+}
+
+pub(super) async fn all_posts(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(auth): Query<PinboardAuth>,
+    Query(params): Query<PinboardAllParams>,
+) -> Result<Response, AppError> {
+    let caller = authorize(&state, &headers, &auth, Scope::Read).await?;
+    let posts = state.services.pinboard.all(caller, params).await?;
+    if is_json(&auth) {
+        return Ok(Json(posts).into_response());
+    }
+    let user = auth
+        .auth_token
+        .as_deref()
+        .and_then(|token| token.split_once(':'))
+        .map_or("", |(user, _)| user);
+    let mut body = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" ?>\n<posts user=\"{}\">\n",
+        escape_xml(user)
+    );
+    for post in &posts {
+        write_post(&mut body, post);
+    }
+    body.push_str("</posts>\n");
+    Ok(xml(body))
+}
+
+/// Pinboard's `auth_token` (`username:TOKEN`) or an `Authorization` header. Writes here
+/// are GETs, so the session cookie is never accepted.
+async fn authorize(
+    state: &AppState,
+    headers: &HeaderMap,
+    auth: &PinboardAuth,
+    scope: Scope,
+) -> Result<Caller, AppError> {
+    if let Some(token) = auth.auth_token.as_deref() {
+        let token = token.rsplit_once(':').map_or(token, |(_, token)| token);
+        return state
+            .services
+            .auth
+            .authorize_token(token.trim(), None, scope)
+            .await;
+    }
+    if !headers.contains_key(AUTHORIZATION) {
+        return Err(AppError::unauthorized("missing auth_token"));
+    }
+    state.services.auth.authorize(headers, scope).await
+}
+
+/// Pinboard answers XML unless asked for `format=json`.
+fn is_json(auth: &PinboardAuth) -> bool {
+    auth.format.as_deref() == Some("json")
+}
+
+fn result(auth: &PinboardAuth, code: &str) -> Response {
+    if is_json(auth) {
+        return Json(json!({ "result_code": code })).into_response();
+    }
+    xml(format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" ?>\n<result code=\"{}\" />\n",
+        escape_xml(code)
+    ))
+}
+
+fn write_post(body: &mut String, post: &PinboardPost) {
+    let _ = writeln!(
+        body,
+        "  <post href=\"{}\" time=\"{}\" description=\"{}\" extended=\"{}\" tag=\"{}\" hash=\"{}\" meta=\"{}\" shared=\"{}\" toread=\"{}\" />",
+        escape_xml(&post.href),
+        escape_xml(&post.time),
+        escape_xml(&post.description),
+        escape_xml(&post.extended),
+        escape_xml(&post.tags),
+        post.hash,
+        post.meta,
+        post.shared,
+        post.toread
+    );
+}
+
+fn xml(body: String) -> Response {
+    ([(CONTENT_TYPE, "text/xml; charset=utf-8")], body).into_response()
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\n', "&#10;")
+}
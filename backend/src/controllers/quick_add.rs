@@ -0,0 +1,117 @@
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::{Html, IntoResponse, Response};
+
+use odin_core::errors::AppError;
+use odin_core::types::{IngestUrlsRequest, QuickAddParams, Scope};
+
+use super::AppState;
+
+/// Builds the bookmarklet from a pasted token, so the token never reaches the server
+/// until the bookmarklet is used.
+const BOOKMARKLET_PAGE: &str = r##"<!doctype html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>Odin bookmarklet</title>
+<style>
+body { font: 16px/1.5 system-ui, sans-serif; max-width: 36rem; margin: 3rem auto; padding: 0 1rem; color: #1c1917; }
+input { width: 100%; padding: .5rem; font: inherit; box-sizing: border-box; }
+a.bookmarklet { display: inline-block; margin-top: 1rem; padding: .5rem 1rem; border-radius: 999px; background: #292524; color: #fff; text-decoration: none; }
+.hidden { display: none; }
+</style>
+</head>
+<body>
+<h1>Save to Odin</h1>
+<p>Paste an API token with the <code>ingest</code> scope. It is stored in the bookmark, so
+use a dedicated key, e.g. <code>odin key add bookmarklet --scope ingest</code>.</p>
+<input id="token" type="password" placeholder="API token" autocomplete="off">
+<p><a id="link" class="bookmarklet hidden" href="#">Save to Odin</a></p>
+<p id="hint" class="hidden">Drag the button to your bookmarks bar, then click it on any page to save it.</p>
+<script>
+const token = document.getElementById("token");
+const link = document.getElementById("link");
+const hint = document.getElementById("hint");
+token.addEventListener("input", () => {
+  const value = token.value.trim();
+  const target = location.origin + "/v1/quick-add?token=" + encodeURIComponent(value) + "&url=";
+  link.href = "javascript:(()=>{window.open(" + JSON.stringify(target) +
+    "+encodeURIComponent(location.href),'odin-quick-add','width=420,height=200')})()";
+  link.classList.toggle("hidden", !value);
+  hint.classList.toggle("hidden", !value);
+});
+</script>
+</body>
+</html>
+"##;
+
+pub(super) async fn bookmarklet() -> Html<&'static str> {
+    Html(BOOKMARKLET_PAGE)
+}
+
+/// Save one URL for the bookmarklet and answer with a small page that closes itself;
+/// failures are shown on the page too, since the user is looking at a popup.
+pub(super) async fn quick_add(
+    State(state): State<AppState>,
+    Query(params): Query<QuickAddParams>,
+) -> Response {
+    match save(&state, params).await {
+        Ok(message) => page(StatusCode::OK, "Saved", message, true),
+        Err(err) => {
+            err.log_source();
+            page(err.status(), "Not saved", err.message(), false)
+        }
+    }
+}
+
+async fn save(state: &AppState, params: QuickAddParams) -> Result<&'static str, AppError> {
+    let caller = state
+        .services
+        .auth
+        .authorize_token(
+            params.token.trim(),
+            params.namespace.as_deref(),
+            Scope::Ingest,
+        )
+        .await?;
+    let response = state
+        .services
+        .ingest
+        .ingest_urls(
+            caller,
+            IngestUrlsRequest {
+                urls: vec![params.url],
+                tags: Vec::new(),
+            },
+        )
+        .await?;
+    Ok(if response.accepted > 0 {
+        "Saved to Odin."
+    } else {
+        "Already saved in Odin."
+    })
+}
+
+fn page(status: StatusCode, title: &str, message: &str, close: bool) -> Response {
+    let script = if close {
+        "<script>setTimeout(() => window.close(), 1500)</script>"
+    } else {
+        ""
+    };
+    let body = format!(
+        "<!doctype html><html lang=\"en\"><head><meta charset=\"utf-8\"><title>{}</title></head>\
+         <body style=\"font: 16px/1.5 system-ui, sans-serif; margin: 2rem; color: #1c1917\">\
+         <p>{}</p>{}</body></html>",
+        title,
+        escape_html(message),
+        script
+    );
+    (status, Html(body)).into_response()
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,62 @@
+use std::net::SocketAddr;
+
+use axum::extract::{ConnectInfo, Request, State};
+use axum::http::StatusCode;
+use axum::http::header::{AUTHORIZATION, RETRY_AFTER};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+
+use odin_core::rate_limit::RateLimitBucket;
+
+use super::AppState;
+
+pub(super) async fn limit_search(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    enforce(state, RateLimitBucket::Search, request, next).await
+}
+
+pub(super) async fn limit_ingest(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    enforce(state, RateLimitBucket::Ingest, request, next).await
+}
+
+/// Key clients by bearer token when present, otherwise by peer address.
+async fn enforce(
+    state: AppState,
+    bucket: RateLimitBucket,
+    request: Request,
+    next: Next,
+) -> Response {
+    let token = request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim)
+        .filter(|value| !value.is_empty());
+    let key = match token {
+        Some(token) => format!("token:{}", token),
+        None => match request.extensions().get::<ConnectInfo<SocketAddr>>() {
+            Some(ConnectInfo(addr)) => format!("ip:{}", addr.ip()),
+            None => "ip:unknown".to_string(),
+        },
+    };
+
+    if let Err(wait) = state.services.rate_limits.check(bucket, &key) {
+        let retry_after = wait.as_secs().max(1);
+        return (
+            StatusCode::TOO_MANY_REQUESTS,
+            [(RETRY_AFTER, retry_after.to_string())],
+            "rate limit exceeded",
+        )
+            .into_response();
+    }
+
+    next.run(request).await
+}
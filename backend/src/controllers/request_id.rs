@@ -0,0 +1,52 @@
+use axum::extract::Request;
+use axum::http::HeaderName;
+use axum::middleware::Next;
+use axum::response::Response;
+use tracing::Span;
+
+use odin_core::errors::REQUEST_ID;
+
+pub(super) static X_REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Read the id assigned by `SetRequestIdLayer`, or `-` if the header is missing.
+fn request_id(request: &Request) -> String {
+    request
+        .headers()
+        .get(&X_REQUEST_ID)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("-")
+        .to_string()
+}
+
+/// Tracing span for one request, tagged with its id so log lines can be grepped by it.
+pub(super) fn make_span(request: &Request) -> Span {
+    tracing::info_span!(
+        "request",
+        method = %request.method(),
+        uri = %redacted_uri(request),
+        request_id = %request_id(request),
+    )
+}
+
+/// The request URI with any `token` or `auth_token` query value masked, since event
+/// streams, bookmarklet saves, and Pinboard clients carry their API token there.
+fn redacted_uri(request: &Request) -> String {
+    let uri = request.uri();
+    let Some(query) = uri.query() else {
+        return uri.to_string();
+    };
+    let query: Vec<String> = query
+        .split('&')
+        .map(|pair| match pair.split_once('=') {
+            Some((name @ ("token" | "auth_token"), _)) => format!("{}=redacted", name),
+            _ => pair.to_string(),
+        })
+        .collect();
+    format!("{}?{}", uri.path(), query.join("&"))
+}
+
+/// Make the request id available to `AppError` while the handler runs.
+pub(super) async fn scope_request_id(request: Request, next: Next) -> Response {
+    let id = request_id(&request);
+    REQUEST_ID.scope(id, next.run(request)).await
+}
@@ -1,12 +1,24 @@
-use axum::Json;
 use axum::extract::{Query, State};
-use crate::errors::AppError;
-use crate::types::{AppState, SearchParams, SearchResponse};
+use axum::http::HeaderMap;
+use axum::response::Response;
+use odin_core::errors::AppError;
+use odin_core::types::{Scope, SearchParams};
+
+use super::etag;
+
+use super::AppState;
 
 pub(super) async fn search(
     State(state): State<AppState>,
+    headers: HeaderMap,
     Query(params): Query<SearchParams>,
-) -> Result<Json<SearchResponse>, AppError> {
-    let response = state.services.search.search(params).await?;
-    Ok(Json(response))
+) -> Result<Response, AppError> {
+    let caller = state.services.auth.authorize(&headers, Scope::Read).await?;
+    // Results only change when the index does, so the ETag is known before searching.
+    let etag = etag::weak_etag(state.services.search.cache_key(&caller, &params).as_bytes());
+    if etag::is_fresh(&headers, &etag) {
+        return Ok(etag::not_modified(etag));
+    }
+    let response = state.services.search.search(caller, params).await?;
+    Ok(etag::json_with_etag(etag, response))
 }
@@ -0,0 +1,71 @@
+use axum::Json;
+use axum::extract::{Query, State};
+use axum::http::header::{LOCATION, SET_COOKIE};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{AppendHeaders, IntoResponse};
+
+use odin_core::errors::AppError;
+use odin_core::types::{LoginRequest, OidcCallbackParams, OidcStatusResponse, SessionResponse};
+
+use super::AppState;
+
+pub(super) async fn login(
+    State(state): State<AppState>,
+    Json(request): Json<LoginRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let (session, response) = state.services.auth.login(request).await?;
+    let cookie = state.services.auth.session_cookie(&session);
+    Ok(([(SET_COOKIE, cookie)], Json(response)))
+}
+
+pub(super) async fn session(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<SessionResponse>, AppError> {
+    let session = state.services.auth.session(&headers).await?;
+    Ok(Json(session))
+}
+
+pub(super) async fn logout(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, AppError> {
+    state.services.auth.logout(&headers).await?;
+    let cookie = state.services.auth.clear_session_cookie();
+    Ok((StatusCode::NO_CONTENT, [(SET_COOKIE, cookie)]))
+}
+
+pub(super) async fn oidc_status(State(state): State<AppState>) -> Json<OidcStatusResponse> {
+    Json(OidcStatusResponse {
+        enabled: state.services.oidc.enabled(),
+    })
+}
+
+pub(super) async fn oidc_login(
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse, AppError> {
+    let (location, cookie) = state.services.oidc.begin().await?;
+    Ok((
+        StatusCode::SEE_OTHER,
+        AppendHeaders([(LOCATION, location), (SET_COOKIE, cookie)]),
+    ))
+}
+
+pub(super) async fn oidc_callback(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<OidcCallbackParams>,
+) -> Result<impl IntoResponse, AppError> {
+    let (session, _) = state.services.oidc.finish(&headers, params).await?;
+    Ok((
+        StatusCode::SEE_OTHER,
+        AppendHeaders([
+            (
+                LOCATION,
+                state.services.oidc.post_login_redirect().to_string(),
+            ),
+            (SET_COOKIE, state.services.auth.session_cookie(&session)),
+            (SET_COOKIE, state.services.oidc.clear_login_cookie()),
+        ]),
+    ))
+}
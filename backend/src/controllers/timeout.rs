@@ -0,0 +1,73 @@
+use std::time::Duration;
+
+use axum::extract::{MatchedPath, Request, State};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use tracing::warn;
+
+use odin_core::config::ServerConfig;
+use odin_core::errors::AppError;
+
+/// Routes that legitimately run long: uploads, archives, and admin passes over every
+/// bookmark. Streamed bodies (events, NDJSON exports) are not timed once headers are sent.
+const LONG_ROUTES: &[&str] = &[
+    "/v1/import",
+    "/v1/import/instapaper",
+    "/v1/import/opml",
+    "/v1/import/browser",
+    "/v1/export/markdown",
+    "/v1/admin/backup",
+    "/v1/admin/restore",
+    "/v1/admin/reindex",
+    "/v1/admin/reconcile",
+    "/v1/admin/consistency",
+    "/v1/admin/maintenance/db",
+];
+
+/// Deadlines for producing a response; `None` leaves requests untimed.
+#[derive(Clone, Copy)]
+pub(super) struct RequestTimeouts {
+    default: Option<Duration>,
+    long: Option<Duration>,
+}
+
+impl RequestTimeouts {
+    pub(super) fn new(config: &ServerConfig) -> Self {
+        let budget = |secs: u64| (secs > 0).then(|| Duration::from_secs(secs));
+        Self {
+            default: budget(config.request_timeout_secs),
+            long: budget(config.long_request_timeout_secs),
+        }
+    }
+}
+
+/// Answer 503 once a handler runs past its route's budget, dropping it so the
+/// connection is freed.
+pub(super) async fn enforce(
+    State(timeouts): State<RequestTimeouts>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let path = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string());
+    let budget = match path.as_deref() {
+        Some(path) if LONG_ROUTES.contains(&path) => timeouts.long,
+        _ => timeouts.default,
+    };
+    let Some(budget) = budget else {
+        return next.run(request).await;
+    };
+    match tokio::time::timeout(budget, next.run(request)).await {
+        Ok(response) => response,
+        Err(_) => {
+            warn!(
+                "request timed out: route={} budget_secs={}",
+                path.as_deref().unwrap_or("unmatched"),
+                budget.as_secs()
+            );
+            AppError::unavailable("request timed out; retry later").into_response()
+        }
+    }
+}
@@ -0,0 +1,35 @@
+use axum::Json;
+use axum::extract::State;
+use axum::http::HeaderMap;
+
+use odin_core::errors::AppError;
+use odin_core::types::{CreateUserRequest, CreateUserResponse, Scope, UsersResponse};
+
+use super::AppState;
+
+pub(super) async fn list_users(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<UsersResponse>, AppError> {
+    state
+        .services
+        .auth
+        .authorize(&headers, Scope::Admin)
+        .await?;
+    let response = state.services.users.list().await?;
+    Ok(Json(response))
+}
+
+pub(super) async fn create_user(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<CreateUserRequest>,
+) -> Result<Json<CreateUserResponse>, AppError> {
+    state
+        .services
+        .auth
+        .authorize(&headers, Scope::Admin)
+        .await?;
+    let response = state.services.users.create(payload).await?;
+    Ok(Json(response))
+}
@@ -0,0 +1,30 @@
+use axum::Json;
+use axum::extract::{Path, State};
+use axum::http::HeaderMap;
+
+use odin_core::errors::AppError;
+use odin_core::types::{Scope, WaybackSnapshot};
+
+use super::AppState;
+
+pub(super) async fn snapshot(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<i64>,
+) -> Result<Json<WaybackSnapshot>, AppError> {
+    let caller = state.services.auth.authorize(&headers, Scope::Read).await?;
+    Ok(Json(state.services.wayback.get(caller, id).await?))
+}
+
+pub(super) async fn submit(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<i64>,
+) -> Result<Json<WaybackSnapshot>, AppError> {
+    let caller = state
+        .services
+        .auth
+        .authorize(&headers, Scope::Ingest)
+        .await?;
+    Ok(Json(state.services.wayback.submit(caller, id).await?))
+}
@@ -0,0 +1,49 @@
+use axum::Json;
+use axum::extract::{Path, State};
+use axum::http::{HeaderMap, StatusCode};
+
+use odin_core::errors::AppError;
+use odin_core::types::{Scope, WebhookCreateRequest, WebhookItem, WebhooksResponse};
+
+use super::AppState;
+
+/// Needs the ingest scope, since webhook URLs often embed their receiver's token.
+pub(super) async fn list_webhooks(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<WebhooksResponse>, AppError> {
+    let caller = state
+        .services
+        .auth
+        .authorize(&headers, Scope::Ingest)
+        .await?;
+    Ok(Json(state.services.webhooks.list(caller).await?))
+}
+
+pub(super) async fn add_webhook(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<WebhookCreateRequest>,
+) -> Result<(StatusCode, Json<WebhookItem>), AppError> {
+    let caller = state
+        .services
+        .auth
+        .authorize(&headers, Scope::Ingest)
+        .await?;
+    let webhook = state.services.webhooks.add(caller, payload).await?;
+    Ok((StatusCode::CREATED, Json(webhook)))
+}
+
+pub(super) async fn remove_webhook(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<i64>,
+) -> Result<StatusCode, AppError> {
+    let caller = state
+        .services
+        .auth
+        .authorize(&headers, Scope::Ingest)
+        .await?;
+    state.services.webhooks.remove(caller, id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,253 @@
+use async_graphql::{Context, EmptyMutation, EmptySubscription, ErrorExtensions, Object, Schema};
+use odin_core::errors::AppError;
+
+use odin_core::services::Services;
+use odin_core::types::{
+    BookmarkListItem, BookmarksParams, Caller, NamespaceItem, SearchParams, SearchResponse,
+    SearchResultItem, SearchSort,
+};
+
+pub type GraphqlSchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+/// Deeper nesting than search → bookmark has no use and only multiplies lookups.
+const MAX_DEPTH: usize = 6;
+const MAX_COMPLEXITY: usize = 500;
+
+/// Resolvers read `Services` from the schema and the authorized `Caller` from each request.
+pub fn build_schema(services: Services) -> GraphqlSchema {
+    Schema::build(QueryRoot, EmptyMutation, EmptySubscription)
+        .data(services)
+        .limit_depth(MAX_DEPTH)
+        .limit_complexity(MAX_COMPLEXITY)
+        .finish()
+}
+
+/// GraphQL reports failures in the response body; the HTTP status travels as an extension.
+fn gql_error(err: AppError) -> async_graphql::Error {
+    err.log_source();
+    let status = err.status().as_u16();
+    async_graphql::Error::new(err.message())
+        .extend_with(|_, extensions| extensions.set("status", status))
+}
+
+fn scope<'a>(ctx: &Context<'a>) -> (&'a Services, &'a Caller) {
+    (
+        ctx.data_unchecked::<Services>(),
+        ctx.data_unchecked::<Caller>(),
+    )
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    /// Bookmarks in the caller's namespace, most recently updated first, 50 per page
+    /// by default. `status` is `queued`, `indexed`, `partial`, or `failed`; `q` matches
+    /// URLs and titles.
+    async fn bookmarks(
+        &self,
+        ctx: &Context<'_>,
+        tag: Option<String>,
+        status: Option<String>,
+        q: Option<String>,
+        page: Option<u32>,
+        per_page: Option<u32>,
+    ) -> async_graphql::Result<Vec<Bookmark>> {
+        let (services, caller) = scope(ctx);
+        let params = BookmarksParams {
+            tag,
+            status,
+            q,
+            page,
+            per_page,
+            ..BookmarksParams::default()
+        };
+        let list = services
+            .bookmarks
+            .list(caller.clone(), params)
+            .await
+            .map_err(gql_error)?;
+        Ok(list.results.into_iter().map(Bookmark).collect())
+    }
+
+    async fn bookmark(
+        &self,
+        ctx: &Context<'_>,
+        id: i64,
+    ) -> async_graphql::Result<Option<Bookmark>> {
+        let (services, caller) = scope(ctx);
+        let bookmark = services
+            .bookmarks
+            .get(caller, id)
+            .await
+            .map_err(gql_error)?;
+        Ok(bookmark.map(Bookmark))
+    }
+
+    /// Best match first, or most recently fetched first with `recent`. `site` matches
+    /// subdomains too; `after` and `before` bound the fetch time, as RFC 3339 or
+    /// `YYYY-MM-DD`.
+    #[allow(clippy::too_many_arguments)]
+    async fn search(
+        &self,
+        ctx: &Context<'_>,
+        query: String,
+        page: Option<u32>,
+        per_page: Option<u32>,
+        tag: Option<String>,
+        site: Option<String>,
+        after: Option<String>,
+        before: Option<String>,
+        recent: Option<bool>,
+    ) -> async_graphql::Result<SearchResults> {
+        let (services, caller) = scope(ctx);
+        let params = SearchParams {
+            query,
+            page,
+            per_page,
+            tag,
+            site,
+            after,
+            before,
+            sort: recent.unwrap_or(false).then_some(SearchSort::Recent),
+        };
+        services
+            .search
+            .search(caller.clone(), params)
+            .await
+            .map(SearchResults::from)
+            .map_err(gql_error)
+    }
+
+    async fn namespaces(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<Namespace>> {
+        let (services, caller) = scope(ctx);
+        let list = services
+            .bookmarks
+            .namespaces(caller.clone())
+            .await
+            .map_err(gql_error)?;
+        Ok(list.results.into_iter().map(Namespace).collect())
+    }
+}
+
+pub struct Bookmark(BookmarkListItem);
+
+#[Object]
+impl Bookmark {
+    async fn id(&self) -> i64 {
+        self.0.id
+    }
+
+    /// Normalized; what is fetched and what duplicates are detected by.
+    async fn url(&self) -> &str {
+        &self.0.url
+    }
+
+    /// The URL as it was submitted.
+    async fn original_url(&self) -> &str {
+        &self.0.original_url
+    }
+
+    async fn title(&self) -> Option<&str> {
+        self.0.title.as_deref()
+    }
+
+    /// `queued`, `indexed`, `partial`, or `failed`.
+    async fn status(&self) -> &str {
+        &self.0.status
+    }
+
+    /// Fetches made in the latest ingest; a `queued` bookmark with some is waiting to retry.
+    async fn attempts(&self) -> i64 {
+        self.0.attempts
+    }
+
+    /// `html`, `pdf`, or `text` once fetched and indexed.
+    async fn document_type(&self) -> Option<&str> {
+        self.0.document_type.as_deref()
+    }
+
+    async fn updated_at(&self) -> &str {
+        &self.0.updated_at
+    }
+
+    async fn tags(&self) -> &[String] {
+        &self.0.tags
+    }
+}
+
+pub struct SearchResults {
+    total_hits: u64,
+    results: Vec<SearchResult>,
+}
+
+impl From<SearchResponse> for SearchResults {
+    fn from(response: SearchResponse) -> Self {
+        Self {
+            total_hits: response.total_hits,
+            results: response.results.into_iter().map(SearchResult).collect(),
+        }
+    }
+}
+
+#[Object]
+impl SearchResults {
+    async fn total_hits(&self) -> u64 {
+        self.total_hits
+    }
+
+    async fn results(&self) -> &[SearchResult] {
+        &self.results
+    }
+}
+
+pub struct SearchResult(SearchResultItem);
+
+#[Object]
+impl SearchResult {
+    async fn url(&self) -> &str {
+        &self.0.url
+    }
+
+    async fn title(&self) -> Option<&str> {
+        self.0.title.as_deref()
+    }
+
+    async fn excerpt(&self) -> Option<&str> {
+        self.0.excerpt.as_deref()
+    }
+
+    /// The passage of the page that best matches the query, HTML-escaped with each
+    /// match wrapped in `<mark>`.
+    async fn snippet(&self) -> Option<&str> {
+        self.0.snippet.as_ref().map(|snippet| snippet.html.as_str())
+    }
+
+    async fn score(&self) -> f32 {
+        self.0.score
+    }
+
+    /// The stored bookmark behind this hit; null if it was deleted since indexing.
+    async fn bookmark(&self, ctx: &Context<'_>) -> async_graphql::Result<Option<Bookmark>> {
+        let (services, caller) = scope(ctx);
+        let bookmark = services
+            .bookmarks
+            .get(caller, self.0.bookmark_id)
+            .await
+            .map_err(gql_error)?;
+        Ok(bookmark.map(Bookmark))
+    }
+}
+
+pub struct Namespace(NamespaceItem);
+
+#[Object]
+impl Namespace {
+    async fn name(&self) -> &str {
+        &self.0.name
+    }
+
+    async fn bookmarks(&self) -> i64 {
+        self.0.bookmarks
+    }
+}
@@ -0,0 +1,236 @@
+use std::collections::HashSet;
+use std::pin::Pin;
+
+use axum::http::StatusCode;
+use futures_util::{Stream, StreamExt};
+use odin_core::errors::AppError;
+use tonic::{Code, Request, Response, Status};
+
+use odin_core::services::{IngestService, Services};
+use odin_core::types::{
+    BookmarkEventKind, BookmarkListItem, BookmarksParams, Caller, IngestUrlsRequest, Peer, Scope,
+    SearchParams, SearchSort,
+};
+
+pub mod proto {
+    tonic::include_proto!("odin.v1");
+}
+
+use proto::ingest_progress::Progress;
+use proto::odin_server::{Odin, OdinServer};
+use proto::{
+    Bookmark, DeleteBookmarkRequest, DeleteBookmarkResponse, IngestProgress, IngestQueued,
+    IngestRequest, IngestResult, ListBookmarksRequest, ListBookmarksResponse, SearchRequest,
+    SearchResponse, SearchResult,
+};
+
+/// The gRPC mirror of the search, ingest, and bookmark routes, backed by the same services.
+#[derive(Clone)]
+pub struct OdinGrpc {
+    services: Services,
+}
+
+impl OdinGrpc {
+    pub fn new(services: Services) -> Self {
+        Self { services }
+    }
+
+    pub fn into_server(self) -> OdinServer<Self> {
+        OdinServer::new(self)
+    }
+
+    /// Metadata carries the same `authorization` and `x-odin-namespace` headers as HTTP,
+    /// and ingest-scoped calls are held to `auth.privileged_cidrs` like their routes.
+    async fn authorize<T>(&self, request: &Request<T>, scope: Scope) -> Result<Caller, Status> {
+        if scope != Scope::Read {
+            self.services
+                .auth
+                .check_privileged_peer(
+                    request
+                        .remote_addr()
+                        .map_or(Peer::Unknown, |addr| Peer::Tcp(addr.ip())),
+                )
+                .map_err(grpc_status)?;
+        }
+        let headers = request.metadata().clone().into_headers();
+        self.services
+            .auth
+            .authorize(&headers, scope)
+            .await
+            .map_err(grpc_status)
+    }
+}
+
+type ProgressStream = Pin<Box<dyn Stream<Item = Result<IngestProgress, Status>> + Send>>;
+
+#[tonic::async_trait]
+impl Odin for OdinGrpc {
+    async fn search(
+        &self,
+        request: Request<SearchRequest>,
+    ) -> Result<Response<SearchResponse>, Status> {
+        let caller = self.authorize(&request, Scope::Read).await?;
+        let request = request.into_inner();
+        let params = SearchParams {
+            query: request.query,
+            page: request.page,
+            per_page: request.per_page,
+            site: request.site,
+            after: request.after,
+            before: request.before,
+            sort: request
+                .recent
+                .unwrap_or(false)
+                .then_some(SearchSort::Recent),
+            ..SearchParams::default()
+        };
+        let response = self
+            .services
+            .search
+            .search(caller, params)
+            .await
+            .map_err(grpc_status)?;
+        Ok(Response::new(SearchResponse {
+            total_hits: response.total_hits,
+            results: response
+                .results
+                .into_iter()
+                .map(|item| SearchResult {
+                    bookmark_id: item.bookmark_id,
+                    url: item.url,
+                    title: item.title,
+                    excerpt: item.excerpt,
+                    score: item.score,
+                })
+                .collect(),
+        }))
+    }
+
+    async fn list_bookmarks(
+        &self,
+        request: Request<ListBookmarksRequest>,
+    ) -> Result<Response<ListBookmarksResponse>, Status> {
+        let caller = self.authorize(&request, Scope::Read).await?;
+        let request = request.into_inner();
+        let params = BookmarksParams {
+            status: request.status,
+            q: request.q,
+            page: request.page,
+            per_page: request.per_page,
+            ..BookmarksParams::default()
+        };
+        let response = self
+            .services
+            .bookmarks
+            .list(caller, params)
+            .await
+            .map_err(grpc_status)?;
+        Ok(Response::new(ListBookmarksResponse {
+            bookmarks: response.results.into_iter().map(Bookmark::from).collect(),
+            total: response.total,
+        }))
+    }
+
+    async fn delete_bookmark(
+        &self,
+        request: Request<DeleteBookmarkRequest>,
+    ) -> Result<Response<DeleteBookmarkResponse>, Status> {
+        let caller = self.authorize(&request, Scope::Delete).await?;
+        let id = request.into_inner().id;
+        self.services
+            .bookmarks
+            .delete(caller, id)
+            .await
+            .map_err(grpc_status)?;
+        Ok(Response::new(DeleteBookmarkResponse {}))
+    }
+
+    type IngestStream = ProgressStream;
+
+    async fn ingest(
+        &self,
+        request: Request<IngestRequest>,
+    ) -> Result<Response<Self::IngestStream>, Status> {
+        let caller = self.authorize(&request, Scope::Ingest).await?;
+        let urls = request.into_inner().urls;
+        let mut pending: HashSet<String> = urls
+            .iter()
+            .filter_map(|url| IngestService::normalize_url(url))
+            .collect();
+
+        // Subscribe before queueing so no outcome can land unobserved.
+        let events = self.services.events.subscribe(caller.clone());
+        let queued = self
+            .services
+            .ingest
+            .ingest_urls(
+                caller,
+                IngestUrlsRequest {
+                    urls,
+                    tags: Vec::new(),
+                },
+            )
+            .await
+            .map_err(grpc_status)?;
+
+        let first = IngestProgress {
+            progress: Some(Progress::Queued(IngestQueued {
+                accepted: queued.accepted as u64,
+                deduped: queued.deduped as u64,
+                queue_depth: queued.queue_depth as u64,
+            })),
+        };
+        let results = events
+            .filter_map(move |item| {
+                let progress = match item {
+                    Ok(event) if pending.remove(&event.url) => Some(Ok(IngestProgress {
+                        progress: Some(Progress::Result(IngestResult {
+                            bookmark_id: event.id,
+                            url: event.url,
+                            indexed: event.event == BookmarkEventKind::Indexed,
+                            title: event.title,
+                            error: match event.event {
+                                BookmarkEventKind::Deleted => Some("deleted".to_string()),
+                                _ => event.error,
+                            },
+                        })),
+                    })),
+                    Ok(_) => None,
+                    Err(skipped) => Some(Err(Status::data_loss(format!(
+                        "{} events were dropped; check the bookmarks list for results",
+                        skipped
+                    )))),
+                };
+                std::future::ready(progress)
+            })
+            .take(queued.accepted);
+        let stream = futures_util::stream::once(std::future::ready(Ok(first))).chain(results);
+        Ok(Response::new(Box::pin(stream)))
+    }
+}
+
+fn grpc_status(err: AppError) -> Status {
+    err.log_source();
+    let code = match err.status() {
+        StatusCode::BAD_REQUEST | StatusCode::PAYLOAD_TOO_LARGE => Code::InvalidArgument,
+        StatusCode::UNAUTHORIZED => Code::Unauthenticated,
+        StatusCode::FORBIDDEN => Code::PermissionDenied,
+        StatusCode::NOT_FOUND => Code::NotFound,
+        StatusCode::CONFLICT => Code::FailedPrecondition,
+        StatusCode::SERVICE_UNAVAILABLE => Code::Unavailable,
+        _ => Code::Internal,
+    };
+    Status::new(code, err.message())
+}
+
+impl From<BookmarkListItem> for Bookmark {
+    fn from(item: BookmarkListItem) -> Self {
+        Self {
+            id: item.id,
+            url: item.url,
+            title: item.title,
+            status: item.status,
+            updated_at: item.updated_at,
+        }
+    }
+}
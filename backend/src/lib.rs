@@ -0,0 +1,190 @@
+//! The odin server: REST, GraphQL, and gRPC front ends over `odin-core`.
+//!
+//! The `backend` binary and `odin serve` both call [`run`]. Integration tests can
+//! build the same router over in-memory storage with [`AppState::in_memory`] and
+//! [`build_router`].
+
+use std::env;
+
+use anyhow::Context;
+use odin_core::Odin;
+use odin_core::config::Config;
+use odin_core::services::Services;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use crate::telemetry::LogFilter;
+
+mod controllers;
+mod graphql;
+mod grpc;
+mod server;
+mod systemd;
+mod telemetry;
+
+pub use crate::controllers::{AppState, build_router};
+pub use crate::telemetry::Telemetry;
+
+/// Read `.env` into the environment, then load the config file and `ODIN_*` overrides.
+pub fn load_config() -> anyhow::Result<Config> {
+    dotenvy::dotenv().ok();
+    Config::load().context("load config")
+}
+
+/// Serve until SIGINT or SIGTERM, then drain requests and background work and shut down.
+/// Admin keys listed in `ADMIN_TOKEN` are registered first.
+pub async fn run(config: Config, mut telemetry: Telemetry) -> anyhow::Result<()> {
+    telemetry.log_filter().set(&config.log.level)?;
+    telemetry
+        .enable(&config.telemetry)
+        .context("enable telemetry")?;
+
+    let odin = Odin::open(config.clone()).await?;
+    let services = odin.services().clone();
+    services
+        .api_keys
+        .bootstrap(load_admin_tokens().context("load ADMIN_TOKEN")?)
+        .await?;
+    let grpc = grpc::OdinGrpc::new(services.clone());
+    let state = AppState::new(services.clone());
+
+    let app = build_router(state, &config);
+
+    tokio::spawn({
+        let shutdown = odin.shutdown_token();
+        async move {
+            shutdown_signal().await;
+            systemd::notify_stopping();
+            shutdown.cancel();
+        }
+    });
+    systemd::spawn_watchdog(odin.shutdown_token());
+    spawn_config_reload(
+        services.clone(),
+        telemetry.log_filter(),
+        config.storage.in_memory,
+        odin.shutdown_token(),
+    );
+    server::serve(app, grpc, &config, odin.shutdown_token()).await?;
+
+    let result = odin.shutdown().await;
+    telemetry.shutdown().await;
+    result
+}
+
+/// Resolve once SIGINT or SIGTERM arrives.
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(err) = tokio::signal::ctrl_c().await {
+            warn!("failed to listen for ctrl-c: {}", err);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(err) => {
+                warn!("failed to listen for SIGTERM: {}", err);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+    info!("shutdown signal received; draining requests");
+}
+
+/// Reload the config file on SIGHUP without touching listeners, open connections, or
+/// the index. A config that fails to load or validate is logged and leaves the running
+/// one in place. `in_memory` carries over `odin serve --ephemeral`, which no file sets.
+#[cfg(unix)]
+fn spawn_config_reload(
+    services: Services,
+    log_filter: LogFilter,
+    in_memory: bool,
+    shutdown: CancellationToken,
+) {
+    use tokio::signal::unix::{SignalKind, signal};
+
+    let mut hangup = match signal(SignalKind::hangup()) {
+        Ok(hangup) => hangup,
+        Err(err) => {
+            warn!(
+                "failed to listen for SIGHUP; config reload disabled: {}",
+                err
+            );
+            return;
+        }
+    };
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = shutdown.cancelled() => break,
+                received = hangup.recv() => {
+                    if received.is_none() {
+                        break;
+                    }
+                }
+            }
+            info!("SIGHUP received; reloading config");
+            let mut config = match Config::load() {
+                Ok(config) => config,
+                Err(err) => {
+                    warn!(
+                        "config reload failed; keeping the running config: {:?}",
+                        err
+                    );
+                    continue;
+                }
+            };
+            config.storage.in_memory |= in_memory;
+            if let Err(err) = log_filter.set(&config.log.level) {
+                warn!(
+                    "config reload failed; keeping the running config: {:?}",
+                    err
+                );
+                continue;
+            }
+            services.config.reload(config);
+        }
+    });
+}
+
+#[cfg(not(unix))]
+fn spawn_config_reload(
+    _services: Services,
+    _log_filter: LogFilter,
+    _in_memory: bool,
+    _shutdown: CancellationToken,
+) {
+}
+
+/// Optional bootstrap admin keys, comma-separated so a new token can be rolled out
+/// alongside the old one; further keys are managed through `/v1/keys`.
+fn load_admin_tokens() -> anyhow::Result<Vec<String>> {
+    match env::var("ADMIN_TOKEN") {
+        Ok(value) => {
+            let tokens: Vec<String> = value
+                .split(',')
+                .map(|token| token.trim().to_string())
+                .collect();
+            if tokens.iter().any(String::is_empty) {
+                anyhow::bail!("ADMIN_TOKEN is set but has an empty entry");
+            }
+            Ok(tokens)
+        }
+        Err(env::VarError::NotPresent) => Ok(Vec::new()),
+        Err(env::VarError::NotUnicode(_)) => {
+            anyhow::bail!("ADMIN_TOKEN is not valid unicode");
+        }
+    }
+}
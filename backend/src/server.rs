@@ -0,0 +1,299 @@
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use anyhow::Context;
+use axum::Router;
+use axum::extract::connect_info::Connected;
+use axum::serve::IncomingStream;
+use axum_server::Handle;
+use axum_server::tls_rustls::RustlsConfig;
+use futures_util::FutureExt;
+use listenfd::ListenFd;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use crate::grpc::OdinGrpc;
+use crate::systemd;
+use odin_core::config::Config;
+
+/// A bound socket waiting to be served.
+enum Listener {
+    Tcp(std::net::TcpListener),
+    /// `cleanup` is the socket file to remove on exit; inherited sockets belong to systemd.
+    #[cfg(unix)]
+    Unix {
+        listener: std::os::unix::net::UnixListener,
+        cleanup: Option<PathBuf>,
+    },
+}
+
+/// Serve `app` until `shutdown` is cancelled, on the sockets passed by systemd
+/// socket activation when present, otherwise on every configured listener.
+/// `grpc` gets its own `grpc.listen_addr` port either way.
+pub async fn serve(
+    app: Router,
+    grpc: OdinGrpc,
+    config: &Config,
+    shutdown: CancellationToken,
+) -> anyhow::Result<()> {
+    let mut listeners = inherited_listeners()?;
+    if listeners.is_empty() {
+        listeners = bind_listeners(config)?;
+    } else {
+        info!(
+            "using {} socket(s) from systemd; configured listeners are ignored",
+            listeners.len()
+        );
+    }
+
+    let tls = match config.tls.paths() {
+        Some((cert_path, key_path)) => {
+            // Only the ring provider is compiled in; installing twice is harmless.
+            let _ = rustls::crypto::ring::default_provider().install_default();
+            let tls = RustlsConfig::from_pem_file(cert_path, key_path)
+                .await
+                .with_context(|| format!("load tls certificate {}", cert_path.display()))?;
+            tokio::spawn(watch_certificates(
+                tls.clone(),
+                cert_path.to_path_buf(),
+                key_path.to_path_buf(),
+                Duration::from_secs(config.tls.reload_interval_secs),
+                shutdown.clone(),
+            ));
+            Some(tls)
+        }
+        None => None,
+    };
+
+    let grpc_listener = match config.grpc.listen_addr {
+        Some(addr) => Some(
+            tokio::net::TcpListener::bind(addr)
+                .await
+                .with_context(|| format!("bind grpc listener {}", addr))?,
+        ),
+        None => None,
+    };
+
+    systemd::notify_ready();
+    let mut served: Vec<_> = listeners
+        .into_iter()
+        .map(|listener| match listener {
+            Listener::Tcp(listener) => {
+                serve_tcp(app.clone(), listener, tls.clone(), shutdown.clone()).boxed()
+            }
+            #[cfg(unix)]
+            Listener::Unix { listener, cleanup } => {
+                serve_unix(app.clone(), listener, cleanup, shutdown.clone()).boxed()
+            }
+        })
+        .collect();
+    if let Some(listener) = grpc_listener {
+        served.push(serve_grpc(grpc, listener, shutdown.clone()).boxed());
+    }
+    futures_util::future::try_join_all(served).await?;
+    Ok(())
+}
+
+/// Sockets passed in via `LISTEN_FDS`, in the order the socket unit lists them.
+fn inherited_listeners() -> anyhow::Result<Vec<Listener>> {
+    let mut fds = ListenFd::from_env();
+    let mut listeners = Vec::new();
+    for idx in 0..fds.len() {
+        if let Ok(Some(listener)) = fds.take_tcp_listener(idx) {
+            listeners.push(Listener::Tcp(listener));
+            continue;
+        }
+        #[cfg(unix)]
+        if let Ok(Some(listener)) = fds.take_unix_listener(idx) {
+            listeners.push(Listener::Unix {
+                listener,
+                cleanup: None,
+            });
+            continue;
+        }
+        anyhow::bail!("inherited fd {} is not a stream socket", idx);
+    }
+    Ok(listeners)
+}
+
+fn bind_listeners(config: &Config) -> anyhow::Result<Vec<Listener>> {
+    let mut listeners = Vec::new();
+    if config.server.tcp_enabled {
+        let addr = config.server.listen_addr;
+        let listener = std::net::TcpListener::bind(addr)
+            .with_context(|| format!("bind tcp listener {}", addr))?;
+        listeners.push(Listener::Tcp(listener));
+    }
+    if let Some(path) = &config.server.unix_socket_path {
+        listeners.push(bind_unix(path, config.server.unix_socket_mode)?);
+    }
+    Ok(listeners)
+}
+
+#[cfg(unix)]
+fn bind_unix(path: &Path, mode: u32) -> anyhow::Result<Listener> {
+    use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+
+    // A socket left behind by an unclean exit would make bind fail; anything else at
+    // the path is not ours to delete.
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => {
+            std::fs::remove_file(path)
+                .with_context(|| format!("remove stale socket {}", path.display()))?;
+        }
+        Ok(_) => anyhow::bail!(
+            "unix socket path {} exists and is not a socket",
+            path.display()
+        ),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+        Err(err) => {
+            return Err(err).with_context(|| format!("inspect unix socket {}", path.display()));
+        }
+    }
+    let listener = std::os::unix::net::UnixListener::bind(path)
+        .with_context(|| format!("bind unix socket {}", path.display()))?;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))
+        .with_context(|| format!("set permissions on {}", path.display()))?;
+    Ok(Listener::Unix {
+        listener,
+        cleanup: Some(path.to_path_buf()),
+    })
+}
+
+/// Connect info marking a request as coming over the unix socket, which the access
+/// checks trust as local.
+#[cfg(unix)]
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct UnixPeer;
+
+#[cfg(unix)]
+impl Connected<IncomingStream<'_, tokio::net::UnixListener>> for UnixPeer {
+    fn connect_info(_stream: IncomingStream<'_, tokio::net::UnixListener>) -> Self {
+        Self
+    }
+}
+
+#[cfg(not(unix))]
+fn bind_unix(_path: &Path, _mode: u32) -> anyhow::Result<Listener> {
+    anyhow::bail!("unix sockets are not supported on this platform")
+}
+
+/// TCP listener, over HTTPS when TLS paths are configured.
+async fn serve_tcp(
+    app: Router,
+    listener: std::net::TcpListener,
+    tls: Option<RustlsConfig>,
+    shutdown: CancellationToken,
+) -> anyhow::Result<()> {
+    listener.set_nonblocking(true)?;
+    let addr = listener.local_addr()?;
+    let service = app.into_make_service_with_connect_info::<SocketAddr>();
+
+    let Some(tls) = tls else {
+        info!("listening on http://{}", addr);
+        axum::serve(tokio::net::TcpListener::from_std(listener)?, service)
+            .with_graceful_shutdown(shutdown.cancelled_owned())
+            .await?;
+        return Ok(());
+    };
+
+    let handle = Handle::new();
+    tokio::spawn({
+        let handle = handle.clone();
+        async move {
+            shutdown.cancelled().await;
+            handle.graceful_shutdown(None);
+        }
+    });
+
+    info!("listening on https://{}", addr);
+    axum_server::from_tcp_rustls(listener, tls)?
+        .handle(handle)
+        .serve(service)
+        .await?;
+    Ok(())
+}
+
+/// Plain HTTP on a unix socket, for a reverse proxy on the same host.
+#[cfg(unix)]
+async fn serve_unix(
+    app: Router,
+    listener: std::os::unix::net::UnixListener,
+    cleanup: Option<PathBuf>,
+    shutdown: CancellationToken,
+) -> anyhow::Result<()> {
+    listener.set_nonblocking(true)?;
+    let listener = tokio::net::UnixListener::from_std(listener)?;
+    match listener.local_addr()?.as_pathname() {
+        Some(path) => info!("listening on unix:{}", path.display()),
+        None => info!("listening on an unnamed unix socket"),
+    }
+    let served = axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<UnixPeer>(),
+    )
+    .with_graceful_shutdown(shutdown.cancelled_owned())
+    .await;
+    if let Some(path) = cleanup {
+        let _ = tokio::fs::remove_file(path).await;
+    }
+    served?;
+    Ok(())
+}
+
+async fn serve_grpc(
+    grpc: OdinGrpc,
+    listener: tokio::net::TcpListener,
+    shutdown: CancellationToken,
+) -> anyhow::Result<()> {
+    info!("grpc listening on {}", listener.local_addr()?);
+    tonic::transport::Server::builder()
+        .add_service(grpc.into_server())
+        .serve_with_incoming_shutdown(
+            tonic::transport::server::TcpIncoming::from(listener),
+            shutdown.cancelled_owned(),
+        )
+        .await?;
+    Ok(())
+}
+
+/// Poll the certificate files and swap them in when renewed, keeping the old pair on errors.
+async fn watch_certificates(
+    tls: RustlsConfig,
+    cert_path: PathBuf,
+    key_path: PathBuf,
+    interval: Duration,
+    shutdown: CancellationToken,
+) {
+    let mut loaded = latest_modified(&cert_path, &key_path);
+    loop {
+        tokio::select! {
+            _ = shutdown.cancelled() => break,
+            _ = tokio::time::sleep(interval) => {}
+        }
+
+        let modified = latest_modified(&cert_path, &key_path);
+        if modified == loaded {
+            continue;
+        }
+        match tls.reload_from_pem_file(&cert_path, &key_path).await {
+            Ok(()) => {
+                loaded = modified;
+                info!("tls certificate reloaded: {}", cert_path.display());
+            }
+            Err(err) => warn!("tls certificate reload failed: {}", err),
+        }
+    }
+}
+
+fn latest_modified(cert_path: &Path, key_path: &Path) -> Option<SystemTime> {
+    [cert_path, key_path]
+        .iter()
+        .filter_map(|path| {
+            std::fs::metadata(path)
+                .and_then(|meta| meta.modified())
+                .ok()
+        })
+        .max()
+}
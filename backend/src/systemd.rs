@@ -0,0 +1,52 @@
+//! Service manager integration: readiness, stopping, and watchdog notifications.
+//! Every call is a no-op when the process is not run by systemd (`NOTIFY_SOCKET` unset).
+
+#[cfg(unix)]
+use std::time::Duration;
+
+use tokio_util::sync::CancellationToken;
+#[cfg(unix)]
+use tracing::{info, warn};
+
+/// Tell systemd startup is complete, so `Type=notify` units and their dependents proceed.
+pub fn notify_ready() {
+    #[cfg(unix)]
+    notify(&[sd_notify::NotifyState::Ready]);
+}
+
+/// Tell systemd a graceful shutdown is under way.
+pub fn notify_stopping() {
+    #[cfg(unix)]
+    notify(&[sd_notify::NotifyState::Stopping]);
+}
+
+/// Ping the watchdog at half of `WatchdogSec=` until shutdown. Pings come from the
+/// async runtime, so a wedged runtime stops them and systemd restarts the service.
+#[cfg(unix)]
+pub fn spawn_watchdog(shutdown: CancellationToken) {
+    let mut usec = 0;
+    if !sd_notify::watchdog_enabled(false, &mut usec) {
+        return;
+    }
+    let interval = Duration::from_micros(usec) / 2;
+    info!("systemd watchdog enabled: pinging every {:?}", interval);
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            tokio::select! {
+                _ = shutdown.cancelled() => break,
+                _ = ticker.tick() => notify(&[sd_notify::NotifyState::Watchdog]),
+            }
+        }
+    });
+}
+
+#[cfg(not(unix))]
+pub fn spawn_watchdog(_shutdown: CancellationToken) {}
+
+#[cfg(unix)]
+fn notify(state: &[sd_notify::NotifyState]) {
+    if let Err(err) = sd_notify::notify(false, state) {
+        warn!("systemd notify failed: {}", err);
+    }
+}
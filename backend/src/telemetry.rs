@@ -0,0 +1,104 @@
+use anyhow::Context;
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::Resource;
+use opentelemetry_sdk::trace::{Sampler, SdkTracerProvider, Tracer};
+use tracing::{info, warn};
+use tracing_opentelemetry::OpenTelemetryLayer;
+use tracing_subscriber::layer::{Layered, SubscriberExt};
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Registry, reload};
+
+use odin_core::config::TelemetryConfig;
+
+type OtelLayer = OpenTelemetryLayer<Registry, Tracer>;
+type FilterHandle =
+    reload::Handle<EnvFilter, Layered<reload::Layer<Option<OtelLayer>, Registry>, Registry>>;
+
+/// Log output plus an OpenTelemetry layer that stays empty until the config,
+/// which is loaded after logging starts, names an OTLP endpoint.
+pub struct Telemetry {
+    otel: reload::Handle<Option<OtelLayer>, Registry>,
+    filter: LogFilter,
+    provider: Option<SdkTracerProvider>,
+}
+
+/// Handle to the active log filter, `info` until `log.level` is applied.
+#[derive(Clone)]
+pub(crate) struct LogFilter(FilterHandle);
+
+impl LogFilter {
+    /// Switch to the `tracing` filter directives in `level`, keeping the old filter
+    /// when they do not parse.
+    pub(crate) fn set(&self, level: &str) -> anyhow::Result<()> {
+        let filter =
+            EnvFilter::try_new(level).with_context(|| format!("invalid log.level {:?}", level))?;
+        self.0.reload(filter).context("install log filter")
+    }
+}
+
+impl Telemetry {
+    pub fn init() -> Self {
+        let (otel, otel_handle) = reload::Layer::new(None::<OtelLayer>);
+        let (filter, filter_handle) = reload::Layer::new(EnvFilter::new("info"));
+        tracing_subscriber::registry()
+            .with(otel)
+            .with(filter)
+            .with(tracing_subscriber::fmt::layer())
+            .init();
+        Self {
+            otel: otel_handle,
+            filter: LogFilter(filter_handle),
+            provider: None,
+        }
+    }
+
+    pub(crate) fn log_filter(&self) -> LogFilter {
+        self.filter.clone()
+    }
+
+    /// Start exporting spans when `otlp_endpoint` is set.
+    pub fn enable(&mut self, config: &TelemetryConfig) -> anyhow::Result<()> {
+        let Some(endpoint) = &config.otlp_endpoint else {
+            return Ok(());
+        };
+        let exporter = SpanExporter::builder()
+            .with_http()
+            .with_endpoint(endpoint.as_str())
+            .build()
+            .context("build otlp exporter")?;
+        let provider = SdkTracerProvider::builder()
+            .with_batch_exporter(exporter)
+            .with_sampler(Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(
+                config.sample_ratio,
+            ))))
+            .with_resource(
+                Resource::builder()
+                    .with_service_name(config.service_name.clone())
+                    .build(),
+            )
+            .build();
+        let layer = tracing_opentelemetry::layer().with_tracer(provider.tracer("odin"));
+        self.otel
+            .reload(Some(layer))
+            .context("install otlp layer")?;
+        self.provider = Some(provider);
+        info!(
+            "exporting traces to {} (sample ratio {})",
+            endpoint, config.sample_ratio
+        );
+        Ok(())
+    }
+
+    /// Flush spans still buffered for export; the SDK blocks, so this runs off the async workers.
+    pub async fn shutdown(self) {
+        let Some(provider) = self.provider else {
+            return;
+        };
+        match tokio::task::spawn_blocking(move || provider.shutdown()).await {
+            Ok(Ok(())) => {}
+            Ok(Err(err)) => warn!("trace export shutdown failed: {}", err),
+            Err(err) => warn!("trace export shutdown panicked: {}", err),
+        }
+    }
+}
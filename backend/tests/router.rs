@@ -0,0 +1,312 @@
+//! The router over in-memory storage (`AppState::in_memory`), driven one request at a
+//! time with the connect info the TCP listener would attach.
+
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+
+use axum::Router;
+use axum::body::Body;
+use axum::extract::ConnectInfo;
+use axum::http::header::{AUTHORIZATION, CONTENT_TYPE, COOKIE, ORIGIN, SET_COOKIE};
+use axum::http::{HeaderMap, Method, Request, StatusCode};
+use axum::routing::get;
+use odin_core::Odin;
+use odin_core::config::Config;
+use odin_server::{AppState, build_router};
+use serde_json::{Value, json};
+use tower::ServiceExt;
+
+const ADMIN_TOKEN: &str = "router-test-admin-token";
+
+/// Over `MIN_MERGE_TEXT_CHARS`, so identical copies on one host merge.
+const LONG_TEXT: &str = "Ferris the crab keeps every bookmark it is given. ";
+
+struct App {
+    odin: Odin,
+    router: Router,
+}
+
+impl App {
+    async fn in_memory() -> Self {
+        let (odin, state) = AppState::in_memory().await.unwrap();
+        Self::build(odin, state).await
+    }
+
+    async fn with_config(config: Config) -> Self {
+        let odin = Odin::open(config).await.unwrap();
+        let state = AppState::new(odin.services().clone());
+        Self::build(odin, state).await
+    }
+
+    async fn build(odin: Odin, state: AppState) -> Self {
+        let router = build_router(state, odin.config());
+        Self { odin, router }
+    }
+
+    /// Register `ADMIN_TOKEN` as the admin key, as `odin serve` does from the environment.
+    async fn with_admin(self) -> Self {
+        self.odin
+            .services()
+            .api_keys
+            .bootstrap(vec![ADMIN_TOKEN.to_string()])
+            .await
+            .unwrap();
+        self
+    }
+
+    async fn send(&self, request: Request<Body>) -> (StatusCode, HeaderMap, Value) {
+        let response = self.router.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (
+            status,
+            headers,
+            serde_json::from_slice(&body).unwrap_or(Value::Null),
+        )
+    }
+
+    async fn get(&self, path: &str) -> Value {
+        let (status, _, body) = self
+            .send(from_peer(
+                "127.0.0.1:40000",
+                authorized(Method::GET, path, None),
+            ))
+            .await;
+        assert_eq!(status, StatusCode::OK, "GET {}: {}", path, body);
+        body
+    }
+
+    /// Queue `urls` with `tags` and wait until no bookmark is left in the queue.
+    async fn ingest(&self, urls: &[String], tags: &[&str]) {
+        let body = json!({ "urls": urls, "tags": tags });
+        let (status, _, body) = self
+            .send(from_peer(
+                "127.0.0.1:40000",
+                authorized(Method::POST, "/v1/ingest/urls", Some(body)),
+            ))
+            .await;
+        assert_eq!(status, StatusCode::OK, "ingest: {}", body);
+        let started = Instant::now();
+        loop {
+            let bookmarks = self.get("/v1/bookmarks").await;
+            let queued = bookmarks["results"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .any(|bookmark| bookmark["status"] == "queued");
+            if !queued {
+                return;
+            }
+            assert!(
+                started.elapsed() < Duration::from_secs(30),
+                "ingest did not finish: {}",
+                bookmarks
+            );
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    }
+
+    async fn shutdown(self) {
+        self.odin.shutdown().await.unwrap();
+    }
+}
+
+fn request(method: Method, path: &str, body: Option<Value>) -> Request<Body> {
+    let builder = Request::builder().method(method).uri(path);
+    match body {
+        Some(body) => builder
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string())),
+        None => builder.body(Body::empty()),
+    }
+    .unwrap()
+}
+
+fn authorized(method: Method, path: &str, body: Option<Value>) -> Request<Body> {
+    let mut request = request(method, path, body);
+    request.headers_mut().insert(
+        AUTHORIZATION,
+        format!("Bearer {}", ADMIN_TOKEN).parse().unwrap(),
+    );
+    request
+}
+
+/// As the TCP listener records the connection's peer.
+fn from_peer(peer: &str, mut request: Request<Body>) -> Request<Body> {
+    let peer: SocketAddr = peer.parse().unwrap();
+    request.extensions_mut().insert(ConnectInfo(peer));
+    request
+}
+
+/// Serve `pages` of `(path, text)` as HTML on a loopback port, returning its base URL.
+async fn serve_pages(pages: Vec<(&'static str, String)>) -> String {
+    let mut router = Router::new();
+    for (path, text) in pages {
+        let html = format!(
+            "<html><head><title>Page</title></head><body><p>{}</p></body></html>",
+            text
+        );
+        router = router.route(
+            path,
+            get(move || async move { ([(CONTENT_TYPE, "text/html")], html) }),
+        );
+    }
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, router).await });
+    format!("http://{}", addr)
+}
+
+#[tokio::test]
+async fn test_bootstrap_over_tcp_needs_the_setup_secret() {
+    let app = App::in_memory().await;
+    let bootstrap = |body: Value| request(Method::POST, "/v1/admin/bootstrap", Some(body));
+
+    for peer in ["127.0.0.1:40000", "[::1]:40000", "192.0.2.7:40000"] {
+        let (status, _, _) = app.send(from_peer(peer, bootstrap(json!({})))).await;
+        assert_eq!(status, StatusCode::FORBIDDEN, "from {}", peer);
+    }
+    let (status, _, _) = app
+        .send(from_peer(
+            "127.0.0.1:40000",
+            bootstrap(json!({ "secret": "not-the-secret" })),
+        ))
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    // No connect info at all is not mistaken for the unix socket.
+    let (status, _, _) = app.send(bootstrap(json!({}))).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    app.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_bootstrap_trusts_only_unforwarded_loopback_when_configured() {
+    let mut config = Config::in_memory();
+    config.auth.bootstrap_trust_loopback = true;
+    let app = App::with_config(config).await;
+
+    let mut forwarded = request(Method::POST, "/v1/admin/bootstrap", Some(json!({})));
+    forwarded
+        .headers_mut()
+        .insert("x-forwarded-for", "203.0.113.9".parse().unwrap());
+    let (status, _, _) = app.send(from_peer("127.0.0.1:40000", forwarded)).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, _, body) = app
+        .send(from_peer(
+            "127.0.0.1:40000",
+            request(Method::POST, "/v1/admin/bootstrap", Some(json!({}))),
+        ))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert!(
+        body["token"]
+            .as_str()
+            .is_some_and(|token| !token.is_empty())
+    );
+    app.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_cookie_writes_need_the_csrf_token() {
+    let app = App::in_memory().await.with_admin().await;
+    let (status, headers, session) = app
+        .send(request(
+            Method::POST,
+            "/v1/auth/login",
+            Some(json!({ "token": ADMIN_TOKEN })),
+        ))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let cookie = headers[SET_COOKIE].to_str().unwrap();
+    let cookie = cookie.split(';').next().unwrap().to_string();
+    let csrf = session["csrf_token"].as_str().unwrap().to_string();
+
+    let write = |csrf: Option<&str>| {
+        let mut request = request(Method::POST, "/v1/ingest/urls", Some(json!({ "urls": [] })));
+        let headers = request.headers_mut();
+        headers.insert(COOKIE, cookie.parse().unwrap());
+        headers.insert(ORIGIN, "https://elsewhere.example".parse().unwrap());
+        if let Some(csrf) = csrf {
+            headers.insert("x-csrf-token", csrf.parse().unwrap());
+        }
+        from_peer("127.0.0.1:40000", request)
+    };
+    let (status, _, _) = app.send(write(None)).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _, _) = app.send(write(Some("forged"))).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _, body) = app.send(write(Some(&csrf))).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    app.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_privileged_cidrs_refuse_other_peers() {
+    let mut config = Config::in_memory();
+    config.auth.privileged_cidrs = vec!["10.0.0.0/8".parse().unwrap()];
+    let app = App::with_config(config).await.with_admin().await;
+    let ingest = || authorized(Method::POST, "/v1/ingest/urls", Some(json!({ "urls": [] })));
+
+    let (status, _, _) = app.send(from_peer("192.0.2.7:40000", ingest())).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _, _) = app.send(ingest()).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _, _) = app.send(from_peer("10.1.2.3:40000", ingest())).await;
+    assert_eq!(status, StatusCode::OK);
+    // IPv4 clients of a dual-stack listener arrive IPv4-mapped.
+    let (status, _, _) = app
+        .send(from_peer("[::ffff:10.1.2.3]:40000", ingest()))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    // Reads are not privileged.
+    let (status, _, _) = app
+        .send(from_peer(
+            "192.0.2.7:40000",
+            authorized(Method::GET, "/v1/bookmarks", None),
+        ))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    app.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_duplicates_merge_into_the_first_bookmark_and_stay_findable() {
+    let app = App::in_memory().await.with_admin().await;
+    let long = LONG_TEXT.repeat(20);
+    let site = serve_pages(vec![
+        ("/original", long.clone()),
+        ("/copy", long),
+        ("/short", "Too short to tell apart.".to_string()),
+        ("/short-copy", "Too short to tell apart.".to_string()),
+    ])
+    .await;
+    let url = |path: &str| format!("{}{}", site, path);
+
+    app.ingest(&[url("/original")], &["first"]).await;
+    app.ingest(&[url("/copy")], &["second"]).await;
+    let bookmarks = app.get("/v1/bookmarks").await;
+    assert_eq!(bookmarks["total"], 1, "{}", bookmarks);
+    let original = &bookmarks["results"][0];
+    assert_eq!(original["url"], url("/original"));
+    assert_eq!(original["tags"], json!(["first", "second"]));
+
+    // The merged URL is kept as an alias of the bookmark it joined.
+    let found = app
+        .get(&format!(
+            "/v1/bookmarks/lookup?url={}",
+            url::form_urlencoded::byte_serialize(url("/copy").as_bytes()).collect::<String>()
+        ))
+        .await;
+    assert_eq!(found["id"], original["id"]);
+
+    // Pages with little text are not merged on it.
+    app.ingest(&[url("/short")], &[]).await;
+    app.ingest(&[url("/short-copy")], &[]).await;
+    let bookmarks = app.get("/v1/bookmarks").await;
+    assert_eq!(bookmarks["total"], 3, "{}", bookmarks);
+    app.shutdown().await;
+}
@@ -0,0 +1,377 @@
+//! `odin bench`: synthetic load against a running server, reporting throughput and
+//! latency percentiles so indexing and search regressions show up as numbers.
+//!
+//! `bench ingest` serves generated pages from a listener in this process and has the
+//! server fetch them into a throwaway namespace; `bench search` replays queries from
+//! the same vocabulary at a fixed concurrency.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result, bail};
+use odin_client::Client;
+use odin_client::types::BulkDeleteRequest;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinSet;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// Bookmarks removed per bulk delete once an ingest run is done.
+const DELETE_BATCH: usize = 1000;
+/// Largest request head the page server reads.
+const MAX_REQUEST_HEAD: usize = 8 * 1024;
+
+/// Words the synthetic pages are written in and the search queries drawn from.
+const VOCABULARY: &[&str] = &[
+    "anchor", "archive", "atlas", "beacon", "binary", "bramble", "cache", "canyon", "cipher",
+    "cobalt", "compiler", "copper", "crystal", "delta", "drift", "ember", "engine", "falcon",
+    "fathom", "fern", "garnet", "glacier", "granite", "harbor", "helix", "horizon", "index",
+    "iris", "jasper", "kernel", "lantern", "lattice", "ledger", "lichen", "marble", "meadow",
+    "meridian", "mosaic", "nebula", "nimbus", "orbit", "orchard", "packet", "pebble", "prism",
+    "quarry", "quartz", "radius", "raven", "relay", "ripple", "river", "saddle", "scaffold",
+    "shard", "signal", "sparrow", "spindle", "summit", "tensor", "thicket", "timber", "token",
+    "topaz", "tundra", "vector", "velvet", "vertex", "willow", "zephyr",
+];
+
+/// A small deterministic generator, so page `n` reads the same on every request.
+struct Words(u64);
+
+impl Words {
+    fn new(seed: u64) -> Self {
+        Self(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1)
+    }
+
+    fn next(&mut self) -> &'static str {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        VOCABULARY[(self.0 % VOCABULARY.len() as u64) as usize]
+    }
+}
+
+pub struct IngestOptions {
+    pub docs: usize,
+    pub words: usize,
+    pub batch: usize,
+    pub listen: SocketAddr,
+    pub advertise: Option<String>,
+    pub namespace: Option<String>,
+    pub keep: bool,
+    pub timeout: Duration,
+}
+
+pub struct SearchOptions {
+    pub requests: usize,
+    pub concurrency: usize,
+    pub queries: Vec<String>,
+}
+
+/// Have the server fetch and index `docs` generated pages, timing each from submission
+/// until the bookmark leaves `queued`, as seen by polling the bookmark list. Every page
+/// comes from one host, so `ingest.max_connections_per_host` bounds the run too.
+pub async fn ingest(builder: odin_client::ClientBuilder, options: IngestOptions) -> Result<()> {
+    if options.docs == 0 || options.batch == 0 {
+        bail!("--docs and --batch must be at least 1");
+    }
+    let run = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .context("system clock is before 1970")?
+        .as_secs();
+    let namespace = options
+        .namespace
+        .clone()
+        .unwrap_or_else(|| format!("bench-{}", run));
+    let client = builder
+        .namespace(namespace.as_str())
+        .build()
+        .context("failed to build http client")?;
+
+    let listener = TcpListener::bind(options.listen)
+        .await
+        .with_context(|| format!("failed to listen on {}", options.listen))?;
+    let local = listener
+        .local_addr()
+        .context("failed to read listen address")?;
+    let base = options
+        .advertise
+        .clone()
+        .unwrap_or_else(|| format!("http://{}", local));
+    let base = base.trim_end_matches('/').to_string();
+    let words = options.words;
+    let server = tokio::spawn(serve_pages(listener, words));
+    println!(
+        "Serving {} pages of {} words on {} as {}; namespace {}.",
+        options.docs, words, local, base, namespace
+    );
+
+    let urls: Vec<String> = (0..options.docs)
+        .map(|n| format!("{}/doc/{}/{}", base, run, n))
+        .collect();
+    let started = Instant::now();
+    let mut submitted: HashMap<&str, Instant> = HashMap::with_capacity(urls.len());
+    let mut latencies = Vec::with_capacity(urls.len());
+    let mut statuses: HashMap<String, usize> = HashMap::new();
+    let mut ids = Vec::with_capacity(urls.len());
+    let mut finished_at = started;
+    for batch in urls.chunks(options.batch) {
+        let response = client.ingest(batch).await?;
+        if response.accepted < batch.len() {
+            println!(
+                "Warning: {} of {} URLs were already saved and are not timed.",
+                batch.len() - response.accepted,
+                batch.len()
+            );
+        }
+        let now = Instant::now();
+        submitted.extend(batch.iter().map(|url| (url.as_str(), now)));
+        collect_done(
+            &client,
+            &mut submitted,
+            &mut latencies,
+            &mut statuses,
+            &mut ids,
+            &mut finished_at,
+        )
+        .await?;
+    }
+    let deadline = started + options.timeout;
+    while !submitted.is_empty() {
+        if Instant::now() >= deadline {
+            println!(
+                "Gave up after {:?} with {} pages still queued.",
+                options.timeout,
+                submitted.len()
+            );
+            break;
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+        collect_done(
+            &client,
+            &mut submitted,
+            &mut latencies,
+            &mut statuses,
+            &mut ids,
+            &mut finished_at,
+        )
+        .await?;
+    }
+    server.abort();
+
+    let elapsed = finished_at.duration_since(started);
+    let mut statuses: Vec<_> = statuses.into_iter().collect();
+    statuses.sort();
+    let statuses: Vec<String> = statuses
+        .into_iter()
+        .map(|(status, count)| format!("{} {}", count, status))
+        .collect();
+    println!(
+        "Ingested {} pages in {:.2}s ({:.1} pages/s): {}.",
+        latencies.len(),
+        elapsed.as_secs_f64(),
+        latencies.len() as f64 / elapsed.as_secs_f64().max(f64::EPSILON),
+        statuses.join(", ")
+    );
+    print_latencies("Queue to done", &mut latencies);
+
+    if options.keep {
+        println!("Kept the bookmarks in namespace {}.", namespace);
+    } else {
+        let mut deleted = 0;
+        for batch in ids.chunks(DELETE_BATCH) {
+            deleted += client
+                .bulk_delete(&BulkDeleteRequest {
+                    ids: batch.to_vec(),
+                    urls: Vec::new(),
+                })
+                .await?
+                .deleted;
+        }
+        println!("Deleted {} bench bookmarks.", deleted);
+    }
+    Ok(())
+}
+
+/// Move bookmarks that left `queued` since the last poll from `pending` to the results.
+async fn collect_done(
+    client: &Client,
+    pending: &mut HashMap<&str, Instant>,
+    latencies: &mut Vec<Duration>,
+    statuses: &mut HashMap<String, usize>,
+    ids: &mut Vec<i64>,
+    finished_at: &mut Instant,
+) -> Result<()> {
+    let now = Instant::now();
+    for bookmark in client.bookmarks().await?.results {
+        if bookmark.status == "queued" {
+            continue;
+        }
+        if let Some(queued_at) = pending.remove(bookmark.url.as_str()) {
+            latencies.push(now.duration_since(queued_at));
+            *statuses.entry(bookmark.status).or_default() += 1;
+            ids.push(bookmark.id);
+            *finished_at = now;
+        }
+    }
+    Ok(())
+}
+
+/// Run `requests` searches from `concurrency` tasks, timing each.
+pub async fn search(client: Client, options: SearchOptions) -> Result<()> {
+    if options.requests == 0 || options.concurrency == 0 {
+        bail!("--requests and --concurrency must be at least 1");
+    }
+    let queries: Arc<Vec<String>> = Arc::new(if options.queries.is_empty() {
+        let mut words = Words::new(1);
+        (0..64)
+            .map(|n| match n % 3 {
+                0 => format!("{} {}", words.next(), words.next()),
+                _ => words.next().to_string(),
+            })
+            .collect()
+    } else {
+        options.queries
+    });
+    let next = Arc::new(AtomicUsize::new(0));
+    let client = Arc::new(client);
+    let started = Instant::now();
+    let mut tasks = JoinSet::new();
+    for _ in 0..options.concurrency {
+        let (client, queries, next) = (client.clone(), queries.clone(), next.clone());
+        let requests = options.requests;
+        tasks.spawn(async move {
+            let mut latencies = Vec::new();
+            let mut errors = Vec::new();
+            loop {
+                let n = next.fetch_add(1, Ordering::Relaxed);
+                if n >= requests {
+                    return (latencies, errors);
+                }
+                let query = &queries[n % queries.len()];
+                let sent = Instant::now();
+                match client.search(query).await {
+                    Ok(_) => latencies.push(sent.elapsed()),
+                    Err(err) => errors.push(err.to_string()),
+                }
+            }
+        });
+    }
+    let mut latencies = Vec::with_capacity(options.requests);
+    let mut errors = Vec::new();
+    while let Some(result) = tasks.join_next().await {
+        let (task_latencies, task_errors) = result.context("search task panicked")?;
+        latencies.extend(task_latencies);
+        errors.extend(task_errors);
+    }
+    let elapsed = started.elapsed();
+    println!(
+        "Ran {} searches in {:.2}s ({:.1} requests/s) at concurrency {}; {} failed.",
+        options.requests,
+        elapsed.as_secs_f64(),
+        options.requests as f64 / elapsed.as_secs_f64().max(f64::EPSILON),
+        options.concurrency,
+        errors.len()
+    );
+    if let Some(error) = errors.first() {
+        // Usually the server's search rate limit; see `[rate_limit]`.
+        println!("First failure: {}", error);
+    }
+    print_latencies("Latency", &mut latencies);
+    Ok(())
+}
+
+fn print_latencies(label: &str, latencies: &mut [Duration]) {
+    if latencies.is_empty() {
+        return;
+    }
+    latencies.sort();
+    let percentile = |p: f64| {
+        let rank = ((p / 100.0) * latencies.len() as f64).ceil() as usize;
+        latencies[rank.clamp(1, latencies.len()) - 1]
+    };
+    println!(
+        "{}: p50 {:.1}ms  p90 {:.1}ms  p99 {:.1}ms  max {:.1}ms",
+        label,
+        millis(percentile(50.0)),
+        millis(percentile(90.0)),
+        millis(percentile(99.0)),
+        millis(latencies[latencies.len() - 1])
+    );
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+/// Answer every request for `/doc/<run>/<n>` with page `n`, one request per connection.
+async fn serve_pages(listener: TcpListener, words: usize) {
+    loop {
+        let Ok((stream, _)) = listener.accept().await else {
+            continue;
+        };
+        tokio::spawn(async move {
+            let _ = serve_page(stream, words).await;
+        });
+    }
+}
+
+async fn serve_page(mut stream: TcpStream, words: usize) -> std::io::Result<()> {
+    let mut head = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !head.windows(4).any(|window| window == b"\r\n\r\n") && head.len() < MAX_REQUEST_HEAD {
+        let read = stream.read(&mut buf).await?;
+        if read == 0 {
+            break;
+        }
+        head.extend_from_slice(&buf[..read]);
+    }
+    let head = String::from_utf8_lossy(&head);
+    let page = head
+        .split_whitespace()
+        .nth(1)
+        .and_then(|path| path.rsplit('/').next())
+        .and_then(|n| n.parse::<u64>().ok());
+    let response = match page {
+        Some(n) => {
+            let body = document(n, words);
+            format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            )
+        }
+        None => {
+            "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string()
+        }
+    };
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+fn document(n: u64, words: usize) -> String {
+    let mut generator = Words::new(n + 1);
+    let mut page = format!(
+        "<!doctype html><html><head><title>Bench page {}: {} {}</title></head><body><h1>Bench page {}</h1>",
+        n,
+        generator.next(),
+        generator.next(),
+        n
+    );
+    let mut remaining = words;
+    while remaining > 0 {
+        let paragraph = remaining.min(80);
+        page.push_str("<p>");
+        for i in 0..paragraph {
+            if i > 0 {
+                page.push(' ');
+            }
+            page.push_str(generator.next());
+        }
+        page.push_str(".</p>");
+        remaining -= paragraph;
+    }
+    page.push_str("</body></html>");
+    page
+}
@@ -0,0 +1,363 @@
+//! Text renderings of server responses for the terminal; `--json` bypasses these.
+
+use std::fmt::Write as _;
+use std::io::IsTerminal;
+
+use anyhow::Result;
+use odin_client::types::{
+    ApiKeysResponse, BookmarkListItem, BookmarksResponse, BulkDeleteResponse, DomainStatsResponse,
+    FeedsResponse, IngestUrlsResponse, JobsResponse, Scope, SearchResponse, SearchSnippet,
+    StatsResponse, WebhooksResponse,
+};
+
+pub fn format_query_results(response: &SearchResponse) -> Result<String> {
+    let mut out = String::new();
+    if response.results.is_empty() {
+        writeln!(out, "No results.")?;
+        return Ok(out);
+    }
+
+    writeln!(
+        out,
+        "Found {} result{}.",
+        response.total_hits,
+        if response.total_hits == 1 { "" } else { "s" }
+    )?;
+
+    for (index, item) in response.results.iter().enumerate() {
+        let title = item
+            .title
+            .as_deref()
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .unwrap_or(item.url.as_str());
+        let label = if item.url.trim().is_empty() {
+            title.to_string()
+        } else {
+            hyperlink(&item.url, title)
+        };
+        writeln!(out, "{:>2}. {}", index + 1, label)?;
+        if let Some(snippet) = &item.snippet {
+            writeln!(out, "    {}", highlight_snippet(snippet))?;
+        }
+    }
+
+    Ok(out)
+}
+
+/// A snippet on one line, with matches in bold on a terminal.
+fn highlight_snippet(snippet: &SearchSnippet) -> String {
+    let terminal = std::io::stdout().is_terminal();
+    let mut out = String::new();
+    let mut end = 0;
+    for &[start, stop] in &snippet.highlights {
+        let (Some(before), Some(matched)) =
+            (snippet.text.get(end..start), snippet.text.get(start..stop))
+        else {
+            continue;
+        };
+        out.push_str(before);
+        if terminal {
+            out.push_str(&format!("\u{1b}[1m{}\u{1b}[0m", matched));
+        } else {
+            out.push_str(matched);
+        }
+        end = stop;
+    }
+    out.push_str(snippet.text.get(end..).unwrap_or_default());
+    out.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+pub fn format_bookmarks(response: &BookmarksResponse) -> Result<String> {
+    let mut out = String::new();
+    if response.results.is_empty() {
+        writeln!(out, "No bookmarks.")?;
+        return Ok(out);
+    }
+
+    let id_width = response
+        .results
+        .iter()
+        .map(|item| item.id.to_string().len())
+        .max()
+        .unwrap_or(2)
+        .max("ID".len());
+    let status_width = response
+        .results
+        .iter()
+        .map(|item| item.status.len())
+        .max()
+        .unwrap_or(6)
+        .max("Status".len());
+    let mut title_width = response
+        .results
+        .iter()
+        .map(|item| {
+            item.title
+                .as_deref()
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .unwrap_or(item.url.as_str())
+                .len()
+        })
+        .max()
+        .unwrap_or(5)
+        .max("Title".len());
+    let title_width_cap = 80usize;
+    if title_width > title_width_cap {
+        title_width = title_width_cap;
+    }
+
+    writeln!(
+        out,
+        "{:>id_width$}  {:<status_width$}  {:<title_width$}  Tags",
+        "ID", "Status", "Title"
+    )?;
+    writeln!(
+        out,
+        "{:-<id_width$}  {:-<status_width$}  {:-<title_width$}  ----",
+        "", "", ""
+    )?;
+
+    for item in response.results.iter() {
+        let title = item
+            .title
+            .as_deref()
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .unwrap_or(item.url.as_str());
+        let title = truncate_with_ellipsis(title, title_width);
+        writeln!(
+            out,
+            "{:>id_width$}  {:<status_width$}  {:<title_width$}  {}",
+            item.id,
+            item.status,
+            title,
+            item.tags.join(", ")
+        )?;
+    }
+    if let (Some(total), Some(page), Some(per_page)) =
+        (response.total, response.page, response.per_page)
+    {
+        let pages = (total.max(1) as u64).div_ceil(u64::from(per_page.max(1)));
+        let noun = if total == 1 { "bookmark" } else { "bookmarks" };
+        writeln!(out, "\nPage {} of {} ({} {})", page, pages, total, noun)?;
+    }
+
+    Ok(out)
+}
+
+/// One bookmark's fields, one per line, for `odin status <id>`.
+pub fn format_bookmark(bookmark: &BookmarkListItem) -> Result<String> {
+    let mut out = String::new();
+    writeln!(out, "ID       {}", bookmark.id)?;
+    writeln!(out, "URL      {}", bookmark.url)?;
+    if let Some(original_url) = bookmark
+        .original_url
+        .as_deref()
+        .filter(|original_url| *original_url != bookmark.url)
+    {
+        writeln!(out, "Saved as {}", original_url)?;
+    }
+    writeln!(
+        out,
+        "Title    {}",
+        bookmark.title.as_deref().unwrap_or("Untitled")
+    )?;
+    match bookmark.attempts {
+        0 | 1 => writeln!(out, "Status   {}", bookmark.status)?,
+        attempts => writeln!(out, "Status   {} ({} attempts)", bookmark.status, attempts)?,
+    }
+    if let Some(document_type) = &bookmark.document_type {
+        writeln!(out, "Type     {}", document_type)?;
+    }
+    if !bookmark.tags.is_empty() {
+        writeln!(out, "Tags     {}", bookmark.tags.join(", "))?;
+    }
+    writeln!(out, "Updated  {}", bookmark.updated_at)?;
+    Ok(out)
+}
+
+pub fn format_ingest(response: &IngestUrlsResponse) -> Result<String> {
+    let mut out = String::new();
+    writeln!(
+        out,
+        "Queued {} URL{}, {} already saved; queue at {}/{}.",
+        response.accepted,
+        if response.accepted == 1 { "" } else { "s" },
+        response.deduped,
+        response.queue_depth,
+        response.queue_capacity
+    )?;
+    Ok(out)
+}
+
+pub fn format_stats(stats: &StatsResponse) -> Result<String> {
+    let mut out = String::new();
+    writeln!(out, "Bookmarks  {}", stats.bookmarks)?;
+    for (status, count) in &stats.by_status {
+        writeln!(out, "  {:<9}{}", status, count)?;
+    }
+    writeln!(out, "Index      {}", human_bytes(stats.index_bytes))?;
+    writeln!(out, "Database   {}", human_bytes(stats.db_bytes))?;
+    Ok(out)
+}
+
+fn human_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+pub fn print_keys(response: &ApiKeysResponse) {
+    for key in &response.results {
+        let mut state = match (&key.revoked_at, &key.last_used_at) {
+            (Some(revoked_at), _) => format!("revoked {}", revoked_at),
+            (None, Some(last_used_at)) => format!("last used {}", last_used_at),
+            (None, None) => "never used".to_string(),
+        };
+        if let (None, Some(expires_at)) = (&key.revoked_at, &key.expires_at) {
+            state.push_str(&format!(", expires {}", expires_at));
+        }
+        println!(
+            "{:>4}  {:<20}  {:<18}  {:<12}  {}",
+            key.id,
+            key.name,
+            join_scopes(&key.scopes),
+            key.namespace.as_deref().unwrap_or("any"),
+            state
+        );
+    }
+}
+
+pub fn print_feeds(response: &FeedsResponse) {
+    for feed in &response.results {
+        let state = match (&feed.last_error, &feed.last_polled_at) {
+            (Some(error), _) => format!("error: {}", error),
+            (None, Some(last_polled_at)) => format!("polled {}", last_polled_at),
+            (None, None) => "not polled yet".to_string(),
+        };
+        println!(
+            "{:>4}  {:<40}  {:>6}  {}",
+            feed.id,
+            feed.title.as_deref().unwrap_or(&feed.url),
+            feed.entries_seen,
+            state
+        );
+    }
+}
+
+pub fn print_webhooks(response: &WebhooksResponse) {
+    for webhook in &response.results {
+        let state = match (&webhook.last_error, &webhook.last_attempt_at) {
+            (Some(error), _) => format!("error: {}", error),
+            (None, Some(last_attempt_at)) => format!("delivered {}", last_attempt_at),
+            (None, None) => "no deliveries yet".to_string(),
+        };
+        println!(
+            "{:>4}  {:<40}  {:<22}  {}",
+            webhook.id,
+            webhook.url,
+            webhook.events.join(","),
+            state
+        );
+    }
+}
+
+pub fn join_scopes(scopes: &[Scope]) -> String {
+    scopes
+        .iter()
+        .map(|scope| scope.as_str())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+pub fn print_jobs(response: &JobsResponse) {
+    for job in &response.results {
+        let progress = match job.total {
+            Some(total) => format!("{}/{}", job.done, total),
+            None => job.done.to_string(),
+        };
+        println!(
+            "{:>4}  {:<8}  {:<10}  {:>11}  {}{}",
+            job.id,
+            job.kind,
+            job.state,
+            progress,
+            job.created_at,
+            job.error
+                .as_deref()
+                .map(|error| format!("  {}", error))
+                .unwrap_or_default()
+        );
+    }
+}
+
+pub fn print_domain_stats(response: &DomainStatsResponse) {
+    if response.results.is_empty() {
+        println!("No fetches in the last {} hours.", response.hours);
+        return;
+    }
+    println!(
+        "{:<40}  {:>8}  {:>8}  {:>10}  {:>8}  {:>8}  {:>10}",
+        "DOMAIN", "FETCHES", "FAILED", "BYTES", "AVG_MS", "MAX_MS", "TOTAL_S"
+    );
+    for stat in &response.results {
+        println!(
+            "{:<40}  {:>8}  {:>7.1}%  {:>10}  {:>8}  {:>8}  {:>10.1}",
+            stat.domain,
+            stat.fetches,
+            stat.failure_rate * 100.0,
+            stat.bytes,
+            stat.avg_latency_ms,
+            stat.max_latency_ms,
+            stat.total_latency_ms as f64 / 1000.0
+        );
+    }
+}
+
+pub fn format_bulk_delete(response: &BulkDeleteResponse) -> Result<String> {
+    let mut out = String::new();
+    writeln!(
+        out,
+        "Deleted {} bookmark{}.",
+        response.deleted,
+        if response.deleted == 1 { "" } else { "s" }
+    )?;
+    if !response.not_found.is_empty() {
+        writeln!(out, "Not found ({}):", response.not_found.len())?;
+        for entry in &response.not_found {
+            writeln!(out, "  {}", entry)?;
+        }
+    }
+    Ok(out)
+}
+
+fn hyperlink(url: &str, text: &str) -> String {
+    if std::io::stdout().is_terminal() {
+        format!("\u{1b}]8;;{}\u{1b}\\{}\u{1b}]8;;\u{1b}\\", url, text)
+    } else {
+        text.to_string()
+    }
+}
+
+pub fn truncate_with_ellipsis(value: &str, max_width: usize) -> String {
+    if value.len() <= max_width {
+        return value.to_string();
+    }
+    if max_width <= 3 {
+        return value.chars().take(max_width).collect();
+    }
+    format!(
+        "{}...",
+        value.chars().take(max_width - 3).collect::<String>()
+    )
+}
@@ -1,18 +1,56 @@
+use std::fmt::Write as _;
 use std::fs;
-use std::io::IsTerminal;
+use std::io::Write as _;
 use std::path::{Path, PathBuf};
-use std::time::Duration;
 
 use anyhow::{Context, Result};
 use clap::{Parser, Subcommand};
-use reqwest::header::{AUTHORIZATION, HeaderMap, HeaderValue};
+use odin_client::types::{
+    BookmarkSort, BookmarksQuery, BootstrapRequest, BulkDeleteRequest, BulkDeleteResponse,
+    CreateApiKeyRequest, DomainStatsSort, ExportFormat, FeedCreateRequest,
+    FeedReaderConnectRequest, FeedReaderKind, ImportFormat, ReadwiseConnectRequest, ReindexRequest,
+    Scope, SearchQuery, SearchResponse, SearchSort, SetReadOnlyRequest, UpdateSettingsRequest,
+    WebhookCreateRequest,
+};
+use odin_client::{Client, ReindexStream, RetryConfig};
 use serde::{Deserialize, Serialize};
 
+use format::{
+    format_bookmark, format_bookmarks, format_bulk_delete, format_ingest, format_query_results,
+    format_stats, join_scopes, print_domain_stats, print_feeds, print_jobs, print_keys,
+    print_webhooks, truncate_with_ellipsis,
+};
+use output::{Format, Output, emit, print_json};
+
+mod bench;
+mod format;
+mod mcp;
+mod output;
+
+/// Ids and URLs the server accepts in one bulk delete request.
+const BULK_DELETE_LIMIT: usize = 1000;
+
 #[derive(Parser)]
 #[command(name = "odin", about = "CLI for querying and ingesting URLs")]
 struct Cli {
     #[arg(long)]
     config: Option<PathBuf>,
+    /// Server URL for this invocation, overriding `base_url` from the config file.
+    #[arg(long, global = true)]
+    base_url: Option<String>,
+    /// Admin token for this invocation, overriding `admin_token` from the config file.
+    #[arg(long, global = true)]
+    token: Option<String>,
+    /// Namespace for this invocation, overriding `namespace` from the config file.
+    #[arg(long, global = true)]
+    namespace: Option<String>,
+    /// Never pipe long output through $PAGER.
+    #[arg(long, global = true)]
+    no_pager: bool,
+    /// Print query, list, status, delete, and ingest results as JSON; also set by
+    /// ODIN_OUTPUT=json.
+    #[arg(long, global = true)]
+    json: bool,
     #[command(subcommand)]
     command: Commands,
 }
@@ -22,15 +60,465 @@ enum Commands {
     Config,
     Query {
         query: String,
+        /// Fetch every page of results instead of only the first.
+        #[arg(long)]
+        all: bool,
+        /// Only search bookmarks with this tag.
+        #[arg(long)]
+        tag: Option<String>,
+        /// Only search pages on this host or its subdomains, e.g. example.com.
+        #[arg(long)]
+        site: Option<String>,
+        /// Only search pages fetched on or after this date (YYYY-MM-DD or RFC 3339).
+        #[arg(long)]
+        after: Option<String>,
+        /// Only search pages fetched before this date (YYYY-MM-DD or RFC 3339).
+        #[arg(long)]
+        before: Option<String>,
+        /// relevance (default) or recent.
+        #[arg(long)]
+        sort: Option<SearchSort>,
+    },
+    List {
+        /// Only list bookmarks with this tag.
+        #[arg(long)]
+        tag: Option<String>,
+        /// Only list bookmarks with this status: queued, indexed, partial, or failed.
+        #[arg(long)]
+        status: Option<String>,
+        /// Only list bookmarks whose URL or title contains this text.
+        #[arg(long = "match", value_name = "TEXT")]
+        matching: Option<String>,
+        /// updated (default), created, oldest, title, or url.
+        #[arg(long)]
+        sort: Option<BookmarkSort>,
+        #[arg(long, default_value_t = 1)]
+        page: u32,
+        /// Bookmarks per page, up to 500.
+        #[arg(long, default_value_t = 50)]
+        limit: u32,
+    },
+    /// Show whether a URL is saved, and its bookmark if so.
+    Lookup {
+        url: String,
+    },
+    /// List namespaces holding bookmarks, with their bookmark counts.
+    Namespaces,
+    /// Print the text extracted from a bookmark's page, as stored at its last fetch.
+    #[command(alias = "show")]
+    Read {
+        id: i64,
+        /// Write the archived page itself instead, as the site served it.
+        #[arg(long)]
+        html: bool,
     },
-    List,
     Delete {
+        #[arg(required_unless_present = "file", conflicts_with = "file")]
+        id: Option<i64>,
+        /// Delete every bookmark id or URL listed in the file, one per line.
+        #[arg(short = 'f', long = "file")]
+        file: Option<PathBuf>,
+    },
+    /// Fetch and index a bookmark again, or every bookmark with `--status`.
+    Refetch {
+        #[arg(required_unless_present = "status", conflicts_with = "status")]
+        id: Option<i64>,
+        /// Refetch every bookmark with this status: indexed, partial, or failed.
+        #[arg(long)]
+        status: Option<String>,
+    },
+    /// Replace a bookmark's tags; give none to remove them all.
+    Tag {
+        id: i64,
+        tags: Vec<String>,
+    },
+    /// List tags with their bookmark counts.
+    Tags,
+    /// Mark a bookmark public, posting it to the server's fediverse followers.
+    Publish {
+        id: i64,
+    },
+    /// Make a public bookmark private again and delete its fediverse post.
+    Unpublish {
         id: i64,
     },
     Ingest {
         #[arg(short = 'f', long = "file")]
         file: Option<PathBuf>,
         urls: Vec<String>,
+        /// Tag each new bookmark; repeat for several tags.
+        #[arg(long = "tag")]
+        tags: Vec<String>,
+    },
+    /// Upload a bookmarks export (browser HTML, CSV, or a URL list) and ingest every URL in it.
+    Import {
+        path: PathBuf,
+        /// `netscape` for a browser's bookmarks HTML (detected by `auto` too), `pocket`
+        /// for Pocket's CSV, HTML, or API JSON, `pinboard` for Pinboard JSON; these keep
+        /// titles and dates. `omnivore` for an Omnivore export zip or one of its
+        /// metadata_*.json files, `karakeep` for a Karakeep (Hoarder) JSON export,
+        /// `shiori` for Shiori JSON.
+        #[arg(long, default_value = "auto")]
+        format: ImportFormat,
+    },
+    /// Import Instapaper's CSV export. Folders and stars are counted but not kept.
+    ImportInstapaper {
+        path: PathBuf,
+        /// Only report what would be imported.
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Import an RSS reader's OPML export: feeds are subscribed to and links saved.
+    ImportOpml {
+        path: PathBuf,
+        /// Ingest only entries the feeds publish from now on.
+        #[arg(long)]
+        from_now: bool,
+    },
+    /// Sync a browser's bookmarks file: Chrome's (or Edge's, Brave's) `Bookmarks` JSON or a
+    /// copy of Firefox's places.sqlite. New URLs are saved; run it again to pick up changes.
+    SyncBrowser {
+        path: PathBuf,
+        /// Keeps profiles apart; defaults to `chrome` or `firefox`.
+        #[arg(long)]
+        source: Option<String>,
+        /// Also delete bookmarks removed from the browser since the last sync.
+        #[arg(long)]
+        prune: bool,
+    },
+    /// Write every bookmark as a Markdown note (for Obsidian, Logseq, ...) into a directory,
+    /// or into a zip when the path ends in `.zip`; with `--format`, as one file instead.
+    Export {
+        #[arg(required_unless_present = "output")]
+        path: Option<PathBuf>,
+        /// Where to write; the same as the path argument.
+        #[arg(short, long, conflicts_with = "path")]
+        output: Option<PathBuf>,
+        /// json, ndjson, csv, or netscape; a directory path gets a timestamped file name.
+        #[arg(long)]
+        format: Option<ExportFormat>,
+        /// The same as `--format ndjson`.
+        #[arg(long, conflicts_with = "format")]
+        ndjson: bool,
+        /// With `--format json` or `ndjson`, include each bookmark's extracted text.
+        #[arg(long)]
+        content: bool,
+    },
+    /// Download a database and index snapshot; a directory path gets a timestamped file name.
+    Backup {
+        path: PathBuf,
+    },
+    /// Upload a backup archive; the server applies it on its next restart.
+    Restore {
+        path: PathBuf,
+    },
+    /// Manage user accounts (requires an admin token).
+    User {
+        #[command(subcommand)]
+        command: UserCommands,
+    },
+    /// Manage API keys for the configured token's user.
+    Key {
+        #[command(subcommand)]
+        command: KeyCommands,
+    },
+    /// Rebuild the server's search index, printing progress as it goes.
+    Reindex {
+        #[arg(long, required_unless_present = "all", conflicts_with = "all")]
+        id: Option<i64>,
+        #[arg(long)]
+        all: bool,
+        /// Fetch pages again instead of reusing their stored text.
+        #[arg(long)]
+        refetch: bool,
+    },
+    /// Create the first admin key on a fresh server and save it to the config file when
+    /// it has no token yet. Pass the setup secret the server logged.
+    Bootstrap {
+        #[arg(long)]
+        secret: Option<String>,
+    },
+    /// Show one bookmark's fetch status, or without an id print server status (version,
+    /// uptime, index and queue sizes) as JSON.
+    Status {
+        id: Option<i64>,
+    },
+    /// Show bookmark counts by status and the server's index and database sizes.
+    Stats,
+    /// Open the nth result of the last `odin query` in the browser ($BROWSER if set).
+    Open {
+        n: usize,
+    },
+    /// Show fetch counts, failures, bytes, and latency per domain, slowest first.
+    Domains {
+        /// Hours to look back, up to 168.
+        #[arg(long, default_value_t = 24)]
+        hours: u32,
+        #[arg(long, default_value_t = 20)]
+        limit: u32,
+        /// `time` (total fetch time), `latency`, `failures`, `fetches`, or `bytes`.
+        #[arg(long, default_value = "time")]
+        sort: DomainStatsSort,
+    },
+    /// Vacuum free pages, refresh statistics, and checkpoint the WAL of the server's database.
+    DbMaintenance,
+    /// Re-add bookmarks missing from the search index and drop documents of deleted bookmarks.
+    Reconcile {
+        /// Only report the drift, changing nothing.
+        #[arg(long)]
+        check: bool,
+    },
+    /// Show read-only mode, or turn it on or off. While on, searches and reads keep
+    /// working and changes (ingests, imports, deletes, key and user changes) get 503.
+    ReadOnly {
+        #[command(subcommand)]
+        command: Option<ReadOnlyCommands>,
+    },
+    /// Show runtime-adjustable ingest limits and slow-log thresholds, or change the ones given.
+    Settings {
+        #[arg(long)]
+        fetch_concurrency: Option<usize>,
+        #[arg(long)]
+        fetch_timeout_secs: Option<u64>,
+        #[arg(long)]
+        max_urls: Option<usize>,
+        #[arg(long)]
+        excerpt_len: Option<usize>,
+        /// Warn about SQL statements taking at least this long (0 disables).
+        #[arg(long)]
+        slow_query_ms: Option<u64>,
+        /// Warn about searches taking at least this long (0 disables).
+        #[arg(long)]
+        slow_search_ms: Option<u64>,
+        /// Warn about index commits taking at least this long (0 disables).
+        #[arg(long)]
+        slow_index_commit_ms: Option<u64>,
+    },
+    /// Push bookmarks to Readwise Reader in the background.
+    Readwise {
+        #[command(subcommand)]
+        command: ReadwiseCommands,
+    },
+    /// Import entries starred in Miniflux or FreshRSS in the background.
+    FeedReader {
+        #[command(subcommand)]
+        command: FeedReaderCommands,
+    },
+    /// Subscribe to RSS and Atom feeds whose new entries are saved in the background.
+    Feeds {
+        #[command(subcommand)]
+        command: FeedCommands,
+    },
+    /// Have the server POST bookmark events (indexed, failed, deleted) to a URL.
+    Webhooks {
+        #[command(subcommand)]
+        command: WebhookCommands,
+    },
+    /// Back bookmarks up to the Wayback Machine with Save Page Now.
+    Wayback {
+        #[command(subcommand)]
+        command: WaybackCommands,
+    },
+    /// Follow and cancel long-running operations (imports, reindexes, backups).
+    Job {
+        #[command(subcommand)]
+        command: JobCommands,
+    },
+    /// Serve search, page text, and saving as Model Context Protocol tools over stdio,
+    /// for assistants such as Claude Desktop.
+    Mcp,
+    /// Measure ingest or search throughput and latency against the server with synthetic load.
+    Bench {
+        #[command(subcommand)]
+        command: BenchCommands,
+    },
+    /// Run the server in this process, configured by `odin.toml` and `ODIN_*` like `backend`.
+    Serve {
+        /// Keep the database and index in memory; nothing is written to disk or kept after exit.
+        #[arg(long)]
+        ephemeral: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum BenchCommands {
+    /// Serve generated pages from this process and time the server fetching and indexing
+    /// them into a throwaway namespace, deleted afterwards.
+    Ingest {
+        #[arg(long, default_value_t = 200)]
+        docs: usize,
+        /// Words of text per page.
+        #[arg(long, default_value_t = 800)]
+        words: usize,
+        /// URLs per ingest request; keep it within the server's `max_urls`.
+        #[arg(long, default_value_t = 100)]
+        batch: usize,
+        /// Where the page server listens; the odin server must be able to reach it.
+        #[arg(long, default_value = "127.0.0.1:0")]
+        listen: std::net::SocketAddr,
+        /// Base URL the server should fetch pages from, when it differs from `--listen`.
+        #[arg(long)]
+        advertise: Option<String>,
+        /// Namespace to save into; defaults to `bench-<unix time>`.
+        #[arg(long = "into")]
+        namespace: Option<String>,
+        /// Keep the bench bookmarks instead of deleting them.
+        #[arg(long)]
+        keep: bool,
+        /// Stop waiting for pages still queued after this long.
+        #[arg(long, default_value_t = 600)]
+        timeout_secs: u64,
+    },
+    /// Send searches at a fixed concurrency and report latency percentiles. Without
+    /// `--query`, queries come from the vocabulary `bench ingest` writes pages in.
+    Search {
+        #[arg(long, default_value_t = 500)]
+        requests: usize,
+        #[arg(long, default_value_t = 8)]
+        concurrency: usize,
+        #[arg(long = "query")]
+        queries: Vec<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum JobCommands {
+    List,
+    Show {
+        id: i64,
+    },
+    /// Ask a running job to stop.
+    Cancel {
+        id: i64,
+    },
+}
+
+#[derive(Subcommand)]
+enum ReadwiseCommands {
+    /// Show whether sync is connected and how many bookmarks are still to push.
+    Status,
+    /// Connect with a token from readwise.io/access_token; pushes the whole archive.
+    Connect {
+        #[arg(value_name = "TOKEN")]
+        readwise_token: String,
+        /// Push only bookmarks saved from now on.
+        #[arg(long)]
+        from_now: bool,
+    },
+    /// Stop pushing and forget the token.
+    Disconnect,
+}
+
+#[derive(Subcommand)]
+enum FeedCommands {
+    /// Subscribe; entries already in the feed are saved unless --from-now.
+    Add {
+        url: String,
+        /// Save only entries published from now on.
+        #[arg(long)]
+        from_now: bool,
+    },
+    List,
+    /// Unsubscribe by id; bookmarks saved from the feed stay.
+    Remove {
+        id: i64,
+    },
+}
+
+#[derive(Subcommand)]
+enum WebhookCommands {
+    /// Register a URL; it is sent every event unless some are given with --event.
+    Add {
+        url: String,
+        /// Sign deliveries with this secret (`X-Odin-Signature`).
+        #[arg(long)]
+        secret: Option<String>,
+        /// indexed, failed, or deleted; repeat for several.
+        #[arg(long = "event")]
+        events: Vec<String>,
+    },
+    List,
+    Remove {
+        id: i64,
+    },
+}
+
+#[derive(Subcommand)]
+enum FeedReaderCommands {
+    /// Show whether import is connected and when it last polled.
+    Status,
+    /// Connect an account; entries starred already are imported unless --from-now.
+    Connect {
+        /// `miniflux` or `freshrss`.
+        kind: FeedReaderKind,
+        /// The reader's base URL, e.g. https://reader.example.com.
+        url: String,
+        /// Miniflux API key, or FreshRSS API password.
+        #[arg(value_name = "TOKEN")]
+        reader_token: String,
+        /// FreshRSS username.
+        #[arg(long)]
+        username: Option<String>,
+        /// Import only entries starred from now on.
+        #[arg(long)]
+        from_now: bool,
+    },
+    /// Stop importing and forget the credentials.
+    Disconnect,
+}
+
+#[derive(Subcommand)]
+enum WaybackCommands {
+    /// Queue a capture; the server records the archive URL once it is taken.
+    Submit { id: i64 },
+    /// Show a bookmark's capture status and archive URL.
+    Show { id: i64 },
+}
+
+#[derive(Subcommand)]
+enum ReadOnlyCommands {
+    On {
+        /// Told to clients whose writes are refused, e.g. "backup in progress".
+        #[arg(long)]
+        message: Option<String>,
+    },
+    Off,
+}
+
+#[derive(Subcommand)]
+enum UserCommands {
+    /// Create a user and print its token.
+    Add {
+        username: String,
+        #[arg(long)]
+        admin: bool,
+    },
+    List,
+}
+
+#[derive(Subcommand)]
+enum KeyCommands {
+    /// Create a key and print its token.
+    Add {
+        name: String,
+        /// Scopes to grant (read, ingest, delete, admin); defaults to read, ingest, and delete.
+        #[arg(long = "scope")]
+        scopes: Vec<Scope>,
+        /// Bind the key to one namespace.
+        #[arg(long = "bind-namespace")]
+        bind_namespace: Option<String>,
+    },
+    List,
+    /// Revoke a key by id.
+    Revoke {
+        id: i64,
+    },
+    /// Issue a replacement token; the old one keeps working for the grace period.
+    Rotate {
+        id: i64,
+        /// Seconds the old token stays valid; defaults to the server's setting.
+        #[arg(long)]
+        grace_secs: Option<u64>,
     },
 }
 
@@ -39,6 +527,12 @@ struct Config {
     base_url: String,
     #[serde(alias = "ingest_token")]
     admin_token: Option<String>,
+    /// Sent as `x-odin-namespace`; unset uses the key's namespace or the server default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    namespace: Option<String>,
+    /// Retry policy for idempotent requests.
+    #[serde(default)]
+    retry: RetryConfig,
 }
 
 impl Default for Config {
@@ -46,84 +540,216 @@ impl Default for Config {
         Self {
             base_url: "http://localhost:3000".to_string(),
             admin_token: None,
+            namespace: None,
+            retry: RetryConfig::default(),
         }
     }
 }
 
-#[derive(Deserialize)]
-struct SearchResponse {
-    total_hits: u64,
-    results: Vec<SearchResultItem>,
-}
-
-#[derive(Deserialize)]
-struct SearchResultItem {
-    url: String,
-    title: Option<String>,
-}
-
-#[derive(Deserialize)]
-struct BookmarksResponse {
-    results: Vec<BookmarkListItem>,
-}
-
-#[derive(Deserialize)]
-struct BookmarkListItem {
-    id: i64,
-    url: String,
-    title: Option<String>,
-    status: String,
-}
-
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
+    if let Commands::Serve { ephemeral } = cli.command {
+        let telemetry = odin_server::Telemetry::init();
+        let mut config = odin_server::load_config()?;
+        config.storage.in_memory |= ephemeral;
+        return odin_server::run(config, telemetry).await;
+    }
+
     let config_path = resolve_config_path(cli.config);
-    let config = load_config(&config_path)?;
-    let base_url = config.base_url.trim_end_matches('/');
+    let mut config = load_config(&config_path)?;
+    if let Some(base_url) = cli.base_url {
+        config.base_url = base_url;
+    }
+    if let Some(token) = cli.token {
+        config.admin_token = Some(token);
+    }
+    if let Some(namespace) = cli.namespace {
+        config.namespace = Some(namespace);
+    }
 
-    let client = reqwest::Client::builder()
-        .timeout(Duration::from_secs(30))
-        .build()
-        .context("failed to build http client")?;
+    // Reads are scoped to the caller too, so every request carries the token when one is set.
+    let builder = || {
+        let mut builder = Client::builder(config.base_url.as_str()).retry(config.retry.clone());
+        if let Some(token) = config.admin_token.as_deref() {
+            builder = builder.token(token);
+        }
+        if let Some(namespace) = config.namespace.as_deref() {
+            builder = builder.namespace(namespace);
+        }
+        builder
+    };
+    let client = builder().build().context("failed to build http client")?;
+    let output = Output::new(Format::resolve(cli.json)?, !cli.no_pager);
     match cli.command {
         Commands::Config => {
             println!("{}", config_path.display());
         }
-        Commands::Query { query } => {
-            let response = client
-                .get(format!("{}/v1/search", base_url))
-                .query(&[("query", query)])
-                .send()
-                .await
-                .context("failed to send query request")?;
-            handle_query_response(response).await?;
-        }
-        Commands::List => {
-            let response = client
-                .get(format!("{}/v1/bookmarks", base_url))
-                .send()
-                .await
-                .context("failed to send bookmarks request")?;
-            handle_bookmarks_response(response).await?;
-        }
-        Commands::Delete { id } => {
-            let token = config
-                .admin_token
-                .as_deref()
-                .context("admin_token missing in config; required for delete")?;
-            let mut headers = HeaderMap::new();
-            headers.insert(AUTHORIZATION, auth_header(token)?);
-
+        Commands::Query {
+            query,
+            all,
+            tag,
+            site,
+            after,
+            before,
+            sort,
+        } => {
+            let query = SearchQuery {
+                query,
+                tag,
+                site,
+                after,
+                before,
+                sort,
+                ..SearchQuery::default()
+            };
+            let response = if all {
+                client.search_all_with(query).await?
+            } else {
+                client.search_with(&query).await?
+            };
+            save_last_query(&config_path, &response)?;
+            output.show(&response, format_query_results)?;
+        }
+        Commands::List {
+            tag,
+            status,
+            matching,
+            sort,
+            page,
+            limit,
+        } => {
             let response = client
-                .delete(format!("{}/v1/bookmarks/{}", base_url, id))
-                .headers(headers)
-                .send()
-                .await
-                .context("failed to send delete request")?;
-            handle_delete_response(response, id).await?;
-        }
-        Commands::Ingest { file, urls } => {
+                .bookmarks_page(&BookmarksQuery {
+                    tag,
+                    status,
+                    q: matching,
+                    sort,
+                    page: Some(page),
+                    per_page: Some(limit),
+                })
+                .await?;
+            output.show(&response, format_bookmarks)?;
+        }
+        Commands::Lookup { url } => match client.lookup(&url).await? {
+            Some(bookmark) => println!(
+                "{}  {}  {}",
+                bookmark.id,
+                bookmark.status,
+                bookmark.title.as_deref().unwrap_or("Untitled")
+            ),
+            None => println!("Not saved."),
+        },
+        Commands::Namespaces => {
+            for namespace in client.namespaces().await?.results {
+                println!("{:<24}  {}", namespace.name, namespace.bookmarks);
+            }
+        }
+        Commands::Read { id, html: true } => {
+            std::io::stdout()
+                .write_all(&client.archived_page(id).await?)
+                .context("failed to write archived page")?;
+        }
+        Commands::Read { id, html: false } => {
+            let content = client.content(id).await?;
+            let mut output = String::new();
+            writeln!(
+                output,
+                "{}",
+                content.title.as_deref().unwrap_or(content.url.as_str())
+            )?;
+            writeln!(output, "{}", content.url)?;
+            writeln!(output)?;
+            writeln!(output, "{}", content.body)?;
+            emit(&output, !cli.no_pager)?;
+        }
+        Commands::Delete { id, file } => {
+            require_token(&config, "delete")?;
+            if let Some(path) = file {
+                let contents = fs::read_to_string(&path)
+                    .with_context(|| format!("failed to read delete file {}", path.display()))?;
+                let mut request = BulkDeleteRequest::default();
+                for line in contents
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty())
+                {
+                    match line.parse::<i64>() {
+                        Ok(id) => request.ids.push(id),
+                        Err(_) => request.urls.push(line.to_string()),
+                    }
+                }
+                if request.ids.is_empty() && request.urls.is_empty() {
+                    anyhow::bail!("delete file {} is empty", path.display());
+                }
+                // Longer lists go out in chunks the server accepts, with the results summed.
+                let mut response = BulkDeleteResponse {
+                    deleted: 0,
+                    not_found: Vec::new(),
+                };
+                let chunks =
+                    request
+                        .ids
+                        .chunks(BULK_DELETE_LIMIT)
+                        .map(|ids| BulkDeleteRequest {
+                            ids: ids.to_vec(),
+                            urls: Vec::new(),
+                        })
+                        .chain(request.urls.chunks(BULK_DELETE_LIMIT).map(|urls| {
+                            BulkDeleteRequest {
+                                ids: Vec::new(),
+                                urls: urls.to_vec(),
+                            }
+                        }));
+                for chunk in chunks {
+                    let chunk = client.bulk_delete(&chunk).await?;
+                    response.deleted += chunk.deleted;
+                    response.not_found.extend(chunk.not_found);
+                }
+                output.show(&response, format_bulk_delete)?;
+            } else if let Some(id) = id {
+                client.delete_bookmark(id).await?;
+                // The bulk response's shape, so scripts read one kind of delete result.
+                let response = BulkDeleteResponse {
+                    deleted: 1,
+                    not_found: Vec::new(),
+                };
+                output.show(&response, |_| Ok(format!("Deleted bookmark {}.\n", id)))?;
+            }
+        }
+        Commands::Refetch { id, status } => {
+            require_token(&config, "refetch")?;
+            let response = match id {
+                Some(id) => client.refetch_bookmark(id).await?,
+                None => client.refetch_bookmarks(status.as_deref()).await?,
+            };
+            println!("Queued {} bookmarks for refetch.", response.queued);
+        }
+        Commands::Tag { id, tags } => {
+            require_token(&config, "tag")?;
+            let response = client.set_tags(id, &tags).await?;
+            if response.tags.is_empty() {
+                println!("Bookmark {} has no tags.", id);
+            } else {
+                println!("Bookmark {} tagged {}.", id, response.tags.join(", "));
+            }
+        }
+        Commands::Tags => {
+            for tag in client.tags().await?.results {
+                println!("{:<24}  {}", tag.name, tag.bookmarks);
+            }
+        }
+        Commands::Publish { id } => {
+            require_token(&config, "publish")?;
+            client.publish_bookmark(id).await?;
+            println!("Published bookmark {}.", id);
+        }
+        Commands::Unpublish { id } => {
+            require_token(&config, "unpublish")?;
+            client.unpublish_bookmark(id).await?;
+            println!("Unpublished bookmark {}.", id);
+        }
+        Commands::Ingest { file, urls, tags } => {
             let mut ingest_urls = Vec::new();
             ingest_urls.extend(urls);
             if let Some(path) = file {
@@ -141,20 +767,403 @@ async fn main() -> Result<()> {
             if ingest_urls.is_empty() {
                 anyhow::bail!("provide at least one url or a non-empty file to ingest");
             }
-            let mut headers = HeaderMap::new();
-            if let Some(token) = config.admin_token.as_deref() {
-                headers.insert(AUTHORIZATION, auth_header(token)?);
+            output.show(
+                &client.ingest_tagged(&ingest_urls, &tags).await?,
+                format_ingest,
+            )?;
+        }
+        Commands::Import { path, format } => {
+            require_token(&config, "import")?;
+            print_json(&client.import_as(&path, format).await?)?;
+        }
+        Commands::ImportInstapaper { path, dry_run } => {
+            require_token(&config, "import-instapaper")?;
+            print_json(&client.import_instapaper(&path, dry_run).await?)?;
+        }
+        Commands::ImportOpml { path, from_now } => {
+            require_token(&config, "import-opml")?;
+            print_json(&client.import_opml(&path, from_now).await?)?;
+        }
+        Commands::SyncBrowser {
+            path,
+            source,
+            prune,
+        } => {
+            require_token(&config, "sync-browser")?;
+            print_json(&client.sync_browser(&path, source.as_deref(), prune).await?)?;
+        }
+        Commands::Export {
+            path,
+            output,
+            format,
+            ndjson,
+            content,
+        } => {
+            require_token(&config, "export")?;
+            let path = path.or(output).context("an output path is required")?;
+            match format.or(ndjson.then_some(ExportFormat::Ndjson)) {
+                Some(format) => {
+                    let (target, written) = client.export(&path, format, content).await?;
+                    println!("Wrote export to {} ({} bytes).", target.display(), written);
+                }
+                None if content => {
+                    anyhow::bail!("--content needs --format json or --format ndjson");
+                }
+                None => export_markdown(&client, &path).await?,
             }
-
-            let response = client
-                .post(format!("{}/v1/ingest/urls", base_url))
-                .headers(headers)
-                .json(&serde_json::json!({ "urls": ingest_urls }))
-                .send()
-                .await
-                .context("failed to send ingest request")?;
-            handle_response(response).await?;
         }
+        Commands::Backup { path } => {
+            require_token(&config, "backup")?;
+            let (target, written) = client.backup(&path).await?;
+            println!("Wrote backup to {} ({} bytes).", target.display(), written);
+        }
+        Commands::Restore { path } => {
+            require_token(&config, "restore")?;
+            client.restore(&path).await?;
+            println!("Restore staged; restart the server to apply it.");
+        }
+        Commands::User { command } => {
+            require_token(&config, "user management")?;
+            match command {
+                UserCommands::Add { username, admin } => {
+                    let user = client.create_user(&username, admin).await?;
+                    println!("Created user {} (id {}).", user.username, user.id);
+                    println!("Token (shown once): {}", user.token);
+                }
+                UserCommands::List => {
+                    for user in client.users().await?.results {
+                        println!(
+                            "{:>4}  {}{}",
+                            user.id,
+                            user.username,
+                            if user.is_admin { " (admin)" } else { "" }
+                        );
+                    }
+                }
+            }
+        }
+        Commands::Key { command } => {
+            require_token(&config, "key management")?;
+            match command {
+                KeyCommands::Add {
+                    name,
+                    scopes,
+                    bind_namespace,
+                } => {
+                    let request = CreateApiKeyRequest {
+                        name,
+                        scopes: (!scopes.is_empty()).then_some(scopes),
+                        namespace: bind_namespace,
+                    };
+                    let key = client.create_key(&request).await?;
+                    println!(
+                        "Created key {} (id {}, scopes {}, namespace {}).",
+                        key.name,
+                        key.id,
+                        join_scopes(&key.scopes),
+                        key.namespace.as_deref().unwrap_or("any")
+                    );
+                    println!("Token (shown once): {}", key.token);
+                }
+                KeyCommands::List => {
+                    print_keys(&client.keys().await?);
+                }
+                KeyCommands::Revoke { id } => {
+                    client.revoke_key(id).await?;
+                    println!("Revoked key {}.", id);
+                }
+                KeyCommands::Rotate { id, grace_secs } => {
+                    let key = client.rotate_key(id, grace_secs).await?;
+                    println!(
+                        "Rotated key {} (id {} -> {}); the old token expires {}.",
+                        key.name, key.previous_id, key.id, key.previous_expires_at
+                    );
+                    println!("Token (shown once): {}", key.token);
+                }
+            }
+        }
+        Commands::Reindex {
+            id,
+            all: _,
+            refetch,
+        } => {
+            require_token(&config, "reindex")?;
+            let stream = client.reindex(&ReindexRequest { id, refetch }).await?;
+            print_reindex_progress(stream).await?;
+        }
+        Commands::Readwise { command } => {
+            require_token(&config, "readwise sync")?;
+            match command {
+                ReadwiseCommands::Status => {
+                    print_json(&client.readwise_status().await?)?;
+                }
+                ReadwiseCommands::Connect {
+                    readwise_token,
+                    from_now,
+                } => {
+                    let status = client
+                        .connect_readwise(&ReadwiseConnectRequest {
+                            token: readwise_token,
+                            from_now,
+                        })
+                        .await?;
+                    println!(
+                        "Connected to Readwise; {} bookmarks to push.",
+                        status.pending
+                    );
+                }
+                ReadwiseCommands::Disconnect => {
+                    client.disconnect_readwise().await?;
+                    println!("Disconnected from Readwise.");
+                }
+            }
+        }
+        Commands::FeedReader { command } => {
+            require_token(&config, "feed reader import")?;
+            match command {
+                FeedReaderCommands::Status => {
+                    print_json(&client.feed_reader_status().await?)?;
+                }
+                FeedReaderCommands::Connect {
+                    kind,
+                    url,
+                    reader_token,
+                    username,
+                    from_now,
+                } => {
+                    client
+                        .connect_feed_reader(&FeedReaderConnectRequest {
+                            kind,
+                            url,
+                            username,
+                            token: reader_token,
+                            from_now,
+                        })
+                        .await?;
+                    println!("Connected; starred entries are imported in the background.");
+                }
+                FeedReaderCommands::Disconnect => {
+                    client.disconnect_feed_reader().await?;
+                    println!("Disconnected from the feed reader.");
+                }
+            }
+        }
+        Commands::Feeds { command } => {
+            require_token(&config, "feeds")?;
+            match command {
+                FeedCommands::Add { url, from_now } => {
+                    let feed = client
+                        .add_feed(&FeedCreateRequest { url, from_now })
+                        .await?;
+                    println!(
+                        "Subscribed to {} (id {}); new entries are saved in the background.",
+                        feed.title.as_deref().unwrap_or(&feed.url),
+                        feed.id
+                    );
+                }
+                FeedCommands::List => {
+                    print_feeds(&client.feeds().await?);
+                }
+                FeedCommands::Remove { id } => {
+                    client.remove_feed(id).await?;
+                    println!("Removed feed {}.", id);
+                }
+            }
+        }
+        Commands::Webhooks { command } => {
+            require_token(&config, "webhooks")?;
+            match command {
+                WebhookCommands::Add {
+                    url,
+                    secret,
+                    events,
+                } => {
+                    let webhook = client
+                        .add_webhook(&WebhookCreateRequest {
+                            url,
+                            secret,
+                            events: (!events.is_empty()).then_some(events),
+                        })
+                        .await?;
+                    println!(
+                        "Added webhook {} for {} ({}).",
+                        webhook.id,
+                        webhook.url,
+                        webhook.events.join(",")
+                    );
+                }
+                WebhookCommands::List => {
+                    print_webhooks(&client.webhooks().await?);
+                }
+                WebhookCommands::Remove { id } => {
+                    client.remove_webhook(id).await?;
+                    println!("Removed webhook {}.", id);
+                }
+            }
+        }
+        Commands::Wayback { command } => match command {
+            WaybackCommands::Submit { id } => {
+                require_token(&config, "wayback submit")?;
+                client.submit_wayback(id).await?;
+                println!("Queued bookmark {} for the Wayback Machine.", id);
+            }
+            WaybackCommands::Show { id } => {
+                print_json(&client.wayback(id).await?)?;
+            }
+        },
+        Commands::Job { command } => match command {
+            JobCommands::List => {
+                print_jobs(&client.jobs().await?);
+            }
+            JobCommands::Show { id } => {
+                print_json(&client.job(id).await?)?;
+            }
+            JobCommands::Cancel { id } => {
+                client.cancel_job(id).await?;
+                println!("Cancellation requested for job {}.", id);
+            }
+        },
+        Commands::Bootstrap { secret } => {
+            let key = client.bootstrap(&BootstrapRequest { secret }).await?;
+            println!("Created admin key {} (id {}).", key.name, key.id);
+            if config.admin_token.is_none() {
+                config.admin_token = Some(key.token);
+                write_config(&config_path, &config)?;
+                println!("Saved the token to {}.", config_path.display());
+            } else {
+                println!("Token (shown once): {}", key.token);
+            }
+        }
+        Commands::Status { id: None } => {
+            print_json(&client.status().await?)?;
+        }
+        Commands::Status { id: Some(id) } => {
+            let Some(bookmark) = client.bookmark(id).await? else {
+                anyhow::bail!("bookmark {} not found", id);
+            };
+            output.show(&bookmark, format_bookmark)?;
+        }
+        Commands::Stats => {
+            output.show(&client.stats().await?, format_stats)?;
+        }
+        Commands::Open { n } => {
+            let urls = load_last_query(&config_path)?;
+            let Some(url) = n.checked_sub(1).and_then(|index| urls.get(index)) else {
+                anyhow::bail!(
+                    "the last query has {} result{}",
+                    urls.len(),
+                    if urls.len() == 1 { "" } else { "s" }
+                );
+            };
+            webbrowser::open(url).with_context(|| format!("failed to open {}", url))?;
+            println!("Opened {}.", url);
+        }
+        Commands::Domains { hours, limit, sort } => {
+            print_domain_stats(&client.domain_stats(hours, limit, sort).await?);
+        }
+        Commands::Bench { command } => match command {
+            BenchCommands::Ingest {
+                docs,
+                words,
+                batch,
+                listen,
+                advertise,
+                namespace,
+                keep,
+                timeout_secs,
+            } => {
+                require_token(&config, "bench ingest")?;
+                bench::ingest(
+                    builder(),
+                    bench::IngestOptions {
+                        docs,
+                        words,
+                        batch,
+                        listen,
+                        advertise,
+                        namespace,
+                        keep,
+                        timeout: std::time::Duration::from_secs(timeout_secs),
+                    },
+                )
+                .await?;
+            }
+            BenchCommands::Search {
+                requests,
+                concurrency,
+                queries,
+            } => {
+                bench::search(
+                    client,
+                    bench::SearchOptions {
+                        requests,
+                        concurrency,
+                        queries,
+                    },
+                )
+                .await?;
+            }
+        },
+        Commands::Mcp => {
+            mcp::serve(client).await?;
+        }
+        Commands::DbMaintenance => {
+            print_json(&client.db_maintenance().await?)?;
+        }
+        Commands::Reconcile { check: true } => {
+            print_json(&client.consistency().await?)?;
+        }
+        Commands::Reconcile { check: false } => {
+            print_json(&client.reconcile().await?)?;
+        }
+        Commands::ReadOnly { command } => {
+            let status = match command {
+                None => client.read_only().await?,
+                Some(ReadOnlyCommands::On { message }) => {
+                    client
+                        .set_read_only(&SetReadOnlyRequest {
+                            enabled: true,
+                            message,
+                        })
+                        .await?
+                }
+                Some(ReadOnlyCommands::Off) => {
+                    client
+                        .set_read_only(&SetReadOnlyRequest {
+                            enabled: false,
+                            message: None,
+                        })
+                        .await?
+                }
+            };
+            print_json(&status)?;
+        }
+        Commands::Settings {
+            fetch_concurrency,
+            fetch_timeout_secs,
+            max_urls,
+            excerpt_len,
+            slow_query_ms,
+            slow_search_ms,
+            slow_index_commit_ms,
+        } => {
+            let update = UpdateSettingsRequest {
+                fetch_concurrency,
+                fetch_timeout_secs,
+                max_urls,
+                excerpt_len,
+                slow_query_ms,
+                slow_search_ms,
+                slow_index_commit_ms,
+            };
+            let settings = if update == UpdateSettingsRequest::default() {
+                client.settings().await?
+            } else {
+                client.update_settings(&update).await?
+            };
+            print_json(&settings)?;
+        }
+        Commands::Serve { .. } => unreachable!("serve runs before the client config is loaded"),
     }
 
     Ok(())
@@ -203,174 +1212,100 @@ fn write_config(path: &Path, config: &Config) -> Result<()> {
     Ok(())
 }
 
-fn auth_header(token: &str) -> Result<HeaderValue> {
-    let value = if token.starts_with("Bearer ") {
-        token.to_string()
-    } else {
-        format!("Bearer {}", token)
-    };
-    HeaderValue::from_str(&value).context("invalid admin token")
+/// The result URLs of the last `odin query`, in order, kept beside the config file for
+/// `odin open`.
+fn last_query_path(config_path: &Path) -> PathBuf {
+    config_path.with_file_name("last_query.json")
 }
 
-async fn handle_response(response: reqwest::Response) -> Result<()> {
-    let status = response.status();
-    let body = response.text().await.context("failed to read response")?;
-    if !status.is_success() {
-        anyhow::bail!("request failed with status {}: {}", status, body);
-    }
-    println!("{}", body);
-    Ok(())
+fn save_last_query(config_path: &Path, response: &SearchResponse) -> Result<()> {
+    let urls = response
+        .results
+        .iter()
+        .map(|item| item.url.as_str())
+        .collect::<Vec<_>>();
+    let path = last_query_path(config_path);
+    let raw = serde_json::to_string(&urls).context("failed to serialize query results")?;
+    fs::write(&path, raw).with_context(|| format!("failed to write {}", path.display()))
 }
 
-async fn handle_query_response(response: reqwest::Response) -> Result<()> {
-    let status = response.status();
-    let body = response.text().await.context("failed to read response")?;
-    if !status.is_success() {
-        anyhow::bail!("request failed with status {}: {}", status, body);
-    }
-    let response: SearchResponse =
-        serde_json::from_str(&body).context("failed to parse search response")?;
-
-    if response.results.is_empty() {
-        println!("No results.");
-        return Ok(());
+fn load_last_query(config_path: &Path) -> Result<Vec<String>> {
+    let path = last_query_path(config_path);
+    if !path.exists() {
+        anyhow::bail!("no saved query results; run `odin query` first");
     }
+    let raw =
+        fs::read_to_string(&path).with_context(|| format!("failed to read {}", path.display()))?;
+    serde_json::from_str(&raw).with_context(|| format!("failed to parse {}", path.display()))
+}
 
-    println!(
-        "Found {} result{}.",
-        response.total_hits,
-        if response.total_hits == 1 { "" } else { "s" }
-    );
-
-    for (index, item) in response.results.iter().enumerate() {
-        let title = item
-            .title
-            .as_deref()
-            .map(str::trim)
-            .filter(|value| !value.is_empty())
-            .unwrap_or(item.url.as_str());
-        let label = if item.url.trim().is_empty() {
-            title.to_string()
-        } else {
-            hyperlink(&item.url, title)
-        };
-        println!("{:>2}. {}", index + 1, label);
+fn require_token(config: &Config, operation: &str) -> Result<()> {
+    if config.admin_token.is_none() {
+        anyhow::bail!("admin_token missing in config; required for {}", operation);
     }
-
     Ok(())
 }
 
-async fn handle_bookmarks_response(response: reqwest::Response) -> Result<()> {
-    let status = response.status();
-    let body = response.text().await.context("failed to read response")?;
-    if !status.is_success() {
-        anyhow::bail!("request failed with status {}: {}", status, body);
-    }
-    let response: BookmarksResponse =
-        serde_json::from_str(&body).context("failed to parse bookmarks response")?;
-
-    if response.results.is_empty() {
-        println!("No bookmarks.");
-        return Ok(());
-    }
-
-    let id_width = response
-        .results
-        .iter()
-        .map(|item| item.id.to_string().len())
-        .max()
-        .unwrap_or(2)
-        .max("ID".len());
-    let status_width = response
-        .results
-        .iter()
-        .map(|item| item.status.len())
-        .max()
-        .unwrap_or(6)
-        .max("Status".len());
-    let mut title_width = response
-        .results
-        .iter()
-        .map(|item| {
-            item.title
-                .as_deref()
-                .map(str::trim)
-                .filter(|value| !value.is_empty())
-                .unwrap_or(item.url.as_str())
-                .len()
-        })
-        .max()
-        .unwrap_or(5)
-        .max("Title".len());
-    let title_width_cap = 80usize;
-    if title_width > title_width_cap {
-        title_width = title_width_cap;
+async fn print_reindex_progress(mut stream: ReindexStream) -> Result<()> {
+    let mut indexed = 0usize;
+    let mut failed = 0usize;
+    while let Some(progress) = stream
+        .next()
+        .await
+        .context("failed to read reindex progress")?
+    {
+        if progress.status == "indexed" {
+            indexed += 1;
+        } else {
+            failed += 1;
+        }
+        let mut line = format!(
+            "[{}/{}] {:<7}  {}  {}",
+            progress.done, progress.total, progress.status, progress.id, progress.url
+        );
+        if let Some(error) = progress.error.as_deref() {
+            line.push_str(&format!(" ({})", truncate_with_ellipsis(error, 80)));
+        }
+        println!("{}", line);
     }
 
     println!(
-        "{:>id_width$}  {:<status_width$}  {:<title_width$}",
-        "ID",
-        "Status",
-        "Title"
+        "Reindexed {} bookmark{}, {} failed.",
+        indexed,
+        if indexed == 1 { "" } else { "s" },
+        failed
     );
-    println!(
-        "{:-<id_width$}  {:-<status_width$}  {:-<title_width$}",
-        "",
-        "",
-        ""
-    );
-
-    for (index, item) in response.results.iter().enumerate() {
-        let title = item
-            .title
-            .as_deref()
-            .map(str::trim)
-            .filter(|value| !value.is_empty())
-            .unwrap_or(item.url.as_str());
-        let title = truncate_with_ellipsis(title, title_width);
-        println!(
-            "{:>id_width$}  {:<status_width$}  {:<title_width$}",
-            item.id,
-            item.status,
-            title
-        );
-    }
-
     Ok(())
 }
 
-async fn handle_delete_response(response: reqwest::Response, id: i64) -> Result<()> {
-    let status = response.status();
-    let body = response.text().await.context("failed to read response")?;
-    if status == reqwest::StatusCode::NO_CONTENT {
-        println!("Deleted bookmark {}.", id);
+/// Download the notes zip, then unpack it unless the path names a zip. Unpacking over an
+/// earlier export replaces its notes and leaves other files alone.
+async fn export_markdown(client: &Client, path: &Path) -> Result<()> {
+    let is_zip = path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("zip"));
+    if is_zip {
+        let (target, written) = client.export_markdown(path).await?;
+        println!("Wrote notes to {} ({} bytes).", target.display(), written);
         return Ok(());
     }
-    if !status.is_success() {
-        anyhow::bail!("request failed with status {}: {}", status, body);
-    }
-    if body.trim().is_empty() {
-        println!("Deleted bookmark {}.", id);
-        return Ok(());
-    }
-    println!("{}", body);
-    Ok(())
-}
-
-fn hyperlink(url: &str, text: &str) -> String {
-    if std::io::stdout().is_terminal() {
-        format!("\u{1b}]8;;{}\u{1b}\\{}\u{1b}]8;;\u{1b}\\", url, text)
-    } else {
-        text.to_string()
-    }
-}
 
-fn truncate_with_ellipsis(value: &str, max_width: usize) -> String {
-    if value.len() <= max_width {
-        return value.to_string();
-    }
-    if max_width <= 3 {
-        return value.chars().take(max_width).collect();
-    }
-    format!("{}...", value.chars().take(max_width - 3).collect::<String>())
+    fs::create_dir_all(path).with_context(|| format!("failed to create {}", path.display()))?;
+    let (archive, _) = client.export_markdown(path).await?;
+    let directory = path.to_path_buf();
+    let notes = tokio::task::spawn_blocking(move || -> Result<usize> {
+        let file = fs::File::open(&archive)
+            .with_context(|| format!("failed to open {}", archive.display()))?;
+        let mut zip = zip::ZipArchive::new(file).context("failed to read the export")?;
+        let notes = zip.len();
+        let extracted = zip
+            .extract(&directory)
+            .context("failed to unpack the export");
+        fs::remove_file(&archive)
+            .with_context(|| format!("failed to remove {}", archive.display()))?;
+        extracted.map(|_| notes)
+    })
+    .await??;
+    println!("Wrote {} notes to {}.", notes, path.display());
+    Ok(())
 }
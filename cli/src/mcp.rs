@@ -0,0 +1,227 @@
+//! `odin mcp`: a Model Context Protocol server over stdio, so assistants can search,
+//! read, and save bookmarks through the configured server and token.
+
+use std::fmt::Write as _;
+
+use anyhow::{Context, Result};
+use odin_client::Client;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+/// The newest revision this server speaks; older ones a client asks for are echoed.
+const PROTOCOL_VERSION: &str = "2025-06-18";
+const SUPPORTED_VERSIONS: &[&str] = &["2024-11-05", "2025-03-26", "2025-06-18"];
+const DEFAULT_SEARCH_LIMIT: u32 = 10;
+const MAX_SEARCH_LIMIT: u32 = 50;
+
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const PARSE_ERROR: i64 = -32700;
+
+#[derive(Deserialize)]
+struct Request {
+    /// Absent on notifications, which get no response.
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Deserialize)]
+struct ToolCall {
+    name: String,
+    #[serde(default)]
+    arguments: Value,
+}
+
+#[derive(Deserialize)]
+struct SearchArgs {
+    query: String,
+    limit: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct ContentArgs {
+    id: Option<i64>,
+    url: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct SaveArgs {
+    urls: Vec<String>,
+}
+
+/// Answer JSON-RPC messages, one per line on stdin, until stdin closes.
+pub async fn serve(client: Client) -> Result<()> {
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    let mut stdout = tokio::io::stdout();
+    while let Some(line) = lines.next_line().await.context("failed to read stdin")? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str::<Request>(&line) {
+            Ok(request) => handle(&client, request).await,
+            Err(err) => Some(error(Value::Null, PARSE_ERROR, &err.to_string())),
+        };
+        if let Some(response) = response {
+            let mut out = serde_json::to_vec(&response)?;
+            out.push(b'\n');
+            stdout.write_all(&out).await?;
+            stdout.flush().await?;
+        }
+    }
+    Ok(())
+}
+
+async fn handle(client: &Client, request: Request) -> Option<Value> {
+    let id = request.id?;
+    let result = match request.method.as_str() {
+        "initialize" => {
+            let requested = request.params["protocolVersion"].as_str().unwrap_or("");
+            let version = SUPPORTED_VERSIONS
+                .iter()
+                .find(|version| **version == requested)
+                .unwrap_or(&PROTOCOL_VERSION);
+            json!({
+                "protocolVersion": version,
+                "capabilities": { "tools": {} },
+                "serverInfo": { "name": "odin", "version": env!("CARGO_PKG_VERSION") },
+                "instructions": "Search and read the user's saved web pages, and save new links to them.",
+            })
+        }
+        "ping" => json!({}),
+        "tools/list" => json!({ "tools": tools() }),
+        "tools/call" => match serde_json::from_value::<ToolCall>(request.params) {
+            Ok(call) => call_tool(client, call).await,
+            Err(err) => return Some(error(id, INVALID_PARAMS, &err.to_string())),
+        },
+        method => {
+            return Some(error(
+                id,
+                METHOD_NOT_FOUND,
+                &format!("unknown method {}", method),
+            ));
+        }
+    };
+    Some(json!({ "jsonrpc": "2.0", "id": id, "result": result }))
+}
+
+fn tools() -> Value {
+    json!([
+        {
+            "name": "search",
+            "description": "Full-text search of the user's saved pages. Returns titles, URLs, bookmark ids, and excerpts, best match first.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "query": { "type": "string", "description": "Search words" },
+                    "limit": { "type": "integer", "minimum": 1, "maximum": MAX_SEARCH_LIMIT, "description": "Results to return (default 10)" },
+                },
+                "required": ["query"],
+            },
+        },
+        {
+            "name": "get_content",
+            "description": "The text of a saved page as extracted when it was fetched, by bookmark id (from search) or URL.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "id": { "type": "integer", "description": "Bookmark id" },
+                    "url": { "type": "string", "description": "Saved URL" },
+                },
+            },
+        },
+        {
+            "name": "save_urls",
+            "description": "Save links to the user's archive. Pages are fetched and indexed in the background; already saved URLs are skipped.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "urls": { "type": "array", "items": { "type": "string" }, "minItems": 1 },
+                },
+                "required": ["urls"],
+            },
+        },
+    ])
+}
+
+/// Tool failures are results with `isError`, so the model sees what went wrong.
+async fn call_tool(client: &Client, call: ToolCall) -> Value {
+    let output = match call.name.as_str() {
+        "search" => search(client, call.arguments).await,
+        "get_content" => content(client, call.arguments).await,
+        "save_urls" => save(client, call.arguments).await,
+        name => Err(anyhow::anyhow!("unknown tool {}", name)),
+    };
+    let (text, is_error) = match output {
+        Ok(text) => (text, false),
+        Err(err) => (format!("{:#}", err), true),
+    };
+    json!({ "content": [{ "type": "text", "text": text }], "isError": is_error })
+}
+
+async fn search(client: &Client, arguments: Value) -> Result<String> {
+    let args: SearchArgs = serde_json::from_value(arguments).context("invalid arguments")?;
+    let limit = args
+        .limit
+        .unwrap_or(DEFAULT_SEARCH_LIMIT)
+        .clamp(1, MAX_SEARCH_LIMIT);
+    let response = client.search_page(&args.query, 1, limit).await?;
+    if response.results.is_empty() {
+        return Ok(format!("No saved pages match \"{}\".", args.query));
+    }
+    let mut text = format!(
+        "{} of {} matches:\n",
+        response.results.len(),
+        response.total_hits
+    );
+    for result in &response.results {
+        writeln!(
+            text,
+            "\n[{}] {}\n{}",
+            result.bookmark_id,
+            result.title.as_deref().unwrap_or("Untitled"),
+            result.url
+        )?;
+        if let Some(excerpt) = result.excerpt.as_deref() {
+            writeln!(text, "{}", excerpt)?;
+        }
+    }
+    Ok(text)
+}
+
+async fn content(client: &Client, arguments: Value) -> Result<String> {
+    let args: ContentArgs = serde_json::from_value(arguments).context("invalid arguments")?;
+    let id = match (args.id, args.url) {
+        (Some(id), _) => id,
+        (None, Some(url)) => match client.lookup(&url).await? {
+            Some(bookmark) => bookmark.id,
+            None => anyhow::bail!("{} is not saved", url),
+        },
+        (None, None) => anyhow::bail!("pass a bookmark id or url"),
+    };
+    let content = client.content(id).await?;
+    Ok(format!(
+        "{}\n{}\n\n{}",
+        content.title.as_deref().unwrap_or("Untitled"),
+        content.url,
+        content.body
+    ))
+}
+
+async fn save(client: &Client, arguments: Value) -> Result<String> {
+    let args: SaveArgs = serde_json::from_value(arguments).context("invalid arguments")?;
+    if args.urls.is_empty() {
+        anyhow::bail!("pass at least one url");
+    }
+    let response = client.ingest(&args.urls).await?;
+    Ok(format!(
+        "Saved {} new link(s); {} were already saved or invalid. Pages are fetched in the background.",
+        response.accepted, response.deduped
+    ))
+}
+
+fn error(id: Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
@@ -0,0 +1,110 @@
+//! Where command results go: text for people, paged when it would overflow the terminal,
+//! or one line of JSON per response for scripts (`--json` or `ODIN_OUTPUT=json`).
+
+use std::io::{IsTerminal, Write as _};
+use std::process::{Command, Stdio};
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use terminal_size::{Height, terminal_size};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Format {
+    Text,
+    Json,
+}
+
+impl Format {
+    /// `--json` when passed, otherwise `ODIN_OUTPUT` (`json` or `text`), otherwise text.
+    pub fn resolve(json: bool) -> Result<Self> {
+        if json {
+            return Ok(Self::Json);
+        }
+        let Ok(value) = std::env::var("ODIN_OUTPUT") else {
+            return Ok(Self::Text);
+        };
+        match value.trim().to_ascii_lowercase().as_str() {
+            "" | "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            other => anyhow::bail!("ODIN_OUTPUT must be json or text, not {}", other),
+        }
+    }
+}
+
+pub struct Output {
+    format: Format,
+    pager: bool,
+}
+
+impl Output {
+    pub fn new(format: Format, pager: bool) -> Self {
+        Self { format, pager }
+    }
+
+    /// Print `value` as JSON, or the text `render` makes of it.
+    pub fn show<T: Serialize>(
+        &self,
+        value: &T,
+        render: impl FnOnce(&T) -> Result<String>,
+    ) -> Result<()> {
+        match self.format {
+            Format::Json => print_json(value),
+            Format::Text => emit(&render(value)?, self.pager),
+        }
+    }
+}
+
+pub fn print_json<T: Serialize>(value: &T) -> Result<()> {
+    println!(
+        "{}",
+        serde_json::to_string(value).context("failed to serialize response")?
+    );
+    Ok(())
+}
+
+/// Print output, piping it through `$PAGER` when it would overflow the terminal.
+pub fn emit(output: &str, use_pager: bool) -> Result<()> {
+    if use_pager
+        && needs_pager(output)
+        && let Some(mut child) = spawn_pager()
+    {
+        if let Some(mut stdin) = child.stdin.take()
+            && let Err(err) = stdin.write_all(output.as_bytes())
+            && err.kind() != std::io::ErrorKind::BrokenPipe
+        {
+            return Err(err).context("failed to write to pager");
+        }
+        child.wait().context("failed to wait for pager")?;
+        return Ok(());
+    }
+    print!("{}", output);
+    Ok(())
+}
+
+fn needs_pager(output: &str) -> bool {
+    if !std::io::stdout().is_terminal() {
+        return false;
+    }
+    let Some((_, Height(rows))) = terminal_size() else {
+        return false;
+    };
+    output.lines().count() >= rows as usize
+}
+
+fn spawn_pager() -> Option<std::process::Child> {
+    let pager = std::env::var("PAGER")
+        .ok()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+        .unwrap_or_else(|| "less".to_string());
+    if pager == "cat" {
+        return None;
+    }
+
+    let mut command = Command::new("sh");
+    command.arg("-c").arg(&pager).stdin(Stdio::piped());
+    if std::env::var_os("LESS").is_none() {
+        command.env("LESS", "FRX");
+    }
+    command.spawn().ok()
+}
@@ -0,0 +1,873 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use reqwest::header::{AUTHORIZATION, CONTENT_DISPOSITION, CONTENT_TYPE, HeaderMap, HeaderValue};
+use reqwest::{RequestBuilder, Response, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+
+use crate::error::{Error, Result};
+use crate::types::*;
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+const LONG_OPERATION_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+const SEARCH_PAGE_SIZE: u32 = 50;
+/// The server's largest bookmark page.
+const BOOKMARKS_PAGE_SIZE: u32 = 500;
+/// Hits the server pages through before rejecting deeper pages.
+const SEARCH_MAX_DEPTH: u32 = 1000;
+
+/// Retry policy for idempotent requests.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RetryConfig {
+    pub max_attempts: u32,
+    pub base_delay_ms: u64,
+    pub max_delay_ms: u64,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay_ms: 250,
+            max_delay_ms: 5_000,
+        }
+    }
+}
+
+pub struct ClientBuilder {
+    base_url: String,
+    token: Option<String>,
+    namespace: Option<String>,
+    retry: RetryConfig,
+    timeout: Duration,
+}
+
+impl ClientBuilder {
+    /// API key sent as a bearer token on every request.
+    pub fn token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// Namespace sent as `x-odin-namespace`; unset uses the key's namespace or the server default.
+    pub fn namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.namespace = Some(namespace.into());
+        self
+    }
+
+    pub fn retry(mut self, retry: RetryConfig) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Timeout for ordinary requests; imports, backups, restores, and reindexes allow 30 minutes.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn build(self) -> Result<Client> {
+        let mut headers = HeaderMap::new();
+        if let Some(token) = self.token.as_deref() {
+            let value = if token.starts_with("Bearer ") {
+                token.to_string()
+            } else {
+                format!("Bearer {}", token)
+            };
+            let value = HeaderValue::from_str(&value).map_err(|_| Error::InvalidHeader("token"))?;
+            headers.insert(AUTHORIZATION, value);
+        }
+        if let Some(namespace) = self.namespace.as_deref() {
+            let value =
+                HeaderValue::from_str(namespace).map_err(|_| Error::InvalidHeader("namespace"))?;
+            headers.insert("x-odin-namespace", value);
+        }
+        let http = reqwest::Client::builder()
+            .default_headers(headers)
+            .timeout(self.timeout)
+            .build()?;
+        Ok(Client {
+            http,
+            base_url: self.base_url.trim_end_matches('/').to_string(),
+            retry: self.retry,
+        })
+    }
+}
+
+/// A handle to one odin server, cheap to clone.
+#[derive(Clone)]
+pub struct Client {
+    http: reqwest::Client,
+    base_url: String,
+    retry: RetryConfig,
+}
+
+impl Client {
+    pub fn builder(base_url: impl Into<String>) -> ClientBuilder {
+        ClientBuilder {
+            base_url: base_url.into(),
+            token: None,
+            namespace: None,
+            retry: RetryConfig::default(),
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// The first page of hits, sized by the server's default.
+    pub async fn search(&self, query: &str) -> Result<SearchResponse> {
+        self.search_tagged(query, None).await
+    }
+
+    /// Like [`Self::search`], only among bookmarks with `tag` when one is given.
+    pub async fn search_tagged(&self, query: &str, tag: Option<&str>) -> Result<SearchResponse> {
+        self.search_with(&SearchQuery {
+            query: query.to_string(),
+            tag: tag.map(str::to_string),
+            ..SearchQuery::default()
+        })
+        .await
+    }
+
+    pub async fn search_page(
+        &self,
+        query: &str,
+        page: u32,
+        per_page: u32,
+    ) -> Result<SearchResponse> {
+        self.search_page_tagged(query, None, page, per_page).await
+    }
+
+    pub async fn search_page_tagged(
+        &self,
+        query: &str,
+        tag: Option<&str>,
+        page: u32,
+        per_page: u32,
+    ) -> Result<SearchResponse> {
+        self.search_with(&SearchQuery {
+            query: query.to_string(),
+            tag: tag.map(str::to_string),
+            page: Some(page),
+            per_page: Some(per_page),
+            ..SearchQuery::default()
+        })
+        .await
+    }
+
+    /// One page of hits for `query` with its filters and order.
+    pub async fn search_with(&self, query: &SearchQuery) -> Result<SearchResponse> {
+        let request = self.get("/v1/search").query(query);
+        read_json(self.send_idempotent(request).await?).await
+    }
+
+    /// Every hit up to the server's depth limit of 1000, fetched page by page.
+    pub async fn search_all(&self, query: &str) -> Result<SearchResponse> {
+        self.search_all_tagged(query, None).await
+    }
+
+    /// Like [`Self::search_all`], only among bookmarks with `tag` when one is given.
+    pub async fn search_all_tagged(
+        &self,
+        query: &str,
+        tag: Option<&str>,
+    ) -> Result<SearchResponse> {
+        self.search_all_with(SearchQuery {
+            query: query.to_string(),
+            tag: tag.map(str::to_string),
+            ..SearchQuery::default()
+        })
+        .await
+    }
+
+    /// Like [`Self::search_all`], with the filters and order of `query`; its page
+    /// and page size are replaced.
+    pub async fn search_all_with(&self, mut query: SearchQuery) -> Result<SearchResponse> {
+        query.page = Some(1);
+        query.per_page = Some(SEARCH_PAGE_SIZE);
+        let mut response = self.search_with(&query).await?;
+        let mut page = 1;
+        while (response.results.len() as u64) < response.total_hits
+            && (page + 1) * SEARCH_PAGE_SIZE <= SEARCH_MAX_DEPTH
+        {
+            page += 1;
+            query.page = Some(page);
+            let next = self.search_with(&query).await?;
+            if next.results.is_empty() {
+                break;
+            }
+            response.results.extend(next.results);
+        }
+        Ok(response)
+    }
+
+    /// Every bookmark, read page by page.
+    pub async fn bookmarks(&self) -> Result<BookmarksResponse> {
+        self.bookmarks_all(BookmarksQuery::default()).await
+    }
+
+    /// Every bookmark with `tag`, read page by page.
+    pub async fn bookmarks_tagged(&self, tag: &str) -> Result<BookmarksResponse> {
+        self.bookmarks_all(BookmarksQuery {
+            tag: Some(tag.to_string()),
+            ..BookmarksQuery::default()
+        })
+        .await
+    }
+
+    /// One page of bookmarks matching `query`.
+    pub async fn bookmarks_page(&self, query: &BookmarksQuery) -> Result<BookmarksResponse> {
+        let request = self.get("/v1/bookmarks").query(query);
+        read_json(self.send_idempotent(request).await?).await
+    }
+
+    /// Every page of `query`; a server without pagination answers with one page.
+    async fn bookmarks_all(&self, mut query: BookmarksQuery) -> Result<BookmarksResponse> {
+        query.page = Some(1);
+        query.per_page = Some(BOOKMARKS_PAGE_SIZE);
+        let mut response = self.bookmarks_page(&query).await?;
+        while let Some(total) = response.total {
+            if response.results.len() as i64 >= total {
+                break;
+            }
+            query.page = query.page.map(|page| page + 1);
+            let next = self.bookmarks_page(&query).await?;
+            if next.results.is_empty() {
+                break;
+            }
+            response.results.extend(next.results);
+        }
+        response.page = None;
+        response.per_page = None;
+        Ok(response)
+    }
+
+    /// Tags in the namespace with how many bookmarks have each.
+    pub async fn tags(&self) -> Result<TagsResponse> {
+        self.get_json("/v1/tags").await
+    }
+
+    /// Replace a bookmark's tags; an empty list removes them all.
+    pub async fn set_tags(&self, id: i64, tags: &[String]) -> Result<BookmarkTagsResponse> {
+        let request = self
+            .http
+            .put(self.url(&format!("/v1/bookmarks/{}/tags", id)))
+            .json(&serde_json::json!({ "tags": tags }));
+        read_json(self.send_idempotent(request).await?).await
+    }
+
+    /// The bookmark with `id`, or `None` when the caller has no such bookmark.
+    pub async fn bookmark(&self, id: i64) -> Result<Option<BookmarkListItem>> {
+        let request = self.get(&format!("/v1/bookmarks/{}", id));
+        match self.send_idempotent(request).await {
+            Ok(response) => read_json(response).await.map(Some),
+            Err(err) if err.status() == Some(StatusCode::NOT_FOUND) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// The bookmark saved for `url`, or `None` when it is not saved.
+    pub async fn lookup(&self, url: &str) -> Result<Option<BookmarkListItem>> {
+        let request = self.get("/v1/bookmarks/lookup").query(&[("url", url)]);
+        match self.send_idempotent(request).await {
+            Ok(response) => read_json(response).await.map(Some),
+            Err(err) if err.status() == Some(StatusCode::NOT_FOUND) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    pub async fn namespaces(&self) -> Result<NamespacesResponse> {
+        self.get_json("/v1/namespaces").await
+    }
+
+    /// The text extracted at a bookmark's last fetch.
+    pub async fn content(&self, id: i64) -> Result<BookmarkContentResponse> {
+        self.get_json(&format!("/v1/bookmarks/{}/content", id))
+            .await
+    }
+
+    /// The page as it was last fetched, in the encoding the site served it in.
+    pub async fn archived_page(&self, id: i64) -> Result<Vec<u8>> {
+        let request = self
+            .get(&format!("/v1/bookmarks/{}/content", id))
+            .query(&[("format", "html")]);
+        let response = self.send_idempotent(request).await?;
+        Ok(response.bytes().await?.to_vec())
+    }
+
+    pub async fn delete_bookmark(&self, id: i64) -> Result<()> {
+        let request = self.http.delete(self.url(&format!("/v1/bookmarks/{}", id)));
+        self.send(request).await?;
+        Ok(())
+    }
+
+    /// Mark a bookmark public, which publishes it to the server's fediverse followers.
+    pub async fn publish_bookmark(&self, id: i64) -> Result<()> {
+        let request = self
+            .http
+            .put(self.url(&format!("/v1/bookmarks/{}/public", id)));
+        self.send(request).await?;
+        Ok(())
+    }
+
+    /// Make a bookmark private again; followers are asked to delete its post.
+    pub async fn unpublish_bookmark(&self, id: i64) -> Result<()> {
+        let request = self
+            .http
+            .delete(self.url(&format!("/v1/bookmarks/{}/public", id)));
+        self.send(request).await?;
+        Ok(())
+    }
+
+    /// The bookmark's Wayback Machine capture, if one was requested.
+    pub async fn wayback(&self, id: i64) -> Result<WaybackSnapshot> {
+        self.get_json(&format!("/v1/bookmarks/{}/wayback", id))
+            .await
+    }
+
+    /// Ask the server to capture the bookmark with the Wayback Machine's Save Page Now.
+    pub async fn submit_wayback(&self, id: i64) -> Result<WaybackSnapshot> {
+        let request = self.post(&format!("/v1/bookmarks/{}/wayback", id));
+        read_json(self.send(request).await?).await
+    }
+
+    pub async fn bulk_delete(&self, request: &BulkDeleteRequest) -> Result<BulkDeleteResponse> {
+        let request = self.post("/v1/bookmarks/bulk-delete").json(request);
+        read_json(self.send(request).await?).await
+    }
+
+    /// Fetch and index a bookmark again in place.
+    pub async fn refetch_bookmark(&self, id: i64) -> Result<RefetchResponse> {
+        let request = self.post(&format!("/v1/bookmarks/{}/refetch", id));
+        read_json(self.send(request).await?).await
+    }
+
+    /// Refetch every bookmark in the namespace, or only those with `status`
+    /// (`indexed`, `partial`, or `failed`).
+    pub async fn refetch_bookmarks(&self, status: Option<&str>) -> Result<RefetchResponse> {
+        let mut request = self.post("/v1/bookmarks/refetch");
+        if let Some(status) = status {
+            request = request.query(&[("status", status)]);
+        }
+        read_json(self.send(request).await?).await
+    }
+
+    /// Queue URLs for ingest. Retried like a read while the queue is full, since the server
+    /// dedupes re-sent URLs.
+    pub async fn ingest(&self, urls: &[String]) -> Result<IngestUrlsResponse> {
+        self.ingest_tagged(urls, &[]).await
+    }
+
+    /// Like [`Self::ingest`], giving each new bookmark `tags`.
+    pub async fn ingest_tagged(
+        &self,
+        urls: &[String],
+        tags: &[String],
+    ) -> Result<IngestUrlsResponse> {
+        let request = self
+            .post("/v1/ingest/urls")
+            .json(&serde_json::json!({ "urls": urls, "tags": tags }));
+        read_json(self.send_idempotent(request).await?).await
+    }
+
+    /// Upload a bookmarks export (browser HTML, CSV, or a URL list) and ingest every URL in it.
+    pub async fn import(&self, path: &Path) -> Result<ImportResponse> {
+        self.import_as(path, ImportFormat::Auto).await
+    }
+
+    /// Like [`Self::import`], for an export the server should parse as `format`.
+    pub async fn import_as(&self, path: &Path, format: ImportFormat) -> Result<ImportResponse> {
+        let file = open(path).await?;
+        let request = self
+            .post("/v1/import")
+            .query(&[("format", format.as_str())])
+            .header(CONTENT_TYPE, "application/octet-stream")
+            .timeout(LONG_OPERATION_TIMEOUT)
+            .body(file);
+        read_json(self.send(request).await?).await
+    }
+
+    /// Upload Instapaper's CSV export; with `dry_run` the server only reports what it
+    /// would import.
+    pub async fn import_instapaper(
+        &self,
+        path: &Path,
+        dry_run: bool,
+    ) -> Result<InstapaperImportResponse> {
+        let file = open(path).await?;
+        let request = self
+            .post("/v1/import/instapaper")
+            .query(&[("dry_run", dry_run)])
+            .header(CONTENT_TYPE, "text/csv")
+            .timeout(LONG_OPERATION_TIMEOUT)
+            .body(file);
+        read_json(self.send(request).await?).await
+    }
+
+    /// Upload an RSS reader's OPML export: its feeds are subscribed to (with `from_now`,
+    /// from their next entry on) and its links saved.
+    pub async fn import_opml(&self, path: &Path, from_now: bool) -> Result<OpmlImportResponse> {
+        let file = open(path).await?;
+        let request = self
+            .post("/v1/import/opml")
+            .query(&[("from_now", from_now)])
+            .header(CONTENT_TYPE, "text/x-opml")
+            .timeout(LONG_OPERATION_TIMEOUT)
+            .body(file);
+        read_json(self.send(request).await?).await
+    }
+
+    /// Upload a browser's bookmarks file (Chrome's `Bookmarks` or Firefox's
+    /// `places.sqlite`) for the server to sync against the last upload from `source`.
+    pub async fn sync_browser(
+        &self,
+        path: &Path,
+        source: Option<&str>,
+        prune: bool,
+    ) -> Result<BrowserSyncResponse> {
+        let file = open(path).await?;
+        let mut request = self
+            .post("/v1/import/browser")
+            .query(&[("prune", prune)])
+            .header(CONTENT_TYPE, "application/octet-stream")
+            .timeout(LONG_OPERATION_TIMEOUT)
+            .body(file);
+        if let Some(source) = source {
+            request = request.query(&[("source", source)]);
+        }
+        read_json(self.send(request).await?).await
+    }
+
+    /// Download a database and index snapshot to `path`; a directory gets the server's
+    /// timestamped file name. Returns the file written and its size.
+    pub async fn backup(&self, path: &Path) -> Result<(PathBuf, u64)> {
+        let request = self.get("/v1/admin/backup").timeout(LONG_OPERATION_TIMEOUT);
+        self.download(request, path, "odin-backup.tar.gz").await
+    }
+
+    /// Download the caller's bookmarks as a zip of Markdown notes, written like
+    /// [`Self::backup`].
+    pub async fn export_markdown(&self, path: &Path) -> Result<(PathBuf, u64)> {
+        let request = self
+            .get("/v1/export/markdown")
+            .timeout(LONG_OPERATION_TIMEOUT);
+        self.download(request, path, "odin-markdown.zip").await
+    }
+
+    /// Download every bookmark as newline-delimited JSON, with extracted text when
+    /// `content` is set, written like [`Self::backup`].
+    pub async fn export_ndjson(&self, path: &Path, content: bool) -> Result<(PathBuf, u64)> {
+        let request = self
+            .get("/v1/export/stream")
+            .query(&[("content", content)])
+            .timeout(LONG_OPERATION_TIMEOUT);
+        self.download(request, path, "odin-export.ndjson").await
+    }
+
+    /// Download every bookmark in `format`, with extracted text when `content` is set
+    /// (JSON and NDJSON only), written like [`Self::backup`].
+    pub async fn export(
+        &self,
+        path: &Path,
+        format: ExportFormat,
+        content: bool,
+    ) -> Result<(PathBuf, u64)> {
+        let request = self
+            .get("/v1/export")
+            .query(&[("format", format.as_str())])
+            .query(&[("content", content)])
+            .timeout(LONG_OPERATION_TIMEOUT);
+        let extension = match format {
+            ExportFormat::Netscape => "html",
+            other => other.as_str(),
+        };
+        self.download(request, path, &format!("odin-export.{}", extension))
+            .await
+    }
+
+    /// Stream a response body to `path`, or into a directory under the file name the
+    /// server suggests.
+    async fn download(
+        &self,
+        request: RequestBuilder,
+        path: &Path,
+        default_name: &str,
+    ) -> Result<(PathBuf, u64)> {
+        let mut response = self.send(request).await?;
+
+        let target = if path.is_dir() {
+            let file_name = response
+                .headers()
+                .get(CONTENT_DISPOSITION)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.split("filename=").nth(1))
+                .map(|value| value.trim_matches('"').to_string())
+                .filter(|value| !value.is_empty() && !value.contains(['/', '\\']))
+                .unwrap_or_else(|| default_name.to_string());
+            path.join(file_name)
+        } else {
+            path.to_path_buf()
+        };
+
+        let io_error = |source: std::io::Error| Error::Io {
+            path: target.clone(),
+            source,
+        };
+        let mut file = tokio::fs::File::create(&target).await.map_err(io_error)?;
+        let mut written = 0u64;
+        while let Some(chunk) = response.chunk().await? {
+            file.write_all(&chunk).await.map_err(io_error)?;
+            written += chunk.len() as u64;
+        }
+        file.flush().await.map_err(io_error)?;
+        Ok((target, written))
+    }
+
+    /// Upload a backup archive; the server applies it on its next restart.
+    pub async fn restore(&self, path: &Path) -> Result<()> {
+        let file = open(path).await?;
+        let request = self
+            .post("/v1/admin/restore")
+            .header(CONTENT_TYPE, "application/gzip")
+            .timeout(LONG_OPERATION_TIMEOUT)
+            .body(file);
+        self.send(request).await?;
+        Ok(())
+    }
+
+    /// Start rebuilding the search index; the stream yields one entry per bookmark.
+    pub async fn reindex(&self, request: &ReindexRequest) -> Result<ReindexStream> {
+        let request = self
+            .post("/v1/admin/reindex")
+            .timeout(LONG_OPERATION_TIMEOUT)
+            .json(request);
+        Ok(ReindexStream {
+            response: self.send(request).await?,
+            buffer: Vec::new(),
+        })
+    }
+
+    pub async fn create_user(&self, username: &str, is_admin: bool) -> Result<CreateUserResponse> {
+        let request = self
+            .post("/v1/users")
+            .json(&serde_json::json!({ "username": username, "is_admin": is_admin }));
+        read_json(self.send(request).await?).await
+    }
+
+    pub async fn users(&self) -> Result<UsersResponse> {
+        self.get_json("/v1/users").await
+    }
+
+    pub async fn create_key(&self, request: &CreateApiKeyRequest) -> Result<CreateApiKeyResponse> {
+        let request = self.post("/v1/keys").json(request);
+        read_json(self.send(request).await?).await
+    }
+
+    pub async fn keys(&self) -> Result<ApiKeysResponse> {
+        self.get_json("/v1/keys").await
+    }
+
+    pub async fn revoke_key(&self, id: i64) -> Result<()> {
+        let request = self.http.delete(self.url(&format!("/v1/keys/{}", id)));
+        self.send(request).await?;
+        Ok(())
+    }
+
+    /// Issue a replacement token; the old one keeps working for `grace_secs`, or the
+    /// server's `auth.rotation_grace_secs` when `None`.
+    pub async fn rotate_key(
+        &self,
+        id: i64,
+        grace_secs: Option<u64>,
+    ) -> Result<RotateApiKeyResponse> {
+        let request = self
+            .post(&format!("/v1/keys/{}/rotate", id))
+            .json(&serde_json::json!({ "grace_secs": grace_secs }));
+        read_json(self.send(request).await?).await
+    }
+
+    pub async fn jobs(&self) -> Result<JobsResponse> {
+        self.get_json("/v1/jobs").await
+    }
+
+    pub async fn job(&self, id: i64) -> Result<JobItem> {
+        self.get_json(&format!("/v1/jobs/{}", id)).await
+    }
+
+    /// Ask a running job to stop.
+    pub async fn cancel_job(&self, id: i64) -> Result<JobItem> {
+        let request = self.post(&format!("/v1/jobs/{}/cancel", id));
+        read_json(self.send(request).await?).await
+    }
+
+    /// Bookmark counts by status in the namespace, and the server's index and database sizes.
+    pub async fn stats(&self) -> Result<StatsResponse> {
+        self.get_json("/v1/stats").await
+    }
+
+    pub async fn status(&self) -> Result<AdminStatusResponse> {
+        self.get_json("/v1/admin/status").await
+    }
+
+    /// Fetch totals per domain over the last `hours`, worst first by `sort`.
+    pub async fn domain_stats(
+        &self,
+        hours: u32,
+        limit: u32,
+        sort: DomainStatsSort,
+    ) -> Result<DomainStatsResponse> {
+        let request = self.get("/v1/admin/domains/stats").query(&[
+            ("hours", hours.to_string()),
+            ("limit", limit.to_string()),
+            ("sort", sort.as_str().to_string()),
+        ]);
+        read_json(self.send_idempotent(request).await?).await
+    }
+
+    pub async fn db_maintenance(&self) -> Result<DbMaintenanceResponse> {
+        let request = self.post("/v1/admin/maintenance/db");
+        read_json(self.send(request).await?).await
+    }
+
+    /// Repair drift between the server's database and search index.
+    pub async fn reconcile(&self) -> Result<ReconcileResponse> {
+        let request = self.post("/v1/admin/reconcile");
+        read_json(self.send(request).await?).await
+    }
+
+    /// Compare the server's database and search index without repairing anything.
+    pub async fn consistency(&self) -> Result<ConsistencyResponse> {
+        self.get_json("/v1/admin/consistency").await
+    }
+
+    pub async fn settings(&self) -> Result<RuntimeSettings> {
+        self.get_json("/v1/admin/settings").await
+    }
+
+    pub async fn update_settings(&self, update: &UpdateSettingsRequest) -> Result<RuntimeSettings> {
+        let request = self.http.patch(self.url("/v1/admin/settings")).json(update);
+        read_json(self.send(request).await?).await
+    }
+
+    pub async fn read_only(&self) -> Result<ReadOnlyStatus> {
+        self.get_json("/v1/admin/read-only").await
+    }
+
+    /// Create the first admin key on a server that has none; works without a token.
+    pub async fn bootstrap(&self, request: &BootstrapRequest) -> Result<CreateApiKeyResponse> {
+        let request = self.post("/v1/admin/bootstrap").json(request);
+        read_json(self.send(request).await?).await
+    }
+
+    /// Turn read-only mode on (with an optional message for rejected writers) or off.
+    pub async fn set_read_only(&self, request: &SetReadOnlyRequest) -> Result<ReadOnlyStatus> {
+        let request = self.http.put(self.url("/v1/admin/read-only")).json(request);
+        read_json(self.send_idempotent(request).await?).await
+    }
+
+    /// Whether Readwise sync is connected for the token's user, and how far it got.
+    pub async fn readwise_status(&self) -> Result<ReadwiseStatus> {
+        self.get_json("/v1/integrations/readwise").await
+    }
+
+    /// Store a Readwise token for the token's user; the server checks it first.
+    pub async fn connect_readwise(
+        &self,
+        request: &ReadwiseConnectRequest,
+    ) -> Result<ReadwiseStatus> {
+        let request = self
+            .http
+            .put(self.url("/v1/integrations/readwise"))
+            .json(request);
+        read_json(self.send(request).await?).await
+    }
+
+    pub async fn disconnect_readwise(&self) -> Result<()> {
+        let request = self.http.delete(self.url("/v1/integrations/readwise"));
+        self.send(request).await?;
+        Ok(())
+    }
+
+    /// Whether starred-entry import is connected for the token's user.
+    pub async fn feed_reader_status(&self) -> Result<FeedReaderStatus> {
+        self.get_json("/v1/integrations/feed-reader").await
+    }
+
+    /// Store Miniflux or FreshRSS credentials for the token's user; the server checks
+    /// them first.
+    pub async fn connect_feed_reader(
+        &self,
+        request: &FeedReaderConnectRequest,
+    ) -> Result<FeedReaderStatus> {
+        let request = self
+            .http
+            .put(self.url("/v1/integrations/feed-reader"))
+            .json(request);
+        read_json(self.send(request).await?).await
+    }
+
+    pub async fn disconnect_feed_reader(&self) -> Result<()> {
+        let request = self.http.delete(self.url("/v1/integrations/feed-reader"));
+        self.send(request).await?;
+        Ok(())
+    }
+
+    /// RSS and Atom feeds the token's namespace is subscribed to.
+    pub async fn feeds(&self) -> Result<FeedsResponse> {
+        self.get_json("/v1/feeds").await
+    }
+
+    /// Subscribe to a feed; the server checks that it parses first.
+    pub async fn add_feed(&self, request: &FeedCreateRequest) -> Result<FeedItem> {
+        let request = self.http.post(self.url("/v1/feeds")).json(request);
+        read_json(self.send(request).await?).await
+    }
+
+    pub async fn remove_feed(&self, id: i64) -> Result<()> {
+        let request = self.http.delete(self.url(&format!("/v1/feeds/{}", id)));
+        self.send(request).await?;
+        Ok(())
+    }
+
+    /// Webhooks registered for the token's namespace.
+    pub async fn webhooks(&self) -> Result<WebhooksResponse> {
+        self.get_json("/v1/webhooks").await
+    }
+
+    pub async fn add_webhook(&self, request: &WebhookCreateRequest) -> Result<WebhookItem> {
+        let request = self.http.post(self.url("/v1/webhooks")).json(request);
+        read_json(self.send(request).await?).await
+    }
+
+    pub async fn remove_webhook(&self, id: i64) -> Result<()> {
+        let request = self.http.delete(self.url(&format!("/v1/webhooks/{}", id)));
+        self.send(request).await?;
+        Ok(())
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+
+    fn get(&self, path: &str) -> RequestBuilder {
+        self.http.get(self.url(path))
+    }
+
+    fn post(&self, path: &str) -> RequestBuilder {
+        self.http.post(self.url(path))
+    }
+
+    async fn get_json<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+        read_json(self.send_idempotent(self.get(path)).await?).await
+    }
+
+    async fn send(&self, request: RequestBuilder) -> Result<Response> {
+        check_status(request.send().await?).await
+    }
+
+    /// Send a request, retrying gateway errors and dropped connections with exponential
+    /// backoff and full jitter.
+    async fn send_idempotent(&self, request: RequestBuilder) -> Result<Response> {
+        let max_attempts = self.retry.max_attempts.max(1);
+        let mut attempt = 1;
+        loop {
+            let Some(next) = request.try_clone() else {
+                return self.send(request).await;
+            };
+            let result = next.send().await;
+            let retryable = match &result {
+                Ok(response) => matches!(
+                    response.status(),
+                    StatusCode::BAD_GATEWAY
+                        | StatusCode::SERVICE_UNAVAILABLE
+                        | StatusCode::GATEWAY_TIMEOUT
+                ),
+                Err(err) => is_transient_error(err),
+            };
+            if !retryable || attempt >= max_attempts {
+                return check_status(result?).await;
+            }
+
+            let ceiling = self
+                .retry
+                .base_delay_ms
+                .saturating_mul(1 << (attempt - 1).min(16))
+                .min(self.retry.max_delay_ms);
+            tokio::time::sleep(Duration::from_millis(rand::random_range(0..=ceiling))).await;
+            attempt += 1;
+        }
+    }
+}
+
+/// Progress of a reindex, read line by line from the server's NDJSON response.
+pub struct ReindexStream {
+    response: Response,
+    buffer: Vec<u8>,
+}
+
+impl ReindexStream {
+    /// The next bookmark processed, or `None` once the reindex has finished.
+    pub async fn next(&mut self) -> Result<Option<ReindexProgress>> {
+        loop {
+            if let Some(newline) = self.buffer.iter().position(|byte| *byte == b'\n') {
+                let line: Vec<u8> = self.buffer.drain(..=newline).collect();
+                return serde_json::from_slice(&line)
+                    .map(Some)
+                    .map_err(Error::Decode);
+            }
+            match self.response.chunk().await? {
+                Some(chunk) => self.buffer.extend_from_slice(&chunk),
+                None => return Ok(None),
+            }
+        }
+    }
+}
+
+async fn check_status(response: Response) -> Result<Response> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let body = response.text().await?;
+    Err(Error::Status { status, body })
+}
+
+async fn read_json<T: DeserializeOwned>(response: Response) -> Result<T> {
+    let body = response.text().await?;
+    serde_json::from_str(&body).map_err(Error::Decode)
+}
+
+async fn open(path: &Path) -> Result<tokio::fs::File> {
+    tokio::fs::File::open(path)
+        .await
+        .map_err(|source| Error::Io {
+            path: path.to_path_buf(),
+            source,
+        })
+}
+
+fn is_transient_error(err: &reqwest::Error) -> bool {
+    if err.is_connect() || err.is_timeout() {
+        return true;
+    }
+    let mut source = std::error::Error::source(err);
+    while let Some(inner) = source {
+        if let Some(io_err) = inner.downcast_ref::<std::io::Error>() {
+            return matches!(
+                io_err.kind(),
+                std::io::ErrorKind::ConnectionReset
+                    | std::io::ErrorKind::ConnectionAborted
+                    | std::io::ErrorKind::BrokenPipe
+                    | std::io::ErrorKind::UnexpectedEof
+            );
+        }
+        source = inner.source();
+    }
+    false
+}
@@ -0,0 +1,65 @@
+use std::fmt;
+use std::path::PathBuf;
+
+use reqwest::StatusCode;
+
+#[derive(Debug)]
+pub enum Error {
+    /// A token or namespace that cannot be sent as a header.
+    InvalidHeader(&'static str),
+    /// The request could not be sent or its response could not be read.
+    Http(reqwest::Error),
+    /// The server answered with a non-success status; `body` is its error message.
+    Status { status: StatusCode, body: String },
+    /// The response body did not match the expected type.
+    Decode(serde_json::Error),
+    /// Reading an upload or writing a download failed.
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+}
+
+impl Error {
+    /// The HTTP status of a rejected request.
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            Error::Status { status, .. } => Some(*status),
+            Error::Http(err) => err.status(),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidHeader(what) => write!(f, "invalid {}", what),
+            Error::Http(err) => write!(f, "{}", err),
+            Error::Status { status, body } => {
+                write!(f, "request failed with status {}: {}", status, body)
+            }
+            Error::Decode(_) => f.write_str("failed to parse response"),
+            Error::Io { path, .. } => write!(f, "failed to access {}", path.display()),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Http(err) => err.source(),
+            Error::Decode(err) => Some(err),
+            Error::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(err: reqwest::Error) -> Self {
+        Error::Http(err)
+    }
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
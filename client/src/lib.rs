@@ -0,0 +1,26 @@
+//! Typed client for the odin HTTP API, shared by the `odin` CLI.
+//!
+//! [`Client`] sends the configured API key and namespace on every request, retries
+//! idempotent requests on gateway errors and dropped connections (see [`RetryConfig`]),
+//! and returns the response bodies in [`types`]. Non-success responses become
+//! [`Error::Status`] carrying the server's message.
+//!
+//! ```no_run
+//! # async fn run() -> odin_client::Result<()> {
+//! let client = odin_client::Client::builder("http://localhost:3000")
+//!     .token("my-api-key")
+//!     .build()?;
+//! let results = client.search_all("rust").await?;
+//! for hit in &results.results {
+//!     println!("{} {}", hit.score, hit.url);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+mod client;
+mod error;
+pub mod types;
+
+pub use client::{Client, ClientBuilder, ReindexStream, RetryConfig};
+pub use error::{Error, Result};
@@ -0,0 +1,878 @@
+//! Request and response bodies of the odin HTTP API.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+/// What an API key may do; `delete` removes bookmarks, `admin` covers user, key, and
+/// server management.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    Read,
+    Ingest,
+    Delete,
+    Admin,
+}
+
+impl Scope {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Scope::Read => "read",
+            Scope::Ingest => "ingest",
+            Scope::Delete => "delete",
+            Scope::Admin => "admin",
+        }
+    }
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Scope {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "read" => Ok(Scope::Read),
+            "ingest" => Ok(Scope::Ingest),
+            "delete" => Ok(Scope::Delete),
+            "admin" => Ok(Scope::Admin),
+            other => Err(format!(
+                "unknown scope `{}` (expected read, ingest, delete, or admin)",
+                other
+            )),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SearchResponse {
+    pub total_hits: u64,
+    pub results: Vec<SearchResultItem>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SearchResultItem {
+    pub bookmark_id: i64,
+    pub url: String,
+    pub title: Option<String>,
+    pub excerpt: Option<String>,
+    /// The passage of the page text that best matches the query; absent when only the
+    /// title matched, and from servers without snippets.
+    #[serde(default)]
+    pub snippet: Option<SearchSnippet>,
+    pub score: f32,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SearchSnippet {
+    pub text: String,
+    /// Byte ranges of `text` matching query terms, as `[start, end)` pairs.
+    pub highlights: Vec<[usize; 2]>,
+    /// `text` HTML-escaped, with each match wrapped in `<mark>`.
+    pub html: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct BookmarksResponse {
+    /// Bookmarks matching the filters, across every page; absent from servers that
+    /// return every bookmark at once.
+    #[serde(default)]
+    pub total: Option<i64>,
+    #[serde(default)]
+    pub page: Option<u32>,
+    #[serde(default)]
+    pub per_page: Option<u32>,
+    pub results: Vec<BookmarkListItem>,
+}
+
+/// Filters, order, and page for `GET /v1/bookmarks`; unset fields use the server's
+/// defaults.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct BookmarksQuery {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    /// `queued`, `indexed`, `partial`, or `failed`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+    /// Substring of the URL or title, ignoring case.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub q: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sort: Option<BookmarkSort>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub per_page: Option<u32>,
+}
+
+/// Order of a bookmark listing.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BookmarkSort {
+    /// Most recently updated first.
+    #[default]
+    Updated,
+    /// Most recently saved first.
+    Created,
+    /// Least recently saved first.
+    Oldest,
+    Title,
+    Url,
+}
+
+impl FromStr for BookmarkSort {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "updated" => Ok(BookmarkSort::Updated),
+            "created" => Ok(BookmarkSort::Created),
+            "oldest" => Ok(BookmarkSort::Oldest),
+            "title" => Ok(BookmarkSort::Title),
+            "url" => Ok(BookmarkSort::Url),
+            other => Err(format!(
+                "unknown sort `{}` (expected updated, created, oldest, title, or url)",
+                other
+            )),
+        }
+    }
+}
+
+/// Parameters of `GET /v1/search`; only `query` is required.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct SearchQuery {
+    pub query: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    /// Host name; subdomains match too.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub site: Option<String>,
+    /// Fetched at or after this time: RFC 3339 or `YYYY-MM-DD`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub after: Option<String>,
+    /// Fetched before this time: RFC 3339 or `YYYY-MM-DD`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub before: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sort: Option<SearchSort>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub per_page: Option<u32>,
+}
+
+/// Order of search hits.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchSort {
+    /// Best match first.
+    #[default]
+    Relevance,
+    /// Most recently fetched first.
+    Recent,
+}
+
+impl FromStr for SearchSort {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "relevance" => Ok(SearchSort::Relevance),
+            "recent" => Ok(SearchSort::Recent),
+            other => Err(format!(
+                "unknown sort `{}` (expected relevance or recent)",
+                other
+            )),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct BookmarkListItem {
+    pub id: i64,
+    /// Normalized; what is fetched and what duplicates are detected by.
+    pub url: String,
+    /// The URL as it was submitted; absent from servers before it was kept.
+    #[serde(default)]
+    pub original_url: Option<String>,
+    pub title: Option<String>,
+    /// `queued`, `indexed`, `partial` (indexed from a truncated page), or `failed`.
+    pub status: String,
+    /// Fetches made in the latest ingest; a `queued` bookmark with some is waiting to
+    /// retry. Absent from servers without retries.
+    #[serde(default)]
+    pub attempts: i64,
+    /// `html`, `pdf`, or `text` once indexed; absent from servers that only index HTML.
+    #[serde(default)]
+    pub document_type: Option<String>,
+    pub updated_at: String,
+    /// Lowercased and sorted by name; absent from servers without tags.
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct BookmarkContentResponse {
+    pub id: i64,
+    pub url: String,
+    pub title: Option<String>,
+    pub fetched_at: Option<String>,
+    pub body: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct NamespacesResponse {
+    pub results: Vec<NamespaceItem>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct NamespaceItem {
+    pub name: String,
+    pub bookmarks: i64,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct BulkDeleteRequest {
+    pub ids: Vec<i64>,
+    pub urls: Vec<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct BulkDeleteResponse {
+    pub deleted: usize,
+    /// Requested ids and URLs that matched no bookmark.
+    pub not_found: Vec<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RefetchResponse {
+    /// Bookmarks put back in the ingest queue; ones already queued are not counted.
+    pub queued: usize,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct BookmarkTagsResponse {
+    pub id: i64,
+    pub tags: Vec<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct TagsResponse {
+    pub results: Vec<TagItem>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct TagItem {
+    pub name: String,
+    pub bookmarks: i64,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct IngestUrlsResponse {
+    pub accepted: usize,
+    pub deduped: usize,
+    /// URLs waiting for or being processed by the ingest workers, this batch included.
+    pub queue_depth: usize,
+    pub queue_capacity: usize,
+}
+
+/// An Instapaper import, or with `dry_run` what one would do.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct InstapaperImportResponse {
+    pub dry_run: bool,
+    pub job_id: Option<i64>,
+    /// Data rows in the CSV.
+    pub rows: usize,
+    /// Rows without an http(s) URL.
+    pub invalid: usize,
+    /// Rows in Instapaper's Starred folder.
+    pub starred: usize,
+    /// Rows per Instapaper folder.
+    pub folders: BTreeMap<String, usize>,
+    /// URLs queued for ingest, or on a dry run, not yet saved.
+    pub accepted: usize,
+    /// Repeated or already saved URLs.
+    pub deduped: usize,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct BrowserSyncResponse {
+    pub source: String,
+    /// Bookmarks in the upload, counted once per URL and folder.
+    pub found: usize,
+    /// URL and folder pairs new since the last sync.
+    pub added: usize,
+    /// Pairs from the last sync that are gone.
+    pub removed: usize,
+    /// New URLs queued for ingest; the rest were already saved.
+    pub accepted: usize,
+    /// Bookmarks deleted by `prune`.
+    pub deleted: usize,
+}
+
+/// How `GET /v1/export` writes the bookmarks.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    /// One JSON array of bookmark objects.
+    #[default]
+    Json,
+    /// One JSON object per line.
+    Ndjson,
+    Csv,
+    /// A Netscape bookmark file, as browsers import.
+    Netscape,
+}
+
+impl ExportFormat {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ExportFormat::Json => "json",
+            ExportFormat::Ndjson => "ndjson",
+            ExportFormat::Csv => "csv",
+            ExportFormat::Netscape => "netscape",
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "json" => Ok(ExportFormat::Json),
+            "ndjson" => Ok(ExportFormat::Ndjson),
+            "csv" => Ok(ExportFormat::Csv),
+            "netscape" => Ok(ExportFormat::Netscape),
+            other => Err(format!(
+                "unknown export format `{}` (expected json, ndjson, csv, or netscape)",
+                other
+            )),
+        }
+    }
+}
+
+/// How the server reads an import upload.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportFormat {
+    /// Every `http(s)://` URL in any text export: browser HTML, CSV, or a URL list.
+    #[default]
+    Auto,
+    /// An Omnivore export zip, or one of its `metadata_*.json` files.
+    Omnivore,
+    /// A Karakeep (Hoarder) JSON export.
+    Karakeep,
+    /// Shiori bookmarks as JSON, from its API or database tools.
+    Shiori,
+    /// A Netscape bookmark file (Chrome, Firefox, Safari), keeping titles and add dates.
+    Netscape,
+    /// Pocket's CSV or HTML export, or its API JSON, keeping titles and add dates.
+    Pocket,
+    /// Pinboard's JSON export, keeping titles and dates.
+    Pinboard,
+}
+
+impl ImportFormat {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ImportFormat::Auto => "auto",
+            ImportFormat::Omnivore => "omnivore",
+            ImportFormat::Karakeep => "karakeep",
+            ImportFormat::Shiori => "shiori",
+            ImportFormat::Netscape => "netscape",
+            ImportFormat::Pocket => "pocket",
+            ImportFormat::Pinboard => "pinboard",
+        }
+    }
+}
+
+impl FromStr for ImportFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "auto" => Ok(ImportFormat::Auto),
+            "omnivore" => Ok(ImportFormat::Omnivore),
+            "karakeep" => Ok(ImportFormat::Karakeep),
+            "shiori" => Ok(ImportFormat::Shiori),
+            "netscape" => Ok(ImportFormat::Netscape),
+            "pocket" => Ok(ImportFormat::Pocket),
+            "pinboard" => Ok(ImportFormat::Pinboard),
+            other => Err(format!(
+                "unknown import format `{}` (expected auto, netscape, pocket, pinboard, omnivore, karakeep, or shiori)",
+                other
+            )),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ImportResponse {
+    pub job_id: i64,
+    /// URLs seen in the upload, including repeats.
+    pub found: usize,
+    pub accepted: usize,
+    pub deduped: usize,
+}
+
+/// An OPML import: feed outlines become subscriptions and link outlines bookmarks.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct OpmlImportResponse {
+    /// Distinct feed outlines.
+    pub feeds: usize,
+    pub subscribed: usize,
+    pub already_subscribed: usize,
+    /// Feeds that could not be read or did not fit under the server's feed limit.
+    pub failed: Vec<OpmlFeedFailure>,
+    pub links: usize,
+    /// Set when any link was queued for ingest.
+    pub job_id: Option<i64>,
+    pub accepted: usize,
+    pub deduped: usize,
+    /// Outlines whose URL is not http(s).
+    pub invalid: usize,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct OpmlFeedFailure {
+    pub url: String,
+    pub error: String,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct ReindexRequest {
+    /// One bookmark to reindex; `None` reindexes every bookmark.
+    pub id: Option<i64>,
+    /// Fetch pages again instead of rebuilding from stored extracted text.
+    pub refetch: bool,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ReindexProgress {
+    pub id: i64,
+    pub url: String,
+    pub status: String,
+    pub error: Option<String>,
+    pub done: usize,
+    pub total: usize,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct JobsResponse {
+    pub results: Vec<JobItem>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct JobItem {
+    pub id: i64,
+    /// `import`, `reindex`, or `backup`.
+    pub kind: String,
+    /// `running`, `succeeded`, `failed`, or `cancelled`.
+    pub state: String,
+    pub done: i64,
+    pub total: Option<i64>,
+    pub error: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+    pub finished_at: Option<String>,
+}
+
+/// The caller's bookmarks by status, with the server's disk usage.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct StatsResponse {
+    pub bookmarks: i64,
+    /// Bookmark counts keyed by status (`queued`, `indexed`, `partial`, `failed`).
+    pub by_status: BTreeMap<String, i64>,
+    pub index_bytes: u64,
+    pub db_bytes: u64,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct AdminStatusResponse {
+    pub version: String,
+    pub uptime_secs: u64,
+    pub index_bytes: u64,
+    pub index_segments: usize,
+    pub index_docs: u64,
+    pub ingest_queue_depth: i64,
+    pub fetches_in_flight: usize,
+    pub fetch_concurrency: usize,
+    pub db_bytes: u64,
+    pub storage: StorageUsage,
+    pub read_only: bool,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct StorageUsage {
+    pub db_bytes: u64,
+    pub index_bytes: u64,
+    /// Stored page text; counted within `db_bytes`.
+    pub content_bytes: u64,
+    pub snapshot_bytes: u64,
+    pub total_bytes: u64,
+    pub quota_bytes: Option<u64>,
+    pub over_quota: bool,
+}
+
+/// What the per-domain fetch stats are ranked by, worst first.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DomainStatsSort {
+    /// Total time spent fetching.
+    #[default]
+    Time,
+    Latency,
+    Failures,
+    Fetches,
+    Bytes,
+}
+
+impl DomainStatsSort {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DomainStatsSort::Time => "time",
+            DomainStatsSort::Latency => "latency",
+            DomainStatsSort::Failures => "failures",
+            DomainStatsSort::Fetches => "fetches",
+            DomainStatsSort::Bytes => "bytes",
+        }
+    }
+}
+
+impl FromStr for DomainStatsSort {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "time" => Ok(DomainStatsSort::Time),
+            "latency" => Ok(DomainStatsSort::Latency),
+            "failures" => Ok(DomainStatsSort::Failures),
+            "fetches" => Ok(DomainStatsSort::Fetches),
+            "bytes" => Ok(DomainStatsSort::Bytes),
+            other => Err(format!(
+                "unknown sort `{}` (expected time, latency, failures, fetches, or bytes)",
+                other
+            )),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DomainStat {
+    pub domain: String,
+    pub fetches: i64,
+    /// Fetches that errored or answered with a non-2xx status.
+    pub failures: i64,
+    pub failure_rate: f64,
+    pub bytes: i64,
+    pub total_latency_ms: i64,
+    pub avg_latency_ms: i64,
+    pub max_latency_ms: i64,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DomainStatsResponse {
+    pub hours: i64,
+    pub results: Vec<DomainStat>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DbMaintenanceResponse {
+    /// Whether the database needed a one-off full `VACUUM` to enable incremental vacuuming.
+    pub vacuumed: bool,
+    pub freed_pages: i64,
+    /// Stored page texts from before compression that were compressed in this run.
+    pub compressed_contents: u64,
+    pub db_bytes_before: u64,
+    pub db_bytes_after: u64,
+    pub elapsed_ms: u64,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ReconcileResponse {
+    pub bookmarks_checked: usize,
+    pub index_docs_checked: usize,
+    /// Bookmarks missing from the index, re-added from stored text.
+    pub reindexed: Vec<i64>,
+    /// Missing bookmarks without stored text, queued for a fresh fetch.
+    pub refetch_queued: Vec<i64>,
+    /// Bookmarks whose index documents outlived their rows.
+    pub removed_docs: Vec<i64>,
+    pub elapsed_ms: u64,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ConsistencyResponse {
+    pub bookmarks_checked: usize,
+    pub index_docs_checked: usize,
+    /// Bookmarks marked indexed but missing from the index.
+    pub missing_from_index: Vec<i64>,
+    /// Bookmarks whose index documents outlived their rows.
+    pub orphaned_docs: Vec<i64>,
+    pub elapsed_ms: u64,
+}
+
+/// Ingest limits and slow-operation thresholds the server applies without a restart.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct RuntimeSettings {
+    pub fetch_concurrency: usize,
+    pub fetch_timeout_secs: u64,
+    pub max_urls: usize,
+    pub excerpt_len: usize,
+    pub slow_query_ms: u64,
+    pub slow_search_ms: u64,
+    pub slow_index_commit_ms: u64,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ReadOnlyStatus {
+    pub enabled: bool,
+    pub message: Option<String>,
+    pub since: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SetReadOnlyRequest {
+    pub enabled: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// A settings change; fields left `None` keep their current value.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct UpdateSettingsRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fetch_concurrency: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fetch_timeout_secs: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_urls: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub excerpt_len: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub slow_query_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub slow_search_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub slow_index_commit_ms: Option<u64>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CreateUserResponse {
+    pub id: i64,
+    pub username: String,
+    pub is_admin: bool,
+    /// The user's first API key, shown only in this response.
+    pub token: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct UsersResponse {
+    pub results: Vec<UserItem>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct UserItem {
+    pub id: i64,
+    pub username: String,
+    pub is_admin: bool,
+    pub created_at: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CreateApiKeyRequest {
+    pub name: String,
+    /// Defaults to `read` and `ingest` on the server.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scopes: Option<Vec<Scope>>,
+    /// Bind the key to one namespace; unbound keys pick one per request.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ReadwiseConnectRequest {
+    pub token: String,
+    /// Push only bookmarks saved from now on instead of the whole archive.
+    #[serde(default)]
+    pub from_now: bool,
+}
+
+/// The feed reader whose starred entries are imported.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FeedReaderKind {
+    Miniflux,
+    FreshRss,
+}
+
+impl FromStr for FeedReaderKind {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "miniflux" => Ok(FeedReaderKind::Miniflux),
+            "freshrss" => Ok(FeedReaderKind::FreshRss),
+            other => Err(format!(
+                "unknown feed reader `{}` (expected miniflux or freshrss)",
+                other
+            )),
+        }
+    }
+}
+
+/// Miniflux takes an API key as `token`; FreshRSS takes `username` and its API
+/// password as `token`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct FeedReaderConnectRequest {
+    pub kind: FeedReaderKind,
+    pub url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    pub token: String,
+    /// Import only entries starred from now on.
+    #[serde(default)]
+    pub from_now: bool,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct FeedReaderStatus {
+    pub connected: bool,
+    pub kind: Option<FeedReaderKind>,
+    pub url: Option<String>,
+    /// Starred entries seen so far, imported or skipped by `from_now`.
+    pub entries_seen: i64,
+    pub last_synced_at: Option<String>,
+    pub last_error: Option<String>,
+}
+
+/// Registers a URL that is POSTed the namespace's bookmark events.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct WebhookCreateRequest {
+    pub url: String,
+    /// Shared secret for the `X-Odin-Signature` HMAC.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+    /// `indexed`, `failed`, or `deleted`; every event when absent.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub events: Option<Vec<String>>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct WebhookItem {
+    pub id: i64,
+    pub url: String,
+    pub events: Vec<String>,
+    pub signed: bool,
+    pub created_at: String,
+    pub last_attempt_at: Option<String>,
+    pub last_error: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct WebhooksResponse {
+    pub results: Vec<WebhookItem>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct FeedCreateRequest {
+    pub url: String,
+    /// Ingest only entries published from now on.
+    #[serde(default)]
+    pub from_now: bool,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct FeedItem {
+    pub id: i64,
+    pub url: String,
+    pub title: Option<String>,
+    /// Entries seen so far, ingested or skipped by `from_now`.
+    pub entries_seen: i64,
+    pub created_at: String,
+    pub last_polled_at: Option<String>,
+    pub last_error: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct FeedsResponse {
+    pub results: Vec<FeedItem>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ReadwiseStatus {
+    pub connected: bool,
+    /// Bookmarks are pushed in id order; everything up to this id has been sent.
+    pub last_bookmark_id: i64,
+    /// Bookmarks still to push.
+    pub pending: i64,
+    pub last_synced_at: Option<String>,
+    pub last_error: Option<String>,
+}
+
+/// A bookmark's Wayback Machine capture: `pending`, then `saved` or `failed`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct WaybackSnapshot {
+    pub bookmark_id: i64,
+    pub status: String,
+    pub archive_url: Option<String>,
+    pub error: Option<String>,
+    pub requested_at: String,
+    pub completed_at: Option<String>,
+}
+
+/// Creates the first admin key; `secret` is the setup secret the server logged, not
+/// needed over the server's unix socket.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct BootstrapRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CreateApiKeyResponse {
+    pub id: i64,
+    pub name: String,
+    pub scopes: Vec<Scope>,
+    pub namespace: Option<String>,
+    /// Shown only in this response.
+    pub token: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ApiKeysResponse {
+    pub results: Vec<ApiKeyItem>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ApiKeyItem {
+    pub id: i64,
+    pub name: String,
+    pub scopes: Vec<Scope>,
+    pub created_at: String,
+    pub last_used_at: Option<String>,
+    pub revoked_at: Option<String>,
+    pub expires_at: Option<String>,
+    pub namespace: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RotateApiKeyResponse {
+    pub id: i64,
+    pub name: String,
+    pub scopes: Vec<Scope>,
+    pub namespace: Option<String>,
+    /// Shown only in this response.
+    pub token: String,
+    pub previous_id: i64,
+    /// When the replaced token stops working.
+    pub previous_expires_at: String,
+}
@@ -0,0 +1,1277 @@
+use std::env;
+use std::fmt::Display;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use anyhow::Context;
+use axum::http::{HeaderName, HeaderValue, Method};
+use ipnet::IpNet;
+use serde::Deserialize;
+use tracing::info;
+use url::Url;
+
+use crate::types::{BookmarkEventKind, Peer};
+
+const DEFAULT_CONFIG_FILE: &str = "odin.toml";
+
+/// Server configuration loaded from `odin.toml` (or `$ODIN_CONFIG`) with `ODIN_*` env overrides.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub log: LogConfig,
+    pub server: ServerConfig,
+    pub tls: TlsConfig,
+    pub cors: CorsConfig,
+    pub auth: AuthConfig,
+    pub oidc: OidcConfig,
+    pub storage: StorageConfig,
+    pub quota: QuotaConfig,
+    pub ingest: IngestConfig,
+    pub rate_limit: RateLimitConfig,
+    pub maintenance: MaintenanceConfig,
+    pub slow_log: SlowLogConfig,
+    pub telemetry: TelemetryConfig,
+    pub grpc: GrpcConfig,
+    pub telegram: TelegramConfig,
+    pub readwise: ReadwiseConfig,
+    pub feed_reader: FeedReaderConfig,
+    pub feeds: FeedsConfig,
+    pub activitypub: ActivityPubConfig,
+    pub wayback: WaybackConfig,
+    pub webhooks: Vec<WebhookConfig>,
+    pub notifiers: Vec<NotifierConfig>,
+}
+
+/// Log output; reloaded on SIGHUP.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    /// A `tracing` filter such as `info` or `info,odin_core=debug`.
+    pub level: String,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            level: "info".to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub listen_addr: SocketAddr,
+    /// Set to false to serve only on `unix_socket_path`.
+    pub tcp_enabled: bool,
+    pub unix_socket_path: Option<PathBuf>,
+    /// Permission bits applied to the socket file, e.g. 0o660 to admit a proxy's group.
+    pub unix_socket_mode: u32,
+    pub max_body_bytes: usize,
+    /// Limit for streamed bookmark imports, which are scanned rather than buffered.
+    pub import_max_body_bytes: usize,
+    pub shutdown_grace_secs: u64,
+    /// Per-component deadline for `/readyz` checks.
+    pub health_timeout_ms: u64,
+    /// How long the index writer may stay locked before `/readyz` reports it wedged; 0 never does.
+    pub writer_stall_secs: u64,
+    /// Serve the embedded web UI (`frontend/dist` at build time) for paths outside the API.
+    pub serve_frontend: bool,
+    /// How long a handler may take to respond before the request gets a 503; 0 never.
+    pub request_timeout_secs: u64,
+    /// The same for imports, exports, backups, restores, and admin passes over the index.
+    pub long_request_timeout_secs: u64,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            listen_addr: SocketAddr::from(([0, 0, 0, 0], 3000)),
+            tcp_enabled: true,
+            unix_socket_path: None,
+            unix_socket_mode: 0o660,
+            max_body_bytes: 2 * 1024 * 1024,
+            import_max_body_bytes: 256 * 1024 * 1024,
+            shutdown_grace_secs: 30,
+            health_timeout_ms: 2_000,
+            writer_stall_secs: 600,
+            serve_frontend: false,
+            request_timeout_secs: 30,
+            long_request_timeout_secs: 3600,
+        }
+    }
+}
+
+/// Serve HTTPS directly when both paths are set; files are re-read when they change.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TlsConfig {
+    pub cert_path: Option<PathBuf>,
+    pub key_path: Option<PathBuf>,
+    pub reload_interval_secs: u64,
+}
+
+impl Default for TlsConfig {
+    fn default() -> Self {
+        Self {
+            cert_path: None,
+            key_path: None,
+            reload_interval_secs: 60,
+        }
+    }
+}
+
+impl TlsConfig {
+    pub fn paths(&self) -> Option<(&Path, &Path)> {
+        Some((self.cert_path.as_deref()?, self.key_path.as_deref()?))
+    }
+}
+
+/// Browser access to the API; a list holding only `"*"` allows anything.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CorsConfig {
+    pub allowed_origins: Vec<String>,
+    pub allowed_methods: Vec<String>,
+    pub allowed_headers: Vec<String>,
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        Self {
+            allowed_origins: vec![
+                "http://localhost:5173".to_string(),
+                "http://127.0.0.1:5173".to_string(),
+            ],
+            allowed_methods: ["GET", "POST", "PATCH", "DELETE", "OPTIONS"]
+                .map(String::from)
+                .to_vec(),
+            allowed_headers: [
+                "authorization",
+                "content-type",
+                "x-request-id",
+                "x-odin-namespace",
+                "x-csrf-token",
+            ]
+            .map(String::from)
+            .to_vec(),
+        }
+    }
+}
+
+impl CorsConfig {
+    /// Whether `list` is the single wildcard entry.
+    pub fn is_any(list: &[String]) -> bool {
+        list.iter().any(|entry| entry == "*")
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthConfig {
+    /// How long a rotated key keeps working when the rotate request names no grace period.
+    pub rotation_grace_secs: u64,
+    /// Upper bound on any requested grace period.
+    pub max_rotation_grace_secs: u64,
+    /// Networks that may make requests needing the ingest or admin scope, on top of
+    /// token auth; empty allows any address. Unix socket clients are always allowed.
+    pub privileged_cidrs: Vec<IpNet>,
+    /// Lifetime of a web UI session from login.
+    pub session_ttl_secs: u64,
+    /// Mark the session cookie `Secure`; always set when serving TLS directly.
+    pub session_cookie_secure: bool,
+    /// Let a loopback TCP peer without forwarding headers bootstrap the first admin key
+    /// without the setup secret. Off by default, since a local proxy that adds no such
+    /// headers makes every client look local; unix socket clients never need the secret.
+    pub bootstrap_trust_loopback: bool,
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        Self {
+            rotation_grace_secs: 86_400,
+            max_rotation_grace_secs: 30 * 86_400,
+            privileged_cidrs: Vec::new(),
+            session_ttl_secs: 7 * 86_400,
+            session_cookie_secure: false,
+            bootstrap_trust_loopback: false,
+        }
+    }
+}
+
+impl AuthConfig {
+    /// Whether `peer` may make privileged requests. Unix socket clients always may,
+    /// being on the host already, so bootstrap and admin work there whatever the list;
+    /// a peer without an address only may when the list is empty.
+    pub fn allows_privileged(&self, peer: Peer) -> bool {
+        match peer {
+            Peer::Unix => true,
+            _ if self.privileged_cidrs.is_empty() => true,
+            Peer::Tcp(ip) => {
+                // IPv4 clients of a dual-stack listener show up as IPv4-mapped IPv6 addresses.
+                let ip = ip.to_canonical();
+                self.privileged_cidrs.iter().any(|net| net.contains(&ip))
+            }
+            Peer::Unknown => false,
+        }
+    }
+}
+
+/// Single sign-on through an OpenID Connect provider; off unless `issuer_url` is set.
+/// Signing in starts the same session as `POST /v1/auth/login`.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OidcConfig {
+    /// Discovery is read from `<issuer_url>/.well-known/openid-configuration`.
+    pub issuer_url: Option<Url>,
+    pub client_id: String,
+    pub client_secret: Option<String>,
+    /// Where the provider sends the browser back: `<public url>/v1/auth/oidc/callback`.
+    pub redirect_url: Option<Url>,
+    pub scopes: Vec<String>,
+    /// Claim matched against odin usernames.
+    pub username_claim: String,
+    /// Create a user on first sign-in instead of refusing unknown usernames.
+    pub create_users: bool,
+    /// Claim listing the user's groups, for `admin_groups`.
+    pub groups_claim: String,
+    /// Members of any of these groups sign in with admin scope.
+    pub admin_groups: Vec<String>,
+    /// Where the browser lands after signing in.
+    pub post_login_redirect: String,
+}
+
+impl Default for OidcConfig {
+    fn default() -> Self {
+        Self {
+            issuer_url: None,
+            client_id: String::new(),
+            client_secret: None,
+            redirect_url: None,
+            scopes: vec![
+                "openid".to_string(),
+                "email".to_string(),
+                "profile".to_string(),
+            ],
+            username_claim: "preferred_username".to_string(),
+            create_users: false,
+            groups_claim: "groups".to_string(),
+            admin_groups: Vec::new(),
+            post_login_redirect: "/".to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StorageConfig {
+    pub data_dir: PathBuf,
+    /// Keep the database and index in memory and never touch `data_dir`; everything
+    /// is lost on shutdown, and backups and restores are refused.
+    pub in_memory: bool,
+    pub db_max_connections: u32,
+    pub db_busy_timeout_ms: u64,
+    /// Indexing memory budget shared by all writer threads of each shard.
+    pub writer_heap_bytes: usize,
+    /// Indexing threads per shard; 0 lets tantivy pick (CPU count, at most 8, and no
+    /// more than the heap allows).
+    pub writer_threads: usize,
+    /// Partitions the index is split into by bookmark id; changing it rebuilds the index.
+    pub index_shards: usize,
+    /// Threads shared by all shards for searching segments in parallel; 1 searches each
+    /// shard's segments on the calling thread, 0 uses one thread per CPU.
+    pub search_threads: usize,
+    /// When searchers pick up index commits. Either way they are reloaded and warmed in
+    /// the background, so searches keep using the old searchers meanwhile.
+    pub reader_reload: ReaderReload,
+    /// How often `interval` reloading checks for new commits.
+    pub reader_reload_interval_ms: u64,
+    /// Ingested documents that trigger a commit as soon as that many are waiting.
+    pub commit_batch_docs: usize,
+    /// Longest an ingested document waits for its batch to be committed; 0 commits
+    /// whatever is waiting right away.
+    pub commit_interval_ms: u64,
+    /// Keep each fetched page, compressed, so a copy can be served after the site is gone.
+    pub archive_pages: bool,
+}
+
+/// When searchers are reloaded to include index commits.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReaderReload {
+    /// After every commit, coalescing commits made while a reload runs.
+    #[default]
+    OnCommit,
+    /// Every `reader_reload_interval_ms` when something was committed since the last
+    /// reload, trading freshness for fewer reloads during bulk ingests.
+    Interval,
+}
+
+impl FromStr for ReaderReload {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "on_commit" => Ok(ReaderReload::OnCommit),
+            "interval" => Ok(ReaderReload::Interval),
+            other => Err(format!(
+                "unknown reader reload policy `{}` (expected on_commit or interval)",
+                other
+            )),
+        }
+    }
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            data_dir: PathBuf::from("data"),
+            in_memory: false,
+            db_max_connections: 5,
+            db_busy_timeout_ms: 5_000,
+            writer_heap_bytes: 50_000_000,
+            writer_threads: 0,
+            index_shards: 1,
+            search_threads: 1,
+            reader_reload: ReaderReload::OnCommit,
+            reader_reload_interval_ms: 1000,
+            commit_batch_docs: 100,
+            commit_interval_ms: 1000,
+            archive_pages: true,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct IngestConfig {
+    pub fetch_concurrency: usize,
+    pub fetch_timeout_secs: u64,
+    pub max_urls: usize,
+    /// Characters of page text kept as the search-result excerpt.
+    pub excerpt_len: usize,
+    /// Tasks consuming the ingest queue; each holds one fetch permit while working.
+    pub workers: usize,
+    /// URLs that may wait for a worker before ingest requests get 503s.
+    pub queue_capacity: usize,
+    /// Fetches running against one host at a time; 0 leaves only `fetch_concurrency`.
+    pub max_connections_per_host: usize,
+    /// Idle keep-alive connections kept open per host for the next fetch.
+    pub pool_idle_per_host: usize,
+    pub pool_idle_timeout_secs: u64,
+    /// Offer HTTP/2 to TLS servers; HTTP/1.1 is used when they decline.
+    pub http2: bool,
+    /// How long resolved addresses are reused; 0 resolves on every new connection.
+    pub dns_cache_ttl_secs: u64,
+    /// Time a page may spend being parsed on its blocking thread before it is failed.
+    pub extract_timeout_secs: u64,
+    /// Bytes of a response read before the rest is dropped; this also bounds the DOM.
+    pub max_page_bytes: usize,
+    /// Nodes a page's DOM may have before extraction falls back to its bare text.
+    pub max_dom_nodes: usize,
+    /// Characters of extracted text indexed and stored per page.
+    pub max_text_chars: usize,
+    /// Fetches of one URL before a transient failure (a network error, a timeout, 408,
+    /// 429, or 5xx) is final; 1 disables retries.
+    pub max_attempts: u32,
+    /// Wait before the first retry, doubled for each later one.
+    pub retry_backoff_secs: u64,
+    /// Longest wait between retries.
+    pub retry_max_backoff_secs: u64,
+    /// Fetch each site's robots.txt and fail bookmarks it disallows for `odin-agent`.
+    pub respect_robots: bool,
+    /// How long a site's robots.txt is reused before it is fetched again.
+    pub robots_cache_secs: u64,
+    /// Least time between fetches starting against one host; a longer `Crawl-delay`
+    /// (up to a minute) wins when robots.txt is respected.
+    pub host_delay_ms: u64,
+}
+
+impl Default for IngestConfig {
+    fn default() -> Self {
+        Self {
+            fetch_concurrency: 10,
+            fetch_timeout_secs: 20,
+            max_urls: 100,
+            excerpt_len: 280,
+            workers: 10,
+            queue_capacity: 10_000,
+            max_connections_per_host: 4,
+            pool_idle_per_host: 8,
+            pool_idle_timeout_secs: 90,
+            http2: true,
+            dns_cache_ttl_secs: 300,
+            extract_timeout_secs: 15,
+            max_page_bytes: 10 * 1024 * 1024,
+            max_dom_nodes: 200_000,
+            max_text_chars: 2_000_000,
+            max_attempts: 4,
+            retry_backoff_secs: 30,
+            retry_max_backoff_secs: 3600,
+            respect_robots: true,
+            robots_cache_secs: 3600,
+            host_delay_ms: 250,
+        }
+    }
+}
+
+/// Per-client request budgets; a limit of 0 disables that bucket.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RateLimitConfig {
+    pub enabled: bool,
+    pub search_per_minute: u32,
+    pub ingest_per_minute: u32,
+    pub burst: u32,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            search_per_minute: 120,
+            ingest_per_minute: 30,
+            burst: 10,
+        }
+    }
+}
+
+/// Background segment merging and SQLite upkeep; an interval of 0 disables that task.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MaintenanceConfig {
+    pub optimize_interval_secs: u64,
+    /// How long the index must go without writes before a merge may start.
+    pub idle_secs: u64,
+    /// Merge once at least this many segments exist (or any hold deleted docs).
+    pub min_segments: usize,
+    /// How often to vacuum free pages, refresh planner statistics, and truncate the WAL.
+    pub db_interval_secs: u64,
+    /// Also run database maintenance once this many bookmarks were deleted; 0 never does.
+    pub db_deleted_rows: usize,
+    /// How often to compare the index with the database and repair any drift.
+    pub reconcile_interval_secs: u64,
+}
+
+impl Default for MaintenanceConfig {
+    fn default() -> Self {
+        Self {
+            optimize_interval_secs: 3_600,
+            idle_secs: 300,
+            min_segments: 8,
+            db_interval_secs: 86_400,
+            db_deleted_rows: 1_000,
+            reconcile_interval_secs: 21_600,
+        }
+    }
+}
+
+/// Disk budget for everything under `storage.data_dir`.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct QuotaConfig {
+    /// Bytes the database, index, and kept snapshots may use together; 0 is unlimited.
+    pub max_bytes: u64,
+    pub policy: QuotaPolicy,
+}
+
+/// What happens to snapshots (backups and the copies kept when a restore is applied)
+/// once usage reaches `quota.max_bytes`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QuotaPolicy {
+    /// Refuse new backups and restores until usage drops.
+    #[default]
+    StopSnapshots,
+    /// Delete the oldest kept snapshots until usage fits, then refuse if it still does not.
+    EvictSnapshots,
+}
+
+impl FromStr for QuotaPolicy {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "stop_snapshots" => Ok(QuotaPolicy::StopSnapshots),
+            "evict_snapshots" => Ok(QuotaPolicy::EvictSnapshots),
+            other => Err(format!(
+                "unknown quota policy `{}` (expected stop_snapshots or evict_snapshots)",
+                other
+            )),
+        }
+    }
+}
+
+/// Warn when an operation takes at least this long; 0 disables that check.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SlowLogConfig {
+    pub query_ms: u64,
+    pub search_ms: u64,
+    pub index_commit_ms: u64,
+}
+
+impl Default for SlowLogConfig {
+    fn default() -> Self {
+        Self {
+            query_ms: 500,
+            search_ms: 1_000,
+            index_commit_ms: 2_000,
+        }
+    }
+}
+
+/// OpenTelemetry trace export over OTLP/HTTP; disabled unless an endpoint is set.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TelemetryConfig {
+    /// Full traces URL, e.g. `http://localhost:4318/v1/traces`.
+    pub otlp_endpoint: Option<Url>,
+    pub service_name: String,
+    /// Fraction of new traces to record, from 0.0 to 1.0.
+    pub sample_ratio: f64,
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        Self {
+            otlp_endpoint: None,
+            service_name: "odin".to_string(),
+            sample_ratio: 1.0,
+        }
+    }
+}
+
+/// The gRPC API (`proto/odin/v1/odin.proto`) on its own plaintext HTTP/2 port; off unless set.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GrpcConfig {
+    pub listen_addr: Option<SocketAddr>,
+}
+
+/// A built-in Telegram bot that saves links sent to it and answers `/search`; off
+/// unless `bot_token` is set.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TelegramConfig {
+    /// From @BotFather.
+    pub bot_token: Option<String>,
+    /// The odin API token the bot acts as; it needs the read and ingest scopes.
+    pub api_token: Option<String>,
+    /// Chats the bot serves; other chats are told their id so it can be added here.
+    pub allowed_chat_ids: Vec<i64>,
+    pub api_url: Url,
+}
+
+impl Default for TelegramConfig {
+    fn default() -> Self {
+        Self {
+            bot_token: None,
+            api_token: None,
+            allowed_chat_ids: Vec::new(),
+            api_url: Url::parse("https://api.telegram.org").expect("valid default url"),
+        }
+    }
+}
+
+/// Pushing new bookmarks to Readwise Reader for users who connected a Readwise token.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReadwiseConfig {
+    pub api_url: Url,
+    /// Seconds between pushes; 0 turns syncing off for every user.
+    pub sync_interval_secs: u64,
+    /// Bookmarks pushed per user per sync, kept under Readwise's 50 saves a minute.
+    pub batch_size: usize,
+}
+
+impl Default for ReadwiseConfig {
+    fn default() -> Self {
+        Self {
+            api_url: Url::parse("https://readwise.io").expect("valid default url"),
+            sync_interval_secs: 300,
+            batch_size: 20,
+        }
+    }
+}
+
+/// Importing starred entries from users' Miniflux or FreshRSS accounts.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FeedReaderConfig {
+    /// Seconds between polls; 0 turns importing off for every user.
+    pub sync_interval_secs: u64,
+}
+
+impl Default for FeedReaderConfig {
+    fn default() -> Self {
+        Self {
+            sync_interval_secs: 300,
+        }
+    }
+}
+
+/// RSS and Atom subscriptions whose new entries are ingested.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FeedsConfig {
+    /// Seconds between polls of every feed; 0 turns polling off.
+    pub poll_interval_secs: u64,
+    /// Feeds one user may subscribe to in a namespace.
+    pub max_feeds: usize,
+}
+
+impl Default for FeedsConfig {
+    fn default() -> Self {
+        Self {
+            poll_interval_secs: 900,
+            max_feeds: 500,
+        }
+    }
+}
+
+/// A fediverse actor that publishes one user's public bookmarks as notes; off unless
+/// `base_url` is set.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ActivityPubConfig {
+    /// The server's public origin, e.g. `https://odin.example.com`; actor and note ids
+    /// are built on it, so it must not change once people follow.
+    pub base_url: Option<Url>,
+    /// The odin user whose public bookmarks are published; also the actor's handle.
+    pub username: String,
+    pub display_name: Option<String>,
+    pub summary: Option<String>,
+}
+
+/// Submitting bookmarks to the Wayback Machine's Save Page Now API; off unless both
+/// keys are set.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WaybackConfig {
+    /// S3-style keys from archive.org/account/s3.php.
+    pub access_key: Option<String>,
+    pub secret_key: Option<String>,
+    /// Submit every bookmark once it is indexed; otherwise only on request.
+    pub submit_all: bool,
+    pub api_url: Url,
+}
+
+impl Default for WaybackConfig {
+    fn default() -> Self {
+        Self {
+            access_key: None,
+            secret_key: None,
+            submit_all: false,
+            api_url: Url::parse("https://web.archive.org").expect("valid default url"),
+        }
+    }
+}
+
+/// An endpoint that receives signed JSON bookmark events.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WebhookConfig {
+    pub url: Url,
+    /// Shared secret for the `X-Odin-Signature` HMAC; unsigned when absent.
+    pub secret: Option<String>,
+    #[serde(default = "all_events")]
+    pub events: Vec<BookmarkEventKind>,
+}
+
+fn all_events() -> Vec<BookmarkEventKind> {
+    BookmarkEventKind::ALL.to_vec()
+}
+
+/// A chat or push destination that gets short human-readable messages.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NotifierConfig {
+    pub kind: NotifierKind,
+    /// Slack or Discord incoming-webhook URL, ntfy topic URL, or Matrix homeserver URL.
+    pub url: Url,
+    /// Matrix room to post in, e.g. `!abc123:example.org`.
+    pub room_id: Option<String>,
+    /// Matrix access token, or an ntfy access token for protected topics.
+    pub token: Option<String>,
+    #[serde(default = "default_notify_events")]
+    pub events: Vec<NotifyEvent>,
+    /// Queries checked against each newly indexed bookmark; a match sends a message.
+    #[serde(default)]
+    pub searches: Vec<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotifierKind {
+    Slack,
+    Discord,
+    Matrix,
+    Ntfy,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotifyEvent {
+    /// A bookmark failed to fetch or index.
+    Failed,
+    /// Bookmarks saved in the past week, sent weekly.
+    Digest,
+}
+
+fn default_notify_events() -> Vec<NotifyEvent> {
+    vec![NotifyEvent::Failed]
+}
+
+impl Config {
+    /// Defaults with `storage.in_memory` set, for tests and throwaway instances.
+    pub fn in_memory() -> Self {
+        let mut config = Self::default();
+        config.storage.in_memory = true;
+        config
+    }
+
+    /// Read the config file if present, then apply environment overrides.
+    pub fn load() -> anyhow::Result<Self> {
+        let explicit = env::var_os("ODIN_CONFIG").map(PathBuf::from);
+        let path = explicit
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_CONFIG_FILE));
+
+        let mut config = if explicit.is_some() || path.exists() {
+            Self::from_file(&path)?
+        } else {
+            Self::default()
+        };
+        config.apply_env()?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn from_file(path: &Path) -> anyhow::Result<Self> {
+        let raw = std::fs::read_to_string(path)
+            .with_context(|| format!("read config file {}", path.display()))?;
+        let config = toml::from_str(&raw)
+            .with_context(|| format!("parse config file {}", path.display()))?;
+        info!("loaded config from {}", path.display());
+        Ok(config)
+    }
+
+    fn apply_env(&mut self) -> anyhow::Result<()> {
+        env_override("ODIN_LOG_LEVEL", &mut self.log.level)?;
+        env_override("ODIN_LISTEN_ADDR", &mut self.server.listen_addr)?;
+        env_override("ODIN_TCP_ENABLED", &mut self.server.tcp_enabled)?;
+        env_override_some("ODIN_UNIX_SOCKET_PATH", &mut self.server.unix_socket_path)?;
+        if let Some(mode) = env_value::<String>("ODIN_UNIX_SOCKET_MODE")? {
+            self.server.unix_socket_mode = u32::from_str_radix(mode.trim_start_matches("0o"), 8)
+                .map_err(|err| anyhow::anyhow!("invalid ODIN_UNIX_SOCKET_MODE: {}", err))?;
+        }
+        env_override("ODIN_MAX_BODY_BYTES", &mut self.server.max_body_bytes)?;
+        env_override(
+            "ODIN_IMPORT_MAX_BODY_BYTES",
+            &mut self.server.import_max_body_bytes,
+        )?;
+        env_override(
+            "ODIN_SHUTDOWN_GRACE_SECS",
+            &mut self.server.shutdown_grace_secs,
+        )?;
+        env_override("ODIN_HEALTH_TIMEOUT_MS", &mut self.server.health_timeout_ms)?;
+        env_override("ODIN_WRITER_STALL_SECS", &mut self.server.writer_stall_secs)?;
+        env_override(
+            "ODIN_REQUEST_TIMEOUT_SECS",
+            &mut self.server.request_timeout_secs,
+        )?;
+        env_override(
+            "ODIN_LONG_REQUEST_TIMEOUT_SECS",
+            &mut self.server.long_request_timeout_secs,
+        )?;
+        env_override("ODIN_SERVE_FRONTEND", &mut self.server.serve_frontend)?;
+        env_override_some("ODIN_TLS_CERT_PATH", &mut self.tls.cert_path)?;
+        env_override_some("ODIN_TLS_KEY_PATH", &mut self.tls.key_path)?;
+        env_override(
+            "ODIN_TLS_RELOAD_INTERVAL_SECS",
+            &mut self.tls.reload_interval_secs,
+        )?;
+        env_list("ODIN_CORS_ALLOWED_ORIGINS", &mut self.cors.allowed_origins)?;
+        env_list("ODIN_CORS_ALLOWED_METHODS", &mut self.cors.allowed_methods)?;
+        env_list("ODIN_CORS_ALLOWED_HEADERS", &mut self.cors.allowed_headers)?;
+        env_override(
+            "ODIN_KEY_ROTATION_GRACE_SECS",
+            &mut self.auth.rotation_grace_secs,
+        )?;
+        env_override(
+            "ODIN_KEY_MAX_ROTATION_GRACE_SECS",
+            &mut self.auth.max_rotation_grace_secs,
+        )?;
+        env_list("ODIN_PRIVILEGED_CIDRS", &mut self.auth.privileged_cidrs)?;
+        env_override("ODIN_SESSION_TTL_SECS", &mut self.auth.session_ttl_secs)?;
+        env_override(
+            "ODIN_SESSION_COOKIE_SECURE",
+            &mut self.auth.session_cookie_secure,
+        )?;
+        env_override(
+            "ODIN_BOOTSTRAP_TRUST_LOOPBACK",
+            &mut self.auth.bootstrap_trust_loopback,
+        )?;
+        env_override("ODIN_DATA_DIR", &mut self.storage.data_dir)?;
+        env_override("ODIN_IN_MEMORY", &mut self.storage.in_memory)?;
+        env_override(
+            "ODIN_DB_MAX_CONNECTIONS",
+            &mut self.storage.db_max_connections,
+        )?;
+        env_override(
+            "ODIN_DB_BUSY_TIMEOUT_MS",
+            &mut self.storage.db_busy_timeout_ms,
+        )?;
+        env_override(
+            "ODIN_WRITER_HEAP_BYTES",
+            &mut self.storage.writer_heap_bytes,
+        )?;
+        env_override("ODIN_WRITER_THREADS", &mut self.storage.writer_threads)?;
+        env_override("ODIN_INDEX_SHARDS", &mut self.storage.index_shards)?;
+        env_override("ODIN_SEARCH_THREADS", &mut self.storage.search_threads)?;
+        env_override("ODIN_READER_RELOAD", &mut self.storage.reader_reload)?;
+        env_override(
+            "ODIN_READER_RELOAD_INTERVAL_MS",
+            &mut self.storage.reader_reload_interval_ms,
+        )?;
+        env_override(
+            "ODIN_COMMIT_BATCH_DOCS",
+            &mut self.storage.commit_batch_docs,
+        )?;
+        env_override(
+            "ODIN_COMMIT_INTERVAL_MS",
+            &mut self.storage.commit_interval_ms,
+        )?;
+        env_override("ODIN_ARCHIVE_PAGES", &mut self.storage.archive_pages)?;
+        env_override("ODIN_QUOTA_MAX_BYTES", &mut self.quota.max_bytes)?;
+        env_override("ODIN_QUOTA_POLICY", &mut self.quota.policy)?;
+        // The name older deployments used; the prefixed one wins when both are set.
+        env_override("CONCURRENT_FETCH_LIMIT", &mut self.ingest.fetch_concurrency)?;
+        env_override("ODIN_FETCH_CONCURRENCY", &mut self.ingest.fetch_concurrency)?;
+        env_override(
+            "ODIN_FETCH_TIMEOUT_SECS",
+            &mut self.ingest.fetch_timeout_secs,
+        )?;
+        env_override("ODIN_MAX_INGEST_URLS", &mut self.ingest.max_urls)?;
+        env_override("ODIN_EXCERPT_LEN", &mut self.ingest.excerpt_len)?;
+        env_override("ODIN_INGEST_WORKERS", &mut self.ingest.workers)?;
+        env_override(
+            "ODIN_INGEST_QUEUE_CAPACITY",
+            &mut self.ingest.queue_capacity,
+        )?;
+        env_override(
+            "ODIN_INGEST_MAX_CONNECTIONS_PER_HOST",
+            &mut self.ingest.max_connections_per_host,
+        )?;
+        env_override(
+            "ODIN_INGEST_POOL_IDLE_PER_HOST",
+            &mut self.ingest.pool_idle_per_host,
+        )?;
+        env_override(
+            "ODIN_INGEST_POOL_IDLE_TIMEOUT_SECS",
+            &mut self.ingest.pool_idle_timeout_secs,
+        )?;
+        env_override("ODIN_INGEST_HTTP2", &mut self.ingest.http2)?;
+        env_override(
+            "ODIN_INGEST_DNS_CACHE_TTL_SECS",
+            &mut self.ingest.dns_cache_ttl_secs,
+        )?;
+        env_override(
+            "ODIN_INGEST_EXTRACT_TIMEOUT_SECS",
+            &mut self.ingest.extract_timeout_secs,
+        )?;
+        env_override(
+            "ODIN_INGEST_MAX_PAGE_BYTES",
+            &mut self.ingest.max_page_bytes,
+        )?;
+        env_override("ODIN_INGEST_MAX_DOM_NODES", &mut self.ingest.max_dom_nodes)?;
+        env_override(
+            "ODIN_INGEST_MAX_TEXT_CHARS",
+            &mut self.ingest.max_text_chars,
+        )?;
+        env_override("ODIN_INGEST_MAX_ATTEMPTS", &mut self.ingest.max_attempts)?;
+        env_override(
+            "ODIN_INGEST_RETRY_BACKOFF_SECS",
+            &mut self.ingest.retry_backoff_secs,
+        )?;
+        env_override(
+            "ODIN_INGEST_RETRY_MAX_BACKOFF_SECS",
+            &mut self.ingest.retry_max_backoff_secs,
+        )?;
+        env_override(
+            "ODIN_INGEST_RESPECT_ROBOTS",
+            &mut self.ingest.respect_robots,
+        )?;
+        env_override(
+            "ODIN_INGEST_ROBOTS_CACHE_SECS",
+            &mut self.ingest.robots_cache_secs,
+        )?;
+        env_override("ODIN_INGEST_HOST_DELAY_MS", &mut self.ingest.host_delay_ms)?;
+        env_override("ODIN_RATE_LIMIT_ENABLED", &mut self.rate_limit.enabled)?;
+        env_override(
+            "ODIN_RATE_LIMIT_SEARCH_PER_MINUTE",
+            &mut self.rate_limit.search_per_minute,
+        )?;
+        env_override(
+            "ODIN_RATE_LIMIT_INGEST_PER_MINUTE",
+            &mut self.rate_limit.ingest_per_minute,
+        )?;
+        env_override("ODIN_RATE_LIMIT_BURST", &mut self.rate_limit.burst)?;
+        env_override(
+            "ODIN_OPTIMIZE_INTERVAL_SECS",
+            &mut self.maintenance.optimize_interval_secs,
+        )?;
+        env_override("ODIN_OPTIMIZE_IDLE_SECS", &mut self.maintenance.idle_secs)?;
+        env_override(
+            "ODIN_OPTIMIZE_MIN_SEGMENTS",
+            &mut self.maintenance.min_segments,
+        )?;
+        env_override(
+            "ODIN_DB_MAINTENANCE_INTERVAL_SECS",
+            &mut self.maintenance.db_interval_secs,
+        )?;
+        env_override(
+            "ODIN_DB_MAINTENANCE_DELETED_ROWS",
+            &mut self.maintenance.db_deleted_rows,
+        )?;
+        env_override(
+            "ODIN_RECONCILE_INTERVAL_SECS",
+            &mut self.maintenance.reconcile_interval_secs,
+        )?;
+        env_override("ODIN_SLOW_QUERY_MS", &mut self.slow_log.query_ms)?;
+        env_override("ODIN_SLOW_SEARCH_MS", &mut self.slow_log.search_ms)?;
+        env_override(
+            "ODIN_SLOW_INDEX_COMMIT_MS",
+            &mut self.slow_log.index_commit_ms,
+        )?;
+        env_override_some("ODIN_OIDC_ISSUER_URL", &mut self.oidc.issuer_url)?;
+        env_override("ODIN_OIDC_CLIENT_ID", &mut self.oidc.client_id)?;
+        env_override_some("ODIN_OIDC_CLIENT_SECRET", &mut self.oidc.client_secret)?;
+        env_override_some("ODIN_OIDC_REDIRECT_URL", &mut self.oidc.redirect_url)?;
+        env_list("ODIN_OIDC_SCOPES", &mut self.oidc.scopes)?;
+        env_override("ODIN_OIDC_USERNAME_CLAIM", &mut self.oidc.username_claim)?;
+        env_override("ODIN_OIDC_CREATE_USERS", &mut self.oidc.create_users)?;
+        env_override("ODIN_OIDC_GROUPS_CLAIM", &mut self.oidc.groups_claim)?;
+        env_list("ODIN_OIDC_ADMIN_GROUPS", &mut self.oidc.admin_groups)?;
+        env_override(
+            "ODIN_OIDC_POST_LOGIN_REDIRECT",
+            &mut self.oidc.post_login_redirect,
+        )?;
+        env_override_some("ODIN_GRPC_LISTEN_ADDR", &mut self.grpc.listen_addr)?;
+        env_override_some("ODIN_TELEGRAM_BOT_TOKEN", &mut self.telegram.bot_token)?;
+        env_override_some("ODIN_TELEGRAM_API_TOKEN", &mut self.telegram.api_token)?;
+        env_list(
+            "ODIN_TELEGRAM_ALLOWED_CHAT_IDS",
+            &mut self.telegram.allowed_chat_ids,
+        )?;
+        env_override("ODIN_TELEGRAM_API_URL", &mut self.telegram.api_url)?;
+        env_override("ODIN_READWISE_API_URL", &mut self.readwise.api_url)?;
+        env_override(
+            "ODIN_READWISE_SYNC_INTERVAL_SECS",
+            &mut self.readwise.sync_interval_secs,
+        )?;
+        env_override("ODIN_READWISE_BATCH_SIZE", &mut self.readwise.batch_size)?;
+        env_override(
+            "ODIN_FEED_READER_SYNC_INTERVAL_SECS",
+            &mut self.feed_reader.sync_interval_secs,
+        )?;
+        env_override(
+            "ODIN_FEEDS_POLL_INTERVAL_SECS",
+            &mut self.feeds.poll_interval_secs,
+        )?;
+        env_override("ODIN_FEEDS_MAX_FEEDS", &mut self.feeds.max_feeds)?;
+        env_override_some("ODIN_ACTIVITYPUB_BASE_URL", &mut self.activitypub.base_url)?;
+        env_override("ODIN_ACTIVITYPUB_USERNAME", &mut self.activitypub.username)?;
+        env_override_some(
+            "ODIN_ACTIVITYPUB_DISPLAY_NAME",
+            &mut self.activitypub.display_name,
+        )?;
+        env_override_some("ODIN_ACTIVITYPUB_SUMMARY", &mut self.activitypub.summary)?;
+        env_override_some("ODIN_WAYBACK_ACCESS_KEY", &mut self.wayback.access_key)?;
+        env_override_some("ODIN_WAYBACK_SECRET_KEY", &mut self.wayback.secret_key)?;
+        env_override("ODIN_WAYBACK_SUBMIT_ALL", &mut self.wayback.submit_all)?;
+        env_override("ODIN_WAYBACK_API_URL", &mut self.wayback.api_url)?;
+        env_override_some("ODIN_OTLP_ENDPOINT", &mut self.telemetry.otlp_endpoint)?;
+        env_override("ODIN_OTEL_SERVICE_NAME", &mut self.telemetry.service_name)?;
+        env_override("ODIN_OTEL_SAMPLE_RATIO", &mut self.telemetry.sample_ratio)?;
+        Ok(())
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        if self.log.level.trim().is_empty() {
+            anyhow::bail!("log.level must not be empty");
+        }
+        if self.server.health_timeout_ms == 0 {
+            anyhow::bail!("server.health_timeout_ms must be at least 1");
+        }
+        if !self.server.tcp_enabled && self.server.unix_socket_path.is_none() {
+            anyhow::bail!("server.unix_socket_path is required when server.tcp_enabled is false");
+        }
+        if self.tls.cert_path.is_some() != self.tls.key_path.is_some() {
+            anyhow::bail!("tls.cert_path and tls.key_path must be set together");
+        }
+        if self.tls.reload_interval_secs == 0 {
+            anyhow::bail!("tls.reload_interval_secs must be at least 1");
+        }
+        for (key, list) in [
+            ("cors.allowed_origins", &self.cors.allowed_origins),
+            ("cors.allowed_methods", &self.cors.allowed_methods),
+            ("cors.allowed_headers", &self.cors.allowed_headers),
+        ] {
+            if CorsConfig::is_any(list) && list.len() > 1 {
+                anyhow::bail!("{} cannot mix \"*\" with other entries", key);
+            }
+        }
+        if !CorsConfig::is_any(&self.cors.allowed_origins) {
+            for origin in &self.cors.allowed_origins {
+                HeaderValue::from_str(origin)
+                    .map_err(|_| anyhow::anyhow!("invalid cors origin {:?}", origin))?;
+            }
+        }
+        if !CorsConfig::is_any(&self.cors.allowed_methods) {
+            for method in &self.cors.allowed_methods {
+                Method::from_str(method)
+                    .map_err(|_| anyhow::anyhow!("invalid cors method {:?}", method))?;
+            }
+        }
+        if !CorsConfig::is_any(&self.cors.allowed_headers) {
+            for header in &self.cors.allowed_headers {
+                HeaderName::from_str(header)
+                    .map_err(|_| anyhow::anyhow!("invalid cors header {:?}", header))?;
+            }
+        }
+        if self.auth.rotation_grace_secs > self.auth.max_rotation_grace_secs {
+            anyhow::bail!("auth.rotation_grace_secs must not exceed auth.max_rotation_grace_secs");
+        }
+        if self.auth.session_ttl_secs == 0 {
+            anyhow::bail!("auth.session_ttl_secs must be at least 1");
+        }
+        if self.storage.db_max_connections == 0 {
+            anyhow::bail!("storage.db_max_connections must be at least 1");
+        }
+        if self.ingest.fetch_concurrency == 0 {
+            anyhow::bail!("ingest.fetch_concurrency must be at least 1");
+        }
+        if self.ingest.max_urls == 0 {
+            anyhow::bail!("ingest.max_urls must be at least 1");
+        }
+        if self.ingest.workers == 0 {
+            anyhow::bail!("ingest.workers must be at least 1");
+        }
+        if self.ingest.extract_timeout_secs == 0 {
+            anyhow::bail!("ingest.extract_timeout_secs must be at least 1");
+        }
+        if self.ingest.max_page_bytes == 0
+            || self.ingest.max_dom_nodes == 0
+            || self.ingest.max_text_chars == 0
+        {
+            anyhow::bail!(
+                "ingest.max_page_bytes, max_dom_nodes, and max_text_chars must be at least 1"
+            );
+        }
+        if self.ingest.max_attempts == 0 || self.ingest.retry_backoff_secs == 0 {
+            anyhow::bail!("ingest.max_attempts and retry_backoff_secs must be at least 1");
+        }
+        if self.ingest.retry_max_backoff_secs < self.ingest.retry_backoff_secs {
+            anyhow::bail!("ingest.retry_max_backoff_secs must be at least retry_backoff_secs");
+        }
+        if self.ingest.queue_capacity < self.ingest.max_urls {
+            anyhow::bail!("ingest.queue_capacity must be at least ingest.max_urls");
+        }
+        // Tantivy refuses writer arenas smaller than 15 MB.
+        if self.storage.writer_heap_bytes < 15_000_000 {
+            anyhow::bail!("storage.writer_heap_bytes must be at least 15000000");
+        }
+        if !(1..=64).contains(&self.storage.index_shards) {
+            anyhow::bail!("storage.index_shards must be between 1 and 64");
+        }
+        if self.storage.reader_reload == ReaderReload::Interval
+            && self.storage.reader_reload_interval_ms == 0
+        {
+            anyhow::bail!("storage.reader_reload_interval_ms must be at least 1");
+        }
+        if self.storage.commit_batch_docs == 0 {
+            anyhow::bail!("storage.commit_batch_docs must be at least 1");
+        }
+        // Each thread gets an equal share of the heap, bounded like a single arena.
+        if let Some(per_thread) = self
+            .storage
+            .writer_heap_bytes
+            .checked_div(self.storage.writer_threads)
+        {
+            if per_thread < 15_000_000 {
+                anyhow::bail!(
+                    "storage.writer_heap_bytes must be at least 15000000 per storage.writer_threads"
+                );
+            }
+            if per_thread >= u32::MAX as usize - 1_000_000 {
+                anyhow::bail!(
+                    "storage.writer_heap_bytes per storage.writer_threads must be under 4 GB"
+                );
+            }
+        }
+        if self.maintenance.min_segments < 2 {
+            anyhow::bail!("maintenance.min_segments must be at least 2");
+        }
+        if !(0.0..=1.0).contains(&self.telemetry.sample_ratio) {
+            anyhow::bail!("telemetry.sample_ratio must be between 0.0 and 1.0");
+        }
+        if let Some(endpoint) = &self.telemetry.otlp_endpoint
+            && !matches!(endpoint.scheme(), "http" | "https")
+        {
+            anyhow::bail!("telemetry.otlp_endpoint {} must be http or https", endpoint);
+        }
+        if let Some(issuer) = &self.oidc.issuer_url {
+            if !matches!(issuer.scheme(), "http" | "https") {
+                anyhow::bail!("oidc.issuer_url {} must be http or https", issuer);
+            }
+            if self.oidc.client_id.trim().is_empty() {
+                anyhow::bail!("oidc.client_id is required when oidc.issuer_url is set");
+            }
+            if self.oidc.redirect_url.is_none() {
+                anyhow::bail!("oidc.redirect_url is required when oidc.issuer_url is set");
+            }
+            if !self.oidc.scopes.iter().any(|scope| scope == "openid") {
+                anyhow::bail!("oidc.scopes must include \"openid\"");
+            }
+            if self.oidc.username_claim.trim().is_empty() {
+                anyhow::bail!("oidc.username_claim cannot be empty");
+            }
+            // An absolute URL here would make the callback an open redirect.
+            if !self.oidc.post_login_redirect.starts_with('/')
+                || self.oidc.post_login_redirect.starts_with("//")
+            {
+                anyhow::bail!("oidc.post_login_redirect must be a path starting with /");
+            }
+        }
+        if self.telegram.bot_token.is_some()
+            && self.telegram.api_token.as_deref().is_none_or(str::is_empty)
+        {
+            anyhow::bail!("telegram.api_token is required when telegram.bot_token is set");
+        }
+        if !matches!(self.telegram.api_url.scheme(), "http" | "https") {
+            anyhow::bail!(
+                "telegram.api_url {} must be http or https",
+                self.telegram.api_url
+            );
+        }
+        if !matches!(self.readwise.api_url.scheme(), "http" | "https") {
+            anyhow::bail!(
+                "readwise.api_url {} must be http or https",
+                self.readwise.api_url
+            );
+        }
+        if self.readwise.batch_size == 0 {
+            anyhow::bail!("readwise.batch_size must be at least 1");
+        }
+        if let Some(base_url) = &self.activitypub.base_url {
+            if !matches!(base_url.scheme(), "http" | "https") || base_url.host_str().is_none() {
+                anyhow::bail!(
+                    "activitypub.base_url {} must be an http(s) origin",
+                    base_url
+                );
+            }
+            if self.activitypub.username.trim().is_empty() {
+                anyhow::bail!("activitypub.username is required when activitypub.base_url is set");
+            }
+        }
+        if self.wayback.access_key.is_some() != self.wayback.secret_key.is_some() {
+            anyhow::bail!("wayback.access_key and wayback.secret_key must be set together");
+        }
+        if self.wayback.submit_all && self.wayback.access_key.is_none() {
+            anyhow::bail!("wayback.submit_all needs wayback.access_key and wayback.secret_key");
+        }
+        if !matches!(self.wayback.api_url.scheme(), "http" | "https") {
+            anyhow::bail!(
+                "wayback.api_url {} must be http or https",
+                self.wayback.api_url
+            );
+        }
+        for webhook in &self.webhooks {
+            if !matches!(webhook.url.scheme(), "http" | "https") {
+                anyhow::bail!("webhook url {} must be http or https", webhook.url);
+            }
+        }
+        for notifier in &self.notifiers {
+            if !matches!(notifier.url.scheme(), "http" | "https") {
+                anyhow::bail!("notifier url {} must be http or https", notifier.url);
+            }
+            if notifier.kind == NotifierKind::Matrix
+                && (notifier.room_id.is_none() || notifier.token.is_none())
+            {
+                anyhow::bail!("matrix notifier {} needs room_id and token", notifier.url);
+            }
+            if notifier
+                .searches
+                .iter()
+                .any(|query| query.trim().is_empty())
+            {
+                anyhow::bail!("notifier {} has an empty search", notifier.url);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Overwrite `target` with the parsed value of `key` when the variable is set.
+fn env_override<T>(key: &str, target: &mut T) -> anyhow::Result<()>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    if let Some(value) = env_value(key)? {
+        *target = value;
+    }
+    Ok(())
+}
+
+/// Like [`env_override`], for settings that are unset by default.
+fn env_override_some<T>(key: &str, target: &mut Option<T>) -> anyhow::Result<()>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    if let Some(value) = env_value(key)? {
+        *target = Some(value);
+    }
+    Ok(())
+}
+
+/// Replace `target` with the comma-separated entries of `key` when the variable is set.
+fn env_list<T>(key: &str, target: &mut Vec<T>) -> anyhow::Result<()>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    if let Some(raw) = env_value::<String>(key)? {
+        *target = raw
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| {
+                entry
+                    .parse()
+                    .map_err(|err| anyhow::anyhow!("invalid {} entry {:?}: {}", key, entry, err))
+            })
+            .collect::<anyhow::Result<_>>()?;
+    }
+    Ok(())
+}
+
+fn env_value<T>(key: &str) -> anyhow::Result<Option<T>>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    match env::var(key) {
+        Ok(raw) => raw
+            .trim()
+            .parse()
+            .map(Some)
+            .map_err(|err| anyhow::anyhow!("invalid {}: {}", key, err)),
+        Err(env::VarError::NotPresent) => Ok(None),
+        Err(env::VarError::NotUnicode(_)) => anyhow::bail!("{} is not valid unicode", key),
+    }
+}
@@ -0,0 +1,57 @@
+//! Extracted page text as kept in `bookmark_contents`, so rebuilds and readers never
+//! refetch a page, and fetched pages as archived in `bookmark_archives`.
+
+use std::io::{Read, Write};
+
+use anyhow::Context;
+use flate2::Compression;
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+
+/// `bookmark_contents.encoding` of zlib-compressed UTF-8 text, used for every new write.
+pub(crate) const ZLIB: &str = "zlib";
+
+/// `bookmark_contents.encoding` of uncompressed text stored before compression; database
+/// maintenance rewrites these rows as `zlib`.
+pub(crate) const PLAIN: &str = "plain";
+
+pub(crate) fn compress(text: &str) -> anyhow::Result<Vec<u8>> {
+    compress_bytes(text.as_bytes())
+}
+
+/// zlib-compress bytes that need not be UTF-8, such as an archived page.
+pub(crate) fn compress_bytes(bytes: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(bytes).context("compress content")?;
+    encoder.finish().context("compress content")
+}
+
+/// Bytes of a stored body in the given encoding, without requiring UTF-8.
+pub(crate) fn decode_bytes(encoding: &str, body: Vec<u8>) -> anyhow::Result<Vec<u8>> {
+    match encoding {
+        ZLIB => {
+            let mut bytes = Vec::new();
+            ZlibDecoder::new(body.as_slice())
+                .read_to_end(&mut bytes)
+                .context("decompress content")?;
+            Ok(bytes)
+        }
+        PLAIN => Ok(body),
+        other => anyhow::bail!("unknown content encoding `{}`", other),
+    }
+}
+
+/// Text of a stored body in the given encoding.
+pub(crate) fn decode(encoding: &str, body: Vec<u8>) -> anyhow::Result<String> {
+    match encoding {
+        ZLIB => {
+            let mut text = String::new();
+            ZlibDecoder::new(body.as_slice())
+                .read_to_string(&mut text)
+                .context("decompress content")?;
+            Ok(text)
+        }
+        PLAIN => String::from_utf8(body).context("decode content"),
+        other => anyhow::bail!("unknown content encoding `{}`", other),
+    }
+}
@@ -0,0 +1,433 @@
+use std::time::Duration;
+
+use anyhow::Context;
+use log::LevelFilter;
+use sqlx::sqlite::SqliteConnectOptions;
+use sqlx::{ConnectOptions, SqlitePool};
+use tracing::info;
+
+/// Owner of bookmarks created with the bootstrap `ADMIN_TOKEN`.
+pub const ADMIN_USER_ID: i64 = 1;
+
+/// Namespace used when a request names none and its key is not bound to one.
+pub const DEFAULT_NAMESPACE: &str = "default";
+
+/// Ordered schema migrations; entry `n` moves `PRAGMA user_version` from `n` to `n + 1`.
+const MIGRATIONS: &[&str] = &[
+    r#"
+    CREATE TABLE IF NOT EXISTS bookmarks (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        url TEXT NOT NULL UNIQUE,
+        title TEXT,
+        excerpt TEXT,
+        status TEXT NOT NULL,
+        http_status INTEGER,
+        content_type TEXT,
+        error TEXT,
+        created_at TEXT NOT NULL,
+        updated_at TEXT NOT NULL,
+        fetched_at TEXT,
+        indexed_at TEXT
+    );
+    CREATE INDEX IF NOT EXISTS idx_bookmarks_status ON bookmarks(status);
+    "#,
+    r#"
+    CREATE TABLE users (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        username TEXT NOT NULL UNIQUE,
+        token_hash TEXT UNIQUE,
+        is_admin INTEGER NOT NULL DEFAULT 0,
+        created_at TEXT NOT NULL
+    );
+    INSERT INTO users (id, username, token_hash, is_admin, created_at)
+    VALUES (1, 'admin', NULL, 1, strftime('%Y-%m-%dT%H:%M:%SZ', 'now'));
+
+    CREATE TABLE bookmarks_v2 (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        owner_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+        url TEXT NOT NULL,
+        title TEXT,
+        excerpt TEXT,
+        status TEXT NOT NULL,
+        http_status INTEGER,
+        content_type TEXT,
+        error TEXT,
+        created_at TEXT NOT NULL,
+        updated_at TEXT NOT NULL,
+        fetched_at TEXT,
+        indexed_at TEXT,
+        UNIQUE (owner_id, url)
+    );
+    INSERT INTO bookmarks_v2 (id, owner_id, url, title, excerpt, status, http_status, content_type, error, created_at, updated_at, fetched_at, indexed_at)
+    SELECT id, 1, url, title, excerpt, status, http_status, content_type, error, created_at, updated_at, fetched_at, indexed_at
+    FROM bookmarks;
+    DROP TABLE bookmarks;
+    ALTER TABLE bookmarks_v2 RENAME TO bookmarks;
+    CREATE INDEX idx_bookmarks_status ON bookmarks(status);
+    CREATE INDEX idx_bookmarks_owner ON bookmarks(owner_id, updated_at);
+    "#,
+    r#"
+    CREATE TABLE api_keys (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+        name TEXT NOT NULL,
+        key_hash TEXT NOT NULL UNIQUE,
+        scopes TEXT NOT NULL,
+        created_at TEXT NOT NULL,
+        last_used_at TEXT,
+        revoked_at TEXT
+    );
+    INSERT INTO api_keys (user_id, name, key_hash, scopes, created_at)
+    SELECT id, 'default', token_hash,
+           CASE WHEN is_admin THEN 'read,ingest,admin' ELSE 'read,ingest' END,
+           created_at
+    FROM users
+    WHERE token_hash IS NOT NULL;
+    CREATE INDEX idx_api_keys_user ON api_keys(user_id);
+
+    CREATE TABLE users_v3 (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        username TEXT NOT NULL UNIQUE,
+        is_admin INTEGER NOT NULL DEFAULT 0,
+        created_at TEXT NOT NULL
+    );
+    INSERT INTO users_v3 (id, username, is_admin, created_at)
+    SELECT id, username, is_admin, created_at FROM users;
+    DROP TABLE users;
+    ALTER TABLE users_v3 RENAME TO users;
+    "#,
+    r#"
+    CREATE TABLE bookmark_contents (
+        bookmark_id INTEGER PRIMARY KEY REFERENCES bookmarks(id) ON DELETE CASCADE,
+        body TEXT NOT NULL
+    );
+    "#,
+    r#"
+    CREATE TABLE jobs (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        owner_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+        kind TEXT NOT NULL,
+        state TEXT NOT NULL,
+        done INTEGER NOT NULL DEFAULT 0,
+        total INTEGER,
+        error TEXT,
+        created_at TEXT NOT NULL,
+        updated_at TEXT NOT NULL,
+        finished_at TEXT
+    );
+    CREATE INDEX idx_jobs_owner ON jobs(owner_id, id);
+    "#,
+    r#"
+    CREATE TABLE settings (
+        key TEXT PRIMARY KEY,
+        value TEXT NOT NULL,
+        updated_at TEXT NOT NULL
+    );
+    "#,
+    r#"
+    ALTER TABLE api_keys ADD COLUMN expires_at TEXT;
+    "#,
+    r#"
+    CREATE TABLE bookmarks_v3 (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        owner_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+        namespace TEXT NOT NULL DEFAULT 'default',
+        url TEXT NOT NULL,
+        title TEXT,
+        excerpt TEXT,
+        status TEXT NOT NULL,
+        http_status INTEGER,
+        content_type TEXT,
+        error TEXT,
+        created_at TEXT NOT NULL,
+        updated_at TEXT NOT NULL,
+        fetched_at TEXT,
+        indexed_at TEXT,
+        UNIQUE (owner_id, namespace, url)
+    );
+    INSERT INTO bookmarks_v3 (id, owner_id, url, title, excerpt, status, http_status, content_type, error, created_at, updated_at, fetched_at, indexed_at)
+    SELECT id, owner_id, url, title, excerpt, status, http_status, content_type, error, created_at, updated_at, fetched_at, indexed_at
+    FROM bookmarks;
+    DROP TABLE bookmarks;
+    ALTER TABLE bookmarks_v3 RENAME TO bookmarks;
+    CREATE INDEX idx_bookmarks_status ON bookmarks(status);
+    CREATE INDEX idx_bookmarks_owner ON bookmarks(owner_id, namespace, updated_at);
+
+    ALTER TABLE api_keys ADD COLUMN namespace TEXT;
+    "#,
+    r#"
+    ALTER TABLE bookmark_contents ADD COLUMN encoding TEXT NOT NULL DEFAULT 'plain';
+    "#,
+    r#"
+    CREATE TABLE sessions (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        token_hash TEXT NOT NULL UNIQUE,
+        key_id INTEGER NOT NULL REFERENCES api_keys(id) ON DELETE CASCADE,
+        created_at TEXT NOT NULL,
+        expires_at TEXT NOT NULL
+    );
+    CREATE INDEX idx_sessions_expires ON sessions(expires_at);
+    "#,
+    r#"
+    CREATE TABLE readwise_sync (
+        user_id INTEGER PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+        token TEXT NOT NULL,
+        last_bookmark_id INTEGER NOT NULL DEFAULT 0,
+        last_synced_at TEXT,
+        last_error TEXT,
+        created_at TEXT NOT NULL
+    );
+    "#,
+    r#"
+    CREATE TABLE notification_digests (
+        target TEXT PRIMARY KEY,
+        sent_at TEXT NOT NULL
+    );
+    "#,
+    r#"
+    CREATE TABLE public_bookmarks (
+        bookmark_id INTEGER PRIMARY KEY,
+        owner_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+        published_at TEXT NOT NULL
+    );
+    CREATE TABLE activitypub_keys (
+        id INTEGER PRIMARY KEY CHECK (id = 1),
+        private_key_pem TEXT NOT NULL,
+        public_key_pem TEXT NOT NULL
+    );
+    CREATE TABLE activitypub_followers (
+        actor TEXT PRIMARY KEY,
+        inbox TEXT NOT NULL,
+        created_at TEXT NOT NULL
+    );
+    "#,
+    r#"
+    CREATE TABLE wayback_snapshots (
+        bookmark_id INTEGER PRIMARY KEY REFERENCES bookmarks(id) ON DELETE CASCADE,
+        status TEXT NOT NULL,
+        archive_url TEXT,
+        error TEXT,
+        requested_at TEXT NOT NULL,
+        completed_at TEXT
+    );
+    CREATE INDEX idx_wayback_snapshots_status ON wayback_snapshots(status, requested_at);
+    "#,
+    r#"
+    CREATE TABLE browser_sync_entries (
+        owner_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+        namespace TEXT NOT NULL,
+        source TEXT NOT NULL,
+        url TEXT NOT NULL,
+        folder TEXT NOT NULL,
+        synced_at TEXT NOT NULL,
+        PRIMARY KEY (owner_id, namespace, source, url, folder)
+    );
+    "#,
+    r#"
+    CREATE TABLE feed_reader_sync (
+        user_id INTEGER PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+        key_id INTEGER NOT NULL REFERENCES api_keys(id) ON DELETE CASCADE,
+        namespace TEXT NOT NULL,
+        kind TEXT NOT NULL,
+        url TEXT NOT NULL,
+        username TEXT,
+        token TEXT NOT NULL,
+        last_synced_at TEXT,
+        last_error TEXT,
+        created_at TEXT NOT NULL
+    );
+    CREATE TABLE feed_reader_entries (
+        user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+        entry_id TEXT NOT NULL,
+        seen_at TEXT NOT NULL,
+        PRIMARY KEY (user_id, entry_id)
+    );
+    "#,
+    // `url` is the normalized form that is fetched and deduplicated on, under
+    // UNIQUE (owner_id, namespace, url); `original_url` keeps what was submitted.
+    r#"
+    ALTER TABLE bookmarks ADD COLUMN original_url TEXT;
+    UPDATE bookmarks SET original_url = url;
+    "#,
+    // Fetch totals per domain and hour since the Unix epoch; `latency_ms` is a sum.
+    r#"
+    CREATE TABLE domain_stats (
+        domain TEXT NOT NULL,
+        hour INTEGER NOT NULL,
+        fetches INTEGER NOT NULL,
+        failures INTEGER NOT NULL,
+        bytes INTEGER NOT NULL,
+        latency_ms INTEGER NOT NULL,
+        max_latency_ms INTEGER NOT NULL,
+        PRIMARY KEY (domain, hour)
+    );
+    CREATE INDEX idx_domain_stats_hour ON domain_stats(hour);
+    "#,
+    // Deletes used to leave `public_bookmarks` rows behind unless ActivityPub was on.
+    r#"
+    DELETE FROM public_bookmarks WHERE bookmark_id NOT IN (SELECT id FROM bookmarks);
+    "#,
+    // `attempts` counts fetches in the current ingest; a `queued` row waiting to be
+    // retried has `next_attempt_at` set.
+    r#"
+    ALTER TABLE bookmarks ADD COLUMN attempts INTEGER NOT NULL DEFAULT 0;
+    ALTER TABLE bookmarks ADD COLUMN next_attempt_at TEXT;
+    "#,
+    // Tag names are per owner and namespace; rows no bookmark links to are dropped
+    // when tags are replaced.
+    r#"
+    CREATE TABLE tags (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        owner_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+        namespace TEXT NOT NULL,
+        name TEXT NOT NULL,
+        UNIQUE (owner_id, namespace, name)
+    );
+    CREATE TABLE bookmark_tags (
+        bookmark_id INTEGER NOT NULL REFERENCES bookmarks(id) ON DELETE CASCADE,
+        tag_id INTEGER NOT NULL REFERENCES tags(id) ON DELETE CASCADE,
+        PRIMARY KEY (bookmark_id, tag_id)
+    );
+    CREATE INDEX idx_bookmark_tags_tag ON bookmark_tags(tag_id);
+    "#,
+    // The page as last fetched, zlib-compressed, with the `Content-Type` it was served as.
+    r#"
+    CREATE TABLE bookmark_archives (
+        bookmark_id INTEGER PRIMARY KEY REFERENCES bookmarks(id) ON DELETE CASCADE,
+        body BLOB NOT NULL,
+        encoding TEXT NOT NULL,
+        content_type TEXT NOT NULL,
+        fetched_at TEXT NOT NULL
+    );
+    "#,
+    // How the fetched body was read: `html`, `pdf`, or `text`. Only HTML was indexed before.
+    r#"
+    ALTER TABLE bookmarks ADD COLUMN document_type TEXT;
+    UPDATE bookmarks SET document_type = 'html' WHERE status IN ('indexed', 'partial');
+    "#,
+    // RSS/Atom subscriptions, polled as the key that added them; `feed_entries` holds
+    // the entry ids already ingested or skipped.
+    r#"
+    CREATE TABLE feeds (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        owner_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+        key_id INTEGER NOT NULL REFERENCES api_keys(id) ON DELETE CASCADE,
+        namespace TEXT NOT NULL,
+        url TEXT NOT NULL,
+        title TEXT,
+        last_polled_at TEXT,
+        last_error TEXT,
+        created_at TEXT NOT NULL,
+        UNIQUE (owner_id, namespace, url)
+    );
+    CREATE TABLE feed_entries (
+        feed_id INTEGER NOT NULL REFERENCES feeds(id) ON DELETE CASCADE,
+        guid TEXT NOT NULL,
+        seen_at TEXT NOT NULL,
+        PRIMARY KEY (feed_id, guid)
+    );
+    "#,
+    // Deleting bookmarks became its own scope; keys that could ingest keep deleting.
+    r#"
+    UPDATE api_keys SET scopes = scopes || ',delete'
+    WHERE ',' || scopes || ',' LIKE '%,ingest,%';
+    "#,
+    // Webhooks registered over the API, each sent one namespace's events; `events`
+    // is comma-separated like `api_keys.scopes`.
+    r#"
+    CREATE TABLE webhooks (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        owner_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+        namespace TEXT NOT NULL,
+        url TEXT NOT NULL,
+        secret TEXT,
+        events TEXT NOT NULL,
+        created_at TEXT NOT NULL,
+        last_attempt_at TEXT,
+        last_error TEXT,
+        UNIQUE (owner_id, namespace, url)
+    );
+    "#,
+    // SHA-256 of a bookmark's extracted text, so a URL that serves the same page as a
+    // saved one is merged into it after its first fetch.
+    r#"
+    ALTER TABLE bookmarks ADD COLUMN content_hash TEXT;
+    CREATE INDEX idx_bookmarks_content_hash ON bookmarks(owner_id, namespace, content_hash);
+    "#,
+    // URLs of duplicates merged into a bookmark, normalized and as submitted, so
+    // lookups of either still find the bookmark that absorbed them.
+    r#"
+    CREATE TABLE bookmark_aliases (
+        bookmark_id INTEGER NOT NULL REFERENCES bookmarks(id) ON DELETE CASCADE,
+        url TEXT NOT NULL,
+        original_url TEXT NOT NULL,
+        PRIMARY KEY (bookmark_id, url)
+    );
+    CREATE INDEX idx_bookmark_aliases_url ON bookmark_aliases(url);
+    CREATE INDEX idx_bookmark_aliases_original_url ON bookmark_aliases(original_url);
+    "#,
+];
+
+/// Have sqlx log statements slower than `threshold_ms` at warn level; 0 disables it.
+pub fn log_slow_statements(
+    options: SqliteConnectOptions,
+    threshold_ms: u64,
+) -> SqliteConnectOptions {
+    let level = if threshold_ms == 0 {
+        LevelFilter::Off
+    } else {
+        LevelFilter::Warn
+    };
+    options.log_slow_statements(level, Duration::from_millis(threshold_ms))
+}
+
+/// Bring the database schema up to date, one transaction per migration.
+pub async fn init_db(db: &SqlitePool) -> anyhow::Result<()> {
+    let mut conn = db.acquire().await.context("acquire migration connection")?;
+    let version: i64 = sqlx::query_scalar("PRAGMA user_version")
+        .fetch_one(&mut *conn)
+        .await
+        .context("read schema version")?;
+    if version as usize >= MIGRATIONS.len() {
+        return Ok(());
+    }
+
+    // Table rebuilds drop parents of foreign keys; SQLite only allows toggling
+    // enforcement outside a transaction, so integrity is re-checked at the end.
+    sqlx::query("PRAGMA foreign_keys = OFF")
+        .execute(&mut *conn)
+        .await
+        .context("disable foreign keys")?;
+
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        let next = index + 1;
+        let mut tx = sqlx::Connection::begin(&mut *conn)
+            .await
+            .context("begin migration")?;
+        sqlx::raw_sql(migration)
+            .execute(&mut *tx)
+            .await
+            .with_context(|| format!("apply migration {}", next))?;
+        sqlx::query(&format!("PRAGMA user_version = {}", next))
+            .execute(&mut *tx)
+            .await
+            .context("record schema version")?;
+        tx.commit().await.context("commit migration")?;
+        info!("database migrated to schema version {}", next);
+    }
+
+    let violations: Vec<(String, i64)> =
+        sqlx::query_as("SELECT \"table\", rowid FROM pragma_foreign_key_check")
+            .fetch_all(&mut *conn)
+            .await
+            .context("check foreign keys")?;
+    if let Some((table, rowid)) = violations.first() {
+        anyhow::bail!("foreign key violation in {} row {}", table, rowid);
+    }
+    sqlx::query("PRAGMA foreign_keys = ON")
+        .execute(&mut *conn)
+        .await
+        .context("enable foreign keys")?;
+
+    Ok(())
+}
@@ -0,0 +1,82 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+
+/// Host names remembered before expired entries are pruned.
+const PRUNE_THRESHOLD: usize = 10_000;
+
+struct Entry {
+    resolved_at: Instant,
+    addrs: Vec<SocketAddr>,
+}
+
+/// Resolves host names for the fetch client through the system resolver, reusing each
+/// answer for a fixed time so crawls of one site look its host up once instead of per
+/// connection. Failed or empty lookups are not cached.
+#[derive(Clone)]
+pub(crate) struct DnsCache {
+    ttl: Duration,
+    entries: Arc<Mutex<HashMap<String, Entry>>>,
+}
+
+impl DnsCache {
+    pub(crate) fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    fn cached(&self, host: &str) -> Option<Vec<SocketAddr>> {
+        let entries = self
+            .entries
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        entries
+            .get(host)
+            .filter(|entry| entry.resolved_at.elapsed() < self.ttl)
+            .map(|entry| entry.addrs.clone())
+    }
+
+    fn store(&self, host: String, addrs: Vec<SocketAddr>) {
+        let mut entries = self
+            .entries
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if entries.len() >= PRUNE_THRESHOLD {
+            entries.retain(|_, entry| entry.resolved_at.elapsed() < self.ttl);
+        }
+        entries.insert(
+            host,
+            Entry {
+                resolved_at: Instant::now(),
+                addrs,
+            },
+        );
+    }
+}
+
+impl Resolve for DnsCache {
+    fn resolve(&self, name: Name) -> Resolving {
+        let cache = self.clone();
+        Box::pin(async move {
+            let host = name.as_str().to_string();
+            let addrs = match cache.cached(&host) {
+                Some(addrs) => addrs,
+                None => {
+                    let addrs: Vec<SocketAddr> =
+                        tokio::net::lookup_host((host.as_str(), 0)).await?.collect();
+                    if !addrs.is_empty() {
+                        cache.store(host, addrs.clone());
+                    }
+                    addrs
+                }
+            };
+            let addrs: Addrs = Box::new(addrs.into_iter());
+            Ok(addrs)
+        })
+    }
+}
@@ -0,0 +1,160 @@
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use http_body_util::LengthLimitError;
+use tantivy::TantivyError;
+use tracing::error;
+
+tokio::task_local! {
+    /// The `x-request-id` of the request being handled, echoed in error bodies.
+    pub static REQUEST_ID: String;
+}
+
+#[derive(Debug)]
+pub struct AppError {
+    status: StatusCode,
+    message: String,
+    source: Option<anyhow::Error>,
+}
+
+impl AppError {
+    pub fn bad_request(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::BAD_REQUEST,
+            message: message.into(),
+            source: None,
+        }
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::NOT_FOUND,
+            message: message.into(),
+            source: None,
+        }
+    }
+
+    pub fn unauthorized(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::UNAUTHORIZED,
+            message: message.into(),
+            source: None,
+        }
+    }
+
+    pub fn forbidden(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::FORBIDDEN,
+            message: message.into(),
+            source: None,
+        }
+    }
+
+    pub fn conflict(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::CONFLICT,
+            message: message.into(),
+            source: None,
+        }
+    }
+
+    pub fn unavailable(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::SERVICE_UNAVAILABLE,
+            message: message.into(),
+            source: None,
+        }
+    }
+
+    pub fn insufficient_storage(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::INSUFFICIENT_STORAGE,
+            message: message.into(),
+            source: None,
+        }
+    }
+
+    /// A streamed request body failed mid-read; over-limit uploads become 413s.
+    pub fn upload(err: impl std::error::Error + 'static) -> Self {
+        let mut source: Option<&(dyn std::error::Error + 'static)> = Some(&err);
+        while let Some(inner) = source {
+            if inner.is::<LengthLimitError>() {
+                return Self {
+                    status: StatusCode::PAYLOAD_TOO_LARGE,
+                    message: "request body too large".to_string(),
+                    source: None,
+                };
+            }
+            source = inner.source();
+        }
+        Self::bad_request(format!("failed to read upload: {}", err))
+    }
+
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// The client-facing message; internal causes are only logged.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Log the internal cause, if any, before the error is reported to a client.
+    pub fn log_source(&self) {
+        if let Some(source) = &self.source {
+            error!("{:?}", source);
+        }
+    }
+}
+
+impl From<anyhow::Error> for AppError {
+    fn from(value: anyhow::Error) -> Self {
+        Self {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            message: "internal error".to_string(),
+            source: Some(value),
+        }
+    }
+}
+
+impl From<sqlx::Error> for AppError {
+    fn from(value: sqlx::Error) -> Self {
+        Self {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            message: "database error".to_string(),
+            source: Some(value.into()),
+        }
+    }
+}
+
+impl From<TantivyError> for AppError {
+    fn from(value: TantivyError) -> Self {
+        Self {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            message: "search index error".to_string(),
+            source: Some(value.into()),
+        }
+    }
+}
+
+impl std::fmt::Display for AppError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for AppError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.source.as_ref().map(|source| source.as_ref() as _)
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        self.log_source();
+        let message = match REQUEST_ID.try_with(Clone::clone) {
+            Ok(request_id) => format!("{} (request id {})", self.message, request_id),
+            Err(_) => self.message,
+        };
+        (self.status, message).into_response()
+    }
+}
@@ -0,0 +1,432 @@
+//! The Tantivy index, split into `storage.index_shards` partitions by bookmark id.
+//!
+//! Each bookmark's document lives in exactly one shard, so ingests and deletes touch
+//! one shard's writer and commits and merges stay proportional to a shard's size.
+//! Searches fan out to every shard and score with BM25 statistics summed across all of
+//! them, so merged rankings match those of a single index. Commits are not atomic across
+//! shards; a crash between two shard commits is repaired by the reconciler.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use anyhow::Context;
+use tantivy::query::{Bm25StatisticsProvider, Query};
+use tantivy::schema::{Field, IndexRecordOption};
+use tantivy::{
+    DocAddress, DocId, DocSet, Index, IndexReader, IndexWriter, Score, Searcher,
+    SearcherGeneration, SegmentReader, TERMINATED, TantivyDocument, TantivyError, Term, Warmer,
+    collector,
+};
+use tokio::sync::{Notify, watch};
+use tokio_util::sync::CancellationToken;
+use tracing::warn;
+use url::{Host, Url};
+
+use crate::config::{ReaderReload, StorageConfig};
+
+/// Version of the documents odin writes to the index. Bump it when documents must be
+/// rebuilt even though the schema compares equal, e.g. after changing how a field is filled.
+pub(crate) const INDEX_SCHEMA_VERSION: u32 = 1;
+
+/// Holds `INDEX_SCHEMA_VERSION` and the shard count once the index has been fully built
+/// with them.
+pub(crate) const SCHEMA_VERSION_FILE: &str = "odin_schema_version";
+
+/// Directories of each shard: the index directory itself for an unsharded index,
+/// otherwise one `shard-N` subdirectory per shard.
+pub fn shard_dirs(index_dir: &Path, shards: usize) -> Vec<PathBuf> {
+    if shards == 1 {
+        return vec![index_dir.to_path_buf()];
+    }
+    (0..shards)
+        .map(|shard| index_dir.join(format!("shard-{}", shard)))
+        .collect()
+}
+
+/// The schema version and shard count of the last complete build, if any.
+pub(crate) fn read_schema_version(index_dir: &Path) -> Option<(u32, usize)> {
+    let contents = std::fs::read_to_string(index_dir.join(SCHEMA_VERSION_FILE)).ok()?;
+    let mut parts = contents.split_whitespace();
+    let version = parts.next()?.parse().ok()?;
+    let shards = match parts.next() {
+        Some(shards) => shards.parse().ok()?,
+        None => 1,
+    };
+    Some((version, shards))
+}
+
+pub(crate) fn write_schema_version(index_dir: &Path, shards: usize) -> std::io::Result<()> {
+    std::fs::write(
+        index_dir.join(SCHEMA_VERSION_FILE),
+        format!("{} {}", INDEX_SCHEMA_VERSION, shards),
+    )
+}
+
+#[derive(Clone)]
+pub struct Shard {
+    pub index: Index,
+    pub reader: IndexReader,
+}
+
+/// Values of the `host` field for a page: its host and every parent domain of at least
+/// two labels, lowercased and without a leading `www.`, so that a search restricted to
+/// `example.com` also finds `blog.example.com`. IP addresses are kept whole.
+pub(crate) fn host_terms(url: &str) -> Vec<String> {
+    let Ok(url) = Url::parse(url) else {
+        return vec![];
+    };
+    let host = match url.host() {
+        Some(Host::Domain(domain)) => domain.to_lowercase(),
+        Some(address) => return vec![address.to_string()],
+        None => return vec![],
+    };
+    let host = host.strip_prefix("www.").unwrap_or(&host);
+    let mut terms = vec![host.to_string()];
+    let mut rest = host;
+    while let Some((_, parent)) = rest.split_once('.') {
+        if !parent.contains('.') {
+            break;
+        }
+        terms.push(parent.to_string());
+        rest = parent;
+    }
+    terms
+}
+
+/// Opens the term dictionaries of the given fields on every segment of a reloading
+/// searcher, before it replaces the old one, so the first query on new segments does
+/// not read them in.
+pub struct FieldWarmer {
+    fields: Vec<Field>,
+}
+
+impl FieldWarmer {
+    pub fn new(fields: Vec<Field>) -> Self {
+        Self { fields }
+    }
+}
+
+impl Warmer for FieldWarmer {
+    fn warm(&self, searcher: &Searcher) -> tantivy::Result<()> {
+        for segment in searcher.segment_readers() {
+            for &field in &self.fields {
+                segment.inverted_index(field)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn garbage_collect(&self, _live_generations: &[&SearcherGeneration]) {}
+}
+
+/// Read side of the sharded index. Commits are counted as they are made and become
+/// searchable once the reader refresher reloads past them.
+#[derive(Clone)]
+pub struct SearchIndex {
+    shards: Vec<Shard>,
+    committed: Arc<AtomicU64>,
+    /// The commit count the current searchers include.
+    visible: Arc<watch::Sender<u64>>,
+    commits: Arc<Notify>,
+    /// Readers hold their warmers weakly.
+    _warmer: Arc<FieldWarmer>,
+}
+
+impl SearchIndex {
+    pub fn new(shards: Vec<Shard>, warmer: Arc<FieldWarmer>) -> Self {
+        Self {
+            shards,
+            committed: Arc::default(),
+            visible: Arc::new(watch::channel(0).0),
+            commits: Arc::default(),
+            _warmer: warmer,
+        }
+    }
+
+    pub fn shards(&self) -> &[Shard] {
+        &self.shards
+    }
+
+    /// Any shard's index; all share the schema and tokenizers, e.g. for query parsing.
+    pub fn first(&self) -> &Index {
+        &self.shards[0].index
+    }
+
+    /// One searcher per shard, in shard order.
+    pub fn searchers(&self) -> Vec<Searcher> {
+        self.shards
+            .iter()
+            .map(|shard| shard.reader.searcher())
+            .collect()
+    }
+
+    /// Reload and warm every shard's searcher now, including all commits made so far.
+    pub fn reload(&self) -> tantivy::Result<()> {
+        let committed = self.committed.load(Ordering::Acquire);
+        for shard in &self.shards {
+            shard.reader.reload()?;
+        }
+        self.visible.send_if_modified(|visible| {
+            let advanced = committed > *visible;
+            *visible = (*visible).max(committed);
+            advanced
+        });
+        Ok(())
+    }
+
+    /// Record a commit for the refresher to pick up.
+    pub(crate) fn committed(&self) {
+        self.committed.fetch_add(1, Ordering::AcqRel);
+        self.commits.notify_one();
+    }
+
+    /// Wait until searchers include every commit made before the call.
+    pub async fn refreshed(&self) {
+        let committed = self.committed.load(Ordering::Acquire);
+        let _ = self
+            .visible
+            .subscribe()
+            .wait_for(|visible| *visible >= committed)
+            .await;
+    }
+
+    /// Reload searchers in the background as `storage.reader_reload` says, off the
+    /// commit and query paths, until shutdown.
+    pub(crate) fn start_refresher(&self, storage: &StorageConfig, shutdown: CancellationToken) {
+        let index = self.clone();
+        let policy = storage.reader_reload;
+        let interval = Duration::from_millis(storage.reader_reload_interval_ms);
+        tokio::spawn(async move {
+            loop {
+                match policy {
+                    ReaderReload::OnCommit => tokio::select! {
+                        _ = shutdown.cancelled() => return,
+                        _ = index.commits.notified() => {}
+                    },
+                    ReaderReload::Interval => {
+                        tokio::select! {
+                            _ = shutdown.cancelled() => return,
+                            _ = tokio::time::sleep(interval) => {}
+                        }
+                        if index.committed.load(Ordering::Acquire) <= *index.visible.borrow() {
+                            continue;
+                        }
+                    }
+                }
+                let reloading = index.clone();
+                match tokio::task::spawn_blocking(move || reloading.reload()).await {
+                    Ok(Ok(())) => {}
+                    Ok(Err(err)) => warn!("index reader reload failed: {}", err),
+                    Err(err) => warn!("index reader reload panicked: {}", err),
+                }
+            }
+        });
+    }
+
+    pub fn num_docs(&self) -> u64 {
+        self.shards
+            .iter()
+            .map(|shard| shard.reader.searcher().num_docs())
+            .sum()
+    }
+}
+
+/// Bookmark ids with at least one live document in `searchers`, read from the
+/// `bookmark_id` term dictionary rather than stored documents.
+pub(crate) fn bookmark_ids(
+    searchers: &[Searcher],
+    bookmark_id: Field,
+) -> anyhow::Result<HashSet<i64>> {
+    let mut ids = HashSet::new();
+    let segments = searchers
+        .iter()
+        .flat_map(|searcher| searcher.segment_readers());
+    for segment in segments {
+        let inverted = segment.inverted_index(bookmark_id)?;
+        let alive = segment.alive_bitset();
+        let mut terms = inverted.terms().stream()?;
+        while terms.advance() {
+            let mut postings =
+                inverted.read_postings_from_terminfo(terms.value(), IndexRecordOption::Basic)?;
+            let mut doc = postings.doc();
+            while doc != TERMINATED && alive.is_some_and(|alive| alive.is_deleted(doc)) {
+                doc = postings.advance();
+            }
+            if doc != TERMINATED {
+                let bytes: [u8; 8] = terms
+                    .key()
+                    .try_into()
+                    .context("bookmark_id term is not a u64")?;
+                ids.insert(u64::from_be_bytes(bytes) as i64);
+            }
+        }
+    }
+    Ok(ids)
+}
+
+/// BM25 statistics summed over every shard, so scores from different shards compare.
+pub struct ShardStatistics<'a>(pub &'a [Searcher]);
+
+impl Bm25StatisticsProvider for ShardStatistics<'_> {
+    fn total_num_tokens(&self, field: Field) -> tantivy::Result<u64> {
+        self.0
+            .iter()
+            .map(|searcher| searcher.total_num_tokens(field))
+            .sum()
+    }
+
+    fn total_num_docs(&self) -> tantivy::Result<u64> {
+        self.0
+            .iter()
+            .map(|searcher| searcher.total_num_docs())
+            .sum()
+    }
+
+    fn doc_freq(&self, term: &Term) -> tantivy::Result<u64> {
+        self.0.iter().map(|searcher| searcher.doc_freq(term)).sum()
+    }
+}
+
+/// How [`search_top`] orders hits.
+#[derive(Clone, Copy)]
+pub enum Ranking {
+    /// Highest score first.
+    Score,
+    /// Highest value of this i64 fast field first, then highest score.
+    Newest(Field),
+}
+
+/// Run `query` on every shard in parallel, returning the summed hit count and the
+/// best `limit` hits after `offset`, ordered by `ranking`, with their scores.
+pub fn search_top(
+    searchers: &[Searcher],
+    query: &dyn Query,
+    limit: usize,
+    offset: usize,
+    ranking: Ranking,
+) -> tantivy::Result<(u64, Vec<(f32, TantivyDocument)>)> {
+    let statistics = ShardStatistics(searchers);
+    let per_shard: Vec<_> = std::thread::scope(|scope| {
+        let handles: Vec<_> = searchers
+            .iter()
+            .map(|searcher| {
+                let statistics = &statistics;
+                scope.spawn(move || {
+                    let count = searcher.search(query, &collector::Count)? as u64;
+                    let top = collector::TopDocs::with_limit(limit + offset);
+                    let hits: Vec<(i64, f32, DocAddress)> = match ranking {
+                        Ranking::Score => searcher
+                            .search_with_statistics_provider(query, &top, statistics)?
+                            .into_iter()
+                            .map(|(score, address)| (0, score, address))
+                            .collect(),
+                        Ranking::Newest(field) => {
+                            let name = searcher.schema().get_field_name(field).to_string();
+                            let top = top.tweak_score(move |segment: &SegmentReader| {
+                                let column = segment.fast_fields().i64(&name);
+                                move |doc: DocId, score: Score| {
+                                    let value = column
+                                        .as_ref()
+                                        .ok()
+                                        .and_then(|column| column.first(doc))
+                                        .unwrap_or(i64::MIN);
+                                    (value, score)
+                                }
+                            });
+                            searcher
+                                .search_with_statistics_provider(query, &top, statistics)?
+                                .into_iter()
+                                .map(|((value, score), address)| (value, score, address))
+                                .collect()
+                        }
+                    };
+                    Ok::<_, TantivyError>((count, hits))
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("shard search panicked"))
+            .collect::<tantivy::Result<_>>()
+    })?;
+
+    let mut total_hits = 0;
+    let mut hits = Vec::new();
+    for (shard, (count, shard_hits)) in per_shard.into_iter().enumerate() {
+        total_hits += count;
+        hits.extend(
+            shard_hits
+                .into_iter()
+                .map(|(value, score, address)| (value, score, shard, address)),
+        );
+    }
+    // Stable, so equal keys keep shard and then document order.
+    hits.sort_by(|a, b| b.0.cmp(&a.0).then(b.1.total_cmp(&a.1)));
+    let results = hits
+        .into_iter()
+        .skip(offset)
+        .take(limit)
+        .map(|(_, score, shard, address)| Ok((score, searchers[shard].doc(address)?)))
+        .collect::<tantivy::Result<_>>()?;
+    Ok((total_hits, results))
+}
+
+/// Write side of the sharded index; routes each bookmark to its shard's writer and
+/// commits only the shards that changed.
+pub struct ShardedWriter {
+    writers: Vec<IndexWriter>,
+    dirty: Vec<bool>,
+    bookmark_id: Field,
+}
+
+impl ShardedWriter {
+    pub fn new(writers: Vec<IndexWriter>, bookmark_id: Field) -> Self {
+        let dirty = vec![false; writers.len()];
+        Self {
+            writers,
+            dirty,
+            bookmark_id,
+        }
+    }
+
+    fn shard_of(&self, bookmark_id: i64) -> usize {
+        (bookmark_id as u64 % self.writers.len() as u64) as usize
+    }
+
+    pub fn shard_mut(&mut self, shard: usize) -> &mut IndexWriter {
+        &mut self.writers[shard]
+    }
+
+    pub fn add_document(&mut self, bookmark_id: i64, doc: TantivyDocument) -> tantivy::Result<()> {
+        let shard = self.shard_of(bookmark_id);
+        self.dirty[shard] = true;
+        self.writers[shard].add_document(doc)?;
+        Ok(())
+    }
+
+    pub fn delete_bookmark(&mut self, bookmark_id: i64) {
+        let shard = self.shard_of(bookmark_id);
+        self.dirty[shard] = true;
+        self.writers[shard].delete_term(Term::from_field_u64(self.bookmark_id, bookmark_id as u64));
+    }
+
+    /// Whether any shard has changes not yet committed.
+    pub fn has_changes(&self) -> bool {
+        self.dirty.iter().any(|dirty| *dirty)
+    }
+
+    /// Commit every shard with pending changes, returning the highest opstamp.
+    pub fn commit(&mut self) -> tantivy::Result<u64> {
+        let mut opstamp = 0;
+        for (writer, dirty) in self.writers.iter_mut().zip(&mut self.dirty) {
+            if *dirty {
+                opstamp = opstamp.max(writer.commit()?);
+                *dirty = false;
+            }
+        }
+        Ok(opstamp)
+    }
+}
@@ -0,0 +1,46 @@
+//! Bookmark storage, ingest, and full-text search for odin, usable without its HTTP server.
+//!
+//! [`Odin::open`] prepares the SQLite database and Tantivy index under
+//! `config.storage.data_dir` and starts the ingest, webhook, and maintenance workers.
+//! [`Odin::services`] then exposes the same services the server's routes call, each
+//! taking the [`types::Caller`] it acts for (see [`services::AuthService::authorize_token`]).
+//! Call [`Odin::shutdown`] before exiting so pending work drains and the index is flushed.
+//!
+//! ```no_run
+//! # async fn run() -> anyhow::Result<()> {
+//! use odin_core::config::Config;
+//! use odin_core::types::{Scope, SearchParams};
+//!
+//! let odin = odin_core::Odin::open(Config::load()?).await?;
+//! let caller = odin
+//!     .services()
+//!     .auth
+//!     .authorize_token("my-api-key", None, Scope::Read)
+//!     .await?;
+//! let params = SearchParams {
+//!     query: "rust".to_string(),
+//!     page: None,
+//!     ..SearchParams::default()
+//! };
+//! let results = odin.services().search.search(caller, params).await?;
+//! println!("{} hits", results.total_hits);
+//! odin.shutdown().await
+//! # }
+//! ```
+
+pub mod config;
+pub mod db;
+pub mod errors;
+pub mod index;
+pub mod metrics;
+pub mod rate_limit;
+pub mod services;
+pub mod types;
+
+mod content;
+mod dns;
+mod odin;
+mod robots;
+mod tags;
+
+pub use odin::Odin;
@@ -0,0 +1,197 @@
+use std::time::Duration;
+
+use anyhow::Context;
+use prometheus::{
+    Encoder, Histogram, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec, Opts,
+    Registry, TextEncoder,
+};
+use sqlx::SqlitePool;
+
+/// Prometheus collectors shared by the HTTP layer and the ingest pipeline.
+#[derive(Clone)]
+pub struct Metrics {
+    registry: Registry,
+    http_requests: IntCounterVec,
+    http_request_duration: HistogramVec,
+    ingest_outcomes: IntCounterVec,
+    ingest_queue_depth: IntGauge,
+    index_commit_duration: HistogramVec,
+    index_commit_batch_docs: Histogram,
+    db_pool_connections: IntGaugeVec,
+    webhook_deliveries: IntCounterVec,
+    fetch_duration: HistogramVec,
+    search_duration: Histogram,
+    index_documents: IntGauge,
+}
+
+impl Metrics {
+    pub fn new() -> anyhow::Result<Self> {
+        let registry = Registry::new_custom(Some("odin".to_string()), None)
+            .context("create metrics registry")?;
+
+        let http_requests = IntCounterVec::new(
+            Opts::new("http_requests_total", "HTTP requests by route and status"),
+            &["method", "route", "status"],
+        )?;
+        let http_request_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "http_request_duration_seconds",
+                "HTTP request latency by route",
+            ),
+            &["method", "route"],
+        )?;
+        let ingest_outcomes = IntCounterVec::new(
+            Opts::new(
+                "ingest_outcomes_total",
+                "Processed URLs by outcome and reason",
+            ),
+            &["outcome", "reason"],
+        )?;
+        let ingest_queue_depth = IntGauge::new(
+            "ingest_queue_depth",
+            "URLs accepted for ingest that have not finished processing",
+        )?;
+        let index_commit_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "index_commit_duration_seconds",
+                "Tantivy commit latency by caller",
+            ),
+            &["operation"],
+        )?;
+        let index_commit_batch_docs = Histogram::with_opts(
+            HistogramOpts::new(
+                "index_commit_batch_docs",
+                "Ingested documents included in each batched index commit",
+            )
+            .buckets(vec![
+                1.0, 2.0, 5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1000.0,
+            ]),
+        )?;
+        let db_pool_connections = IntGaugeVec::new(
+            Opts::new("db_pool_connections", "SQLite pool connections by state"),
+            &["state"],
+        )?;
+        let webhook_deliveries = IntCounterVec::new(
+            Opts::new(
+                "webhook_deliveries_total",
+                "Webhook delivery attempts by outcome",
+            ),
+            &["outcome"],
+        )?;
+
+        let fetch_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "fetch_duration_seconds",
+                "Page fetch latency, from request to full body, by outcome",
+            )
+            .buckets(vec![0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0]),
+            &["outcome"],
+        )?;
+        let search_duration = Histogram::with_opts(HistogramOpts::new(
+            "search_duration_seconds",
+            "Search latency, snippets included",
+        ))?;
+        let index_documents =
+            IntGauge::new("index_documents", "Live documents in the search index")?;
+
+        registry.register(Box::new(http_requests.clone()))?;
+        registry.register(Box::new(http_request_duration.clone()))?;
+        registry.register(Box::new(ingest_outcomes.clone()))?;
+        registry.register(Box::new(ingest_queue_depth.clone()))?;
+        registry.register(Box::new(index_commit_duration.clone()))?;
+        registry.register(Box::new(index_commit_batch_docs.clone()))?;
+        registry.register(Box::new(db_pool_connections.clone()))?;
+        registry.register(Box::new(webhook_deliveries.clone()))?;
+        registry.register(Box::new(fetch_duration.clone()))?;
+        registry.register(Box::new(search_duration.clone()))?;
+        registry.register(Box::new(index_documents.clone()))?;
+
+        Ok(Self {
+            registry,
+            http_requests,
+            http_request_duration,
+            ingest_outcomes,
+            ingest_queue_depth,
+            index_commit_duration,
+            index_commit_batch_docs,
+            db_pool_connections,
+            webhook_deliveries,
+            fetch_duration,
+            search_duration,
+            index_documents,
+        })
+    }
+
+    pub fn observe_request(&self, method: &str, route: &str, status: u16, elapsed: Duration) {
+        self.http_requests
+            .with_label_values(&[method, route, &status.to_string()])
+            .inc();
+        self.http_request_duration
+            .with_label_values(&[method, route])
+            .observe(elapsed.as_secs_f64());
+    }
+
+    pub fn record_ingest(&self, outcome: &str, reason: &str) {
+        self.ingest_outcomes
+            .with_label_values(&[outcome, reason])
+            .inc();
+    }
+
+    pub fn ingest_enqueued(&self) {
+        self.ingest_queue_depth.inc();
+    }
+
+    pub fn ingest_finished(&self) {
+        self.ingest_queue_depth.dec();
+    }
+
+    pub fn ingest_queue_depth(&self) -> i64 {
+        self.ingest_queue_depth.get()
+    }
+
+    pub fn observe_commit(&self, operation: &str, elapsed: Duration) {
+        self.index_commit_duration
+            .with_label_values(&[operation])
+            .observe(elapsed.as_secs_f64());
+    }
+
+    pub fn observe_commit_batch(&self, docs: usize) {
+        self.index_commit_batch_docs.observe(docs as f64);
+    }
+
+    pub fn record_webhook(&self, outcome: &str) {
+        self.webhook_deliveries.with_label_values(&[outcome]).inc();
+    }
+
+    pub fn observe_fetch(&self, elapsed: Duration, failed: bool) {
+        let outcome = if failed { "failed" } else { "ok" };
+        self.fetch_duration
+            .with_label_values(&[outcome])
+            .observe(elapsed.as_secs_f64());
+    }
+
+    pub fn observe_search(&self, elapsed: Duration) {
+        self.search_duration.observe(elapsed.as_secs_f64());
+    }
+
+    pub fn set_index_documents(&self, docs: u64) {
+        self.index_documents.set(docs as i64);
+    }
+
+    /// Render every collector in the Prometheus text exposition format.
+    pub fn render(&self, db: &SqlitePool) -> anyhow::Result<String> {
+        let idle = db.num_idle() as i64;
+        self.db_pool_connections
+            .with_label_values(&["idle"])
+            .set(idle);
+        self.db_pool_connections
+            .with_label_values(&["active"])
+            .set(i64::from(db.size()) - idle);
+
+        let mut buffer = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .context("encode metrics")?;
+        String::from_utf8(buffer).context("metrics are not utf-8")
+    }
+}
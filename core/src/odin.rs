@@ -0,0 +1,404 @@
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use reqwest::header::{ACCEPT, ACCEPT_LANGUAGE, HeaderMap, HeaderValue};
+use sqlx::sqlite::SqliteConnectOptions;
+use sqlx::sqlite::SqlitePoolOptions;
+use sqlx::sqlite::{SqliteAutoVacuum, SqliteJournalMode, SqliteSynchronous};
+use tantivy::directory::MmapDirectory;
+use tantivy::schema::{FAST, INDEXED, STORED, STRING, Schema, TEXT};
+use tantivy::{Executor, Index, ReloadPolicy, Warmer};
+use tokio::sync::{Mutex, Semaphore, broadcast, watch};
+use tokio_util::sync::CancellationToken;
+use tokio_util::task::TaskTracker;
+use tracing::{info, warn};
+
+use crate::config::Config;
+use crate::db::{self, ADMIN_USER_ID};
+use crate::dns::DnsCache;
+use crate::index::{
+    FieldWarmer, INDEX_SCHEMA_VERSION, SearchIndex, Shard, ShardedWriter, read_schema_version,
+    shard_dirs, write_schema_version,
+};
+use crate::metrics::Metrics;
+use crate::services::{Services, apply_pending_restore};
+use crate::types::{Dependencies, IndexFields, JobState, ReindexRequest, RuntimeSettings};
+
+/// Bookmark events buffered per subscriber before slow listeners start missing some.
+const EVENT_BUFFER: usize = 1024;
+
+/// A running odin instance: open storage, background workers, and the services over them.
+pub struct Odin {
+    deps: Arc<Dependencies>,
+    services: Services,
+}
+
+impl Odin {
+    /// Open (creating if needed) the database and index in `config.storage.data_dir`,
+    /// apply a staged restore and pending migrations, and start the background workers.
+    /// An index that is missing, unreadable, at another schema version, or split into
+    /// another number of shards is rebuilt from the database in the background. With
+    /// `storage.in_memory` both start empty in memory instead.
+    pub async fn open(config: Config) -> anyhow::Result<Self> {
+        let storage = &config.storage;
+        let (db_options, index_dir) = if storage.in_memory {
+            let options = SqliteConnectOptions::from_str("sqlite::memory:")
+                .context("in-memory sqlite options")?;
+            (options, None)
+        } else {
+            let data_dir = storage.data_dir.clone();
+            let index_dir = data_dir.join("index");
+            tokio::fs::create_dir_all(&data_dir)
+                .await
+                .context("create data dir")?;
+            apply_pending_restore(&data_dir).context("apply pending restore")?;
+            tokio::fs::create_dir_all(&index_dir)
+                .await
+                .context("create index dir")?;
+            let options = SqliteConnectOptions::new()
+                .filename(data_dir.join("app.db"))
+                .create_if_missing(true);
+            (options, Some(index_dir))
+        };
+
+        let mut pool_options = SqlitePoolOptions::new().max_connections(storage.db_max_connections);
+        if storage.in_memory {
+            // An in-memory database is dropped with its last connection, so keep one open.
+            pool_options = pool_options
+                .min_connections(1)
+                .idle_timeout(None)
+                .max_lifetime(None);
+        }
+        let db = pool_options
+            .connect_with(db::log_slow_statements(
+                db_options
+                    .auto_vacuum(SqliteAutoVacuum::Incremental)
+                    .journal_mode(SqliteJournalMode::Wal)
+                    .synchronous(SqliteSynchronous::Normal)
+                    .busy_timeout(std::time::Duration::from_millis(storage.db_busy_timeout_ms)),
+                config.slow_log.query_ms,
+            ))
+            .await
+            .context("connect sqlite")?;
+
+        db::init_db(&db).await?;
+
+        let (schema, fields) = build_schema();
+        let shards = storage.index_shards;
+        let (indexes, needs_reindex) = match &index_dir {
+            Some(index_dir) => {
+                let has_bookmarks: bool =
+                    sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM bookmarks)")
+                        .fetch_one(&db)
+                        .await
+                        .context("count bookmarks")?;
+                open_index(index_dir, schema, shards, has_bookmarks)?
+            }
+            None => (
+                (0..shards)
+                    .map(|_| Index::create_in_ram(schema.clone()))
+                    .collect(),
+                false,
+            ),
+        };
+        let executor = match storage.search_threads {
+            1 => None,
+            0 => Some(std::thread::available_parallelism().map_or(1, |threads| threads.get())),
+            threads => Some(threads),
+        }
+        .map(|threads| Executor::multi_thread(threads, "odin-search-").map(Arc::new))
+        .transpose()
+        .context("start search threads")?;
+        let warmer = Arc::new(FieldWarmer::new(vec![
+            fields.title,
+            fields.body,
+            fields.owner_id,
+            fields.namespace,
+            fields.bookmark_id,
+            fields.host,
+            fields.tag,
+        ]));
+        let mut shard_readers = Vec::with_capacity(shards);
+        let mut writers = Vec::with_capacity(shards);
+        for mut index in indexes {
+            if let Some(executor) = &executor {
+                index.set_shared_multithread_executor(executor.clone())?;
+            }
+            writers.push(
+                match storage.writer_threads {
+                    0 => index.writer(storage.writer_heap_bytes),
+                    threads => index.writer_with_num_threads(threads, storage.writer_heap_bytes),
+                }
+                .context("create index writer")?,
+            );
+            // Reloads are driven by the refresher, which warms new segments first.
+            let reader = index
+                .reader_builder()
+                .reload_policy(ReloadPolicy::Manual)
+                .warmers(vec![Arc::downgrade(&warmer) as Weak<dyn Warmer>])
+                .try_into()?;
+            shard_readers.push(Shard { index, reader });
+        }
+
+        let http_client = build_http_client(&config)?;
+
+        let deps = Arc::new(Dependencies {
+            db,
+            index: SearchIndex::new(shard_readers, warmer),
+            writer: Arc::new(Mutex::new(ShardedWriter::new(writers, fields.bookmark_id))),
+            fields,
+            fetch_semaphore: Arc::new(Semaphore::new(config.ingest.fetch_concurrency)),
+            http_client,
+            config: config.clone(),
+            live_config: watch::channel(config.clone()).0,
+            metrics: Metrics::new().context("register metrics")?,
+            tasks: TaskTracker::new(),
+            events: broadcast::channel(EVENT_BUFFER).0,
+            last_index_write: Arc::new(std::sync::Mutex::new(Instant::now())),
+            shutdown: CancellationToken::new(),
+            started_at: Instant::now(),
+            settings: Arc::new(std::sync::RwLock::new(RuntimeSettings::from_config(
+                &config,
+            ))),
+            read_only: Arc::default(),
+            bootstrap_secret: Arc::default(),
+        });
+        deps.index
+            .start_refresher(&config.storage, deps.shutdown.clone());
+        let services = Services::new(deps.clone());
+        services.settings.load().await?;
+        services.jobs.recover().await?;
+        services.indexer.start();
+        services.ingest.start();
+        services.ingest.resume().await?;
+        services.webhooks.start();
+        services.notifications.start();
+        services.telegram.start();
+        services.readwise.start();
+        services.feed_reader.start();
+        services.feeds.start();
+        services.activitypub.start();
+        services.wayback.start();
+        services.maintenance.start();
+        services.reconcile.start();
+        services.domain_stats.start();
+        // Applying a restore keeps the replaced data, which may push usage over the quota.
+        if let Err(err) = services.storage.enforce_quota().await {
+            warn!("storage quota check failed: {:?}", err);
+        }
+        if let (true, Some(index_dir)) = (needs_reindex, index_dir) {
+            let (job_id, mut progress) = services
+                .ingest
+                .reindex(
+                    ADMIN_USER_ID,
+                    ReindexRequest {
+                        id: None,
+                        refetch: false,
+                    },
+                )
+                .await
+                .map_err(|_| anyhow::anyhow!("failed to schedule index rebuild"))?;
+
+            // Only a finished rebuild records the version; an interrupted one starts over.
+            let db = deps.db.clone();
+            deps.tasks.spawn(async move {
+                while progress.recv().await.is_some() {}
+                let state: Option<String> =
+                    sqlx::query_scalar("SELECT state FROM jobs WHERE id = ?1")
+                        .bind(job_id)
+                        .fetch_optional(&db)
+                        .await
+                        .unwrap_or_default();
+                if state.as_deref() != Some(JobState::Succeeded.as_str()) {
+                    warn!(
+                        "index rebuild job {} did not finish; it restarts on the next start",
+                        job_id
+                    );
+                    return;
+                }
+                match write_schema_version(&index_dir, shards) {
+                    Ok(()) => info!("index rebuilt at schema version {}", INDEX_SCHEMA_VERSION),
+                    Err(err) => warn!("failed to record index schema version: {:?}", err),
+                }
+            });
+        }
+
+        Ok(Self { deps, services })
+    }
+
+    pub fn services(&self) -> &Services {
+        &self.services
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.deps.config
+    }
+
+    /// Cancelled when shutdown begins; background workers and event streams stop on it.
+    pub fn shutdown_token(&self) -> CancellationToken {
+        self.deps.shutdown.clone()
+    }
+
+    /// Stop the workers, let background ingests drain (bounded by
+    /// `server.shutdown_grace_secs`), flush the index, and close the database.
+    pub async fn shutdown(self) -> anyhow::Result<()> {
+        let deps = &self.deps;
+        deps.shutdown.cancel();
+        deps.tasks.close();
+        let grace = std::time::Duration::from_secs(deps.config.server.shutdown_grace_secs);
+        info!(
+            "waiting up to {}s for {} background tasks",
+            grace.as_secs(),
+            deps.tasks.len()
+        );
+        if tokio::time::timeout(grace, deps.tasks.wait())
+            .await
+            .is_err()
+        {
+            warn!(
+                "shutdown grace period elapsed with {} background tasks still running",
+                deps.tasks.len()
+            );
+        }
+
+        {
+            let mut writer = deps.writer.lock().await;
+            deps.commit_index(&mut writer, "shutdown")
+                .context("commit index on shutdown")?;
+        }
+        deps.db.close().await;
+        info!("shutdown complete");
+        Ok(())
+    }
+}
+
+fn build_http_client(config: &Config) -> anyhow::Result<reqwest::Client> {
+    let mut default_headers = HeaderMap::new();
+    default_headers.insert(ACCEPT, HeaderValue::from_static("text/html"));
+    default_headers.insert(ACCEPT_LANGUAGE, HeaderValue::from_static("en-US,en;q=0.9"));
+
+    let ingest = &config.ingest;
+    let mut builder = reqwest::Client::builder()
+        .cookie_store(true)
+        .default_headers(default_headers)
+        .user_agent("odin-agent/0.1")
+        .timeout(Duration::from_secs(ingest.fetch_timeout_secs))
+        .pool_max_idle_per_host(ingest.pool_idle_per_host)
+        .pool_idle_timeout(Duration::from_secs(ingest.pool_idle_timeout_secs))
+        .tcp_keepalive(Duration::from_secs(60));
+    if !ingest.http2 {
+        builder = builder.http1_only();
+    }
+    if ingest.dns_cache_ttl_secs > 0 {
+        builder = builder.dns_resolver(Arc::new(DnsCache::new(Duration::from_secs(
+            ingest.dns_cache_ttl_secs,
+        ))));
+    }
+    let client = builder.build().context("build http client")?;
+
+    Ok(client)
+}
+
+/// Open every shard, recreating the index empty when it is missing, cannot be opened,
+/// has another schema or shard count, or was not fully built at `INDEX_SCHEMA_VERSION`.
+///
+/// Returns `true` as the second value when the caller must repopulate the index.
+fn open_index(
+    index_dir: &Path,
+    schema: Schema,
+    shards: usize,
+    has_bookmarks: bool,
+) -> anyhow::Result<(Vec<Index>, bool)> {
+    let empty = std::fs::read_dir(index_dir)
+        .context("read index dir")?
+        .next()
+        .is_none();
+    let reason = if empty {
+        "is missing".to_string()
+    } else {
+        match open_shards(index_dir, &schema, shards) {
+            Ok(indexes) => return Ok((indexes, false)),
+            Err(reason) => reason,
+        }
+    };
+
+    if has_bookmarks {
+        warn!(
+            "index {}; recreating the index and re-indexing all bookmarks",
+            reason
+        );
+    }
+    std::fs::remove_dir_all(index_dir).context("remove stale index")?;
+    let mut indexes = Vec::with_capacity(shards);
+    for dir in shard_dirs(index_dir, shards) {
+        std::fs::create_dir_all(&dir).context("create index dir")?;
+        indexes.push(Index::create_in_dir(&dir, schema.clone())?);
+    }
+    if !has_bookmarks {
+        write_schema_version(index_dir, shards).context("write index schema version")?;
+    }
+    Ok((indexes, has_bookmarks))
+}
+
+/// Open each shard of a complete build, or explain why the index must be rebuilt.
+fn open_shards(index_dir: &Path, schema: &Schema, shards: usize) -> Result<Vec<Index>, String> {
+    match read_schema_version(index_dir) {
+        None => return Err("has no complete build recorded".to_string()),
+        Some((version, _)) if version != INDEX_SCHEMA_VERSION => {
+            return Err(format!(
+                "is at schema version {} (expected {})",
+                version, INDEX_SCHEMA_VERSION
+            ));
+        }
+        Some((_, built)) if built != shards => {
+            return Err(format!("has {} shards (expected {})", built, shards));
+        }
+        Some(_) => {}
+    }
+
+    let mut indexes = Vec::with_capacity(shards);
+    for dir in shard_dirs(index_dir, shards) {
+        let index = MmapDirectory::open(&dir)
+            .map_err(|err| err.to_string())
+            .and_then(|directory| Index::open(directory).map_err(|err| err.to_string()))
+            .map_err(|err| format!("shard {} cannot be opened ({})", dir.display(), err))?;
+        if index.schema() != *schema {
+            return Err("has a different schema".to_string());
+        }
+        indexes.push(index);
+    }
+    Ok(indexes)
+}
+
+fn build_schema() -> (Schema, IndexFields) {
+    let mut schema_builder = Schema::builder();
+    let bookmark_id = schema_builder.add_u64_field("bookmark_id", INDEXED | STORED);
+    let owner_id = schema_builder.add_u64_field("owner_id", INDEXED);
+    let namespace = schema_builder.add_text_field("namespace", STRING);
+    let url = schema_builder.add_text_field("url", STRING | STORED);
+    let title = schema_builder.add_text_field("title", TEXT | STORED);
+    let body = schema_builder.add_text_field("body", TEXT);
+    let excerpt = schema_builder.add_text_field("excerpt", STORED);
+    let fetched_at = schema_builder.add_i64_field("fetched_at", INDEXED | STORED | FAST);
+    let host = schema_builder.add_text_field("host", STRING);
+    let tag = schema_builder.add_text_field("tag", STRING);
+    let schema = schema_builder.build();
+    (
+        schema,
+        IndexFields {
+            bookmark_id,
+            owner_id,
+            namespace,
+            url,
+            title,
+            body,
+            excerpt,
+            fetched_at,
+            host,
+            tag,
+        },
+    )
+}
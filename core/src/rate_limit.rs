@@ -0,0 +1,84 @@
+use std::num::NonZeroU32;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use governor::clock::{Clock, DefaultClock};
+use governor::{DefaultKeyedRateLimiter, Quota};
+
+use crate::config::RateLimitConfig;
+
+/// Keys tracked before idle entries are pruned from a limiter.
+const PRUNE_THRESHOLD: usize = 10_000;
+
+#[derive(Clone, Copy, Debug)]
+pub enum RateLimitBucket {
+    Search,
+    Ingest,
+}
+
+/// Per-client request budgets for the expensive routes.
+#[derive(Clone)]
+pub struct RateLimits {
+    limiters: Arc<RwLock<Limiters>>,
+}
+
+struct Limiters {
+    search: Option<Arc<DefaultKeyedRateLimiter<String>>>,
+    ingest: Option<Arc<DefaultKeyedRateLimiter<String>>>,
+}
+
+impl Limiters {
+    fn new(config: &RateLimitConfig) -> Self {
+        let build = |per_minute: u32| {
+            let per_minute = NonZeroU32::new(per_minute).filter(|_| config.enabled)?;
+            let burst = NonZeroU32::new(config.burst).unwrap_or(per_minute);
+            Some(Arc::new(DefaultKeyedRateLimiter::keyed(
+                Quota::per_minute(per_minute).allow_burst(burst),
+            )))
+        };
+        Self {
+            search: build(config.search_per_minute),
+            ingest: build(config.ingest_per_minute),
+        }
+    }
+}
+
+impl RateLimits {
+    pub fn new(config: &RateLimitConfig) -> Self {
+        Self {
+            limiters: Arc::new(RwLock::new(Limiters::new(config))),
+        }
+    }
+
+    /// Switch to the budgets in `config`; every client starts over with a full burst.
+    pub fn reconfigure(&self, config: &RateLimitConfig) {
+        *self
+            .limiters
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = Limiters::new(config);
+    }
+
+    /// Consume one request for `key`, returning how long to wait when over budget.
+    pub fn check(&self, bucket: RateLimitBucket, key: &str) -> Result<(), Duration> {
+        let limiter = {
+            let limiters = self
+                .limiters
+                .read()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            match bucket {
+                RateLimitBucket::Search => limiters.search.clone(),
+                RateLimitBucket::Ingest => limiters.ingest.clone(),
+            }
+        };
+        let Some(limiter) = limiter else {
+            return Ok(());
+        };
+
+        if limiter.len() > PRUNE_THRESHOLD {
+            limiter.retain_recent();
+        }
+        limiter
+            .check_key(&key.to_string())
+            .map_err(|not_until| not_until.wait_time_from(DefaultClock::default().now()))
+    }
+}
@@ -0,0 +1,128 @@
+/// Product token of the fetch client's `User-Agent`, matched against `User-agent` lines.
+const AGENT: &str = "odin-agent";
+/// Bytes of a robots.txt read; Google stops at the same size.
+pub(crate) const MAX_ROBOTS_BYTES: usize = 500 * 1024;
+
+/// The `Allow`/`Disallow` rules of one site's robots.txt that apply to this crawler: the
+/// group naming it, or the `*` group when none does. A missing or unreadable file is
+/// [`Robots::allow_all`].
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Robots {
+    /// `(pattern, allow)` in file order.
+    rules: Vec<(String, bool)>,
+    crawl_delay: Option<f64>,
+}
+
+impl Robots {
+    pub(crate) fn allow_all() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn parse(text: &str) -> Self {
+        // Each group is the agents named by consecutive `User-agent` lines and the rules
+        // under them; groups naming the same agent are combined.
+        let mut specific = Self::default();
+        let mut specific_len = 0;
+        let mut wildcard = Self::default();
+        let mut agents: Vec<String> = Vec::new();
+        let mut in_rules = false;
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let key = key.trim().to_ascii_lowercase();
+            let value = value.trim();
+            if key == "user-agent" {
+                if in_rules {
+                    agents.clear();
+                    in_rules = false;
+                }
+                // `odin-agent/0.1` names the same agent as `odin-agent`.
+                let agent = value.split('/').next().unwrap_or_default();
+                agents.push(agent.trim().to_ascii_lowercase());
+                continue;
+            }
+            if !matches!(key.as_str(), "allow" | "disallow" | "crawl-delay") {
+                continue;
+            }
+            in_rules = true;
+            for agent in &agents {
+                let group = if agent == "*" {
+                    &mut wildcard
+                } else if !agent.is_empty()
+                    && AGENT.starts_with(agent.as_str())
+                    && agent.len() >= specific_len
+                {
+                    if agent.len() > specific_len {
+                        specific = Self::default();
+                        specific_len = agent.len();
+                    }
+                    &mut specific
+                } else {
+                    continue;
+                };
+                match key.as_str() {
+                    "crawl-delay" => group.crawl_delay = value.parse().ok(),
+                    // An empty `Disallow` allows everything, same as no rule.
+                    _ if value.is_empty() => {}
+                    _ => group.rules.push((value.to_string(), key == "allow")),
+                }
+            }
+        }
+        if specific_len > 0 { specific } else { wildcard }
+    }
+
+    /// Whether the URL's path and query may be fetched. The longest matching pattern
+    /// decides, and `Allow` wins a tie; `/robots.txt` itself is always allowed.
+    pub(crate) fn allows(&self, path_and_query: &str) -> bool {
+        if path_and_query == "/robots.txt" {
+            return true;
+        }
+        let mut best: Option<(usize, bool)> = None;
+        for (pattern, allow) in &self.rules {
+            if !matches(pattern, path_and_query) {
+                continue;
+            }
+            let candidate = (pattern.len(), *allow);
+            if best.is_none_or(|best| candidate > best) {
+                best = Some(candidate);
+            }
+        }
+        best.is_none_or(|(_, allow)| allow)
+    }
+
+    /// The `Crawl-delay` asked for, if any, in seconds.
+    pub(crate) fn crawl_delay(&self) -> Option<f64> {
+        self.crawl_delay
+            .filter(|secs| secs.is_finite() && *secs > 0.0)
+    }
+}
+
+/// Whether a robots.txt path pattern matches: a prefix match where `*` stands for any
+/// run of characters and a trailing `$` anchors the end.
+fn matches(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(pattern) => (pattern, true),
+        None => (pattern, false),
+    };
+    let mut parts = pattern.split('*');
+    let Some(mut rest) = path.strip_prefix(parts.next().unwrap_or_default()) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return !anchored || rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(at) => rest = &rest[at + part.len()..],
+            None => return false,
+        }
+    }
+    if anchored {
+        rest.ends_with(last)
+    } else {
+        rest.contains(last)
+    }
+}
@@ -0,0 +1,764 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Context;
+use axum::http::HeaderMap;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use rsa::pkcs1v15::{Signature, SigningKey, VerifyingKey};
+use rsa::pkcs8::{
+    DecodePrivateKey, DecodePublicKey, EncodePrivateKey, EncodePublicKey, LineEnding,
+};
+use rsa::signature::{SignatureEncoding, Signer, Verifier};
+use rsa::{RsaPrivateKey, RsaPublicKey};
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+use sqlx::FromRow;
+use time::format_description::well_known::Rfc3339;
+use time::format_description::{self, BorrowedFormatItem};
+use time::{OffsetDateTime, PrimitiveDateTime};
+use tokio::sync::OnceCell;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{info, warn};
+use url::Url;
+
+use crate::config::ActivityPubConfig;
+use crate::errors::AppError;
+use crate::services::BookmarkService;
+use crate::types::{BookmarkEventKind, Caller, Dependencies};
+
+const PUBLIC: &str = "https://www.w3.org/ns/activitystreams#Public";
+const CONTEXT: &str = "https://www.w3.org/ns/activitystreams";
+pub const ACTIVITY_JSON: &str = "application/activity+json";
+const KEY_BITS: usize = 2048;
+const OUTBOX_PAGE: i64 = 20;
+const MAX_ATTEMPTS: u32 = 3;
+const BASE_RETRY_DELAY: Duration = Duration::from_secs(5);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// How far a signed request's `Date` may be from now, as Mastodon allows.
+const MAX_CLOCK_SKEW: time::Duration = time::Duration::hours(12);
+
+struct Keys {
+    signing: SigningKey<rsa::sha2::Sha256>,
+    public_pem: String,
+}
+
+#[derive(FromRow)]
+struct NoteRow {
+    id: i64,
+    url: String,
+    title: Option<String>,
+    excerpt: Option<String>,
+    published_at: String,
+}
+
+/// The actor a Follow or Undo came from, as its signature proved.
+struct RemoteActor {
+    id: String,
+    inbox: String,
+}
+
+/// A fediverse actor for `activitypub.username`, publishing the bookmarks that user marks
+/// public as notes. Followers are kept in `activitypub_followers` and sent each note as
+/// it is published, and a delete when it is unpublished or the bookmark goes. Requests
+/// in both directions carry HTTP signatures, made with a key generated on first use and
+/// kept in `activitypub_keys`.
+#[derive(Clone)]
+pub struct ActivityPubService {
+    deps: Arc<Dependencies>,
+    bookmarks: BookmarkService,
+    keys: Arc<OnceCell<Keys>>,
+}
+
+impl ActivityPubService {
+    pub fn new(deps: Arc<Dependencies>, bookmarks: BookmarkService) -> Self {
+        Self {
+            deps,
+            bookmarks,
+            keys: Arc::default(),
+        }
+    }
+
+    /// Make the key ahead of the first follower, and send deletes for public bookmarks
+    /// that get deleted.
+    pub fn start(&self) {
+        if self.deps.config.activitypub.base_url.is_none() {
+            return;
+        }
+        let service = self.clone();
+        let mut events = self.deps.events.subscribe();
+        tokio::spawn(async move {
+            if let Err(err) = service.keys().await {
+                warn!("activitypub key setup failed: {:?}", err);
+            }
+            loop {
+                let event = match events.recv().await {
+                    Ok(event) => event,
+                    Err(RecvError::Lagged(skipped)) => {
+                        warn!("activitypub lagged; dropped {} events", skipped);
+                        continue;
+                    }
+                    Err(RecvError::Closed) => break,
+                };
+                if event.event != BookmarkEventKind::Deleted || !event.was_public {
+                    continue;
+                }
+                if let Err(err) = service.send_delete(event.id).await {
+                    warn!("activitypub unpublish failed: {:?}", err);
+                }
+            }
+        });
+    }
+
+    pub async fn webfinger(&self, resource: &str) -> Result<Value, AppError> {
+        let (config, base_url) = self.config()?;
+        let host = base_url.host_str().unwrap_or_default();
+        let handle = format!("acct:{}@{}", config.username, host);
+        let actor = self.actor_id(base_url);
+        if !resource.eq_ignore_ascii_case(&handle) && resource != actor {
+            return Err(AppError::not_found("no such account"));
+        }
+        Ok(json!({
+            "subject": handle,
+            "aliases": [actor],
+            "links": [{ "rel": "self", "type": ACTIVITY_JSON, "href": actor }],
+        }))
+    }
+
+    pub async fn actor(&self) -> Result<Value, AppError> {
+        let (config, base_url) = self.config()?;
+        let keys = self.keys().await?;
+        let actor = self.actor_id(base_url);
+        Ok(json!({
+            "@context": [CONTEXT, "https://w3id.org/security/v1"],
+            "id": actor,
+            "type": "Person",
+            "preferredUsername": config.username,
+            "name": config.display_name.as_deref().unwrap_or(&config.username),
+            "summary": config.summary.as_deref().map(escape_html).unwrap_or_default(),
+            "url": base_url.as_str(),
+            "inbox": self.path(base_url, "inbox"),
+            "outbox": self.path(base_url, "outbox"),
+            "followers": self.path(base_url, "followers"),
+            "manuallyApprovesFollowers": false,
+            "discoverable": true,
+            "publicKey": {
+                "id": format!("{}#main-key", actor),
+                "owner": actor,
+                "publicKeyPem": keys.public_pem,
+            },
+        }))
+    }
+
+    /// The newest public bookmarks as `Create` activities, enough for profile pages.
+    pub async fn outbox(&self) -> Result<Value, AppError> {
+        let (_, base_url) = self.config()?;
+        let owner_id = self.owner_id().await?;
+        let total: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM public_bookmarks p JOIN bookmarks b ON b.id = p.bookmark_id WHERE p.owner_id = ?1",
+        )
+        .bind(owner_id)
+        .fetch_one(&self.deps.db)
+        .await?;
+        let rows: Vec<NoteRow> = sqlx::query_as(
+            r#"
+            SELECT b.id, b.url, b.title, b.excerpt, p.published_at
+            FROM public_bookmarks p JOIN bookmarks b ON b.id = p.bookmark_id
+            WHERE p.owner_id = ?1
+            ORDER BY p.published_at DESC
+            LIMIT ?2
+            "#,
+        )
+        .bind(owner_id)
+        .bind(OUTBOX_PAGE)
+        .fetch_all(&self.deps.db)
+        .await?;
+        let items: Vec<Value> = rows
+            .iter()
+            .map(|row| self.create_activity(base_url, self.note_object(base_url, row)))
+            .collect();
+        Ok(json!({
+            "@context": CONTEXT,
+            "id": self.path(base_url, "outbox"),
+            "type": "OrderedCollection",
+            "totalItems": total,
+            "orderedItems": items,
+        }))
+    }
+
+    /// Only the count; who follows is nobody else's business.
+    pub async fn followers(&self) -> Result<Value, AppError> {
+        let (_, base_url) = self.config()?;
+        let total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM activitypub_followers")
+            .fetch_one(&self.deps.db)
+            .await?;
+        Ok(json!({
+            "@context": CONTEXT,
+            "id": self.path(base_url, "followers"),
+            "type": "OrderedCollection",
+            "totalItems": total,
+        }))
+    }
+
+    pub async fn note(&self, id: i64) -> Result<Value, AppError> {
+        let (_, base_url) = self.config()?;
+        let owner_id = self.owner_id().await?;
+        let Some(row) = self.public_note(owner_id, id).await? else {
+            return Err(AppError::not_found("note not found"));
+        };
+        let mut note = self.note_object(base_url, &row);
+        note["@context"] = CONTEXT.into();
+        Ok(note)
+    }
+
+    async fn public_note(&self, owner_id: i64, id: i64) -> Result<Option<NoteRow>, AppError> {
+        let row = sqlx::query_as(
+            r#"
+            SELECT b.id, b.url, b.title, b.excerpt, p.published_at
+            FROM public_bookmarks p JOIN bookmarks b ON b.id = p.bookmark_id
+            WHERE p.bookmark_id = ?1 AND p.owner_id = ?2
+            "#,
+        )
+        .bind(id)
+        .bind(owner_id)
+        .fetch_optional(&self.deps.db)
+        .await?;
+        Ok(row)
+    }
+
+    /// Handle a signed activity delivered to the inbox: follows are accepted at once,
+    /// undone follows and deleted accounts are dropped, and anything else is ignored.
+    pub async fn inbox(
+        &self,
+        headers: &HeaderMap,
+        request_target: &str,
+        body: &[u8],
+    ) -> Result<(), AppError> {
+        let (_, base_url) = self.config()?;
+        let activity: Value = serde_json::from_slice(body)
+            .map_err(|err| AppError::bad_request(format!("invalid activity: {}", err)))?;
+        let sender = self.verify(headers, request_target, body).await?;
+        if activity["actor"].as_str() != Some(sender.id.as_str()) {
+            return Err(AppError::unauthorized(
+                "activity actor does not match its signature",
+            ));
+        }
+        let actor = self.actor_id(base_url);
+        let object_type = activity["object"]["type"].as_str();
+        match activity["type"].as_str() {
+            Some("Follow") if activity["object"].as_str() == Some(actor.as_str()) => {
+                self.deps.ensure_writable()?;
+                sqlx::query(
+                    r#"
+                    INSERT INTO activitypub_followers (actor, inbox, created_at)
+                    VALUES (?1, ?2, ?3)
+                    ON CONFLICT(actor) DO UPDATE SET inbox = excluded.inbox
+                    "#,
+                )
+                .bind(&sender.id)
+                .bind(&sender.inbox)
+                .bind(now())
+                .execute(&self.deps.db)
+                .await?;
+                info!("activitypub follower added: {}", sender.id);
+                let accept = json!({
+                    "@context": CONTEXT,
+                    "id": format!("{}#accepts/{}", actor, hex::encode(rand::random::<[u8; 16]>())),
+                    "type": "Accept",
+                    "actor": actor,
+                    "object": activity,
+                });
+                self.spawn_delivery(sender.inbox, accept);
+            }
+            Some("Undo") if object_type == Some("Follow") => {
+                self.remove_follower(&sender.id).await?
+            }
+            Some("Delete") if activity["object"].as_str() == Some(sender.id.as_str()) => {
+                self.remove_follower(&sender.id).await?
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Mark one of the caller's bookmarks public and send it to followers, or take it
+    /// back. Only `activitypub.username` publishes.
+    pub async fn set_public(&self, caller: Caller, id: i64, public: bool) -> Result<(), AppError> {
+        self.deps.ensure_writable()?;
+        let (_, base_url) = self.config()?;
+        if caller.user_id != self.owner_id().await? {
+            return Err(AppError::forbidden(
+                "only the activitypub.username user publishes bookmarks",
+            ));
+        }
+        if self.bookmarks.get(&caller, id).await?.is_none() {
+            return Err(AppError::not_found("bookmark not found"));
+        }
+        if !public {
+            return self.unpublish(id).await;
+        }
+
+        let inserted = sqlx::query(
+            "INSERT OR IGNORE INTO public_bookmarks (bookmark_id, owner_id, published_at) VALUES (?1, ?2, ?3)",
+        )
+        .bind(id)
+        .bind(caller.user_id)
+        .bind(now())
+        .execute(&self.deps.db)
+        .await?;
+        if inserted.rows_affected() == 0 {
+            return Ok(());
+        }
+        let Some(row) = self.public_note(caller.user_id, id).await? else {
+            return Ok(());
+        };
+        info!("activitypub published: bookmark_id={}", id);
+        let create = self.create_activity(base_url, self.note_object(base_url, &row));
+        self.send_to_followers(create).await
+    }
+
+    /// Forget that `id` was public and tell followers to delete its note, if it was.
+    async fn unpublish(&self, id: i64) -> Result<(), AppError> {
+        let removed = sqlx::query("DELETE FROM public_bookmarks WHERE bookmark_id = ?1")
+            .bind(id)
+            .execute(&self.deps.db)
+            .await?;
+        if removed.rows_affected() == 0 {
+            return Ok(());
+        }
+        info!("activitypub unpublished: bookmark_id={}", id);
+        self.send_delete(id).await
+    }
+
+    /// Tell followers to delete the note of `id`, whose `public_bookmarks` row is gone.
+    async fn send_delete(&self, id: i64) -> Result<(), AppError> {
+        let Some(base_url) = self.deps.config.activitypub.base_url.as_ref() else {
+            return Ok(());
+        };
+        let note_id = self.note_id(base_url, id);
+        let delete = json!({
+            "@context": CONTEXT,
+            "id": format!("{}#delete", note_id),
+            "type": "Delete",
+            "actor": self.actor_id(base_url),
+            "to": [PUBLIC],
+            "object": { "id": note_id, "type": "Tombstone" },
+        });
+        self.send_to_followers(delete).await
+    }
+
+    async fn remove_follower(&self, actor: &str) -> Result<(), AppError> {
+        self.deps.ensure_writable()?;
+        let removed = sqlx::query("DELETE FROM activitypub_followers WHERE actor = ?1")
+            .bind(actor)
+            .execute(&self.deps.db)
+            .await?;
+        if removed.rows_affected() > 0 {
+            info!("activitypub follower removed: {}", actor);
+        }
+        Ok(())
+    }
+
+    /// One delivery per inbox, so followers on the same server share one.
+    async fn send_to_followers(&self, activity: Value) -> Result<(), AppError> {
+        let inboxes: Vec<String> =
+            sqlx::query_scalar("SELECT DISTINCT inbox FROM activitypub_followers")
+                .fetch_all(&self.deps.db)
+                .await?;
+        for inbox in inboxes {
+            self.spawn_delivery(inbox, activity.clone());
+        }
+        Ok(())
+    }
+
+    fn spawn_delivery(&self, inbox: String, activity: Value) {
+        let service = self.clone();
+        self.deps
+            .tasks
+            .clone()
+            .spawn(async move { service.deliver(&inbox, &activity).await });
+    }
+
+    /// POST a signed activity, retrying with backoff on transport errors and 5xx replies.
+    async fn deliver(&self, inbox: &str, activity: &Value) {
+        let body = activity.to_string();
+        for attempt in 1..=MAX_ATTEMPTS {
+            let failure = match self.signed_post(inbox, &body).await {
+                Ok(response) if response.status().is_success() => {
+                    info!(
+                        "activitypub delivered: {} type={} attempt={}",
+                        inbox,
+                        activity["type"].as_str().unwrap_or_default(),
+                        attempt
+                    );
+                    return;
+                }
+                Ok(response) if response.status().is_client_error() => {
+                    warn!(
+                        "activitypub delivery refused: {} http status {}",
+                        inbox,
+                        response.status()
+                    );
+                    return;
+                }
+                Ok(response) => format!("http status {}", response.status()),
+                Err(err) => format!("{:#}", err),
+            };
+            if attempt == MAX_ATTEMPTS {
+                warn!(
+                    "activitypub delivery failed: {} attempts={} error={}",
+                    inbox, attempt, failure
+                );
+                return;
+            }
+            tokio::time::sleep(BASE_RETRY_DELAY * 2u32.pow(attempt - 1)).await;
+        }
+    }
+
+    async fn signed_post(&self, inbox: &str, body: &str) -> anyhow::Result<reqwest::Response> {
+        let url = Url::parse(inbox).context("invalid inbox url")?;
+        let digest = format!("SHA-256={}", BASE64.encode(Sha256::digest(body.as_bytes())));
+        let date = http_date(OffsetDateTime::now_utc());
+        let host = host_header(&url);
+        let signed = format!(
+            "(request-target): post {}\nhost: {}\ndate: {}\ndigest: {}",
+            request_target(&url),
+            host,
+            date,
+            digest
+        );
+        let signature = self
+            .signature_header(&signed, "(request-target) host date digest")
+            .await?;
+        let response = self
+            .deps
+            .http_client
+            .post(url)
+            .timeout(REQUEST_TIMEOUT)
+            .header("content-type", ACTIVITY_JSON)
+            .header("host", host)
+            .header("date", date)
+            .header("digest", digest)
+            .header("signature", signature)
+            .body(body.to_string())
+            .send()
+            .await
+            .map_err(reqwest::Error::without_url)?;
+        Ok(response)
+    }
+
+    /// GET an ActivityPub document, signed so servers that require it answer.
+    async fn signed_get(&self, target: &str) -> anyhow::Result<Value> {
+        let url = Url::parse(target).context("invalid url")?;
+        let date = http_date(OffsetDateTime::now_utc());
+        let host = host_header(&url);
+        let signed = format!(
+            "(request-target): get {}\nhost: {}\ndate: {}",
+            request_target(&url),
+            host,
+            date
+        );
+        let signature = self
+            .signature_header(&signed, "(request-target) host date")
+            .await?;
+        let response = self
+            .deps
+            .http_client
+            .get(url)
+            .timeout(REQUEST_TIMEOUT)
+            .header("accept", ACTIVITY_JSON)
+            .header("host", host)
+            .header("date", date)
+            .header("signature", signature)
+            .send()
+            .await
+            .map_err(reqwest::Error::without_url)?
+            .error_for_status()
+            .map_err(reqwest::Error::without_url)?;
+        let body = response.bytes().await?;
+        serde_json::from_slice(&body).context("invalid json")
+    }
+
+    async fn signature_header(&self, signed: &str, headers: &str) -> anyhow::Result<String> {
+        let keys = self
+            .keys()
+            .await
+            .map_err(|err| anyhow::anyhow!(err.message().to_string()))?;
+        let base_url = self
+            .deps
+            .config
+            .activitypub
+            .base_url
+            .as_ref()
+            .context("activitypub is not enabled")?;
+        let signature = BASE64.encode(keys.signing.sign(signed.as_bytes()).to_bytes());
+        Ok(format!(
+            "keyId=\"{}#main-key\",algorithm=\"rsa-sha256\",headers=\"{}\",signature=\"{}\"",
+            self.actor_id(base_url),
+            headers,
+            signature
+        ))
+    }
+
+    /// Check a request's HTTP signature against the key its sender publishes, along
+    /// with the body digest and date it covers.
+    async fn verify(
+        &self,
+        headers: &HeaderMap,
+        request_target: &str,
+        body: &[u8],
+    ) -> Result<RemoteActor, AppError> {
+        let unauthorized =
+            |reason: &str| AppError::unauthorized(format!("bad signature: {}", reason));
+        let header = headers
+            .get("signature")
+            .and_then(|value| value.to_str().ok())
+            .ok_or_else(|| unauthorized("missing Signature header"))?;
+        let params = parse_signature(header);
+        let param = |name: &str| {
+            params
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.as_str())
+        };
+        let key_id = param("keyId").ok_or_else(|| unauthorized("missing keyId"))?;
+        let signature = param("signature").ok_or_else(|| unauthorized("missing signature"))?;
+        let covered: Vec<&str> = param("headers").unwrap_or("date").split(' ').collect();
+        for required in ["(request-target)", "host", "date", "digest"] {
+            if !covered.contains(&required) {
+                return Err(unauthorized(&format!("{} is not signed", required)));
+            }
+        }
+
+        let header_value = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .ok_or_else(|| unauthorized(&format!("missing {} header", name)))
+        };
+        let digest = format!("SHA-256={}", BASE64.encode(Sha256::digest(body)));
+        if !header_value("digest")?
+            .split(',')
+            .any(|value| value.trim() == digest)
+        {
+            return Err(unauthorized("digest does not match the body"));
+        }
+        let date = PrimitiveDateTime::parse(header_value("date")?, &http_date_format())
+            .map_err(|_| unauthorized("unreadable date"))?
+            .assume_utc();
+        if (OffsetDateTime::now_utc() - date).abs() > MAX_CLOCK_SKEW {
+            return Err(unauthorized("date is too far from now"));
+        }
+        let mut lines = Vec::with_capacity(covered.len());
+        for name in &covered {
+            let value = match *name {
+                "(request-target)" => format!("post {}", request_target),
+                name => header_value(name)?.to_string(),
+            };
+            lines.push(format!("{}: {}", name, value));
+        }
+        let signed = lines.join("\n");
+
+        let actor_url = key_id.split('#').next().unwrap_or(key_id);
+        let document = self
+            .signed_get(actor_url)
+            .await
+            .map_err(|err| unauthorized(&format!("could not fetch {}: {:#}", actor_url, err)))?;
+        if document["publicKey"]["id"].as_str() != Some(key_id) {
+            return Err(unauthorized("keyId is not the actor's key"));
+        }
+        let (Some(id), Some(pem), Some(inbox)) = (
+            document["id"].as_str(),
+            document["publicKey"]["publicKeyPem"].as_str(),
+            document["endpoints"]["sharedInbox"]
+                .as_str()
+                .or(document["inbox"].as_str()),
+        ) else {
+            return Err(unauthorized("actor has no id, key, or inbox"));
+        };
+        let key = RsaPublicKey::from_public_key_pem(pem)
+            .map_err(|_| unauthorized("unreadable public key"))?;
+        let signature = BASE64
+            .decode(signature)
+            .ok()
+            .and_then(|bytes| Signature::try_from(bytes.as_slice()).ok())
+            .ok_or_else(|| unauthorized("unreadable signature"))?;
+        VerifyingKey::<rsa::sha2::Sha256>::new(key)
+            .verify(signed.as_bytes(), &signature)
+            .map_err(|_| unauthorized("signature does not verify"))?;
+        Ok(RemoteActor {
+            id: id.to_string(),
+            inbox: inbox.to_string(),
+        })
+    }
+
+    /// The actor's key pair, generated and stored on first use.
+    async fn keys(&self) -> Result<&Keys, AppError> {
+        self.keys
+            .get_or_try_init(|| async {
+                let existing: Option<(String, String)> = sqlx::query_as(
+                    "SELECT private_key_pem, public_key_pem FROM activitypub_keys WHERE id = 1",
+                )
+                .fetch_optional(&self.deps.db)
+                .await?;
+                let (private_pem, public_pem) = match existing {
+                    Some(pems) => pems,
+                    None => {
+                        let pems = tokio::task::spawn_blocking(generate_keys)
+                            .await
+                            .context("key generation panicked")??;
+                        sqlx::query(
+                            "INSERT OR IGNORE INTO activitypub_keys (id, private_key_pem, public_key_pem) VALUES (1, ?1, ?2)",
+                        )
+                        .bind(&pems.0)
+                        .bind(&pems.1)
+                        .execute(&self.deps.db)
+                        .await?;
+                        info!("activitypub key pair generated");
+                        pems
+                    }
+                };
+                let private = RsaPrivateKey::from_pkcs8_pem(&private_pem)
+                    .context("read activitypub private key")?;
+                Ok::<_, AppError>(Keys {
+                    signing: SigningKey::new(private),
+                    public_pem,
+                })
+            })
+            .await
+    }
+
+    fn config(&self) -> Result<(&ActivityPubConfig, &Url), AppError> {
+        let config = &self.deps.config.activitypub;
+        match config.base_url.as_ref() {
+            Some(base_url) => Ok((config, base_url)),
+            None => Err(AppError::not_found("activitypub is not enabled")),
+        }
+    }
+
+    async fn owner_id(&self) -> Result<i64, AppError> {
+        let id = sqlx::query_scalar("SELECT id FROM users WHERE username = ?1")
+            .bind(&self.deps.config.activitypub.username)
+            .fetch_optional(&self.deps.db)
+            .await?;
+        id.ok_or_else(|| AppError::not_found("the activitypub.username user does not exist"))
+    }
+
+    fn path(&self, base_url: &Url, path: &str) -> String {
+        format!("{}/ap/{}", base_url.as_str().trim_end_matches('/'), path)
+    }
+
+    fn actor_id(&self, base_url: &Url) -> String {
+        self.path(base_url, "actor")
+    }
+
+    fn note_id(&self, base_url: &Url, id: i64) -> String {
+        self.path(base_url, &format!("notes/{}", id))
+    }
+
+    fn note_object(&self, base_url: &Url, row: &NoteRow) -> Value {
+        let mut content = format!(
+            "<p><a href=\"{}\">{}</a></p>",
+            escape_html(&row.url),
+            escape_html(row.title.as_deref().unwrap_or(&row.url))
+        );
+        if let Some(excerpt) = row
+            .excerpt
+            .as_deref()
+            .map(str::trim)
+            .filter(|excerpt| !excerpt.is_empty())
+        {
+            content.push_str(&format!("<p>{}</p>", escape_html(excerpt)));
+        }
+        json!({
+            "id": self.note_id(base_url, row.id),
+            "type": "Note",
+            "attributedTo": self.actor_id(base_url),
+            "published": row.published_at,
+            "url": row.url,
+            "to": [PUBLIC],
+            "cc": [self.path(base_url, "followers")],
+            "content": content,
+        })
+    }
+
+    fn create_activity(&self, base_url: &Url, note: Value) -> Value {
+        json!({
+            "@context": CONTEXT,
+            "id": format!("{}#create", note["id"].as_str().unwrap_or_default()),
+            "type": "Create",
+            "actor": self.actor_id(base_url),
+            "published": note["published"],
+            "to": [PUBLIC],
+            "cc": [self.path(base_url, "followers")],
+            "object": note,
+        })
+    }
+}
+
+/// The `Date` header format, e.g. `Sun, 06 Nov 1994 08:49:37 GMT`.
+fn http_date_format() -> Vec<BorrowedFormatItem<'static>> {
+    format_description::parse(
+        "[weekday repr:short], [day] [month repr:short] [year] [hour]:[minute]:[second] GMT",
+    )
+    .expect("valid date format")
+}
+
+fn http_date(at: OffsetDateTime) -> String {
+    at.format(&http_date_format())
+        .expect("failed to format date")
+}
+
+fn host_header(url: &Url) -> String {
+    match url.port() {
+        Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+        None => url.host_str().unwrap_or_default().to_string(),
+    }
+}
+
+fn request_target(url: &Url) -> String {
+    match url.query() {
+        Some(query) => format!("{}?{}", url.path(), query),
+        None => url.path().to_string(),
+    }
+}
+
+/// `key="value"` pairs of a `Signature` header.
+fn parse_signature(header: &str) -> Vec<(String, String)> {
+    header
+        .split(',')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(key, value)| {
+            (
+                key.trim().to_string(),
+                value.trim().trim_matches('"').to_string(),
+            )
+        })
+        .collect()
+}
+
+fn generate_keys() -> anyhow::Result<(String, String)> {
+    let private =
+        RsaPrivateKey::new(&mut rand_core::OsRng, KEY_BITS).context("generate activitypub key")?;
+    let private_pem = private
+        .to_pkcs8_pem(LineEnding::LF)
+        .context("encode activitypub private key")?
+        .to_string();
+    let public_pem = RsaPublicKey::from(&private)
+        .to_public_key_pem(LineEnding::LF)
+        .context("encode activitypub public key")?;
+    Ok((private_pem, public_pem))
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn now() -> String {
+    OffsetDateTime::now_utc()
+        .format(&Rfc3339)
+        .expect("failed to format timestamp")
+}
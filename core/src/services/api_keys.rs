@@ -0,0 +1,446 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use anyhow::Context;
+use sqlx::{FromRow, SqliteConnection};
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+use tracing::{info, warn};
+
+use crate::db::ADMIN_USER_ID;
+use crate::errors::AppError;
+use crate::services::AuthService;
+use crate::types::{
+    ApiKeyItem, ApiKeysResponse, BootstrapRequest, Caller, CreateApiKeyRequest,
+    CreateApiKeyResponse, Dependencies, Peer, RotateApiKeyRequest, RotateApiKeyResponse, Scope,
+};
+
+/// Name of the admin key derived from `ADMIN_TOKEN` (or created through `/v1/admin/bootstrap`).
+const BOOTSTRAP_KEY_NAME: &str = "bootstrap";
+
+#[derive(FromRow)]
+struct ApiKeyRow {
+    id: i64,
+    name: String,
+    scopes: String,
+    created_at: String,
+    last_used_at: Option<String>,
+    revoked_at: Option<String>,
+    expires_at: Option<String>,
+    namespace: Option<String>,
+}
+
+#[derive(FromRow)]
+struct RotatedKeyRow {
+    user_id: i64,
+    name: String,
+    scopes: String,
+    expires_at: Option<String>,
+    namespace: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct ApiKeyService {
+    deps: Arc<Dependencies>,
+}
+
+impl ApiKeyService {
+    const MAX_NAME_LEN: usize = 64;
+
+    pub fn new(deps: Arc<Dependencies>) -> Self {
+        Self { deps }
+    }
+
+    /// List the caller's keys, newest first; revoked keys are kept for auditing.
+    /// A key bound to a namespace sees only the keys bound to the same one.
+    pub async fn list(&self, caller: Caller) -> Result<ApiKeysResponse, AppError> {
+        let rows: Vec<ApiKeyRow> = sqlx::query_as(
+            r#"
+            SELECT id, name, scopes, created_at, last_used_at, revoked_at, expires_at, namespace
+            FROM api_keys
+            WHERE user_id = ?1 AND (namespace = ?2 OR NOT ?3)
+            ORDER BY id DESC
+            "#,
+        )
+        .bind(caller.user_id)
+        .bind(&caller.namespace)
+        .bind(caller.bound_namespace)
+        .fetch_all(&self.deps.db)
+        .await?;
+
+        let results = rows
+            .into_iter()
+            .map(|row| ApiKeyItem {
+                id: row.id,
+                name: row.name,
+                scopes: Scope::parse_list(&row.scopes),
+                created_at: row.created_at,
+                last_used_at: row.last_used_at,
+                revoked_at: row.revoked_at,
+                expires_at: row.expires_at,
+                namespace: row.namespace,
+            })
+            .collect();
+        Ok(ApiKeysResponse { results })
+    }
+
+    /// Issue a key for the caller; it can never carry scopes the caller lacks.
+    pub async fn create(
+        &self,
+        caller: Caller,
+        payload: CreateApiKeyRequest,
+    ) -> Result<CreateApiKeyResponse, AppError> {
+        self.deps.ensure_writable()?;
+        let name = payload.name.trim().to_string();
+        if name.is_empty() || name.len() > Self::MAX_NAME_LEN {
+            return Err(AppError::bad_request("key name must be 1-64 characters"));
+        }
+
+        let scopes = match payload.scopes {
+            Some(scopes) if scopes.is_empty() => {
+                return Err(AppError::bad_request("at least one scope is required"));
+            }
+            Some(scopes) => scopes,
+            None => Scope::DEFAULT.to_vec(),
+        };
+        if let Some(missing) = scopes.iter().find(|scope| !caller.has_scope(**scope)) {
+            return Err(AppError::forbidden(format!(
+                "cannot grant {} scope without holding it",
+                missing.as_str()
+            )));
+        }
+
+        // A bound key can only hand out keys bound to its own namespace.
+        let namespace = match payload.namespace {
+            Some(namespace) => {
+                AuthService::validate_namespace(&namespace)?;
+                if caller.bound_namespace && namespace != caller.namespace {
+                    return Err(AppError::forbidden(format!(
+                        "key is bound to namespace {}",
+                        caller.namespace
+                    )));
+                }
+                Some(namespace)
+            }
+            None if caller.bound_namespace => Some(caller.namespace.clone()),
+            None => None,
+        };
+
+        let mut conn = self.deps.db.acquire().await?;
+        let (id, token) = Self::insert_key(
+            &mut conn,
+            caller.user_id,
+            &name,
+            &scopes,
+            namespace.as_deref(),
+        )
+        .await?;
+        info!(
+            "api key created: id={} user_id={} scopes={} namespace={}",
+            id,
+            caller.user_id,
+            Scope::join(&scopes),
+            namespace.as_deref().unwrap_or("*")
+        );
+        Ok(CreateApiKeyResponse {
+            id,
+            name,
+            scopes,
+            namespace,
+            token,
+        })
+    }
+
+    /// Revoke one of the caller's keys; admins may revoke any key.
+    pub async fn revoke(&self, caller: Caller, id: i64) -> Result<(), AppError> {
+        let result = sqlx::query(
+            r#"
+            UPDATE api_keys
+            SET revoked_at = ?1
+            WHERE id = ?2 AND revoked_at IS NULL
+              AND ((user_id = ?3 AND (namespace = ?5 OR NOT ?6)) OR ?4)
+            "#,
+        )
+        .bind(Self::now())
+        .bind(id)
+        .bind(caller.user_id)
+        .bind(caller.has_scope(Scope::Admin))
+        .bind(&caller.namespace)
+        .bind(caller.bound_namespace)
+        .execute(&self.deps.db)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::not_found("api key not found"));
+        }
+        info!("api key revoked: id={} by key {}", id, caller.key_id);
+        Ok(())
+    }
+
+    /// Replace a key with a fresh token carrying the same name and scopes. The old
+    /// token keeps working for the grace period so clients can switch without downtime.
+    pub async fn rotate(
+        &self,
+        caller: Caller,
+        id: i64,
+        payload: RotateApiKeyRequest,
+    ) -> Result<RotateApiKeyResponse, AppError> {
+        self.deps.ensure_writable()?;
+        let auth = &self.deps.config.auth;
+        let grace_secs = payload.grace_secs.unwrap_or(auth.rotation_grace_secs);
+        if grace_secs > auth.max_rotation_grace_secs {
+            return Err(AppError::bad_request(format!(
+                "grace_secs must be at most {}",
+                auth.max_rotation_grace_secs
+            )));
+        }
+
+        let now = OffsetDateTime::now_utc();
+        let mut tx = self.deps.db.begin().await?;
+        let key: Option<RotatedKeyRow> = sqlx::query_as(
+            r#"
+            SELECT user_id, name, scopes, expires_at, namespace
+            FROM api_keys
+            WHERE id = ?1 AND revoked_at IS NULL AND (expires_at IS NULL OR expires_at > ?4)
+              AND ((user_id = ?2 AND (namespace = ?5 OR NOT ?6)) OR ?3)
+            "#,
+        )
+        .bind(id)
+        .bind(caller.user_id)
+        .bind(caller.has_scope(Scope::Admin))
+        .bind(Self::format_time(now))
+        .bind(&caller.namespace)
+        .bind(caller.bound_namespace)
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some(key) = key else {
+            return Err(AppError::not_found("api key not found"));
+        };
+
+        let scopes = Scope::parse_list(&key.scopes);
+        if let Some(missing) = scopes.iter().find(|scope| !caller.has_scope(**scope)) {
+            return Err(AppError::forbidden(format!(
+                "cannot rotate a key with {} scope without holding it",
+                missing.as_str()
+            )));
+        }
+
+        // Rotating twice never extends the life of the original token.
+        let expires_at = Self::format_time(now + time::Duration::seconds(grace_secs as i64));
+        let expires_at = match key.expires_at {
+            Some(existing) if existing < expires_at => existing,
+            _ => expires_at,
+        };
+        sqlx::query("UPDATE api_keys SET expires_at = ?1 WHERE id = ?2")
+            .bind(&expires_at)
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        let (new_id, token) = Self::insert_key(
+            &mut tx,
+            key.user_id,
+            &key.name,
+            &scopes,
+            key.namespace.as_deref(),
+        )
+        .await?;
+        tx.commit().await?;
+
+        info!(
+            "api key rotated: id={} new_id={} expires_at={} by key {}",
+            id, new_id, expires_at, caller.key_id
+        );
+        Ok(RotateApiKeyResponse {
+            id: new_id,
+            name: key.name,
+            scopes,
+            namespace: key.namespace,
+            token,
+            previous_id: id,
+            previous_expires_at: expires_at,
+        })
+    }
+
+    /// Ensure an admin key can be had: register every `ADMIN_TOKEN` entry when set,
+    /// otherwise, while no admin key exists, log a one-time setup secret for
+    /// [`Self::claim_bootstrap`].
+    ///
+    /// Listing both the old and new token during a deploy lets clients move over
+    /// without downtime; bootstrap keys no longer listed are revoked.
+    pub async fn bootstrap(&self, admin_tokens: Vec<String>) -> anyhow::Result<()> {
+        let mut conn = self.deps.db.acquire().await.context("acquire connection")?;
+
+        if !admin_tokens.is_empty() {
+            let hashes: HashSet<String> = admin_tokens
+                .iter()
+                .map(|token| AuthService::hash_token(token))
+                .collect();
+            let existing: Vec<(i64, String)> = sqlx::query_as(
+                r#"
+                SELECT id, key_hash
+                FROM api_keys
+                WHERE user_id = ?1 AND name = ?2 AND revoked_at IS NULL
+                "#,
+            )
+            .bind(ADMIN_USER_ID)
+            .bind(BOOTSTRAP_KEY_NAME)
+            .fetch_all(&mut *conn)
+            .await
+            .context("load bootstrap keys")?;
+            for (id, hash) in existing {
+                if hashes.contains(&hash) {
+                    continue;
+                }
+                sqlx::query("UPDATE api_keys SET revoked_at = ?1 WHERE id = ?2")
+                    .bind(Self::now())
+                    .bind(id)
+                    .execute(&mut *conn)
+                    .await
+                    .context("revoke stale bootstrap key")?;
+                info!("revoked bootstrap key not listed in ADMIN_TOKEN: id={}", id);
+            }
+            // Listed tokens are authoritative, so an expiry or revocation from an earlier
+            // rotation is lifted and the full scopes restored.
+            for hash in &hashes {
+                sqlx::query(
+                    r#"
+                    INSERT INTO api_keys (user_id, name, key_hash, scopes, created_at)
+                    VALUES (?1, ?2, ?3, ?4, ?5)
+                    ON CONFLICT(key_hash) DO UPDATE SET
+                        expires_at = NULL, revoked_at = NULL, scopes = excluded.scopes
+                    "#,
+                )
+                .bind(ADMIN_USER_ID)
+                .bind(BOOTSTRAP_KEY_NAME)
+                .bind(hash)
+                .bind(Scope::join(&Scope::ALL))
+                .bind(Self::now())
+                .execute(&mut *conn)
+                .await
+                .context("register ADMIN_TOKEN")?;
+            }
+            return Ok(());
+        }
+
+        let active_admin_keys = Self::active_admin_keys(&mut conn)
+            .await
+            .context("count admin keys")?;
+        if active_admin_keys == 0 {
+            let secret = AuthService::generate_token();
+            *self.deps.bootstrap_secret.lock().await = Some(AuthService::hash_token(&secret));
+            warn!(
+                "no admin API key exists; create one with `odin bootstrap --secret` (POST /v1/admin/bootstrap) \
+                 using the setup secret (shown once): {}",
+                secret
+            );
+        }
+        Ok(())
+    }
+
+    /// Create the first admin key. Only works while no active admin key exists, and only
+    /// for a request carrying the setup secret or one from the server host: a unix socket
+    /// client, or with `auth.bootstrap_trust_loopback`, a loopback peer whose request was
+    /// not `forwarded`. The secret is spent once a key exists.
+    pub async fn claim_bootstrap(
+        &self,
+        peer: Peer,
+        forwarded: bool,
+        request: BootstrapRequest,
+    ) -> Result<CreateApiKeyResponse, AppError> {
+        let local = match peer {
+            Peer::Unix => true,
+            Peer::Tcp(ip) => {
+                self.deps.config.auth.bootstrap_trust_loopback
+                    && !forwarded
+                    && ip.to_canonical().is_loopback()
+            }
+            Peer::Unknown => false,
+        };
+        self.deps.ensure_writable()?;
+        // Held until the key is in, so concurrent claims cannot both pass the check.
+        let mut secret = self.deps.bootstrap_secret.lock().await;
+        let mut tx = self.deps.db.begin().await?;
+        if Self::active_admin_keys(&mut tx).await? > 0 {
+            *secret = None;
+            return Err(AppError::conflict("an admin key already exists"));
+        }
+        let secret_matches = match (secret.as_deref(), request.secret.as_deref()) {
+            (Some(expected), Some(sent)) => {
+                AuthService::digests_match(expected, &AuthService::hash_token(sent.trim()))
+            }
+            _ => false,
+        };
+        if !local && !secret_matches {
+            return Err(AppError::forbidden(
+                "bootstrap needs the setup secret from the server log unless sent over the unix socket",
+            ));
+        }
+
+        let (id, token) = Self::insert_key(
+            &mut tx,
+            ADMIN_USER_ID,
+            BOOTSTRAP_KEY_NAME,
+            &Scope::ALL,
+            None,
+        )
+        .await?;
+        tx.commit().await?;
+        *secret = None;
+        warn!("bootstrap admin key created: id={}", id);
+        Ok(CreateApiKeyResponse {
+            id,
+            name: BOOTSTRAP_KEY_NAME.to_string(),
+            scopes: Scope::ALL.to_vec(),
+            namespace: None,
+            token,
+        })
+    }
+
+    async fn active_admin_keys(conn: &mut SqliteConnection) -> Result<i64, sqlx::Error> {
+        sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*)
+            FROM api_keys
+            WHERE revoked_at IS NULL AND (expires_at IS NULL OR expires_at > ?1)
+              AND (',' || scopes || ',') LIKE '%,admin,%'
+            "#,
+        )
+        .bind(Self::now())
+        .fetch_one(conn)
+        .await
+    }
+
+    /// Insert a fresh key and return its id and plain-text token; only the hash is stored.
+    pub(crate) async fn insert_key(
+        conn: &mut SqliteConnection,
+        user_id: i64,
+        name: &str,
+        scopes: &[Scope],
+        namespace: Option<&str>,
+    ) -> Result<(i64, String), sqlx::Error> {
+        let token = AuthService::generate_token();
+        let result = sqlx::query(
+            r#"
+            INSERT INTO api_keys (user_id, name, key_hash, scopes, created_at, namespace)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+            "#,
+        )
+        .bind(user_id)
+        .bind(name)
+        .bind(AuthService::hash_token(&token))
+        .bind(Scope::join(scopes))
+        .bind(Self::now())
+        .bind(namespace)
+        .execute(conn)
+        .await?;
+        Ok((result.last_insert_rowid(), token))
+    }
+
+    fn now() -> String {
+        Self::format_time(OffsetDateTime::now_utc())
+    }
+
+    fn format_time(at: OffsetDateTime) -> String {
+        at.format(&Rfc3339).expect("failed to format timestamp")
+    }
+}
@@ -0,0 +1,417 @@
+use std::sync::Arc;
+
+use axum::http::HeaderMap;
+use reqwest::header::{AUTHORIZATION, COOKIE};
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+
+use crate::db::DEFAULT_NAMESPACE;
+use crate::errors::AppError;
+use crate::types::{Caller, Dependencies, LoginRequest, Peer, Scope, SessionResponse};
+
+/// Selects the namespace a request works in; keys bound to a namespace may omit it.
+pub const X_ODIN_NAMESPACE: &str = "x-odin-namespace";
+
+#[derive(Clone)]
+pub struct AuthService {
+    deps: Arc<Dependencies>,
+}
+
+/// An unrevoked, unexpired key: id, owner, stored digest, scopes, bound namespace, and
+/// expiry.
+pub(crate) type KeyRow = (i64, i64, String, String, Option<String>, Option<String>);
+
+impl AuthService {
+    const LAST_USED_INTERVAL: time::Duration = time::Duration::minutes(1);
+    /// HttpOnly cookie carrying the web UI session token.
+    pub const SESSION_COOKIE: &str = "odin_session";
+    /// Header that must echo the session's CSRF token on cookie-authenticated writes.
+    pub const X_CSRF_TOKEN: &str = "x-csrf-token";
+
+    pub fn new(deps: Arc<Dependencies>) -> Self {
+        Self { deps }
+    }
+
+    /// Resolve the API key behind the bearer token, or behind the session cookie when no
+    /// `Authorization` header is sent, and require `scope` on it.
+    pub async fn authorize(&self, headers: &HeaderMap, scope: Scope) -> Result<Caller, AppError> {
+        let namespace = match headers.get(X_ODIN_NAMESPACE) {
+            Some(value) => Some(
+                value
+                    .to_str()
+                    .map_err(|_| AppError::bad_request("invalid namespace header"))?,
+            ),
+            None => None,
+        };
+
+        let Some(raw_header) = headers
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+        else {
+            if let Some(session) = Self::session_token(headers) {
+                let key = self.session_key(session).await?;
+                return self.caller(key, namespace, scope).await;
+            }
+            return Err(AppError::unauthorized("missing authorization header"));
+        };
+
+        // linkding clients send `Token <token>`, Django REST framework style.
+        let token = raw_header
+            .strip_prefix("Bearer ")
+            .or_else(|| raw_header.strip_prefix("Token "))
+            .map(str::trim)
+            .unwrap_or_default();
+
+        self.authorize_token(token, namespace, scope).await
+    }
+
+    /// Refuse a request needing the ingest, delete, or admin scope from outside
+    /// `auth.privileged_cidrs`; checked before the token so the key is not touched.
+    pub fn check_privileged_peer(&self, peer: Peer) -> Result<(), AppError> {
+        if self.deps.live_config.borrow().auth.allows_privileged(peer) {
+            return Ok(());
+        }
+        tracing::warn!("privileged request refused from {}", peer);
+        Err(AppError::forbidden(
+            "client address is not allowed to make this request",
+        ))
+    }
+
+    /// Resolve a raw token, for clients such as `EventSource` that cannot send headers.
+    pub async fn authorize_token(
+        &self,
+        token: &str,
+        namespace: Option<&str>,
+        scope: Scope,
+    ) -> Result<Caller, AppError> {
+        let key = self.token_key(token).await?;
+        self.caller(key, namespace, scope).await
+    }
+
+    /// Act as a stored key, for background work done on a user's behalf; fails once
+    /// the key is revoked or expired.
+    pub(crate) async fn authorize_key(
+        &self,
+        key_id: i64,
+        namespace: &str,
+        scope: Scope,
+    ) -> Result<Caller, AppError> {
+        let key: Option<KeyRow> = sqlx::query_as(
+            r#"
+            SELECT id, user_id, key_hash, scopes, namespace, expires_at
+            FROM api_keys
+            WHERE id = ?1 AND revoked_at IS NULL AND (expires_at IS NULL OR expires_at > ?2)
+            "#,
+        )
+        .bind(key_id)
+        .bind(Self::format_time(OffsetDateTime::now_utc()))
+        .fetch_optional(&self.deps.db)
+        .await?;
+        let key = key.ok_or_else(|| AppError::unauthorized("key revoked or expired"))?;
+        self.caller(key, Some(namespace), scope).await
+    }
+
+    /// Look up the live key behind a raw token.
+    async fn token_key(&self, token: &str) -> Result<KeyRow, AppError> {
+        if token.is_empty() {
+            return Err(AppError::unauthorized("missing token"));
+        }
+
+        // The lookup is keyed by digest, so its timing says nothing about the token;
+        // the stored digest is then checked without short-circuiting.
+        let hash = Self::hash_token(token);
+        let key: Option<KeyRow> = sqlx::query_as(
+            r#"
+            SELECT id, user_id, key_hash, scopes, namespace, expires_at
+            FROM api_keys
+            WHERE key_hash = ?1 AND revoked_at IS NULL AND (expires_at IS NULL OR expires_at > ?2)
+            "#,
+        )
+        .bind(&hash)
+        .bind(Self::format_time(OffsetDateTime::now_utc()))
+        .fetch_optional(&self.deps.db)
+        .await?;
+        match key {
+            Some(key) if Self::digests_match(&key.2, &hash) => Ok(key),
+            _ => Err(AppError::unauthorized("invalid token")),
+        }
+    }
+
+    /// Look up the live key behind an unexpired session; revoking or expiring the key
+    /// ends its sessions too.
+    async fn session_key(&self, session: &str) -> Result<KeyRow, AppError> {
+        let hash = Self::hash_token(session);
+        let key: Option<KeyRow> = sqlx::query_as(
+            r#"
+            SELECT k.id, k.user_id, k.key_hash, k.scopes, k.namespace, k.expires_at
+            FROM sessions s
+            JOIN api_keys k ON k.id = s.key_id
+            WHERE s.token_hash = ?1 AND s.expires_at > ?2
+              AND k.revoked_at IS NULL AND (k.expires_at IS NULL OR k.expires_at > ?2)
+            "#,
+        )
+        .bind(&hash)
+        .bind(Self::format_time(OffsetDateTime::now_utc()))
+        .fetch_optional(&self.deps.db)
+        .await?;
+        key.ok_or_else(|| AppError::unauthorized("session expired or signed out"))
+    }
+
+    /// Resolve the namespace for `key` and require `scope` on it.
+    async fn caller(
+        &self,
+        key: KeyRow,
+        namespace: Option<&str>,
+        scope: Scope,
+    ) -> Result<Caller, AppError> {
+        let (key_id, user_id, _, scopes, key_namespace, _) = key;
+        self.touch(key_id).await?;
+
+        let requested = namespace.map(str::trim).filter(|name| !name.is_empty());
+        let (namespace, bound_namespace) = match (key_namespace, requested) {
+            (Some(bound), Some(requested)) if bound != requested => {
+                return Err(AppError::forbidden(format!(
+                    "key is bound to namespace {}",
+                    bound
+                )));
+            }
+            (Some(bound), _) => (bound, true),
+            (None, Some(requested)) => {
+                Self::validate_namespace(requested)?;
+                (requested.to_string(), false)
+            }
+            (None, None) => (DEFAULT_NAMESPACE.to_string(), false),
+        };
+
+        let caller = Caller {
+            user_id,
+            key_id,
+            scopes: Scope::parse_list(&scopes),
+            namespace,
+            bound_namespace,
+        };
+        if !caller.has_scope(scope) {
+            return Err(AppError::forbidden(format!(
+                "{} scope required",
+                scope.as_str()
+            )));
+        }
+        Ok(caller)
+    }
+
+    /// Start a web UI session for a valid API token. Returns the session token for the
+    /// cookie.
+    pub async fn login(
+        &self,
+        request: LoginRequest,
+    ) -> Result<(String, SessionResponse), AppError> {
+        let key = self.token_key(request.token.trim()).await?;
+        self.start_session(key).await
+    }
+
+    /// Start a session on `key`, lasting `auth.session_ttl_secs` or until the key
+    /// expires if that is sooner.
+    pub(crate) async fn start_session(
+        &self,
+        key: KeyRow,
+    ) -> Result<(String, SessionResponse), AppError> {
+        let (key_id, user_id, _, scopes, namespace, key_expires_at) = key;
+        let now = OffsetDateTime::now_utc();
+        let mut expires_at =
+            now + time::Duration::seconds_f64(self.deps.config.auth.session_ttl_secs as f64);
+        if let Some(key_expires_at) = key_expires_at
+            .as_deref()
+            .and_then(|at| OffsetDateTime::parse(at, &Rfc3339).ok())
+        {
+            expires_at = expires_at.min(key_expires_at);
+        }
+
+        sqlx::query("DELETE FROM sessions WHERE expires_at <= ?1")
+            .bind(Self::format_time(now))
+            .execute(&self.deps.db)
+            .await?;
+        let session = Self::generate_token();
+        sqlx::query(
+            r#"
+            INSERT INTO sessions (token_hash, key_id, created_at, expires_at)
+            VALUES (?1, ?2, ?3, ?4)
+            "#,
+        )
+        .bind(Self::hash_token(&session))
+        .bind(key_id)
+        .bind(Self::format_time(now))
+        .bind(Self::format_time(expires_at))
+        .execute(&self.deps.db)
+        .await?;
+        tracing::info!(user_id, key_id, "web session started");
+
+        let response = SessionResponse {
+            user_id,
+            scopes: Scope::parse_list(&scopes),
+            namespace,
+            csrf_token: Self::csrf_token(&session),
+            expires_at: Self::format_time(expires_at),
+        };
+        Ok((session, response))
+    }
+
+    /// Describe the session behind the request's cookie.
+    pub async fn session(&self, headers: &HeaderMap) -> Result<SessionResponse, AppError> {
+        let Some(session) = Self::session_token(headers) else {
+            return Err(AppError::unauthorized("not signed in"));
+        };
+        let (_, user_id, _, scopes, namespace, _) = self.session_key(session).await?;
+        let expires_at: String =
+            sqlx::query_scalar("SELECT expires_at FROM sessions WHERE token_hash = ?1")
+                .bind(Self::hash_token(session))
+                .fetch_one(&self.deps.db)
+                .await?;
+        Ok(SessionResponse {
+            user_id,
+            scopes: Scope::parse_list(&scopes),
+            namespace,
+            csrf_token: Self::csrf_token(session),
+            expires_at,
+        })
+    }
+
+    /// End the session behind the request's cookie, if any.
+    pub async fn logout(&self, headers: &HeaderMap) -> Result<(), AppError> {
+        if let Some(session) = Self::session_token(headers) {
+            sqlx::query("DELETE FROM sessions WHERE token_hash = ?1")
+                .bind(Self::hash_token(session))
+                .execute(&self.deps.db)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Require a matching `X-CSRF-Token` on writes authenticated by the session cookie.
+    /// Bearer requests are not exposed to CSRF and pass unchecked.
+    pub fn check_csrf(headers: &HeaderMap) -> Result<(), AppError> {
+        if headers.contains_key(AUTHORIZATION) {
+            return Ok(());
+        }
+        let Some(session) = Self::session_token(headers) else {
+            return Ok(());
+        };
+        let sent = headers
+            .get(Self::X_CSRF_TOKEN)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default();
+        if !Self::digests_match(sent, &Self::csrf_token(session)) {
+            return Err(AppError::forbidden("missing or invalid CSRF token"));
+        }
+        Ok(())
+    }
+
+    /// `Set-Cookie` value carrying a new session token.
+    pub fn session_cookie(&self, session: &str) -> String {
+        format!(
+            "{}={}; Max-Age={}{}",
+            Self::SESSION_COOKIE,
+            session,
+            self.deps.config.auth.session_ttl_secs,
+            self.cookie_attributes()
+        )
+    }
+
+    /// `Set-Cookie` value that removes the session cookie.
+    pub fn clear_session_cookie(&self) -> String {
+        format!(
+            "{}=; Max-Age=0{}",
+            Self::SESSION_COOKIE,
+            self.cookie_attributes()
+        )
+    }
+
+    fn cookie_attributes(&self) -> &'static str {
+        if self.secure_cookies() {
+            "; Path=/; HttpOnly; SameSite=Strict; Secure"
+        } else {
+            "; Path=/; HttpOnly; SameSite=Strict"
+        }
+    }
+
+    /// Whether cookies get the `Secure` attribute.
+    pub(crate) fn secure_cookies(&self) -> bool {
+        let config = &self.deps.config;
+        config.auth.session_cookie_secure || config.tls.cert_path.is_some()
+    }
+
+    fn session_token(headers: &HeaderMap) -> Option<&str> {
+        Self::cookie(headers, Self::SESSION_COOKIE)
+    }
+
+    /// The non-empty value of cookie `name`.
+    pub(crate) fn cookie<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+        headers
+            .get_all(COOKIE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(';'))
+            .filter_map(|pair| pair.trim().split_once('='))
+            .find(|(cookie, _)| *cookie == name)
+            .map(|(_, value)| value.trim())
+            .filter(|value| !value.is_empty())
+    }
+
+    /// Derived from the session token, so it needs no storage and only a page that
+    /// read it from `/v1/auth/session` can send it.
+    fn csrf_token(session: &str) -> String {
+        hex::encode(Sha256::digest(format!("odin-csrf:{}", session).as_bytes()))
+    }
+
+    /// Record key usage, at most once per interval to keep reads cheap.
+    async fn touch(&self, key_id: i64) -> Result<(), AppError> {
+        let now = OffsetDateTime::now_utc();
+        sqlx::query(
+            r#"
+            UPDATE api_keys
+            SET last_used_at = ?1
+            WHERE id = ?2 AND (last_used_at IS NULL OR last_used_at < ?3)
+            "#,
+        )
+        .bind(Self::format_time(now))
+        .bind(key_id)
+        .bind(Self::format_time(now - Self::LAST_USED_INTERVAL))
+        .execute(&self.deps.db)
+        .await?;
+        Ok(())
+    }
+
+    /// Tokens are stored as hex-encoded SHA-256 digests, never in plain text.
+    pub fn hash_token(token: &str) -> String {
+        hex::encode(Sha256::digest(token.as_bytes()))
+    }
+
+    /// Namespaces are short slugs: lowercase letters, digits, `-`, and `_`.
+    pub fn validate_namespace(name: &str) -> Result<(), AppError> {
+        let valid = (1..=64).contains(&name.len())
+            && name
+                .bytes()
+                .all(|byte| matches!(byte, b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_'));
+        if !valid {
+            return Err(AppError::bad_request(
+                "namespace must be 1-64 characters of a-z, 0-9, '-', or '_'",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Compare two digests in constant time.
+    pub fn digests_match(a: &str, b: &str) -> bool {
+        a.as_bytes().ct_eq(b.as_bytes()).into()
+    }
+
+    fn format_time(at: OffsetDateTime) -> String {
+        at.format(&Rfc3339).expect("failed to format timestamp")
+    }
+
+    /// Generate a new random bearer token.
+    pub fn generate_token() -> String {
+        hex::encode(rand::random::<[u8; 32]>())
+    }
+}
@@ -0,0 +1,312 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::Context;
+use axum::body::Body;
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use futures_util::StreamExt;
+use time::OffsetDateTime;
+use time::format_description;
+use tokio::io::AsyncWriteExt;
+use tracing::info;
+
+use crate::errors::AppError;
+use crate::index::{SCHEMA_VERSION_FILE, shard_dirs};
+use crate::services::storage::PRE_RESTORE_PREFIX;
+use crate::services::{JobHandle, JobService, StorageService};
+use crate::types::{Dependencies, JobKind};
+
+/// Directory (under the data dir) holding a restore that is applied on next startup.
+pub const RESTORE_DIR: &str = "restore";
+
+pub(crate) const DB_FILE: &str = "app.db";
+pub(crate) const INDEX_DIR: &str = "index";
+
+/// Snapshot steps reported as job progress: database, index files, archive.
+const BACKUP_STEPS: usize = 3;
+
+pub struct BackupArchive {
+    pub job_id: i64,
+    pub file_name: String,
+    pub file: tokio::fs::File,
+}
+
+#[derive(Clone)]
+pub struct BackupService {
+    deps: Arc<Dependencies>,
+    jobs: JobService,
+    storage: StorageService,
+}
+
+impl BackupService {
+    pub fn new(deps: Arc<Dependencies>, jobs: JobService, storage: StorageService) -> Self {
+        Self {
+            deps,
+            jobs,
+            storage,
+        }
+    }
+
+    fn ensure_on_disk(&self) -> Result<(), AppError> {
+        if self.deps.config.storage.in_memory {
+            return Err(AppError::conflict(
+                "backups and restores are unavailable with in-memory storage",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Snapshot the database and committed index segments into a gzipped tarball.
+    pub async fn create(&self, owner_id: i64) -> Result<BackupArchive, AppError> {
+        self.ensure_on_disk()?;
+        self.storage.ensure_snapshot_room().await?;
+        let mut job = self
+            .jobs
+            .start(owner_id, JobKind::Backup, Some(BACKUP_STEPS))
+            .await?;
+        let stamp = Self::timestamp()?;
+        let file_name = format!("odin-backup-{}.tar.gz", stamp);
+        let staging = self
+            .deps
+            .config
+            .storage
+            .data_dir
+            .join(format!("backup-{}", stamp));
+        let archive_path = self.deps.config.storage.data_dir.join(&file_name);
+        info!("backup start: {}", file_name);
+
+        tokio::fs::create_dir_all(staging.join(INDEX_DIR))
+            .await
+            .context("create backup staging dir")?;
+
+        let result = self.snapshot_into(&staging, &archive_path, &mut job).await;
+        tokio::fs::remove_dir_all(&staging)
+            .await
+            .context("remove backup staging dir")?;
+        if let Err(err) = result {
+            let _ = tokio::fs::remove_file(&archive_path).await;
+            let cancelled = job.is_cancelled();
+            job.finish(0, Err(format!("{:#}", err))).await;
+            if cancelled {
+                return Err(AppError::conflict("backup cancelled"));
+            }
+            return Err(err.into());
+        }
+
+        // The open handle keeps the archive readable after the path is unlinked.
+        let file = tokio::fs::File::open(&archive_path)
+            .await
+            .context("open backup archive")?;
+        tokio::fs::remove_file(&archive_path)
+            .await
+            .context("remove backup archive")?;
+
+        info!("backup ready: {}", file_name);
+        let job_id = job.id();
+        job.finish(BACKUP_STEPS, Ok(())).await;
+        Ok(BackupArchive {
+            job_id,
+            file_name,
+            file,
+        })
+    }
+
+    /// Stream an uploaded archive to disk, validate it, and stage it for the next startup.
+    pub async fn stage_restore(&self, body: Body) -> Result<(), AppError> {
+        self.ensure_on_disk()?;
+        self.storage.ensure_snapshot_room().await?;
+        let upload_path = self
+            .deps
+            .config
+            .storage
+            .data_dir
+            .join("restore-upload.tar.gz");
+        let restore_dir = self.deps.config.storage.data_dir.join(RESTORE_DIR);
+
+        let mut upload = tokio::fs::File::create(&upload_path)
+            .await
+            .context("create restore upload")?;
+        let mut stream = body.into_data_stream();
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk.map_err(|err| AppError::bad_request(err.to_string()))?;
+            upload
+                .write_all(&chunk)
+                .await
+                .context("write restore upload")?;
+        }
+        upload.flush().await.context("flush restore upload")?;
+        drop(upload);
+
+        if tokio::fs::try_exists(&restore_dir).await.unwrap_or(false) {
+            tokio::fs::remove_dir_all(&restore_dir)
+                .await
+                .context("clear previous restore")?;
+        }
+
+        let unpack_path = upload_path.clone();
+        let unpack_dir = restore_dir.clone();
+        let unpacked =
+            tokio::task::spawn_blocking(move || Self::unpack_archive(&unpack_path, &unpack_dir))
+                .await
+                .context("join restore unpack")?;
+        tokio::fs::remove_file(&upload_path)
+            .await
+            .context("remove restore upload")?;
+
+        if let Err(err) = unpacked {
+            let _ = tokio::fs::remove_dir_all(&restore_dir).await;
+            return Err(AppError::bad_request(format!(
+                "invalid backup archive: {}",
+                err
+            )));
+        }
+
+        info!("restore staged: {}", restore_dir.display());
+        Ok(())
+    }
+
+    async fn snapshot_into(
+        &self,
+        staging: &Path,
+        archive_path: &Path,
+        job: &mut JobHandle,
+    ) -> anyhow::Result<()> {
+        let db_snapshot = staging.join(DB_FILE);
+        sqlx::query("VACUUM INTO ?1")
+            .bind(db_snapshot.to_string_lossy().to_string())
+            .execute(&self.deps.db)
+            .await
+            .context("snapshot database")?;
+        Self::checkpoint(job, 1).await?;
+
+        {
+            // Holding the writer lock keeps new commits from swapping segments mid-copy.
+            let _writer = self.deps.writer.lock().await;
+            let index_dir = self.deps.config.storage.data_dir.join(INDEX_DIR);
+            let shards = self.deps.index.shards();
+            let mut files = vec![PathBuf::from(SCHEMA_VERSION_FILE)];
+            for (shard, dir) in shards.iter().zip(shard_dirs(Path::new(""), shards.len())) {
+                let metas = shard.index.load_metas().context("load index metas")?;
+                files.extend(
+                    metas
+                        .segments
+                        .iter()
+                        .flat_map(|segment| segment.list_files())
+                        .chain([PathBuf::from("meta.json"), PathBuf::from(".managed.json")])
+                        .map(|file| dir.join(file)),
+                );
+                tokio::fs::create_dir_all(staging.join(INDEX_DIR).join(&dir))
+                    .await
+                    .context("create staging shard dir")?;
+            }
+
+            for file in files {
+                let source = index_dir.join(&file);
+                if !tokio::fs::try_exists(&source).await.unwrap_or(false) {
+                    continue;
+                }
+                tokio::fs::copy(&source, staging.join(INDEX_DIR).join(&file))
+                    .await
+                    .with_context(|| format!("copy index file {}", file.display()))?;
+            }
+        }
+        Self::checkpoint(job, 2).await?;
+
+        let staging = staging.to_path_buf();
+        let archive_path = archive_path.to_path_buf();
+        tokio::task::spawn_blocking(move || Self::write_archive(&staging, &archive_path))
+            .await
+            .context("join backup archive")??;
+        Ok(())
+    }
+
+    /// Record a finished step, stopping here if the job was cancelled.
+    async fn checkpoint(job: &mut JobHandle, step: usize) -> anyhow::Result<()> {
+        if job.is_cancelled() {
+            anyhow::bail!("backup cancelled");
+        }
+        job.progress(step, None).await;
+        Ok(())
+    }
+
+    fn write_archive(staging: &Path, archive_path: &Path) -> anyhow::Result<()> {
+        let file = std::fs::File::create(archive_path).context("create backup archive")?;
+        let mut builder = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+        builder
+            .append_path_with_name(staging.join(DB_FILE), DB_FILE)
+            .context("archive database")?;
+        builder
+            .append_dir_all(INDEX_DIR, staging.join(INDEX_DIR))
+            .context("archive index")?;
+        builder
+            .into_inner()
+            .context("finish backup archive")?
+            .finish()
+            .context("finish backup compression")?;
+        Ok(())
+    }
+
+    fn unpack_archive(archive_path: &Path, restore_dir: &Path) -> anyhow::Result<()> {
+        std::fs::create_dir_all(restore_dir).context("create restore dir")?;
+        let file = std::fs::File::open(archive_path).context("open restore upload")?;
+        let mut archive = tar::Archive::new(GzDecoder::new(file));
+        for entry in archive.entries().context("read archive entries")? {
+            let mut entry = entry.context("read archive entry")?;
+            let path = entry
+                .path()
+                .context("read archive entry path")?
+                .into_owned();
+            let allowed = path == Path::new(DB_FILE) || path.starts_with(INDEX_DIR);
+            if !allowed {
+                anyhow::bail!("unexpected entry {}", path.display());
+            }
+            entry
+                .unpack_in(restore_dir)
+                .with_context(|| format!("unpack {}", path.display()))?;
+        }
+        if !restore_dir.join(DB_FILE).exists() || !restore_dir.join(INDEX_DIR).exists() {
+            anyhow::bail!("archive must contain {} and {}/", DB_FILE, INDEX_DIR);
+        }
+        Ok(())
+    }
+
+    fn timestamp() -> anyhow::Result<String> {
+        let format = format_description::parse("[year][month][day]T[hour][minute][second]Z")
+            .context("parse timestamp format")?;
+        OffsetDateTime::now_utc()
+            .format(&format)
+            .context("format timestamp")
+    }
+}
+
+/// Swap a staged restore into place, keeping the replaced data alongside it.
+pub fn apply_pending_restore(data_dir: &Path) -> anyhow::Result<()> {
+    let restore_dir = data_dir.join(RESTORE_DIR);
+    if !restore_dir.join(DB_FILE).exists() {
+        return Ok(());
+    }
+
+    let stamp = BackupService::timestamp()?;
+    let previous = data_dir.join(format!("{}{}", PRE_RESTORE_PREFIX, stamp));
+    std::fs::create_dir_all(&previous).context("create pre-restore dir")?;
+    for name in [DB_FILE, "app.db-wal", "app.db-shm", INDEX_DIR] {
+        let current = data_dir.join(name);
+        if current.exists() {
+            std::fs::rename(&current, previous.join(name))
+                .with_context(|| format!("move aside {}", name))?;
+        }
+    }
+    std::fs::rename(restore_dir.join(DB_FILE), data_dir.join(DB_FILE))
+        .context("move restored database")?;
+    std::fs::rename(restore_dir.join(INDEX_DIR), data_dir.join(INDEX_DIR))
+        .context("move restored index")?;
+    std::fs::remove_dir_all(&restore_dir).context("remove restore dir")?;
+
+    info!(
+        "restore applied; previous data kept in {}",
+        previous.display()
+    );
+    Ok(())
+}
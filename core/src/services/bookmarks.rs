@@ -0,0 +1,446 @@
+use std::sync::Arc;
+
+use tracing::info;
+
+use crate::errors::AppError;
+use crate::services::IngestService;
+use crate::types::{
+    ArchivedPage, BookmarkContentResponse, BookmarkListItem, BookmarkSort, BookmarkTagsRequest,
+    BookmarkTagsResponse, BookmarksParams, BookmarksResponse, BulkDeleteRequest,
+    BulkDeleteResponse, Caller, Dependencies, LookupParams, NamespaceItem, NamespacesResponse,
+    RefetchParams, RefetchResponse, TagItem, TagsResponse,
+};
+use crate::{content, tags};
+
+#[derive(Clone)]
+pub struct BookmarkService {
+    deps: Arc<Dependencies>,
+    ingest: IngestService,
+}
+
+impl BookmarkService {
+    const MAX_BULK_DELETE: usize = 1000;
+    const DEFAULT_PER_PAGE: u32 = 50;
+    const MAX_PER_PAGE: u32 = 500;
+
+    pub fn new(deps: Arc<Dependencies>, ingest: IngestService) -> Self {
+        Self { deps, ingest }
+    }
+
+    pub async fn list(
+        &self,
+        caller: Caller,
+        params: BookmarksParams,
+    ) -> Result<BookmarksResponse, AppError> {
+        let tag = params.tag.as_deref().map(tags::normalize).transpose()?;
+        let status = params.status.as_deref().filter(|status| !status.is_empty());
+        if status
+            .is_some_and(|status| !matches!(status, "queued" | "indexed" | "partial" | "failed"))
+        {
+            return Err(AppError::bad_request(
+                "status must be queued, indexed, partial, or failed",
+            ));
+        }
+        let q = params.q.as_deref().map(str::trim).filter(|q| !q.is_empty());
+        let page = params.page.unwrap_or(1).max(1);
+        let per_page = params
+            .per_page
+            .unwrap_or(Self::DEFAULT_PER_PAGE)
+            .clamp(1, Self::MAX_PER_PAGE);
+        let order = match params.sort.unwrap_or_default() {
+            BookmarkSort::Updated => "updated_at DESC, id DESC",
+            BookmarkSort::Created => "created_at DESC, id DESC",
+            BookmarkSort::Oldest => "created_at ASC, id ASC",
+            BookmarkSort::Title => "lower(COALESCE(title, url)) ASC, id DESC",
+            BookmarkSort::Url => "url ASC, id DESC",
+        };
+
+        let filter = r#"
+            owner_id = ?1 AND namespace = ?2
+              AND (?3 IS NULL OR id IN (
+                  SELECT bt.bookmark_id FROM bookmark_tags bt JOIN tags t ON t.id = bt.tag_id
+                  WHERE t.owner_id = ?1 AND t.namespace = ?2 AND t.name = ?3
+              ))
+              AND (?4 IS NULL OR status = ?4)
+              AND (?5 IS NULL OR instr(lower(url || ' ' || COALESCE(title, '')), lower(?5)) > 0)
+        "#;
+        let total: i64 =
+            sqlx::query_scalar(&format!("SELECT COUNT(*) FROM bookmarks WHERE {}", filter))
+                .bind(caller.user_id)
+                .bind(&caller.namespace)
+                .bind(&tag)
+                .bind(status)
+                .bind(q)
+                .fetch_one(&self.deps.db)
+                .await?;
+        let mut results: Vec<BookmarkListItem> = sqlx::query_as(&format!(
+            r#"
+            SELECT id, url, COALESCE(original_url, url) AS original_url, title, status, attempts, document_type,
+                updated_at
+            FROM bookmarks
+            WHERE {}
+            ORDER BY {}
+            LIMIT ?6 OFFSET ?7
+            "#,
+            filter, order
+        ))
+        .bind(caller.user_id)
+        .bind(&caller.namespace)
+        .bind(&tag)
+        .bind(status)
+        .bind(q)
+        .bind(per_page)
+        .bind(i64::from(page - 1) * i64::from(per_page))
+        .fetch_all(&self.deps.db)
+        .await?;
+        // Only the page's tags; the page is at most `per_page` ids.
+        let ids: Vec<i64> = results.iter().map(|bookmark| bookmark.id).collect();
+        let mut tags = tags::of_bookmarks(&self.deps.db, &ids).await?;
+        for bookmark in &mut results {
+            bookmark.tags = tags.remove(&bookmark.id).unwrap_or_default();
+        }
+
+        info!(
+            "bookmarks listed: {} of {} (page {})",
+            results.len(),
+            total,
+            page
+        );
+        Ok(BookmarksResponse {
+            total,
+            page,
+            per_page,
+            results,
+        })
+    }
+
+    pub async fn get(
+        &self,
+        caller: &Caller,
+        id: i64,
+    ) -> Result<Option<BookmarkListItem>, AppError> {
+        let bookmark = sqlx::query_as(
+            r#"
+            SELECT id, url, COALESCE(original_url, url) AS original_url, title, status, attempts, document_type,
+                updated_at
+            FROM bookmarks
+            WHERE id = ?1 AND owner_id = ?2 AND namespace = ?3
+            "#,
+        )
+        .bind(id)
+        .bind(caller.user_id)
+        .bind(&caller.namespace)
+        .fetch_optional(&self.deps.db)
+        .await?;
+        self.with_tags(bookmark).await
+    }
+
+    /// The caller's bookmark for `url`, matched after the same normalization ingest applies,
+    /// or as submitted for one that moved to where its page redirected or that absorbed a
+    /// duplicate saved under `url`, so extensions can mark a tab as saved.
+    pub async fn lookup(
+        &self,
+        caller: Caller,
+        params: LookupParams,
+    ) -> Result<BookmarkListItem, AppError> {
+        let Some(url) = IngestService::normalize_url(&params.url) else {
+            return Err(AppError::bad_request("invalid url"));
+        };
+        let bookmark = sqlx::query_as(
+            r#"
+            SELECT id, url, COALESCE(original_url, url) AS original_url, title, status, attempts, document_type,
+                updated_at
+            FROM bookmarks
+            WHERE (url = ?1 OR original_url = ?4 OR id IN (
+                    SELECT bookmark_id FROM bookmark_aliases WHERE url = ?1 OR original_url = ?4
+                ))
+                AND owner_id = ?2 AND namespace = ?3
+            ORDER BY url = ?1 DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(&url)
+        .bind(caller.user_id)
+        .bind(&caller.namespace)
+        .bind(params.url.trim())
+        .fetch_optional(&self.deps.db)
+        .await?;
+        self.with_tags(bookmark)
+            .await?
+            .ok_or_else(|| AppError::not_found("bookmark not found"))
+    }
+
+    async fn with_tags(
+        &self,
+        bookmark: Option<BookmarkListItem>,
+    ) -> Result<Option<BookmarkListItem>, AppError> {
+        let Some(mut bookmark) = bookmark else {
+            return Ok(None);
+        };
+        bookmark.tags = tags::of_bookmark(&self.deps.db, bookmark.id).await?;
+        Ok(Some(bookmark))
+    }
+
+    /// Replace a bookmark's tags and rewrite its index document to match.
+    pub async fn set_tags(
+        &self,
+        caller: Caller,
+        id: i64,
+        payload: BookmarkTagsRequest,
+    ) -> Result<BookmarkTagsResponse, AppError> {
+        self.deps.ensure_writable()?;
+        let mut names = tags::normalize_all(&payload.tags)?;
+        if self.get(&caller, id).await?.is_none() {
+            return Err(AppError::not_found("bookmark not found"));
+        }
+        let mut tx = self.deps.db.begin().await?;
+        tags::replace(&mut tx, caller.user_id, &caller.namespace, id, &names).await?;
+        tx.commit().await?;
+        self.ingest.refresh_document(id).await?;
+        info!("bookmark tags set: id={} tags={}", id, names.len());
+        names.sort();
+        Ok(BookmarkTagsResponse { id, tags: names })
+    }
+
+    /// Tags in the caller's namespace with how many bookmarks have each, by name.
+    pub async fn tags(&self, caller: Caller) -> Result<TagsResponse, AppError> {
+        let results: Vec<TagItem> = sqlx::query_as(
+            r#"
+            SELECT t.name, COUNT(*) AS bookmarks
+            FROM tags t
+            JOIN bookmark_tags bt ON bt.tag_id = t.id
+            WHERE t.owner_id = ?1 AND t.namespace = ?2
+            GROUP BY t.id
+            ORDER BY t.name
+            "#,
+        )
+        .bind(caller.user_id)
+        .bind(&caller.namespace)
+        .fetch_all(&self.deps.db)
+        .await?;
+        Ok(TagsResponse { results })
+    }
+
+    /// The text extracted at the bookmark's last fetch, read from the database.
+    pub async fn content(
+        &self,
+        caller: Caller,
+        id: i64,
+    ) -> Result<BookmarkContentResponse, AppError> {
+        let bookmark: Option<(i64, String, Option<String>, Option<String>)> = sqlx::query_as(
+            r#"
+            SELECT id, url, title, fetched_at
+            FROM bookmarks
+            WHERE id = ?1 AND owner_id = ?2 AND namespace = ?3
+            "#,
+        )
+        .bind(id)
+        .bind(caller.user_id)
+        .bind(&caller.namespace)
+        .fetch_optional(&self.deps.db)
+        .await?;
+        let Some((id, url, title, fetched_at)) = bookmark else {
+            return Err(AppError::not_found("bookmark not found"));
+        };
+        let stored: Option<(Vec<u8>, String)> = sqlx::query_as(
+            "SELECT CAST(body AS BLOB), encoding FROM bookmark_contents WHERE bookmark_id = ?1",
+        )
+        .bind(id)
+        .fetch_optional(&self.deps.db)
+        .await?;
+        let Some((body, encoding)) = stored else {
+            return Err(AppError::not_found("bookmark has no extracted text yet"));
+        };
+        Ok(BookmarkContentResponse {
+            id,
+            url,
+            title,
+            fetched_at,
+            body: content::decode(&encoding, body)?,
+        })
+    }
+
+    /// The page as it was last fetched, if it was archived.
+    pub async fn archive(&self, caller: Caller, id: i64) -> Result<ArchivedPage, AppError> {
+        let archived: Option<(Vec<u8>, String, String, String)> = sqlx::query_as(
+            r#"
+            SELECT CAST(a.body AS BLOB), a.encoding, a.content_type, a.fetched_at
+            FROM bookmark_archives a
+            JOIN bookmarks b ON b.id = a.bookmark_id
+            WHERE b.id = ?1 AND b.owner_id = ?2 AND b.namespace = ?3
+            "#,
+        )
+        .bind(id)
+        .bind(caller.user_id)
+        .bind(&caller.namespace)
+        .fetch_optional(&self.deps.db)
+        .await?;
+        let Some((body, encoding, content_type, fetched_at)) = archived else {
+            if self.get(&caller, id).await?.is_none() {
+                return Err(AppError::not_found("bookmark not found"));
+            }
+            return Err(AppError::not_found("bookmark has no archived page"));
+        };
+        Ok(ArchivedPage {
+            content_type,
+            fetched_at,
+            body: content::decode_bytes(&encoding, body)?,
+        })
+    }
+
+    /// Namespaces holding the caller's bookmarks; a bound key sees only its own.
+    pub async fn namespaces(&self, caller: Caller) -> Result<NamespacesResponse, AppError> {
+        let results: Vec<NamespaceItem> = sqlx::query_as(
+            r#"
+            SELECT namespace AS name, COUNT(*) AS bookmarks
+            FROM bookmarks
+            WHERE owner_id = ?1 AND (namespace = ?2 OR NOT ?3)
+            GROUP BY namespace
+            ORDER BY namespace
+            "#,
+        )
+        .bind(caller.user_id)
+        .bind(&caller.namespace)
+        .bind(caller.bound_namespace)
+        .fetch_all(&self.deps.db)
+        .await?;
+        Ok(NamespacesResponse { results })
+    }
+
+    /// Fetch and index a bookmark again in place, keeping its id and what it was saved
+    /// with; a bookmark already queued is left alone.
+    pub async fn refetch(&self, caller: Caller, id: i64) -> Result<RefetchResponse, AppError> {
+        info!("bookmark refetch requested: id={}", id);
+        if id <= 0 {
+            return Err(AppError::bad_request("invalid bookmark id"));
+        }
+        if self.get(&caller, id).await?.is_none() {
+            return Err(AppError::not_found("bookmark not found"));
+        }
+        let queued = self.ingest.refetch(&caller, Some(id), None).await?;
+        Ok(RefetchResponse { queued })
+    }
+
+    /// Refetch every bookmark in the caller's namespace, or those with `status`.
+    pub async fn refetch_many(
+        &self,
+        caller: Caller,
+        params: RefetchParams,
+    ) -> Result<RefetchResponse, AppError> {
+        let status = params.status.as_deref();
+        info!("bookmark bulk refetch requested: status={:?}", status);
+        if status.is_some_and(|status| !matches!(status, "indexed" | "partial" | "failed")) {
+            return Err(AppError::bad_request(
+                "status must be indexed, partial, or failed",
+            ));
+        }
+        let queued = self.ingest.refetch(&caller, None, status).await?;
+        Ok(RefetchResponse { queued })
+    }
+
+    pub async fn delete(&self, caller: Caller, id: i64) -> Result<(), AppError> {
+        info!("bookmark delete requested: id={}", id);
+        self.deps.ensure_writable()?;
+        if id <= 0 {
+            return Err(AppError::bad_request("invalid bookmark id"));
+        }
+
+        let url: Option<String> = sqlx::query_scalar(
+            "SELECT url FROM bookmarks WHERE id = ?1 AND owner_id = ?2 AND namespace = ?3",
+        )
+        .bind(id)
+        .bind(caller.user_id)
+        .bind(&caller.namespace)
+        .fetch_optional(&self.deps.db)
+        .await?;
+        let Some(url) = url else {
+            info!("bookmark delete not found: id={}", id);
+            return Err(AppError::not_found("bookmark not found"));
+        };
+
+        if self
+            .ingest
+            .purge(
+                caller.user_id,
+                &caller.namespace,
+                vec![(id, url.clone())],
+                "delete",
+            )
+            .await?
+            == 0
+        {
+            info!("bookmark delete missing row after select: id={}", id);
+            return Err(AppError::not_found("bookmark not found"));
+        }
+        info!("bookmark deleted: id={} url={}", id, url);
+        Ok(())
+    }
+
+    pub async fn delete_many(
+        &self,
+        caller: Caller,
+        payload: BulkDeleteRequest,
+    ) -> Result<BulkDeleteResponse, AppError> {
+        self.deps.ensure_writable()?;
+        info!(
+            "bookmark bulk delete requested: ids={} urls={}",
+            payload.ids.len(),
+            payload.urls.len()
+        );
+        if payload.ids.len() + payload.urls.len() > Self::MAX_BULK_DELETE {
+            return Err(AppError::bad_request("too many bookmarks"));
+        }
+
+        let mut found: Vec<(i64, String)> = Vec::new();
+        let mut not_found = Vec::new();
+
+        for id in payload.ids {
+            let url: Option<String> = sqlx::query_scalar(
+                "SELECT url FROM bookmarks WHERE id = ?1 AND owner_id = ?2 AND namespace = ?3",
+            )
+            .bind(id)
+            .bind(caller.user_id)
+            .bind(&caller.namespace)
+            .fetch_optional(&self.deps.db)
+            .await?;
+            match url {
+                Some(url) => found.push((id, url)),
+                None => not_found.push(id.to_string()),
+            }
+        }
+
+        for raw_url in payload.urls {
+            let Some(normalized) = IngestService::normalize_url(&raw_url) else {
+                not_found.push(raw_url);
+                continue;
+            };
+            let id: Option<i64> = sqlx::query_scalar(
+                "SELECT id FROM bookmarks WHERE url = ?1 AND owner_id = ?2 AND namespace = ?3",
+            )
+            .bind(&normalized)
+            .bind(caller.user_id)
+            .bind(&caller.namespace)
+            .fetch_optional(&self.deps.db)
+            .await?;
+            match id {
+                Some(id) => found.push((id, normalized)),
+                None => not_found.push(raw_url),
+            }
+        }
+
+        found.sort_unstable();
+        found.dedup();
+
+        let deleted = self
+            .ingest
+            .purge(caller.user_id, &caller.namespace, found, "bulk_delete")
+            .await?;
+
+        info!(
+            "bookmark bulk delete finished: deleted={} not_found={}",
+            deleted,
+            not_found.len()
+        );
+        Ok(BulkDeleteResponse { deleted, not_found })
+    }
+}
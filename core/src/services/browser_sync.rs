@@ -0,0 +1,327 @@
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::Context;
+use axum::body::Body;
+use futures_util::StreamExt;
+use serde::Deserialize;
+use sqlx::sqlite::SqliteConnectOptions;
+use sqlx::{ConnectOptions, Connection};
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tracing::info;
+
+use crate::errors::AppError;
+use crate::services::{BookmarkService, IngestService};
+use crate::types::{
+    BrowserSyncParams, BrowserSyncResponse, BulkDeleteRequest, Caller, Dependencies, Scope,
+};
+
+const SQLITE_HEADER: &[u8] = b"SQLite format 3\0";
+const MAX_SOURCE_CHARS: usize = 64;
+/// Bookmarks deleted per `delete_many` call, its own limit.
+const DELETE_BATCH: usize = 1000;
+
+/// Folders in a Firefox profile as slash-joined paths, skipping the tags tree, whose
+/// entries are tag assignments rather than bookmarks.
+const FIREFOX_BOOKMARKS: &str = r#"
+    WITH RECURSIVE folders(id, path) AS (
+        SELECT id, '' FROM moz_bookmarks WHERE parent = 0
+        UNION ALL
+        SELECT b.id, CASE WHEN f.path = '' THEN COALESCE(b.title, '')
+                          ELSE f.path || '/' || COALESCE(b.title, '') END
+        FROM moz_bookmarks b
+        JOIN folders f ON b.parent = f.id
+        WHERE b.type = 2 AND b.guid <> 'tags________'
+    )
+    SELECT p.url, f.path
+    FROM moz_bookmarks b
+    JOIN moz_places p ON p.id = b.fk
+    JOIN folders f ON f.id = b.parent
+    WHERE b.type = 1
+"#;
+
+/// A node of Chrome's `Bookmarks` file, also written by Edge, Brave, and Vivaldi.
+#[derive(Deserialize)]
+struct ChromeNode {
+    #[serde(rename = "type")]
+    kind: Option<String>,
+    #[serde(default)]
+    name: String,
+    url: Option<String>,
+    #[serde(default)]
+    children: Vec<ChromeNode>,
+}
+
+#[derive(Deserialize)]
+struct ChromeBookmarks {
+    roots: BTreeMap<String, serde_json::Value>,
+}
+
+/// Keeps odin in step with a browser's bookmarks. Each upload of Chrome's `Bookmarks`
+/// JSON or Firefox's `places.sqlite` is compared with the URL and folder pairs the last
+/// upload from the same source left in `browser_sync_entries`: new URLs are ingested,
+/// and with `prune`, bookmarks whose URL left every folder are deleted.
+#[derive(Clone)]
+pub struct BrowserSyncService {
+    deps: Arc<Dependencies>,
+    bookmarks: BookmarkService,
+    ingest: IngestService,
+}
+
+impl BrowserSyncService {
+    pub fn new(deps: Arc<Dependencies>, bookmarks: BookmarkService, ingest: IngestService) -> Self {
+        Self {
+            deps,
+            bookmarks,
+            ingest,
+        }
+    }
+
+    pub async fn sync(
+        &self,
+        caller: Caller,
+        params: BrowserSyncParams,
+        body: Body,
+    ) -> Result<BrowserSyncResponse, AppError> {
+        self.deps.ensure_writable()?;
+        if params.prune && !caller.has_scope(Scope::Delete) {
+            return Err(AppError::forbidden("delete scope required to prune"));
+        }
+        if let Some(source) = &params.source
+            && (source.trim().is_empty() || source.chars().count() > MAX_SOURCE_CHARS)
+        {
+            return Err(AppError::bad_request(format!(
+                "source must be 1 to {} characters",
+                MAX_SOURCE_CHARS
+            )));
+        }
+
+        let path = std::env::temp_dir().join(format!(
+            "odin-browser-sync-{}-{}",
+            caller.user_id,
+            OffsetDateTime::now_utc().unix_timestamp_nanos()
+        ));
+        let result = self.read_upload(&path, body).await;
+        let _ = tokio::fs::remove_file(&path).await;
+        let (format, entries) = result?;
+        let source = params
+            .source
+            .map(|source| source.trim().to_string())
+            .unwrap_or_else(|| format.to_string());
+
+        let previous: Vec<(String, String)> = sqlx::query_as(
+            r#"
+            SELECT url, folder FROM browser_sync_entries
+            WHERE owner_id = ?1 AND namespace = ?2 AND source = ?3
+            "#,
+        )
+        .bind(caller.user_id)
+        .bind(&caller.namespace)
+        .bind(&source)
+        .fetch_all(&self.deps.db)
+        .await?;
+        let previous: HashSet<(String, String)> = previous.into_iter().collect();
+        let added: Vec<&(String, String)> = entries.difference(&previous).collect();
+        let removed: Vec<&(String, String)> = previous.difference(&entries).collect();
+
+        // Folders only matter for the diff; a URL is ingested or deleted once. Ingest
+        // comes first, so a failure leaves the new pairs unrecorded for the next sync.
+        let current: HashSet<&String> = entries.iter().map(|(url, _)| url).collect();
+        let known: HashSet<&String> = previous.iter().map(|(url, _)| url).collect();
+        let new_urls: Vec<String> = added
+            .iter()
+            .map(|(url, _)| url)
+            .filter(|url| !known.contains(url))
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .cloned()
+            .collect();
+        let mut accepted = 0;
+        for batch in new_urls.chunks(self.deps.settings().max_urls.max(1)) {
+            accepted += self
+                .ingest
+                .ingest_urls_waiting(caller.clone(), batch.to_vec(), &[])
+                .await?
+                .accepted;
+        }
+
+        let now = OffsetDateTime::now_utc()
+            .format(&Rfc3339)
+            .expect("failed to format timestamp");
+        let mut tx = self.deps.db.begin().await?;
+        for (url, folder) in &removed {
+            sqlx::query(
+                r#"
+                DELETE FROM browser_sync_entries
+                WHERE owner_id = ?1 AND namespace = ?2 AND source = ?3 AND url = ?4 AND folder = ?5
+                "#,
+            )
+            .bind(caller.user_id)
+            .bind(&caller.namespace)
+            .bind(&source)
+            .bind(url)
+            .bind(folder)
+            .execute(&mut *tx)
+            .await?;
+        }
+        for (url, folder) in &added {
+            sqlx::query(
+                r#"
+                INSERT INTO browser_sync_entries (owner_id, namespace, source, url, folder, synced_at)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                "#,
+            )
+            .bind(caller.user_id)
+            .bind(&caller.namespace)
+            .bind(&source)
+            .bind(url)
+            .bind(folder)
+            .bind(&now)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+
+        let mut deleted = 0;
+        if params.prune {
+            let gone: Vec<String> = removed
+                .iter()
+                .map(|(url, _)| url)
+                .filter(|url| !current.contains(url))
+                .collect::<HashSet<_>>()
+                .into_iter()
+                .cloned()
+                .collect();
+            for batch in gone.chunks(DELETE_BATCH) {
+                deleted += self
+                    .bookmarks
+                    .delete_many(
+                        caller.clone(),
+                        BulkDeleteRequest {
+                            ids: Vec::new(),
+                            urls: batch.to_vec(),
+                        },
+                    )
+                    .await?
+                    .deleted;
+            }
+        }
+
+        info!(
+            "browser sync finished: source={} found={} added={} removed={} accepted={} deleted={}",
+            source,
+            entries.len(),
+            added.len(),
+            removed.len(),
+            accepted,
+            deleted
+        );
+        Ok(BrowserSyncResponse {
+            source,
+            found: entries.len(),
+            added: added.len(),
+            removed: removed.len(),
+            accepted,
+            deleted,
+        })
+    }
+
+    /// Write the upload to `path`, then read its bookmarks as normalized URL and folder
+    /// pairs, telling the formats apart by the SQLite header.
+    async fn read_upload(
+        &self,
+        path: &Path,
+        body: Body,
+    ) -> Result<(&'static str, HashSet<(String, String)>), AppError> {
+        let mut file = tokio::fs::File::create(path)
+            .await
+            .context("create browser sync upload")?;
+        let mut stream = body.into_data_stream();
+        while let Some(chunk) = stream.next().await {
+            file.write_all(&chunk.map_err(AppError::upload)?)
+                .await
+                .context("write browser sync upload")?;
+        }
+        file.flush().await.context("flush browser sync upload")?;
+        drop(file);
+
+        let mut header = [0u8; SQLITE_HEADER.len()];
+        let is_sqlite = tokio::fs::File::open(path)
+            .await
+            .context("open browser sync upload")?
+            .read_exact(&mut header)
+            .await
+            .is_ok()
+            && header == SQLITE_HEADER;
+        let (format, raw) = if is_sqlite {
+            (
+                "firefox",
+                Self::firefox_bookmarks(path.to_path_buf()).await?,
+            )
+        } else {
+            let json = tokio::fs::read(path)
+                .await
+                .context("read browser sync upload")?;
+            ("chrome", Self::chrome_bookmarks(&json)?)
+        };
+        // Bookmarklets, `place:` queries, and browser pages have nothing to fetch.
+        let entries = raw
+            .into_iter()
+            .filter_map(|(url, folder)| Some((IngestService::normalize_url(&url)?, folder)))
+            .filter(|(url, _)| url.starts_with("http://") || url.starts_with("https://"))
+            .collect();
+        Ok((format, entries))
+    }
+
+    async fn firefox_bookmarks(path: PathBuf) -> Result<Vec<(String, String)>, AppError> {
+        let invalid = |err: sqlx::Error| {
+            AppError::bad_request(format!("not a Firefox places.sqlite: {}", err))
+        };
+        // Immutable, so SQLite neither locks the copy nor looks for its WAL file.
+        let mut conn = SqliteConnectOptions::new()
+            .filename(&path)
+            .read_only(true)
+            .immutable(true)
+            .connect()
+            .await
+            .map_err(invalid)?;
+        let rows = sqlx::query_as(FIREFOX_BOOKMARKS)
+            .fetch_all(&mut conn)
+            .await
+            .map_err(invalid)?;
+        let _ = conn.close().await;
+        Ok(rows)
+    }
+
+    fn chrome_bookmarks(json: &[u8]) -> Result<Vec<(String, String)>, AppError> {
+        let file: ChromeBookmarks = serde_json::from_slice(json).map_err(|err| {
+            AppError::bad_request(format!(
+                "expected Chrome's Bookmarks JSON or Firefox's places.sqlite: {}",
+                err
+            ))
+        })?;
+        let mut entries = Vec::new();
+        // `roots` also holds bookkeeping values such as `sync_transaction_version`.
+        for root in file.roots.into_values() {
+            if let Ok(node) = serde_json::from_value::<ChromeNode>(root) {
+                Self::walk_chrome(&node, &node.name, &mut entries);
+            }
+        }
+        Ok(entries)
+    }
+
+    fn walk_chrome(node: &ChromeNode, folder: &str, entries: &mut Vec<(String, String)>) {
+        for child in &node.children {
+            match (child.kind.as_deref(), &child.url) {
+                (Some("url"), Some(url)) => entries.push((url.clone(), folder.to_string())),
+                (Some("folder"), _) => {
+                    Self::walk_chrome(child, &format!("{}/{}", folder, child.name), entries)
+                }
+                _ => {}
+            }
+        }
+    }
+}
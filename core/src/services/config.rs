@@ -0,0 +1,114 @@
+use std::sync::Arc;
+
+use tokio::sync::watch;
+use tracing::{info, warn};
+
+use crate::config::Config;
+use crate::rate_limit::RateLimits;
+use crate::types::Dependencies;
+
+/// The live config: `[log]`, `[rate_limit]`, `[maintenance]`, `cors.allowed_origins`, and
+/// `auth.privileged_cidrs` follow reloads, everything else keeps its startup value until
+/// a restart.
+#[derive(Clone)]
+pub struct ConfigService {
+    deps: Arc<Dependencies>,
+    rate_limits: RateLimits,
+}
+
+impl ConfigService {
+    pub fn new(deps: Arc<Dependencies>, rate_limits: RateLimits) -> Self {
+        Self { deps, rate_limits }
+    }
+
+    /// The config as last loaded; keep the guard short, it blocks reloads.
+    pub fn current(&self) -> watch::Ref<'_, Config> {
+        self.deps.live_config.borrow()
+    }
+
+    /// Apply a freshly loaded config. Rate limiters are rebuilt when `[rate_limit]`
+    /// changed and scheduled jobs pick up new intervals; changes that need a restart are
+    /// logged and otherwise ignored. The log level is applied by the server.
+    pub fn reload(&self, config: Config) {
+        let startup = &self.deps.config;
+        let restart_only: Vec<&str> = [
+            ("server", config.server != startup.server),
+            ("tls", config.tls != startup.tls),
+            (
+                "cors.allowed_methods",
+                config.cors.allowed_methods != startup.cors.allowed_methods,
+            ),
+            (
+                "cors.allowed_headers",
+                config.cors.allowed_headers != startup.cors.allowed_headers,
+            ),
+            (
+                "auth.rotation_grace_secs",
+                config.auth.rotation_grace_secs != startup.auth.rotation_grace_secs,
+            ),
+            (
+                "auth.max_rotation_grace_secs",
+                config.auth.max_rotation_grace_secs != startup.auth.max_rotation_grace_secs,
+            ),
+            (
+                "auth.session_ttl_secs",
+                config.auth.session_ttl_secs != startup.auth.session_ttl_secs,
+            ),
+            (
+                "auth.session_cookie_secure",
+                config.auth.session_cookie_secure != startup.auth.session_cookie_secure,
+            ),
+            ("oidc", config.oidc != startup.oidc),
+            ("readwise", config.readwise != startup.readwise),
+            ("feed_reader", config.feed_reader != startup.feed_reader),
+            ("feeds", config.feeds != startup.feeds),
+            ("activitypub", config.activitypub != startup.activitypub),
+            ("wayback", config.wayback != startup.wayback),
+            ("storage", config.storage != startup.storage),
+            ("quota", config.quota != startup.quota),
+            ("ingest", config.ingest != startup.ingest),
+            ("slow_log", config.slow_log != startup.slow_log),
+            ("telemetry", config.telemetry != startup.telemetry),
+            ("grpc", config.grpc != startup.grpc),
+            ("telegram", config.telegram != startup.telegram),
+            ("webhooks", config.webhooks != startup.webhooks),
+            ("notifiers", config.notifiers != startup.notifiers),
+        ]
+        .into_iter()
+        .filter_map(|(section, changed)| changed.then_some(section))
+        .collect();
+        if !restart_only.is_empty() {
+            warn!(
+                "config changes to {} take effect after a restart",
+                restart_only.join(", ")
+            );
+        }
+
+        let previous = self.deps.live_config.send_replace(config);
+        let current = self.current();
+        let applied: Vec<&str> = [
+            ("log", current.log != previous.log),
+            ("rate_limit", current.rate_limit != previous.rate_limit),
+            ("maintenance", current.maintenance != previous.maintenance),
+            (
+                "cors.allowed_origins",
+                current.cors.allowed_origins != previous.cors.allowed_origins,
+            ),
+            (
+                "auth.privileged_cidrs",
+                current.auth.privileged_cidrs != previous.auth.privileged_cidrs,
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(section, changed)| changed.then_some(section))
+        .collect();
+        if current.rate_limit != previous.rate_limit {
+            self.rate_limits.reconfigure(&current.rate_limit);
+        }
+        if applied.is_empty() {
+            info!("config reloaded: no live settings changed");
+        } else {
+            info!("config reloaded: applied {}", applied.join(", "));
+        }
+    }
+}
@@ -0,0 +1,134 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use time::OffsetDateTime;
+use tracing::warn;
+use url::Url;
+
+use crate::errors::AppError;
+use crate::types::{
+    Dependencies, DomainStat, DomainStatsParams, DomainStatsResponse, DomainStatsSort,
+};
+
+/// Hours of buckets kept, and the longest window a report may cover.
+const RETENTION_HOURS: i64 = 24 * 7;
+const DEFAULT_HOURS: i64 = 24;
+const DEFAULT_LIMIT: i64 = 50;
+const MAX_LIMIT: i64 = 1000;
+const PRUNE_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// Fetch counts, failures, bytes, and latency per domain in hourly buckets of
+/// `domain_stats`, so operators can find the hosts that slow a crawl down.
+#[derive(Clone)]
+pub struct DomainStatsService {
+    deps: Arc<Dependencies>,
+}
+
+impl DomainStatsService {
+    pub fn new(deps: Arc<Dependencies>) -> Self {
+        Self { deps }
+    }
+
+    /// Add one fetch of `url` to its domain's current bucket. Bookkeeping only, so a
+    /// failed write is logged rather than failing the ingest.
+    pub(crate) async fn record(&self, url: &str, elapsed: Duration, bytes: usize, failed: bool) {
+        self.deps.metrics.observe_fetch(elapsed, failed);
+        let Some(domain) = Url::parse(url)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_ascii_lowercase))
+        else {
+            return;
+        };
+        let latency_ms = elapsed.as_millis() as i64;
+        let recorded = sqlx::query(
+            r#"
+            INSERT INTO domain_stats (domain, hour, fetches, failures, bytes, latency_ms, max_latency_ms)
+            VALUES (?1, ?2, 1, ?3, ?4, ?5, ?5)
+            ON CONFLICT(domain, hour) DO UPDATE SET
+                fetches = fetches + 1,
+                failures = failures + excluded.failures,
+                bytes = bytes + excluded.bytes,
+                latency_ms = latency_ms + excluded.latency_ms,
+                max_latency_ms = MAX(max_latency_ms, excluded.max_latency_ms)
+            "#,
+        )
+        .bind(&domain)
+        .bind(Self::current_hour())
+        .bind(failed as i64)
+        .bind(bytes as i64)
+        .bind(latency_ms)
+        .execute(&self.deps.db)
+        .await;
+        if let Err(err) = recorded {
+            warn!("domain stats not recorded: domain={} error={}", domain, err);
+        }
+    }
+
+    /// Totals per domain over the last `hours`, worst first by the chosen measure.
+    pub async fn stats(&self, params: DomainStatsParams) -> Result<DomainStatsResponse, AppError> {
+        let hours = params.hours.unwrap_or(DEFAULT_HOURS);
+        if !(1..=RETENTION_HOURS).contains(&hours) {
+            return Err(AppError::bad_request(format!(
+                "hours must be between 1 and {}",
+                RETENTION_HOURS
+            )));
+        }
+        let limit = params.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+        let order = match params.sort.unwrap_or_default() {
+            DomainStatsSort::Time => "total_latency_ms DESC",
+            DomainStatsSort::Latency => "avg_latency_ms DESC",
+            DomainStatsSort::Failures => "failure_rate DESC, failures DESC",
+            DomainStatsSort::Fetches => "fetches DESC",
+            DomainStatsSort::Bytes => "bytes DESC",
+        };
+        let results: Vec<DomainStat> = sqlx::query_as(&format!(
+            r#"
+            SELECT domain,
+                   SUM(fetches) AS fetches,
+                   SUM(failures) AS failures,
+                   CAST(SUM(failures) AS REAL) / SUM(fetches) AS failure_rate,
+                   SUM(bytes) AS bytes,
+                   SUM(latency_ms) AS total_latency_ms,
+                   SUM(latency_ms) / SUM(fetches) AS avg_latency_ms,
+                   MAX(max_latency_ms) AS max_latency_ms
+            FROM domain_stats
+            WHERE hour > ?1
+            GROUP BY domain
+            ORDER BY {}, domain
+            LIMIT ?2
+            "#,
+            order
+        ))
+        .bind(Self::current_hour() - hours)
+        .bind(limit)
+        .fetch_all(&self.deps.db)
+        .await?;
+        Ok(DomainStatsResponse { hours, results })
+    }
+
+    /// Drop buckets older than the retention window, hourly.
+    pub fn start(&self) {
+        let service = self.clone();
+        tokio::spawn(async move {
+            loop {
+                if service.deps.ensure_writable().is_ok()
+                    && let Err(err) = sqlx::query("DELETE FROM domain_stats WHERE hour <= ?1")
+                        .bind(Self::current_hour() - RETENTION_HOURS)
+                        .execute(&service.deps.db)
+                        .await
+                {
+                    warn!("domain stats pruning failed: {}", err);
+                }
+                tokio::select! {
+                    _ = service.deps.shutdown.cancelled() => return,
+                    _ = tokio::time::sleep(PRUNE_INTERVAL) => {}
+                }
+            }
+        });
+    }
+
+    /// Hours since the Unix epoch; buckets are keyed by them.
+    fn current_hour() -> i64 {
+        OffsetDateTime::now_utc().unix_timestamp() / 3600
+    }
+}
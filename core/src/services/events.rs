@@ -0,0 +1,41 @@
+use std::sync::Arc;
+
+use futures_util::{Stream, StreamExt};
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
+
+use crate::types::{BookmarkEvent, Caller, Dependencies};
+
+#[derive(Clone)]
+pub struct EventService {
+    deps: Arc<Dependencies>,
+}
+
+impl EventService {
+    pub fn new(deps: Arc<Dependencies>) -> Self {
+        Self { deps }
+    }
+
+    /// Follow the caller's bookmark events in its namespace until shutdown. `Err(n)` reports `n`
+    /// events missed by a slow reader, after which clients should refetch.
+    pub fn subscribe(
+        &self,
+        caller: Caller,
+    ) -> impl Stream<Item = Result<BookmarkEvent, u64>> + use<> {
+        BroadcastStream::new(self.deps.events.subscribe())
+            .filter_map(move |item| {
+                let item = match item {
+                    Ok(event)
+                        if event.owner_id == caller.user_id
+                            && event.namespace == caller.namespace =>
+                    {
+                        Some(Ok(event))
+                    }
+                    Ok(_) => None,
+                    Err(BroadcastStreamRecvError::Lagged(skipped)) => Some(Err(skipped)),
+                };
+                std::future::ready(item)
+            })
+            .take_until(self.deps.shutdown.clone().cancelled_owned())
+    }
+}
@@ -0,0 +1,530 @@
+use std::io::{Seek, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::Context;
+use serde::Serialize;
+use sqlx::FromRow;
+use time::OffsetDateTime;
+use time::format_description;
+use time::format_description::well_known::Rfc3339;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+use zip::ZipWriter;
+use zip::write::SimpleFileOptions;
+
+use crate::errors::AppError;
+use crate::types::{Caller, Dependencies, ExportFormat};
+use crate::{content, tags};
+
+/// Bookmarks read from the database per blocking write into the archive.
+const PAGE_SIZE: i64 = 200;
+/// Longest title kept in a note's file name, in characters.
+const MAX_NAME_CHARS: usize = 80;
+const CSV_HEADER: &str = "id,url,original_url,title,excerpt,tags,status,http_status,content_type,\
+    error,created_at,updated_at,fetched_at,indexed_at\r\n";
+const NETSCAPE_HEADER: &str = "<!DOCTYPE NETSCAPE-Bookmark-file-1>
+<META HTTP-EQUIV=\"Content-Type\" CONTENT=\"text/html; charset=UTF-8\">
+<TITLE>Bookmarks</TITLE>
+<H1>Bookmarks</H1>
+<DL><p>
+";
+
+pub struct ExportArchive {
+    pub file_name: String,
+    pub file: tokio::fs::File,
+}
+
+#[derive(FromRow)]
+struct Row {
+    id: i64,
+    url: String,
+    title: Option<String>,
+    excerpt: Option<String>,
+    status: String,
+    created_at: String,
+    updated_at: String,
+    fetched_at: Option<String>,
+    body: Option<Vec<u8>>,
+    encoding: Option<String>,
+}
+
+#[derive(FromRow)]
+struct RecordRow {
+    id: i64,
+    url: String,
+    original_url: String,
+    title: Option<String>,
+    excerpt: Option<String>,
+    status: String,
+    http_status: Option<i64>,
+    content_type: Option<String>,
+    error: Option<String>,
+    created_at: String,
+    updated_at: String,
+    fetched_at: Option<String>,
+    indexed_at: Option<String>,
+    tags: Option<String>,
+    body: Option<Vec<u8>>,
+    encoding: Option<String>,
+}
+
+/// One bookmark of the JSON, NDJSON, CSV, and Netscape exports.
+#[derive(Serialize)]
+struct Record {
+    id: i64,
+    url: String,
+    original_url: String,
+    title: Option<String>,
+    excerpt: Option<String>,
+    status: String,
+    http_status: Option<i64>,
+    content_type: Option<String>,
+    error: Option<String>,
+    created_at: String,
+    updated_at: String,
+    fetched_at: Option<String>,
+    indexed_at: Option<String>,
+    /// Sorted by name.
+    tags: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content: Option<String>,
+}
+
+pub struct ExportStream {
+    pub file_name: String,
+    pub content_type: &'static str,
+    pub lines: mpsc::Receiver<Result<Vec<u8>, AppError>>,
+}
+
+/// Writes the caller's bookmarks as a zip of Markdown notes for Obsidian, Logseq, and
+/// other tools that read a folder of files: YAML frontmatter, then the extracted text.
+/// Also streams them as JSON, NDJSON, CSV, or a Netscape bookmark file.
+#[derive(Clone)]
+pub struct ExportService {
+    deps: Arc<Dependencies>,
+}
+
+impl ExportService {
+    pub fn new(deps: Arc<Dependencies>) -> Self {
+        Self { deps }
+    }
+
+    /// Build the archive in a temporary file, returned open and already unlinked.
+    pub async fn markdown(&self, caller: Caller) -> Result<ExportArchive, AppError> {
+        let stamp = Self::timestamp()?;
+        let path = std::env::temp_dir().join(format!(
+            "odin-export-{}-{}.zip",
+            caller.user_id,
+            OffsetDateTime::now_utc().unix_timestamp_nanos()
+        ));
+        let result = self.write_archive(&caller, &path).await;
+        let removed = tokio::fs::remove_file(&path).await;
+        let (file, notes) = result?;
+        removed.context("remove export archive")?;
+        info!(
+            "markdown export ready: user_id={} notes={}",
+            caller.user_id, notes
+        );
+        Ok(ExportArchive {
+            file_name: format!("odin-markdown-{}.zip", stamp),
+            file,
+        })
+    }
+
+    /// Stream the caller's bookmarks in `format`, one page of rows at a time. The
+    /// channel is small, so a slow reader holds the producer back instead of the export
+    /// piling up in memory; an error ends the stream early, leaving it truncated.
+    pub async fn stream(
+        &self,
+        caller: Caller,
+        format: ExportFormat,
+        content: bool,
+    ) -> Result<ExportStream, AppError> {
+        if content && matches!(format, ExportFormat::Csv | ExportFormat::Netscape) {
+            return Err(AppError::bad_request(
+                "content is only exported as json or ndjson",
+            ));
+        }
+        let stamp = Self::timestamp()?;
+        let (extension, content_type) = match format {
+            ExportFormat::Json => ("json", "application/json"),
+            ExportFormat::Ndjson => ("ndjson", "application/x-ndjson"),
+            ExportFormat::Csv => ("csv", "text/csv; charset=utf-8"),
+            ExportFormat::Netscape => ("html", "text/html; charset=utf-8"),
+        };
+        let (tx, rx) = mpsc::channel(PAGE_SIZE as usize);
+        let service = self.clone();
+        self.deps.tasks.spawn(async move {
+            match service.write_lines(&caller, format, content, &tx).await {
+                Ok(lines) => info!(
+                    "{} export finished: user_id={} bookmarks={}",
+                    extension, caller.user_id, lines
+                ),
+                Err(err) => {
+                    warn!(
+                        "{} export failed: user_id={} error={}",
+                        extension,
+                        caller.user_id,
+                        err.message()
+                    );
+                    let _ = tx.send(Err(err)).await;
+                }
+            }
+        });
+        Ok(ExportStream {
+            file_name: format!("odin-export-{}.{}", stamp, extension),
+            content_type,
+            lines: rx,
+        })
+    }
+
+    /// Returns how many bookmarks were sent, stopping quietly once the reader hangs up.
+    async fn write_lines(
+        &self,
+        caller: &Caller,
+        format: ExportFormat,
+        content: bool,
+        tx: &mpsc::Sender<Result<Vec<u8>, AppError>>,
+    ) -> Result<usize, AppError> {
+        let header = match format {
+            ExportFormat::Json => b"[\n".to_vec(),
+            ExportFormat::Ndjson => Vec::new(),
+            ExportFormat::Csv => CSV_HEADER.as_bytes().to_vec(),
+            ExportFormat::Netscape => NETSCAPE_HEADER.as_bytes().to_vec(),
+        };
+        if !header.is_empty() && tx.send(Ok(header)).await.is_err() {
+            return Ok(0);
+        }
+        let mut cursor = 0;
+        let mut sent = 0;
+        loop {
+            // Without content the join still runs, but the bodies stay in the database.
+            let rows: Vec<RecordRow> = sqlx::query_as(
+                r#"
+                SELECT b.id, b.url, COALESCE(b.original_url, b.url) AS original_url, b.title,
+                       b.excerpt, b.status, b.http_status, b.content_type,
+                       b.error, b.created_at, b.updated_at, b.fetched_at, b.indexed_at,
+                       (SELECT group_concat(t.name, ',')
+                        FROM bookmark_tags bt JOIN tags t ON t.id = bt.tag_id
+                        WHERE bt.bookmark_id = b.id) AS tags,
+                       CASE WHEN ?5 THEN CAST(c.body AS BLOB) END AS body, c.encoding
+                FROM bookmarks b
+                LEFT JOIN bookmark_contents c ON c.bookmark_id = b.id
+                WHERE b.owner_id = ?1 AND b.namespace = ?2 AND b.id > ?3
+                ORDER BY b.id
+                LIMIT ?4
+                "#,
+            )
+            .bind(caller.user_id)
+            .bind(&caller.namespace)
+            .bind(cursor)
+            .bind(PAGE_SIZE)
+            .bind(content)
+            .fetch_all(&self.deps.db)
+            .await?;
+            let Some(last) = rows.last() else {
+                let footer = match format {
+                    ExportFormat::Json => b"\n]\n".to_vec(),
+                    ExportFormat::Netscape => b"</DL><p>\n".to_vec(),
+                    ExportFormat::Ndjson | ExportFormat::Csv => Vec::new(),
+                };
+                if !footer.is_empty() {
+                    let _ = tx.send(Ok(footer)).await;
+                }
+                return Ok(sent);
+            };
+            cursor = last.id;
+            let first = sent == 0;
+            let lines = tokio::task::spawn_blocking(move || -> anyhow::Result<Vec<Vec<u8>>> {
+                rows.into_iter()
+                    .enumerate()
+                    .map(|(index, row)| {
+                        let record = Self::record(row)?;
+                        match format {
+                            ExportFormat::Json => {
+                                // Every object but the first follows a comma.
+                                let mut line = if first && index == 0 {
+                                    Vec::new()
+                                } else {
+                                    b",\n".to_vec()
+                                };
+                                serde_json::to_writer(&mut line, &record)
+                                    .context("encode export record")?;
+                                Ok(line)
+                            }
+                            ExportFormat::Ndjson => {
+                                let mut line =
+                                    serde_json::to_vec(&record).context("encode export record")?;
+                                line.push(b'\n');
+                                Ok(line)
+                            }
+                            ExportFormat::Csv => Ok(Self::csv_row(&record).into_bytes()),
+                            ExportFormat::Netscape => {
+                                Ok(Self::netscape_entry(&record).into_bytes())
+                            }
+                        }
+                    })
+                    .collect()
+            })
+            .await
+            .context("export writer panicked")??;
+            for line in lines {
+                if tx.send(Ok(line)).await.is_err() {
+                    return Ok(sent);
+                }
+                sent += 1;
+            }
+        }
+    }
+
+    fn record(row: RecordRow) -> anyhow::Result<Record> {
+        let content = match (row.body, row.encoding) {
+            (Some(body), Some(encoding)) => Some(content::decode(&encoding, body)?),
+            _ => None,
+        };
+        let mut tags: Vec<String> = row
+            .tags
+            .as_deref()
+            .unwrap_or_default()
+            .split(',')
+            .filter(|tag| !tag.is_empty())
+            .map(str::to_string)
+            .collect();
+        tags.sort();
+        let record = Record {
+            id: row.id,
+            url: row.url,
+            original_url: row.original_url,
+            title: row.title,
+            excerpt: row.excerpt,
+            status: row.status,
+            http_status: row.http_status,
+            content_type: row.content_type,
+            error: row.error,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+            fetched_at: row.fetched_at,
+            indexed_at: row.indexed_at,
+            tags,
+            content,
+        };
+        Ok(record)
+    }
+
+    fn csv_row(record: &Record) -> String {
+        let fields = [
+            record.id.to_string(),
+            record.url.clone(),
+            record.original_url.clone(),
+            record.title.clone().unwrap_or_default(),
+            record.excerpt.clone().unwrap_or_default(),
+            record.tags.join(" "),
+            record.status.clone(),
+            record
+                .http_status
+                .map(|status| status.to_string())
+                .unwrap_or_default(),
+            record.content_type.clone().unwrap_or_default(),
+            record.error.clone().unwrap_or_default(),
+            record.created_at.clone(),
+            record.updated_at.clone(),
+            record.fetched_at.clone().unwrap_or_default(),
+            record.indexed_at.clone().unwrap_or_default(),
+        ];
+        let mut row = fields
+            .iter()
+            .map(|field| csv_field(field))
+            .collect::<Vec<_>>()
+            .join(",");
+        row.push_str("\r\n");
+        row
+    }
+
+    /// One `<DT><A>` line with add and modification dates as unix seconds, the tags, and
+    /// the excerpt as its description.
+    fn netscape_entry(record: &Record) -> String {
+        let unix = |at: &str| {
+            OffsetDateTime::parse(at, &Rfc3339)
+                .map(|at| at.unix_timestamp())
+                .unwrap_or_default()
+        };
+        let title = record
+            .title
+            .as_deref()
+            .filter(|title| !title.trim().is_empty())
+            .unwrap_or(&record.url);
+        let mut entry = format!(
+            "<DT><A HREF=\"{}\" ADD_DATE=\"{}\" LAST_MODIFIED=\"{}\"",
+            escape_html(&record.url),
+            unix(&record.created_at),
+            unix(&record.updated_at)
+        );
+        if !record.tags.is_empty() {
+            entry.push_str(&format!(
+                " TAGS=\"{}\"",
+                escape_html(&record.tags.join(","))
+            ));
+        }
+        entry.push_str(&format!(">{}</A>\n", escape_html(title)));
+        if let Some(excerpt) = record.excerpt.as_deref().filter(|text| !text.is_empty()) {
+            entry.push_str(&format!("<DD>{}\n", escape_html(excerpt)));
+        }
+        entry
+    }
+
+    async fn write_archive(
+        &self,
+        caller: &Caller,
+        path: &Path,
+    ) -> Result<(tokio::fs::File, usize), AppError> {
+        let file = std::fs::File::options()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(path)
+            .context("create export archive")?;
+        let mut writer = ZipWriter::new(file);
+        let mut cursor = 0;
+        let mut notes = 0;
+        loop {
+            let rows: Vec<Row> = sqlx::query_as(
+                r#"
+                SELECT b.id, b.url, b.title, b.excerpt, b.status, b.created_at, b.updated_at,
+                       b.fetched_at, CAST(c.body AS BLOB) AS body, c.encoding
+                FROM bookmarks b
+                LEFT JOIN bookmark_contents c ON c.bookmark_id = b.id
+                WHERE b.owner_id = ?1 AND b.namespace = ?2 AND b.id > ?3
+                ORDER BY b.id
+                LIMIT ?4
+                "#,
+            )
+            .bind(caller.user_id)
+            .bind(&caller.namespace)
+            .bind(cursor)
+            .bind(PAGE_SIZE)
+            .fetch_all(&self.deps.db)
+            .await?;
+            let Some(last) = rows.last() else {
+                break;
+            };
+            cursor = last.id;
+            notes += rows.len();
+            let ids: Vec<i64> = rows.iter().map(|row| row.id).collect();
+            let mut tags = tags::of_bookmarks(&self.deps.db, &ids).await?;
+            writer = tokio::task::spawn_blocking(move || -> anyhow::Result<_> {
+                for row in rows {
+                    let name = Self::note_name(&row);
+                    let tags = tags.remove(&row.id).unwrap_or_default();
+                    let note = Self::note(row, &tags)?;
+                    writer
+                        .start_file(name, SimpleFileOptions::default())
+                        .context("write export archive")?;
+                    writer
+                        .write_all(note.as_bytes())
+                        .context("write export archive")?;
+                }
+                Ok(writer)
+            })
+            .await
+            .context("export writer panicked")??;
+        }
+        let file = tokio::task::spawn_blocking(move || -> anyhow::Result<_> {
+            let mut file = writer.finish().context("finish export archive")?;
+            file.rewind().context("rewind export archive")?;
+            Ok(file)
+        })
+        .await
+        .context("export writer panicked")??;
+        Ok((tokio::fs::File::from_std(file), notes))
+    }
+
+    /// The title (or URL) without characters file systems or wiki links reject, plus
+    /// the id so names stay unique.
+    fn note_name(row: &Row) -> String {
+        let source = row
+            .title
+            .as_deref()
+            .filter(|title| !title.trim().is_empty())
+            .unwrap_or(&row.url);
+        let cleaned: String = source
+            .chars()
+            .map(|ch| {
+                if ch.is_control() || "/\\:*?\"<>|#^[]".contains(ch) {
+                    ' '
+                } else {
+                    ch
+                }
+            })
+            .collect();
+        let title: String = cleaned
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .chars()
+            .take(MAX_NAME_CHARS)
+            .collect();
+        let title = title.trim_matches(|ch: char| ch == '.' || ch.is_whitespace());
+        let title = if title.is_empty() { "Untitled" } else { title };
+        format!("{} ({}).md", title, row.id)
+    }
+
+    /// Strings are written as JSON strings, which YAML reads as double-quoted scalars,
+    /// and `tags` as a JSON array, which it reads as a flow sequence.
+    fn note(row: Row, tags: &[String]) -> anyhow::Result<String> {
+        let text = match (row.body, row.encoding) {
+            (Some(body), Some(encoding)) => Some(content::decode(&encoding, body)?),
+            _ => None,
+        };
+        let quote = |value: &str| serde_json::Value::from(value).to_string();
+        let mut note = String::from("---\n");
+        if let Some(title) = row.title.as_deref() {
+            note.push_str(&format!("title: {}\n", quote(title)));
+        }
+        note.push_str(&format!("url: {}\n", quote(&row.url)));
+        note.push_str(&format!("tags: {}\n", serde_json::to_string(tags)?));
+        note.push_str(&format!("created: {}\n", row.created_at));
+        note.push_str(&format!("updated: {}\n", row.updated_at));
+        if let Some(fetched_at) = row.fetched_at.as_deref() {
+            note.push_str(&format!("fetched: {}\n", fetched_at));
+        }
+        note.push_str(&format!("status: {}\n", row.status));
+        note.push_str(&format!("odin_id: {}\n", row.id));
+        note.push_str("---\n\n");
+        note.push_str(&format!(
+            "# {}\n\n<{}>\n",
+            row.title.as_deref().unwrap_or(&row.url),
+            row.url
+        ));
+        if let Some(body) = text.or(row.excerpt).filter(|body| !body.trim().is_empty()) {
+            note.push('\n');
+            note.push_str(body.trim());
+            note.push('\n');
+        }
+        Ok(note)
+    }
+
+    fn timestamp() -> anyhow::Result<String> {
+        let format = format_description::parse("[year][month][day]T[hour][minute][second]Z")
+            .context("parse timestamp format")?;
+        OffsetDateTime::now_utc()
+            .format(&format)
+            .context("format timestamp")
+    }
+}
+
+/// A CSV field, quoted when it holds a delimiter, quote, or line break.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,465 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use reqwest::StatusCode;
+use serde::Deserialize;
+use sqlx::FromRow;
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+use tracing::{info, warn};
+
+use crate::errors::AppError;
+use crate::services::{AuthService, IngestService};
+use crate::types::{
+    Caller, Dependencies, FeedReaderConnectRequest, FeedReaderKind, FeedReaderStatus, Scope,
+};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+/// Starred entries read per poll, most recently changed first.
+const PAGE_SIZE: usize = 100;
+/// Tag given to bookmarks imported from a feed reader, already normalized.
+const IMPORT_TAG: &str = "from:rss";
+
+#[derive(FromRow)]
+struct Account {
+    user_id: i64,
+    key_id: i64,
+    namespace: String,
+    kind: String,
+    url: String,
+    username: Option<String>,
+    token: String,
+}
+
+#[derive(Deserialize)]
+struct MinifluxEntries {
+    entries: Vec<MinifluxEntry>,
+}
+
+#[derive(Deserialize)]
+struct MinifluxEntry {
+    id: i64,
+    url: String,
+}
+
+#[derive(Deserialize)]
+struct GReaderStream {
+    #[serde(default)]
+    items: Vec<GReaderItem>,
+}
+
+#[derive(Deserialize)]
+struct GReaderItem {
+    id: String,
+    #[serde(default)]
+    canonical: Vec<GReaderLink>,
+    #[serde(default)]
+    alternate: Vec<GReaderLink>,
+}
+
+#[derive(Deserialize)]
+struct GReaderLink {
+    href: String,
+}
+
+/// Imports entries starred in a user's Miniflux (REST API) or FreshRSS (Google Reader
+/// API) account. Each poll reads the latest starred entries and ingests those not yet
+/// in `feed_reader_entries`, acting as the API key that connected, so revoking that key
+/// stops the import. New bookmarks are tagged [`IMPORT_TAG`] to mark where they came from.
+#[derive(Clone)]
+pub struct FeedReaderService {
+    deps: Arc<Dependencies>,
+    auth: AuthService,
+    ingest: IngestService,
+}
+
+impl FeedReaderService {
+    pub fn new(deps: Arc<Dependencies>, auth: AuthService, ingest: IngestService) -> Self {
+        Self { deps, auth, ingest }
+    }
+
+    pub async fn status(&self, caller: Caller) -> Result<FeedReaderStatus, AppError> {
+        let row: Option<(String, String, Option<String>, Option<String>)> = sqlx::query_as(
+            "SELECT kind, url, last_synced_at, last_error FROM feed_reader_sync WHERE user_id = ?1",
+        )
+        .bind(caller.user_id)
+        .fetch_optional(&self.deps.db)
+        .await?;
+        let Some((kind, url, last_synced_at, last_error)) = row else {
+            return Ok(FeedReaderStatus {
+                connected: false,
+                kind: None,
+                url: None,
+                entries_seen: 0,
+                last_synced_at: None,
+                last_error: None,
+            });
+        };
+        let entries_seen: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM feed_reader_entries WHERE user_id = ?1")
+                .bind(caller.user_id)
+                .fetch_one(&self.deps.db)
+                .await?;
+        Ok(FeedReaderStatus {
+            connected: true,
+            kind: Self::kind(&kind),
+            url: Some(url),
+            entries_seen,
+            last_synced_at,
+            last_error,
+        })
+    }
+
+    /// Check the credentials by reading the starred entries, then store them; starred
+    /// entries are imported on the next poll unless `from_now` marks them seen.
+    /// Reconnecting replaces the account and forgets what was seen.
+    pub async fn connect(
+        &self,
+        caller: Caller,
+        payload: FeedReaderConnectRequest,
+    ) -> Result<FeedReaderStatus, AppError> {
+        self.deps.ensure_writable()?;
+        if !matches!(payload.url.scheme(), "http" | "https") {
+            return Err(AppError::bad_request("url must be http or https"));
+        }
+        let token = payload.token.trim();
+        if token.is_empty() {
+            return Err(AppError::bad_request("token must not be empty"));
+        }
+        let username = payload
+            .username
+            .as_deref()
+            .map(str::trim)
+            .filter(|username| !username.is_empty());
+        if payload.kind == FeedReaderKind::FreshRss && username.is_none() {
+            return Err(AppError::bad_request("FreshRSS needs a username"));
+        }
+        let account = Account {
+            user_id: caller.user_id,
+            key_id: caller.key_id,
+            namespace: caller.namespace.clone(),
+            kind: payload.kind.as_str().to_string(),
+            url: payload.url.as_str().trim_end_matches('/').to_string(),
+            username: username.map(str::to_string),
+            token: token.to_string(),
+        };
+        let starred = self.starred(&account).await.map_err(|err| match err {
+            FetchError::Rejected => AppError::bad_request(format!(
+                "{} rejected the credentials",
+                payload.kind.as_str()
+            )),
+            FetchError::Failed(message) => AppError::unavailable(format!(
+                "{} unreachable: {}",
+                payload.kind.as_str(),
+                message
+            )),
+        })?;
+
+        let now = Self::now();
+        let mut tx = self.deps.db.begin().await?;
+        sqlx::query(
+            r#"
+            INSERT INTO feed_reader_sync
+                (user_id, key_id, namespace, kind, url, username, token, created_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+            ON CONFLICT(user_id) DO UPDATE SET
+                key_id = excluded.key_id,
+                namespace = excluded.namespace,
+                kind = excluded.kind,
+                url = excluded.url,
+                username = excluded.username,
+                token = excluded.token,
+                last_synced_at = NULL,
+                last_error = NULL
+            "#,
+        )
+        .bind(account.user_id)
+        .bind(account.key_id)
+        .bind(&account.namespace)
+        .bind(&account.kind)
+        .bind(&account.url)
+        .bind(&account.username)
+        .bind(&account.token)
+        .bind(&now)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query("DELETE FROM feed_reader_entries WHERE user_id = ?1")
+            .bind(caller.user_id)
+            .execute(&mut *tx)
+            .await?;
+        if payload.from_now {
+            for (entry_id, _) in &starred {
+                Self::mark_seen(&mut tx, caller.user_id, entry_id, &now).await?;
+            }
+        }
+        tx.commit().await?;
+        info!(
+            "feed reader connected: user_id={} kind={} from_now={}",
+            caller.user_id, account.kind, payload.from_now
+        );
+        self.status(caller).await
+    }
+
+    pub async fn disconnect(&self, caller: Caller) -> Result<(), AppError> {
+        self.deps.ensure_writable()?;
+        let mut tx = self.deps.db.begin().await?;
+        let result = sqlx::query("DELETE FROM feed_reader_sync WHERE user_id = ?1")
+            .bind(caller.user_id)
+            .execute(&mut *tx)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(AppError::not_found("feed reader is not connected"));
+        }
+        sqlx::query("DELETE FROM feed_reader_entries WHERE user_id = ?1")
+            .bind(caller.user_id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        info!("feed reader disconnected: user_id={}", caller.user_id);
+        Ok(())
+    }
+
+    /// Poll every `feed_reader.sync_interval_secs` until shutdown, unless that is 0.
+    pub fn start(&self) {
+        let interval = self.deps.config.feed_reader.sync_interval_secs;
+        if interval == 0 {
+            return;
+        }
+        let service = self.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = service.deps.shutdown.cancelled() => return,
+                    _ = tokio::time::sleep(Duration::from_secs(interval)) => {}
+                }
+                // Ingest is a write, so read-only mode pauses polling.
+                if service.deps.ensure_writable().is_err() {
+                    continue;
+                }
+                if let Err(err) = service.sync().await {
+                    warn!("feed reader sync failed: {:?}", err);
+                }
+            }
+        });
+    }
+
+    async fn sync(&self) -> Result<(), AppError> {
+        let accounts: Vec<Account> = sqlx::query_as(
+            r#"
+            SELECT user_id, key_id, namespace, kind, url, username, token
+            FROM feed_reader_sync
+            "#,
+        )
+        .fetch_all(&self.deps.db)
+        .await?;
+        for account in accounts {
+            let error = match self.sync_user(&account).await {
+                Ok(imported) => {
+                    if imported > 0 {
+                        info!(
+                            "feed reader imported: user_id={} entries={}",
+                            account.user_id, imported
+                        );
+                    }
+                    None
+                }
+                Err(error) => {
+                    warn!(
+                        "feed reader sync failed: user_id={} error={}",
+                        account.user_id, error
+                    );
+                    Some(error)
+                }
+            };
+            sqlx::query(
+                "UPDATE feed_reader_sync SET last_synced_at = ?2, last_error = ?3 WHERE user_id = ?1",
+            )
+            .bind(account.user_id)
+            .bind(Self::now())
+            .bind(error)
+            .execute(&self.deps.db)
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// Ingest the user's unseen starred entries; returns how many there were.
+    async fn sync_user(&self, account: &Account) -> Result<usize, String> {
+        let caller = self
+            .auth
+            .authorize_key(account.key_id, &account.namespace, Scope::Ingest)
+            .await
+            .map_err(|err| format!("{}; reconnect with a live key", err.message()))?;
+        let starred = self.starred(account).await.map_err(|err| match err {
+            FetchError::Rejected => "credentials rejected; reconnect".to_string(),
+            FetchError::Failed(message) => message,
+        })?;
+
+        let mut unseen = Vec::new();
+        for (entry_id, url) in starred {
+            let seen: Option<i64> = sqlx::query_scalar(
+                "SELECT 1 FROM feed_reader_entries WHERE user_id = ?1 AND entry_id = ?2",
+            )
+            .bind(account.user_id)
+            .bind(&entry_id)
+            .fetch_optional(&self.deps.db)
+            .await
+            .map_err(|err| err.to_string())?;
+            if seen.is_none() {
+                unseen.push((entry_id, url));
+            }
+        }
+        if unseen.is_empty() {
+            return Ok(0);
+        }
+
+        let urls: Vec<String> = unseen
+            .iter()
+            .filter_map(|(_, url)| IngestService::normalize_url(url))
+            .collect();
+        let tags = [IMPORT_TAG.to_string()];
+        for batch in urls.chunks(self.deps.settings().max_urls.max(1)) {
+            self.ingest
+                .ingest_urls_waiting(caller.clone(), batch.to_vec(), &tags)
+                .await
+                .map_err(|err| err.message().to_string())?;
+        }
+        let now = Self::now();
+        let mut tx = self.deps.db.begin().await.map_err(|err| err.to_string())?;
+        for (entry_id, _) in &unseen {
+            Self::mark_seen(&mut tx, account.user_id, entry_id, &now)
+                .await
+                .map_err(|err| err.to_string())?;
+        }
+        tx.commit().await.map_err(|err| err.to_string())?;
+        Ok(unseen.len())
+    }
+
+    /// The latest starred entries as (entry id, URL) pairs.
+    async fn starred(&self, account: &Account) -> Result<Vec<(String, String)>, FetchError> {
+        match Self::kind(&account.kind) {
+            Some(FeedReaderKind::Miniflux) => {
+                let response = self
+                    .deps
+                    .http_client
+                    .get(format!(
+                        "{}/v1/entries?starred=true&order=changed_at&direction=desc&limit={}",
+                        account.url, PAGE_SIZE
+                    ))
+                    .header("x-auth-token", &account.token)
+                    .timeout(REQUEST_TIMEOUT)
+                    .send()
+                    .await?;
+                let entries: MinifluxEntries = Self::checked(response)?.json().await?;
+                Ok(entries
+                    .entries
+                    .into_iter()
+                    .map(|entry| (entry.id.to_string(), entry.url))
+                    .collect())
+            }
+            Some(FeedReaderKind::FreshRss) => {
+                let auth = self.greader_login(account).await?;
+                let response = self
+                    .deps
+                    .http_client
+                    .get(format!(
+                        "{}/api/greader.php/reader/api/0/stream/contents/user/-/state/com.google/starred?n={}&output=json",
+                        account.url, PAGE_SIZE
+                    ))
+                    .header("authorization", format!("GoogleLogin auth={}", auth))
+                    .timeout(REQUEST_TIMEOUT)
+                    .send()
+                    .await?;
+                let stream: GReaderStream = Self::checked(response)?.json().await?;
+                Ok(stream
+                    .items
+                    .into_iter()
+                    .filter_map(|item| {
+                        let link = item.canonical.into_iter().chain(item.alternate).next()?;
+                        Some((item.id, link.href))
+                    })
+                    .collect())
+            }
+            None => Err(FetchError::Failed(format!(
+                "unknown feed reader {}",
+                account.kind
+            ))),
+        }
+    }
+
+    /// Trade the username and API password for a Google Reader API auth token.
+    async fn greader_login(&self, account: &Account) -> Result<String, FetchError> {
+        let body = url::form_urlencoded::Serializer::new(String::new())
+            .append_pair("Email", account.username.as_deref().unwrap_or_default())
+            .append_pair("Passwd", &account.token)
+            .finish();
+        let response = self
+            .deps
+            .http_client
+            .post(format!(
+                "{}/api/greader.php/accounts/ClientLogin",
+                account.url
+            ))
+            .header("content-type", "application/x-www-form-urlencoded")
+            .body(body)
+            .timeout(REQUEST_TIMEOUT)
+            .send()
+            .await?;
+        let text = Self::checked(response)?.text().await?;
+        text.lines()
+            .find_map(|line| line.strip_prefix("Auth="))
+            .map(|auth| auth.trim().to_string())
+            .ok_or_else(|| FetchError::Failed("login returned no Auth token".to_string()))
+    }
+
+    fn checked(response: reqwest::Response) -> Result<reqwest::Response, FetchError> {
+        match response.status() {
+            status if status.is_success() => Ok(response),
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Err(FetchError::Rejected),
+            status => Err(FetchError::Failed(format!("http status {}", status))),
+        }
+    }
+
+    async fn mark_seen(
+        tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+        user_id: i64,
+        entry_id: &str,
+        now: &str,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "INSERT OR IGNORE INTO feed_reader_entries (user_id, entry_id, seen_at) VALUES (?1, ?2, ?3)",
+        )
+        .bind(user_id)
+        .bind(entry_id)
+        .bind(now)
+        .execute(&mut **tx)
+        .await?;
+        Ok(())
+    }
+
+    fn kind(kind: &str) -> Option<FeedReaderKind> {
+        match kind {
+            "miniflux" => Some(FeedReaderKind::Miniflux),
+            "freshrss" => Some(FeedReaderKind::FreshRss),
+            _ => None,
+        }
+    }
+
+    fn now() -> String {
+        OffsetDateTime::now_utc()
+            .format(&Rfc3339)
+            .expect("failed to format timestamp")
+    }
+}
+
+enum FetchError {
+    /// The reader refused the credentials.
+    Rejected,
+    Failed(String),
+}
+
+impl From<reqwest::Error> for FetchError {
+    fn from(err: reqwest::Error) -> Self {
+        FetchError::Failed(err.without_url().to_string())
+    }
+}
@@ -0,0 +1,431 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures_util::StreamExt;
+use reqwest::header::ACCEPT;
+use sqlx::FromRow;
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+use tracing::{info, warn};
+use url::Url;
+
+use crate::errors::AppError;
+use crate::services::{AuthService, IngestService};
+use crate::types::{
+    Caller, Dependencies, FeedCreateRequest, FeedItem, FeedsResponse, OpmlFeedFailure, Scope,
+};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+/// Feeds of one OPML import fetched at a time to check them.
+const IMPORT_CHECKS: usize = 8;
+const FEED_ACCEPT: &str = "application/rss+xml, application/atom+xml, application/feed+json, \
+    application/xml;q=0.9, text/xml;q=0.9, */*;q=0.8";
+
+#[derive(FromRow)]
+struct Feed {
+    id: i64,
+    key_id: i64,
+    namespace: String,
+    url: String,
+}
+
+/// A parsed feed: its title and each entry's id and link.
+struct Entries {
+    title: Option<String>,
+    entries: Vec<(String, String)>,
+}
+
+/// How an OPML import's feed outlines fared.
+#[derive(Default)]
+pub(crate) struct FeedImport {
+    pub(crate) subscribed: usize,
+    pub(crate) already_subscribed: usize,
+    pub(crate) failed: Vec<OpmlFeedFailure>,
+}
+
+/// RSS and Atom subscriptions. Each poll fetches every feed and ingests the entries
+/// whose id is not yet in `feed_entries`, acting as the API key that subscribed, so
+/// revoking that key stops the feed.
+#[derive(Clone)]
+pub struct FeedService {
+    deps: Arc<Dependencies>,
+    auth: AuthService,
+    ingest: IngestService,
+}
+
+impl FeedService {
+    pub fn new(deps: Arc<Dependencies>, auth: AuthService, ingest: IngestService) -> Self {
+        Self { deps, auth, ingest }
+    }
+
+    pub async fn list(&self, caller: Caller) -> Result<FeedsResponse, AppError> {
+        let results = sqlx::query_as(
+            r#"
+            SELECT f.id, f.url, f.title, f.created_at, f.last_polled_at, f.last_error,
+                (SELECT COUNT(*) FROM feed_entries e WHERE e.feed_id = f.id) AS entries_seen
+            FROM feeds f
+            WHERE f.owner_id = ?1 AND f.namespace = ?2
+            ORDER BY f.id
+            "#,
+        )
+        .bind(caller.user_id)
+        .bind(&caller.namespace)
+        .fetch_all(&self.deps.db)
+        .await?;
+        Ok(FeedsResponse { results })
+    }
+
+    /// Check that the URL serves a feed, then subscribe; its entries are ingested on the
+    /// next poll unless `from_now` marks them seen.
+    pub async fn add(
+        &self,
+        caller: Caller,
+        payload: FeedCreateRequest,
+    ) -> Result<FeedItem, AppError> {
+        self.deps.ensure_writable()?;
+        if !matches!(payload.url.scheme(), "http" | "https") {
+            return Err(AppError::bad_request("url must be http or https"));
+        }
+        self.check_room(&caller).await?;
+        let parsed = self.fetch(&payload.url).await.map_err(|message| {
+            AppError::bad_request(format!("not a readable feed: {}", message))
+        })?;
+        let id = self
+            .subscribe(&caller, &payload.url, None, &parsed, payload.from_now)
+            .await?
+            .ok_or_else(|| AppError::conflict("already subscribed to this feed"))?;
+        self.get(&caller, id).await
+    }
+
+    /// Subscribe to an OPML import's feeds, fetching a few at a time to check each is
+    /// readable; a feed that is not, or that goes over `feeds.max_feeds`, is reported
+    /// with why instead of failing the import. `titles` are the outlines' names, used
+    /// when a feed has no title of its own.
+    pub(crate) async fn import(
+        &self,
+        caller: &Caller,
+        feeds: Vec<(Url, Option<String>)>,
+        from_now: bool,
+    ) -> Result<FeedImport, AppError> {
+        let mut outcome = FeedImport::default();
+        let mut checks = futures_util::stream::iter(feeds)
+            .map(|(url, title)| async move {
+                let parsed = self.fetch(&url).await;
+                (url, title, parsed)
+            })
+            .buffered(IMPORT_CHECKS);
+        while let Some((url, title, parsed)) = checks.next().await {
+            let fail = |error: String| OpmlFeedFailure {
+                url: url.to_string(),
+                error,
+            };
+            let parsed = match parsed {
+                Ok(parsed) => parsed,
+                Err(message) => {
+                    outcome
+                        .failed
+                        .push(fail(format!("not a readable feed: {}", message)));
+                    continue;
+                }
+            };
+            if let Err(err) = self.check_room(caller).await {
+                outcome.failed.push(fail(err.message().to_string()));
+                continue;
+            }
+            match self
+                .subscribe(caller, &url, title.as_deref(), &parsed, from_now)
+                .await?
+            {
+                Some(_) => outcome.subscribed += 1,
+                None => outcome.already_subscribed += 1,
+            }
+        }
+        info!(
+            "feeds imported: user_id={} subscribed={} already_subscribed={} failed={}",
+            caller.user_id,
+            outcome.subscribed,
+            outcome.already_subscribed,
+            outcome.failed.len()
+        );
+        Ok(outcome)
+    }
+
+    /// Refuse another subscription once the namespace has `feeds.max_feeds`.
+    async fn check_room(&self, caller: &Caller) -> Result<(), AppError> {
+        let count: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM feeds WHERE owner_id = ?1 AND namespace = ?2")
+                .bind(caller.user_id)
+                .bind(&caller.namespace)
+                .fetch_one(&self.deps.db)
+                .await?;
+        if count as usize >= self.deps.config.feeds.max_feeds {
+            return Err(AppError::bad_request(format!(
+                "at most {} feeds per namespace",
+                self.deps.config.feeds.max_feeds
+            )));
+        }
+        Ok(())
+    }
+
+    /// Store a checked feed; `None` when the namespace already has it.
+    async fn subscribe(
+        &self,
+        caller: &Caller,
+        url: &Url,
+        title: Option<&str>,
+        parsed: &Entries,
+        from_now: bool,
+    ) -> Result<Option<i64>, AppError> {
+        let now = Self::now();
+        let mut tx = self.deps.db.begin().await?;
+        let id: Option<i64> = sqlx::query_scalar(
+            r#"
+            INSERT INTO feeds (owner_id, key_id, namespace, url, title, created_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+            ON CONFLICT(owner_id, namespace, url) DO NOTHING
+            RETURNING id
+            "#,
+        )
+        .bind(caller.user_id)
+        .bind(caller.key_id)
+        .bind(&caller.namespace)
+        .bind(url.as_str())
+        .bind(parsed.title.as_deref().or(title))
+        .bind(&now)
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some(id) = id else {
+            return Ok(None);
+        };
+        if from_now {
+            for (guid, _) in &parsed.entries {
+                Self::mark_seen(&mut tx, id, guid, &now).await?;
+            }
+        }
+        tx.commit().await?;
+        info!(
+            "feed added: user_id={} feed_id={} entries={} from_now={}",
+            caller.user_id,
+            id,
+            parsed.entries.len(),
+            from_now
+        );
+        Ok(Some(id))
+    }
+
+    /// Unsubscribe; bookmarks already ingested from the feed stay.
+    pub async fn remove(&self, caller: Caller, id: i64) -> Result<(), AppError> {
+        self.deps.ensure_writable()?;
+        let result =
+            sqlx::query("DELETE FROM feeds WHERE id = ?1 AND owner_id = ?2 AND namespace = ?3")
+                .bind(id)
+                .bind(caller.user_id)
+                .bind(&caller.namespace)
+                .execute(&self.deps.db)
+                .await?;
+        if result.rows_affected() == 0 {
+            return Err(AppError::not_found("feed not found"));
+        }
+        info!("feed removed: user_id={} feed_id={}", caller.user_id, id);
+        Ok(())
+    }
+
+    /// Poll every `feeds.poll_interval_secs` until shutdown, unless that is 0.
+    pub fn start(&self) {
+        let interval = self.deps.config.feeds.poll_interval_secs;
+        if interval == 0 {
+            return;
+        }
+        let service = self.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = service.deps.shutdown.cancelled() => return,
+                    _ = tokio::time::sleep(Duration::from_secs(interval)) => {}
+                }
+                // Ingest is a write, so read-only mode pauses polling.
+                if service.deps.ensure_writable().is_err() {
+                    continue;
+                }
+                if let Err(err) = service.poll().await {
+                    warn!("feed poll failed: {:?}", err);
+                }
+            }
+        });
+    }
+
+    async fn get(&self, caller: &Caller, id: i64) -> Result<FeedItem, AppError> {
+        sqlx::query_as(
+            r#"
+            SELECT f.id, f.url, f.title, f.created_at, f.last_polled_at, f.last_error,
+                (SELECT COUNT(*) FROM feed_entries e WHERE e.feed_id = f.id) AS entries_seen
+            FROM feeds f
+            WHERE f.id = ?1 AND f.owner_id = ?2 AND f.namespace = ?3
+            "#,
+        )
+        .bind(id)
+        .bind(caller.user_id)
+        .bind(&caller.namespace)
+        .fetch_optional(&self.deps.db)
+        .await?
+        .ok_or_else(|| AppError::not_found("feed not found"))
+    }
+
+    async fn poll(&self) -> Result<(), AppError> {
+        let feeds: Vec<Feed> = sqlx::query_as("SELECT id, key_id, namespace, url FROM feeds")
+            .fetch_all(&self.deps.db)
+            .await?;
+        for feed in feeds {
+            if self.deps.shutdown.is_cancelled() {
+                break;
+            }
+            let (title, error) = match self.poll_feed(&feed).await {
+                Ok((title, ingested)) => {
+                    if ingested > 0 {
+                        info!("feed polled: feed_id={} entries={}", feed.id, ingested);
+                    }
+                    (title, None)
+                }
+                Err(error) => {
+                    warn!("feed poll failed: feed_id={} error={}", feed.id, error);
+                    (None, Some(error))
+                }
+            };
+            sqlx::query(
+                r#"
+                UPDATE feeds SET title = COALESCE(?2, title), last_polled_at = ?3, last_error = ?4
+                WHERE id = ?1
+                "#,
+            )
+            .bind(feed.id)
+            .bind(title)
+            .bind(Self::now())
+            .bind(error)
+            .execute(&self.deps.db)
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// Ingest the feed's unseen entries; returns its title and how many there were.
+    async fn poll_feed(&self, feed: &Feed) -> Result<(Option<String>, usize), String> {
+        let caller = self
+            .auth
+            .authorize_key(feed.key_id, &feed.namespace, Scope::Ingest)
+            .await
+            .map_err(|err| format!("{}; add the feed again with a live key", err.message()))?;
+        let url = Url::parse(&feed.url).map_err(|err| err.to_string())?;
+        let parsed = self.fetch(&url).await?;
+
+        let mut unseen = Vec::new();
+        for (guid, link) in parsed.entries {
+            let seen: Option<i64> =
+                sqlx::query_scalar("SELECT 1 FROM feed_entries WHERE feed_id = ?1 AND guid = ?2")
+                    .bind(feed.id)
+                    .bind(&guid)
+                    .fetch_optional(&self.deps.db)
+                    .await
+                    .map_err(|err| err.to_string())?;
+            if seen.is_none() {
+                unseen.push((guid, link));
+            }
+        }
+        if unseen.is_empty() {
+            return Ok((parsed.title, 0));
+        }
+
+        let urls: Vec<String> = unseen
+            .iter()
+            .filter_map(|(_, link)| IngestService::normalize_url(link))
+            .collect();
+        for batch in urls.chunks(self.deps.settings().max_urls.max(1)) {
+            self.ingest
+                .ingest_urls_waiting(caller.clone(), batch.to_vec(), &[])
+                .await
+                .map_err(|err| err.message().to_string())?;
+        }
+        let now = Self::now();
+        let mut tx = self.deps.db.begin().await.map_err(|err| err.to_string())?;
+        for (guid, _) in &unseen {
+            Self::mark_seen(&mut tx, feed.id, guid, &now)
+                .await
+                .map_err(|err| err.to_string())?;
+        }
+        tx.commit().await.map_err(|err| err.to_string())?;
+        Ok((parsed.title, unseen.len()))
+    }
+
+    /// Fetch and parse a feed, capped at `ingest.max_page_bytes`. Entries without a
+    /// link are dropped; relative links are resolved against the feed's URL.
+    async fn fetch(&self, url: &Url) -> Result<Entries, String> {
+        let response = self
+            .deps
+            .http_client
+            .get(url.as_str())
+            .header(ACCEPT, FEED_ACCEPT)
+            .timeout(REQUEST_TIMEOUT)
+            .send()
+            .await
+            .map_err(|err| err.without_url().to_string())?;
+        if !response.status().is_success() {
+            return Err(format!("http status {}", response.status()));
+        }
+        let max_bytes = self.deps.config.ingest.max_page_bytes;
+        let body = IngestService::read_capped(response, max_bytes)
+            .await
+            .map_err(|err| err.without_url().to_string())?;
+        if body.len() >= max_bytes {
+            return Err("feed is larger than ingest.max_page_bytes".to_string());
+        }
+
+        let base = url.clone();
+        tokio::task::spawn_blocking(move || {
+            let feed = feed_rs::parser::parse(body.as_slice()).map_err(|err| err.to_string())?;
+            let entries = feed
+                .entries
+                .into_iter()
+                .filter_map(|entry| {
+                    let link = entry
+                        .links
+                        .iter()
+                        .find(|link| link.rel.as_deref().is_none_or(|rel| rel == "alternate"))
+                        .or_else(|| entry.links.first())?;
+                    let link = base.join(link.href.trim()).ok()?;
+                    Some((entry.id, link.to_string()))
+                })
+                .collect();
+            Ok(Entries {
+                title: feed
+                    .title
+                    .map(|title| title.content.trim().to_string())
+                    .filter(|title| !title.is_empty()),
+                entries,
+            })
+        })
+        .await
+        .map_err(|err| err.to_string())?
+    }
+
+    async fn mark_seen(
+        tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+        feed_id: i64,
+        guid: &str,
+        now: &str,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "INSERT OR IGNORE INTO feed_entries (feed_id, guid, seen_at) VALUES (?1, ?2, ?3)",
+        )
+        .bind(feed_id)
+        .bind(guid)
+        .bind(now)
+        .execute(&mut **tx)
+        .await?;
+        Ok(())
+    }
+
+    fn now() -> String {
+        OffsetDateTime::now_utc()
+            .format(&Rfc3339)
+            .expect("failed to format timestamp")
+    }
+}
@@ -0,0 +1,134 @@
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use tantivy::collector::TopDocs;
+use tantivy::query::AllQuery;
+
+use crate::types::{ComponentHealth, Dependencies, HealthStatus, ReadinessResponse};
+
+#[derive(Clone)]
+pub struct HealthService {
+    deps: Arc<Dependencies>,
+    /// First time a check found the writer locked, cleared once it is free again.
+    writer_busy_since: Arc<std::sync::Mutex<Option<Instant>>>,
+}
+
+impl HealthService {
+    pub fn new(deps: Arc<Dependencies>) -> Self {
+        Self {
+            deps,
+            writer_busy_since: Arc::new(std::sync::Mutex::new(None)),
+        }
+    }
+
+    /// Probe the database, the index reader, and the writer lock concurrently.
+    pub async fn readiness(&self) -> ReadinessResponse {
+        let (database, index, writer) = tokio::join!(
+            self.check_database(),
+            self.check_index(),
+            self.check_writer()
+        );
+        let status = if [&database, &index, &writer]
+            .iter()
+            .any(|component| component.status == HealthStatus::Error)
+        {
+            HealthStatus::Error
+        } else {
+            HealthStatus::Ok
+        };
+        ReadinessResponse {
+            status,
+            database,
+            index,
+            writer,
+        }
+    }
+
+    async fn check_database(&self) -> ComponentHealth {
+        self.timed(async {
+            sqlx::query("SELECT 1")
+                .execute(&self.deps.db)
+                .await
+                .map(|_| (HealthStatus::Ok, None))
+                .map_err(|err| err.to_string())
+        })
+        .await
+    }
+
+    async fn check_index(&self) -> ComponentHealth {
+        let searchers = self.deps.index.searchers();
+        self.timed(async move {
+            tokio::task::spawn_blocking(move || {
+                searchers.iter().try_for_each(|searcher| {
+                    searcher.search(&AllQuery, &TopDocs::with_limit(1))?;
+                    Ok::<_, tantivy::TantivyError>(())
+                })
+            })
+            .await
+            .map_err(|err| err.to_string())?
+            .map(|_| (HealthStatus::Ok, None))
+            .map_err(|err| err.to_string())
+        })
+        .await
+    }
+
+    /// A briefly busy writer is normal; one held past `writer_stall_secs` is reported wedged.
+    async fn check_writer(&self) -> ComponentHealth {
+        let stall = Duration::from_secs(self.deps.config.server.writer_stall_secs);
+        let started = Instant::now();
+        let locked = tokio::time::timeout(self.timeout(), self.deps.writer.lock())
+            .await
+            .is_ok();
+
+        let mut busy_since = self
+            .writer_busy_since
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let (status, detail) = if locked {
+            *busy_since = None;
+            (HealthStatus::Ok, None)
+        } else {
+            let busy_for = busy_since.get_or_insert(started).elapsed();
+            let detail = Some(format!(
+                "writer locked for at least {}s",
+                busy_for.as_secs()
+            ));
+            if !stall.is_zero() && busy_for >= stall {
+                (HealthStatus::Error, detail)
+            } else {
+                (HealthStatus::Busy, detail)
+            }
+        };
+        ComponentHealth {
+            status,
+            detail,
+            elapsed_ms: started.elapsed().as_millis() as u64,
+        }
+    }
+
+    /// Run one check under the configured deadline, timing it.
+    async fn timed(
+        &self,
+        check: impl Future<Output = Result<(HealthStatus, Option<String>), String>>,
+    ) -> ComponentHealth {
+        let started = Instant::now();
+        let (status, detail) = match tokio::time::timeout(self.timeout(), check).await {
+            Ok(Ok(outcome)) => outcome,
+            Ok(Err(err)) => (HealthStatus::Error, Some(err)),
+            Err(_) => (
+                HealthStatus::Error,
+                Some(format!("timed out after {}ms", self.timeout().as_millis())),
+            ),
+        };
+        ComponentHealth {
+            status,
+            detail,
+            elapsed_ms: started.elapsed().as_millis() as u64,
+        }
+    }
+
+    fn timeout(&self) -> Duration {
+        Duration::from_millis(self.deps.config.server.health_timeout_ms)
+    }
+}
@@ -0,0 +1,754 @@
+use std::collections::{BTreeMap, HashSet};
+use std::io::{Cursor, Read};
+use std::sync::Arc;
+
+use axum::body::Body;
+use futures_util::StreamExt;
+use scraper::{Html, Selector};
+use serde::Deserialize;
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+use tracing::info;
+use url::Url;
+
+use crate::errors::AppError;
+use crate::services::ingest::ImportedUrl;
+use crate::services::{FeedService, IngestService, JobHandle, JobService};
+use crate::types::{
+    Caller, Dependencies, ImportFormat, ImportResponse, InstapaperImportResponse, JobKind,
+    OpmlImportResponse,
+};
+
+/// Longest token kept across chunk boundaries; anything longer is not a URL worth saving.
+const MAX_TOKEN_BYTES: usize = 8 * 1024;
+/// How browsers start a Netscape bookmark file.
+const NETSCAPE_DOCTYPE: &[u8] = b"<!doctype netscape-bookmark-file-1>";
+
+#[derive(Clone)]
+pub struct ImportService {
+    deps: Arc<Dependencies>,
+    ingest: IngestService,
+    jobs: JobService,
+    feeds: FeedService,
+}
+
+impl ImportService {
+    pub fn new(
+        deps: Arc<Dependencies>,
+        ingest: IngestService,
+        jobs: JobService,
+        feeds: FeedService,
+    ) -> Self {
+        Self {
+            deps,
+            ingest,
+            jobs,
+            feeds,
+        }
+    }
+
+    /// Start an import job for `caller`; feed it the upload chunk by chunk.
+    pub async fn begin(
+        &self,
+        caller: Caller,
+        format: ImportFormat,
+    ) -> Result<ImportSession, AppError> {
+        self.deps.ensure_writable()?;
+        let job = self
+            .jobs
+            .start(caller.user_id, JobKind::Import, None)
+            .await?;
+        Ok(ImportSession {
+            service: self.clone(),
+            response: ImportResponse {
+                job_id: job.id(),
+                found: 0,
+                accepted: 0,
+                deduped: 0,
+            },
+            caller,
+            job,
+            format,
+            part_format: format,
+            part_started: false,
+            export: Vec::new(),
+            scanner: UrlScanner::default(),
+            seen: HashSet::new(),
+            batch: Vec::new(),
+        })
+    }
+
+    /// Import Instapaper's CSV export, or with `dry_run` only report what it holds.
+    pub async fn instapaper(
+        &self,
+        caller: Caller,
+        body: Body,
+        dry_run: bool,
+    ) -> Result<InstapaperImportResponse, AppError> {
+        let mut csv = Vec::new();
+        let mut stream = body.into_data_stream();
+        while let Some(chunk) = stream.next().await {
+            csv.extend_from_slice(&chunk.map_err(AppError::upload)?);
+        }
+        let csv = String::from_utf8(csv)
+            .map_err(|_| AppError::bad_request("not an Instapaper export: not UTF-8"))?;
+        let rows = instapaper_rows(&csv)?;
+
+        let mut response = InstapaperImportResponse {
+            dry_run,
+            job_id: None,
+            rows: rows.len(),
+            invalid: 0,
+            starred: 0,
+            folders: BTreeMap::new(),
+            accepted: 0,
+            deduped: 0,
+        };
+        let mut urls = Vec::new();
+        for row in rows {
+            *response.folders.entry(row.folder.clone()).or_default() += 1;
+            if row.folder.eq_ignore_ascii_case("starred") {
+                response.starred += 1;
+            }
+            match IngestService::normalize_url(&row.url)
+                .filter(|url| url.starts_with("http://") || url.starts_with("https://"))
+            {
+                Some(url) => urls.push(url),
+                None => response.invalid += 1,
+            }
+        }
+
+        if dry_run {
+            let mut seen = HashSet::new();
+            for url in urls {
+                if !seen.insert(url.clone()) {
+                    response.deduped += 1;
+                    continue;
+                }
+                let saved: Option<i64> = sqlx::query_scalar(
+                    "SELECT id FROM bookmarks WHERE url = ?1 AND owner_id = ?2 AND namespace = ?3",
+                )
+                .bind(&url)
+                .bind(caller.user_id)
+                .bind(&caller.namespace)
+                .fetch_optional(&self.deps.db)
+                .await?;
+                if saved.is_some() {
+                    response.deduped += 1;
+                } else {
+                    response.accepted += 1;
+                }
+            }
+            return Ok(response);
+        }
+
+        let mut session = self.begin(caller, ImportFormat::Auto).await?;
+        session
+            .collect(urls.into_iter().map(ImportedUrl::from).collect())
+            .await?;
+        let imported = session.finish().await?;
+        response.job_id = Some(imported.job_id);
+        response.accepted = imported.accepted;
+        response.deduped = imported.deduped;
+        Ok(response)
+    }
+
+    /// Import an RSS reader's OPML export: feed outlines are subscribed to (with
+    /// `from_now`, from their next entry on) and link outlines are saved as bookmarks.
+    pub async fn opml(
+        &self,
+        caller: Caller,
+        body: Body,
+        from_now: bool,
+    ) -> Result<OpmlImportResponse, AppError> {
+        self.deps.ensure_writable()?;
+        let mut xml = Vec::new();
+        let mut stream = body.into_data_stream();
+        while let Some(chunk) = stream.next().await {
+            xml.extend_from_slice(&chunk.map_err(AppError::upload)?);
+        }
+        let xml = String::from_utf8(xml)
+            .map_err(|_| AppError::bad_request("not an OPML file: not UTF-8"))?;
+        let outlines = tokio::task::spawn_blocking(move || opml_outlines(&xml))
+            .await
+            .map_err(anyhow::Error::from)??;
+
+        let mut response = OpmlImportResponse {
+            feeds: outlines.feeds.len(),
+            subscribed: 0,
+            already_subscribed: 0,
+            failed: Vec::new(),
+            links: outlines.links.len(),
+            job_id: None,
+            accepted: 0,
+            deduped: 0,
+            invalid: outlines.invalid,
+        };
+        if !outlines.links.is_empty() {
+            let mut session = self.begin(caller.clone(), ImportFormat::Auto).await?;
+            session.collect(outlines.links).await?;
+            let imported = session.finish().await?;
+            response.job_id = Some(imported.job_id);
+            response.accepted = imported.accepted;
+            response.deduped = imported.deduped;
+        }
+        let feeds = self.feeds.import(&caller, outlines.feeds, from_now).await?;
+        response.subscribed = feeds.subscribed;
+        response.already_subscribed = feeds.already_subscribed;
+        response.failed = feeds.failed;
+        Ok(response)
+    }
+}
+
+/// An in-progress import: URLs are queued for ingest in `max_urls` batches as they are
+/// found, so the upload itself is never held in memory. Structured exports are the
+/// exception: each file is buffered, up to the import body limit, and parsed whole.
+/// With `auto`, a file that opens as a Netscape bookmark file is one of them, so its
+/// titles and dates are kept.
+pub struct ImportSession {
+    service: ImportService,
+    caller: Caller,
+    job: JobHandle,
+    format: ImportFormat,
+    /// `format`, or what `auto` detected for the current file.
+    part_format: ImportFormat,
+    part_started: bool,
+    export: Vec<u8>,
+    scanner: UrlScanner,
+    seen: HashSet<String>,
+    batch: Vec<ImportedUrl>,
+    response: ImportResponse,
+}
+
+impl ImportSession {
+    pub async fn feed(&mut self, chunk: &[u8]) -> Result<(), AppError> {
+        if !self.part_started {
+            self.part_started = true;
+            if self.part_format == ImportFormat::Auto && is_netscape(chunk) {
+                self.part_format = ImportFormat::Netscape;
+            }
+        }
+        if self.part_format != ImportFormat::Auto {
+            self.export.extend_from_slice(chunk);
+            return Ok(());
+        }
+        let urls = self.scanner.feed(chunk);
+        self.collect(urls.into_iter().map(ImportedUrl::from).collect())
+            .await
+    }
+
+    /// Mark the end of one file so a URL cannot run on into the next.
+    pub async fn end_part(&mut self) -> Result<(), AppError> {
+        let urls = match self.part_format {
+            ImportFormat::Auto => self
+                .scanner
+                .finish()
+                .into_iter()
+                .map(ImportedUrl::from)
+                .collect(),
+            _ if self.export.is_empty() => Vec::new(),
+            format => {
+                let export = std::mem::take(&mut self.export);
+                tokio::task::spawn_blocking(move || {
+                    let urls = match format {
+                        ImportFormat::Netscape => netscape_urls(&export),
+                        ImportFormat::Pocket => pocket_urls(&export)?,
+                        ImportFormat::Pinboard => pinboard_urls(&export)?,
+                        ImportFormat::Karakeep => plain(karakeep_urls(&export)?),
+                        ImportFormat::Shiori => plain(shiori_urls(&export)?),
+                        _ => plain(omnivore_urls(&export)?),
+                    };
+                    Ok::<_, AppError>(urls)
+                })
+                .await
+                .map_err(anyhow::Error::from)??
+            }
+        };
+        self.part_format = self.format;
+        self.part_started = false;
+        self.collect(urls).await
+    }
+
+    pub async fn finish(mut self) -> Result<ImportResponse, AppError> {
+        self.end_part().await?;
+        self.flush().await?;
+        info!(
+            "import finished: found={} accepted={} deduped={}",
+            self.response.found, self.response.accepted, self.response.deduped
+        );
+        self.job.finish(self.response.found, Ok(())).await;
+        Ok(self.response)
+    }
+
+    async fn collect(&mut self, urls: Vec<ImportedUrl>) -> Result<(), AppError> {
+        if self.job.is_cancelled() {
+            return Err(AppError::conflict("import cancelled"));
+        }
+        self.job.progress(self.response.found, None).await;
+        for url in urls {
+            self.response.found += 1;
+            if !self.seen.insert(url.url.clone()) {
+                self.response.deduped += 1;
+                continue;
+            }
+            self.batch.push(url);
+            if self.batch.len() >= self.service.deps.settings().max_urls {
+                self.flush().await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<(), AppError> {
+        if self.batch.is_empty() {
+            return Ok(());
+        }
+        let urls = std::mem::take(&mut self.batch);
+        let result = self
+            .service
+            .ingest
+            .ingest_imported_waiting(self.caller.clone(), urls)
+            .await?;
+        self.response.accepted += result.accepted;
+        self.response.deduped += result.deduped;
+        Ok(())
+    }
+}
+
+fn plain(urls: Vec<String>) -> Vec<ImportedUrl> {
+    urls.into_iter().map(ImportedUrl::from).collect()
+}
+
+fn is_netscape(chunk: &[u8]) -> bool {
+    let chunk = chunk.strip_prefix(b"\xef\xbb\xbf").unwrap_or(chunk);
+    chunk
+        .trim_ascii_start()
+        .get(..NETSCAPE_DOCTYPE.len())
+        .is_some_and(|start| start.eq_ignore_ascii_case(NETSCAPE_DOCTYPE))
+}
+
+/// A Unix timestamp from an export as RFC 3339. Seconds are the norm, but some tools
+/// write milliseconds or microseconds; zero and garbage mean no date.
+fn unix_time(value: &str) -> Option<String> {
+    let value: i64 = value.trim().parse().ok()?;
+    let secs = match value {
+        value if value > 100_000_000_000_000 => value / 1_000_000,
+        value if value > 100_000_000_000 => value / 1_000,
+        value => value,
+    };
+    if secs <= 0 {
+        return None;
+    }
+    OffsetDateTime::from_unix_timestamp(secs)
+        .ok()?
+        .format(&Rfc3339)
+        .ok()
+}
+
+fn non_empty(text: Option<String>) -> Option<String> {
+    text.map(|text| text.trim().to_string())
+        .filter(|text| !text.is_empty())
+}
+
+/// Every link in a Netscape bookmark file, as Chrome, Firefox, Safari, and Pinboard
+/// export them: the anchor text is the title and `add_date` when it was saved. Pocket's
+/// `ril_export.html` is read the same way, with its `time_added`. Folders are dropped.
+fn netscape_urls(export: &[u8]) -> Vec<ImportedUrl> {
+    let html = String::from_utf8_lossy(export);
+    let document = Html::parse_document(&html);
+    let anchors = Selector::parse("a[href]").expect("valid selector");
+    document
+        .select(&anchors)
+        .filter_map(|anchor| {
+            let href = anchor.value().attr("href")?.trim();
+            let lower = href.get(..8)?.to_ascii_lowercase();
+            if !lower.starts_with("http://") && !lower.starts_with("https://") {
+                return None;
+            }
+            let added_at = anchor
+                .value()
+                .attr("add_date")
+                .or_else(|| anchor.value().attr("time_added"))
+                .and_then(unix_time);
+            Some(ImportedUrl {
+                url: href.to_string(),
+                title: non_empty(Some(anchor.text().collect())),
+                added_at,
+            })
+        })
+        .collect()
+}
+
+/// One item of Pocket's `/v3/get` API; `time_added` is a string of Unix seconds.
+#[derive(Deserialize)]
+struct PocketItem {
+    given_url: Option<String>,
+    resolved_url: Option<String>,
+    given_title: Option<String>,
+    resolved_title: Option<String>,
+    time_added: Option<String>,
+}
+
+/// Items keyed by id; Pocket sends an empty `list` as `[]` rather than `{}`.
+#[derive(Deserialize)]
+struct PocketExport {
+    list: serde_json::Value,
+}
+
+/// Pocket's exports: the CSV of its current exporter (`title,url,time_added,...`), the
+/// `ril_export.html` of its old one, or a saved `/v3/get` API response. Tags and the
+/// archived status are dropped.
+fn pocket_urls(export: &[u8]) -> Result<Vec<ImportedUrl>, AppError> {
+    let start = export.strip_prefix(b"\xef\xbb\xbf").unwrap_or(export);
+    match start.trim_ascii_start().first() {
+        Some(b'<') => return Ok(netscape_urls(export)),
+        Some(b'{') => {
+            let invalid = |err: serde_json::Error| {
+                AppError::bad_request(format!("not a Pocket export: {}", err))
+            };
+            let export: PocketExport = serde_json::from_slice(export).map_err(invalid)?;
+            if !export.list.is_object() {
+                return Ok(Vec::new());
+            }
+            let items: BTreeMap<String, PocketItem> =
+                serde_json::from_value(export.list).map_err(invalid)?;
+            return Ok(items
+                .into_values()
+                .filter_map(|item| {
+                    Some(ImportedUrl {
+                        url: non_empty(item.given_url).or(non_empty(item.resolved_url))?,
+                        title: non_empty(item.given_title).or(non_empty(item.resolved_title)),
+                        added_at: item.time_added.as_deref().and_then(unix_time),
+                    })
+                })
+                .collect());
+        }
+        _ => {}
+    }
+
+    let csv = String::from_utf8(export.to_vec())
+        .map_err(|_| AppError::bad_request("not a Pocket export: not UTF-8"))?;
+    let mut records = parse_csv(csv.trim_start_matches('\u{feff}')).into_iter();
+    let header = records.next().unwrap_or_default();
+    let column = |name: &str| {
+        header
+            .iter()
+            .position(|field| field.trim().eq_ignore_ascii_case(name))
+    };
+    let Some(url_column) = column("url") else {
+        return Err(AppError::bad_request(
+            "not a Pocket export: expected its CSV, ril_export.html, or API JSON",
+        ));
+    };
+    let title_column = column("title");
+    let time_column = column("time_added");
+    Ok(records
+        .filter_map(|record| {
+            let field = |index: Option<usize>| non_empty(record.get(index?).cloned());
+            Some(ImportedUrl {
+                url: field(Some(url_column))?,
+                title: field(title_column),
+                added_at: field(time_column).as_deref().and_then(unix_time),
+            })
+        })
+        .collect())
+}
+
+/// One post of Pinboard's JSON export (`posts/all?format=json`); `description` is the
+/// title and `time` is RFC 3339.
+#[derive(Deserialize)]
+struct PinboardPost {
+    href: String,
+    description: Option<String>,
+    time: Option<String>,
+}
+
+fn pinboard_urls(export: &[u8]) -> Result<Vec<ImportedUrl>, AppError> {
+    let posts: Vec<PinboardPost> = serde_json::from_slice(export)
+        .map_err(|err| AppError::bad_request(format!("not a Pinboard export: {}", err)))?;
+    Ok(posts
+        .into_iter()
+        .map(|post| ImportedUrl {
+            url: post.href,
+            title: non_empty(post.description),
+            added_at: post
+                .time
+                .and_then(|time| OffsetDateTime::parse(&time, &Rfc3339).ok())
+                .and_then(|time| time.format(&Rfc3339).ok()),
+        })
+        .collect())
+}
+
+/// One saved item in Omnivore's `metadata_*.json` files; highlights and labels are
+/// exported separately and have nowhere to go in odin.
+#[derive(Deserialize)]
+struct OmnivoreItem {
+    url: Option<String>,
+}
+
+/// The saved URLs in an Omnivore export zip, or in one of its metadata files.
+fn omnivore_urls(export: &[u8]) -> Result<Vec<String>, AppError> {
+    let invalid = |err: &dyn std::fmt::Display| {
+        AppError::bad_request(format!("not an Omnivore export: {}", err))
+    };
+    if !export.starts_with(b"PK") {
+        let items: Vec<OmnivoreItem> =
+            serde_json::from_slice(export).map_err(|err| invalid(&err))?;
+        return Ok(items.into_iter().filter_map(|item| item.url).collect());
+    }
+
+    let mut archive = zip::ZipArchive::new(Cursor::new(export)).map_err(|err| invalid(&err))?;
+    let mut urls = Vec::new();
+    for index in 0..archive.len() {
+        let mut file = archive.by_index(index).map_err(|err| invalid(&err))?;
+        let name = file.name().rsplit('/').next().unwrap_or_default();
+        if !name.starts_with("metadata_") || !name.ends_with(".json") {
+            continue;
+        }
+        let mut json = Vec::new();
+        file.read_to_end(&mut json).map_err(|err| invalid(&err))?;
+        let items: Vec<OmnivoreItem> =
+            serde_json::from_slice(&json).map_err(|err| invalid(&err))?;
+        urls.extend(items.into_iter().filter_map(|item| item.url));
+    }
+    if urls.is_empty() {
+        return Err(AppError::bad_request(
+            "not an Omnivore export: no metadata_*.json files with saved items",
+        ));
+    }
+    Ok(urls)
+}
+
+/// A Karakeep (formerly Hoarder) JSON export. Only link bookmarks have a URL; text
+/// notes and uploaded assets are skipped. Lists, tags, notes, and the archived flag have
+/// no odin equivalent, and odin keeps its own copy of every page it fetches.
+#[derive(Deserialize)]
+struct KarakeepExport {
+    bookmarks: Vec<KarakeepBookmark>,
+}
+
+#[derive(Deserialize)]
+struct KarakeepBookmark {
+    content: Option<KarakeepContent>,
+}
+
+#[derive(Deserialize)]
+struct KarakeepContent {
+    #[serde(rename = "type")]
+    kind: String,
+    url: Option<String>,
+}
+
+fn karakeep_urls(export: &[u8]) -> Result<Vec<String>, AppError> {
+    let export: KarakeepExport = serde_json::from_slice(export)
+        .map_err(|err| AppError::bad_request(format!("not a Karakeep export: {}", err)))?;
+    Ok(export
+        .bookmarks
+        .into_iter()
+        .filter_map(|bookmark| bookmark.content)
+        .filter(|content| content.kind == "link")
+        .filter_map(|content| content.url)
+        .collect())
+}
+
+/// Shiori's bookmarks as JSON: a bare array, or the `bookmarks` of an API page. Its
+/// `shiori export` HTML needs no format of its own; the default scan reads it. Tags and
+/// offline archives are dropped, as for Karakeep.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ShioriExport {
+    Items(Vec<ShioriItem>),
+    Page { bookmarks: Vec<ShioriItem> },
+}
+
+#[derive(Deserialize)]
+struct ShioriItem {
+    url: Option<String>,
+}
+
+fn shiori_urls(export: &[u8]) -> Result<Vec<String>, AppError> {
+    let export: ShioriExport = serde_json::from_slice(export).map_err(|_| {
+        AppError::bad_request(
+            "not a Shiori export: expected a JSON array of bookmarks or an object with `bookmarks`",
+        )
+    })?;
+    let (ShioriExport::Items(items) | ShioriExport::Page { bookmarks: items }) = export;
+    Ok(items.into_iter().filter_map(|item| item.url).collect())
+}
+
+struct InstapaperRow {
+    url: String,
+    folder: String,
+}
+
+/// An OPML document's outlines: feeds by `xmlUrl` (distinct, with the outline's name)
+/// and links by `url`, as `type="link"` outlines carry them.
+struct OpmlOutlines {
+    feeds: Vec<(Url, Option<String>)>,
+    links: Vec<ImportedUrl>,
+    invalid: usize,
+}
+
+fn opml_outlines(xml: &str) -> Result<OpmlOutlines, AppError> {
+    let options = roxmltree::ParsingOptions {
+        allow_dtd: true,
+        ..roxmltree::ParsingOptions::default()
+    };
+    let document = roxmltree::Document::parse_with_options(xml, options)
+        .map_err(|err| AppError::bad_request(format!("not an OPML file: {}", err)))?;
+    if !document.root_element().has_tag_name("opml") {
+        return Err(AppError::bad_request("not an OPML file: no <opml> root"));
+    }
+    let mut outlines = OpmlOutlines {
+        feeds: Vec::new(),
+        links: Vec::new(),
+        invalid: 0,
+    };
+    let mut seen = HashSet::new();
+    for outline in document
+        .descendants()
+        .filter(|node| node.has_tag_name("outline"))
+    {
+        // Readers disagree on the case of `xmlUrl` and `htmlUrl`.
+        let attribute = |name: &str| {
+            outline
+                .attributes()
+                .find(|attribute| attribute.name().eq_ignore_ascii_case(name))
+                .map(|attribute| attribute.value().trim())
+                .filter(|value| !value.is_empty())
+        };
+        let name = attribute("title").or(attribute("text")).map(str::to_string);
+        if let Some(feed) = attribute("xmlUrl") {
+            match Url::parse(feed)
+                .ok()
+                .filter(|url| matches!(url.scheme(), "http" | "https"))
+            {
+                Some(url) if seen.insert(url.to_string()) => outlines.feeds.push((url, name)),
+                Some(_) => {}
+                None => outlines.invalid += 1,
+            }
+        } else if let Some(link) = attribute("url") {
+            match IngestService::normalize_url(link)
+                .filter(|url| url.starts_with("http://") || url.starts_with("https://"))
+            {
+                Some(url) => outlines.links.push(ImportedUrl {
+                    url,
+                    title: name,
+                    added_at: None,
+                }),
+                None => outlines.invalid += 1,
+            }
+        }
+    }
+    Ok(outlines)
+}
+
+/// Rows of Instapaper's `URL,Title,Selection,Folder,Timestamp` CSV, found by header
+/// name so added or reordered columns still read.
+fn instapaper_rows(csv: &str) -> Result<Vec<InstapaperRow>, AppError> {
+    let mut records = parse_csv(csv.trim_start_matches('\u{feff}')).into_iter();
+    let header = records.next().unwrap_or_default();
+    let column = |name: &str| {
+        header
+            .iter()
+            .position(|field| field.trim().eq_ignore_ascii_case(name))
+    };
+    let Some(url_column) = column("url") else {
+        return Err(AppError::bad_request(
+            "not an Instapaper export: no URL column in the header",
+        ));
+    };
+    let folder_column = column("folder");
+    Ok(records
+        // A trailing newline leaves one empty record.
+        .filter(|record| record.iter().any(|field| !field.is_empty()))
+        .map(|record| {
+            let field = |index: Option<usize>| {
+                index
+                    .and_then(|index| record.get(index))
+                    .map(|field| field.trim().to_string())
+                    .unwrap_or_default()
+            };
+            InstapaperRow {
+                url: field(Some(url_column)),
+                folder: field(folder_column),
+            }
+        })
+        .collect())
+}
+
+/// RFC 4180 CSV: quoted fields may hold commas, doubled quotes, and line breaks.
+fn parse_csv(text: &str) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => record.push(std::mem::take(&mut field)),
+            '\r' if !quoted && chars.peek() == Some(&'\n') => {}
+            '\n' | '\r' if !quoted => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            ch => field.push(ch),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    records
+}
+
+/// Pulls `http(s)://` URLs out of a byte stream in any text-ish export format
+/// (Netscape bookmark HTML, CSV, one URL per line) without buffering it whole.
+#[derive(Default)]
+struct UrlScanner {
+    tail: Vec<u8>,
+}
+
+impl UrlScanner {
+    fn feed(&mut self, chunk: &[u8]) -> Vec<String> {
+        self.tail.extend_from_slice(chunk);
+        // Only complete tokens are scanned; the unfinished one waits for the next chunk.
+        let split = match self.tail.iter().rposition(|byte| Self::is_delimiter(*byte)) {
+            Some(position) => position + 1,
+            None if self.tail.len() > MAX_TOKEN_BYTES => self.tail.len(),
+            None => return Vec::new(),
+        };
+        let rest = self.tail.split_off(split);
+        let complete = std::mem::replace(&mut self.tail, rest);
+        Self::scan(&complete)
+    }
+
+    fn finish(&mut self) -> Vec<String> {
+        let rest = std::mem::take(&mut self.tail);
+        Self::scan(&rest)
+    }
+
+    fn scan(bytes: &[u8]) -> Vec<String> {
+        bytes
+            .split(|byte| Self::is_delimiter(*byte))
+            .filter_map(|token| {
+                let token = std::str::from_utf8(token).ok()?;
+                let lower = token.get(..8)?.to_ascii_lowercase();
+                if !lower.starts_with("http://") && !lower.starts_with("https://") {
+                    return None;
+                }
+                let token = token.trim_end_matches(['.', ';', ')', ']', '}']);
+                Some(token.replace("&amp;", "&"))
+            })
+            .collect()
+    }
+
+    fn is_delimiter(byte: u8) -> bool {
+        byte.is_ascii_whitespace() || matches!(byte, b'"' | b'\'' | b'<' | b'>' | b',' | b'`')
+    }
+}
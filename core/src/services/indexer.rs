@@ -0,0 +1,105 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use tantivy::TantivyDocument;
+use tokio::sync::{Mutex, mpsc};
+use tokio::time::Instant;
+use tracing::{info, warn};
+
+use crate::types::Dependencies;
+
+/// Commits ingested documents in batches. Ingest workers add their document to the
+/// writer without committing and report it here; one task commits once
+/// `storage.commit_batch_docs` documents are waiting or `storage.commit_interval_ms`
+/// after the first of them, so a bulk ingest pays for a commit per batch, not per page.
+/// Uncommitted documents are flushed by the shutdown commit; after a crash the
+/// reconciler re-adds them.
+#[derive(Clone)]
+pub struct IndexerService {
+    deps: Arc<Dependencies>,
+    added: mpsc::UnboundedSender<i64>,
+    /// Taken by the committer task once it starts.
+    pending: Arc<Mutex<Option<mpsc::UnboundedReceiver<i64>>>>,
+}
+
+impl IndexerService {
+    pub fn new(deps: Arc<Dependencies>) -> Self {
+        let (added, pending) = mpsc::unbounded_channel();
+        Self {
+            deps,
+            added,
+            pending: Arc::new(Mutex::new(Some(pending))),
+        }
+    }
+
+    /// Replace a bookmark's document, leaving the commit to the next batch. The writer
+    /// lock is held only for the add, so deletes and other writers keep their order.
+    pub(crate) async fn add(
+        &self,
+        bookmark_id: i64,
+        document: TantivyDocument,
+    ) -> anyhow::Result<()> {
+        {
+            let mut writer = self.deps.writer.lock().await;
+            writer.delete_bookmark(bookmark_id);
+            writer.add_document(bookmark_id, document)?;
+        }
+        // Only fails once the committer is gone at shutdown, whose commit covers it.
+        let _ = self.added.send(bookmark_id);
+        Ok(())
+    }
+
+    /// Spawn the committer task; it stops when shutdown begins.
+    pub fn start(&self) {
+        let service = self.clone();
+        tokio::spawn(async move {
+            let Some(mut pending) = service.pending.lock().await.take() else {
+                return;
+            };
+            let storage = &service.deps.config.storage;
+            let batch_docs = storage.commit_batch_docs;
+            let interval = Duration::from_millis(storage.commit_interval_ms);
+            info!(
+                "index committer started: batch_docs={} interval_ms={}",
+                batch_docs,
+                interval.as_millis()
+            );
+            let shutdown = service.deps.shutdown.clone();
+            loop {
+                tokio::select! {
+                    _ = shutdown.cancelled() => return,
+                    added = pending.recv() => if added.is_none() {
+                        return;
+                    },
+                }
+                let deadline = Instant::now() + interval;
+                let mut batch = 1;
+                while batch < batch_docs {
+                    tokio::select! {
+                        // Documents already waiting join the batch even when the
+                        // interval is 0.
+                        biased;
+                        added = pending.recv() => match added {
+                            Some(_) => batch += 1,
+                            None => break,
+                        },
+                        _ = tokio::time::sleep_until(deadline) => break,
+                    }
+                }
+                service.commit(batch).await;
+            }
+        });
+    }
+
+    async fn commit(&self, batch: usize) {
+        let mut writer = self.deps.writer.lock().await;
+        // Another writer's commit may already have included the batch.
+        if !writer.has_changes() {
+            return;
+        }
+        self.deps.metrics.observe_commit_batch(batch);
+        if let Err(err) = self.deps.commit_index(&mut writer, "ingest") {
+            warn!("index batch commit failed: docs={} error={}", batch, err);
+        }
+    }
+}
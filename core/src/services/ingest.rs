@@ -0,0 +1,2056 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use futures_util::StreamExt;
+use reqwest::header::CONTENT_TYPE;
+use scraper::{Html, Selector};
+use sha2::{Digest, Sha256};
+use sqlx::{FromRow, QueryBuilder, Sqlite};
+use tantivy::{TantivyDocument, doc};
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+use tokio::sync::{Mutex, OwnedSemaphorePermit, Semaphore, mpsc};
+use tracing::{Instrument, Span, error, info, info_span, warn};
+use url::{Position, Url};
+
+use crate::errors::AppError;
+use crate::robots::{MAX_ROBOTS_BYTES, Robots};
+use crate::services::{
+    DomainStatsService, IndexerService, JobHandle, JobService, MaintenanceService,
+};
+use crate::types::{
+    BookmarkEvent, BookmarkEventKind, Caller, Dependencies, IngestUrlsRequest, IngestUrlsResponse,
+    JobKind, ReindexProgress, ReindexRequest,
+};
+use crate::{content, index, tags};
+
+const CONCURRENT_REINDEX_LIMIT: usize = 4;
+/// Bookmarks whose stored text a rebuild reads per hold of the writer lock.
+const REBUILD_BATCH: usize = 100;
+/// Rows per multi-row INSERT, well under SQLite's 32766 bound parameters.
+const INSERT_BATCH: usize = 1_000;
+/// Hosts tracked before ones without running fetches are pruned from the limiter.
+const HOST_PRUNE_THRESHOLD: usize = 1_000;
+/// Longest `Crawl-delay` honored; sites asking for more are still fetched this often.
+const MAX_CRAWL_DELAY: Duration = Duration::from_secs(60);
+/// Extracted text a page needs before matching text alone merges it into another
+/// bookmark; shorter pages are too often error or login screens shared across a site.
+const MIN_MERGE_TEXT_CHARS: usize = 500;
+const BLOCKED_BY_ROBOTS: &str = "blocked by robots.txt";
+/// Why a page was stored as `partial`.
+const TRUNCATED_PAGE: &str = "truncated: page is larger than ingest.max_page_bytes";
+const TRUNCATED_DOM: &str = "truncated: page has more elements than ingest.max_dom_nodes";
+const TRUNCATED_TEXT: &str = "truncated: text is longer than ingest.max_text_chars";
+/// Dropped from URLs before they are saved, along with every `utm_*` parameter.
+const TRACKING_PARAMS: &[&str] = &[
+    "fbclid",
+    "gclid",
+    "dclid",
+    "gbraid",
+    "wbraid",
+    "msclkid",
+    "yclid",
+    "twclid",
+    "igshid",
+    "mc_cid",
+    "mc_eid",
+    "_hsenc",
+    "_hsmi",
+    "mkt_tok",
+    "oly_anon_id",
+    "oly_enc_id",
+    "vero_id",
+];
+
+/// What a fetched body is, by `Content-Type` or, when that is missing or generic, by
+/// sniffing; stored as `bookmarks.document_type`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum DocumentType {
+    Html,
+    Pdf,
+    /// Plain text or Markdown, indexed as is.
+    Text,
+}
+
+impl DocumentType {
+    fn as_str(self) -> &'static str {
+        match self {
+            DocumentType::Html => "html",
+            DocumentType::Pdf => "pdf",
+            DocumentType::Text => "text",
+        }
+    }
+}
+
+/// What the response said a page was, and how it was read.
+struct FetchedPage<'a> {
+    http_status: u16,
+    content_type: &'a str,
+    document_type: DocumentType,
+    content_hash: Option<&'a str>,
+}
+
+/// What became of a bookmark once its fetch showed where the page lives.
+enum Settled {
+    /// Kept, under this URL.
+    Kept(String),
+    /// Merged into this earlier bookmark, and deleted.
+    Merged(i64),
+}
+
+/// Caps on what one page may cost to extract.
+struct ExtractLimits {
+    /// The body already stopped at `ingest.max_page_bytes`.
+    page_truncated: bool,
+    max_dom_nodes: usize,
+    max_text_chars: usize,
+}
+
+/// What extraction made of a fetched body.
+struct Extracted {
+    title: Option<String>,
+    body: String,
+    excerpt: Option<String>,
+    truncated: Option<&'static str>,
+    /// The compressed page, when `storage.archive_pages` is on.
+    archive: anyhow::Result<Option<Vec<u8>>>,
+    /// The page's `rel="canonical"` link as written, possibly relative.
+    canonical: Option<String>,
+    /// SHA-256 of `body`; unset for an empty one, which says nothing about the page.
+    content_hash: Option<String>,
+}
+
+/// A URL from an export, with what the export said about it. The title is kept until
+/// a fetch finds one, and `added_at` (RFC 3339) becomes the bookmark's `created_at`.
+pub(crate) struct ImportedUrl {
+    pub(crate) url: String,
+    pub(crate) title: Option<String>,
+    pub(crate) added_at: Option<String>,
+}
+
+impl From<String> for ImportedUrl {
+    fn from(url: String) -> Self {
+        Self {
+            url,
+            title: None,
+            added_at: None,
+        }
+    }
+}
+
+/// A bookmark row queued for fetching and indexing.
+#[derive(Clone, FromRow)]
+struct IngestTarget {
+    id: i64,
+    owner_id: i64,
+    namespace: String,
+    url: String,
+    /// Fetches already made for this ingest; the next one is `attempts + 1`.
+    attempts: i64,
+}
+
+/// A `queued` row read back at startup, possibly waiting for a retry.
+#[derive(FromRow)]
+struct ResumedTarget {
+    #[sqlx(flatten)]
+    target: IngestTarget,
+    next_attempt_at: Option<String>,
+}
+
+/// An ingest queue entry, carrying the span of the request that queued it so fetch
+/// logs keep its request id.
+struct QueuedTarget {
+    target: IngestTarget,
+    span: Span,
+}
+
+/// Extracted page content, as written to the index and kept for rebuilds.
+struct IndexedContent {
+    id: i64,
+    owner_id: i64,
+    namespace: String,
+    url: String,
+    title: Option<String>,
+    excerpt: Option<String>,
+    body: String,
+    fetched_at: i64,
+    tags: Vec<String>,
+}
+
+/// An `IndexedContent` as read back, with its body still encoded.
+#[derive(FromRow)]
+struct StoredContent {
+    id: i64,
+    owner_id: i64,
+    namespace: String,
+    url: String,
+    title: Option<String>,
+    excerpt: Option<String>,
+    body: Vec<u8>,
+    encoding: String,
+    fetched_at: i64,
+}
+
+/// Streams reindex progress to the requester and its job; a dropped receiver never
+/// stops the work (cancel the job for that).
+struct ReindexReporter {
+    tx: mpsc::Sender<ReindexProgress>,
+    job: JobHandle,
+    done: usize,
+    total: usize,
+    detached: bool,
+}
+
+impl ReindexReporter {
+    async fn report(&mut self, id: i64, url: String, status: String, error: Option<String>) {
+        self.done += 1;
+        let progress = ReindexProgress {
+            id,
+            url,
+            status,
+            error,
+            done: self.done,
+            total: self.total,
+        };
+        if !self.detached && self.tx.send(progress).await.is_err() {
+            self.detached = true;
+            info!("reindex progress receiver dropped; continuing in background");
+        }
+        self.job.progress(self.done, Some(self.total)).await;
+    }
+}
+
+struct CachedRobots {
+    fetched_at: Instant,
+    rules: Arc<Robots>,
+}
+
+#[derive(Clone)]
+pub struct IngestService {
+    deps: Arc<Dependencies>,
+    jobs: JobService,
+    domain_stats: DomainStatsService,
+    indexer: IndexerService,
+    maintenance: MaintenanceService,
+    queue: mpsc::Sender<QueuedTarget>,
+    /// Shared by the workers; whichever is idle takes the next target.
+    pending: Arc<Mutex<mpsc::Receiver<QueuedTarget>>>,
+    /// Fetch slots per host, capped at `ingest.max_connections_per_host`.
+    hosts: Arc<std::sync::Mutex<HashMap<String, Arc<Semaphore>>>>,
+    /// Each site's robots.txt by origin, with when it was fetched.
+    robots: Arc<std::sync::Mutex<HashMap<String, CachedRobots>>>,
+    /// When the next fetch may start against each host.
+    next_fetch: Arc<std::sync::Mutex<HashMap<String, Instant>>>,
+}
+
+impl IngestService {
+    pub fn new(
+        deps: Arc<Dependencies>,
+        jobs: JobService,
+        domain_stats: DomainStatsService,
+        indexer: IndexerService,
+        maintenance: MaintenanceService,
+    ) -> Self {
+        let (queue, pending) = mpsc::channel(deps.config.ingest.queue_capacity);
+        Self {
+            deps,
+            jobs,
+            domain_stats,
+            indexer,
+            maintenance,
+            queue,
+            pending: Arc::new(Mutex::new(pending)),
+            hosts: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            robots: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            next_fetch: Arc::new(std::sync::Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Spawn the fixed pool of ingest workers. After shutdown begins they drain what
+    /// is already queued (bounded by the shutdown grace period) and exit.
+    pub fn start(&self) {
+        let workers = self.deps.config.ingest.workers;
+        info!(
+            "ingest workers started: workers={} queue_capacity={}",
+            workers, self.deps.config.ingest.queue_capacity
+        );
+        for _ in 0..workers {
+            let service = self.clone();
+            self.deps.tasks.spawn(async move {
+                let shutdown = service.deps.shutdown.clone();
+                loop {
+                    let next = {
+                        let mut pending = service.pending.lock().await;
+                        if shutdown.is_cancelled() {
+                            pending.try_recv().ok()
+                        } else {
+                            tokio::select! {
+                                target = pending.recv() => target,
+                                _ = shutdown.cancelled() => pending.try_recv().ok(),
+                            }
+                        }
+                    };
+                    let Some(QueuedTarget { target, span }) = next else {
+                        break;
+                    };
+                    if let Err(err) = service.process_url(target).instrument(span).await {
+                        error!("ingest error: {:?}", err);
+                    }
+                    service.deps.metrics.ingest_finished();
+                }
+            });
+        }
+    }
+
+    /// Feed bookmarks left `queued` by a previous process back to the workers: those
+    /// due now in id order, and those waiting for a retry once their backoff elapses.
+    pub async fn resume(&self) -> anyhow::Result<()> {
+        let rows: Vec<ResumedTarget> = sqlx::query_as(
+            r#"
+            SELECT id, owner_id, namespace, url, attempts, next_attempt_at
+            FROM bookmarks
+            WHERE status = 'queued'
+            ORDER BY id
+            "#,
+        )
+        .fetch_all(&self.deps.db)
+        .await
+        .context("load queued bookmarks")?;
+        if rows.is_empty() {
+            return Ok(());
+        }
+        info!("resuming {} queued bookmarks", rows.len());
+
+        let now = OffsetDateTime::now_utc();
+        let mut due = Vec::new();
+        for ResumedTarget {
+            target,
+            next_attempt_at,
+        } in rows
+        {
+            let retry_at = next_attempt_at
+                .and_then(|at| OffsetDateTime::parse(&at, &Rfc3339).ok())
+                .filter(|at| *at > now);
+            match retry_at {
+                Some(at) => self.requeue_after(target, (at - now).unsigned_abs()),
+                None => due.push(target),
+            }
+        }
+
+        self.requeue_all(due);
+        Ok(())
+    }
+
+    /// Queue the caller's bookmarks for a fresh fetch, all of them or only the one with
+    /// `id` or those with `status`; each starts over with no attempts. Rows are marked
+    /// `queued` at once and fed to the workers as the queue has room, so a large batch
+    /// survives a restart. Bookmarks already queued are skipped. Returns how many were
+    /// queued.
+    pub(crate) async fn refetch(
+        &self,
+        caller: &Caller,
+        id: Option<i64>,
+        status: Option<&str>,
+    ) -> Result<usize, AppError> {
+        self.deps.ensure_writable()?;
+        let targets: Vec<IngestTarget> = sqlx::query_as(
+            r#"
+            UPDATE bookmarks
+            SET status = 'queued', error = NULL, attempts = 0, next_attempt_at = NULL, updated_at = ?1
+            WHERE owner_id = ?2 AND namespace = ?3 AND status != 'queued'
+              AND (?4 IS NULL OR id = ?4) AND (?5 IS NULL OR status = ?5)
+            RETURNING id, owner_id, namespace, url, attempts
+            "#,
+        )
+        .bind(Self::now_rfc3339())
+        .bind(caller.user_id)
+        .bind(&caller.namespace)
+        .bind(id)
+        .bind(status)
+        .fetch_all(&self.deps.db)
+        .await?;
+        let queued = targets.len();
+        info!("refetch queued: {} bookmarks", queued);
+        self.requeue_all(targets);
+        Ok(queued)
+    }
+
+    /// Feed `targets` to the workers in order from a background task.
+    fn requeue_all(&self, mut targets: Vec<IngestTarget>) {
+        if targets.is_empty() {
+            return;
+        }
+        targets.sort_by_key(|target| target.id);
+        let service = self.clone();
+        self.deps.tasks.spawn(async move {
+            for target in targets {
+                if !service.requeue(target).await {
+                    break;
+                }
+            }
+        });
+    }
+
+    /// Queue URLs for ingest, rejecting the whole batch with 503 when the queue
+    /// cannot take it so clients can back off and retry.
+    pub async fn ingest_urls(
+        &self,
+        caller: Caller,
+        payload: IngestUrlsRequest,
+    ) -> Result<IngestUrlsResponse, AppError> {
+        info!("ingest request received: {} urls", payload.urls.len());
+        let tags = tags::normalize_all(&payload.tags)?;
+        let urls = payload.urls.into_iter().map(ImportedUrl::from).collect();
+        self.enqueue(caller, urls, &tags, false).await
+    }
+
+    /// Like [`Self::ingest_urls`], but waits for queue space instead of failing;
+    /// imports use this so a large upload is paced by the workers. `tags` must
+    /// already be normalized.
+    pub(crate) async fn ingest_urls_waiting(
+        &self,
+        caller: Caller,
+        urls: Vec<String>,
+        tags: &[String],
+    ) -> Result<IngestUrlsResponse, AppError> {
+        let urls = urls.into_iter().map(ImportedUrl::from).collect();
+        self.enqueue(caller, urls, tags, true).await
+    }
+
+    /// Like [`Self::ingest_urls_waiting`], keeping the titles and dates an export gave.
+    pub(crate) async fn ingest_imported_waiting(
+        &self,
+        caller: Caller,
+        urls: Vec<ImportedUrl>,
+    ) -> Result<IngestUrlsResponse, AppError> {
+        self.enqueue(caller, urls, &[], true).await
+    }
+
+    /// Insert and queue the URLs not saved yet, giving the new bookmarks `tags`.
+    async fn enqueue(
+        &self,
+        caller: Caller,
+        urls: Vec<ImportedUrl>,
+        tags: &[String],
+        wait: bool,
+    ) -> Result<IngestUrlsResponse, AppError> {
+        self.deps.ensure_writable()?;
+        if urls.is_empty() {
+            return Ok(self.response(0, 0));
+        }
+
+        if urls.len() > self.deps.settings().max_urls {
+            return Err(AppError::bad_request("too many urls"));
+        }
+
+        // Reserve room for every URL up front; permits left unused (dedupes) are released on drop.
+        let permits = if wait {
+            self.queue
+                .reserve_many(urls.len())
+                .await
+                .map_err(|_| anyhow::anyhow!("ingest queue closed"))?
+        } else {
+            self.queue.try_reserve_many(urls.len()).map_err(|_| {
+                AppError::unavailable(format!(
+                    "ingest queue full ({} queued); retry later",
+                    self.deps.metrics.ingest_queue_depth()
+                ))
+            })?
+        };
+        let mut permits = permits.into_iter();
+
+        // (normalized, as submitted); rows are unique on the normalized form.
+        let normalized: Vec<(String, &ImportedUrl)> = urls
+            .iter()
+            .filter_map(|imported| Some((Self::normalize_url(&imported.url)?, imported)))
+            .collect();
+        let mut inserted: HashMap<String, i64> = HashMap::with_capacity(normalized.len());
+        if !normalized.is_empty() {
+            let now = Self::now_rfc3339();
+            let mut tx = self.deps.db.begin().await?;
+            for batch in normalized.chunks(INSERT_BATCH) {
+                // Ignored rows (already saved, or repeated in the batch) return nothing.
+                let mut insert = QueryBuilder::<Sqlite>::new(
+                    "INSERT OR IGNORE INTO bookmarks (owner_id, namespace, url, original_url, title, status, created_at, updated_at) ",
+                );
+                insert.push_values(batch, |mut row, (url, imported)| {
+                    row.push_bind(caller.user_id)
+                        .push_bind(&caller.namespace)
+                        .push_bind(url)
+                        .push_bind(imported.url.trim())
+                        .push_bind(imported.title.as_deref())
+                        .push_bind("queued")
+                        .push_bind(imported.added_at.as_deref().unwrap_or(&now))
+                        .push_bind(&now);
+                });
+                insert.push(" RETURNING id, url");
+                let rows: Vec<(i64, String)> = insert.build_query_as().fetch_all(&mut *tx).await?;
+                let ids: Vec<i64> = rows.iter().map(|(id, _)| *id).collect();
+                tags::add(&mut tx, caller.user_id, &caller.namespace, &ids, tags).await?;
+                inserted.extend(rows.into_iter().map(|(id, url)| (url, id)));
+            }
+            tx.commit().await?;
+        }
+
+        // Queued in request order, once the rows are visible to the workers.
+        let accepted = inserted.len();
+        let deduped = urls.len() - accepted;
+        for (url, _) in normalized {
+            let Some(id) = inserted.remove(&url) else {
+                continue;
+            };
+            let target = IngestTarget {
+                id,
+                owner_id: caller.user_id,
+                namespace: caller.namespace.clone(),
+                url,
+                attempts: 0,
+            };
+
+            self.deps.metrics.ingest_enqueued();
+            permits
+                .next()
+                .expect("a permit was reserved per url")
+                .send(QueuedTarget {
+                    target,
+                    span: Span::current(),
+                });
+        }
+
+        Ok(self.response(accepted, deduped))
+    }
+
+    fn response(&self, accepted: usize, deduped: usize) -> IngestUrlsResponse {
+        IngestUrlsResponse {
+            accepted,
+            deduped,
+            queue_depth: self.deps.metrics.ingest_queue_depth().max(0) as usize,
+            queue_capacity: self.deps.config.ingest.queue_capacity,
+        }
+    }
+
+    /// Re-index one bookmark (or all of them), reporting each result as it lands.
+    ///
+    /// By default documents are rebuilt from stored extracted text, so a full rebuild
+    /// rewrites every document and drops those of deleted bookmarks without network
+    /// access; bookmarks with no stored text (or every bookmark, with `refetch`) are
+    /// fetched again.
+    ///
+    /// Returns the id of the job tracking the run alongside its progress stream.
+    pub async fn reindex(
+        &self,
+        owner_id: i64,
+        request: ReindexRequest,
+    ) -> Result<(i64, mpsc::Receiver<ReindexProgress>), AppError> {
+        let targets: Vec<IngestTarget> = match request.id {
+            Some(id) => sqlx::query_as(
+                "SELECT id, owner_id, namespace, url, 0 AS attempts FROM bookmarks WHERE id = ?1",
+            )
+            .bind(id)
+            .fetch_all(&self.deps.db)
+            .await?,
+            None => {
+                sqlx::query_as(
+                    "SELECT id, owner_id, namespace, url, 0 AS attempts FROM bookmarks ORDER BY id",
+                )
+                .fetch_all(&self.deps.db)
+                .await?
+            }
+        };
+        if request.id.is_some() && targets.is_empty() {
+            return Err(AppError::not_found("bookmark not found"));
+        }
+
+        let total = targets.len();
+        info!(
+            "reindex requested: {} bookmarks refetch={}",
+            total, request.refetch
+        );
+        let job = self
+            .jobs
+            .start(owner_id, JobKind::Reindex, Some(total))
+            .await?;
+        let job_id = job.id();
+        let (tx, rx) = mpsc::channel(32);
+        let service = self.clone();
+        let full_rebuild = request.id.is_none();
+
+        self.deps.tasks.spawn(async move {
+            let cancel = job.cancellation();
+            let mut reporter = ReindexReporter {
+                tx,
+                job,
+                done: 0,
+                total,
+                detached: false,
+            };
+            let targets = if request.refetch {
+                targets
+            } else {
+                match service
+                    .rebuild_from_stored(targets, full_rebuild, &mut reporter)
+                    .await
+                {
+                    Ok(missing) => missing,
+                    Err(err) => {
+                        error!("reindex from stored content failed: {:?}", err);
+                        let done = reporter.done;
+                        reporter.job.finish(done, Err(format!("{:#}", err))).await;
+                        return;
+                    }
+                }
+            };
+
+            // Fetches already started finish; no new ones begin once cancelled.
+            let mut results = futures_util::stream::iter(targets)
+                .take_until(Box::pin(cancel.cancelled_owned()))
+                .map(|target| {
+                    let service = service.clone();
+                    async move {
+                        let (id, url) = (target.id, target.url.clone());
+                        if let Err(err) = service.process_url(target).await {
+                            error!("reindex error: {:?}", err);
+                        }
+                        (id, url)
+                    }
+                })
+                .buffer_unordered(CONCURRENT_REINDEX_LIMIT);
+
+            while let Some((id, url)) = results.next().await {
+                let row: Option<(String, Option<String>)> =
+                    sqlx::query_as("SELECT status, error FROM bookmarks WHERE id = ?1")
+                        .bind(id)
+                        .fetch_optional(&service.deps.db)
+                        .await
+                        .unwrap_or_default();
+                let (status, error) = row.unwrap_or_else(|| ("deleted".to_string(), None));
+                reporter.report(id, url, status, error).await;
+            }
+            info!("reindex finished: {} bookmarks", reporter.done);
+            let done = reporter.done;
+            reporter.job.finish(done, Ok(())).await;
+        });
+
+        Ok((job_id, rx))
+    }
+
+    /// Re-add documents from `bookmark_contents`, returning the targets that have no
+    /// stored text. A full rebuild also drops documents of bookmarks that no longer exist.
+    /// Excerpts are cut again at the current `excerpt_len`.
+    ///
+    /// Text is read `REBUILD_BATCH` bookmarks at a time before the writer lock is taken,
+    /// so ingest keeps indexing meanwhile. Each document replaces the bookmark's own, so a
+    /// rebuild that stops early leaves nothing to undo: what it added is committed with the
+    /// next batch like any other document.
+    async fn rebuild_from_stored(
+        &self,
+        targets: Vec<IngestTarget>,
+        clear: bool,
+        reporter: &mut ReindexReporter,
+    ) -> anyhow::Result<Vec<IngestTarget>> {
+        let mut missing = Vec::new();
+        let mut excerpts = Vec::new();
+        let excerpt_len = self.deps.settings().excerpt_len;
+        // Read before the bookmark rows, so every id here had a row when that read began
+        // and one missing from it was deleted.
+        let indexed = if clear {
+            self.deps.index.reload().context("reload index readers")?;
+            index::bookmark_ids(&self.deps.index.searchers(), self.deps.fields.bookmark_id)
+                .context("scan index bookmark ids")?
+        } else {
+            HashSet::new()
+        };
+
+        let mut targets = targets.into_iter().peekable();
+        while targets.peek().is_some() {
+            let mut batch = Vec::with_capacity(REBUILD_BATCH);
+            for target in targets.by_ref().take(REBUILD_BATCH) {
+                if reporter.job.is_cancelled() {
+                    anyhow::bail!("reindex cancelled");
+                }
+                let Some(mut content) = self.stored_content(target.id).await? else {
+                    missing.push(target);
+                    continue;
+                };
+                let excerpt = Self::make_excerpt(&content.body, excerpt_len);
+                if excerpt != content.excerpt {
+                    content.excerpt = excerpt;
+                    excerpts.push((content.id, content.excerpt.clone()));
+                }
+                batch.push((target, self.document(&content)));
+            }
+            let mut added = Vec::with_capacity(batch.len());
+            {
+                let mut writer = self.deps.writer.lock().await;
+                for (target, document) in batch {
+                    writer.delete_bookmark(target.id);
+                    writer.add_document(target.id, document)?;
+                    added.push(target);
+                }
+            }
+            for target in added {
+                reporter
+                    .report(target.id, target.url, "indexed".to_string(), None)
+                    .await;
+            }
+        }
+
+        let orphaned = if indexed.is_empty() {
+            Vec::new()
+        } else {
+            let existing: HashSet<i64> = sqlx::query_scalar("SELECT id FROM bookmarks")
+                .fetch_all(&self.deps.db)
+                .await?
+                .into_iter()
+                .collect();
+            indexed.difference(&existing).copied().collect()
+        };
+        {
+            let mut writer = self.deps.writer.lock().await;
+            for &id in &orphaned {
+                writer.delete_bookmark(id);
+            }
+            self.deps.commit_index(&mut writer, "reindex")?;
+        }
+
+        for (id, excerpt) in excerpts {
+            sqlx::query("UPDATE bookmarks SET excerpt = ?1 WHERE id = ?2")
+                .bind(excerpt)
+                .bind(id)
+                .execute(&self.deps.db)
+                .await?;
+        }
+        Ok(missing)
+    }
+
+    /// Re-add documents for bookmarks marked indexed that are missing from the index,
+    /// from stored text, and commit them. Those without stored text are queued for a
+    /// fresh fetch while the queue has room. Returns the rebuilt and the queued ids.
+    pub(crate) async fn restore_documents(
+        &self,
+        ids: &[i64],
+    ) -> anyhow::Result<(Vec<i64>, Vec<i64>)> {
+        let mut rebuilt = Vec::new();
+        let mut unstored = Vec::new();
+        // As in a rebuild, text is read before the writer lock is taken.
+        for batch in ids.chunks(REBUILD_BATCH) {
+            let mut documents = Vec::with_capacity(batch.len());
+            for &id in batch {
+                match self.stored_content(id).await? {
+                    Some(content) => documents.push((id, self.document(&content))),
+                    None => unstored.push(id),
+                }
+            }
+            let mut writer = self.deps.writer.lock().await;
+            for (id, document) in documents {
+                writer.delete_bookmark(id);
+                writer.add_document(id, document)?;
+                rebuilt.push(id);
+            }
+        }
+        if !rebuilt.is_empty() {
+            let mut writer = self.deps.writer.lock().await;
+            self.deps.commit_index(&mut writer, "reconcile")?;
+        }
+
+        let mut queued = Vec::new();
+        for &id in &unstored {
+            // Rows deleted since the caller looked are skipped.
+            let target: Option<IngestTarget> = sqlx::query_as(
+                "SELECT id, owner_id, namespace, url, 0 AS attempts FROM bookmarks WHERE id = ?1 AND status IN ('indexed', 'partial')",
+            )
+            .bind(id)
+            .fetch_optional(&self.deps.db)
+            .await?;
+            let Some(target) = target else {
+                continue;
+            };
+            let Ok(permit) = self.queue.try_reserve() else {
+                warn!(
+                    "ingest queue full; {} bookmarks left for the next reconcile",
+                    unstored.len() - queued.len()
+                );
+                break;
+            };
+            self.deps.metrics.ingest_enqueued();
+            permit.send(QueuedTarget {
+                target,
+                span: Span::current(),
+            });
+            queued.push(id);
+        }
+        Ok((rebuilt, queued))
+    }
+
+    /// A bookmark's document as last extracted, if its text was kept.
+    async fn stored_content(&self, id: i64) -> anyhow::Result<Option<IndexedContent>> {
+        let stored: Option<StoredContent> = sqlx::query_as(
+            r#"
+            SELECT b.id, b.owner_id, b.namespace, b.url, b.title, b.excerpt,
+                   CAST(c.body AS BLOB) AS body, c.encoding,
+                   COALESCE(CAST(strftime('%s', b.fetched_at) AS INTEGER), 0) AS fetched_at
+            FROM bookmarks b
+            JOIN bookmark_contents c ON c.bookmark_id = b.id
+            WHERE b.id = ?1
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(&self.deps.db)
+        .await?;
+        let Some(stored) = stored else {
+            return Ok(None);
+        };
+        Ok(Some(IndexedContent {
+            id: stored.id,
+            owner_id: stored.owner_id,
+            namespace: stored.namespace,
+            url: stored.url,
+            title: stored.title,
+            excerpt: stored.excerpt,
+            body: content::decode(&stored.encoding, stored.body)
+                .with_context(|| format!("stored content of bookmark {}", id))?,
+            fetched_at: stored.fetched_at,
+            tags: tags::of_bookmark(&self.deps.db, id).await?,
+        }))
+    }
+
+    /// Rewrite a bookmark's document from its stored text, e.g. after its tags changed,
+    /// and commit it right away rather than with the next ingest batch. One not fetched
+    /// yet has nothing to rewrite; its fetch reads the current tags.
+    pub(crate) async fn refresh_document(&self, id: i64) -> anyhow::Result<()> {
+        let Some(content) = self.stored_content(id).await? else {
+            return Ok(());
+        };
+        let mut writer = self.deps.writer.lock().await;
+        writer.delete_bookmark(content.id);
+        writer.add_document(content.id, self.document(&content))?;
+        self.deps.commit_index(&mut writer, "refresh")?;
+        Ok(())
+    }
+
+    /// Fetch, parse, index, and persist a single URL, under a span whose children
+    /// time each stage.
+    async fn process_url(&self, target: IngestTarget) -> anyhow::Result<()> {
+        let span = info_span!("ingest_url", bookmark_id = target.id, url = %target.url);
+        self.fetch_and_index(target).instrument(span).await
+    }
+
+    /// A slot for one fetch from the URL's host, or `None` when hosts are not limited.
+    async fn host_permit(&self, url: &str) -> anyhow::Result<Option<OwnedSemaphorePermit>> {
+        let limit = self.deps.config.ingest.max_connections_per_host;
+        let host = Url::parse(url)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_ascii_lowercase));
+        let Some(host) = host.filter(|_| limit > 0) else {
+            return Ok(None);
+        };
+        let semaphore = {
+            let mut hosts = self
+                .hosts
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            if hosts.len() >= HOST_PRUNE_THRESHOLD {
+                // Only the map holds an idle host's semaphore.
+                hosts.retain(|_, semaphore| Arc::strong_count(semaphore) > 1);
+            }
+            hosts
+                .entry(host)
+                .or_insert_with(|| Arc::new(Semaphore::new(limit)))
+                .clone()
+        };
+        Ok(Some(semaphore.acquire_owned().await?))
+    }
+
+    /// The robots.txt rules for the URL's site, reused for `ingest.robots_cache_secs`. A
+    /// missing file (any 4xx) allows everything; so does one that could not be fetched,
+    /// which is not cached so the next fetch asks again.
+    async fn robots(&self, url: &Url) -> Arc<Robots> {
+        let origin = url.origin().ascii_serialization();
+        let ttl = Duration::from_secs(self.deps.config.ingest.robots_cache_secs);
+        {
+            let robots = self
+                .robots
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            if let Some(cached) = robots.get(&origin)
+                && cached.fetched_at.elapsed() < ttl
+            {
+                return cached.rules.clone();
+            }
+        }
+
+        let timeout = Duration::from_secs(self.deps.settings().fetch_timeout_secs);
+        let response = self
+            .deps
+            .http_client
+            .get(format!("{origin}/robots.txt"))
+            .timeout(timeout)
+            .send()
+            .await;
+        let rules = match response {
+            Ok(response) if response.status().is_success() => {
+                match Self::read_capped(response, MAX_ROBOTS_BYTES).await {
+                    Ok(body) => Robots::parse(&String::from_utf8_lossy(&body)),
+                    Err(err) => {
+                        warn!("robots.txt unreadable: origin={} error={}", origin, err);
+                        return Arc::new(Robots::allow_all());
+                    }
+                }
+            }
+            Ok(response) if response.status().is_client_error() => Robots::allow_all(),
+            Ok(response) => {
+                warn!(
+                    "robots.txt unavailable: origin={} http_status={}",
+                    origin,
+                    response.status()
+                );
+                return Arc::new(Robots::allow_all());
+            }
+            Err(err) => {
+                warn!("robots.txt unavailable: origin={} error={}", origin, err);
+                return Arc::new(Robots::allow_all());
+            }
+        };
+        let rules = Arc::new(rules);
+        let mut robots = self
+            .robots
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if robots.len() >= HOST_PRUNE_THRESHOLD {
+            robots.retain(|_, cached| cached.fetched_at.elapsed() < ttl);
+        }
+        robots.insert(
+            origin,
+            CachedRobots {
+                fetched_at: Instant::now(),
+                rules: rules.clone(),
+            },
+        );
+        rules
+    }
+
+    /// Wait out the spacing between fetches to the URL's host, then reserve the next
+    /// slot, so concurrent fetches to one host start `delay` apart.
+    async fn pace(&self, url: &Url, delay: Duration) {
+        let Some(host) = url.host_str().map(str::to_ascii_lowercase) else {
+            return;
+        };
+        if delay.is_zero() {
+            return;
+        }
+        let wait = {
+            let mut next_fetch = self
+                .next_fetch
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            let now = Instant::now();
+            if next_fetch.len() >= HOST_PRUNE_THRESHOLD {
+                next_fetch.retain(|_, at| *at > now);
+            }
+            let slot = next_fetch.entry(host).or_insert(now);
+            let start = (*slot).max(now);
+            *slot = start + delay;
+            start - now
+        };
+        tokio::time::sleep(wait).await;
+    }
+
+    /// Check robots.txt and wait for the host's next fetch slot. False when robots.txt
+    /// disallows the URL.
+    async fn admit(&self, url: &str) -> bool {
+        let Ok(url) = Url::parse(url) else {
+            // The fetch itself reports the bad URL.
+            return true;
+        };
+        let ingest = &self.deps.config.ingest;
+        let mut delay = Duration::from_millis(ingest.host_delay_ms);
+        if ingest.respect_robots {
+            let robots = self.robots(&url).instrument(info_span!("robots")).await;
+            if !robots.allows(&url[Position::BeforePath..Position::AfterQuery]) {
+                return false;
+            }
+            if let Some(secs) = robots.crawl_delay() {
+                delay = delay.max(Duration::from_secs_f64(secs).min(MAX_CRAWL_DELAY));
+            }
+        }
+        self.pace(&url, delay)
+            .instrument(info_span!("host_delay"))
+            .await;
+        true
+    }
+
+    async fn fetch_and_index(&self, target: IngestTarget) -> anyhow::Result<()> {
+        let IngestTarget {
+            id,
+            owner_id,
+            ref namespace,
+            ref url,
+            attempts,
+        } = target;
+        let start = std::time::Instant::now();
+        info!("ingest start: {}", url);
+        // The host slot comes first, so fetches waiting on a busy host do not hold
+        // permits other hosts could use.
+        let _host_permit = self
+            .host_permit(url)
+            .instrument(info_span!("host_permit"))
+            .await?;
+        if !self.admit(url).await {
+            self.mark_failed(&target, 0, "", BLOCKED_BY_ROBOTS).await?;
+            self.deps
+                .metrics
+                .record_ingest("failed", "robots_disallowed");
+            info!(
+                "ingest end: {} status=failed reason=robots_disallowed elapsed_ms={}",
+                url,
+                start.elapsed().as_millis()
+            );
+            return Ok(());
+        }
+        let _permit = self
+            .deps
+            .fetch_semaphore
+            .acquire()
+            .instrument(info_span!("fetch_permit"))
+            .await?;
+
+        let timeout = Duration::from_secs(self.deps.settings().fetch_timeout_secs);
+        let fetch_start = std::time::Instant::now();
+        let response = match self
+            .deps
+            .http_client
+            .get(url)
+            .timeout(timeout)
+            .send()
+            .instrument(info_span!("fetch"))
+            .await
+        {
+            Ok(response) => response,
+            Err(err) => {
+                self.domain_stats
+                    .record(url, fetch_start.elapsed(), 0, true)
+                    .await;
+                // Only a request that could not even be built is not worth retrying.
+                let outcome = self
+                    .fail_or_retry(
+                        &target,
+                        0,
+                        "",
+                        &Self::truncate_error(&err.to_string()),
+                        !err.is_builder(),
+                    )
+                    .await?;
+                self.deps.metrics.record_ingest(outcome, "request_error");
+                info!(
+                    "ingest end: {} status={} reason=request_error elapsed_ms={}",
+                    url,
+                    outcome,
+                    start.elapsed().as_millis()
+                );
+
+                return Ok(());
+            }
+        };
+
+        let status = response.status();
+        let http_status = status.as_u16();
+        // Where redirects ended; the bookmark moves there once the page is read.
+        let final_url = response.url().clone();
+        let content_type = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_string())
+            .unwrap_or_default();
+
+        let max_page_bytes = self.deps.config.ingest.max_page_bytes;
+        let body = match Self::read_capped(response, max_page_bytes)
+            .instrument(info_span!("fetch_body"))
+            .await
+        {
+            Ok(body) => body,
+            Err(err) => {
+                self.domain_stats
+                    .record(url, fetch_start.elapsed(), 0, true)
+                    .await;
+                let outcome = self
+                    .fail_or_retry(
+                        &target,
+                        http_status,
+                        &content_type,
+                        &Self::truncate_error(&err.to_string()),
+                        true,
+                    )
+                    .await?;
+                self.deps.metrics.record_ingest(outcome, "read_body_error");
+                info!(
+                    "ingest end: {} status={} reason=read_body_error error={} elapsed_ms={}",
+                    url,
+                    outcome,
+                    err,
+                    start.elapsed().as_millis()
+                );
+                return Ok(());
+            }
+        };
+        self.domain_stats
+            .record(url, fetch_start.elapsed(), body.len(), !status.is_success())
+            .await;
+
+        if !status.is_success() {
+            let mut message = format!("http error: {}", status);
+            if let Some(preview) = Self::body_preview(&body) {
+                message.push_str(&format!(" body_preview={}", preview));
+            }
+            let transient = status == reqwest::StatusCode::REQUEST_TIMEOUT
+                || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+                || status.is_server_error();
+            let outcome = self
+                .fail_or_retry(
+                    &target,
+                    http_status,
+                    &content_type,
+                    &Self::truncate_error(&message),
+                    transient,
+                )
+                .await?;
+            self.deps.metrics.record_ingest(outcome, "http_error");
+            info!(
+                "ingest end: {} status={} reason=http_error http_status={} elapsed_ms={}",
+                url,
+                outcome,
+                http_status,
+                start.elapsed().as_millis()
+            );
+            return Ok(());
+        }
+
+        let Some(document_type) = Self::document_type(&content_type, &body) else {
+            self.mark_failed(
+                &target,
+                http_status,
+                &content_type,
+                "unsupported content type",
+            )
+            .await?;
+            self.deps
+                .metrics
+                .record_ingest("failed", "unsupported_content_type");
+            info!(
+                "ingest end: {} status=failed reason=unsupported_content_type content_type={} elapsed_ms={}",
+                url,
+                content_type,
+                start.elapsed().as_millis()
+            );
+            return Ok(());
+        };
+
+        // Parsing a multi-MB page takes long enough to stall the executor, so it runs on
+        // a blocking thread. A page over the budget is failed; its thread cannot be
+        // stopped and finishes in the background.
+        let excerpt_len = self.deps.settings().excerpt_len;
+        let limits = ExtractLimits {
+            page_truncated: body.len() >= max_page_bytes,
+            max_dom_nodes: self.deps.config.ingest.max_dom_nodes,
+            max_text_chars: self.deps.config.ingest.max_text_chars,
+        };
+        let archive_pages = self.deps.config.storage.archive_pages;
+        let span = info_span!("extract");
+        let extraction = tokio::task::spawn_blocking(move || {
+            span.in_scope(|| {
+                let archive = archive_pages
+                    .then(|| content::compress_bytes(&body))
+                    .transpose();
+                let (title, body, mut truncated, canonical) = match document_type {
+                    DocumentType::Html => {
+                        let html = String::from_utf8_lossy(&body).to_string();
+                        Self::extract_text(&html, &limits)
+                    }
+                    DocumentType::Pdf => (None, Self::extract_pdf(&body)?, None, None),
+                    DocumentType::Text => {
+                        let text = String::from_utf8_lossy(&body).to_string();
+                        (Self::markdown_title(&text), text, None, None)
+                    }
+                };
+                let mut cleaned = Self::clean_text(&body);
+                if let Some((end, _)) = cleaned.char_indices().nth(limits.max_text_chars) {
+                    cleaned.truncate(end);
+                    truncated = Some(TRUNCATED_TEXT);
+                }
+                if limits.page_truncated {
+                    truncated = Some(TRUNCATED_PAGE);
+                }
+                let excerpt = Self::make_excerpt(&cleaned, excerpt_len);
+                let content_hash =
+                    (!cleaned.is_empty()).then(|| hex::encode(Sha256::digest(cleaned.as_bytes())));
+                Ok::<_, String>(Extracted {
+                    title,
+                    body: cleaned,
+                    excerpt,
+                    truncated,
+                    archive,
+                    canonical,
+                    content_hash,
+                })
+            })
+        });
+        let budget = Duration::from_secs(self.deps.config.ingest.extract_timeout_secs);
+        let Extracted {
+            title,
+            body: cleaned,
+            excerpt,
+            truncated,
+            archive,
+            canonical,
+            content_hash,
+        } = match tokio::time::timeout(budget, extraction).await {
+            Ok(extracted) => match extracted.context("text extraction panicked")? {
+                Ok(extracted) => extracted,
+                Err(message) => {
+                    self.mark_failed(&target, http_status, &content_type, &message)
+                        .await?;
+                    self.deps.metrics.record_ingest("failed", "extract_error");
+                    info!(
+                        "ingest end: {} status=failed reason=extract_error document_type={} elapsed_ms={}",
+                        url,
+                        document_type.as_str(),
+                        start.elapsed().as_millis()
+                    );
+                    return Ok(());
+                }
+            },
+            Err(_) => {
+                self.mark_failed(
+                    &target,
+                    http_status,
+                    &content_type,
+                    "page took too long to parse",
+                )
+                .await?;
+                self.deps.metrics.record_ingest("failed", "extract_timeout");
+                info!(
+                    "ingest end: {} status=failed reason=extract_timeout elapsed_ms={}",
+                    url,
+                    start.elapsed().as_millis()
+                );
+                return Ok(());
+            }
+        };
+        let archive = archive?;
+        // A page without a title keeps the one its import gave, if any.
+        let title = match title {
+            Some(title) => Some(title),
+            None => {
+                sqlx::query_scalar::<_, Option<String>>("SELECT title FROM bookmarks WHERE id = ?1")
+                    .bind(id)
+                    .fetch_optional(&self.deps.db)
+                    .await?
+                    .flatten()
+            }
+        };
+        // PDFs and text files rarely carry a usable title; fall back to the file name.
+        let title = title.or_else(|| match document_type {
+            DocumentType::Html => None,
+            DocumentType::Pdf | DocumentType::Text => Self::file_name(url),
+        });
+
+        let resolved = Self::resolved_url(&final_url, canonical.as_deref());
+        let merge_hash = content_hash
+            .as_deref()
+            .filter(|_| cleaned.chars().count() >= MIN_MERGE_TEXT_CHARS);
+        let url = match self.settle(&target, resolved, merge_hash).await? {
+            Settled::Kept(url) => url,
+            Settled::Merged(into) => {
+                self.deps.metrics.record_ingest("merged", "duplicate");
+                info!(
+                    "ingest end: {} status=merged into={} elapsed_ms={}",
+                    url,
+                    into,
+                    start.elapsed().as_millis()
+                );
+                return Ok(());
+            }
+        };
+
+        let content = IndexedContent {
+            id,
+            owner_id,
+            namespace: namespace.clone(),
+            url: url.clone(),
+            title,
+            excerpt,
+            body: cleaned,
+            fetched_at: OffsetDateTime::now_utc().unix_timestamp(),
+            tags: tags::of_bookmark(&self.deps.db, id).await?,
+        };
+        if let Err(err) = self
+            .index_document(&content)
+            .instrument(info_span!("index"))
+            .await
+        {
+            self.mark_failed(&target, http_status, &content_type, &err.to_string())
+                .await?;
+            self.deps.metrics.record_ingest("failed", "index_error");
+            info!(
+                "ingest end: {} status=failed reason=index_error error={} elapsed_ms={}",
+                url,
+                err,
+                start.elapsed().as_millis()
+            );
+            return Ok(());
+        }
+
+        if let Err(err) = self
+            .persist_indexed(
+                &content,
+                attempts + 1,
+                FetchedPage {
+                    http_status,
+                    content_type: &content_type,
+                    document_type,
+                    content_hash: content_hash.as_deref(),
+                },
+                truncated,
+                archive.as_deref(),
+            )
+            .instrument(info_span!("persist"))
+            .await
+        {
+            self.deps.metrics.record_ingest("failed", "db_update_error");
+            info!(
+                "ingest end: {} status=failed reason=db_update_error error={} elapsed_ms={}",
+                url,
+                err,
+                start.elapsed().as_millis()
+            );
+            return Ok(());
+        }
+
+        let status = if truncated.is_some() {
+            "partial"
+        } else {
+            "indexed"
+        };
+        self.deps.metrics.record_ingest(status, "ok");
+        self.deps.publish(BookmarkEvent {
+            title: content.title,
+            ..BookmarkEvent::new(BookmarkEventKind::Indexed, id, owner_id, namespace, &url)
+        });
+        info!(
+            "ingest end: {} status={} http_status={} elapsed_ms={}",
+            url,
+            status,
+            http_status,
+            start.elapsed().as_millis()
+        );
+        Ok(())
+    }
+
+    /// Where a fetched page lives: its `rel="canonical"` link when that stays on the
+    /// site redirects ended at, otherwise the final URL; normalized.
+    fn resolved_url(final_url: &Url, canonical: Option<&str>) -> Option<String> {
+        let site = Self::site;
+        let canonical = canonical
+            .and_then(|href| final_url.join(href).ok())
+            .filter(|canonical| matches!(canonical.scheme(), "http" | "https"))
+            .filter(|canonical| site(canonical).is_some() && site(canonical) == site(final_url));
+        Self::normalize_url(canonical.as_ref().unwrap_or(final_url).as_str())
+    }
+
+    /// Move a bookmark to the URL its fetch resolved to. On its first fetch, a bookmark
+    /// whose resolved URL matches another in its namespace, or whose extracted text
+    /// matches one on the same host, is merged into that one instead. Only bookmarks
+    /// whose last fetch succeeded take merges, and a bookmark that was fetched before,
+    /// including one that failed or is being retried, never merges, so a refetch cannot
+    /// delete one.
+    async fn settle(
+        &self,
+        target: &IngestTarget,
+        resolved: Option<String>,
+        content_hash: Option<&str>,
+    ) -> anyhow::Result<Settled> {
+        let resolved = resolved.filter(|resolved| *resolved != target.url);
+        let first_fetch: Option<bool> =
+            sqlx::query_scalar("SELECT fetched_at IS NULL FROM bookmarks WHERE id = ?1")
+                .bind(target.id)
+                .fetch_optional(&self.deps.db)
+                .await?;
+        if first_fetch == Some(true) && (resolved.is_some() || content_hash.is_some()) {
+            let candidates: Vec<(i64, String)> = sqlx::query_as(
+                r#"
+                SELECT id, url FROM bookmarks
+                WHERE owner_id = ?1 AND namespace = ?2 AND id != ?3
+                    AND (url = ?4 OR content_hash = ?5) AND status IN ('indexed', 'partial')
+                ORDER BY url = ?4 DESC, id
+                "#,
+            )
+            .bind(target.owner_id)
+            .bind(&target.namespace)
+            .bind(target.id)
+            .bind(resolved.as_deref())
+            .bind(content_hash)
+            .fetch_all(&self.deps.db)
+            .await?;
+            let site = |url: &str| Url::parse(url).ok().as_ref().and_then(Self::site);
+            let host = site(resolved.as_deref().unwrap_or(&target.url));
+            let duplicate = candidates.into_iter().find(|(_, url)| {
+                resolved.as_deref() == Some(url.as_str()) || (host.is_some() && site(url) == host)
+            });
+            if let Some((into, _)) = duplicate {
+                self.merge(target, into).await?;
+                return Ok(Settled::Merged(into));
+            }
+        }
+
+        let Some(resolved) = resolved else {
+            return Ok(Settled::Kept(target.url.clone()));
+        };
+        // A bookmark that already has the URL keeps it; this one keeps its own.
+        let moved = sqlx::query("UPDATE OR IGNORE bookmarks SET url = ?1 WHERE id = ?2")
+            .bind(&resolved)
+            .bind(target.id)
+            .execute(&self.deps.db)
+            .await?
+            .rows_affected()
+            > 0;
+        Ok(Settled::Kept(if moved {
+            resolved
+        } else {
+            target.url.clone()
+        }))
+    }
+
+    /// A URL's host without a leading `www.`, for telling whether two pages share a site.
+    fn site(url: &Url) -> Option<String> {
+        url.host_str()
+            .map(|host| host.trim_start_matches("www.").to_string())
+    }
+
+    /// Fold a just-fetched duplicate into `into`: its tags join that bookmark's, as many
+    /// as fit, the URL it was submitted as becomes an alias lookups find `into` by, and
+    /// it is purged like any deleted bookmark.
+    async fn merge(&self, target: &IngestTarget, into: i64) -> anyhow::Result<()> {
+        let tags = tags::room_for(
+            &tags::of_bookmark(&self.deps.db, into).await?,
+            tags::of_bookmark(&self.deps.db, target.id).await?,
+        );
+        let mut tx = self.deps.db.begin().await?;
+        tags::add(&mut tx, target.owner_id, &target.namespace, &[into], &tags).await?;
+        sqlx::query(
+            r#"
+            INSERT OR IGNORE INTO bookmark_aliases (bookmark_id, url, original_url)
+            SELECT ?1, url, COALESCE(original_url, url) FROM bookmarks WHERE id = ?2
+            "#,
+        )
+        .bind(into)
+        .bind(target.id)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        self.purge(
+            target.owner_id,
+            &target.namespace,
+            vec![(target.id, target.url.clone())],
+            "merge",
+        )
+        .await?;
+        if !tags.is_empty() {
+            self.refresh_document(into).await?;
+        }
+        Ok(())
+    }
+
+    /// Remove bookmarks of an owner's namespace and everything kept for them, returning how many
+    /// were removed. Index documents go first, so a failure leaves rows the reconciler
+    /// re-indexes rather than documents nothing refers to; the rows and their artifacts
+    /// go in one transaction. Stored text and Wayback snapshots follow their bookmark
+    /// through `ON DELETE CASCADE`; tables without a foreign key are cleared here.
+    pub(crate) async fn purge(
+        &self,
+        owner_id: i64,
+        namespace: &str,
+        found: Vec<(i64, String)>,
+        operation: &str,
+    ) -> Result<usize, AppError> {
+        if found.is_empty() {
+            return Ok(0);
+        }
+        {
+            let mut writer = self.deps.writer.lock().await;
+            for (id, _) in &found {
+                writer.delete_bookmark(*id);
+            }
+            self.deps.commit_index(&mut writer, operation)?;
+        }
+
+        let mut tx = self.deps.db.begin().await?;
+        let mut removed = Vec::with_capacity(found.len());
+        for (id, url) in found {
+            let result = sqlx::query(
+                "DELETE FROM bookmarks WHERE id = ?1 AND owner_id = ?2 AND namespace = ?3",
+            )
+            .bind(id)
+            .bind(owner_id)
+            .bind(namespace)
+            .execute(&mut *tx)
+            .await?;
+            if result.rows_affected() == 0 {
+                continue;
+            }
+            let was_public = sqlx::query("DELETE FROM public_bookmarks WHERE bookmark_id = ?1")
+                .bind(id)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected()
+                > 0;
+            removed.push((id, url, was_public));
+        }
+        tx.commit().await?;
+
+        self.maintenance.record_deletes(removed.len());
+        let deleted = removed.len();
+        for (id, url, was_public) in removed {
+            let mut event =
+                BookmarkEvent::new(BookmarkEventKind::Deleted, id, owner_id, namespace, url);
+            event.was_public = was_public;
+            self.deps.publish(event);
+        }
+        Ok(deleted)
+    }
+
+    /// Write the fetched document into the Tantivy index; the indexer commits it with
+    /// the rest of its batch.
+    async fn index_document(&self, content: &IndexedContent) -> anyhow::Result<()> {
+        self.indexer.add(content.id, self.document(content)).await
+    }
+
+    fn document(&self, content: &IndexedContent) -> TantivyDocument {
+        let mut document = doc!(
+            self.deps.fields.bookmark_id => content.id as u64,
+            self.deps.fields.owner_id => content.owner_id as u64,
+            self.deps.fields.namespace => content.namespace.as_str(),
+            self.deps.fields.url => content.url.as_str(),
+            self.deps.fields.title => content.title.clone().unwrap_or_default(),
+            self.deps.fields.body => content.body.as_str(),
+            self.deps.fields.excerpt => content.excerpt.clone().unwrap_or_default(),
+            self.deps.fields.fetched_at => content.fetched_at,
+        );
+        for host in index::host_terms(&content.url) {
+            document.add_text(self.deps.fields.host, &host);
+        }
+        for tag in &content.tags {
+            document.add_text(self.deps.fields.tag, tag);
+        }
+        document
+    }
+
+    /// Record a successful fetch and keep the extracted text for index rebuilds, and the
+    /// compressed page when `storage.archive_pages` is on. A truncated page is stored as
+    /// `partial`, with the reason in `error`.
+    async fn persist_indexed(
+        &self,
+        content: &IndexedContent,
+        attempts: i64,
+        page: FetchedPage<'_>,
+        truncated: Option<&str>,
+        archive: Option<&[u8]>,
+    ) -> anyhow::Result<()> {
+        let now = Self::now_rfc3339();
+        let status = if truncated.is_some() {
+            "partial"
+        } else {
+            "indexed"
+        };
+        let mut tx = self.deps.db.begin().await?;
+        sqlx::query(
+            r#"
+            UPDATE bookmarks
+            SET title = ?1, excerpt = ?2, status = ?7, http_status = ?3, content_type = ?4, error = ?8,
+                attempts = ?9, next_attempt_at = NULL, updated_at = ?5, fetched_at = ?5, indexed_at = ?5,
+                document_type = ?10, content_hash = ?11
+            WHERE id = ?6
+            "#,
+        )
+        .bind(content.title.as_deref())
+        .bind(content.excerpt.as_deref())
+        .bind(page.http_status)
+        .bind(page.content_type)
+        .bind(&now)
+        .bind(content.id)
+        .bind(status)
+        .bind(truncated)
+        .bind(attempts)
+        .bind(page.document_type.as_str())
+        .bind(page.content_hash)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query(
+            r#"
+            INSERT INTO bookmark_contents (bookmark_id, body, encoding)
+            SELECT id, ?2, ?3 FROM bookmarks WHERE id = ?1
+            ON CONFLICT(bookmark_id) DO UPDATE SET body = excluded.body, encoding = excluded.encoding
+            "#,
+        )
+        .bind(content.id)
+        .bind(content::compress(&content.body)?)
+        .bind(content::ZLIB)
+        .execute(&mut *tx)
+        .await?;
+        if let Some(archive) = archive {
+            sqlx::query(
+                r#"
+                INSERT INTO bookmark_archives (bookmark_id, body, encoding, content_type, fetched_at)
+                SELECT id, ?2, ?3, ?4, ?5 FROM bookmarks WHERE id = ?1
+                ON CONFLICT(bookmark_id) DO UPDATE SET body = excluded.body,
+                    encoding = excluded.encoding, content_type = excluded.content_type,
+                    fetched_at = excluded.fetched_at
+                "#,
+            )
+            .bind(content.id)
+            .bind(archive)
+            .bind(content::ZLIB)
+            .bind(page.content_type)
+            .bind(&now)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Put a bookmark back in the queue after a transient failure, waiting
+    /// `retry_backoff_secs` doubled per earlier fetch; other failures, and the last of
+    /// `ingest.max_attempts`, mark it failed. Returns the outcome for logs and metrics.
+    async fn fail_or_retry(
+        &self,
+        target: &IngestTarget,
+        http_status: u16,
+        content_type: &str,
+        error: &str,
+        transient: bool,
+    ) -> anyhow::Result<&'static str> {
+        let ingest = &self.deps.config.ingest;
+        let attempts = target.attempts + 1;
+        if !transient || attempts >= i64::from(ingest.max_attempts) {
+            self.mark_failed(target, http_status, content_type, error)
+                .await?;
+            return Ok("failed");
+        }
+
+        let delay = Self::retry_delay(
+            attempts,
+            ingest.retry_backoff_secs,
+            ingest.retry_max_backoff_secs,
+        );
+        let now = OffsetDateTime::now_utc();
+        sqlx::query(
+            r#"
+            UPDATE bookmarks
+            SET status = 'queued', http_status = ?1, content_type = ?2, error = ?3, attempts = ?4,
+                next_attempt_at = ?5, updated_at = ?6, fetched_at = ?6
+            WHERE id = ?7
+            "#,
+        )
+        .bind(http_status)
+        .bind(content_type)
+        .bind(error)
+        .bind(attempts)
+        .bind((now + delay).format(&Rfc3339)?)
+        .bind(now.format(&Rfc3339)?)
+        .bind(target.id)
+        .execute(&self.deps.db)
+        .await?;
+        info!(
+            "ingest retry scheduled: {} attempts={} delay_secs={}",
+            target.url,
+            attempts,
+            delay.as_secs()
+        );
+        self.requeue_after(
+            IngestTarget {
+                attempts,
+                ..target.clone()
+            },
+            delay,
+        );
+        Ok("retrying")
+    }
+
+    /// The wait after the `attempts`th fetch: `base` doubled per earlier fetch, up to `max`.
+    fn retry_delay(attempts: i64, base: u64, max: u64) -> Duration {
+        let doublings = (attempts - 1).clamp(0, 32) as u32;
+        Duration::from_secs(base.saturating_mul(1 << doublings).min(max))
+    }
+
+    /// Queue a bookmark again once `delay` has passed. Shutdown abandons the wait; the
+    /// row stays `queued` and [`Self::resume`] picks it up on the next start.
+    fn requeue_after(&self, target: IngestTarget, delay: Duration) {
+        let service = self.clone();
+        self.deps.tasks.spawn(async move {
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                _ = service.deps.shutdown.cancelled() => return,
+            }
+            service.requeue(target).await;
+        });
+    }
+
+    /// Send a bookmark that is still `queued` to the workers, waiting for queue space.
+    /// Returns false once shutdown begins.
+    async fn requeue(&self, target: IngestTarget) -> bool {
+        // Rows deleted, or fetched by a reindex, since they were scheduled are skipped.
+        let current: Option<IngestTarget> = match sqlx::query_as(
+            "SELECT id, owner_id, namespace, url, attempts FROM bookmarks WHERE id = ?1 AND status = 'queued'",
+        )
+        .bind(target.id)
+        .fetch_optional(&self.deps.db)
+        .await
+        {
+            Ok(current) => current,
+            Err(err) => {
+                warn!("failed to reload queued bookmark {}: {:?}", target.id, err);
+                return true;
+            }
+        };
+        let Some(target) = current else {
+            return true;
+        };
+        let permit = tokio::select! {
+            permit = self.queue.reserve() => permit,
+            _ = self.deps.shutdown.cancelled() => return false,
+        };
+        let Ok(permit) = permit else {
+            return false;
+        };
+        self.deps.metrics.ingest_enqueued();
+        permit.send(QueuedTarget {
+            target,
+            span: Span::current(),
+        });
+        true
+    }
+
+    /// Mark a bookmark as failed with the provided HTTP and error details.
+    async fn mark_failed(
+        &self,
+        target: &IngestTarget,
+        http_status: u16,
+        content_type: &str,
+        error: &str,
+    ) -> anyhow::Result<()> {
+        let now = Self::now_rfc3339();
+        sqlx::query(
+            r#"
+            UPDATE bookmarks
+            SET status = 'failed', http_status = ?1, content_type = ?2, error = ?3, attempts = ?6,
+                next_attempt_at = NULL, updated_at = ?4, fetched_at = ?4
+            WHERE id = ?5
+            "#,
+        )
+        .bind(http_status)
+        .bind(content_type)
+        .bind(error)
+        .bind(&now)
+        .bind(target.id)
+        .bind(target.attempts + 1)
+        .execute(&self.deps.db)
+        .await?;
+        self.deps.publish(BookmarkEvent {
+            error: Some(error.to_string()),
+            ..BookmarkEvent::new(
+                BookmarkEventKind::Failed,
+                target.id,
+                target.owner_id,
+                &target.namespace,
+                &target.url,
+            )
+        });
+        Ok(())
+    }
+
+    /// Read a response body, stopping once it reaches `max_bytes`.
+    pub(crate) async fn read_capped(
+        mut response: reqwest::Response,
+        max_bytes: usize,
+    ) -> reqwest::Result<Vec<u8>> {
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            let room = max_bytes - body.len();
+            body.extend_from_slice(&chunk[..chunk.len().min(room)]);
+            if body.len() >= max_bytes {
+                break;
+            }
+        }
+        Ok(body)
+    }
+
+    /// Extract a best-effort title and raw body text from HTML. A DOM over
+    /// `max_dom_nodes` skips html2text, whose layout pass is the expensive part, for its
+    /// bare text nodes, and says so in the third value.
+    fn extract_text(
+        html: &str,
+        limits: &ExtractLimits,
+    ) -> (Option<String>, String, Option<&'static str>, Option<String>) {
+        let document = Html::parse_document(html);
+        let title = Self::extract_title(&document);
+        let canonical_selector = Selector::parse(r#"link[rel~="canonical"][href]"#).unwrap();
+        let canonical = document
+            .select(&canonical_selector)
+            .next()
+            .and_then(|node| node.value().attr("href"))
+            .map(|href| href.trim().to_string())
+            .filter(|href| !href.is_empty());
+        if document.tree.nodes().len() > limits.max_dom_nodes {
+            let body = document
+                .tree
+                .nodes()
+                .filter_map(|node| {
+                    let text = node.value().as_text()?;
+                    let parent = node.parent()?.value().as_element()?.name();
+                    (!matches!(parent, "script" | "style" | "noscript" | "template"))
+                        .then_some(&**text)
+                })
+                .collect::<Vec<_>>()
+                .join(" ");
+            return (title, body, Some(TRUNCATED_DOM), canonical);
+        }
+        let body = html2text::from_read(html.as_bytes(), 80);
+
+        (title, body, None, canonical)
+    }
+
+    /// Prefer OpenGraph/H1/title metadata for the page title.
+    fn extract_title(document: &Html) -> Option<String> {
+        let og_title_selector = Selector::parse(r#"meta[property="og:title"]"#).unwrap();
+        let h1_selector = Selector::parse("h1").unwrap();
+        let title_selector = Selector::parse("title").unwrap();
+
+        let candidates = [
+            Self::select_meta_content(document, &og_title_selector),
+            Self::select_text(document, &h1_selector),
+            Self::select_text(document, &title_selector).and_then(|t| Self::trim_site_suffix(&t)),
+        ];
+
+        candidates.into_iter().flatten().next()
+    }
+
+    /// Read a meta tag's `content` attribute and normalize whitespace.
+    fn select_meta_content(document: &Html, selector: &Selector) -> Option<String> {
+        document
+            .select(selector)
+            .next()
+            .and_then(|node| node.value().attr("content"))
+            .map(|t| t.trim().to_string())
+            .filter(|t| !t.is_empty())
+    }
+
+    /// Read the first matching node's text content and normalize whitespace.
+    fn select_text(document: &Html, selector: &Selector) -> Option<String> {
+        document
+            .select(selector)
+            .next()
+            .map(|node| node.text().collect::<Vec<_>>().join(" "))
+            .map(|t| t.trim().to_string())
+            .filter(|t| !t.is_empty())
+    }
+
+    /// Remove common site-name suffixes while preserving short titles.
+    fn trim_site_suffix(title: &str) -> Option<String> {
+        let trimmed = title.trim();
+        if trimmed.is_empty() {
+            return None;
+        }
+
+        for delimiter in [" — ", " | ", " - ", " · "] {
+            if let Some((left, _)) = trimmed.split_once(delimiter) {
+                let left = left.trim();
+                if left.len() >= 6 {
+                    return Some(left.to_string());
+                }
+            }
+        }
+
+        Some(trimmed.to_string())
+    }
+
+    /// Collapse whitespace runs and trim the output.
+    fn clean_text(input: &str) -> String {
+        let mut out = String::with_capacity(input.len());
+        let mut prev_space = false;
+        for ch in input.chars() {
+            if ch.is_whitespace() {
+                if !prev_space {
+                    out.push(' ');
+                    prev_space = true;
+                }
+            } else {
+                prev_space = false;
+                out.push(ch);
+            }
+        }
+        out.trim().to_string()
+    }
+
+    /// Build a short excerpt for display or error contexts.
+    fn make_excerpt(text: &str, max_len: usize) -> Option<String> {
+        if text.is_empty() {
+            None
+        } else {
+            Some(
+                text.chars()
+                    .take(max_len)
+                    .chain(std::iter::once('…'))
+                    .collect::<String>(),
+            )
+        }
+    }
+
+    /// Trim and normalize a URL string, stripping fragments.
+    pub fn normalize_url(raw: &str) -> Option<String> {
+        let trimmed = raw.trim();
+        if trimmed.is_empty() {
+            return None;
+        }
+        // Parsing lowercases the host and drops the scheme's default port.
+        let mut url = Url::parse(trimmed).ok()?;
+        url.set_fragment(None);
+        // Re-encoding can change a query, so one without tracking parameters is left as is.
+        if url
+            .query_pairs()
+            .any(|(key, _)| Self::is_tracking_param(&key))
+        {
+            let kept: Vec<(String, String)> = url
+                .query_pairs()
+                .filter(|(key, _)| !Self::is_tracking_param(key))
+                .map(|(key, value)| (key.into_owned(), value.into_owned()))
+                .collect();
+            if kept.is_empty() {
+                url.set_query(None);
+            } else {
+                url.query_pairs_mut().clear().extend_pairs(kept);
+            }
+        } else if url.query() == Some("") {
+            url.set_query(None);
+        }
+        if url.path().len() > 1 && url.path().ends_with('/') {
+            let path = url.path().trim_end_matches('/').to_string();
+            url.set_path(if path.is_empty() { "/" } else { &path });
+        }
+        Some(url.to_string())
+    }
+
+    /// Query parameters that only say where a link was shared, never which page it is.
+    fn is_tracking_param(key: &str) -> bool {
+        let key = key.to_ascii_lowercase();
+        key.starts_with("utm_") || TRACKING_PARAMS.contains(&key.as_str())
+    }
+
+    /// Return the current UTC timestamp in RFC 3339 format.
+    fn now_rfc3339() -> String {
+        OffsetDateTime::now_utc()
+            .format(&Rfc3339)
+            .expect("failed to format timestamp")
+    }
+
+    /// How to extract a body, or `None` when odin cannot index it. Plain text that
+    /// looks like HTML is parsed as HTML, and untyped bodies are sniffed.
+    fn document_type(content_type: &str, body: &[u8]) -> Option<DocumentType> {
+        let ct = content_type.trim().to_ascii_lowercase();
+        let mime = ct.split(';').next().unwrap_or_default().trim();
+        match mime {
+            "text/html" | "application/xhtml+xml" => Some(DocumentType::Html),
+            "application/pdf" | "application/x-pdf" => Some(DocumentType::Pdf),
+            "" | "application/octet-stream" | "binary/octet-stream"
+                if body.starts_with(b"%PDF-") =>
+            {
+                Some(DocumentType::Pdf)
+            }
+            "" | "application/octet-stream" | "binary/octet-stream" | "text/plain"
+                if Self::looks_like_html(body) =>
+            {
+                Some(DocumentType::Html)
+            }
+            "text/plain" | "text/markdown" | "text/x-markdown" => Some(DocumentType::Text),
+            _ => None,
+        }
+    }
+
+    /// Text of a PDF. pdf-extract panics on some malformed files instead of returning
+    /// an error, so both become a failure reason.
+    fn extract_pdf(body: &[u8]) -> Result<String, String> {
+        match std::panic::catch_unwind(|| pdf_extract::extract_text_from_mem(body)) {
+            Ok(Ok(text)) => Ok(text),
+            Ok(Err(err)) => Err(Self::truncate_error(&format!("unreadable pdf: {}", err))),
+            Err(_) => Err("unreadable pdf".to_string()),
+        }
+    }
+
+    /// The first Markdown heading of a text file, if it opens with one.
+    fn markdown_title(text: &str) -> Option<String> {
+        let first = text.lines().map(str::trim).find(|line| !line.is_empty())?;
+        let heading = first.strip_prefix('#')?.trim_start_matches('#').trim();
+        (!heading.is_empty()).then(|| heading.to_string())
+    }
+
+    /// The last path segment of a URL, e.g. `paper.pdf`.
+    fn file_name(url: &str) -> Option<String> {
+        let url = Url::parse(url).ok()?;
+        url.path_segments()?
+            .rev()
+            .find(|segment| !segment.is_empty())
+            .map(str::to_string)
+    }
+
+    /// Heuristically detect HTML from a short body prefix.
+    fn looks_like_html(body: &[u8]) -> bool {
+        let prefix = &body[..body.len().min(512)];
+        let s = String::from_utf8_lossy(prefix);
+        let trimmed = s
+            .trim_start_matches(|c: char| c.is_whitespace() || c == '\u{feff}')
+            .to_ascii_lowercase();
+
+        [
+            "<!doctype",
+            "<html",
+            "<head",
+            "<body",
+            "<meta",
+            "<title",
+            "<",
+        ]
+        .iter()
+        .any(|prefix| trimmed.starts_with(prefix))
+    }
+
+    /// Build a compact, human-readable preview for error messages.
+    fn body_preview(body: &[u8]) -> Option<String> {
+        let prefix = &body[..body.len().min(600)];
+        let s = String::from_utf8_lossy(prefix);
+        let mut out = Self::clean_text(&s);
+        if out.is_empty() {
+            return None;
+        }
+        if out.chars().count() > 240 {
+            out = out.chars().take(240).collect::<String>();
+            out.push('…');
+        }
+        Some(out)
+    }
+
+    /// Truncate long error messages for storage.
+    fn truncate_error(message: &str) -> String {
+        const MAX_CHARS: usize = 900;
+
+        if message.chars().count() <= MAX_CHARS {
+            message.to_string()
+        } else {
+            message
+                .chars()
+                .take(MAX_CHARS)
+                .chain(std::iter::once('…'))
+                .collect::<String>()
+        }
+    }
+}
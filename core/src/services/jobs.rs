@@ -0,0 +1,261 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use crate::errors::AppError;
+use crate::types::{Caller, Dependencies, JobItem, JobKind, JobState, JobsResponse, Scope};
+
+/// Progress is written at most this often; the final state is always written.
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+const LIST_LIMIT: i64 = 100;
+
+type Registry = Arc<std::sync::Mutex<HashMap<i64, CancellationToken>>>;
+
+/// Long-running operations (imports, reindexes, backups) recorded in `jobs` so
+/// clients can follow and cancel them from any connection.
+#[derive(Clone)]
+pub struct JobService {
+    deps: Arc<Dependencies>,
+    running: Registry,
+}
+
+impl JobService {
+    pub fn new(deps: Arc<Dependencies>) -> Self {
+        Self {
+            deps,
+            running: Arc::new(std::sync::Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Jobs still marked running belonged to a previous process and can never finish.
+    pub async fn recover(&self) -> anyhow::Result<()> {
+        let now = Self::now();
+        let result = sqlx::query(
+            r#"
+            UPDATE jobs
+            SET state = ?1, error = 'interrupted by restart', updated_at = ?2, finished_at = ?2
+            WHERE state = ?3
+            "#,
+        )
+        .bind(JobState::Failed.as_str())
+        .bind(&now)
+        .bind(JobState::Running.as_str())
+        .execute(&self.deps.db)
+        .await
+        .context("mark interrupted jobs")?;
+        if result.rows_affected() > 0 {
+            warn!("jobs interrupted by restart: {}", result.rows_affected());
+        }
+        Ok(())
+    }
+
+    /// Record a running job owned by `owner_id`; the handle reports on it until finished.
+    pub async fn start(
+        &self,
+        owner_id: i64,
+        kind: JobKind,
+        total: Option<usize>,
+    ) -> Result<JobHandle, AppError> {
+        let now = Self::now();
+        let result = sqlx::query(
+            r#"
+            INSERT INTO jobs (owner_id, kind, state, done, total, created_at, updated_at)
+            VALUES (?1, ?2, ?3, 0, ?4, ?5, ?5)
+            "#,
+        )
+        .bind(owner_id)
+        .bind(kind.as_str())
+        .bind(JobState::Running.as_str())
+        .bind(total.map(|total| total as i64))
+        .bind(&now)
+        .execute(&self.deps.db)
+        .await?;
+
+        let id = result.last_insert_rowid();
+        let cancel = CancellationToken::new();
+        self.lock_running().insert(id, cancel.clone());
+        info!("job started: id={} kind={}", id, kind.as_str());
+        Ok(JobHandle {
+            id,
+            deps: self.deps.clone(),
+            running: self.running.clone(),
+            cancel,
+            last_saved: Instant::now(),
+            finished: false,
+        })
+    }
+
+    /// The caller's jobs, newest first; admins see everyone's.
+    pub async fn list(&self, caller: Caller) -> Result<JobsResponse, AppError> {
+        let results: Vec<JobItem> = sqlx::query_as(
+            r#"
+            SELECT id, kind, state, done, total, error, created_at, updated_at, finished_at
+            FROM jobs
+            WHERE owner_id = ?1 OR ?2
+            ORDER BY id DESC
+            LIMIT ?3
+            "#,
+        )
+        .bind(caller.user_id)
+        .bind(caller.has_scope(Scope::Admin))
+        .bind(LIST_LIMIT)
+        .fetch_all(&self.deps.db)
+        .await?;
+        Ok(JobsResponse { results })
+    }
+
+    pub async fn get(&self, caller: Caller, id: i64) -> Result<JobItem, AppError> {
+        sqlx::query_as(
+            r#"
+            SELECT id, kind, state, done, total, error, created_at, updated_at, finished_at
+            FROM jobs
+            WHERE id = ?1 AND (owner_id = ?2 OR ?3)
+            "#,
+        )
+        .bind(id)
+        .bind(caller.user_id)
+        .bind(caller.has_scope(Scope::Admin))
+        .fetch_optional(&self.deps.db)
+        .await?
+        .ok_or_else(|| AppError::not_found("job not found"))
+    }
+
+    /// Ask a running job to stop; it finishes as `cancelled` at its next checkpoint.
+    pub async fn cancel(&self, caller: Caller, id: i64) -> Result<JobItem, AppError> {
+        let job = self.get(caller, id).await?;
+        if job.state != JobState::Running.as_str() {
+            return Err(AppError::conflict(format!("job is already {}", job.state)));
+        }
+        let Some(cancel) = self.lock_running().get(&id).cloned() else {
+            return Err(AppError::conflict("job is no longer running"));
+        };
+        cancel.cancel();
+        info!("job cancel requested: id={}", id);
+        Ok(job)
+    }
+
+    fn lock_running(&self) -> std::sync::MutexGuard<'_, HashMap<i64, CancellationToken>> {
+        self.running
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn now() -> String {
+        OffsetDateTime::now_utc()
+            .format(&Rfc3339)
+            .expect("failed to format timestamp")
+    }
+}
+
+/// Reports on one running job. Dropping it unfinished (e.g. the request was
+/// aborted) records the job as failed.
+pub struct JobHandle {
+    id: i64,
+    deps: Arc<Dependencies>,
+    running: Registry,
+    cancel: CancellationToken,
+    last_saved: Instant,
+    finished: bool,
+}
+
+impl JobHandle {
+    pub fn id(&self) -> i64 {
+        self.id
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+
+    /// Resolves once cancellation is requested.
+    pub fn cancellation(&self) -> CancellationToken {
+        self.cancel.clone()
+    }
+
+    /// Record progress, throttled so hot loops do not write on every item.
+    pub async fn progress(&mut self, done: usize, total: Option<usize>) {
+        if self.last_saved.elapsed() < PROGRESS_INTERVAL {
+            return;
+        }
+        self.last_saved = Instant::now();
+        let result = sqlx::query(
+            "UPDATE jobs SET done = ?1, total = COALESCE(?2, total), updated_at = ?3 WHERE id = ?4",
+        )
+        .bind(done as i64)
+        .bind(total.map(|total| total as i64))
+        .bind(JobService::now())
+        .bind(self.id)
+        .execute(&self.deps.db)
+        .await;
+        if let Err(err) = result {
+            warn!("job progress update failed: id={} error={}", self.id, err);
+        }
+    }
+
+    /// Record the outcome; a requested cancellation wins over success or failure.
+    pub async fn finish(mut self, done: usize, outcome: Result<(), String>) {
+        self.finished = true;
+        let (state, error) = match outcome {
+            _ if self.cancel.is_cancelled() => (JobState::Cancelled, None),
+            Ok(()) => (JobState::Succeeded, None),
+            Err(err) => (JobState::Failed, Some(err)),
+        };
+        Self::save_final(&self.deps, self.id, state, Some(done), error).await;
+    }
+
+    async fn save_final(
+        deps: &Dependencies,
+        id: i64,
+        state: JobState,
+        done: Option<usize>,
+        error: Option<String>,
+    ) {
+        let now = JobService::now();
+        let result = sqlx::query(
+            r#"
+            UPDATE jobs
+            SET state = ?1, done = COALESCE(?2, done), error = ?3, updated_at = ?4, finished_at = ?4
+            WHERE id = ?5
+            "#,
+        )
+        .bind(state.as_str())
+        .bind(done.map(|done| done as i64))
+        .bind(error)
+        .bind(&now)
+        .bind(id)
+        .execute(&deps.db)
+        .await;
+        match result {
+            Ok(_) => info!("job finished: id={} state={}", id, state.as_str()),
+            Err(err) => warn!("job state update failed: id={} error={}", id, err),
+        }
+    }
+}
+
+impl Drop for JobHandle {
+    fn drop(&mut self) {
+        self.running
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .remove(&self.id);
+        if self.finished {
+            return;
+        }
+        let (deps, id) = (self.deps.clone(), self.id);
+        let state = if self.cancel.is_cancelled() {
+            JobState::Cancelled
+        } else {
+            JobState::Failed
+        };
+        tokio::spawn(async move {
+            let error = (state == JobState::Failed).then(|| "interrupted".to_string());
+            Self::save_final(&deps, id, state, None, error).await;
+        });
+    }
+}
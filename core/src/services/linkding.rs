@@ -0,0 +1,401 @@
+use std::sync::Arc;
+
+use sqlx::FromRow;
+use tracing::info;
+
+use crate::errors::AppError;
+use crate::services::{BookmarkService, IngestService};
+use crate::tags;
+use crate::types::{
+    BookmarkTagsRequest, Caller, Dependencies, IngestUrlsRequest, LinkdingBookmark,
+    LinkdingBookmarkRequest, LinkdingCheckResponse, LinkdingListParams, LinkdingMetadata,
+    LinkdingPage, LinkdingProfile, LinkdingTag, LookupParams,
+};
+
+#[derive(FromRow)]
+struct Row {
+    id: i64,
+    url: String,
+    title: Option<String>,
+    excerpt: Option<String>,
+    created_at: String,
+    updated_at: String,
+}
+
+impl Row {
+    fn bookmark(self, tag_names: Vec<String>) -> LinkdingBookmark {
+        LinkdingBookmark {
+            id: self.id,
+            url: self.url,
+            title: self.title.clone().unwrap_or_default(),
+            description: self.excerpt.clone().unwrap_or_default(),
+            notes: String::new(),
+            web_archive_snapshot_url: String::new(),
+            favicon_url: None,
+            preview_image_url: None,
+            is_archived: false,
+            unread: false,
+            shared: false,
+            tag_names,
+            date_added: self.created_at,
+            date_modified: self.updated_at,
+            website_title: self.title,
+            website_description: self.excerpt,
+        }
+    }
+}
+
+/// linkding's REST API mapped onto odin bookmarks, so linkding apps and extensions
+/// can save, tag, and browse. Odin has no archive or notes: bookmarks are never
+/// archived, and fields odin fills from the page are read-only.
+#[derive(Clone)]
+pub struct LinkdingService {
+    deps: Arc<Dependencies>,
+    bookmarks: BookmarkService,
+    ingest: IngestService,
+}
+
+impl LinkdingService {
+    const DEFAULT_LIMIT: u32 = 100;
+    const MAX_LIMIT: u32 = 1000;
+    /// The namespace's tags in use, as `LinkdingTag` rows; binds owner and namespace.
+    const TAGS: &str = r#"
+        SELECT t.id, t.name, MIN(b.created_at) AS date_added
+        FROM tags t
+        JOIN bookmark_tags bt ON bt.tag_id = t.id
+        JOIN bookmarks b ON b.id = bt.bookmark_id
+        WHERE t.owner_id = ?1 AND t.namespace = ?2
+        GROUP BY t.id
+    "#;
+
+    pub fn new(deps: Arc<Dependencies>, bookmarks: BookmarkService, ingest: IngestService) -> Self {
+        Self {
+            deps,
+            bookmarks,
+            ingest,
+        }
+    }
+
+    /// Newest first; `archived` lists are always empty.
+    pub async fn list(
+        &self,
+        caller: Caller,
+        params: LinkdingListParams,
+        archived: bool,
+    ) -> Result<LinkdingPage<LinkdingBookmark>, AppError> {
+        let path = if archived {
+            "/api/bookmarks/archived/"
+        } else {
+            "/api/bookmarks/"
+        };
+        let limit = params
+            .limit
+            .unwrap_or(Self::DEFAULT_LIMIT)
+            .clamp(1, Self::MAX_LIMIT);
+        let offset = params.offset.unwrap_or(0);
+        if archived {
+            return Ok(Self::page(path, &params.q, limit, offset, 0, Vec::new()));
+        }
+
+        let terms: Vec<&str> = params.q.split_whitespace().collect();
+        let terms = serde_json::to_string(&terms).map_err(anyhow::Error::from)?;
+        // A bookmark matches when no term is missing from its text.
+        let filter = r#"
+            owner_id = ?1 AND namespace = ?2 AND NOT EXISTS (
+                SELECT 1 FROM json_each(?3) AS term
+                WHERE instr(
+                    lower(url || ' ' || coalesce(title, '') || ' ' || coalesce(excerpt, '')),
+                    lower(term.value)
+                ) = 0
+            )
+        "#;
+        let count: i64 =
+            sqlx::query_scalar(&format!("SELECT COUNT(*) FROM bookmarks WHERE {}", filter))
+                .bind(caller.user_id)
+                .bind(&caller.namespace)
+                .bind(&terms)
+                .fetch_one(&self.deps.db)
+                .await?;
+        let rows: Vec<Row> = sqlx::query_as(&format!(
+            r#"
+            SELECT id, url, title, excerpt, created_at, updated_at
+            FROM bookmarks
+            WHERE {}
+            ORDER BY created_at DESC, id DESC
+            LIMIT ?4 OFFSET ?5
+            "#,
+            filter
+        ))
+        .bind(caller.user_id)
+        .bind(&caller.namespace)
+        .bind(&terms)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.deps.db)
+        .await?;
+
+        let results = self.with_tags(rows).await?;
+        Ok(Self::page(path, &params.q, limit, offset, count, results))
+    }
+
+    async fn with_tags(&self, rows: Vec<Row>) -> Result<Vec<LinkdingBookmark>, AppError> {
+        let ids: Vec<i64> = rows.iter().map(|row| row.id).collect();
+        let mut tags = tags::of_bookmarks(&self.deps.db, &ids).await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| {
+                let tags = tags.remove(&row.id).unwrap_or_default();
+                row.bookmark(tags)
+            })
+            .collect())
+    }
+
+    async fn by_url(
+        &self,
+        caller: &Caller,
+        url: &str,
+    ) -> Result<Option<LinkdingBookmark>, AppError> {
+        let row: Option<Row> = sqlx::query_as(
+            r#"
+            SELECT id, url, title, excerpt, created_at, updated_at
+            FROM bookmarks
+            WHERE url = ?1 AND owner_id = ?2 AND namespace = ?3
+            "#,
+        )
+        .bind(url)
+        .bind(caller.user_id)
+        .bind(&caller.namespace)
+        .fetch_optional(&self.deps.db)
+        .await?;
+        Ok(self.with_tags(row.into_iter().collect()).await?.pop())
+    }
+
+    pub async fn get(&self, caller: &Caller, id: i64) -> Result<LinkdingBookmark, AppError> {
+        let row: Option<Row> = sqlx::query_as(
+            r#"
+            SELECT id, url, title, excerpt, created_at, updated_at
+            FROM bookmarks
+            WHERE id = ?1 AND owner_id = ?2 AND namespace = ?3
+            "#,
+        )
+        .bind(id)
+        .bind(caller.user_id)
+        .bind(&caller.namespace)
+        .fetch_optional(&self.deps.db)
+        .await?;
+        self.with_tags(row.into_iter().collect())
+            .await?
+            .pop()
+            .ok_or_else(|| AppError::not_found("bookmark not found"))
+    }
+
+    /// Whether `url` is saved; metadata is only known once odin has fetched the page.
+    /// Odin has no tagging rules, so `auto_tags` are the saved bookmark's tags, which
+    /// clients prefill.
+    pub async fn check(
+        &self,
+        caller: Caller,
+        params: LookupParams,
+    ) -> Result<LinkdingCheckResponse, AppError> {
+        let Some(url) = IngestService::normalize_url(&params.url) else {
+            return Err(AppError::bad_request("invalid url"));
+        };
+        let bookmark = self.by_url(&caller, &url).await?;
+        let auto_tags = bookmark
+            .as_ref()
+            .map(|bookmark| bookmark.tag_names.clone())
+            .unwrap_or_default();
+        let metadata = match &bookmark {
+            Some(bookmark) => LinkdingMetadata {
+                url: bookmark.url.clone(),
+                title: bookmark.website_title.clone(),
+                description: bookmark.website_description.clone(),
+            },
+            None => LinkdingMetadata {
+                url,
+                title: None,
+                description: None,
+            },
+        };
+        Ok(LinkdingCheckResponse {
+            bookmark,
+            metadata,
+            auto_tags,
+        })
+    }
+
+    /// Queue the URL for ingest like any other save and return its bookmark, which
+    /// gains a title once fetched. Saving a URL twice returns the existing bookmark,
+    /// with its tags replaced when `tag_names` is given.
+    pub async fn create(
+        &self,
+        caller: Caller,
+        payload: LinkdingBookmarkRequest,
+    ) -> Result<LinkdingBookmark, AppError> {
+        let Some(url) = payload
+            .url
+            .as_deref()
+            .and_then(IngestService::normalize_url)
+        else {
+            return Err(AppError::bad_request("invalid url"));
+        };
+        let response = self
+            .ingest
+            .ingest_urls(
+                caller.clone(),
+                IngestUrlsRequest {
+                    urls: vec![url.clone()],
+                    tags: payload.tag_names.clone().unwrap_or_default(),
+                },
+            )
+            .await?;
+        let mut bookmark = self
+            .by_url(&caller, &url)
+            .await?
+            .ok_or_else(|| AppError::not_found("bookmark not found"))?;
+        if response.accepted == 0
+            && let Some(tags) = payload.tag_names
+        {
+            bookmark.tag_names = self
+                .bookmarks
+                .set_tags(caller, bookmark.id, BookmarkTagsRequest { tags })
+                .await?
+                .tags;
+        }
+        info!("linkding bookmark saved: id={}", bookmark.id);
+        Ok(bookmark)
+    }
+
+    /// Of what linkding edits, odin stores only tags, so an update replaces them when
+    /// `tag_names` is given and otherwise only checks that the URL is left alone.
+    pub async fn update(
+        &self,
+        caller: Caller,
+        id: i64,
+        payload: LinkdingBookmarkRequest,
+    ) -> Result<LinkdingBookmark, AppError> {
+        let mut bookmark = self.get(&caller, id).await?;
+        if let Some(url) = payload.url
+            && IngestService::normalize_url(&url).as_deref() != Some(bookmark.url.as_str())
+        {
+            return Err(AppError::bad_request(
+                "changing a bookmark's url is not supported",
+            ));
+        }
+        if let Some(tags) = payload.tag_names {
+            bookmark.tag_names = self
+                .bookmarks
+                .set_tags(caller, id, BookmarkTagsRequest { tags })
+                .await?
+                .tags;
+        }
+        Ok(bookmark)
+    }
+
+    pub async fn delete(&self, caller: Caller, id: i64) -> Result<(), AppError> {
+        self.bookmarks.delete(caller, id).await
+    }
+
+    /// Bookmarks are never archived, so unarchiving only needs the bookmark to exist.
+    pub async fn set_archived(
+        &self,
+        caller: Caller,
+        id: i64,
+        archived: bool,
+    ) -> Result<(), AppError> {
+        self.get(&caller, id).await?;
+        if archived {
+            return Err(AppError::bad_request("archiving is not supported"));
+        }
+        Ok(())
+    }
+
+    /// Tags some bookmark in the namespace has, by name. Odin keeps no date for a tag,
+    /// so `date_added` is when its oldest bookmark was saved.
+    pub async fn tags(
+        &self,
+        caller: Caller,
+        params: LinkdingListParams,
+    ) -> Result<LinkdingPage<LinkdingTag>, AppError> {
+        let limit = params
+            .limit
+            .unwrap_or(Self::DEFAULT_LIMIT)
+            .clamp(1, Self::MAX_LIMIT);
+        let offset = params.offset.unwrap_or(0);
+        let count: i64 = sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*) FROM tags t
+            WHERE t.owner_id = ?1 AND t.namespace = ?2
+              AND EXISTS (SELECT 1 FROM bookmark_tags bt WHERE bt.tag_id = t.id)
+            "#,
+        )
+        .bind(caller.user_id)
+        .bind(&caller.namespace)
+        .fetch_one(&self.deps.db)
+        .await?;
+        let results: Vec<LinkdingTag> = sqlx::query_as(&format!(
+            "{} ORDER BY t.name LIMIT ?3 OFFSET ?4",
+            Self::TAGS
+        ))
+        .bind(caller.user_id)
+        .bind(&caller.namespace)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.deps.db)
+        .await?;
+        Ok(Self::page("/api/tags/", "", limit, offset, count, results))
+    }
+
+    pub async fn tag(&self, caller: Caller, id: i64) -> Result<LinkdingTag, AppError> {
+        let tag: Option<LinkdingTag> = sqlx::query_as(&format!("{} HAVING t.id = ?3", Self::TAGS))
+            .bind(caller.user_id)
+            .bind(&caller.namespace)
+            .bind(id)
+            .fetch_optional(&self.deps.db)
+            .await?;
+        tag.ok_or_else(|| AppError::not_found("tag not found"))
+    }
+
+    pub fn profile(&self) -> LinkdingProfile {
+        LinkdingProfile {
+            theme: "auto",
+            bookmark_date_display: "relative",
+            bookmark_link_target: "_blank",
+            web_archive_integration: "disabled",
+            tag_search: "lax",
+            enable_sharing: false,
+            enable_public_sharing: false,
+            enable_favicons: false,
+            display_url: false,
+            permanent_notes: false,
+        }
+    }
+
+    fn page<T>(
+        path: &str,
+        q: &str,
+        limit: u32,
+        offset: u32,
+        count: i64,
+        results: Vec<T>,
+    ) -> LinkdingPage<T> {
+        let link = |offset: u32| {
+            let mut query = url::form_urlencoded::Serializer::new(String::new());
+            if !q.is_empty() {
+                query.append_pair("q", q);
+            }
+            query
+                .append_pair("limit", &limit.to_string())
+                .append_pair("offset", &offset.to_string());
+            format!("{}?{}", path, query.finish())
+        };
+        let next = (i64::from(offset) + i64::from(limit) < count).then(|| link(offset + limit));
+        let previous = (offset > 0).then(|| link(offset.saturating_sub(limit)));
+        LinkdingPage {
+            count,
+            next,
+            previous,
+            results,
+        }
+    }
+}
@@ -0,0 +1,239 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use tracing::{info, warn};
+
+use crate::content;
+use crate::errors::AppError;
+use crate::types::{DbMaintenanceResponse, Dependencies};
+
+/// `PRAGMA auto_vacuum` value for incremental mode.
+const AUTO_VACUUM_INCREMENTAL: i64 = 2;
+
+#[derive(Clone)]
+pub struct MaintenanceService {
+    deps: Arc<Dependencies>,
+    /// Held while SQLite maintenance runs so scheduled, delete-triggered, and manual runs never overlap.
+    db_running: Arc<tokio::sync::Mutex<()>>,
+    deleted_rows: Arc<AtomicUsize>,
+}
+
+impl MaintenanceService {
+    pub fn new(deps: Arc<Dependencies>) -> Self {
+        Self {
+            deps,
+            db_running: Arc::new(tokio::sync::Mutex::new(())),
+            deleted_rows: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    pub fn start(&self) {
+        self.start_index_optimize();
+        self.start_db_maintenance();
+    }
+
+    /// Periodically merge index segments, waiting for the index to go quiet first.
+    fn start_index_optimize(&self) {
+        let service = self.clone();
+        tokio::spawn(async move {
+            let shutdown = service.deps.shutdown.clone();
+            while service
+                .deps
+                .wait_interval(|config| config.optimize_interval_secs)
+                .await
+            {
+                loop {
+                    let idle = Duration::from_secs(service.deps.maintenance_config().idle_secs);
+                    let quiet_for = service
+                        .deps
+                        .last_index_write
+                        .lock()
+                        .unwrap_or_else(|poisoned| poisoned.into_inner())
+                        .elapsed();
+                    if quiet_for >= idle && service.deps.metrics.ingest_queue_depth() == 0 {
+                        break;
+                    }
+                    let wait = idle.saturating_sub(quiet_for).max(Duration::from_secs(1));
+                    tokio::select! {
+                        _ = shutdown.cancelled() => return,
+                        _ = tokio::time::sleep(wait) => {}
+                    }
+                }
+
+                if let Err(err) = service.optimize().await {
+                    warn!("index optimize failed: {:?}", err);
+                }
+            }
+        });
+    }
+
+    /// Periodically reclaim free pages and refresh SQLite's query planner statistics.
+    fn start_db_maintenance(&self) {
+        let service = self.clone();
+        tokio::spawn(async move {
+            while service
+                .deps
+                .wait_interval(|config| config.db_interval_secs)
+                .await
+            {
+                if let Err(err) = service.optimize_db().await {
+                    warn!("database maintenance failed: {:?}", err);
+                }
+            }
+        });
+    }
+
+    /// Count deleted bookmarks, running database maintenance in the background once
+    /// `maintenance.db_deleted_rows` have accumulated.
+    pub fn record_deletes(&self, rows: usize) {
+        let threshold = self.deps.maintenance_config().db_deleted_rows;
+        if threshold == 0 || rows == 0 {
+            return;
+        }
+        let total = self.deleted_rows.fetch_add(rows, Ordering::Relaxed) + rows;
+        if total < threshold {
+            return;
+        }
+        self.deleted_rows.store(0, Ordering::Relaxed);
+        info!("database maintenance triggered by deletes: rows={}", total);
+        let service = self.clone();
+        tokio::spawn(async move {
+            if let Err(err) = service.optimize_db().await {
+                warn!("database maintenance failed: {:?}", err);
+            }
+        });
+    }
+
+    /// Return free pages to the filesystem, update planner statistics, and truncate the WAL.
+    /// A database created before incremental vacuuming was enabled gets one full `VACUUM`.
+    pub async fn optimize_db(&self) -> Result<DbMaintenanceResponse, AppError> {
+        let Ok(_running) = self.db_running.try_lock() else {
+            return Err(AppError::conflict(
+                "database maintenance is already running",
+            ));
+        };
+        let started = Instant::now();
+        let mut conn = self.deps.db.acquire().await?;
+        let db_bytes_before = Self::db_bytes(&mut conn).await?;
+        // Before vacuuming, so the pages compression frees are returned too.
+        let compressed_contents = Self::compress_plain_contents(&mut conn).await?;
+
+        let auto_vacuum: i64 = sqlx::query_scalar("PRAGMA auto_vacuum")
+            .fetch_one(&mut *conn)
+            .await?;
+        let vacuumed = auto_vacuum != AUTO_VACUUM_INCREMENTAL;
+        let freed_pages = if vacuumed {
+            // The mode only changes when the file is rebuilt.
+            sqlx::query("PRAGMA auto_vacuum = INCREMENTAL")
+                .execute(&mut *conn)
+                .await?;
+            sqlx::query("VACUUM").execute(&mut *conn).await?;
+            0
+        } else {
+            let free: i64 = sqlx::query_scalar("PRAGMA freelist_count")
+                .fetch_one(&mut *conn)
+                .await?;
+            sqlx::query("PRAGMA incremental_vacuum")
+                .execute(&mut *conn)
+                .await?;
+            free
+        };
+        sqlx::query("ANALYZE").execute(&mut *conn).await?;
+        sqlx::query("PRAGMA optimize").execute(&mut *conn).await?;
+        sqlx::query("PRAGMA wal_checkpoint(TRUNCATE)")
+            .execute(&mut *conn)
+            .await?;
+        let db_bytes_after = Self::db_bytes(&mut conn).await?;
+
+        let response = DbMaintenanceResponse {
+            vacuumed,
+            freed_pages,
+            compressed_contents,
+            db_bytes_before,
+            db_bytes_after,
+            elapsed_ms: started.elapsed().as_millis() as u64,
+        };
+        info!(
+            "database maintenance finished: vacuumed={} freed_pages={} compressed_contents={} db_bytes={}->{} elapsed_ms={}",
+            response.vacuumed,
+            response.freed_pages,
+            response.compressed_contents,
+            response.db_bytes_before,
+            response.db_bytes_after,
+            response.elapsed_ms
+        );
+        Ok(response)
+    }
+
+    /// Compress page texts stored uncompressed by older versions, in batches so other
+    /// writers get the database between them.
+    async fn compress_plain_contents(conn: &mut sqlx::SqliteConnection) -> Result<u64, AppError> {
+        const BATCH: i64 = 200;
+        let mut compressed = 0;
+        loop {
+            let rows: Vec<(i64, Vec<u8>)> = sqlx::query_as(
+                "SELECT bookmark_id, CAST(body AS BLOB) FROM bookmark_contents WHERE encoding = ?1 LIMIT ?2",
+            )
+            .bind(content::PLAIN)
+            .bind(BATCH)
+            .fetch_all(&mut *conn)
+            .await?;
+            if rows.is_empty() {
+                return Ok(compressed);
+            }
+
+            let mut tx = sqlx::Connection::begin(&mut *conn).await?;
+            for (id, body) in rows {
+                let text = content::decode(content::PLAIN, body)?;
+                sqlx::query(
+                    "UPDATE bookmark_contents SET body = ?1, encoding = ?2 WHERE bookmark_id = ?3",
+                )
+                .bind(content::compress(&text)?)
+                .bind(content::ZLIB)
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+                compressed += 1;
+            }
+            tx.commit().await?;
+        }
+    }
+
+    async fn db_bytes(conn: &mut sqlx::SqliteConnection) -> Result<u64, AppError> {
+        let bytes: i64 = sqlx::query_scalar(
+            "SELECT page_count * page_size FROM pragma_page_count(), pragma_page_size()",
+        )
+        .fetch_one(conn)
+        .await?;
+        Ok(bytes as u64)
+    }
+
+    /// Merge each shard's segments into one (dropping deleted docs) and remove unused files.
+    pub async fn optimize(&self) -> anyhow::Result<()> {
+        let mut writer = self.deps.writer.lock().await;
+        for (shard_number, shard) in self.deps.index.shards().iter().enumerate() {
+            let segments = shard.index.searchable_segment_metas()?;
+            let has_deletes = segments.iter().any(|segment| segment.has_deletes());
+            if segments.len() < self.deps.maintenance_config().min_segments && !has_deletes {
+                continue;
+            }
+
+            let started = Instant::now();
+            let ids: Vec<_> = segments.iter().map(|segment| segment.id()).collect();
+            // Live `SegmentMeta`s (ours or a searcher's) keep their files from being collected.
+            drop(segments);
+            let shard_writer = writer.shard_mut(shard_number);
+            shard_writer.merge(&ids).await?;
+            shard.reader.reload()?;
+            shard_writer.garbage_collect_files().await?;
+            info!(
+                "index optimized: shard={} {} segments merged elapsed_ms={}",
+                shard_number,
+                ids.len(),
+                started.elapsed().as_millis()
+            );
+        }
+        Ok(())
+    }
+}
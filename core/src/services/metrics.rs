@@ -0,0 +1,30 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::errors::AppError;
+use crate::types::Dependencies;
+
+#[derive(Clone)]
+pub struct MetricsService {
+    deps: Arc<Dependencies>,
+}
+
+impl MetricsService {
+    pub fn new(deps: Arc<Dependencies>) -> Self {
+        Self { deps }
+    }
+
+    pub fn observe_request(&self, method: &str, route: &str, status: u16, elapsed: Duration) {
+        self.deps
+            .metrics
+            .observe_request(method, route, status, elapsed);
+    }
+
+    pub fn render(&self) -> Result<String, AppError> {
+        let searchers = self.deps.index.searchers();
+        self.deps
+            .metrics
+            .set_index_documents(searchers.iter().map(|searcher| searcher.num_docs()).sum());
+        Ok(self.deps.metrics.render(&self.deps.db)?)
+    }
+}
@@ -0,0 +1,175 @@
+mod activitypub;
+mod api_keys;
+mod auth;
+mod backup;
+mod bookmarks;
+mod browser_sync;
+mod config;
+mod domain_stats;
+mod events;
+mod export;
+mod feed_reader;
+mod feeds;
+mod health;
+mod import;
+mod indexer;
+mod ingest;
+mod jobs;
+mod linkding;
+mod maintenance;
+mod metrics;
+mod notifications;
+mod oidc;
+mod pinboard;
+mod read_only;
+mod readwise;
+mod reconcile;
+mod search;
+mod settings;
+mod status;
+mod storage;
+mod telegram;
+mod users;
+mod wayback;
+mod webhooks;
+
+pub use activitypub::{ACTIVITY_JSON, ActivityPubService};
+pub use api_keys::ApiKeyService;
+pub use auth::AuthService;
+pub use backup::{BackupService, apply_pending_restore};
+pub use bookmarks::BookmarkService;
+pub use browser_sync::BrowserSyncService;
+pub use config::ConfigService;
+pub use domain_stats::DomainStatsService;
+pub use events::EventService;
+pub use export::{ExportArchive, ExportService, ExportStream};
+pub use feed_reader::FeedReaderService;
+pub use feeds::FeedService;
+pub use health::HealthService;
+pub use import::ImportService;
+pub use indexer::IndexerService;
+pub use ingest::IngestService;
+pub use jobs::{JobHandle, JobService};
+pub use linkding::LinkdingService;
+pub use maintenance::MaintenanceService;
+pub use metrics::MetricsService;
+pub use notifications::NotificationService;
+pub use oidc::OidcService;
+pub use pinboard::PinboardService;
+pub use read_only::ReadOnlyService;
+pub use readwise::ReadwiseService;
+pub use reconcile::ReconcileService;
+pub use search::SearchService;
+pub use settings::SettingsService;
+pub use status::StatusService;
+pub use storage::StorageService;
+pub use telegram::TelegramService;
+pub use users::UserService;
+pub use wayback::WaybackService;
+pub use webhooks::WebhookService;
+
+use std::sync::Arc;
+
+use crate::rate_limit::RateLimits;
+use crate::types::Dependencies;
+
+#[derive(Clone)]
+pub struct Services {
+    pub activitypub: ActivityPubService,
+    pub api_keys: ApiKeyService,
+    pub auth: AuthService,
+    pub backup: BackupService,
+    pub bookmarks: BookmarkService,
+    pub browser_sync: BrowserSyncService,
+    pub config: ConfigService,
+    pub domain_stats: DomainStatsService,
+    pub events: EventService,
+    pub export: ExportService,
+    pub feed_reader: FeedReaderService,
+    pub feeds: FeedService,
+    pub health: HealthService,
+    pub import: ImportService,
+    pub search: SearchService,
+    pub indexer: IndexerService,
+    pub ingest: IngestService,
+    pub jobs: JobService,
+    pub linkding: LinkdingService,
+    pub maintenance: MaintenanceService,
+    pub metrics: MetricsService,
+    pub notifications: NotificationService,
+    pub oidc: OidcService,
+    pub pinboard: PinboardService,
+    pub rate_limits: RateLimits,
+    pub read_only: ReadOnlyService,
+    pub readwise: ReadwiseService,
+    pub reconcile: ReconcileService,
+    pub settings: SettingsService,
+    pub status: StatusService,
+    pub storage: StorageService,
+    pub telegram: TelegramService,
+    pub users: UserService,
+    pub wayback: WaybackService,
+    pub webhooks: WebhookService,
+}
+
+impl Services {
+    pub fn new(deps: Arc<Dependencies>) -> Self {
+        let jobs = JobService::new(deps.clone());
+        let domain_stats = DomainStatsService::new(deps.clone());
+        let indexer = IndexerService::new(deps.clone());
+        let maintenance = MaintenanceService::new(deps.clone());
+        let ingest = IngestService::new(
+            deps.clone(),
+            jobs.clone(),
+            domain_stats.clone(),
+            indexer.clone(),
+            maintenance.clone(),
+        );
+        let storage = StorageService::new(deps.clone());
+        let rate_limits = RateLimits::new(&deps.config.rate_limit);
+        let auth = AuthService::new(deps.clone());
+        let search = SearchService::new(deps.clone());
+        let bookmarks = BookmarkService::new(deps.clone(), ingest.clone());
+        let telegram =
+            TelegramService::new(deps.clone(), auth.clone(), ingest.clone(), search.clone());
+        let notifications = NotificationService::new(deps.clone(), search.clone());
+        let feeds = FeedService::new(deps.clone(), auth.clone(), ingest.clone());
+        Self {
+            activitypub: ActivityPubService::new(deps.clone(), bookmarks.clone()),
+            api_keys: ApiKeyService::new(deps.clone()),
+            oidc: OidcService::new(deps.clone(), auth.clone()),
+            feed_reader: FeedReaderService::new(deps.clone(), auth.clone(), ingest.clone()),
+            auth,
+            backup: BackupService::new(deps.clone(), jobs.clone(), storage.clone()),
+            linkding: LinkdingService::new(deps.clone(), bookmarks.clone(), ingest.clone()),
+            pinboard: PinboardService::new(deps.clone(), bookmarks.clone(), ingest.clone()),
+            wayback: WaybackService::new(deps.clone(), bookmarks.clone()),
+            browser_sync: BrowserSyncService::new(deps.clone(), bookmarks.clone(), ingest.clone()),
+            bookmarks,
+            config: ConfigService::new(deps.clone(), rate_limits.clone()),
+            domain_stats,
+            events: EventService::new(deps.clone()),
+            export: ExportService::new(deps.clone()),
+            health: HealthService::new(deps.clone()),
+            import: ImportService::new(deps.clone(), ingest.clone(), jobs.clone(), feeds.clone()),
+            feeds,
+            reconcile: ReconcileService::new(deps.clone(), ingest.clone()),
+            search,
+            indexer,
+            ingest,
+            jobs,
+            maintenance,
+            metrics: MetricsService::new(deps.clone()),
+            notifications,
+            rate_limits,
+            read_only: ReadOnlyService::new(deps.clone()),
+            readwise: ReadwiseService::new(deps.clone()),
+            settings: SettingsService::new(deps.clone()),
+            status: StatusService::new(deps.clone(), storage.clone()),
+            storage,
+            telegram,
+            users: UserService::new(deps.clone()),
+            webhooks: WebhookService::new(deps),
+        }
+    }
+}
@@ -0,0 +1,312 @@
+use std::fmt::Write as _;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde_json::json;
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{info, warn};
+
+use crate::config::{NotifierConfig, NotifierKind, NotifyEvent};
+use crate::errors::AppError;
+use crate::services::SearchService;
+use crate::types::{BookmarkEvent, BookmarkEventKind, Dependencies};
+
+const MAX_ATTEMPTS: u32 = 3;
+const BASE_RETRY_DELAY: Duration = Duration::from_secs(2);
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+const DIGEST_PERIOD: time::Duration = time::Duration::weeks(1);
+const DIGEST_CHECK_INTERVAL: Duration = Duration::from_secs(3600);
+/// Bookmarks listed by name in a digest; the rest are counted.
+const DIGEST_MAX_ITEMS: i64 = 15;
+/// Under Discord's 2000-character message limit, the tightest of the four.
+const MAX_MESSAGE_CHARS: usize = 1900;
+
+/// Posts short messages to Slack, Discord, Matrix, or ntfy: failed saves, new bookmarks
+/// matching a notifier's searches, and a weekly digest. Events come off the same bus
+/// as webhooks; unlike webhooks, messages are meant for people.
+#[derive(Clone)]
+pub struct NotificationService {
+    deps: Arc<Dependencies>,
+    search: SearchService,
+}
+
+impl NotificationService {
+    pub fn new(deps: Arc<Dependencies>, search: SearchService) -> Self {
+        Self { deps, search }
+    }
+
+    pub fn start(&self) {
+        let notifiers = &self.deps.config.notifiers;
+        if notifiers.is_empty() {
+            return;
+        }
+        info!("notifications enabled: {} notifiers", notifiers.len());
+
+        if notifiers.iter().any(|notifier| {
+            notifier.events.contains(&NotifyEvent::Failed) || !notifier.searches.is_empty()
+        }) {
+            let service = self.clone();
+            let events = self.deps.events.subscribe();
+            tokio::spawn(async move { service.forward_events(events).await });
+        }
+        if notifiers
+            .iter()
+            .any(|notifier| notifier.events.contains(&NotifyEvent::Digest))
+        {
+            let service = self.clone();
+            tokio::spawn(async move {
+                loop {
+                    // Digests are bookkept in the database, so read-only mode holds them.
+                    if service.deps.ensure_writable().is_ok()
+                        && let Err(err) = service.send_digests().await
+                    {
+                        warn!("notification digest failed: {:?}", err);
+                    }
+                    tokio::select! {
+                        _ = service.deps.shutdown.cancelled() => return,
+                        _ = tokio::time::sleep(DIGEST_CHECK_INTERVAL) => {}
+                    }
+                }
+            });
+        }
+    }
+
+    async fn forward_events(&self, mut events: tokio::sync::broadcast::Receiver<BookmarkEvent>) {
+        loop {
+            let event = match events.recv().await {
+                Ok(event) => event,
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!("notifier lagged; dropped {} events", skipped);
+                    continue;
+                }
+                Err(RecvError::Closed) => break,
+            };
+            // A new page is searchable only once the reader refresher catches up.
+            if event.event == BookmarkEventKind::Indexed
+                && self
+                    .deps
+                    .config
+                    .notifiers
+                    .iter()
+                    .any(|notifier| !notifier.searches.is_empty())
+            {
+                tokio::select! {
+                    _ = self.deps.shutdown.cancelled() => {}
+                    _ = self.deps.index.refreshed() => {}
+                }
+            }
+            for notifier in &self.deps.config.notifiers {
+                let Some(message) = self.message_for(notifier, &event) else {
+                    continue;
+                };
+                let service = self.clone();
+                let notifier = notifier.clone();
+                self.deps
+                    .tasks
+                    .clone()
+                    .spawn(async move { service.deliver(&notifier, &message).await });
+            }
+        }
+    }
+
+    fn message_for(&self, notifier: &NotifierConfig, event: &BookmarkEvent) -> Option<String> {
+        let name = event.title.as_deref().unwrap_or(&event.url);
+        match event.event {
+            BookmarkEventKind::Failed if notifier.events.contains(&NotifyEvent::Failed) => {
+                Some(format!(
+                    "Odin could not save {}: {}",
+                    event.url,
+                    event.error.as_deref().unwrap_or("unknown error")
+                ))
+            }
+            BookmarkEventKind::Indexed => {
+                let query = notifier.searches.iter().find(|query| {
+                    self.search
+                        .matches(event.id, query)
+                        .inspect_err(|err| {
+                            warn!("notifier search '{}' failed: {}", query, err.message())
+                        })
+                        .unwrap_or(false)
+                })?;
+                Some(format!(
+                    "New bookmark matching \"{}\": {} {}",
+                    query, name, event.url
+                ))
+            }
+            _ => None,
+        }
+    }
+
+    /// Send each digest notifier the bookmarks saved since its last digest, once that is
+    /// a week ago. A notifier's first check only starts the clock.
+    async fn send_digests(&self) -> Result<(), AppError> {
+        let now = OffsetDateTime::now_utc();
+        let now_text = Self::format_time(now);
+        for notifier in &self.deps.config.notifiers {
+            if !notifier.events.contains(&NotifyEvent::Digest) {
+                continue;
+            }
+            let target = Self::target(notifier);
+            let sent_at: Option<String> =
+                sqlx::query_scalar("SELECT sent_at FROM notification_digests WHERE target = ?1")
+                    .bind(&target)
+                    .fetch_optional(&self.deps.db)
+                    .await?;
+            if let Some(sent_at) = &sent_at {
+                let due = OffsetDateTime::parse(sent_at, &Rfc3339)
+                    .map_or(true, |sent_at| now - sent_at >= DIGEST_PERIOD);
+                if !due {
+                    continue;
+                }
+                if let Some(message) = self.digest(sent_at).await? {
+                    self.deliver(notifier, &message).await;
+                }
+            }
+            sqlx::query(
+                r#"
+                INSERT INTO notification_digests (target, sent_at) VALUES (?1, ?2)
+                ON CONFLICT(target) DO UPDATE SET sent_at = excluded.sent_at
+                "#,
+            )
+            .bind(&target)
+            .bind(&now_text)
+            .execute(&self.deps.db)
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn digest(&self, since: &str) -> Result<Option<String>, AppError> {
+        let total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM bookmarks WHERE created_at > ?1")
+            .bind(since)
+            .fetch_one(&self.deps.db)
+            .await?;
+        if total == 0 {
+            return Ok(None);
+        }
+        let newest: Vec<(String, Option<String>)> = sqlx::query_as(
+            "SELECT url, title FROM bookmarks WHERE created_at > ?1 ORDER BY created_at DESC, id DESC LIMIT ?2",
+        )
+        .bind(since)
+        .bind(DIGEST_MAX_ITEMS)
+        .fetch_all(&self.deps.db)
+        .await?;
+        let mut message = format!(
+            "Odin weekly digest: {} new bookmark{}",
+            total,
+            if total == 1 { "" } else { "s" }
+        );
+        for (url, title) in &newest {
+            let _ = match title {
+                Some(title) => write!(message, "\n- {} {}", title, url),
+                None => write!(message, "\n- {}", url),
+            };
+        }
+        let rest = total - newest.len() as i64;
+        if rest > 0 {
+            let _ = write!(message, "\n…and {} more", rest);
+        }
+        Ok(Some(message))
+    }
+
+    /// Post one message, retrying with backoff on transport errors and non-2xx replies.
+    async fn deliver(&self, notifier: &NotifierConfig, message: &str) {
+        let message = Self::truncate(message);
+        // One transaction id for every attempt, so Matrix drops duplicates.
+        let txn_id = hex::encode(rand::random::<[u8; 16]>());
+        for attempt in 1..=MAX_ATTEMPTS {
+            let failure = match self.request(notifier, &message, &txn_id).send().await {
+                Ok(response) if response.status().is_success() => {
+                    info!(
+                        "notification delivered: kind={:?} attempt={}",
+                        notifier.kind, attempt
+                    );
+                    return;
+                }
+                Ok(response) => format!("http status {}", response.status()),
+                Err(err) => err.without_url().to_string(),
+            };
+            if attempt == MAX_ATTEMPTS {
+                warn!(
+                    "notification failed: kind={:?} attempts={} error={}",
+                    notifier.kind, attempt, failure
+                );
+                return;
+            }
+            tokio::time::sleep(BASE_RETRY_DELAY * 2u32.pow(attempt - 1)).await;
+        }
+    }
+
+    fn request(
+        &self,
+        notifier: &NotifierConfig,
+        message: &str,
+        txn_id: &str,
+    ) -> reqwest::RequestBuilder {
+        let client = &self.deps.http_client;
+        let request = match notifier.kind {
+            NotifierKind::Slack => client
+                .post(notifier.url.clone())
+                .header("content-type", "application/json")
+                .body(json!({ "text": message }).to_string()),
+            NotifierKind::Discord => client
+                .post(notifier.url.clone())
+                .header("content-type", "application/json")
+                .body(json!({ "content": message }).to_string()),
+            NotifierKind::Ntfy => {
+                let request = client
+                    .post(notifier.url.clone())
+                    .header("title", "Odin")
+                    .body(message.to_string());
+                match notifier.token.as_deref() {
+                    Some(token) => request.bearer_auth(token),
+                    None => request,
+                }
+            }
+            NotifierKind::Matrix => {
+                let mut url = notifier.url.clone();
+                if let Ok(mut segments) = url.path_segments_mut() {
+                    segments.pop_if_empty().extend([
+                        "_matrix",
+                        "client",
+                        "v3",
+                        "rooms",
+                        notifier.room_id.as_deref().unwrap_or_default(),
+                        "send",
+                        "m.room.message",
+                        txn_id,
+                    ]);
+                }
+                client
+                    .put(url)
+                    .bearer_auth(notifier.token.as_deref().unwrap_or_default())
+                    .header("content-type", "application/json")
+                    .body(json!({ "msgtype": "m.text", "body": message }).to_string())
+            }
+        };
+        request.timeout(DELIVERY_TIMEOUT)
+    }
+
+    /// Where a notifier posts, as the key its digest schedule is stored under.
+    fn target(notifier: &NotifierConfig) -> String {
+        match notifier.room_id.as_deref() {
+            Some(room_id) => format!("{}#{}", notifier.url, room_id),
+            None => notifier.url.to_string(),
+        }
+    }
+
+    fn truncate(message: &str) -> String {
+        if message.chars().count() <= MAX_MESSAGE_CHARS {
+            return message.to_string();
+        }
+        let mut truncated: String = message.chars().take(MAX_MESSAGE_CHARS - 1).collect();
+        truncated.push('…');
+        truncated
+    }
+
+    fn format_time(at: OffsetDateTime) -> String {
+        at.format(&Rfc3339).expect("failed to format timestamp")
+    }
+}
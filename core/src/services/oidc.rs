@@ -0,0 +1,402 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::http::HeaderMap;
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+use tokio::sync::OnceCell;
+use tracing::{info, warn};
+use url::Url;
+
+use super::auth::KeyRow;
+use crate::config::OidcConfig;
+use crate::errors::AppError;
+use crate::services::{ApiKeyService, AuthService, UserService};
+use crate::types::{Dependencies, OidcCallbackParams, Scope, SessionResponse};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long the browser has to come back from the provider.
+const LOGIN_TTL_SECS: u64 = 600;
+const SSO_KEY_NAME: &str = "sso session";
+
+/// The provider's discovery document, fetched once on first use.
+#[derive(Deserialize)]
+struct Provider {
+    issuer: String,
+    authorization_endpoint: Url,
+    token_endpoint: Url,
+    userinfo_endpoint: Option<Url>,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    id_token: String,
+    access_token: Option<String>,
+}
+
+/// OpenID Connect sign-in with the authorization code flow and PKCE. The browser carries
+/// the state, nonce, and code verifier between the two legs in a short-lived HttpOnly
+/// cookie, so nothing is stored until a session starts. A signed-in user gets an API key
+/// scoped from their odin account that expires with the session.
+#[derive(Clone)]
+pub struct OidcService {
+    deps: Arc<Dependencies>,
+    auth: AuthService,
+    client: reqwest::Client,
+    provider: Arc<OnceCell<Provider>>,
+}
+
+impl OidcService {
+    /// Carries `state.nonce.verifier` from the login redirect to the callback.
+    pub const LOGIN_COOKIE: &str = "odin_oidc";
+
+    pub fn new(deps: Arc<Dependencies>, auth: AuthService) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .user_agent("odin/0.1")
+            .build()
+            .expect("failed to build oidc http client");
+        Self {
+            deps,
+            auth,
+            client,
+            provider: Arc::new(OnceCell::new()),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.deps.config.oidc.issuer_url.is_some()
+    }
+
+    /// Where the browser lands after signing in.
+    pub fn post_login_redirect(&self) -> &str {
+        &self.deps.config.oidc.post_login_redirect
+    }
+
+    /// Where to send the browser to sign in, and the `Set-Cookie` value holding the
+    /// login state.
+    pub async fn begin(&self) -> Result<(String, String), AppError> {
+        let (config, issuer, redirect_url) = self.config()?;
+        let provider = self.provider(issuer).await?;
+
+        let state = AuthService::generate_token();
+        let nonce = AuthService::generate_token();
+        let verifier = AuthService::generate_token();
+        let challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()));
+
+        let mut location = provider.authorization_endpoint.clone();
+        location
+            .query_pairs_mut()
+            .append_pair("response_type", "code")
+            .append_pair("client_id", &config.client_id)
+            .append_pair("redirect_uri", redirect_url.as_str())
+            .append_pair("scope", &config.scopes.join(" "))
+            .append_pair("state", &state)
+            .append_pair("nonce", &nonce)
+            .append_pair("code_challenge", &challenge)
+            .append_pair("code_challenge_method", "S256");
+        let cookie =
+            self.login_cookie(&format!("{}.{}.{}", state, nonce, verifier), LOGIN_TTL_SECS);
+        Ok((location.into(), cookie))
+    }
+
+    /// Complete sign-in from the provider's redirect: check the state, redeem the code,
+    /// validate the ID token, map its claims to a user, and start a session.
+    pub async fn finish(
+        &self,
+        headers: &HeaderMap,
+        params: OidcCallbackParams,
+    ) -> Result<(String, SessionResponse), AppError> {
+        let (config, issuer, redirect_url) = self.config()?;
+        if let Some(error) = params.error {
+            let detail = params.error_description.unwrap_or_default();
+            warn!("sso sign-in refused by provider: {} {}", error, detail);
+            return Err(AppError::unauthorized(format!(
+                "identity provider refused sign-in: {}",
+                error
+            )));
+        }
+        let login = AuthService::cookie(headers, Self::LOGIN_COOKIE)
+            .and_then(|value| {
+                let mut parts = value.splitn(3, '.');
+                Some((parts.next()?, parts.next()?, parts.next()?))
+            })
+            .ok_or_else(|| AppError::bad_request("sign-in expired; start again"))?;
+        let (state, nonce, verifier) = login;
+        let sent_state = params.state.unwrap_or_default();
+        if !AuthService::digests_match(&sent_state, state) {
+            return Err(AppError::bad_request("sign-in state does not match"));
+        }
+        let Some(code) = params.code.filter(|code| !code.is_empty()) else {
+            return Err(AppError::bad_request("missing authorization code"));
+        };
+
+        let provider = self.provider(issuer).await?;
+        let mut form = vec![
+            ("grant_type", "authorization_code"),
+            ("code", code.as_str()),
+            ("redirect_uri", redirect_url.as_str()),
+            ("client_id", config.client_id.as_str()),
+            ("code_verifier", verifier),
+        ];
+        if let Some(secret) = &config.client_secret {
+            form.push(("client_secret", secret.as_str()));
+        }
+        let tokens: TokenResponse = self
+            .fetch_json(
+                self.client
+                    .post(provider.token_endpoint.clone())
+                    .form(&form),
+                "token exchange",
+            )
+            .await?;
+
+        // The ID token comes straight from the token endpoint over TLS, which OpenID
+        // Connect Core 3.1.3.7 accepts in place of checking its signature.
+        let mut claims = Self::id_token_claims(&tokens.id_token)?;
+        Self::validate_claims(&claims, provider, &config.client_id, nonce)?;
+        let missing_groups =
+            !config.admin_groups.is_empty() && claims.get(&config.groups_claim).is_none();
+        if (claims.get(&config.username_claim).is_none() || missing_groups)
+            && let (Some(endpoint), Some(access_token)) =
+                (&provider.userinfo_endpoint, &tokens.access_token)
+        {
+            let userinfo: Value = self
+                .fetch_json(
+                    self.client.get(endpoint.clone()).bearer_auth(access_token),
+                    "userinfo",
+                )
+                .await?;
+            if userinfo.get("sub") != claims.get("sub") {
+                return Err(AppError::unauthorized("userinfo subject does not match"));
+            }
+            if let (Value::Object(claims), Value::Object(userinfo)) = (&mut claims, userinfo) {
+                for (name, value) in userinfo {
+                    claims.entry(name).or_insert(value);
+                }
+            }
+        }
+
+        let key = self.session_key(config, &claims).await?;
+        self.auth.start_session(key).await
+    }
+
+    /// `Set-Cookie` value that removes the login state cookie.
+    pub fn clear_login_cookie(&self) -> String {
+        self.login_cookie("", 0)
+    }
+
+    /// Find or create the user named by `username_claim` and mint a key for the session,
+    /// expiring with it.
+    async fn session_key(&self, config: &OidcConfig, claims: &Value) -> Result<KeyRow, AppError> {
+        let username = match claims.get(&config.username_claim) {
+            Some(Value::String(username)) => username.trim().to_string(),
+            _ => {
+                return Err(AppError::forbidden(format!(
+                    "identity provider did not send the {} claim",
+                    config.username_claim
+                )));
+            }
+        };
+        let in_admin_group = match claims.get(&config.groups_claim) {
+            Some(Value::Array(groups)) => groups
+                .iter()
+                .filter_map(Value::as_str)
+                .any(|group| config.admin_groups.iter().any(|admin| admin == group)),
+            Some(Value::String(group)) => config.admin_groups.contains(group),
+            _ => false,
+        };
+
+        let mut tx = self.deps.db.begin().await?;
+        let user: Option<(i64, bool)> =
+            sqlx::query_as("SELECT id, is_admin FROM users WHERE username = ?1")
+                .bind(&username)
+                .fetch_optional(&mut *tx)
+                .await?;
+        let (user_id, is_admin) = match user {
+            Some(user) => user,
+            None if config.create_users => {
+                self.deps.ensure_writable()?;
+                UserService::validate_username(&username)?;
+                let id = sqlx::query(
+                    "INSERT INTO users (username, is_admin, created_at) VALUES (?1, ?2, ?3)",
+                )
+                .bind(&username)
+                .bind(in_admin_group)
+                .bind(Self::format_time(OffsetDateTime::now_utc()))
+                .execute(&mut *tx)
+                .await?
+                .last_insert_rowid();
+                info!(
+                    "user created from sso sign-in: id={} username={}",
+                    id, username
+                );
+                (id, in_admin_group)
+            }
+            None => {
+                warn!("sso sign-in refused for unknown user {}", username);
+                return Err(AppError::forbidden(format!(
+                    "no odin user named {}",
+                    username
+                )));
+            }
+        };
+
+        let scopes: &[Scope] = if is_admin || in_admin_group {
+            &Scope::ALL
+        } else {
+            &Scope::DEFAULT
+        };
+        let expires_at = Self::format_time(
+            OffsetDateTime::now_utc()
+                + time::Duration::seconds_f64(self.deps.config.auth.session_ttl_secs as f64),
+        );
+        let (key_id, _) =
+            ApiKeyService::insert_key(&mut tx, user_id, SSO_KEY_NAME, scopes, None).await?;
+        sqlx::query("UPDATE api_keys SET expires_at = ?1 WHERE id = ?2")
+            .bind(&expires_at)
+            .bind(key_id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        info!(user_id, key_id, "sso sign-in for {}", username);
+
+        Ok((
+            key_id,
+            user_id,
+            String::new(),
+            Scope::join(scopes),
+            None,
+            Some(expires_at),
+        ))
+    }
+
+    fn config(&self) -> Result<(&OidcConfig, &Url, &Url), AppError> {
+        let config = &self.deps.config.oidc;
+        match (&config.issuer_url, &config.redirect_url) {
+            (Some(issuer), Some(redirect_url)) => Ok((config, issuer, redirect_url)),
+            _ => Err(AppError::not_found("single sign-on is not configured")),
+        }
+    }
+
+    async fn provider(&self, issuer: &Url) -> Result<&Provider, AppError> {
+        self.provider
+            .get_or_try_init(|| async {
+                let discovery = Url::parse(&format!(
+                    "{}/.well-known/openid-configuration",
+                    issuer.as_str().trim_end_matches('/')
+                ))
+                .map_err(|_| AppError::unavailable("invalid identity provider url"))?;
+                let provider: Provider = self
+                    .fetch_json(self.client.get(discovery), "discovery")
+                    .await?;
+                info!("sso provider discovered: {}", provider.issuer);
+                Ok(provider)
+            })
+            .await
+    }
+
+    async fn fetch_json<T: DeserializeOwned>(
+        &self,
+        request: reqwest::RequestBuilder,
+        step: &str,
+    ) -> Result<T, AppError> {
+        let response = request
+            .header(reqwest::header::ACCEPT, "application/json")
+            .send()
+            .await
+            .map_err(|err| {
+                warn!("sso {} failed: {}", step, err);
+                AppError::unavailable("identity provider unavailable")
+            })?;
+        let status = response.status();
+        let body = response.bytes().await.map_err(|err| {
+            warn!("sso {} failed: {}", step, err);
+            AppError::unavailable("identity provider unavailable")
+        })?;
+        if !status.is_success() {
+            warn!(
+                "sso {} failed with {}: {}",
+                step,
+                status,
+                String::from_utf8_lossy(&body)
+            );
+            return Err(AppError::unauthorized(format!("sso {} failed", step)));
+        }
+        serde_json::from_slice(&body).map_err(|err| {
+            warn!("sso {} returned an unexpected body: {}", step, err);
+            AppError::unavailable("identity provider sent an unexpected response")
+        })
+    }
+
+    fn id_token_claims(id_token: &str) -> Result<Value, AppError> {
+        let invalid = || AppError::unauthorized("invalid id token");
+        let payload = id_token.split('.').nth(1).ok_or_else(invalid)?;
+        let payload = URL_SAFE_NO_PAD
+            .decode(payload.trim_end_matches('='))
+            .map_err(|_| invalid())?;
+        serde_json::from_slice(&payload).map_err(|_| invalid())
+    }
+
+    fn validate_claims(
+        claims: &Value,
+        provider: &Provider,
+        client_id: &str,
+        nonce: &str,
+    ) -> Result<(), AppError> {
+        if claims.get("iss").and_then(Value::as_str) != Some(provider.issuer.as_str()) {
+            return Err(AppError::unauthorized("id token issuer does not match"));
+        }
+        let audience_matches = match claims.get("aud") {
+            Some(Value::String(aud)) => aud == client_id,
+            Some(Value::Array(aud)) => aud.iter().any(|aud| aud.as_str() == Some(client_id)),
+            _ => false,
+        };
+        if !audience_matches {
+            return Err(AppError::unauthorized("id token audience does not match"));
+        }
+        let expires = claims
+            .get("exp")
+            .and_then(Value::as_i64)
+            .unwrap_or_default();
+        if expires <= OffsetDateTime::now_utc().unix_timestamp() {
+            return Err(AppError::unauthorized("id token expired"));
+        }
+        let sent_nonce = claims
+            .get("nonce")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        if !AuthService::digests_match(sent_nonce, nonce) {
+            return Err(AppError::unauthorized("id token nonce does not match"));
+        }
+        if claims.get("sub").and_then(Value::as_str).is_none() {
+            return Err(AppError::unauthorized("id token has no subject"));
+        }
+        Ok(())
+    }
+
+    /// `SameSite=Lax`, since the provider's redirect back is a cross-site navigation.
+    fn login_cookie(&self, value: &str, max_age: u64) -> String {
+        format!(
+            "{}={}; Max-Age={}; Path=/v1/auth/oidc; HttpOnly; SameSite=Lax{}",
+            Self::LOGIN_COOKIE,
+            value,
+            max_age,
+            if self.auth.secure_cookies() {
+                "; Secure"
+            } else {
+                ""
+            }
+        )
+    }
+
+    fn format_time(at: OffsetDateTime) -> String {
+        at.format(&Rfc3339).expect("failed to format timestamp")
+    }
+}
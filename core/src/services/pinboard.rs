@@ -0,0 +1,212 @@
+use std::sync::Arc;
+
+use md5::{Digest, Md5};
+use sqlx::FromRow;
+use time::format_description::well_known::Rfc3339;
+use time::{OffsetDateTime, UtcOffset};
+use tracing::info;
+
+use crate::errors::AppError;
+use crate::services::{BookmarkService, IngestService};
+use crate::tags;
+use crate::types::{
+    BookmarkTagsRequest, Caller, Dependencies, IngestUrlsRequest, PinboardAddParams,
+    PinboardAllParams, PinboardDeleteParams, PinboardPost,
+};
+
+#[derive(FromRow)]
+struct Row {
+    id: i64,
+    url: String,
+    title: Option<String>,
+    excerpt: Option<String>,
+    created_at: String,
+    updated_at: String,
+}
+
+/// The `posts/add`, `posts/delete`, and `posts/all` subset of the Pinboard v1 API.
+/// Outcomes are Pinboard result codes (`done`, `item not found`, ...), which its
+/// clients expect in a successful response rather than as HTTP errors.
+#[derive(Clone)]
+pub struct PinboardService {
+    deps: Arc<Dependencies>,
+    bookmarks: BookmarkService,
+    ingest: IngestService,
+}
+
+impl PinboardService {
+    pub const DONE: &str = "done";
+    const MAX_RESULTS: u32 = 10_000;
+
+    pub fn new(deps: Arc<Dependencies>, bookmarks: BookmarkService, ingest: IngestService) -> Self {
+        Self {
+            deps,
+            bookmarks,
+            ingest,
+        }
+    }
+
+    pub async fn add(
+        &self,
+        caller: Caller,
+        params: PinboardAddParams,
+    ) -> Result<&'static str, AppError> {
+        let Some(url) = params.url.as_deref().and_then(IngestService::normalize_url) else {
+            return Ok("missing url");
+        };
+        let tags: Option<Vec<String>> = params.tags.as_deref().map(|tags| {
+            tags.split(|c: char| c.is_whitespace() || c == ',')
+                .filter(|tag| !tag.is_empty())
+                .map(str::to_string)
+                .collect()
+        });
+        if let Some(id) = self.find(&caller, &url).await? {
+            if params.replace.as_deref() == Some("no") {
+                return Ok("item already exists");
+            }
+            // Replacing a post replaces its tags, as on Pinboard.
+            if let Some(tags) = tags {
+                self.bookmarks
+                    .set_tags(caller, id, BookmarkTagsRequest { tags })
+                    .await?;
+            }
+            return Ok(Self::DONE);
+        }
+        let response = self
+            .ingest
+            .ingest_urls(
+                caller,
+                IngestUrlsRequest {
+                    urls: vec![url],
+                    tags: tags.unwrap_or_default(),
+                },
+            )
+            .await?;
+        info!("pinboard post added: accepted={}", response.accepted);
+        Ok(Self::DONE)
+    }
+
+    pub async fn delete(
+        &self,
+        caller: Caller,
+        params: PinboardDeleteParams,
+    ) -> Result<&'static str, AppError> {
+        let Some(url) = params.url.as_deref().and_then(IngestService::normalize_url) else {
+            return Ok("item not found");
+        };
+        let Some(id) = self.find(&caller, &url).await? else {
+            return Ok("item not found");
+        };
+        self.bookmarks.delete(caller, id).await?;
+        Ok(Self::DONE)
+    }
+
+    /// Newest first.
+    pub async fn all(
+        &self,
+        caller: Caller,
+        params: PinboardAllParams,
+    ) -> Result<Vec<PinboardPost>, AppError> {
+        let tag = params
+            .tag
+            .as_deref()
+            .filter(|tag| !tag.trim().is_empty())
+            .map(tags::normalize)
+            .transpose()?;
+        let from = params.fromdt.as_deref().map(Self::parse_time).transpose()?;
+        let to = params.todt.as_deref().map(Self::parse_time).transpose()?;
+        let rows: Vec<Row> = sqlx::query_as(
+            r#"
+            SELECT id, url, title, excerpt, created_at, updated_at
+            FROM bookmarks
+            WHERE owner_id = ?1 AND namespace = ?2
+              AND (?3 IS NULL OR created_at >= ?3)
+              AND (?4 IS NULL OR created_at <= ?4)
+              AND (?7 IS NULL OR id IN (
+                  SELECT bt.bookmark_id FROM bookmark_tags bt JOIN tags t ON t.id = bt.tag_id
+                  WHERE t.owner_id = ?1 AND t.namespace = ?2 AND t.name = ?7
+              ))
+            ORDER BY created_at DESC, id DESC
+            LIMIT ?5 OFFSET ?6
+            "#,
+        )
+        .bind(caller.user_id)
+        .bind(&caller.namespace)
+        .bind(from)
+        .bind(to)
+        .bind(
+            params
+                .results
+                .unwrap_or(Self::MAX_RESULTS)
+                .min(Self::MAX_RESULTS),
+        )
+        .bind(params.start.unwrap_or(0))
+        .bind(tag)
+        .fetch_all(&self.deps.db)
+        .await?;
+        let ids: Vec<i64> = rows.iter().map(|row| row.id).collect();
+        let mut tags = tags::of_bookmarks(&self.deps.db, &ids).await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| {
+                let tags = tags.remove(&row.id).unwrap_or_default();
+                Self::post(row, tags)
+            })
+            .collect())
+    }
+
+    async fn find(&self, caller: &Caller, url: &str) -> Result<Option<i64>, AppError> {
+        let id = sqlx::query_scalar(
+            "SELECT id FROM bookmarks WHERE url = ?1 AND owner_id = ?2 AND namespace = ?3",
+        )
+        .bind(url)
+        .bind(caller.user_id)
+        .bind(&caller.namespace)
+        .fetch_optional(&self.deps.db)
+        .await?;
+        Ok(id)
+    }
+
+    /// `hash` is the MD5 of the URL as Pinboard defines it; `meta` changes whenever
+    /// the bookmark does, which clients use to spot edits. Tags are space-separated.
+    fn post(row: Row, tags: Vec<String>) -> PinboardPost {
+        let hash = hex::encode(Md5::digest(row.url.as_bytes()));
+        let tags = tags.join(" ");
+        let meta = hex::encode(Md5::digest(
+            format!(
+                "{}\n{}\n{}\n{}",
+                row.url,
+                row.title.as_deref().unwrap_or_default(),
+                row.updated_at,
+                tags
+            )
+            .as_bytes(),
+        ));
+        PinboardPost {
+            description: row.title.unwrap_or_else(|| row.url.clone()),
+            extended: row.excerpt.unwrap_or_default(),
+            href: row.url,
+            meta,
+            hash,
+            time: OffsetDateTime::parse(&row.created_at, &Rfc3339)
+                .ok()
+                .and_then(|at| at.replace_nanosecond(0).ok())
+                .and_then(|at| at.format(&Rfc3339).ok())
+                .unwrap_or(row.created_at),
+            shared: "no",
+            toread: "no",
+            tags,
+        }
+    }
+
+    /// In UTC like the stored timestamps, so the two compare as strings.
+    fn parse_time(value: &str) -> Result<String, AppError> {
+        OffsetDateTime::parse(value, &Rfc3339)
+            .ok()
+            .map(|at| at.to_offset(UtcOffset::UTC))
+            .and_then(|at| at.format(&Rfc3339).ok())
+            .ok_or_else(|| {
+                AppError::bad_request("dates must be RFC 3339, e.g. 2024-01-31T00:00:00Z")
+            })
+    }
+}
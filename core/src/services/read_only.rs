@@ -0,0 +1,69 @@
+use std::sync::Arc;
+
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+use tracing::{info, warn};
+
+use crate::errors::AppError;
+use crate::types::{Dependencies, ReadOnlyStatus, SetReadOnlyRequest};
+
+/// Toggles read-only mode for backups, migrations, and index rebuilds. Writes already
+/// queued (ingest workers, running jobs) still finish; admin operations stay available.
+#[derive(Clone)]
+pub struct ReadOnlyService {
+    deps: Arc<Dependencies>,
+}
+
+impl ReadOnlyService {
+    pub fn new(deps: Arc<Dependencies>) -> Self {
+        Self { deps }
+    }
+
+    pub fn get(&self) -> ReadOnlyStatus {
+        self.deps
+            .read_only
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    /// Turn read-only mode on or off; turning it on again only replaces the message.
+    pub fn set(&self, request: SetReadOnlyRequest) -> Result<ReadOnlyStatus, AppError> {
+        let message = request
+            .message
+            .map(|message| message.trim().to_string())
+            .filter(|message| !message.is_empty());
+        if !request.enabled && message.is_some() {
+            return Err(AppError::bad_request(
+                "message is only allowed when enabling read-only mode",
+            ));
+        }
+
+        let mut read_only = self
+            .deps
+            .read_only
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if request.enabled {
+            let since = match read_only.since.take() {
+                Some(since) => since,
+                None => OffsetDateTime::now_utc()
+                    .format(&Rfc3339)
+                    .expect("failed to format timestamp"),
+            };
+            *read_only = ReadOnlyStatus {
+                enabled: true,
+                message,
+                since: Some(since),
+            };
+            warn!(
+                "read-only mode enabled: {}",
+                read_only.message.as_deref().unwrap_or("no message")
+            );
+        } else if read_only.enabled {
+            *read_only = ReadOnlyStatus::default();
+            info!("read-only mode disabled");
+        }
+        Ok(read_only.clone())
+    }
+}
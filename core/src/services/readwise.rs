@@ -0,0 +1,274 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use reqwest::StatusCode;
+use serde_json::json;
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+use tracing::{info, warn};
+
+use crate::errors::AppError;
+use crate::types::{Caller, Dependencies, ReadwiseConnectRequest, ReadwiseStatus};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Pushes each connected user's bookmarks to Readwise Reader, oldest first, remembering
+/// the last bookmark id sent. Readwise fetches the pages itself, so queued bookmarks are
+/// pushed too. Odin keeps no highlights, so there are none to send. The token is stored
+/// as given, since every push needs it, and is never returned by the API.
+#[derive(Clone)]
+pub struct ReadwiseService {
+    deps: Arc<Dependencies>,
+}
+
+impl ReadwiseService {
+    pub fn new(deps: Arc<Dependencies>) -> Self {
+        Self { deps }
+    }
+
+    pub async fn status(&self, caller: Caller) -> Result<ReadwiseStatus, AppError> {
+        let row: Option<(i64, Option<String>, Option<String>)> = sqlx::query_as(
+            "SELECT last_bookmark_id, last_synced_at, last_error FROM readwise_sync WHERE user_id = ?1",
+        )
+        .bind(caller.user_id)
+        .fetch_optional(&self.deps.db)
+        .await?;
+        let Some((last_bookmark_id, last_synced_at, last_error)) = row else {
+            return Ok(ReadwiseStatus {
+                connected: false,
+                last_bookmark_id: 0,
+                pending: 0,
+                last_synced_at: None,
+                last_error: None,
+            });
+        };
+        let pending: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM bookmarks WHERE owner_id = ?1 AND id > ?2")
+                .bind(caller.user_id)
+                .bind(last_bookmark_id)
+                .fetch_one(&self.deps.db)
+                .await?;
+        Ok(ReadwiseStatus {
+            connected: true,
+            last_bookmark_id,
+            pending,
+            last_synced_at,
+            last_error,
+        })
+    }
+
+    /// Check the token with Readwise, then store it; reconnecting replaces the token
+    /// and starts over (or from now).
+    pub async fn connect(
+        &self,
+        caller: Caller,
+        payload: ReadwiseConnectRequest,
+    ) -> Result<ReadwiseStatus, AppError> {
+        self.deps.ensure_writable()?;
+        let token = payload.token.trim();
+        if token.is_empty() {
+            return Err(AppError::bad_request("token must not be empty"));
+        }
+        let response = self
+            .deps
+            .http_client
+            .get(self.api_url("/api/v2/auth/"))
+            .header("authorization", format!("Token {}", token))
+            .timeout(REQUEST_TIMEOUT)
+            .send()
+            .await
+            .map_err(|err| AppError::unavailable(format!("Readwise unreachable: {}", err)))?;
+        match response.status() {
+            status if status.is_success() => {}
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+                return Err(AppError::bad_request("Readwise rejected the token"));
+            }
+            status => {
+                return Err(AppError::unavailable(format!(
+                    "Readwise token check failed: http status {}",
+                    status
+                )));
+            }
+        }
+
+        let cursor: i64 = if payload.from_now {
+            sqlx::query_scalar("SELECT COALESCE(MAX(id), 0) FROM bookmarks WHERE owner_id = ?1")
+                .bind(caller.user_id)
+                .fetch_one(&self.deps.db)
+                .await?
+        } else {
+            0
+        };
+        sqlx::query(
+            r#"
+            INSERT INTO readwise_sync (user_id, token, last_bookmark_id, created_at)
+            VALUES (?1, ?2, ?3, ?4)
+            ON CONFLICT(user_id) DO UPDATE SET
+                token = excluded.token,
+                last_bookmark_id = excluded.last_bookmark_id,
+                last_synced_at = NULL,
+                last_error = NULL
+            "#,
+        )
+        .bind(caller.user_id)
+        .bind(token)
+        .bind(cursor)
+        .bind(Self::now())
+        .execute(&self.deps.db)
+        .await?;
+        info!(
+            "readwise connected: user_id={} from_bookmark={}",
+            caller.user_id, cursor
+        );
+        self.status(caller).await
+    }
+
+    pub async fn disconnect(&self, caller: Caller) -> Result<(), AppError> {
+        self.deps.ensure_writable()?;
+        let result = sqlx::query("DELETE FROM readwise_sync WHERE user_id = ?1")
+            .bind(caller.user_id)
+            .execute(&self.deps.db)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(AppError::not_found("readwise is not connected"));
+        }
+        info!("readwise disconnected: user_id={}", caller.user_id);
+        Ok(())
+    }
+
+    /// Push every `readwise.sync_interval_secs` until shutdown, unless that is 0.
+    pub fn start(&self) {
+        let interval = self.deps.config.readwise.sync_interval_secs;
+        if interval == 0 {
+            return;
+        }
+        let service = self.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = service.deps.shutdown.cancelled() => return,
+                    _ = tokio::time::sleep(Duration::from_secs(interval)) => {}
+                }
+                // The cursor is a write, so read-only mode pauses pushes.
+                if service.deps.ensure_writable().is_err() {
+                    continue;
+                }
+                if let Err(err) = service.sync().await {
+                    warn!("readwise sync failed: {:?}", err);
+                }
+            }
+        });
+    }
+
+    async fn sync(&self) -> Result<(), AppError> {
+        let users: Vec<(i64, String, i64)> =
+            sqlx::query_as("SELECT user_id, token, last_bookmark_id FROM readwise_sync")
+                .fetch_all(&self.deps.db)
+                .await?;
+        for (user_id, token, cursor) in users {
+            self.sync_user(user_id, &token, cursor).await?;
+        }
+        Ok(())
+    }
+
+    /// Push up to `batch_size` bookmarks, stopping at the first failure so the order holds.
+    async fn sync_user(&self, user_id: i64, token: &str, cursor: i64) -> Result<(), AppError> {
+        let bookmarks: Vec<(i64, String, Option<String>, Option<String>)> = sqlx::query_as(
+            r#"
+            SELECT id, url, title, excerpt
+            FROM bookmarks
+            WHERE owner_id = ?1 AND id > ?2
+            ORDER BY id
+            LIMIT ?3
+            "#,
+        )
+        .bind(user_id)
+        .bind(cursor)
+        .bind(self.deps.config.readwise.batch_size as i64)
+        .fetch_all(&self.deps.db)
+        .await?;
+        if bookmarks.is_empty() {
+            return Ok(());
+        }
+
+        let mut last_sent = cursor;
+        let mut error = None;
+        for (id, url, title, excerpt) in bookmarks {
+            let mut document = json!({ "url": url, "saved_using": "odin" });
+            if let Some(title) = title {
+                document["title"] = title.into();
+            }
+            if let Some(excerpt) = excerpt {
+                document["summary"] = excerpt.into();
+            }
+            let sent = self
+                .deps
+                .http_client
+                .post(self.api_url("/api/v3/save/"))
+                .header("authorization", format!("Token {}", token))
+                .header("content-type", "application/json")
+                .timeout(REQUEST_TIMEOUT)
+                .body(document.to_string())
+                .send()
+                .await;
+            match sent {
+                Ok(response) if response.status().is_success() => last_sent = id,
+                // Over the rate limit: the rest goes out next time, and that is no error.
+                Ok(response) if response.status() == StatusCode::TOO_MANY_REQUESTS => break,
+                Ok(response) if response.status() == StatusCode::UNAUTHORIZED => {
+                    error = Some("Readwise rejected the token; reconnect".to_string());
+                    break;
+                }
+                Ok(response) => {
+                    error = Some(format!("http status {}", response.status()));
+                    break;
+                }
+                Err(err) => {
+                    error = Some(err.without_url().to_string());
+                    break;
+                }
+            }
+        }
+
+        if let Some(error) = &error {
+            warn!("readwise push failed: user_id={} error={}", user_id, error);
+        }
+        info!(
+            "readwise pushed: user_id={} through_bookmark={}",
+            user_id, last_sent
+        );
+        sqlx::query(
+            r#"
+            UPDATE readwise_sync
+            SET last_bookmark_id = ?2, last_synced_at = ?3, last_error = ?4
+            WHERE user_id = ?1
+            "#,
+        )
+        .bind(user_id)
+        .bind(last_sent)
+        .bind(Self::now())
+        .bind(error)
+        .execute(&self.deps.db)
+        .await?;
+        Ok(())
+    }
+
+    fn api_url(&self, path: &str) -> String {
+        format!(
+            "{}{}",
+            self.deps
+                .config
+                .readwise
+                .api_url
+                .as_str()
+                .trim_end_matches('/'),
+            path
+        )
+    }
+
+    fn now() -> String {
+        OffsetDateTime::now_utc()
+            .format(&Rfc3339)
+            .expect("failed to format timestamp")
+    }
+}
@@ -0,0 +1,161 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Instant;
+
+use anyhow::Context;
+use tracing::{info, warn};
+
+use crate::errors::AppError;
+use crate::index;
+use crate::services::IngestService;
+use crate::types::{ConsistencyResponse, Dependencies, ReconcileResponse};
+
+/// Repairs drift between SQLite and the index, such as a crash between an index
+/// commit and the matching row update.
+#[derive(Clone)]
+pub struct ReconcileService {
+    deps: Arc<Dependencies>,
+    ingest: IngestService,
+    /// Held while a run is in progress so scheduled and manual runs never overlap.
+    running: Arc<tokio::sync::Mutex<()>>,
+}
+
+impl ReconcileService {
+    pub fn new(deps: Arc<Dependencies>, ingest: IngestService) -> Self {
+        Self {
+            deps,
+            ingest,
+            running: Arc::new(tokio::sync::Mutex::new(())),
+        }
+    }
+
+    /// Reconcile every `maintenance.reconcile_interval_secs`.
+    pub fn start(&self) {
+        let service = self.clone();
+        tokio::spawn(async move {
+            while service
+                .deps
+                .wait_interval(|config| config.reconcile_interval_secs)
+                .await
+            {
+                if let Err(err) = service.reconcile().await {
+                    warn!("index reconciliation failed: {:?}", err);
+                }
+            }
+        });
+    }
+
+    /// Re-add bookmarks marked `indexed` that have no index document and delete
+    /// documents whose bookmark row is gone, reporting what was repaired.
+    pub async fn reconcile(&self) -> Result<ReconcileResponse, AppError> {
+        let Ok(_running) = self.running.try_lock() else {
+            return Err(AppError::conflict("reconciliation is already running"));
+        };
+        let started = Instant::now();
+
+        let drift = {
+            // No commit can land while both sides are read and orphans are removed.
+            let mut writer = self.deps.writer.lock().await;
+            let drift = self.compare().await?;
+            if !drift.orphaned.is_empty() {
+                for &id in &drift.orphaned {
+                    writer.delete_bookmark(id);
+                }
+                self.deps
+                    .commit_index(&mut writer, "reconcile")
+                    .context("commit orphan removals")?;
+            }
+            drift
+        };
+
+        // A bookmark deleted meanwhile has no stored text or row left and is skipped.
+        let (reindexed, refetch_queued) = self
+            .ingest
+            .restore_documents(&drift.missing)
+            .await
+            .context("restore missing documents")?;
+
+        let response = ReconcileResponse {
+            bookmarks_checked: drift.bookmarks_checked,
+            index_docs_checked: drift.index_docs_checked,
+            reindexed,
+            refetch_queued,
+            removed_docs: drift.orphaned,
+            elapsed_ms: started.elapsed().as_millis() as u64,
+        };
+        if response.reindexed.is_empty()
+            && response.refetch_queued.is_empty()
+            && response.removed_docs.is_empty()
+        {
+            info!(
+                "index reconciled: no drift bookmarks={} docs={} elapsed_ms={}",
+                response.bookmarks_checked, response.index_docs_checked, response.elapsed_ms
+            );
+        } else {
+            warn!(
+                "index drift repaired: reindexed={:?} refetch_queued={:?} removed_docs={:?} elapsed_ms={}",
+                response.reindexed,
+                response.refetch_queued,
+                response.removed_docs,
+                response.elapsed_ms
+            );
+        }
+        Ok(response)
+    }
+
+    /// Report drift between SQLite and the index without repairing it.
+    pub async fn check(&self) -> Result<ConsistencyResponse, AppError> {
+        let started = Instant::now();
+        let drift = {
+            let _writer = self.deps.writer.lock().await;
+            self.compare().await?
+        };
+        Ok(ConsistencyResponse {
+            bookmarks_checked: drift.bookmarks_checked,
+            index_docs_checked: drift.index_docs_checked,
+            missing_from_index: drift.missing,
+            orphaned_docs: drift.orphaned,
+            elapsed_ms: started.elapsed().as_millis() as u64,
+        })
+    }
+
+    /// Compare bookmark rows with index documents. Callers hold the writer lock so no
+    /// commit lands between the two reads.
+    async fn compare(&self) -> Result<Drift, AppError> {
+        // Searchers must include every commit, or fresh documents would look missing.
+        self.deps.index.reload().context("reload index readers")?;
+        let index_ids =
+            index::bookmark_ids(&self.deps.index.searchers(), self.deps.fields.bookmark_id)
+                .context("scan index bookmark ids")?;
+        let rows: Vec<(i64, bool)> =
+            sqlx::query_as("SELECT id, status IN ('indexed', 'partial') FROM bookmarks")
+                .fetch_all(&self.deps.db)
+                .await?;
+
+        let mut missing = Vec::new();
+        let mut known = HashSet::with_capacity(rows.len());
+        for (id, indexed) in rows {
+            if indexed && !index_ids.contains(&id) {
+                missing.push(id);
+            }
+            known.insert(id);
+        }
+        let mut orphaned: Vec<i64> = index_ids.difference(&known).copied().collect();
+        orphaned.sort_unstable();
+        Ok(Drift {
+            bookmarks_checked: known.len(),
+            index_docs_checked: index_ids.len(),
+            missing,
+            orphaned,
+        })
+    }
+}
+
+struct Drift {
+    bookmarks_checked: usize,
+    index_docs_checked: usize,
+    /// Bookmarks marked indexed without a live document.
+    missing: Vec<i64>,
+    /// Bookmark ids with documents but no row.
+    orphaned: Vec<i64>,
+}
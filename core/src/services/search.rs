@@ -0,0 +1,387 @@
+use std::collections::{HashMap, HashSet};
+use std::ops::Bound;
+use std::sync::Arc;
+use std::time::Instant;
+
+use sqlx::{QueryBuilder, Sqlite};
+use tantivy::query::{BooleanQuery, Occur, Query, QueryParser, RangeQuery, TermQuery};
+use tantivy::schema::{IndexRecordOption, Value};
+use tantivy::{SnippetGenerator, Term};
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+use tracing::{info, warn};
+
+use crate::errors::AppError;
+use crate::types::{
+    Caller, Dependencies, RuntimeSettings, SearchParams, SearchResponse, SearchResultItem,
+    SearchSnippet, SearchSort,
+};
+use crate::{content, index, tags};
+
+/// Characters a query may have.
+const MAX_QUERY_CHARS: usize = 1000;
+/// Distinct terms a parsed query may search for, counting each word of a phrase.
+const MAX_QUERY_TERMS: usize = 64;
+/// Parenthesized groups a query may nest.
+const MAX_QUERY_DEPTH: usize = 8;
+/// Hits a page may reach into; collecting deeper pages costs more than reading them.
+const MAX_RESULT_DEPTH: u32 = 1000;
+/// Characters of page text a snippet shows.
+const SNIPPET_CHARS: usize = 200;
+
+#[derive(Clone)]
+pub struct SearchService {
+    deps: Arc<Dependencies>,
+}
+
+impl SearchService {
+    pub fn new(deps: Arc<Dependencies>) -> Self {
+        Self { deps }
+    }
+
+    /// Identifies a result set: the caller, the query, and the committed index state
+    /// (segment ids and delete opstamps survive restarts, unlike reader generations).
+    pub fn cache_key(&self, caller: &Caller, params: &SearchParams) -> String {
+        let searchers = self.deps.index.searchers();
+        let segments: Vec<String> = searchers
+            .iter()
+            .flat_map(|searcher| searcher.segment_readers())
+            .map(|segment| {
+                format!(
+                    "{}:{}",
+                    segment.segment_id().uuid_string(),
+                    segment.delete_opstamp().unwrap_or_default()
+                )
+            })
+            .collect();
+        format!(
+            "{}|{}|{}|{:?}|{:?}|{:?}|{:?}|{:?}|{:?}|{:?}|{}",
+            caller.user_id,
+            caller.namespace,
+            params.query.trim(),
+            params.page,
+            params.per_page,
+            params.tag,
+            params.site,
+            params.after,
+            params.before,
+            params.sort,
+            segments.join(",")
+        )
+    }
+
+    pub async fn search(
+        &self,
+        caller: Caller,
+        params: SearchParams,
+    ) -> Result<SearchResponse, AppError> {
+        let query = params.query.trim();
+        info!(
+            "search request received: q='{}' page={:?} per_page={:?} tag={:?} site={:?} after={:?} before={:?} sort={:?}",
+            query,
+            params.page,
+            params.per_page,
+            params.tag,
+            params.site,
+            params.after,
+            params.before,
+            params.sort
+        );
+        if query.is_empty() {
+            return Ok(SearchResponse {
+                total_hits: 0,
+                results: vec![],
+            });
+        }
+
+        let page = params.page.unwrap_or(1).max(1);
+        let per_page = params.per_page.unwrap_or(10).clamp(1, 50);
+        if page.saturating_mul(per_page) > MAX_RESULT_DEPTH {
+            return Err(AppError::bad_request(format!(
+                "page {} is too deep: results stop at hit {}",
+                page, MAX_RESULT_DEPTH
+            )));
+        }
+        let offset = ((page - 1) * per_page) as usize;
+
+        let started = Instant::now();
+        let searchers = self.deps.index.searchers();
+        let parsed_query = self.parse(query)?;
+        // Term weights come from the first shard only; they just rank passages.
+        let mut snippets =
+            SnippetGenerator::create(&searchers[0], parsed_query.as_ref(), self.deps.fields.body)?;
+        snippets.set_max_num_chars(SNIPPET_CHARS);
+        let owner_query: Box<dyn Query> = Box::new(TermQuery::new(
+            Term::from_field_u64(self.deps.fields.owner_id, caller.user_id as u64),
+            IndexRecordOption::Basic,
+        ));
+        let namespace_query: Box<dyn Query> = Box::new(TermQuery::new(
+            Term::from_field_text(self.deps.fields.namespace, &caller.namespace),
+            IndexRecordOption::Basic,
+        ));
+        let mut clauses = vec![
+            (Occur::Must, parsed_query),
+            (Occur::Must, owner_query),
+            (Occur::Must, namespace_query),
+        ];
+        if let Some(tag) = params.tag.as_deref() {
+            let tag_query: Box<dyn Query> = Box::new(TermQuery::new(
+                Term::from_field_text(self.deps.fields.tag, &tags::normalize(tag)?),
+                IndexRecordOption::Basic,
+            ));
+            clauses.push((Occur::Must, tag_query));
+        }
+        if let Some(site) = params.site.as_deref() {
+            let site_query: Box<dyn Query> = Box::new(TermQuery::new(
+                Term::from_field_text(self.deps.fields.host, &Self::site(site)?),
+                IndexRecordOption::Basic,
+            ));
+            clauses.push((Occur::Must, site_query));
+        }
+        let after = params.after.as_deref().map(Self::time).transpose()?;
+        let before = params.before.as_deref().map(Self::time).transpose()?;
+        if after.is_some() || before.is_some() {
+            let fetched_query: Box<dyn Query> = Box::new(RangeQuery::new_i64_bounds(
+                searchers[0]
+                    .schema()
+                    .get_field_name(self.deps.fields.fetched_at)
+                    .to_string(),
+                after.map_or(Bound::Unbounded, Bound::Included),
+                before.map_or(Bound::Unbounded, Bound::Excluded),
+            ));
+            clauses.push((Occur::Must, fetched_query));
+        }
+        let tantivy_query = BooleanQuery::new(clauses);
+
+        let ranking = match params.sort.unwrap_or_default() {
+            SearchSort::Relevance => index::Ranking::Score,
+            SearchSort::Recent => index::Ranking::Newest(self.deps.fields.fetched_at),
+        };
+        // The shard fan-out runs on its own threads and blocks until every shard is
+        // searched, so it waits on a blocking thread rather than an executor one.
+        let (total_hits, top_docs) = tokio::task::spawn_blocking(move || {
+            index::search_top(
+                &searchers,
+                &tantivy_query,
+                per_page as usize,
+                offset,
+                ranking,
+            )
+        })
+        .await
+        .map_err(anyhow::Error::from)??;
+
+        let mut results = top_docs
+            .into_iter()
+            .map(|(score, retrieved)| {
+                let bookmark_id = retrieved
+                    .get_first(self.deps.fields.bookmark_id)
+                    .and_then(|v| v.as_u64())
+                    .unwrap_or_default() as i64;
+                let url = retrieved
+                    .get_first(self.deps.fields.url)
+                    .and_then(|v| v.as_str())
+                    .map(|v| v.to_string())
+                    .unwrap_or_default();
+
+                let title = retrieved
+                    .get_first(self.deps.fields.title)
+                    .and_then(|v| v.as_str())
+                    .map(|v| v.to_string());
+
+                let excerpt = retrieved
+                    .get_first(self.deps.fields.excerpt)
+                    .and_then(|v| v.as_str())
+                    .map(|v| v.to_string());
+
+                SearchResultItem {
+                    bookmark_id,
+                    url,
+                    title,
+                    excerpt,
+                    snippet: None,
+                    score,
+                }
+            })
+            .collect::<Vec<_>>();
+        let ids = results.iter().map(|item| item.bookmark_id).collect();
+        let mut snippets = self.snippets(snippets, ids).await?;
+        for item in &mut results {
+            item.snippet = snippets.remove(&item.bookmark_id);
+        }
+
+        let elapsed = started.elapsed();
+        self.deps.metrics.observe_search(elapsed);
+        if RuntimeSettings::is_slow(self.deps.settings().slow_search_ms, elapsed) {
+            warn!(
+                query,
+                namespace = caller.namespace.as_str(),
+                total_hits,
+                returned = results.len(),
+                elapsed_ms = elapsed.as_millis() as u64,
+                "slow search"
+            );
+        }
+
+        info!(
+            "search completed: q='{}' total_hits={} returned={}",
+            query,
+            total_hits,
+            results.len()
+        );
+        Ok(SearchResponse {
+            total_hits,
+            results,
+        })
+    }
+
+    /// The passage of each bookmark's stored text that best matches the query, by id.
+    /// Bookmarks matched by title alone, or without stored text, get none.
+    async fn snippets(
+        &self,
+        generator: SnippetGenerator,
+        ids: Vec<i64>,
+    ) -> Result<HashMap<i64, SearchSnippet>, AppError> {
+        if ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let mut select = QueryBuilder::<Sqlite>::new(
+            "SELECT bookmark_id, CAST(body AS BLOB), encoding FROM bookmark_contents WHERE bookmark_id IN (",
+        );
+        let mut separated = select.separated(", ");
+        for id in ids {
+            separated.push_bind(id);
+        }
+        separated.push_unseparated(")");
+        let rows: Vec<(i64, Vec<u8>, String)> =
+            select.build_query_as().fetch_all(&self.deps.db).await?;
+
+        // Decompressing and tokenizing whole pages is CPU work; keep it off the executor.
+        let snippets = tokio::task::spawn_blocking(move || {
+            let mut snippets = HashMap::with_capacity(rows.len());
+            for (id, body, encoding) in rows {
+                let text = content::decode(&encoding, body)?;
+                let mut snippet = generator.snippet(&text);
+                if snippet.is_empty() {
+                    continue;
+                }
+                snippet.set_snippet_prefix_postfix("<mark>", "</mark>");
+                snippets.insert(
+                    id,
+                    SearchSnippet {
+                        text: snippet.fragment().to_string(),
+                        highlights: snippet
+                            .highlighted()
+                            .iter()
+                            .map(|range| [range.start, range.end])
+                            .collect(),
+                        html: snippet.to_html(),
+                    },
+                );
+            }
+            anyhow::Ok(snippets)
+        })
+        .await
+        .map_err(anyhow::Error::from)??;
+        Ok(snippets)
+    }
+
+    /// Whether one bookmark matches `query`, for alerts on newly indexed pages.
+    pub fn matches(&self, bookmark_id: i64, query: &str) -> Result<bool, AppError> {
+        let searchers = self.deps.index.searchers();
+        let bookmark_query: Box<dyn Query> = Box::new(TermQuery::new(
+            Term::from_field_u64(self.deps.fields.bookmark_id, bookmark_id as u64),
+            IndexRecordOption::Basic,
+        ));
+        let tantivy_query = BooleanQuery::new(vec![
+            (Occur::Must, self.parse(query)?),
+            (Occur::Must, bookmark_query),
+        ]);
+        let (total_hits, _) =
+            index::search_top(&searchers, &tantivy_query, 1, 0, index::Ranking::Score)?;
+        Ok(total_hits > 0)
+    }
+
+    /// A `site` filter as the `host` field holds it: lowercased, without a scheme, path,
+    /// or leading `www.`.
+    fn site(site: &str) -> Result<String, AppError> {
+        let site = site.trim().to_lowercase();
+        let site = site
+            .split_once("://")
+            .map_or(site.as_str(), |(_, rest)| rest);
+        let site = site
+            .split('/')
+            .next()
+            .unwrap_or_default()
+            .trim_end_matches('.');
+        let site = site.strip_prefix("www.").unwrap_or(site);
+        if site.is_empty() || site.contains(|c: char| c.is_whitespace() || c == ':') {
+            return Err(AppError::bad_request(
+                "site must be a host name, e.g. example.com",
+            ));
+        }
+        Ok(site.to_string())
+    }
+
+    /// An `after` or `before` bound as unix seconds; a bare date means UTC midnight.
+    fn time(value: &str) -> Result<i64, AppError> {
+        let value = value.trim();
+        OffsetDateTime::parse(value, &Rfc3339)
+            .or_else(|_| OffsetDateTime::parse(&format!("{}T00:00:00Z", value), &Rfc3339))
+            .map(OffsetDateTime::unix_timestamp)
+            .map_err(|_| {
+                AppError::bad_request(format!(
+                    "'{}' is not a date; use YYYY-MM-DD or RFC 3339, e.g. 2024-01-31T00:00:00Z",
+                    value
+                ))
+            })
+    }
+
+    /// Parse `query`, rejecting ones too long, too nested, or with too many terms to
+    /// run cheaply. Length and nesting are checked first, as they bound the parser itself.
+    fn parse(&self, query: &str) -> Result<Box<dyn Query>, AppError> {
+        if query.chars().count() > MAX_QUERY_CHARS {
+            return Err(AppError::bad_request(format!(
+                "query is longer than {} characters",
+                MAX_QUERY_CHARS
+            )));
+        }
+        let mut depth = 0usize;
+        for c in query.chars() {
+            match c {
+                '(' => {
+                    depth += 1;
+                    if depth > MAX_QUERY_DEPTH {
+                        return Err(AppError::bad_request(format!(
+                            "query nests more than {} groups",
+                            MAX_QUERY_DEPTH
+                        )));
+                    }
+                }
+                ')' => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+        }
+
+        let query_parser = QueryParser::for_index(
+            self.deps.index.first(),
+            vec![self.deps.fields.title, self.deps.fields.body],
+        );
+        let parsed = query_parser
+            .parse_query(query)
+            .map_err(|err| AppError::bad_request(err.to_string()))?;
+        // Each word is searched in title and body; count it once.
+        let mut terms = HashSet::new();
+        parsed.query_terms(&mut |term, _| {
+            terms.insert(term.serialized_value_bytes());
+        });
+        if terms.len() > MAX_QUERY_TERMS {
+            return Err(AppError::bad_request(format!(
+                "query has {} terms; at most {} are allowed",
+                terms.len(),
+                MAX_QUERY_TERMS
+            )));
+        }
+        Ok(parsed)
+    }
+}
@@ -0,0 +1,206 @@
+use std::sync::Arc;
+
+use anyhow::Context;
+use sqlx::FromRow;
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+use tracing::{info, warn};
+
+use crate::db;
+use crate::errors::AppError;
+use crate::types::{Dependencies, RuntimeSettings, UpdateSettingsRequest};
+
+#[derive(FromRow)]
+struct SettingRow {
+    key: String,
+    value: String,
+}
+
+#[derive(Clone)]
+pub struct SettingsService {
+    deps: Arc<Dependencies>,
+}
+
+impl SettingsService {
+    const MAX_FETCH_CONCURRENCY: usize = 256;
+    const MAX_EXCERPT_LEN: usize = 10_000;
+
+    pub fn new(deps: Arc<Dependencies>) -> Self {
+        Self { deps }
+    }
+
+    /// Apply values saved through the API over the config file defaults.
+    pub async fn load(&self) -> anyhow::Result<()> {
+        let rows: Vec<SettingRow> = sqlx::query_as("SELECT key, value FROM settings")
+            .fetch_all(&self.deps.db)
+            .await
+            .context("load settings")?;
+        if rows.is_empty() {
+            return Ok(());
+        }
+
+        let mut patch = UpdateSettingsRequest::default();
+        for row in rows {
+            let parsed = match row.key.as_str() {
+                "fetch_concurrency" => row.value.parse().map(|v| patch.fetch_concurrency = Some(v)),
+                "fetch_timeout_secs" => row
+                    .value
+                    .parse()
+                    .map(|v| patch.fetch_timeout_secs = Some(v)),
+                "max_urls" => row.value.parse().map(|v| patch.max_urls = Some(v)),
+                "excerpt_len" => row.value.parse().map(|v| patch.excerpt_len = Some(v)),
+                "slow_query_ms" => row.value.parse().map(|v| patch.slow_query_ms = Some(v)),
+                "slow_search_ms" => row.value.parse().map(|v| patch.slow_search_ms = Some(v)),
+                "slow_index_commit_ms" => row
+                    .value
+                    .parse()
+                    .map(|v| patch.slow_index_commit_ms = Some(v)),
+                _ => {
+                    warn!("ignoring unknown saved setting: {}", row.key);
+                    Ok(())
+                }
+            };
+            if parsed.is_err() {
+                warn!("ignoring invalid saved setting: {}={}", row.key, row.value);
+            }
+        }
+
+        let settings = self
+            .merged(patch)
+            .map_err(|err| anyhow::anyhow!("saved settings are invalid: {}", err))?;
+        self.apply(settings);
+        info!("runtime settings loaded: {:?}", settings);
+        Ok(())
+    }
+
+    pub fn get(&self) -> RuntimeSettings {
+        self.deps.settings()
+    }
+
+    /// Validate, persist, and apply a partial update; omitted fields keep their values.
+    pub async fn update(&self, patch: UpdateSettingsRequest) -> Result<RuntimeSettings, AppError> {
+        self.deps.ensure_writable()?;
+        let changes: Vec<(&str, String)> = [
+            (
+                "fetch_concurrency",
+                patch.fetch_concurrency.map(|v| v.to_string()),
+            ),
+            (
+                "fetch_timeout_secs",
+                patch.fetch_timeout_secs.map(|v| v.to_string()),
+            ),
+            ("max_urls", patch.max_urls.map(|v| v.to_string())),
+            ("excerpt_len", patch.excerpt_len.map(|v| v.to_string())),
+            ("slow_query_ms", patch.slow_query_ms.map(|v| v.to_string())),
+            (
+                "slow_search_ms",
+                patch.slow_search_ms.map(|v| v.to_string()),
+            ),
+            (
+                "slow_index_commit_ms",
+                patch.slow_index_commit_ms.map(|v| v.to_string()),
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(key, value)| Some((key, value?)))
+        .collect();
+        let settings = self.merged(patch).map_err(AppError::bad_request)?;
+
+        let now = OffsetDateTime::now_utc()
+            .format(&Rfc3339)
+            .expect("failed to format timestamp");
+        let mut tx = self.deps.db.begin().await?;
+        for (key, value) in &changes {
+            sqlx::query(
+                r#"
+                INSERT INTO settings (key, value, updated_at)
+                VALUES (?1, ?2, ?3)
+                ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at
+                "#,
+            )
+            .bind(key)
+            .bind(value)
+            .bind(&now)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+
+        self.apply(settings);
+        info!("runtime settings updated: {:?}", settings);
+        Ok(settings)
+    }
+
+    fn merged(&self, patch: UpdateSettingsRequest) -> Result<RuntimeSettings, String> {
+        let current = self.deps.settings();
+        let settings = RuntimeSettings {
+            fetch_concurrency: patch.fetch_concurrency.unwrap_or(current.fetch_concurrency),
+            fetch_timeout_secs: patch
+                .fetch_timeout_secs
+                .unwrap_or(current.fetch_timeout_secs),
+            max_urls: patch.max_urls.unwrap_or(current.max_urls),
+            excerpt_len: patch.excerpt_len.unwrap_or(current.excerpt_len),
+            slow_query_ms: patch.slow_query_ms.unwrap_or(current.slow_query_ms),
+            slow_search_ms: patch.slow_search_ms.unwrap_or(current.slow_search_ms),
+            slow_index_commit_ms: patch
+                .slow_index_commit_ms
+                .unwrap_or(current.slow_index_commit_ms),
+        };
+
+        if !(1..=Self::MAX_FETCH_CONCURRENCY).contains(&settings.fetch_concurrency) {
+            return Err(format!(
+                "fetch_concurrency must be between 1 and {}",
+                Self::MAX_FETCH_CONCURRENCY
+            ));
+        }
+        if settings.fetch_timeout_secs == 0 {
+            return Err("fetch_timeout_secs must be at least 1".to_string());
+        }
+        // Import batches must fit in the ingest queue in one reservation.
+        let queue_capacity = self.deps.config.ingest.queue_capacity;
+        if !(1..=queue_capacity).contains(&settings.max_urls) {
+            return Err(format!("max_urls must be between 1 and {}", queue_capacity));
+        }
+        if !(1..=Self::MAX_EXCERPT_LEN).contains(&settings.excerpt_len) {
+            return Err(format!(
+                "excerpt_len must be between 1 and {}",
+                Self::MAX_EXCERPT_LEN
+            ));
+        }
+        Ok(settings)
+    }
+
+    fn apply(&self, settings: RuntimeSettings) {
+        let previous = {
+            let mut current = self
+                .deps
+                .settings
+                .write()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            std::mem::replace(&mut *current, settings)
+        };
+        self.resize_fetch_permits(previous.fetch_concurrency, settings.fetch_concurrency);
+        if previous.slow_query_ms != settings.slow_query_ms {
+            // Pooled connections keep the old threshold until they are recycled.
+            let options = (*self.deps.db.connect_options()).clone();
+            self.deps
+                .db
+                .set_connect_options(db::log_slow_statements(options, settings.slow_query_ms));
+        }
+    }
+
+    /// Grow the fetch semaphore at once; shrink it by retiring permits as fetches release them.
+    fn resize_fetch_permits(&self, from: usize, to: usize) {
+        if to > from {
+            self.deps.fetch_semaphore.add_permits(to - from);
+        } else if to < from {
+            let semaphore = self.deps.fetch_semaphore.clone();
+            let excess = (from - to) as u32;
+            tokio::spawn(async move {
+                if let Ok(permits) = semaphore.acquire_many_owned(excess).await {
+                    permits.forget();
+                }
+            });
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use std::sync::Arc;
+
+use crate::errors::AppError;
+use crate::services::StorageService;
+use crate::types::{AdminStatusResponse, Caller, Dependencies, StatsResponse};
+
+#[derive(Clone)]
+pub struct StatusService {
+    deps: Arc<Dependencies>,
+    storage: StorageService,
+}
+
+impl StatusService {
+    pub fn new(deps: Arc<Dependencies>, storage: StorageService) -> Self {
+        Self { deps, storage }
+    }
+
+    /// Snapshot of the running instance for operators and bug reports.
+    pub async fn status(&self) -> Result<AdminStatusResponse, AppError> {
+        let searchers = self.deps.index.searchers();
+        let storage = self.storage.usage().await?;
+
+        let fetch_concurrency = self.deps.settings().fetch_concurrency;
+        Ok(AdminStatusResponse {
+            version: env!("CARGO_PKG_VERSION"),
+            uptime_secs: self.deps.started_at.elapsed().as_secs(),
+            index_bytes: storage.index_bytes,
+            index_segments: searchers
+                .iter()
+                .map(|searcher| searcher.segment_readers().len())
+                .sum(),
+            index_docs: searchers.iter().map(|searcher| searcher.num_docs()).sum(),
+            ingest_queue_depth: self.deps.metrics.ingest_queue_depth(),
+            fetches_in_flight: fetch_concurrency
+                .saturating_sub(self.deps.fetch_semaphore.available_permits()),
+            fetch_concurrency,
+            db_bytes: storage.db_bytes,
+            storage,
+            read_only: self.deps.ensure_writable().is_err(),
+        })
+    }
+
+    /// Bookmark counts for the caller's namespace and how much disk the server uses.
+    pub async fn stats(&self, caller: Caller) -> Result<StatsResponse, AppError> {
+        let rows: Vec<(String, i64)> = sqlx::query_as(
+            r#"
+            SELECT status, COUNT(*)
+            FROM bookmarks
+            WHERE owner_id = ?1 AND namespace = ?2
+            GROUP BY status
+            "#,
+        )
+        .bind(caller.user_id)
+        .bind(&caller.namespace)
+        .fetch_all(&self.deps.db)
+        .await?;
+        let storage = self.storage.usage().await?;
+        Ok(StatsResponse {
+            bookmarks: rows.iter().map(|(_, count)| count).sum(),
+            by_status: rows.into_iter().collect(),
+            index_bytes: storage.index_bytes,
+            db_bytes: storage.db_bytes,
+        })
+    }
+}
@@ -0,0 +1,201 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::Context;
+use tracing::{info, warn};
+
+use crate::config::QuotaPolicy;
+use crate::errors::AppError;
+use crate::services::backup::{DB_FILE, INDEX_DIR, RESTORE_DIR};
+use crate::types::{Dependencies, StorageUsage};
+
+/// Prefix of the copies of replaced data kept when a restore is applied; the only
+/// snapshots that may be evicted.
+pub(crate) const PRE_RESTORE_PREFIX: &str = "pre-restore-";
+
+/// Measures disk usage under the data dir and applies `[quota]`.
+#[derive(Clone)]
+pub struct StorageService {
+    deps: Arc<Dependencies>,
+}
+
+impl StorageService {
+    pub fn new(deps: Arc<Dependencies>) -> Self {
+        Self { deps }
+    }
+
+    /// Bytes used by the database, index, stored page text (part of the database), and
+    /// snapshots: kept pre-restore copies, a staged restore, and in-flight backups.
+    pub async fn usage(&self) -> anyhow::Result<StorageUsage> {
+        let content_bytes: i64 =
+            sqlx::query_scalar("SELECT COALESCE(SUM(length(body)), 0) FROM bookmark_contents")
+                .fetch_one(&self.deps.db)
+                .await
+                .context("measure stored content")?;
+
+        let (db_bytes, index_bytes, snapshot_bytes) = if self.deps.config.storage.in_memory {
+            let mut index_bytes = 0;
+            for searcher in self.deps.index.searchers() {
+                index_bytes += searcher
+                    .space_usage()
+                    .context("measure index")?
+                    .total()
+                    .get_bytes();
+            }
+            let db_bytes: i64 = sqlx::query_scalar(
+                "SELECT page_count * page_size FROM pragma_page_count(), pragma_page_size()",
+            )
+            .fetch_one(&self.deps.db)
+            .await
+            .context("measure database")?;
+            (db_bytes as u64, index_bytes, 0)
+        } else {
+            let data_dir = &self.deps.config.storage.data_dir;
+            let index_bytes = Self::dir_size(data_dir.join(INDEX_DIR))
+                .await
+                .context("measure index dir")?;
+            let mut db_bytes = 0;
+            for suffix in ["", "-wal", "-shm"] {
+                db_bytes +=
+                    Self::file_size(&data_dir.join(format!("{}{}", DB_FILE, suffix))).await?;
+            }
+            let mut snapshot_bytes = 0;
+            for path in Self::snapshot_paths(data_dir).await? {
+                snapshot_bytes += Self::path_size(path).await?;
+            }
+            (db_bytes, index_bytes, snapshot_bytes)
+        };
+
+        let total_bytes = db_bytes + index_bytes + snapshot_bytes;
+        let max_bytes = self.deps.config.quota.max_bytes;
+        Ok(StorageUsage {
+            db_bytes,
+            index_bytes,
+            content_bytes: content_bytes as u64,
+            snapshot_bytes,
+            total_bytes,
+            quota_bytes: (max_bytes > 0).then_some(max_bytes),
+            over_quota: max_bytes > 0 && total_bytes >= max_bytes,
+        })
+    }
+
+    /// Refuse a new snapshot while usage is at `quota.max_bytes`, first deleting the
+    /// oldest pre-restore copies under `evict_snapshots`.
+    pub async fn ensure_snapshot_room(&self) -> Result<(), AppError> {
+        let usage = self.enforce_quota().await?;
+        if usage.over_quota {
+            return Err(AppError::insufficient_storage(format!(
+                "storage quota reached ({} of {} bytes used); free space or raise quota.max_bytes",
+                usage.total_bytes, self.deps.config.quota.max_bytes
+            )));
+        }
+        Ok(())
+    }
+
+    /// Measure usage, evicting snapshots if the policy allows, and warn while still over.
+    pub async fn enforce_quota(&self) -> anyhow::Result<StorageUsage> {
+        let mut usage = self.usage().await?;
+        if !usage.over_quota {
+            return Ok(usage);
+        }
+
+        if self.deps.config.quota.policy == QuotaPolicy::EvictSnapshots {
+            let data_dir = &self.deps.config.storage.data_dir;
+            let mut evictable: Vec<PathBuf> = Self::snapshot_paths(data_dir)
+                .await?
+                .into_iter()
+                .filter(|path| {
+                    path.file_name()
+                        .and_then(|name| name.to_str())
+                        .is_some_and(|name| name.starts_with(PRE_RESTORE_PREFIX))
+                })
+                .collect();
+            // Timestamped names sort oldest first.
+            evictable.sort();
+            for path in evictable {
+                if !usage.over_quota {
+                    break;
+                }
+                let freed = Self::path_size(path.clone()).await?;
+                tokio::fs::remove_dir_all(&path)
+                    .await
+                    .with_context(|| format!("evict snapshot {}", path.display()))?;
+                info!(
+                    "storage quota: evicted snapshot {} freed_bytes={}",
+                    path.display(),
+                    freed
+                );
+                usage = self.usage().await?;
+            }
+        }
+
+        if usage.over_quota {
+            warn!(
+                "storage quota exceeded: total_bytes={} quota_bytes={} db_bytes={} index_bytes={} snapshot_bytes={}",
+                usage.total_bytes,
+                self.deps.config.quota.max_bytes,
+                usage.db_bytes,
+                usage.index_bytes,
+                usage.snapshot_bytes
+            );
+        }
+        Ok(usage)
+    }
+
+    /// Snapshot files and directories directly under the data dir.
+    async fn snapshot_paths(data_dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+        let mut paths = Vec::new();
+        let mut entries = tokio::fs::read_dir(data_dir)
+            .await
+            .context("read data dir")?;
+        while let Some(entry) = entries.next_entry().await? {
+            let name = entry.file_name();
+            let Some(name) = name.to_str() else {
+                continue;
+            };
+            let is_snapshot = name.starts_with(PRE_RESTORE_PREFIX)
+                || name == RESTORE_DIR
+                || name.starts_with("restore-upload")
+                || name.starts_with("backup-")
+                || name.starts_with("odin-backup-");
+            if is_snapshot {
+                paths.push(entry.path());
+            }
+        }
+        Ok(paths)
+    }
+
+    async fn path_size(path: PathBuf) -> anyhow::Result<u64> {
+        if tokio::fs::metadata(&path).await?.is_dir() {
+            Self::dir_size(path).await
+        } else {
+            Self::file_size(&path).await
+        }
+    }
+
+    async fn dir_size(root: PathBuf) -> anyhow::Result<u64> {
+        let mut total = 0;
+        let mut pending = vec![root];
+        while let Some(dir) = pending.pop() {
+            let mut entries = tokio::fs::read_dir(&dir).await?;
+            while let Some(entry) = entries.next_entry().await? {
+                let metadata = entry.metadata().await?;
+                if metadata.is_dir() {
+                    pending.push(entry.path());
+                } else {
+                    total += metadata.len();
+                }
+            }
+        }
+        Ok(total)
+    }
+
+    /// Size of `path`, or 0 when it does not exist (e.g. no WAL yet).
+    async fn file_size(path: &Path) -> anyhow::Result<u64> {
+        match tokio::fs::metadata(path).await {
+            Ok(metadata) => Ok(metadata.len()),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(0),
+            Err(err) => Err(err).with_context(|| format!("stat {}", path.display())),
+        }
+    }
+}
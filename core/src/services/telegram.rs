@@ -0,0 +1,307 @@
+use std::fmt::Write as _;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use tracing::{info, warn};
+use url::Url;
+
+use crate::errors::AppError;
+use crate::services::{AuthService, IngestService, SearchService};
+use crate::types::{Caller, Dependencies, IngestUrlsRequest, Scope, SearchParams};
+
+/// Seconds Telegram holds a `getUpdates` call open waiting for messages.
+const POLL_TIMEOUT_SECS: u64 = 30;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+const SEARCH_RESULTS: u32 = 5;
+const HELP: &str = "Send me a link to save it, or /search <words> to find saved pages.";
+
+#[derive(Deserialize)]
+struct ApiResponse<T> {
+    ok: bool,
+    result: Option<T>,
+    description: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Update {
+    update_id: i64,
+    message: Option<Message>,
+}
+
+#[derive(Deserialize)]
+struct Message {
+    chat: Chat,
+    text: Option<String>,
+    #[serde(default)]
+    entities: Vec<Entity>,
+}
+
+#[derive(Deserialize)]
+struct Chat {
+    id: i64,
+}
+
+/// Only `text_link` entities matter: their URL is hidden behind the link text.
+#[derive(Deserialize)]
+struct Entity {
+    #[serde(rename = "type")]
+    kind: String,
+    url: Option<String>,
+}
+
+/// Long-polls the Telegram Bot API: links in a message are ingested and `/search`
+/// answers with the top results, both as the configured `api_token`'s key, so its
+/// scopes, namespace, and revocation apply.
+#[derive(Clone)]
+pub struct TelegramService {
+    deps: Arc<Dependencies>,
+    auth: AuthService,
+    ingest: IngestService,
+    search: SearchService,
+}
+
+impl TelegramService {
+    pub fn new(
+        deps: Arc<Dependencies>,
+        auth: AuthService,
+        ingest: IngestService,
+        search: SearchService,
+    ) -> Self {
+        Self {
+            deps,
+            auth,
+            ingest,
+            search,
+        }
+    }
+
+    /// Poll for messages until shutdown, when a bot token is configured.
+    pub fn start(&self) {
+        if self.deps.config.telegram.bot_token.is_none() {
+            return;
+        }
+        info!(
+            "telegram bot enabled for {} chats",
+            self.deps.config.telegram.allowed_chat_ids.len()
+        );
+        let service = self.clone();
+        tokio::spawn(async move { service.poll().await });
+    }
+
+    async fn poll(&self) {
+        let mut offset = 0;
+        let mut failures = 0;
+        loop {
+            let request = self
+                .deps
+                .http_client
+                .get(self.method_url("getUpdates"))
+                .query(&[("timeout", POLL_TIMEOUT_SECS as i64), ("offset", offset)])
+                .timeout(REQUEST_TIMEOUT + Duration::from_secs(POLL_TIMEOUT_SECS));
+            let updates = tokio::select! {
+                _ = self.deps.shutdown.cancelled() => return,
+                updates = Self::call::<Vec<Update>>(request) => updates,
+            };
+            match updates {
+                Ok(updates) => {
+                    failures = 0;
+                    for update in updates {
+                        offset = offset.max(update.update_id + 1);
+                        if let Some(message) = update.message {
+                            self.handle(message).await;
+                        }
+                    }
+                }
+                Err(err) => {
+                    failures += 1;
+                    let delay = Duration::from_secs(1 << failures.min(6)).min(MAX_RETRY_DELAY);
+                    warn!(
+                        "telegram getUpdates failed ({}); retrying in {}s",
+                        err,
+                        delay.as_secs()
+                    );
+                    tokio::select! {
+                        _ = self.deps.shutdown.cancelled() => return,
+                        _ = tokio::time::sleep(delay) => {}
+                    }
+                }
+            }
+        }
+    }
+
+    async fn handle(&self, message: Message) {
+        let chat_id = message.chat.id;
+        let config = &self.deps.config.telegram;
+        if !config.allowed_chat_ids.contains(&chat_id) {
+            warn!(
+                "telegram message from chat {} not in allowed_chat_ids",
+                chat_id
+            );
+            self.reply(
+                chat_id,
+                &format!(
+                    "This chat is not allowed to use odin. Add {} to telegram.allowed_chat_ids.",
+                    chat_id
+                ),
+            )
+            .await;
+            return;
+        }
+
+        let text = message.text.as_deref().unwrap_or_default().trim();
+        let (command, rest) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+        // In groups commands may be addressed as `/search@odin_bot`.
+        let command = command.split('@').next().unwrap_or_default();
+        let answer = match command {
+            "/start" | "/help" => Ok(HELP.to_string()),
+            "/search" => self.search(rest.trim()).await,
+            _ => {
+                let urls = Self::extract_urls(text, &message.entities);
+                if urls.is_empty() {
+                    Ok(HELP.to_string())
+                } else {
+                    self.save(urls).await
+                }
+            }
+        };
+        let answer = answer.unwrap_or_else(|err| {
+            err.log_source();
+            format!("Failed: {}", err.message())
+        });
+        self.reply(chat_id, &answer).await;
+    }
+
+    async fn save(&self, urls: Vec<String>) -> Result<String, AppError> {
+        let caller = self.caller(Scope::Ingest).await?;
+        let count = urls.len();
+        let response = self
+            .ingest
+            .ingest_urls(
+                caller,
+                IngestUrlsRequest {
+                    urls,
+                    tags: Vec::new(),
+                },
+            )
+            .await?;
+        Ok(match (response.accepted, count) {
+            (0, 1) => "Already saved.".to_string(),
+            (1, 1) => "Saved.".to_string(),
+            (accepted, _) => format!(
+                "Saved {} of {} links; {} were already saved.",
+                accepted, count, response.deduped
+            ),
+        })
+    }
+
+    async fn search(&self, query: &str) -> Result<String, AppError> {
+        if query.is_empty() {
+            return Ok("Usage: /search <words>".to_string());
+        }
+        let caller = self.caller(Scope::Read).await?;
+        let response = self
+            .search
+            .search(
+                caller,
+                SearchParams {
+                    query: query.to_string(),
+                    page: None,
+                    per_page: Some(SEARCH_RESULTS),
+                    ..SearchParams::default()
+                },
+            )
+            .await?;
+        if response.results.is_empty() {
+            return Ok(format!("No results for \"{}\".", query));
+        }
+        let mut answer = format!("{} results for \"{}\":\n", response.total_hits, query);
+        for (rank, result) in response.results.iter().enumerate() {
+            let title = result.title.as_deref().unwrap_or("Untitled");
+            let _ = write!(answer, "\n{}. {}\n{}\n", rank + 1, title, result.url);
+        }
+        Ok(answer)
+    }
+
+    /// Resolved per message, so revoking the key stops the bot.
+    async fn caller(&self, scope: Scope) -> Result<Caller, AppError> {
+        let token = self
+            .deps
+            .config
+            .telegram
+            .api_token
+            .as_deref()
+            .unwrap_or_default();
+        self.auth.authorize_token(token, None, scope).await
+    }
+
+    async fn reply(&self, chat_id: i64, text: &str) {
+        let body = serde_json::json!({
+            "chat_id": chat_id,
+            "text": text,
+            "disable_web_page_preview": true,
+        });
+        let request = self
+            .deps
+            .http_client
+            .post(self.method_url("sendMessage"))
+            .timeout(REQUEST_TIMEOUT)
+            .header("content-type", "application/json")
+            .body(body.to_string());
+        if let Err(err) = Self::call::<serde_json::Value>(request).await {
+            warn!("telegram sendMessage to chat {} failed: {}", chat_id, err);
+        }
+    }
+
+    /// Bare http(s) URLs in the text, plus the targets of hidden `text_link`s.
+    fn extract_urls(text: &str, entities: &[Entity]) -> Vec<String> {
+        let mut urls: Vec<String> = text
+            .split_whitespace()
+            .map(|word| word.trim_matches(|ch: char| "<>()[]\"'.,;!?".contains(ch)))
+            .filter(|word| {
+                Url::parse(word).is_ok_and(|url| matches!(url.scheme(), "http" | "https"))
+            })
+            .map(str::to_string)
+            .collect();
+        urls.extend(
+            entities
+                .iter()
+                .filter(|entity| entity.kind == "text_link")
+                .filter_map(|entity| entity.url.clone()),
+        );
+        urls.dedup();
+        urls
+    }
+
+    fn method_url(&self, method: &str) -> String {
+        let config = &self.deps.config.telegram;
+        format!(
+            "{}/bot{}/{}",
+            config.api_url.as_str().trim_end_matches('/'),
+            config.bot_token.as_deref().unwrap_or_default(),
+            method
+        )
+    }
+
+    /// Send a Bot API call; the error never includes the URL, which holds the bot token.
+    async fn call<T: DeserializeOwned>(request: reqwest::RequestBuilder) -> Result<T, String> {
+        let response = request
+            .send()
+            .await
+            .map_err(|err| err.without_url().to_string())?;
+        let body = response
+            .bytes()
+            .await
+            .map_err(|err| err.without_url().to_string())?;
+        let response: ApiResponse<T> =
+            serde_json::from_slice(&body).map_err(|err| format!("unexpected response: {}", err))?;
+        match (response.ok, response.result) {
+            (true, Some(result)) => Ok(result),
+            _ => Err(response
+                .description
+                .unwrap_or_else(|| "request refused".to_string())),
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use std::sync::Arc;
+
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+use tracing::info;
+
+use crate::errors::AppError;
+use crate::services::ApiKeyService;
+use crate::types::{
+    CreateUserRequest, CreateUserResponse, Dependencies, Scope, UserItem, UsersResponse,
+};
+
+#[derive(Clone)]
+pub struct UserService {
+    deps: Arc<Dependencies>,
+}
+
+impl UserService {
+    const MAX_USERNAME_LEN: usize = 64;
+
+    pub fn new(deps: Arc<Dependencies>) -> Self {
+        Self { deps }
+    }
+
+    pub async fn list(&self) -> Result<UsersResponse, AppError> {
+        let results: Vec<UserItem> = sqlx::query_as(
+            r#"
+            SELECT id, username, is_admin, created_at
+            FROM users
+            ORDER BY id
+            "#,
+        )
+        .fetch_all(&self.deps.db)
+        .await?;
+        Ok(UsersResponse { results })
+    }
+
+    /// Provision a user with a default API key and return its token; only the hash is persisted.
+    pub async fn create(&self, payload: CreateUserRequest) -> Result<CreateUserResponse, AppError> {
+        self.deps.ensure_writable()?;
+        let username = payload.username.trim().to_string();
+        Self::validate_username(&username)?;
+
+        let now = OffsetDateTime::now_utc()
+            .format(&Rfc3339)
+            .expect("failed to format timestamp");
+        let mut tx = self.deps.db.begin().await?;
+        let result = sqlx::query(
+            r#"
+            INSERT OR IGNORE INTO users (username, is_admin, created_at)
+            VALUES (?1, ?2, ?3)
+            "#,
+        )
+        .bind(&username)
+        .bind(payload.is_admin)
+        .bind(&now)
+        .execute(&mut *tx)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Err(AppError::conflict("username already exists"));
+        }
+
+        let id = result.last_insert_rowid();
+        let scopes: &[Scope] = if payload.is_admin {
+            &Scope::ALL
+        } else {
+            &Scope::DEFAULT
+        };
+        let (_, token) = ApiKeyService::insert_key(&mut tx, id, "default", scopes, None).await?;
+        tx.commit().await?;
+
+        info!("user created: id={} username={}", id, username);
+        Ok(CreateUserResponse {
+            id,
+            username,
+            is_admin: payload.is_admin,
+            token,
+        })
+    }
+
+    /// Usernames are 1-64 letters, digits, `_`, `-`, `.`, and `@`, so email addresses
+    /// from single sign-on fit.
+    pub(crate) fn validate_username(username: &str) -> Result<(), AppError> {
+        if username.is_empty() || username.len() > Self::MAX_USERNAME_LEN {
+            return Err(AppError::bad_request("username must be 1-64 characters"));
+        }
+        if !username
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, '_' | '-' | '.' | '@'))
+        {
+            return Err(AppError::bad_request(
+                "username may only contain letters, digits, '_', '-', '.' and '@'",
+            ));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,348 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::Deserialize;
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+use tokio::sync::Notify;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{info, warn};
+
+use crate::errors::AppError;
+use crate::services::BookmarkService;
+use crate::types::{BookmarkEvent, BookmarkEventKind, Caller, Dependencies, WaybackSnapshot};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+/// How long a capture may stay pending before it is recorded as failed.
+const CAPTURE_TIMEOUT: Duration = Duration::from_secs(180);
+/// Pause after a 429 or a transport error before pending captures are tried again.
+const RETRY_DELAY: Duration = Duration::from_secs(60);
+
+#[derive(Deserialize)]
+struct SaveResponse {
+    job_id: Option<String>,
+    message: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct StatusResponse {
+    status: String,
+    timestamp: Option<String>,
+    original_url: Option<String>,
+    message: Option<String>,
+}
+
+/// How one capture ended; `Retry` leaves it pending for the next round.
+enum Outcome {
+    Saved(String),
+    Failed(String),
+    Retry(String),
+}
+
+/// Submits bookmarks to the Wayback Machine's Save Page Now API, one at a time, and
+/// records where each capture landed. Requests are rows in `wayback_snapshots`, so
+/// pending captures survive restarts; with `wayback.submit_all` every newly indexed
+/// bookmark gets one.
+#[derive(Clone)]
+pub struct WaybackService {
+    deps: Arc<Dependencies>,
+    bookmarks: BookmarkService,
+    wake: Arc<Notify>,
+}
+
+impl WaybackService {
+    pub fn new(deps: Arc<Dependencies>, bookmarks: BookmarkService) -> Self {
+        Self {
+            deps,
+            bookmarks,
+            wake: Arc::new(Notify::new()),
+        }
+    }
+
+    pub async fn get(&self, caller: Caller, id: i64) -> Result<WaybackSnapshot, AppError> {
+        if self.bookmarks.get(&caller, id).await?.is_none() {
+            return Err(AppError::not_found("bookmark not found"));
+        }
+        self.snapshot(id)
+            .await?
+            .ok_or_else(|| AppError::not_found("bookmark has not been sent to the Wayback Machine"))
+    }
+
+    /// Queue a capture of the bookmark, replacing any earlier result.
+    pub async fn submit(&self, caller: Caller, id: i64) -> Result<WaybackSnapshot, AppError> {
+        self.deps.ensure_writable()?;
+        if !self.enabled() {
+            return Err(AppError::not_found("wayback is not configured"));
+        }
+        if self.bookmarks.get(&caller, id).await?.is_none() {
+            return Err(AppError::not_found("bookmark not found"));
+        }
+        sqlx::query(
+            r#"
+            INSERT INTO wayback_snapshots (bookmark_id, status, requested_at)
+            VALUES (?1, 'pending', ?2)
+            ON CONFLICT(bookmark_id) DO UPDATE SET
+                status = 'pending',
+                archive_url = NULL,
+                error = NULL,
+                requested_at = excluded.requested_at,
+                completed_at = NULL
+            "#,
+        )
+        .bind(id)
+        .bind(Self::now())
+        .execute(&self.deps.db)
+        .await?;
+        info!("wayback capture requested: bookmark_id={}", id);
+        self.wake.notify_one();
+        self.get(caller, id).await
+    }
+
+    pub fn start(&self) {
+        if !self.enabled() {
+            return;
+        }
+        info!(
+            "wayback enabled: submit_all={}",
+            self.deps.config.wayback.submit_all
+        );
+        if self.deps.config.wayback.submit_all {
+            let service = self.clone();
+            let events = self.deps.events.subscribe();
+            tokio::spawn(async move { service.queue_indexed(events).await });
+        }
+        let service = self.clone();
+        tokio::spawn(async move {
+            loop {
+                // Results are writes, so read-only mode holds captures as pending.
+                let delay = if service.deps.ensure_writable().is_ok() {
+                    match service.drain().await {
+                        Ok(delay) => delay,
+                        Err(err) => {
+                            warn!("wayback submission failed: {:?}", err);
+                            Some(RETRY_DELAY)
+                        }
+                    }
+                } else {
+                    Some(RETRY_DELAY)
+                };
+                tokio::select! {
+                    _ = service.deps.shutdown.cancelled() => return,
+                    _ = service.wake.notified() => {}
+                    _ = tokio::time::sleep(delay.unwrap_or_default()), if delay.is_some() => {}
+                }
+            }
+        });
+    }
+
+    async fn queue_indexed(&self, mut events: tokio::sync::broadcast::Receiver<BookmarkEvent>) {
+        loop {
+            let event = match events.recv().await {
+                Ok(event) => event,
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!("wayback lagged; dropped {} events", skipped);
+                    continue;
+                }
+                Err(RecvError::Closed) => break,
+            };
+            if event.event != BookmarkEventKind::Indexed || self.deps.ensure_writable().is_err() {
+                continue;
+            }
+            // A refetch indexes the bookmark again; one capture is enough.
+            let queued = sqlx::query(
+                r#"
+                INSERT OR IGNORE INTO wayback_snapshots (bookmark_id, status, requested_at)
+                VALUES (?1, 'pending', ?2)
+                "#,
+            )
+            .bind(event.id)
+            .bind(Self::now())
+            .execute(&self.deps.db)
+            .await;
+            match queued {
+                Ok(result) if result.rows_affected() > 0 => self.wake.notify_one(),
+                Ok(_) => {}
+                Err(err) => warn!(
+                    "wayback queue failed: bookmark_id={} error={}",
+                    event.id, err
+                ),
+            }
+        }
+    }
+
+    /// Capture pending bookmarks oldest first until none are left, or until the API
+    /// asks to back off; returns how long to wait in that case.
+    async fn drain(&self) -> Result<Option<Duration>, AppError> {
+        loop {
+            let next: Option<(i64, String, String)> = sqlx::query_as(
+                r#"
+                SELECT w.bookmark_id, b.url, w.requested_at
+                FROM wayback_snapshots w
+                JOIN bookmarks b ON b.id = w.bookmark_id
+                WHERE w.status = 'pending'
+                ORDER BY w.requested_at, w.bookmark_id
+                LIMIT 1
+                "#,
+            )
+            .fetch_optional(&self.deps.db)
+            .await?;
+            let Some((id, url, requested_at)) = next else {
+                return Ok(None);
+            };
+            let (status, archive_url, error) = match self.capture(&url).await {
+                Outcome::Saved(archive_url) => {
+                    info!(
+                        "wayback capture saved: bookmark_id={} archive_url={}",
+                        id, archive_url
+                    );
+                    ("saved", Some(archive_url), None)
+                }
+                Outcome::Failed(error) => {
+                    warn!("wayback capture failed: bookmark_id={} error={}", id, error);
+                    ("failed", None, Some(error))
+                }
+                Outcome::Retry(reason) => {
+                    warn!(
+                        "wayback capture deferred: bookmark_id={} reason={}",
+                        id, reason
+                    );
+                    return Ok(Some(RETRY_DELAY));
+                }
+            };
+            // A resubmission while this capture ran starts over, so leave its row alone.
+            sqlx::query(
+                r#"
+                UPDATE wayback_snapshots
+                SET status = ?2, archive_url = ?3, error = ?4, completed_at = ?5
+                WHERE bookmark_id = ?1 AND requested_at = ?6
+                "#,
+            )
+            .bind(id)
+            .bind(status)
+            .bind(archive_url)
+            .bind(error)
+            .bind(Self::now())
+            .bind(requested_at)
+            .execute(&self.deps.db)
+            .await?;
+        }
+    }
+
+    /// Start a Save Page Now job for `url` and poll it until it settles.
+    async fn capture(&self, url: &str) -> Outcome {
+        let body = url::form_urlencoded::Serializer::new(String::new())
+            .append_pair("url", url)
+            .finish();
+        let request = self
+            .authorized(self.deps.http_client.post(self.api_url("/save")))
+            .header("content-type", "application/x-www-form-urlencoded")
+            .body(body);
+        let response = match request.send().await {
+            Ok(response) => response,
+            Err(err) => return Outcome::Retry(err.without_url().to_string()),
+        };
+        let status = response.status();
+        if status == reqwest::StatusCode::TOO_MANY_REQUESTS || status.is_server_error() {
+            return Outcome::Retry(format!("http status {}", status));
+        }
+        let saved = match response.json::<SaveResponse>().await {
+            Ok(saved) => saved,
+            Err(_) => return Outcome::Failed(format!("http status {}", status)),
+        };
+        let Some(job_id) = saved.job_id else {
+            return Outcome::Failed(
+                saved
+                    .message
+                    .unwrap_or_else(|| format!("http status {}", status)),
+            );
+        };
+
+        let deadline = tokio::time::Instant::now() + CAPTURE_TIMEOUT;
+        while tokio::time::Instant::now() < deadline {
+            tokio::time::sleep(POLL_INTERVAL).await;
+            let request = self.authorized(
+                self.deps
+                    .http_client
+                    .get(self.api_url(&format!("/save/status/{}", job_id))),
+            );
+            let status = match request.send().await {
+                Ok(response) => match response.json::<StatusResponse>().await {
+                    Ok(status) => status,
+                    Err(_) => continue,
+                },
+                Err(_) => continue,
+            };
+            match status.status.as_str() {
+                "success" => {
+                    let timestamp = status.timestamp.unwrap_or_default();
+                    let original = status.original_url.as_deref().unwrap_or(url);
+                    return Outcome::Saved(
+                        self.api_url(&format!("/web/{}/{}", timestamp, original)),
+                    );
+                }
+                "pending" => {}
+                _ => {
+                    return Outcome::Failed(
+                        status
+                            .message
+                            .unwrap_or_else(|| "capture failed".to_string()),
+                    );
+                }
+            }
+        }
+        Outcome::Failed("capture did not finish in time".to_string())
+    }
+
+    fn authorized(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        let config = &self.deps.config.wayback;
+        request
+            .header("accept", "application/json")
+            .header(
+                "authorization",
+                format!(
+                    "LOW {}:{}",
+                    config.access_key.as_deref().unwrap_or_default(),
+                    config.secret_key.as_deref().unwrap_or_default()
+                ),
+            )
+            .timeout(REQUEST_TIMEOUT)
+    }
+
+    async fn snapshot(&self, id: i64) -> Result<Option<WaybackSnapshot>, AppError> {
+        let snapshot = sqlx::query_as(
+            r#"
+            SELECT bookmark_id, status, archive_url, error, requested_at, completed_at
+            FROM wayback_snapshots
+            WHERE bookmark_id = ?1
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(&self.deps.db)
+        .await?;
+        Ok(snapshot)
+    }
+
+    fn enabled(&self) -> bool {
+        self.deps.config.wayback.access_key.is_some()
+    }
+
+    fn api_url(&self, path: &str) -> String {
+        format!(
+            "{}{}",
+            self.deps
+                .config
+                .wayback
+                .api_url
+                .as_str()
+                .trim_end_matches('/'),
+            path
+        )
+    }
+
+    fn now() -> String {
+        OffsetDateTime::now_utc()
+            .format(&Rfc3339)
+            .expect("failed to format timestamp")
+    }
+}
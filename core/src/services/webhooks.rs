@@ -0,0 +1,320 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use hmac::{Hmac, KeyInit, Mac};
+use sha2::Sha256;
+use sqlx::FromRow;
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{info, warn};
+
+use crate::config::WebhookConfig;
+use crate::errors::AppError;
+use crate::types::{
+    BookmarkEvent, BookmarkEventKind, Caller, Dependencies, WebhookCreateRequest, WebhookItem,
+    WebhooksResponse,
+};
+
+const MAX_ATTEMPTS: u32 = 5;
+const BASE_RETRY_DELAY: Duration = Duration::from_secs(1);
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+/// Webhooks one namespace may register.
+const MAX_WEBHOOKS: i64 = 20;
+
+#[derive(FromRow)]
+struct WebhookRow {
+    id: i64,
+    url: String,
+    secret: Option<String>,
+    events: String,
+    created_at: String,
+    last_attempt_at: Option<String>,
+    last_error: Option<String>,
+}
+
+impl From<WebhookRow> for WebhookItem {
+    fn from(row: WebhookRow) -> Self {
+        Self {
+            id: row.id,
+            url: row.url,
+            events: BookmarkEventKind::parse_list(&row.events),
+            signed: row.secret.is_some(),
+            created_at: row.created_at,
+            last_attempt_at: row.last_attempt_at,
+            last_error: row.last_error,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct WebhookService {
+    deps: Arc<Dependencies>,
+}
+
+impl WebhookService {
+    pub fn new(deps: Arc<Dependencies>) -> Self {
+        Self { deps }
+    }
+
+    pub async fn list(&self, caller: Caller) -> Result<WebhooksResponse, AppError> {
+        let rows: Vec<WebhookRow> = sqlx::query_as(
+            r#"
+            SELECT id, url, secret, events, created_at, last_attempt_at, last_error
+            FROM webhooks
+            WHERE owner_id = ?1 AND namespace = ?2
+            ORDER BY id
+            "#,
+        )
+        .bind(caller.user_id)
+        .bind(&caller.namespace)
+        .fetch_all(&self.deps.db)
+        .await?;
+        Ok(WebhooksResponse {
+            results: rows.into_iter().map(WebhookItem::from).collect(),
+        })
+    }
+
+    /// Register a URL for the caller's namespace; it is sent the events that happen
+    /// from now on.
+    pub async fn add(
+        &self,
+        caller: Caller,
+        payload: WebhookCreateRequest,
+    ) -> Result<WebhookItem, AppError> {
+        self.deps.ensure_writable()?;
+        if !matches!(payload.url.scheme(), "http" | "https") {
+            return Err(AppError::bad_request("url must be http or https"));
+        }
+        if payload.secret.as_deref().is_some_and(str::is_empty) {
+            return Err(AppError::bad_request("secret may not be empty"));
+        }
+        let events = payload
+            .events
+            .unwrap_or_else(|| BookmarkEventKind::ALL.to_vec());
+        if events.is_empty() {
+            return Err(AppError::bad_request("at least one event is required"));
+        }
+        let count: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM webhooks WHERE owner_id = ?1 AND namespace = ?2",
+        )
+        .bind(caller.user_id)
+        .bind(&caller.namespace)
+        .fetch_one(&self.deps.db)
+        .await?;
+        if count >= MAX_WEBHOOKS {
+            return Err(AppError::bad_request(format!(
+                "at most {} webhooks per namespace",
+                MAX_WEBHOOKS
+            )));
+        }
+
+        let row: Option<WebhookRow> = sqlx::query_as(
+            r#"
+            INSERT INTO webhooks (owner_id, namespace, url, secret, events, created_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+            ON CONFLICT(owner_id, namespace, url) DO NOTHING
+            RETURNING id, url, secret, events, created_at, last_attempt_at, last_error
+            "#,
+        )
+        .bind(caller.user_id)
+        .bind(&caller.namespace)
+        .bind(payload.url.as_str())
+        .bind(&payload.secret)
+        .bind(BookmarkEventKind::join(&events))
+        .bind(Self::now())
+        .fetch_optional(&self.deps.db)
+        .await?;
+        let row = row.ok_or_else(|| AppError::conflict("a webhook for this url already exists"))?;
+        info!(
+            "webhook added: user_id={} webhook_id={} events={}",
+            caller.user_id, row.id, row.events
+        );
+        Ok(WebhookItem::from(row))
+    }
+
+    pub async fn remove(&self, caller: Caller, id: i64) -> Result<(), AppError> {
+        self.deps.ensure_writable()?;
+        let result =
+            sqlx::query("DELETE FROM webhooks WHERE id = ?1 AND owner_id = ?2 AND namespace = ?3")
+                .bind(id)
+                .bind(caller.user_id)
+                .bind(&caller.namespace)
+                .execute(&self.deps.db)
+                .await?;
+        if result.rows_affected() == 0 {
+            return Err(AppError::not_found("webhook not found"));
+        }
+        info!(
+            "webhook removed: user_id={} webhook_id={}",
+            caller.user_id, id
+        );
+        Ok(())
+    }
+
+    /// Forward bookmark events to the configured webhook targets, and to those
+    /// registered for the event's namespace, until the event bus closes.
+    pub fn start(&self) {
+        if !self.deps.config.webhooks.is_empty() {
+            info!(
+                "webhooks enabled: {} targets",
+                self.deps.config.webhooks.len()
+            );
+        }
+
+        let service = self.clone();
+        let mut events = self.deps.events.subscribe();
+        tokio::spawn(async move {
+            loop {
+                let event = match events.recv().await {
+                    Ok(event) => event,
+                    Err(RecvError::Lagged(skipped)) => {
+                        warn!("webhook dispatcher lagged; dropped {} events", skipped);
+                        continue;
+                    }
+                    Err(RecvError::Closed) => break,
+                };
+                for target in &service.deps.config.webhooks {
+                    if !target.events.contains(&event.event) {
+                        continue;
+                    }
+                    let service = service.clone();
+                    let target = target.clone();
+                    let event = event.clone();
+                    service.deps.tasks.clone().spawn(async move {
+                        let _ = service.deliver(&target, &event).await;
+                    });
+                }
+                let registered = match service.registered(&event).await {
+                    Ok(registered) => registered,
+                    Err(err) => {
+                        warn!("webhook lookup failed: {}", err);
+                        continue;
+                    }
+                };
+                for (id, target) in registered {
+                    let service = service.clone();
+                    let event = event.clone();
+                    service.deps.tasks.clone().spawn(async move {
+                        let result = service.deliver(&target, &event).await;
+                        if let Err(err) = service.record(id, result.err()).await {
+                            warn!("webhook status update failed: {}", err);
+                        }
+                    });
+                }
+            }
+        });
+    }
+
+    /// Webhooks registered for the event's namespace that want its kind.
+    async fn registered(
+        &self,
+        event: &BookmarkEvent,
+    ) -> Result<Vec<(i64, WebhookConfig)>, sqlx::Error> {
+        let rows: Vec<(i64, String, Option<String>, String)> = sqlx::query_as(
+            "SELECT id, url, secret, events FROM webhooks WHERE owner_id = ?1 AND namespace = ?2",
+        )
+        .bind(event.owner_id)
+        .bind(&event.namespace)
+        .fetch_all(&self.deps.db)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .filter_map(|(id, url, secret, events)| {
+                let target = WebhookConfig {
+                    url: url.parse().ok()?,
+                    secret,
+                    events: BookmarkEventKind::parse_list(&events),
+                };
+                target.events.contains(&event.event).then_some((id, target))
+            })
+            .collect())
+    }
+
+    async fn record(&self, id: i64, error: Option<String>) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE webhooks SET last_attempt_at = ?2, last_error = ?3 WHERE id = ?1")
+            .bind(id)
+            .bind(Self::now())
+            .bind(error)
+            .execute(&self.deps.db)
+            .await?;
+        Ok(())
+    }
+
+    /// POST one event, retrying with exponential backoff on transport errors and non-2xx
+    /// replies; returns the last failure once the attempts run out.
+    async fn deliver(&self, target: &WebhookConfig, event: &BookmarkEvent) -> Result<(), String> {
+        let body = match serde_json::to_vec(event) {
+            Ok(body) => body,
+            Err(err) => {
+                warn!("webhook payload encode failed: {}", err);
+                return Err(err.to_string());
+            }
+        };
+        let signature = target
+            .secret
+            .as_deref()
+            .map(|secret| Self::sign(secret, &body));
+
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            let mut request = self
+                .deps
+                .http_client
+                .post(target.url.clone())
+                .timeout(DELIVERY_TIMEOUT)
+                .header("content-type", "application/json")
+                .header("x-odin-event", event.event.as_str())
+                .body(body.clone());
+            if let Some(signature) = &signature {
+                request = request.header("x-odin-signature", signature);
+            }
+
+            let failure = match request.send().await {
+                Ok(response) if response.status().is_success() => {
+                    self.deps.metrics.record_webhook("delivered");
+                    info!(
+                        "webhook delivered: {} event={} id={} attempt={}",
+                        target.url,
+                        event.event.as_str(),
+                        event.id,
+                        attempt
+                    );
+                    return Ok(());
+                }
+                Ok(response) => format!("http status {}", response.status()),
+                Err(err) => err.to_string(),
+            };
+
+            if attempt == MAX_ATTEMPTS {
+                self.deps.metrics.record_webhook("failed");
+                warn!(
+                    "webhook failed: {} event={} id={} attempts={} error={}",
+                    target.url,
+                    event.event.as_str(),
+                    event.id,
+                    attempt,
+                    failure
+                );
+                return Err(failure);
+            }
+            self.deps.metrics.record_webhook("retried");
+            tokio::time::sleep(BASE_RETRY_DELAY * 2u32.pow(attempt - 1)).await;
+        }
+    }
+
+    /// `sha256=<hex>` HMAC of the raw request body, in the style receivers already verify.
+    fn sign(secret: &str, body: &[u8]) -> String {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("hmac accepts any key length");
+        mac.update(body);
+        format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+    }
+
+    fn now() -> String {
+        OffsetDateTime::now_utc()
+            .format(&Rfc3339)
+            .expect("failed to format timestamp")
+    }
+}
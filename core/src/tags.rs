@@ -0,0 +1,173 @@
+//! Bookmark tags: names live in `tags`, one row per owner, namespace, and name, and
+//! `bookmark_tags` links them to bookmarks. Index documents carry them in the `tag`
+//! field, so a bookmark's document is rewritten whenever its tags change.
+
+use std::collections::HashMap;
+
+use sqlx::{QueryBuilder, Sqlite, SqliteConnection, SqlitePool};
+
+use crate::errors::AppError;
+
+/// Tags one bookmark may have.
+const MAX_TAGS: usize = 32;
+/// Characters a tag may have.
+const MAX_TAG_CHARS: usize = 64;
+
+/// A tag as stored and matched: trimmed and lowercased. Tags may not contain
+/// whitespace or commas, so lists of them can be written either way.
+pub(crate) fn normalize(tag: &str) -> Result<String, AppError> {
+    let tag = tag.trim().to_lowercase();
+    if tag.is_empty() {
+        return Err(AppError::bad_request("tags may not be empty"));
+    }
+    if tag.chars().count() > MAX_TAG_CHARS {
+        return Err(AppError::bad_request(format!(
+            "tags are at most {} characters",
+            MAX_TAG_CHARS
+        )));
+    }
+    if tag.chars().any(|c| c.is_whitespace() || c == ',') {
+        return Err(AppError::bad_request(format!(
+            "tag '{}' contains whitespace or a comma",
+            tag
+        )));
+    }
+    Ok(tag)
+}
+
+/// [`normalize`] each tag, dropping repeats and keeping the first occurrence's place.
+pub(crate) fn normalize_all(tags: &[String]) -> Result<Vec<String>, AppError> {
+    let mut normalized: Vec<String> = Vec::with_capacity(tags.len());
+    for tag in tags {
+        let tag = normalize(tag)?;
+        if !normalized.contains(&tag) {
+            normalized.push(tag);
+        }
+    }
+    if normalized.len() > MAX_TAGS {
+        return Err(AppError::bad_request(format!(
+            "a bookmark may have at most {} tags",
+            MAX_TAGS
+        )));
+    }
+    Ok(normalized)
+}
+
+/// The tags of `extra` that `existing` lacks, as many as fit under [`MAX_TAGS`]
+/// alongside it; both already normalized.
+pub(crate) fn room_for(existing: &[String], extra: Vec<String>) -> Vec<String> {
+    let room = MAX_TAGS.saturating_sub(existing.len());
+    extra
+        .into_iter()
+        .filter(|tag| !existing.contains(tag))
+        .take(room)
+        .collect()
+}
+
+/// Give `bookmark_id` exactly `tags` (already normalized), creating names not yet used
+/// in the namespace and dropping ones no bookmark has any more.
+pub(crate) async fn replace(
+    conn: &mut SqliteConnection,
+    owner_id: i64,
+    namespace: &str,
+    bookmark_id: i64,
+    tags: &[String],
+) -> sqlx::Result<()> {
+    sqlx::query("DELETE FROM bookmark_tags WHERE bookmark_id = ?1")
+        .bind(bookmark_id)
+        .execute(&mut *conn)
+        .await?;
+    add(&mut *conn, owner_id, namespace, &[bookmark_id], tags).await?;
+    sqlx::query(
+        r#"
+        DELETE FROM tags
+        WHERE owner_id = ?1 AND namespace = ?2
+          AND id NOT IN (SELECT tag_id FROM bookmark_tags)
+        "#,
+    )
+    .bind(owner_id)
+    .bind(namespace)
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
+
+/// Add `tags` (already normalized) to each of `bookmark_ids`, keeping the ones they have.
+pub(crate) async fn add(
+    conn: &mut SqliteConnection,
+    owner_id: i64,
+    namespace: &str,
+    bookmark_ids: &[i64],
+    tags: &[String],
+) -> sqlx::Result<()> {
+    if tags.is_empty() || bookmark_ids.is_empty() {
+        return Ok(());
+    }
+    let mut insert =
+        QueryBuilder::<Sqlite>::new("INSERT OR IGNORE INTO tags (owner_id, namespace, name) ");
+    insert.push_values(tags, |mut row, tag| {
+        row.push_bind(owner_id).push_bind(namespace).push_bind(tag);
+    });
+    insert.build().execute(&mut *conn).await?;
+
+    // Bounded by `ingest.max_urls` bookmarks times `MAX_TAGS` names.
+    for &bookmark_id in bookmark_ids {
+        let mut link = QueryBuilder::<Sqlite>::new(
+            "INSERT OR IGNORE INTO bookmark_tags (bookmark_id, tag_id) SELECT ",
+        );
+        link.push_bind(bookmark_id)
+            .push(", id FROM tags WHERE owner_id = ")
+            .push_bind(owner_id)
+            .push(" AND namespace = ")
+            .push_bind(namespace)
+            .push(" AND name IN (");
+        let mut names = link.separated(", ");
+        for tag in tags {
+            names.push_bind(tag);
+        }
+        names.push_unseparated(")");
+        link.build().execute(&mut *conn).await?;
+    }
+    Ok(())
+}
+
+/// One bookmark's tags, sorted by name.
+pub(crate) async fn of_bookmark(db: &SqlitePool, bookmark_id: i64) -> sqlx::Result<Vec<String>> {
+    sqlx::query_scalar(
+        r#"
+        SELECT t.name
+        FROM bookmark_tags bt
+        JOIN tags t ON t.id = bt.tag_id
+        WHERE bt.bookmark_id = ?1
+        ORDER BY t.name
+        "#,
+    )
+    .bind(bookmark_id)
+    .fetch_all(db)
+    .await
+}
+
+/// Tags of each of `bookmark_ids` that has any, each list sorted by name.
+pub(crate) async fn of_bookmarks(
+    db: &SqlitePool,
+    bookmark_ids: &[i64],
+) -> sqlx::Result<HashMap<i64, Vec<String>>> {
+    let mut tags: HashMap<i64, Vec<String>> = HashMap::new();
+    if bookmark_ids.is_empty() {
+        return Ok(tags);
+    }
+    let mut query = QueryBuilder::<Sqlite>::new(
+        "SELECT bt.bookmark_id, t.name FROM bookmark_tags bt JOIN tags t ON t.id = bt.tag_id \
+         WHERE bt.bookmark_id IN (",
+    );
+    let mut ids = query.separated(", ");
+    for id in bookmark_ids {
+        ids.push_bind(id);
+    }
+    ids.push_unseparated(") ORDER BY t.name");
+    let rows: Vec<(i64, String)> = query.build_query_as().fetch_all(db).await?;
+    for (bookmark_id, name) in rows {
+        tags.entry(bookmark_id).or_default().push(name);
+    }
+    Ok(tags)
+}
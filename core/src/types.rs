@@ -0,0 +1,1362 @@
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use sqlx::SqlitePool;
+use tantivy::schema::Field;
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+use tokio::sync::{Mutex, Semaphore, broadcast, watch};
+use tokio_util::sync::CancellationToken;
+use tokio_util::task::TaskTracker;
+use url::Url;
+
+use crate::config::{Config, MaintenanceConfig};
+use crate::errors::AppError;
+use crate::index::{SearchIndex, ShardedWriter};
+use crate::metrics::Metrics;
+
+#[derive(Clone)]
+pub struct Dependencies {
+    pub db: SqlitePool,
+    pub index: SearchIndex,
+    pub writer: Arc<Mutex<ShardedWriter>>,
+    pub fields: IndexFields,
+    pub fetch_semaphore: Arc<Semaphore>,
+    pub http_client: reqwest::Client,
+    /// The config as of startup; settings that need a restart are read from here.
+    pub config: Config,
+    /// The config as last loaded, replaced when it is reloaded on SIGHUP.
+    pub live_config: watch::Sender<Config>,
+    pub metrics: Metrics,
+    pub tasks: TaskTracker,
+    pub events: broadcast::Sender<BookmarkEvent>,
+    /// When the index last took a write, so maintenance can wait for quiet periods.
+    pub last_index_write: Arc<std::sync::Mutex<Instant>>,
+    /// Cancelled once shutdown begins, so long-lived responses can end.
+    pub shutdown: CancellationToken,
+    /// Tunables adjustable at runtime through `/v1/admin/settings`.
+    pub settings: Arc<std::sync::RwLock<RuntimeSettings>>,
+    /// Set through `/v1/admin/read-only`; cleared by a restart.
+    pub read_only: Arc<std::sync::RwLock<ReadOnlyStatus>>,
+    /// Digest of the one-time setup secret for `/v1/admin/bootstrap`, logged at startup
+    /// while no admin key exists.
+    pub bootstrap_secret: Arc<Mutex<Option<String>>>,
+    pub started_at: Instant,
+}
+
+impl Dependencies {
+    /// Commit pending index changes, record the commit latency, and hand the commit to the
+    /// reader refresher, which makes it searchable. Commits slower than
+    /// `slow_index_commit_ms` are logged with the doc count searchers see so far.
+    pub fn commit_index(&self, writer: &mut ShardedWriter, operation: &str) -> tantivy::Result<()> {
+        let started = Instant::now();
+        let opstamp = writer.commit()?;
+        let elapsed = started.elapsed();
+        self.metrics.observe_commit(operation, elapsed);
+        *self
+            .last_index_write
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = Instant::now();
+        self.index.committed();
+        if RuntimeSettings::is_slow(self.settings().slow_index_commit_ms, elapsed) {
+            tracing::warn!(
+                operation,
+                opstamp,
+                docs = self.index.num_docs(),
+                elapsed_ms = elapsed.as_millis() as u64,
+                "slow index commit"
+            );
+        }
+        Ok(())
+    }
+
+    pub fn settings(&self) -> RuntimeSettings {
+        *self
+            .settings
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Refuse a write with 503 while read-only mode is on.
+    pub fn ensure_writable(&self) -> Result<(), AppError> {
+        let read_only = self
+            .read_only
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if !read_only.enabled {
+            return Ok(());
+        }
+        Err(AppError::unavailable(match &read_only.message {
+            Some(message) => format!("server is read-only: {}", message),
+            None => "server is read-only for maintenance; try again later".to_string(),
+        }))
+    }
+
+    /// `[maintenance]` as last loaded.
+    pub fn maintenance_config(&self) -> MaintenanceConfig {
+        self.live_config.borrow().maintenance.clone()
+    }
+
+    /// Wait out the `[maintenance]` interval picked by `interval_secs`, counted from the
+    /// call and re-read whenever the config is reloaded; an interval of 0 waits for a
+    /// reload that sets one. Returns `false` once shutdown begins.
+    pub async fn wait_interval(&self, interval_secs: fn(&MaintenanceConfig) -> u64) -> bool {
+        let started = tokio::time::Instant::now();
+        let mut changes = self.live_config.subscribe();
+        loop {
+            let secs = interval_secs(&changes.borrow_and_update().maintenance);
+            let elapsed = async move {
+                match secs {
+                    0 => std::future::pending().await,
+                    secs => tokio::time::sleep_until(started + Duration::from_secs(secs)).await,
+                }
+            };
+            tokio::select! {
+                _ = self.shutdown.cancelled() => return false,
+                _ = changes.changed() => {}
+                _ = elapsed => return true,
+            }
+        }
+    }
+
+    /// Announce a bookmark lifecycle change; dropped silently when nobody listens.
+    pub fn publish(&self, event: BookmarkEvent) {
+        let _ = self.events.send(event);
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct IndexFields {
+    pub bookmark_id: Field,
+    pub owner_id: Field,
+    pub namespace: Field,
+    pub url: Field,
+    pub title: Field,
+    pub body: Field,
+    pub excerpt: Field,
+    pub fetched_at: Field,
+    /// The page's host and each parent domain, lowercased and without `www.`.
+    pub host: Field,
+    /// One value per tag, lowercased.
+    pub tag: Field,
+}
+
+/// What an API key may do: `read` searches and lists, `ingest` adds and edits
+/// bookmarks and manages keys, `delete` removes bookmarks, `admin` manages users and
+/// server maintenance and implies the other scopes. A `read`-only key can change
+/// nothing, so it is safe to embed in a public frontend; an `ingest`-only key can add
+/// bookmarks but not lose any.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    Read,
+    Ingest,
+    Delete,
+    Admin,
+}
+
+impl Scope {
+    pub const ALL: [Scope; 4] = [Scope::Read, Scope::Ingest, Scope::Delete, Scope::Admin];
+    /// What keys get when no scopes are asked for.
+    pub const DEFAULT: [Scope; 3] = [Scope::Read, Scope::Ingest, Scope::Delete];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Scope::Read => "read",
+            Scope::Ingest => "ingest",
+            Scope::Delete => "delete",
+            Scope::Admin => "admin",
+        }
+    }
+
+    /// Parse the comma-separated form stored in `api_keys.scopes`, skipping unknown names.
+    pub fn parse_list(raw: &str) -> Vec<Scope> {
+        Self::ALL
+            .into_iter()
+            .filter(|scope| raw.split(',').any(|name| name.trim() == scope.as_str()))
+            .collect()
+    }
+
+    pub fn join(scopes: &[Scope]) -> String {
+        Self::ALL
+            .into_iter()
+            .filter(|scope| scopes.contains(scope))
+            .map(Scope::as_str)
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
+
+/// Where a request came from, for the checks that trust some networks or the host.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Peer {
+    /// A unix socket client, which the socket file's mode already limits to the host.
+    Unix,
+    Tcp(IpAddr),
+    /// A connection without an address, trusted with nothing.
+    Unknown,
+}
+
+impl std::fmt::Display for Peer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unix => f.write_str("the unix socket"),
+            Self::Tcp(ip) => ip.fmt(f),
+            Self::Unknown => f.write_str("an unknown address"),
+        }
+    }
+}
+
+/// The authenticated API key behind a request.
+#[derive(Clone, Debug)]
+pub struct Caller {
+    pub user_id: i64,
+    pub key_id: i64,
+    pub scopes: Vec<Scope>,
+    /// Namespace the request works in: the key's own, or the one it asked for.
+    pub namespace: String,
+    /// Set when the key is bound to `namespace` and may not leave it.
+    pub bound_namespace: bool,
+}
+
+impl Caller {
+    pub fn has_scope(&self, scope: Scope) -> bool {
+        self.scopes.contains(&scope) || self.scopes.contains(&Scope::Admin)
+    }
+}
+
+#[derive(Deserialize)]
+pub struct EventsParams {
+    pub token: Option<String>,
+    pub namespace: Option<String>,
+}
+
+/// A bookmarklet save; the token rides in the query since the browser navigates here.
+#[derive(Deserialize)]
+pub struct QuickAddParams {
+    pub url: String,
+    pub token: String,
+    pub namespace: Option<String>,
+}
+
+#[derive(Default, Deserialize)]
+pub struct SearchParams {
+    pub query: String,
+    pub page: Option<u32>,
+    pub per_page: Option<u32>,
+    /// Only bookmarks with this tag.
+    pub tag: Option<String>,
+    /// Only pages on this host or its subdomains, e.g. `example.com`.
+    pub site: Option<String>,
+    /// Only pages fetched at or after this time: RFC 3339 or `YYYY-MM-DD` (UTC midnight).
+    pub after: Option<String>,
+    /// Only pages fetched before this time, in the same formats as `after`.
+    pub before: Option<String>,
+    pub sort: Option<SearchSort>,
+}
+
+/// Order of search results.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchSort {
+    /// Best match first.
+    #[default]
+    Relevance,
+    /// Most recently fetched first; equally recent ones by relevance.
+    Recent,
+}
+
+#[derive(Serialize)]
+pub struct SearchResponse {
+    pub total_hits: u64,
+    pub results: Vec<SearchResultItem>,
+}
+
+#[derive(Serialize)]
+pub struct SearchResultItem {
+    pub bookmark_id: i64,
+    pub url: String,
+    pub title: Option<String>,
+    pub excerpt: Option<String>,
+    /// The passage of the page text that best matches the query; `excerpt` is cut at
+    /// ingest and is the same for every query.
+    pub snippet: Option<SearchSnippet>,
+    pub score: f32,
+}
+
+#[derive(Serialize)]
+pub struct SearchSnippet {
+    pub text: String,
+    /// Byte ranges of `text` matching query terms, as `[start, end)` pairs.
+    pub highlights: Vec<[usize; 2]>,
+    /// `text` HTML-escaped, with each match wrapped in `<mark>`.
+    pub html: String,
+}
+
+#[derive(Serialize)]
+pub struct NamespacesResponse {
+    pub results: Vec<NamespaceItem>,
+}
+
+#[derive(Serialize, FromRow)]
+pub struct NamespaceItem {
+    pub name: String,
+    pub bookmarks: i64,
+}
+
+#[derive(Serialize)]
+pub struct BookmarksResponse {
+    /// Bookmarks matching the filters, across every page.
+    pub total: i64,
+    pub page: u32,
+    pub per_page: u32,
+    pub results: Vec<BookmarkListItem>,
+}
+
+#[derive(Serialize, FromRow)]
+pub struct BookmarkListItem {
+    pub id: i64,
+    /// Normalized; what is fetched and what duplicates are detected by.
+    pub url: String,
+    /// The URL as it was submitted.
+    pub original_url: String,
+    pub title: Option<String>,
+    pub status: String,
+    /// Fetches made in the latest ingest; a `queued` bookmark with some is waiting to retry.
+    pub attempts: i64,
+    /// `html`, `pdf`, or `text` once fetched and indexed.
+    pub document_type: Option<String>,
+    pub updated_at: String,
+    /// Sorted by name.
+    #[sqlx(skip)]
+    pub tags: Vec<String>,
+}
+
+/// Query for `GET /v1/bookmarks`.
+#[derive(Default, Deserialize)]
+pub struct BookmarksParams {
+    /// Only bookmarks with this tag.
+    pub tag: Option<String>,
+    /// Only bookmarks with this status: `queued`, `indexed`, `partial`, or `failed`.
+    pub status: Option<String>,
+    /// Only bookmarks whose URL or title contains this, ignoring case.
+    pub q: Option<String>,
+    pub sort: Option<BookmarkSort>,
+    /// 1-based; defaults to 1.
+    pub page: Option<u32>,
+    /// Defaults to 50, at most 500.
+    pub per_page: Option<u32>,
+}
+
+/// Order of `GET /v1/bookmarks`; ties go to the higher id.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BookmarkSort {
+    /// Most recently updated first.
+    #[default]
+    Updated,
+    /// Most recently saved first.
+    Created,
+    /// Least recently saved first.
+    Oldest,
+    /// By title, then URL for untitled ones.
+    Title,
+    Url,
+}
+
+/// Body of `PUT /v1/bookmarks/{id}/tags`; replaces every tag the bookmark has.
+#[derive(Deserialize)]
+pub struct BookmarkTagsRequest {
+    pub tags: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct BookmarkTagsResponse {
+    pub id: i64,
+    /// Lowercased and sorted by name.
+    pub tags: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct TagsResponse {
+    pub results: Vec<TagItem>,
+}
+
+#[derive(Serialize, FromRow)]
+pub struct TagItem {
+    pub name: String,
+    pub bookmarks: i64,
+}
+
+#[derive(Deserialize)]
+pub struct LookupParams {
+    pub url: String,
+}
+
+#[derive(Deserialize)]
+pub struct BulkDeleteRequest {
+    #[serde(default)]
+    pub ids: Vec<i64>,
+    #[serde(default)]
+    pub urls: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct BulkDeleteResponse {
+    pub deleted: usize,
+    pub not_found: Vec<String>,
+}
+
+/// Query for `POST /v1/bookmarks/refetch`; without `status` every bookmark is refetched.
+#[derive(Deserialize)]
+pub struct RefetchParams {
+    pub status: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct RefetchResponse {
+    /// Bookmarks put back in the ingest queue; ones already queued are not counted.
+    pub queued: usize,
+}
+
+/// Query for linkding's `GET /api/bookmarks/`; every word of `q` must appear in the
+/// URL, title, or excerpt.
+#[derive(Deserialize)]
+pub struct LinkdingListParams {
+    #[serde(default)]
+    pub q: String,
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+}
+
+/// Body of a linkding bookmark create or update. Only `url` and `tag_names` map onto
+/// odin; titles, descriptions, and notes come from the fetched page, so the rest is ignored.
+#[derive(Deserialize)]
+pub struct LinkdingBookmarkRequest {
+    pub url: Option<String>,
+    /// Replaces the bookmark's tags when given.
+    pub tag_names: Option<Vec<String>>,
+}
+
+/// Linkding's paginated list envelope; `next` and `previous` are relative URLs.
+#[derive(Serialize)]
+pub struct LinkdingPage<T> {
+    pub count: i64,
+    pub next: Option<String>,
+    pub previous: Option<String>,
+    pub results: Vec<T>,
+}
+
+#[derive(Serialize)]
+pub struct LinkdingBookmark {
+    pub id: i64,
+    pub url: String,
+    pub title: String,
+    pub description: String,
+    pub notes: String,
+    pub web_archive_snapshot_url: String,
+    pub favicon_url: Option<String>,
+    pub preview_image_url: Option<String>,
+    pub is_archived: bool,
+    pub unread: bool,
+    pub shared: bool,
+    pub tag_names: Vec<String>,
+    pub date_added: String,
+    pub date_modified: String,
+    pub website_title: Option<String>,
+    pub website_description: Option<String>,
+}
+
+#[derive(Serialize, FromRow)]
+pub struct LinkdingTag {
+    pub id: i64,
+    pub name: String,
+    pub date_added: String,
+}
+
+#[derive(Serialize)]
+pub struct LinkdingCheckResponse {
+    pub bookmark: Option<LinkdingBookmark>,
+    pub metadata: LinkdingMetadata,
+    pub auto_tags: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct LinkdingMetadata {
+    pub url: String,
+    pub title: Option<String>,
+    pub description: Option<String>,
+}
+
+/// Credentials and response format shared by the Pinboard v1 endpoints.
+/// `auth_token` is Pinboard's `username:TOKEN`; only the token is checked.
+#[derive(Deserialize)]
+pub struct PinboardAuth {
+    pub auth_token: Option<String>,
+    pub format: Option<String>,
+}
+
+/// `posts/add`. The title and description Pinboard clients send are dropped, since
+/// odin takes them from the page.
+#[derive(Deserialize)]
+pub struct PinboardAddParams {
+    pub url: Option<String>,
+    /// Space-separated; replaces an existing post's tags unless `replace=no`.
+    pub tags: Option<String>,
+    /// `no` keeps an existing bookmark and reports `item already exists`.
+    pub replace: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct PinboardDeleteParams {
+    pub url: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct PinboardAllParams {
+    pub tag: Option<String>,
+    pub start: Option<u32>,
+    pub results: Option<u32>,
+    pub fromdt: Option<String>,
+    pub todt: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct PinboardPost {
+    pub href: String,
+    pub description: String,
+    pub extended: String,
+    pub meta: String,
+    pub hash: String,
+    pub time: String,
+    pub shared: &'static str,
+    pub toread: &'static str,
+    pub tags: String,
+}
+
+/// The settings linkding clients read from `/api/user/profile/`, fixed to what odin does.
+#[derive(Serialize)]
+pub struct LinkdingProfile {
+    pub theme: &'static str,
+    pub bookmark_date_display: &'static str,
+    pub bookmark_link_target: &'static str,
+    pub web_archive_integration: &'static str,
+    pub tag_search: &'static str,
+    pub enable_sharing: bool,
+    pub enable_public_sharing: bool,
+    pub enable_favicons: bool,
+    pub display_url: bool,
+    pub permanent_notes: bool,
+}
+
+#[derive(Deserialize)]
+pub struct IngestUrlsRequest {
+    pub urls: Vec<String>,
+    /// Given to each bookmark the batch creates; URLs already saved keep their tags.
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct IngestUrlsResponse {
+    pub accepted: usize,
+    pub deduped: usize,
+    /// URLs waiting for or being processed by the ingest workers, this batch included.
+    pub queue_depth: usize,
+    pub queue_capacity: usize,
+}
+
+/// Ingest limits and slow-operation thresholds that can change without a restart;
+/// defaults come from `[ingest]` and `[slow_log]`.
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct RuntimeSettings {
+    pub fetch_concurrency: usize,
+    pub fetch_timeout_secs: u64,
+    pub max_urls: usize,
+    pub excerpt_len: usize,
+    pub slow_query_ms: u64,
+    pub slow_search_ms: u64,
+    pub slow_index_commit_ms: u64,
+}
+
+impl RuntimeSettings {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            fetch_concurrency: config.ingest.fetch_concurrency,
+            fetch_timeout_secs: config.ingest.fetch_timeout_secs,
+            max_urls: config.ingest.max_urls,
+            excerpt_len: config.ingest.excerpt_len,
+            slow_query_ms: config.slow_log.query_ms,
+            slow_search_ms: config.slow_log.search_ms,
+            slow_index_commit_ms: config.slow_log.index_commit_ms,
+        }
+    }
+
+    /// Whether `elapsed` reaches a slow-log threshold; a threshold of 0 never does.
+    pub fn is_slow(threshold_ms: u64, elapsed: Duration) -> bool {
+        threshold_ms > 0 && elapsed >= Duration::from_millis(threshold_ms)
+    }
+}
+
+/// Read-only mode: searches and reads keep working while ingests, imports, bookmark
+/// deletes, user and key creation, key rotation, and settings changes are refused with
+/// 503. Revoking a key stays possible.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ReadOnlyStatus {
+    pub enabled: bool,
+    /// Why writes are refused, included in the 503 message.
+    pub message: Option<String>,
+    pub since: Option<String>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct SetReadOnlyRequest {
+    pub enabled: bool,
+    #[serde(default)]
+    pub message: Option<String>,
+}
+
+/// Creates the first admin key; `secret` is needed unless sent from the server host.
+#[derive(Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct BootstrapRequest {
+    #[serde(default)]
+    pub secret: Option<String>,
+}
+
+/// Exchanges an API token for a web UI session cookie.
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct LoginRequest {
+    pub token: String,
+}
+
+/// The signed-in session; `csrf_token` goes in `X-CSRF-Token` on every write.
+#[derive(Deserialize, Serialize)]
+pub struct SessionResponse {
+    pub user_id: i64,
+    pub scopes: Vec<Scope>,
+    pub namespace: Option<String>,
+    pub csrf_token: String,
+    pub expires_at: String,
+}
+
+/// Query the identity provider sends back to `/v1/auth/oidc/callback`.
+#[derive(Deserialize)]
+pub struct OidcCallbackParams {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    pub error: Option<String>,
+    pub error_description: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct OidcStatusResponse {
+    pub enabled: bool,
+}
+
+#[derive(Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct UpdateSettingsRequest {
+    pub fetch_concurrency: Option<usize>,
+    pub fetch_timeout_secs: Option<u64>,
+    pub max_urls: Option<usize>,
+    pub excerpt_len: Option<usize>,
+    pub slow_query_ms: Option<u64>,
+    pub slow_search_ms: Option<u64>,
+    pub slow_index_commit_ms: Option<u64>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JobKind {
+    Import,
+    Reindex,
+    Backup,
+}
+
+impl JobKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            JobKind::Import => "import",
+            JobKind::Reindex => "reindex",
+            JobKind::Backup => "backup",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JobState {
+    Running,
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+impl JobState {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            JobState::Running => "running",
+            JobState::Succeeded => "succeeded",
+            JobState::Failed => "failed",
+            JobState::Cancelled => "cancelled",
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct JobsResponse {
+    pub results: Vec<JobItem>,
+}
+
+#[derive(Serialize, FromRow)]
+pub struct JobItem {
+    pub id: i64,
+    pub kind: String,
+    pub state: String,
+    pub done: i64,
+    pub total: Option<i64>,
+    pub error: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+    pub finished_at: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct ImportResponse {
+    pub job_id: i64,
+    /// URLs seen in the upload, including repeats.
+    pub found: usize,
+    pub accepted: usize,
+    pub deduped: usize,
+}
+
+#[derive(Deserialize)]
+pub struct OpmlImportParams {
+    /// Ingest only entries the feeds publish from now on, not those already in them.
+    #[serde(default)]
+    pub from_now: bool,
+}
+
+/// An OPML import: outlines with an `xmlUrl` become feed subscriptions and outlines
+/// with a `url` become bookmarks.
+#[derive(Serialize)]
+pub struct OpmlImportResponse {
+    /// Distinct feed outlines.
+    pub feeds: usize,
+    pub subscribed: usize,
+    pub already_subscribed: usize,
+    /// Feeds that could not be read or did not fit under `feeds.max_feeds`.
+    pub failed: Vec<OpmlFeedFailure>,
+    /// Distinct link outlines.
+    pub links: usize,
+    /// Set when any link was queued for ingest.
+    pub job_id: Option<i64>,
+    /// Links queued for ingest.
+    pub accepted: usize,
+    /// Links already saved.
+    pub deduped: usize,
+    /// Outlines whose URL is not http(s).
+    pub invalid: usize,
+}
+
+#[derive(Serialize)]
+pub struct OpmlFeedFailure {
+    pub url: String,
+    pub error: String,
+}
+
+#[derive(Deserialize)]
+pub struct ExportStreamParams {
+    /// Include each bookmark's extracted text.
+    #[serde(default)]
+    pub content: bool,
+}
+
+/// How `GET /v1/export` writes the bookmarks.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    /// One JSON array of bookmark objects.
+    #[default]
+    Json,
+    /// One JSON object per line.
+    Ndjson,
+    /// A header row, then one row per bookmark; tags are separated by spaces.
+    Csv,
+    /// A Netscape bookmark file, as browsers and other bookmark managers import.
+    Netscape,
+}
+
+#[derive(Deserialize)]
+pub struct ExportParams {
+    #[serde(default)]
+    pub format: ExportFormat,
+    /// Include each bookmark's extracted text; `json` and `ndjson` only.
+    #[serde(default)]
+    pub content: bool,
+}
+
+#[derive(Deserialize)]
+pub struct InstapaperImportParams {
+    /// Parse and count without saving anything.
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// An Instapaper import, or with `dry_run` what one would do. Odin has no folders or
+/// favorites, so those are only counted.
+#[derive(Serialize)]
+pub struct InstapaperImportResponse {
+    pub dry_run: bool,
+    /// Absent on a dry run.
+    pub job_id: Option<i64>,
+    /// Data rows in the CSV.
+    pub rows: usize,
+    /// Rows without an http(s) URL.
+    pub invalid: usize,
+    /// Rows in Instapaper's Starred folder.
+    pub starred: usize,
+    /// Rows per Instapaper folder.
+    pub folders: BTreeMap<String, usize>,
+    /// URLs queued for ingest, or on a dry run, not yet saved.
+    pub accepted: usize,
+    /// Repeated or already saved URLs.
+    pub deduped: usize,
+}
+
+#[derive(Deserialize)]
+pub struct BrowserSyncParams {
+    /// Names the browser profile, so several sync independently; defaults to the
+    /// detected format, `chrome` or `firefox`.
+    pub source: Option<String>,
+    /// Delete bookmarks whose URL left the browser entirely.
+    #[serde(default)]
+    pub prune: bool,
+}
+
+#[derive(Serialize)]
+pub struct BrowserSyncResponse {
+    pub source: String,
+    /// Bookmarks in the upload, counted once per URL and folder.
+    pub found: usize,
+    /// URL and folder pairs new since the last sync.
+    pub added: usize,
+    /// Pairs from the last sync that are gone.
+    pub removed: usize,
+    /// New URLs queued for ingest; the rest were already saved.
+    pub accepted: usize,
+    /// Bookmarks deleted by `prune`.
+    pub deleted: usize,
+}
+
+/// How `/v1/import` reads the upload.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportFormat {
+    /// Every `http(s)://` URL in any text export: browser HTML, CSV, or a URL list.
+    #[default]
+    Auto,
+    /// An Omnivore export zip, or one of its `metadata_*.json` files.
+    Omnivore,
+    /// A Karakeep (Hoarder) JSON export.
+    Karakeep,
+    /// Shiori bookmarks as JSON, from its API or database tools.
+    Shiori,
+    /// A Netscape bookmark file (Chrome, Firefox, Safari), keeping titles and add dates;
+    /// `auto` detects these by their doctype.
+    Netscape,
+    /// Pocket's CSV or HTML export, or its API JSON, keeping titles and add dates.
+    Pocket,
+    /// Pinboard's JSON export, keeping titles and dates.
+    Pinboard,
+}
+
+#[derive(Deserialize)]
+pub struct ImportParams {
+    #[serde(default)]
+    pub format: ImportFormat,
+}
+
+/// The feed reader whose starred entries a user imports.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FeedReaderKind {
+    Miniflux,
+    FreshRss,
+}
+
+impl FeedReaderKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FeedReaderKind::Miniflux => "miniflux",
+            FeedReaderKind::FreshRss => "freshrss",
+        }
+    }
+}
+
+/// Connects the caller's Miniflux or FreshRSS account. Miniflux takes an API key as
+/// `token`; FreshRSS takes `username` and its API password as `token`.
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct FeedReaderConnectRequest {
+    pub kind: FeedReaderKind,
+    pub url: Url,
+    pub username: Option<String>,
+    pub token: String,
+    /// Import only entries starred from now on, not those starred already.
+    #[serde(default)]
+    pub from_now: bool,
+}
+
+#[derive(Serialize)]
+pub struct FeedReaderStatus {
+    pub connected: bool,
+    pub kind: Option<FeedReaderKind>,
+    pub url: Option<String>,
+    /// Starred entries seen so far, imported or skipped by `from_now`.
+    pub entries_seen: i64,
+    pub last_synced_at: Option<String>,
+    pub last_error: Option<String>,
+}
+
+/// Subscribes the caller's namespace to an RSS or Atom feed.
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct FeedCreateRequest {
+    pub url: Url,
+    /// Ingest only entries published from now on, not those already in the feed.
+    #[serde(default)]
+    pub from_now: bool,
+}
+
+#[derive(Serialize, FromRow)]
+pub struct FeedItem {
+    pub id: i64,
+    pub url: String,
+    /// The feed's own title, as of the last poll.
+    pub title: Option<String>,
+    /// Entries seen so far, ingested or skipped by `from_now`.
+    pub entries_seen: i64,
+    pub created_at: String,
+    pub last_polled_at: Option<String>,
+    pub last_error: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct FeedsResponse {
+    pub results: Vec<FeedItem>,
+}
+
+/// Registers a URL that is sent the caller's bookmark events, like a `[[webhooks]]`
+/// target in the config file but limited to one namespace.
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct WebhookCreateRequest {
+    pub url: Url,
+    /// Shared secret for the `X-Odin-Signature` HMAC; unsigned when absent.
+    pub secret: Option<String>,
+    /// Defaults to every event.
+    pub events: Option<Vec<BookmarkEventKind>>,
+}
+
+#[derive(Serialize)]
+pub struct WebhookItem {
+    pub id: i64,
+    pub url: String,
+    pub events: Vec<BookmarkEventKind>,
+    /// Whether deliveries carry an `X-Odin-Signature`; the secret is never returned.
+    pub signed: bool,
+    pub created_at: String,
+    /// When the latest delivery finished, after its retries.
+    pub last_attempt_at: Option<String>,
+    /// Why the latest delivery failed; cleared by the next one that succeeds.
+    pub last_error: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct WebhooksResponse {
+    pub results: Vec<WebhookItem>,
+}
+
+/// Connects the caller's Readwise account; the token is from readwise.io/access_token.
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ReadwiseConnectRequest {
+    pub token: String,
+    /// Push only bookmarks saved from now on instead of the whole archive.
+    #[serde(default)]
+    pub from_now: bool,
+}
+
+#[derive(Serialize)]
+pub struct ReadwiseStatus {
+    pub connected: bool,
+    /// Bookmarks are pushed in id order; everything up to this id has been sent.
+    pub last_bookmark_id: i64,
+    /// Bookmarks still to push.
+    pub pending: i64,
+    pub last_synced_at: Option<String>,
+    pub last_error: Option<String>,
+}
+
+/// A bookmark's Wayback Machine capture: `pending` until Save Page Now answers, then
+/// `saved` with `archive_url` or `failed` with `error`.
+#[derive(Serialize, FromRow)]
+pub struct WaybackSnapshot {
+    pub bookmark_id: i64,
+    pub status: String,
+    pub archive_url: Option<String>,
+    pub error: Option<String>,
+    pub requested_at: String,
+    pub completed_at: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ReindexRequest {
+    pub id: Option<i64>,
+    /// Fetch pages again instead of rebuilding from stored extracted text.
+    #[serde(default)]
+    pub refetch: bool,
+}
+
+#[derive(Serialize)]
+pub struct ReindexProgress {
+    pub id: i64,
+    pub url: String,
+    pub status: String,
+    pub error: Option<String>,
+    pub done: usize,
+    pub total: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    Ok,
+    /// Working but occupied, e.g. the index writer during a reindex.
+    Busy,
+    Error,
+}
+
+#[derive(Serialize)]
+pub struct ComponentHealth {
+    pub status: HealthStatus,
+    pub detail: Option<String>,
+    pub elapsed_ms: u64,
+}
+
+#[derive(Serialize)]
+pub struct ReadinessResponse {
+    pub status: HealthStatus,
+    pub database: ComponentHealth,
+    pub index: ComponentHealth,
+    pub writer: ComponentHealth,
+}
+
+#[derive(Serialize)]
+pub struct AdminStatusResponse {
+    pub version: &'static str,
+    pub uptime_secs: u64,
+    pub index_bytes: u64,
+    pub index_segments: usize,
+    pub index_docs: u64,
+    pub ingest_queue_depth: i64,
+    pub fetches_in_flight: usize,
+    pub fetch_concurrency: usize,
+    pub db_bytes: u64,
+    pub storage: StorageUsage,
+    pub read_only: bool,
+}
+
+/// The caller's bookmarks by status, with the server's disk usage.
+#[derive(Serialize)]
+pub struct StatsResponse {
+    pub bookmarks: i64,
+    /// Bookmark counts keyed by status (`queued`, `indexed`, `partial`, `failed`).
+    pub by_status: BTreeMap<String, i64>,
+    /// The whole server's index, not only the caller's documents.
+    pub index_bytes: u64,
+    /// The whole server's database with its WAL.
+    pub db_bytes: u64,
+}
+
+/// Disk usage by component, measured against `quota.max_bytes`.
+#[derive(Serialize)]
+pub struct StorageUsage {
+    /// The database with its WAL.
+    pub db_bytes: u64,
+    pub index_bytes: u64,
+    /// Stored page text, already counted in `db_bytes`.
+    pub content_bytes: u64,
+    /// Kept pre-restore copies, a staged restore, and backups being written.
+    pub snapshot_bytes: u64,
+    pub total_bytes: u64,
+    pub quota_bytes: Option<u64>,
+    pub over_quota: bool,
+}
+
+/// What `/v1/admin/domains/stats` ranks domains by, worst first.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DomainStatsSort {
+    /// Total time spent fetching, the cost a domain adds to the crawl.
+    #[default]
+    Time,
+    Latency,
+    Failures,
+    Fetches,
+    Bytes,
+}
+
+#[derive(Deserialize)]
+pub struct DomainStatsParams {
+    /// The window, in hours up to a week; defaults to 24.
+    pub hours: Option<i64>,
+    pub limit: Option<i64>,
+    pub sort: Option<DomainStatsSort>,
+}
+
+#[derive(Serialize, FromRow)]
+pub struct DomainStat {
+    pub domain: String,
+    pub fetches: i64,
+    /// Fetches that errored or answered with a non-2xx status.
+    pub failures: i64,
+    pub failure_rate: f64,
+    /// Body bytes read.
+    pub bytes: i64,
+    pub total_latency_ms: i64,
+    pub avg_latency_ms: i64,
+    pub max_latency_ms: i64,
+}
+
+#[derive(Serialize)]
+pub struct DomainStatsResponse {
+    pub hours: i64,
+    pub results: Vec<DomainStat>,
+}
+
+#[derive(Serialize)]
+pub struct DbMaintenanceResponse {
+    /// Whether the database needed a one-off full `VACUUM` to enable incremental vacuuming.
+    pub vacuumed: bool,
+    pub freed_pages: i64,
+    /// Stored page texts from before compression that were compressed in this run.
+    pub compressed_contents: u64,
+    pub db_bytes_before: u64,
+    pub db_bytes_after: u64,
+    pub elapsed_ms: u64,
+}
+
+/// A bookmark's extracted text as kept at its last fetch.
+#[derive(Serialize)]
+pub struct BookmarkContentResponse {
+    pub id: i64,
+    pub url: String,
+    pub title: Option<String>,
+    pub fetched_at: Option<String>,
+    pub body: String,
+}
+
+/// A page as fetched, kept under `storage.archive_pages`.
+pub struct ArchivedPage {
+    /// As the site served it; may name the charset `body` is in.
+    pub content_type: String,
+    pub fetched_at: String,
+    pub body: Vec<u8>,
+}
+
+#[derive(Serialize)]
+pub struct ReconcileResponse {
+    /// Bookmark rows compared against the index.
+    pub bookmarks_checked: usize,
+    /// Distinct bookmarks with a live index document.
+    pub index_docs_checked: usize,
+    /// Bookmarks marked indexed but missing from the index, re-added from stored text.
+    pub reindexed: Vec<i64>,
+    /// Missing bookmarks without stored text, queued for a fresh fetch.
+    pub refetch_queued: Vec<i64>,
+    /// Bookmark ids whose index documents outlived their rows and were deleted.
+    pub removed_docs: Vec<i64>,
+    pub elapsed_ms: u64,
+}
+
+#[derive(Serialize)]
+pub struct ConsistencyResponse {
+    /// Bookmark rows compared against the index.
+    pub bookmarks_checked: usize,
+    /// Distinct bookmarks with a live index document.
+    pub index_docs_checked: usize,
+    /// Bookmarks marked indexed but missing from the index.
+    pub missing_from_index: Vec<i64>,
+    /// Bookmark ids whose index documents outlived their rows.
+    pub orphaned_docs: Vec<i64>,
+    pub elapsed_ms: u64,
+}
+
+#[derive(Deserialize)]
+pub struct CreateUserRequest {
+    pub username: String,
+    #[serde(default)]
+    pub is_admin: bool,
+}
+
+#[derive(Serialize)]
+pub struct CreateUserResponse {
+    pub id: i64,
+    pub username: String,
+    pub is_admin: bool,
+    pub token: String,
+}
+
+#[derive(Serialize)]
+pub struct UsersResponse {
+    pub results: Vec<UserItem>,
+}
+
+#[derive(Serialize, FromRow)]
+pub struct UserItem {
+    pub id: i64,
+    pub username: String,
+    pub is_admin: bool,
+    pub created_at: String,
+}
+
+#[derive(Deserialize)]
+pub struct CreateApiKeyRequest {
+    pub name: String,
+    pub scopes: Option<Vec<Scope>>,
+    /// Bind the key to one namespace; unbound keys pick one per request.
+    pub namespace: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct CreateApiKeyResponse {
+    pub id: i64,
+    pub name: String,
+    pub scopes: Vec<Scope>,
+    pub namespace: Option<String>,
+    pub token: String,
+}
+
+#[derive(Serialize)]
+pub struct ApiKeysResponse {
+    pub results: Vec<ApiKeyItem>,
+}
+
+#[derive(Serialize)]
+pub struct ApiKeyItem {
+    pub id: i64,
+    pub name: String,
+    pub scopes: Vec<Scope>,
+    pub created_at: String,
+    pub last_used_at: Option<String>,
+    pub revoked_at: Option<String>,
+    pub expires_at: Option<String>,
+    pub namespace: Option<String>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RotateApiKeyRequest {
+    /// Seconds the old token stays valid; defaults to `auth.rotation_grace_secs`.
+    pub grace_secs: Option<u64>,
+}
+
+#[derive(Serialize)]
+pub struct RotateApiKeyResponse {
+    pub id: i64,
+    pub name: String,
+    pub scopes: Vec<Scope>,
+    pub namespace: Option<String>,
+    pub token: String,
+    pub previous_id: i64,
+    pub previous_expires_at: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BookmarkEventKind {
+    Indexed,
+    Failed,
+    Deleted,
+}
+
+impl BookmarkEventKind {
+    pub const ALL: [BookmarkEventKind; 3] = [
+        BookmarkEventKind::Indexed,
+        BookmarkEventKind::Failed,
+        BookmarkEventKind::Deleted,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            BookmarkEventKind::Indexed => "indexed",
+            BookmarkEventKind::Failed => "failed",
+            BookmarkEventKind::Deleted => "deleted",
+        }
+    }
+
+    /// Parse the comma-separated form stored in `webhooks.events`, skipping unknown names.
+    pub fn parse_list(raw: &str) -> Vec<BookmarkEventKind> {
+        Self::ALL
+            .into_iter()
+            .filter(|event| raw.split(',').any(|name| name.trim() == event.as_str()))
+            .collect()
+    }
+
+    pub fn join(events: &[BookmarkEventKind]) -> String {
+        Self::ALL
+            .into_iter()
+            .filter(|event| events.contains(event))
+            .map(BookmarkEventKind::as_str)
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
+
+/// A bookmark lifecycle change as delivered to webhooks and `/v1/events`.
+#[derive(Clone, Debug, Serialize)]
+pub struct BookmarkEvent {
+    pub event: BookmarkEventKind,
+    pub id: i64,
+    pub owner_id: i64,
+    pub namespace: String,
+    pub url: String,
+    pub title: Option<String>,
+    pub error: Option<String>,
+    pub at: String,
+    /// Set on deletes of bookmarks that were published over ActivityPub, whose
+    /// followers are then told to delete the note.
+    #[serde(skip)]
+    pub was_public: bool,
+}
+
+impl BookmarkEvent {
+    pub fn new(
+        event: BookmarkEventKind,
+        id: i64,
+        owner_id: i64,
+        namespace: impl Into<String>,
+        url: impl Into<String>,
+    ) -> Self {
+        Self {
+            event,
+            id,
+            owner_id,
+            namespace: namespace.into(),
+            url: url.into(),
+            title: None,
+            error: None,
+            at: OffsetDateTime::now_utc()
+                .format(&Rfc3339)
+                .expect("failed to format timestamp"),
+            was_public: false,
+        }
+    }
+}
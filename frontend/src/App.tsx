@@ -1,8 +1,37 @@
 import { useCallback, useEffect, useMemo, useState } from "react";
-import type { ChangeEvent } from "react";
-import { useQuery } from "@tanstack/react-query";
+import type { ChangeEvent, FormEvent } from "react";
+import { useQuery, useQueryClient } from "@tanstack/react-query";
 
-const API_BASE = "http://localhost:3000";
+// Empty when the backend serves the UI itself (`server.serve_frontend`), so requests stay same-origin.
+const API_BASE: string =
+  import.meta.env.VITE_ODIN_API_BASE ?? (import.meta.env.DEV ? "http://localhost:3000" : "");
+// Bundled into the page for every visitor, so this should be a read-only key. Leave it
+// unset to sign in with a token instead, which keeps it in an HttpOnly session cookie.
+const API_TOKEN: string | undefined = import.meta.env.VITE_ODIN_TOKEN;
+
+type Session = {
+  user_id: number;
+  scopes: string[];
+  namespace?: string | null;
+  csrf_token: string;
+  expires_at: string;
+};
+
+// Bearer auth when a token is bundled, otherwise the session cookie; writes made with the
+// cookie must echo the session's CSRF token.
+const api_fetch = (path: string, init: RequestInit = {}, session?: Session | null) => {
+  const headers = new Headers(init.headers);
+  if (API_TOKEN) {
+    headers.set("Authorization", `Bearer ${API_TOKEN}`);
+  } else if (session && init.method && init.method !== "GET") {
+    headers.set("X-CSRF-Token", session.csrf_token);
+  }
+  return fetch(`${API_BASE}${path}`, {
+    ...init,
+    headers,
+    credentials: API_TOKEN ? "same-origin" : "include",
+  });
+};
 
 type SearchResultItem = {
   url: string;
@@ -27,9 +56,34 @@ const get_hostname = (url: string) => {
 export default function App() {
   const [draft_query, set_draft_query] = useState("");
   const [active_query, set_active_query] = useState("");
+  const query_client = useQueryClient();
 
   const trimmed_draft_query = useMemo(() => draft_query.trim(), [draft_query]);
 
+  const { data: session, isPending: session_pending } = useQuery({
+    queryKey: ["session"],
+    queryFn: async ({ signal }) => {
+      const response = await api_fetch("/v1/auth/session", { signal });
+      if (response.status === 401) {
+        return null;
+      }
+      if (!response.ok) {
+        throw new Error(`Request failed with ${response.status}`);
+      }
+      return (await response.json()) as Session;
+    },
+    enabled: !API_TOKEN,
+    staleTime: Infinity,
+  });
+  const signed_in = Boolean(API_TOKEN) || Boolean(session);
+  const needs_sign_in = !API_TOKEN && !session_pending && !session;
+
+  const sign_out = useCallback(async () => {
+    await api_fetch("/v1/auth/logout", { method: "POST" }, session);
+    query_client.setQueryData(["session"], null);
+    query_client.removeQueries({ queryKey: ["search"] });
+  }, [query_client, session]);
+
   const update_url_query = useCallback((next_query: string) => {
     const params = new URLSearchParams(window.location.search);
 
@@ -56,8 +110,9 @@ export default function App() {
         return { total_hits: 0, results: [] } satisfies SearchResponse;
       }
 
-      const url = `${API_BASE}/v1/search?q=${encodeURIComponent(query)}`;
-      const response = await fetch(url, { signal });
+      const response = await api_fetch(`/v1/search?q=${encodeURIComponent(query)}`, {
+        signal,
+      });
 
       if (!response.ok) {
         throw new Error(`Request failed with ${response.status}`);
@@ -65,7 +120,7 @@ export default function App() {
 
       return (await response.json()) as SearchResponse;
     },
-    enabled: active_query.length > 0,
+    enabled: active_query.length > 0 && signed_in,
     placeholderData: (previous_data) => previous_data,
   });
 
@@ -94,11 +149,43 @@ export default function App() {
     return () => window.removeEventListener("popstate", handle_popstate);
   }, [apply_query_from_url]);
 
+  useEffect(() => {
+    if (!signed_in) {
+      return;
+    }
+
+    // EventSource cannot send headers, so a bundled token rides in the query string.
+    const source = API_TOKEN
+      ? new EventSource(`${API_BASE}/v1/events?token=${encodeURIComponent(API_TOKEN)}`)
+      : new EventSource(`${API_BASE}/v1/events`, { withCredentials: true });
+    const refresh = () => {
+      query_client.invalidateQueries({ queryKey: ["search"] });
+    };
+
+    for (const name of ["indexed", "failed", "deleted", "lagged"]) {
+      source.addEventListener(name, refresh);
+    }
+    return () => source.close();
+  }, [query_client, signed_in]);
+
   const show_results = active_query.length > 0;
   const title = show_results ? `About ${total_hits} results` : "Results";
 
+  if (needs_sign_in) {
+    return <SignIn />;
+  }
+
   return (
     <div className="min-h-screen bg-stone-50 text-stone-900">
+      {session ? (
+        <button
+          type="button"
+          onClick={sign_out}
+          className="fixed right-4 top-3 rounded px-2 py-1 text-sm text-stone-500 hover:bg-stone-200 hover:text-stone-700"
+        >
+          Sign out
+        </button>
+      ) : null}
       {show_results ? (
         <>
           <header className="border-b border-stone-200 bg-white px-4 py-3">
@@ -267,3 +354,75 @@ export default function App() {
     </div>
   );
 }
+
+function SignIn() {
+  const [token, set_token] = useState("");
+  const [error, set_error] = useState<string | null>(null);
+  const query_client = useQueryClient();
+  const { data: sso_enabled } = useQuery({
+    queryKey: ["oidc"],
+    queryFn: async ({ signal }) => {
+      const response = await api_fetch("/v1/auth/oidc", { signal });
+      return response.ok && ((await response.json()) as { enabled: boolean }).enabled;
+    },
+    staleTime: Infinity,
+  });
+
+  const submit = async (event: FormEvent) => {
+    event.preventDefault();
+    set_error(null);
+    const response = await api_fetch("/v1/auth/login", {
+      method: "POST",
+      headers: { "Content-Type": "application/json" },
+      body: JSON.stringify({ token: token.trim() }),
+    });
+    if (!response.ok) {
+      set_error(
+        response.status === 401
+          ? "That token is not valid."
+          : `Sign in failed with ${response.status}`
+      );
+      return;
+    }
+    set_token("");
+    query_client.setQueryData(["session"], (await response.json()) as Session);
+  };
+
+  return (
+    <section className="flex min-h-screen flex-col items-center justify-center bg-stone-50 px-4 text-stone-900">
+      <form className="flex w-full max-w-sm flex-col gap-4" onSubmit={submit}>
+        <h1 className="text-center text-6xl font-medium text-stone-800">Odin</h1>
+        <input
+          type="password"
+          autoComplete="current-password"
+          className="rounded-full border border-stone-300 bg-white px-4 py-3 text-stone-900 placeholder-stone-400 outline-none focus:border-stone-400 focus:ring-2 focus:ring-stone-200"
+          placeholder="API token"
+          value={token}
+          onChange={(event: ChangeEvent<HTMLInputElement>) =>
+            set_token(event.target.value)
+          }
+        />
+        <button
+          type="submit"
+          disabled={!token.trim()}
+          className="rounded-full bg-stone-800 px-4 py-2 text-white hover:bg-stone-700 disabled:opacity-50"
+        >
+          Sign in
+        </button>
+        {sso_enabled ? (
+          <a
+            href={`${API_BASE}/v1/auth/oidc/login`}
+            className="rounded-full border border-stone-300 bg-white px-4 py-2 text-center text-stone-800 hover:bg-stone-100"
+          >
+            Sign in with SSO
+          </a>
+        ) : null}
+        {error ? (
+          <div className="rounded border border-red-200 bg-red-50 px-4 py-3 text-red-800">
+            {error}
+          </div>
+        ) : null}
+      </form>
+    </section>
+  );
+}